@@ -33,11 +33,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "proto/xray/proxy/vless/inbound/config.proto",
         "proto/xray/proxy/vless/outbound/config.proto",
         "proto/xray/proxy/socks/config.proto",
+        "proto/xray/proxy/trojan/config.proto",
+        "proto/xray/proxy/hysteria/config.proto",
+        "proto/xray/proxy/hysteria/account/config.proto",
         "proto/xray/proxy/freedom/config.proto",
         "proto/xray/transport/internet/config.proto",
         "proto/xray/transport/internet/reality/config.proto",
         "proto/xray/transport/internet/splithttp/config.proto",
         "proto/xray/transport/internet/tcp/config.proto",
+        "proto/xray/transport/internet/hysteria/config.proto",
+        "proto/xray/transport/internet/tls/config.proto",
     ];
 
     for proto in protos {
@@ -0,0 +1,152 @@
+//! Named admin principals with scoped, individually revocable tokens.
+//!
+//! Principals live in Raft state next to the cluster's single legacy admin token, which keeps
+//! full access. A principal token looks like `xpp_<principal_id>_<secret>`; the id picks the one
+//! argon2id hash to verify, so authentication cost does not grow with the number of principals.
+
+use axum::http::Method;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::admin_token::{AdminTokenHash, hash_admin_token_argon2id, parse_admin_token_hash};
+
+const TOKEN_PREFIX: &str = "xpp_";
+const TOKEN_SECRET_BYTES: usize = 32;
+const MAX_NAME_LEN: usize = 64;
+
+/// What an authenticated admin caller may do. Scopes are ordered: each one includes the
+/// permissions of the previous.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+    Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminScope {
+    /// Every `GET` admin route except those returning subscription tokens.
+    ReadOnly,
+    /// Read-only plus user reads that carry subscription tokens, and writes to users, their
+    /// grants and quota policy.
+    UserManager,
+    /// Everything, including principal management.
+    #[default]
+    Admin,
+}
+
+impl AdminScope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ReadOnly => "read_only",
+            Self::UserManager => "user_manager",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn permits(self, required: AdminScope) -> bool {
+        self >= required
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminPrincipal {
+    pub principal_id: String,
+    pub name: String,
+    pub scope: AdminScope,
+    /// argon2id PHC hash of the full principal token.
+    pub token_hash: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+impl AdminPrincipal {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+
+    pub fn token_hash(&self) -> Option<AdminTokenHash> {
+        parse_admin_token_hash(&self.token_hash)
+    }
+}
+
+pub fn validate_principal_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("name must be 1..={MAX_NAME_LEN} bytes"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("name must not contain control characters".to_string());
+    }
+    Ok(())
+}
+
+/// Generates a fresh token for `principal_id` together with its hash.
+pub fn issue_principal_token(principal_id: &str) -> Result<(String, AdminTokenHash), String> {
+    let mut secret = [0u8; TOKEN_SECRET_BYTES];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    let token = format!(
+        "{TOKEN_PREFIX}{principal_id}_{}",
+        URL_SAFE_NO_PAD.encode(secret)
+    );
+    let hash = hash_admin_token_argon2id(&token)?;
+    Ok((token, hash))
+}
+
+/// Returns the principal id of a principal token, or `None` for any other bearer token.
+pub fn principal_id_from_token(token: &str) -> Option<&str> {
+    let (principal_id, secret) = token.strip_prefix(TOKEN_PREFIX)?.split_once('_')?;
+    (!principal_id.is_empty() && !secret.is_empty()).then_some(principal_id)
+}
+
+/// Minimum scope for an admin route; `path` is relative to `/api/admin`.
+pub fn required_scope(method: &Method, path: &str) -> AdminScope {
+    let path = path.trim_end_matches('/');
+    // Backups hold subscription tokens and endpoint keys, so even reading one needs `admin`.
+    if path.starts_with("/_internal")
+        || path.starts_with("/principals")
+        || path.starts_with("/backup")
+    {
+        return AdminScope::Admin;
+    }
+    if matches!(*method, Method::GET | Method::HEAD) {
+        // A subscription token is a working credential for the user's subscription.
+        return if returns_subscription_tokens(path) {
+            AdminScope::UserManager
+        } else {
+            AdminScope::ReadOnly
+        };
+    }
+    let user_scoped =
+        path == "/users" || path.starts_with("/users/") || path.starts_with("/quota-policy/");
+    if user_scoped {
+        AdminScope::UserManager
+    } else {
+        AdminScope::Admin
+    }
+}
+
+/// `GET /users`, `/users/export`, `/users/{id}` and `/users/{id}/subscription-tokens`.
+fn returns_subscription_tokens(path: &str) -> bool {
+    if path == "/users" {
+        return true;
+    }
+    let Some(rest) = path.strip_prefix("/users/") else {
+        return false;
+    };
+    match rest.split('/').collect::<Vec<_>>().as_slice() {
+        [segment] => *segment != "quota-summaries",
+        [_user_id, "subscription-tokens"] => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests;
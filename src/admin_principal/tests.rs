@@ -0,0 +1,70 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+use crate::admin_token::verify_admin_token;
+
+#[test]
+fn scopes_include_lower_scopes() {
+    assert!(AdminScope::Admin.permits(AdminScope::UserManager));
+    assert!(AdminScope::UserManager.permits(AdminScope::ReadOnly));
+    assert!(!AdminScope::ReadOnly.permits(AdminScope::UserManager));
+    assert!(!AdminScope::UserManager.permits(AdminScope::Admin));
+}
+
+#[test]
+fn required_scope_follows_method_and_route() {
+    let cases = [
+        (Method::GET, "/endpoints", AdminScope::ReadOnly),
+        (Method::GET, "/users/u1/traffic", AdminScope::ReadOnly),
+        (Method::GET, "/users/quota-summaries", AdminScope::ReadOnly),
+        (Method::POST, "/users", AdminScope::UserManager),
+        (Method::PUT, "/users/u1/access", AdminScope::UserManager),
+        (
+            Method::PUT,
+            "/quota-policy/nodes/n1/policy",
+            AdminScope::UserManager,
+        ),
+        (Method::POST, "/endpoints", AdminScope::Admin),
+        (Method::POST, "/users-import", AdminScope::Admin),
+        (Method::GET, "/principals", AdminScope::Admin),
+        (Method::GET, "/_internal/principals", AdminScope::Admin),
+        (Method::GET, "/backup", AdminScope::Admin),
+    ];
+    for (method, path, expected) in cases {
+        assert_eq!(required_scope(&method, path), expected, "{method} {path}");
+    }
+}
+
+#[test]
+fn reading_subscription_tokens_needs_user_manager() {
+    let cases = [
+        (Method::GET, "/users"),
+        (Method::GET, "/users/"),
+        (Method::HEAD, "/users"),
+        (Method::GET, "/users/u1"),
+        (Method::GET, "/users/export"),
+        (Method::GET, "/users/u1/subscription-tokens"),
+    ];
+    for (method, path) in cases {
+        assert_eq!(
+            required_scope(&method, path),
+            AdminScope::UserManager,
+            "{method} {path}"
+        );
+    }
+}
+
+#[test]
+fn issued_tokens_carry_the_principal_id_and_verify_against_their_hash() {
+    let principal_id = xp_test_fixtures::identifier_ulid_d();
+    let (token, hash) = issue_principal_token(principal_id).unwrap();
+
+    assert_eq!(principal_id_from_token(&token), Some(principal_id));
+    assert!(verify_admin_token(&token, &hash));
+    let (other, _) = issue_principal_token(principal_id).unwrap();
+    assert!(!verify_admin_token(&other, &hash));
+
+    assert_eq!(principal_id_from_token("testtoken"), None);
+    assert_eq!(principal_id_from_token("xpp__secret"), None);
+    assert_eq!(principal_id_from_token("xpp_id_"), None);
+}
@@ -0,0 +1,256 @@
+//! Cluster-wide audit trail of admin mutations.
+//!
+//! Admin writes are submitted as [`DesiredStateCommand::Audited`], so the principal and origin
+//! node travel through the Raft log with the command itself. Every node records an entry when it
+//! applies such a log entry, keyed by the log index; re-applying a log after restart is
+//! idempotent. Entries are not replicated: a node that catches up from a snapshot only holds
+//! entries applied after it, records that gap, and forwards audit reads to a peer with the whole
+//! log.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::state::DesiredStateCommand;
+
+#[cfg(test)]
+mod tests;
+
+/// Upper bound on recorded field changes per entry; bulk rewrites are truncated.
+const MAX_CHANGES: usize = 200;
+const REDACTED: &str = "[redacted]";
+/// State sections that only carry runtime observations, never admin intent.
+const IGNORED_STATE_SECTIONS: [&str; 2] = [
+    "endpoint_probe_history",
+    "endpoint_probe_participants_by_hour",
+];
+const SENSITIVE_KEY_FRAGMENTS: [&str; 7] = [
+    "token",
+    "secret",
+    "password",
+    "private_key",
+    "psk",
+    "_pem",
+    "credential",
+];
+
+/// Who authenticated the admin request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditPrincipal {
+    AdminToken,
+    LoginToken {
+        token_id: String,
+    },
+    /// A named principal from [`crate::admin_principal`].
+    Principal {
+        principal_id: String,
+        name: String,
+    },
+}
+
+impl AuditPrincipal {
+    pub fn label(&self) -> String {
+        match self {
+            Self::AdminToken => "admin_token".to_string(),
+            Self::LoginToken { token_id } => format!("login_token:{token_id}"),
+            Self::Principal { principal_id, .. } => format!("principal:{principal_id}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditContext {
+    pub principal: AuditPrincipal,
+    /// Node whose admin API accepted the request.
+    pub origin_node_id: String,
+    pub requested_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditChange {
+    /// Dotted path into desired state, e.g. `users.<user_id>.disabled`.
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub log_index: u64,
+    pub occurred_at: String,
+    pub command: String,
+    /// `*_id` fields of the command, e.g. `user_id` or `endpoint_id`.
+    pub targets: BTreeMap<String, String>,
+    pub origin_node_id: String,
+    pub principal: AuditPrincipal,
+    pub changes: Vec<AuditChange>,
+    #[serde(default)]
+    pub changes_truncated: bool,
+}
+
+tokio::task_local! {
+    static CURRENT_PRINCIPAL: AuditPrincipal;
+}
+
+/// Runs `fut` with `principal` attached, so writes it submits are audited.
+pub async fn with_principal<F: std::future::Future>(
+    principal: AuditPrincipal,
+    fut: F,
+) -> F::Output {
+    CURRENT_PRINCIPAL.scope(principal, fut).await
+}
+
+pub fn current_principal() -> Option<AuditPrincipal> {
+    CURRENT_PRINCIPAL.try_with(Clone::clone).ok()
+}
+
+/// Serialized desired state in the shape used for diffing.
+pub fn diff_snapshot<T: Serialize>(state: &T) -> Value {
+    let mut value = serde_json::to_value(state).unwrap_or(Value::Null);
+    if let Value::Object(map) = &mut value {
+        for section in IGNORED_STATE_SECTIONS {
+            map.remove(section);
+        }
+    }
+    value
+}
+
+pub fn build_entry(
+    log_index: u64,
+    occurred_at: String,
+    context: AuditContext,
+    command: &DesiredStateCommand,
+    before: &Value,
+    after: &Value,
+) -> AuditEntry {
+    let command_json = serde_json::to_value(command).unwrap_or(Value::Null);
+    let mut changes = Vec::new();
+    let mut truncated = false;
+    diff_values(before, after, "", &mut changes, &mut truncated);
+    AuditEntry {
+        log_index,
+        occurred_at,
+        command: command_json
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        targets: command_targets(&command_json),
+        origin_node_id: context.origin_node_id,
+        principal: context.principal,
+        changes,
+        changes_truncated: truncated,
+    }
+}
+
+fn command_targets(command: &Value) -> BTreeMap<String, String> {
+    let mut targets = BTreeMap::new();
+    let Value::Object(fields) = command else {
+        return targets;
+    };
+    for (key, value) in fields {
+        match value {
+            Value::String(id) if key.ends_with("_id") => {
+                targets.insert(key.clone(), id.clone());
+            }
+            // Upserts carry the entity itself, e.g. `{"user": {"user_id": ..}}`.
+            Value::Object(inner) => {
+                for (inner_key, inner_value) in inner {
+                    if let Value::String(id) = inner_value
+                        && inner_key.ends_with("_id")
+                    {
+                        targets
+                            .entry(inner_key.clone())
+                            .or_insert_with(|| id.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    targets
+}
+
+fn is_sensitive(key: &str) -> bool {
+    SENSITIVE_KEY_FRAGMENTS
+        .iter()
+        .any(|fragment| key.contains(fragment))
+}
+
+fn redact(key: &str, value: &Value) -> Value {
+    if is_sensitive(key) {
+        return Value::String(REDACTED.to_string());
+    }
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), redact(key, value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|item| redact("", item)).collect()),
+        other => other.clone(),
+    }
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit('.').next().unwrap_or(path)
+}
+
+fn push_change(
+    path: &str,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    changes: &mut Vec<AuditChange>,
+    truncated: &mut bool,
+) {
+    if changes.len() >= MAX_CHANGES {
+        *truncated = true;
+        return;
+    }
+    let key = last_segment(path);
+    changes.push(AuditChange {
+        path: path.to_string(),
+        before: before.map(|value| redact(key, value)),
+        after: after.map(|value| redact(key, value)),
+    });
+}
+
+fn diff_values(
+    before: &Value,
+    after: &Value,
+    path: &str,
+    changes: &mut Vec<AuditChange>,
+    truncated: &mut bool,
+) {
+    if before == after {
+        return;
+    }
+    let (Value::Object(before_map), Value::Object(after_map)) = (before, after) else {
+        push_change(path, Some(before), Some(after), changes, truncated);
+        return;
+    };
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    for (key, before_value) in before_map {
+        match after_map.get(key) {
+            Some(after_value) => {
+                diff_values(before_value, after_value, &join(key), changes, truncated)
+            }
+            None => push_change(&join(key), Some(before_value), None, changes, truncated),
+        }
+    }
+    for (key, after_value) in after_map {
+        if !before_map.contains_key(key) {
+            push_change(&join(key), None, Some(after_value), changes, truncated);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use super::*;
+
+fn context() -> AuditContext {
+    AuditContext {
+        principal: AuditPrincipal::LoginToken {
+            token_id: "jti-1".to_string(),
+        },
+        origin_node_id: xp_test_fixtures::identifier_ulid_d().to_string(),
+        requested_at: "2026-01-01T00:00:00Z".to_string(),
+    }
+}
+
+#[test]
+fn entry_records_targets_and_nested_changes() {
+    let user_id = xp_test_fixtures::primary_user_id();
+    let command = DesiredStateCommand::SetUserDisabled {
+        user_id: user_id.to_string(),
+        disabled: true,
+    };
+    let before = json!({ "users": { user_id: { "disabled": false, "display_name": "a" } } });
+    let after = json!({ "users": { user_id: { "disabled": true, "display_name": "a" } } });
+
+    let entry = build_entry(7, "t".to_string(), context(), &command, &before, &after);
+
+    assert_eq!(entry.command, "set_user_disabled");
+    assert_eq!(entry.targets["user_id"], user_id);
+    assert_eq!(entry.principal.label(), "login_token:jti-1");
+    assert_eq!(
+        entry.changes,
+        vec![AuditChange {
+            path: format!("users.{user_id}.disabled"),
+            before: Some(json!(false)),
+            after: Some(json!(true)),
+        }]
+    );
+}
+
+#[test]
+fn entry_redacts_secrets_in_changed_values() {
+    let command = DesiredStateCommand::CompatNoop {
+        note: String::new(),
+    };
+    let before = json!({ "users": {} });
+    let after = json!({
+        "users": { "u": { "subscription_token": "s3cret", "display_name": "a" } },
+        "webhooks": [{ "secret": "hmac", "url": "https://example.invalid" }]
+    });
+
+    let entry = build_entry(1, "t".to_string(), context(), &command, &before, &after);
+
+    assert_eq!(entry.changes.len(), 2);
+    assert_eq!(
+        entry.changes[0].after,
+        Some(json!({ "subscription_token": REDACTED, "display_name": "a" }))
+    );
+    assert_eq!(
+        entry.changes[1].after,
+        Some(json!([{ "secret": REDACTED, "url": "https://example.invalid" }]))
+    );
+}
+
+#[test]
+fn audited_wrapper_round_trips_and_never_nests() {
+    let command = DesiredStateCommand::DeleteUser {
+        user_id: xp_test_fixtures::primary_user_id().to_string(),
+    };
+    let audited = command.clone().audited(context()).audited(context());
+    assert_eq!(audited.unaudited(), &command);
+
+    let decoded: DesiredStateCommand =
+        serde_json::from_value(serde_json::to_value(&audited).unwrap()).unwrap();
+    assert_eq!(decoded.into_audit_parts(), (Some(context()), command));
+}
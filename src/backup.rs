@@ -0,0 +1,539 @@
+//! Portable desired-state backups: a signed, versioned export of `PersistedState` and the plan
+//! for seeding another cluster from it.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier as _, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{egress::EndpointEgress, state::PersistedState};
+
+pub const BACKUP_FORMAT: &str = "xp-desired-state-backup";
+pub const BACKUP_VERSION: u32 = 1;
+
+const BACKUP_ED25519_KEY_CONTEXT: &[u8] = b"xp:v1:backup-signing-key";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BackupArtifact {
+    pub format: String,
+    pub version: u32,
+    pub cluster_id: String,
+    pub created_at: String,
+    /// Hex Ed25519 public key derived from the source cluster CA key.
+    pub signer_public_key: String,
+    /// `PersistedState` JSON without runtime-only sections, migrated to the latest schema on
+    /// restore.
+    pub state: serde_json::Value,
+    /// Hex Ed25519 signature over every other field.
+    pub signature: String,
+}
+
+#[derive(Serialize)]
+struct SignedBackupPayload<'a> {
+    format: &'a str,
+    version: u32,
+    cluster_id: &'a str,
+    created_at: &'a str,
+    signer_public_key: &'a str,
+    state: &'a serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupError {
+    UnsupportedFormat { format: String },
+    UnsupportedVersion { version: u32 },
+    InvalidSignature,
+    UntrustedSigner { signer_public_key: String },
+    InvalidState { reason: String },
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnsupportedFormat { format } => write!(f, "unsupported backup format: {format}"),
+            Self::UnsupportedVersion { version } => write!(
+                f,
+                "unsupported backup version {version}, this build reads up to {BACKUP_VERSION}"
+            ),
+            Self::InvalidSignature => write!(f, "backup signature does not match its contents"),
+            Self::UntrustedSigner { signer_public_key } => write!(
+                f,
+                "backup was signed by {signer_public_key}, not the expected signer"
+            ),
+            Self::InvalidState { reason } => write!(f, "invalid backup state: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+pub fn backup_signing_key(cluster_id: &str, cluster_ca_key_pem: &str) -> SigningKey {
+    let mut hasher = Sha256::new();
+    hasher.update(BACKUP_ED25519_KEY_CONTEXT);
+    hasher.update(cluster_id.as_bytes());
+    hasher.update([0]);
+    hasher.update(cluster_ca_key_pem.as_bytes());
+    SigningKey::from_bytes(&hasher.finalize().into())
+}
+
+/// Drops the sections tied to the running cluster's topology or runtime: join sessions,
+/// membership operations, probe history, history repositories and reverse mesh assignments.
+pub fn portable_state(state: &PersistedState) -> PersistedState {
+    let mut portable = state.clone();
+    portable.join_sessions.clear();
+    portable.membership_operations.clear();
+    portable.endpoint_probe_history.clear();
+    portable.endpoint_probe_participants_by_hour.clear();
+    portable.node_egress_probes.clear();
+    portable.credential_rotation = None;
+    portable.repository_membership = None;
+    portable.reverse_mesh_epoch = 0;
+    portable.reverse_mesh_assignments.clear();
+    portable.reverse_mesh_generation_counters.clear();
+    portable
+}
+
+pub fn create_backup(
+    state: &PersistedState,
+    cluster_id: &str,
+    cluster_ca_key_pem: &str,
+    created_at: String,
+) -> Result<BackupArtifact, BackupError> {
+    let state =
+        serde_json::to_value(portable_state(state)).map_err(|error| BackupError::InvalidState {
+            reason: error.to_string(),
+        })?;
+    let signing_key = backup_signing_key(cluster_id, cluster_ca_key_pem);
+    let mut artifact = BackupArtifact {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        cluster_id: cluster_id.to_string(),
+        created_at,
+        signer_public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+        state,
+        signature: String::new(),
+    };
+    artifact.signature = hex::encode(signing_key.sign(&artifact.signed_bytes()).to_bytes());
+    Ok(artifact)
+}
+
+impl BackupArtifact {
+    fn signed_bytes(&self) -> Vec<u8> {
+        // `serde_json::Value` keeps object keys sorted, so re-encoding a parsed artifact is stable.
+        serde_json::to_vec(&SignedBackupPayload {
+            format: &self.format,
+            version: self.version,
+            cluster_id: &self.cluster_id,
+            created_at: &self.created_at,
+            signer_public_key: &self.signer_public_key,
+            state: &self.state,
+        })
+        .expect("backup payload json serialization failed")
+    }
+
+    /// Checks format, version and signature, then migrates the embedded state. With
+    /// `trusted_signer` set, the artifact must also have been signed by that key; without it any
+    /// key is accepted, which proves nothing about who wrote the state.
+    pub fn verify(&self, trusted_signer: Option<&str>) -> Result<PersistedState, BackupError> {
+        if self.format != BACKUP_FORMAT {
+            return Err(BackupError::UnsupportedFormat {
+                format: self.format.clone(),
+            });
+        }
+        if self.version == 0 || self.version > BACKUP_VERSION {
+            return Err(BackupError::UnsupportedVersion {
+                version: self.version,
+            });
+        }
+        let key_bytes: [u8; 32] = hex::decode(&self.signer_public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(BackupError::InvalidSignature)?;
+        let signature_bytes: [u8; 64] = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(BackupError::InvalidSignature)?;
+        VerifyingKey::from_bytes(&key_bytes)
+            .map_err(|_| BackupError::InvalidSignature)?
+            .verify(
+                &self.signed_bytes(),
+                &Signature::from_bytes(&signature_bytes),
+            )
+            .map_err(|_| BackupError::InvalidSignature)?;
+        if let Some(trusted) = trusted_signer
+            && !trusted.trim().eq_ignore_ascii_case(&self.signer_public_key)
+        {
+            return Err(BackupError::UntrustedSigner {
+                signer_public_key: self.signer_public_key.clone(),
+            });
+        }
+        crate::state::migrate_state_value_to_latest(self.state.clone()).map_err(|error| {
+            BackupError::InvalidState {
+                reason: error.to_string(),
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RestoreConflict {
+    /// No target node was given for a backed-up node and none matched by id or name.
+    UnmappedNode {
+        source_node_id: String,
+    },
+    UnknownTargetNode {
+        node_id: String,
+    },
+    UserExists {
+        user_id: String,
+    },
+    SubscriptionTokenInUse {
+        user_id: String,
+    },
+    EndpointExists {
+        endpoint_id: String,
+    },
+    EndpointPortInUse {
+        endpoint_id: String,
+        node_id: String,
+        port: u16,
+    },
+    AdminPrincipalExists {
+        principal_id: String,
+    },
+    /// A `node` egress points at a node with no target node.
+    EgressNodeUnmapped {
+        endpoint_id: String,
+        node_id: String,
+    },
+    /// A DNS failover group's primary or standby has no target node.
+    DnsFailoverGroupUnmapped {
+        group_id: String,
+        node_id: String,
+    },
+}
+
+impl std::fmt::Display for RestoreConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnmappedNode { source_node_id } => {
+                write!(f, "backup node {source_node_id} has no target node")
+            }
+            Self::UnknownTargetNode { node_id } => write!(f, "target node not found: {node_id}"),
+            Self::UserExists { user_id } => write!(f, "user already exists: {user_id}"),
+            Self::SubscriptionTokenInUse { user_id } => {
+                write!(f, "subscription token of user {user_id} is already in use")
+            }
+            Self::EndpointExists { endpoint_id } => {
+                write!(f, "endpoint already exists: {endpoint_id}")
+            }
+            Self::EndpointPortInUse {
+                endpoint_id,
+                node_id,
+                port,
+            } => write!(
+                f,
+                "endpoint {endpoint_id} needs port {port} on node {node_id}, which is taken"
+            ),
+            Self::AdminPrincipalExists { principal_id } => {
+                write!(f, "admin principal already exists: {principal_id}")
+            }
+            Self::EgressNodeUnmapped {
+                endpoint_id,
+                node_id,
+            } => write!(
+                f,
+                "endpoint {endpoint_id} egresses through node {node_id}, which has no target node"
+            ),
+            Self::DnsFailoverGroupUnmapped { group_id, node_id } => write!(
+                f,
+                "dns failover group {group_id} uses node {node_id}, which has no target node"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RestorePlan {
+    /// Backed-up node id to target node id, for every node that could be resolved.
+    pub node_map: BTreeMap<String, String>,
+    pub conflicts: Vec<RestoreConflict>,
+    /// The backup with node ids remapped; `nodes` holds the target nodes with restored quotas.
+    pub restored: PersistedState,
+}
+
+/// Resolves every backed-up node to a target node (explicit mapping, then same id, then same
+/// name, then the only node of a single-node pair) and rewrites node ids accordingly.
+pub fn plan_restore(
+    mut source: PersistedState,
+    target: &PersistedState,
+    explicit_map: &BTreeMap<String, String>,
+) -> RestorePlan {
+    let mut node_map = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    let single_pair = source.nodes.len() == 1 && target.nodes.len() == 1;
+    for (source_node_id, source_node) in &source.nodes {
+        let resolved = if let Some(target_node_id) = explicit_map.get(source_node_id) {
+            if !target.nodes.contains_key(target_node_id) {
+                conflicts.push(RestoreConflict::UnknownTargetNode {
+                    node_id: target_node_id.clone(),
+                });
+                continue;
+            }
+            Some(target_node_id.clone())
+        } else if target.nodes.contains_key(source_node_id) {
+            Some(source_node_id.clone())
+        } else {
+            let mut by_name = target
+                .nodes
+                .values()
+                .filter(|node| node.node_name == source_node.node_name);
+            match (by_name.next(), by_name.next()) {
+                (Some(node), None) => Some(node.node_id.clone()),
+                _ if single_pair => target.nodes.keys().next().cloned(),
+                _ => None,
+            }
+        };
+        match resolved {
+            Some(target_node_id) => {
+                node_map.insert(source_node_id.clone(), target_node_id);
+            }
+            None => conflicts.push(RestoreConflict::UnmappedNode {
+                source_node_id: source_node_id.clone(),
+            }),
+        }
+    }
+
+    let remap = |node_id: &String| node_map.get(node_id).cloned().unwrap_or(node_id.clone());
+    let mut nodes = BTreeMap::new();
+    for (source_node_id, source_node) in &source.nodes {
+        let Some(mut node) = node_map
+            .get(source_node_id)
+            .and_then(|target_node_id| target.nodes.get(target_node_id))
+            .cloned()
+        else {
+            continue;
+        };
+        node.quota_limit_bytes = source_node.quota_limit_bytes;
+        node.quota_reset = source_node.quota_reset.clone();
+        nodes.insert(node.node_id.clone(), node);
+    }
+    source.nodes = nodes;
+    for endpoint in source.endpoints.values_mut() {
+        endpoint.node_id = remap(&endpoint.node_id);
+    }
+    source.node_user_endpoint_memberships =
+        std::mem::take(&mut source.node_user_endpoint_memberships)
+            .into_iter()
+            .map(|mut membership| {
+                membership.node_id = remap(&membership.node_id);
+                membership
+            })
+            .collect();
+    for quotas in source.user_node_quotas.values_mut() {
+        *quotas = std::mem::take(quotas)
+            .into_iter()
+            .map(|(node_id, quota)| (remap(&node_id), quota))
+            .collect();
+    }
+    for weights in source.user_node_weights.values_mut() {
+        *weights = std::mem::take(weights)
+            .into_iter()
+            .map(|(node_id, weight)| (remap(&node_id), weight))
+            .collect();
+    }
+    source.node_weight_policies = std::mem::take(&mut source.node_weight_policies)
+        .into_iter()
+        .map(|(node_id, policy)| (remap(&node_id), policy))
+        .collect();
+    for topup in source.traffic_topups.values_mut() {
+        topup.node_id = remap(&topup.node_id);
+    }
+    for domain in &mut source.reality_domains {
+        domain.disabled_node_ids = domain.disabled_node_ids.iter().map(remap).collect();
+    }
+    for egress in source.endpoint_egress.values_mut() {
+        if let EndpointEgress::Node { node_id } = egress {
+            *node_id = remap(node_id);
+        }
+    }
+    for group in source.dns_failover_groups.values_mut() {
+        group.primary_node_id = remap(&group.primary_node_id);
+        group.standby_node_ids = group.standby_node_ids.iter().map(remap).collect();
+    }
+
+    for conflict in restore_conflicts(&source, target) {
+        if !conflicts.contains(&conflict) {
+            conflicts.push(conflict);
+        }
+    }
+    RestorePlan {
+        node_map,
+        conflicts,
+        restored: source,
+    }
+}
+
+/// Conflicts between an already remapped backup and the current state.
+pub fn restore_conflicts(
+    restored: &PersistedState,
+    target: &PersistedState,
+) -> Vec<RestoreConflict> {
+    let mut conflicts = Vec::new();
+    let target_tokens = target
+        .users
+        .values()
+        .map(|user| user.subscription_token.as_str())
+        .chain(
+            target
+                .subscription_tokens
+                .values()
+                .map(|token| token.token.as_str()),
+        )
+        .collect::<BTreeSet<_>>();
+    for user in restored.users.values() {
+        let secondary_token_in_use = restored.subscription_tokens.values().any(|token| {
+            token.user_id == user.user_id && target_tokens.contains(token.token.as_str())
+        });
+        if target.users.contains_key(&user.user_id) {
+            conflicts.push(RestoreConflict::UserExists {
+                user_id: user.user_id.clone(),
+            });
+        } else if target_tokens.contains(user.subscription_token.as_str()) || secondary_token_in_use
+        {
+            conflicts.push(RestoreConflict::SubscriptionTokenInUse {
+                user_id: user.user_id.clone(),
+            });
+        }
+    }
+
+    let mut ports = target
+        .endpoints
+        .values()
+        .map(|endpoint| (endpoint.node_id.clone(), endpoint.port))
+        .collect::<BTreeSet<_>>();
+    let mut unknown_nodes = BTreeSet::new();
+    for endpoint in restored.endpoints.values() {
+        if target.endpoints.contains_key(&endpoint.endpoint_id) {
+            conflicts.push(RestoreConflict::EndpointExists {
+                endpoint_id: endpoint.endpoint_id.clone(),
+            });
+        } else if !target.nodes.contains_key(&endpoint.node_id) {
+            unknown_nodes.insert(endpoint.node_id.clone());
+        } else if !ports.insert((endpoint.node_id.clone(), endpoint.port)) {
+            conflicts.push(RestoreConflict::EndpointPortInUse {
+                endpoint_id: endpoint.endpoint_id.clone(),
+                node_id: endpoint.node_id.clone(),
+                port: endpoint.port,
+            });
+        }
+    }
+    conflicts.extend(
+        unknown_nodes
+            .into_iter()
+            .filter(|node_id| !restored.nodes.contains_key(node_id))
+            .map(|node_id| RestoreConflict::UnknownTargetNode { node_id }),
+    );
+
+    for principal_id in restored.admin_principals.keys() {
+        if target.admin_principals.contains_key(principal_id) {
+            conflicts.push(RestoreConflict::AdminPrincipalExists {
+                principal_id: principal_id.clone(),
+            });
+        }
+    }
+
+    for (endpoint_id, egress) in &restored.endpoint_egress {
+        if let EndpointEgress::Node { node_id } = egress
+            && !target.nodes.contains_key(node_id)
+        {
+            conflicts.push(RestoreConflict::EgressNodeUnmapped {
+                endpoint_id: endpoint_id.clone(),
+                node_id: node_id.clone(),
+            });
+        }
+    }
+    for (group_id, group) in &restored.dns_failover_groups {
+        if let Some(node_id) = std::iter::once(&group.primary_node_id)
+            .chain(&group.standby_node_ids)
+            .find(|node_id| !target.nodes.contains_key(*node_id))
+        {
+            conflicts.push(RestoreConflict::DnsFailoverGroupUnmapped {
+                group_id: group_id.clone(),
+                node_id: node_id.clone(),
+            });
+        }
+    }
+    conflicts
+}
+
+/// Merges a conflict-free, remapped backup into `state`. Target nodes only take the restored
+/// quota settings; Reality domains and webhooks already present by id or name are kept as they
+/// are.
+pub fn apply_restore(state: &mut PersistedState, restored: PersistedState) {
+    for (node_id, node) in restored.nodes {
+        if let Some(existing) = state.nodes.get_mut(&node_id) {
+            existing.quota_limit_bytes = node.quota_limit_bytes;
+            existing.quota_reset = node.quota_reset;
+        }
+    }
+    state.users.extend(restored.users);
+    state.endpoints.extend(restored.endpoints);
+    state
+        .node_user_endpoint_memberships
+        .extend(restored.node_user_endpoint_memberships);
+    state.user_node_quotas.extend(restored.user_node_quotas);
+    state.user_node_weights.extend(restored.user_node_weights);
+    state
+        .user_global_weights
+        .extend(restored.user_global_weights);
+    state
+        .node_weight_policies
+        .extend(restored.node_weight_policies);
+    state
+        .user_auto_assign_endpoint_kinds
+        .extend(restored.user_auto_assign_endpoint_kinds);
+    state
+        .user_mihomo_profiles
+        .extend(restored.user_mihomo_profiles);
+    state.traffic_topups.extend(restored.traffic_topups);
+    state
+        .subscription_tokens
+        .extend(restored.subscription_tokens);
+    state.admin_principals.extend(restored.admin_principals);
+    state.mihomo_delivery_mode = restored.mihomo_delivery_mode;
+    state.xray_routing_policy = restored.xray_routing_policy;
+    state.endpoint_egress.extend(
+        restored
+            .endpoint_egress
+            .into_iter()
+            .filter(|(endpoint_id, _egress)| state.endpoints.contains_key(endpoint_id)),
+    );
+    state.endpoint_probe_targets = restored.endpoint_probe_targets;
+    state
+        .dns_failover_groups
+        .extend(restored.dns_failover_groups);
+    state.mihomo_resource_allow_private_targets = restored.mihomo_resource_allow_private_targets;
+    for domain in restored.reality_domains {
+        let exists = state.reality_domains.iter().any(|existing| {
+            existing.server_name == domain.server_name || existing.domain_id == domain.domain_id
+        });
+        if !exists {
+            state.reality_domains.push(domain);
+        }
+    }
+    for webhook in restored.notification_webhooks {
+        let exists = state
+            .notification_webhooks
+            .iter()
+            .any(|existing| existing.name == webhook.name);
+        if !exists {
+            state.notification_webhooks.push(webhook);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -263,6 +263,14 @@ pub struct ClusterCaPem {
     pub key_pem: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfSignedServerCertPem {
+    pub cert_pem: String,
+    pub key_pem: String,
+    /// Lowercase hex SHA-256 of the DER certificate, used by clients to pin it.
+    pub cert_sha256: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NodeCsrPem {
     pub csr_pem: String,
@@ -326,6 +334,33 @@ pub fn generate_node_keypair_and_csr(node_id: &str) -> Result<NodeCsrPem, CertEr
     })
 }
 
+/// Generates a standalone self-signed certificate for endpoints that terminate TLS themselves
+/// (e.g. Hysteria2). It is not issued by the cluster CA; clients pin it by `cert_sha256`.
+pub fn generate_self_signed_server_cert(
+    server_name: &str,
+) -> Result<SelfSignedServerCertPem, CertError> {
+    let mut params = CertificateParams::default();
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, server_name);
+    params.distinguished_name = dn;
+    params.subject_alt_names = vec![SanType::DnsName(server_name.try_into()?)];
+    params.is_ca = IsCa::NoCa;
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+    let now = OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::days(1);
+    params.not_after = now + time::Duration::days(3650);
+
+    let key = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)?;
+    let cert = params.self_signed(&key)?;
+    let cert_sha256 = hex::encode(<Sha256 as sha2::Digest>::digest(cert.der().as_ref()));
+
+    Ok(SelfSignedServerCertPem {
+        cert_pem: cert.pem(),
+        key_pem: key.serialize_pem(),
+        cert_sha256,
+    })
+}
+
 pub fn sign_node_csr(
     cluster_id: &str,
     ca_key_pem: &str,
@@ -459,4 +494,18 @@ mod tests {
         let (_rem, signed_pem) = parse_x509_pem(signed.as_bytes()).unwrap();
         parse_x509_certificate(signed_pem.contents.as_ref()).unwrap();
     }
+
+    #[test]
+    fn self_signed_server_cert_fingerprint_matches_der() {
+        let cert = generate_self_signed_server_cert(xp_test_fixtures::subscription_host_example())
+            .unwrap();
+
+        let (_rem, pem) = parse_x509_pem(cert.cert_pem.as_bytes()).unwrap();
+        parse_x509_certificate(pem.contents.as_ref()).unwrap();
+        assert_eq!(
+            cert.cert_sha256,
+            hex::encode(<Sha256 as sha2::Digest>::digest(&pem.contents))
+        );
+        KeyPair::from_pem(&cert.key_pem).unwrap();
+    }
 }
@@ -27,6 +27,8 @@ pub struct ClusterPaths {
     pub node_cert_pem: PathBuf,
     pub node_key_pem: PathBuf,
     pub raft_bootstrap_sender: PathBuf,
+    /// Holds the new CA and node certificate while a credential rotation is staged.
+    pub credential_rotation_dir: PathBuf,
 }
 
 impl ClusterPaths {
@@ -41,6 +43,7 @@ impl ClusterPaths {
             node_cert_pem: dir.join("node_cert.pem"),
             node_key_pem: dir.join("node_key.pem"),
             raft_bootstrap_sender: dir.join("raft_bootstrap_sender"),
+            credential_rotation_dir: dir.join("rotation"),
             dir,
         }
     }
@@ -2,12 +2,21 @@ use std::{net::SocketAddr, path::PathBuf};
 
 use clap::{Args, Parser, Subcommand};
 
-use crate::admin_token::{AdminTokenHash, parse_admin_token_hash};
+use crate::{
+    admin_principal::AdminScope,
+    admin_token::{AdminTokenHash, parse_admin_token_hash},
+};
 
 pub const DEFAULT_VLESS_CANARY_BIND: &str = "127.0.0.1:39043";
 pub const DEFAULT_VLESS_CANARY_BIND_PORT: u16 = 39043;
 pub const DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE: &str = "/etc/xp/cloudflare_ddns_api_token";
 
+mod ddns;
+pub use ddns::{
+    DEFAULT_DDNS_HTTP_TOKEN_FILE, DEFAULT_DDNS_RFC2136_TTL, DEFAULT_DDNS_TSIG_SECRET_FILE,
+    DdnsHttpMethod, DdnsProviderConfig, DdnsProviderKind, TsigAlgorithm,
+};
+
 #[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum XrayRestartMode {
     None,
@@ -42,7 +51,14 @@ pub enum Command {
     Join(JoinArgs),
 
     /// Generate a one-time admin login link for the web UI.
-    LoginLink,
+    LoginLink(LoginLinkArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LoginLinkArgs {
+    /// Scope granted to the login session.
+    #[arg(long, value_enum, default_value_t = AdminScope::Admin)]
+    pub scope: AdminScope,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -234,6 +250,36 @@ pub struct Config {
     )]
     pub admin_token_hash: String,
 
+    /// Optional listener for the Prometheus `/metrics` endpoint; disabled when unset.
+    #[arg(
+        long = "metrics-bind",
+        global = true,
+        env = "XP_METRICS_BIND",
+        value_name = "ADDR"
+    )]
+    pub metrics_bind: Option<SocketAddr>,
+
+    /// Argon2id hash of the bearer token accepted by `/metrics` (separate from the admin token).
+    #[arg(
+        long = "metrics-token-hash",
+        global = true,
+        env = "XP_METRICS_TOKEN_HASH",
+        value_name = "HASH",
+        default_value = ""
+    )]
+    pub metrics_token_hash: String,
+
+    /// Refresh interval advertised to subscription clients via `profile-update-interval`.
+    #[arg(
+        long = "subscription-update-interval-hours",
+        global = true,
+        env = "XP_SUBSCRIPTION_UPDATE_INTERVAL_HOURS",
+        value_name = "HOURS",
+        default_value_t = 24,
+        value_parser = clap::value_parser!(u32).range(1..=168)
+    )]
+    pub subscription_update_interval_hours: u32,
+
     #[arg(
         long,
         global = true,
@@ -445,6 +491,9 @@ pub struct Config {
     )]
     pub cloudflare_ddns_family_missing_grace: u64,
 
+    #[command(flatten)]
+    pub ddns: DdnsProviderConfig,
+
     #[arg(
         long = "endpoint-probe-skip-self-test",
         global = true,
@@ -477,6 +526,51 @@ pub struct Config {
     )]
     pub quota_auto_unban: bool,
 
+    /// Percentages of a user's node quota at which a warning is raised (once per cycle each).
+    #[arg(
+        long = "quota-warning-thresholds",
+        global = true,
+        env = "XP_QUOTA_WARNING_THRESHOLDS",
+        value_name = "PERCENTS",
+        value_delimiter = ',',
+        default_values_t = [80u8, 95u8],
+        value_parser = clap::value_parser!(u8).range(1..=99)
+    )]
+    pub quota_warning_thresholds: Vec<u8>,
+
+    /// Distinct source IPs one subscription token may be fetched from within the abuse window
+    /// before an alert is raised; `0` disables the check.
+    #[arg(
+        long = "subscription-abuse-max-ips",
+        global = true,
+        env = "XP_SUBSCRIPTION_ABUSE_MAX_IPS",
+        value_name = "COUNT",
+        default_value_t = 10
+    )]
+    pub subscription_abuse_max_ips: u32,
+
+    /// Distinct countries one subscription token may be fetched from within the abuse window
+    /// before an alert is raised; `0` disables the check.
+    #[arg(
+        long = "subscription-abuse-max-countries",
+        global = true,
+        env = "XP_SUBSCRIPTION_ABUSE_MAX_COUNTRIES",
+        value_name = "COUNT",
+        default_value_t = 3
+    )]
+    pub subscription_abuse_max_countries: u32,
+
+    /// Sliding window, in hours, over which subscription fetches are counted for abuse alerts.
+    #[arg(
+        long = "subscription-abuse-window-hours",
+        global = true,
+        env = "XP_SUBSCRIPTION_ABUSE_WINDOW_HOURS",
+        value_name = "HOURS",
+        default_value_t = 24,
+        value_parser = clap::value_parser!(u32).range(1..=168)
+    )]
+    pub subscription_abuse_window_hours: u32,
+
     #[arg(
         long = "ip-geo-enabled",
         global = true,
@@ -503,6 +597,10 @@ impl Config {
         parse_admin_token_hash(&self.admin_token_hash)
     }
 
+    pub fn metrics_token_hash(&self) -> Option<AdminTokenHash> {
+        parse_admin_token_hash(&self.metrics_token_hash)
+    }
+
     pub fn effective_cloudflared_monitor_mode(&self) -> XrayRestartMode {
         self.cloudflared_monitor_mode
             .unwrap_or(self.cloudflared_restart_mode)
@@ -560,6 +658,12 @@ mod tests {
         assert_eq!(cli.config.cloudflare_ddns_fast_interval_secs, 30);
         assert_eq!(cli.config.cloudflare_ddns_fast_window_secs, 300);
         assert_eq!(cli.config.cloudflare_ddns_family_missing_grace, 3);
+        assert_eq!(cli.config.ddns.provider, DdnsProviderKind::Cloudflare);
+        assert_eq!(
+            cli.config.ddns.rfc2136_tsig_secret_file,
+            DEFAULT_DDNS_TSIG_SECRET_FILE
+        );
+        assert_eq!(cli.config.ddns.rfc2136_ttl, DEFAULT_DDNS_RFC2136_TTL);
         assert_eq!(
             cli.config.vless_canary_bind,
             DEFAULT_VLESS_CANARY_BIND.parse().unwrap()
@@ -708,6 +812,26 @@ mod tests {
         assert!(msg.contains("1..=10"));
     }
 
+    #[test]
+    fn parses_rfc2136_ddns_provider_flags() {
+        let cli = Cli::try_parse_from([
+            "xp",
+            "--ddns-provider",
+            "rfc2136",
+            "--ddns-rfc2136-server",
+            "ns1.example.com:5353",
+            "--ddns-rfc2136-tsig-algorithm",
+            "hmac-sha512",
+        ])
+        .unwrap();
+        assert_eq!(cli.config.ddns.provider, DdnsProviderKind::Rfc2136);
+        assert_eq!(cli.config.ddns.rfc2136_server, "ns1.example.com:5353");
+        assert_eq!(
+            cli.config.ddns.rfc2136_tsig_algorithm,
+            TsigAlgorithm::HmacSha512
+        );
+    }
+
     #[test]
     fn rejects_invalid_quota_poll_interval_secs() {
         let err = Cli::try_parse_from(["xp", "--quota-poll-interval-secs", "4"]).unwrap_err();
@@ -716,6 +840,18 @@ mod tests {
         assert!(msg.contains("5..=30"));
     }
 
+    #[test]
+    fn parses_quota_warning_thresholds_as_percent_list() {
+        let cli = Cli::try_parse_from(["xp"]).unwrap();
+        assert_eq!(cli.config.quota_warning_thresholds, vec![80, 95]);
+
+        let cli = Cli::try_parse_from(["xp", "--quota-warning-thresholds", "50,90,99"]).unwrap();
+        assert_eq!(cli.config.quota_warning_thresholds, vec![50, 90, 99]);
+
+        let err = Cli::try_parse_from(["xp", "--quota-warning-thresholds", "80,100"]).unwrap_err();
+        assert!(err.to_string().contains("--quota-warning-thresholds"));
+    }
+
     #[test]
     fn parses_quota_auto_unban_as_bool_value() {
         let cli = Cli::try_parse_from(["xp", "--quota-auto-unban", "false"]).unwrap();
@@ -0,0 +1,210 @@
+use clap::Args;
+
+pub const DEFAULT_DDNS_TSIG_SECRET_FILE: &str = "/etc/xp/ddns_tsig_secret";
+pub const DEFAULT_DDNS_HTTP_TOKEN_FILE: &str = "/etc/xp/ddns_http_token";
+pub const DEFAULT_DDNS_RFC2136_TTL: u32 = 60;
+
+/// DNS backend that DDNS publishes `XP_ACCESS_HOST` through.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DdnsProviderKind {
+    #[default]
+    Cloudflare,
+    Rfc2136,
+    Http,
+}
+
+impl DdnsProviderKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Cloudflare => "cloudflare",
+            Self::Rfc2136 => "rfc2136",
+            Self::Http => "http",
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TsigAlgorithm {
+    #[default]
+    HmacSha256,
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::HmacSha256 => "hmac-sha256",
+            Self::HmacSha512 => "hmac-sha512",
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DdnsHttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+}
+
+impl DdnsHttpMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::Post => "post",
+            Self::Put => "put",
+        }
+    }
+}
+
+/// Provider selection for DDNS. `XP_CLOUDFLARE_DDNS_ENABLED` and the shared
+/// `XP_CLOUDFLARE_DDNS_*` interval settings still switch and pace reconciliation for every
+/// provider.
+#[derive(Args, Debug, Clone)]
+pub struct DdnsProviderConfig {
+    #[arg(
+        long = "ddns-provider",
+        global = true,
+        env = "XP_DDNS_PROVIDER",
+        value_name = "PROVIDER",
+        default_value = "cloudflare",
+        value_enum
+    )]
+    pub provider: DdnsProviderKind,
+
+    /// Authoritative server accepting RFC 2136 updates, as `host` or `host:port`.
+    #[arg(
+        long = "ddns-rfc2136-server",
+        global = true,
+        env = "XP_DDNS_RFC2136_SERVER",
+        value_name = "HOST[:PORT]",
+        default_value = ""
+    )]
+    pub rfc2136_server: String,
+
+    #[arg(
+        long = "ddns-rfc2136-zone",
+        global = true,
+        env = "XP_DDNS_RFC2136_ZONE",
+        value_name = "ZONE",
+        default_value = ""
+    )]
+    pub rfc2136_zone: String,
+
+    #[arg(
+        long = "ddns-rfc2136-tsig-key-name",
+        global = true,
+        env = "XP_DDNS_RFC2136_TSIG_KEY_NAME",
+        value_name = "NAME",
+        default_value = ""
+    )]
+    pub rfc2136_tsig_key_name: String,
+
+    #[arg(
+        long = "ddns-rfc2136-tsig-algorithm",
+        global = true,
+        env = "XP_DDNS_RFC2136_TSIG_ALGORITHM",
+        value_name = "ALGORITHM",
+        default_value = "hmac-sha256",
+        value_enum
+    )]
+    pub rfc2136_tsig_algorithm: TsigAlgorithm,
+
+    /// File holding the base64 TSIG secret (the `secret` of a `tsig-keygen` key).
+    #[arg(
+        long = "ddns-rfc2136-tsig-secret-file",
+        global = true,
+        env = "XP_DDNS_RFC2136_TSIG_SECRET_FILE",
+        value_name = "PATH",
+        default_value = DEFAULT_DDNS_TSIG_SECRET_FILE
+    )]
+    pub rfc2136_tsig_secret_file: String,
+
+    #[arg(
+        long = "ddns-rfc2136-ttl",
+        global = true,
+        env = "XP_DDNS_RFC2136_TTL",
+        value_name = "SECS",
+        default_value_t = DEFAULT_DDNS_RFC2136_TTL,
+        value_parser = clap::value_parser!(u32).range(1..=86400)
+    )]
+    pub rfc2136_ttl: u32,
+
+    /// Update URL; `{hostname}`, `{ip}`, `{record_type}`, `{family}` and `{token}` are substituted.
+    #[arg(
+        long = "ddns-http-url-template",
+        global = true,
+        env = "XP_DDNS_HTTP_URL_TEMPLATE",
+        value_name = "URL",
+        default_value = ""
+    )]
+    pub http_url_template: String,
+
+    #[arg(
+        long = "ddns-http-method",
+        global = true,
+        env = "XP_DDNS_HTTP_METHOD",
+        value_name = "METHOD",
+        default_value = "get",
+        value_enum
+    )]
+    pub http_method: DdnsHttpMethod,
+
+    #[arg(
+        long = "ddns-http-body-template",
+        global = true,
+        env = "XP_DDNS_HTTP_BODY_TEMPLATE",
+        value_name = "TEMPLATE",
+        default_value = ""
+    )]
+    pub http_body_template: String,
+
+    /// Optional URL called when an address family disappears; without it the record is left as is.
+    #[arg(
+        long = "ddns-http-delete-url-template",
+        global = true,
+        env = "XP_DDNS_HTTP_DELETE_URL_TEMPLATE",
+        value_name = "URL",
+        default_value = ""
+    )]
+    pub http_delete_url_template: String,
+
+    /// Regex a 2xx response body must match, for APIs that report failures with `200 OK`.
+    #[arg(
+        long = "ddns-http-success-regex",
+        global = true,
+        env = "XP_DDNS_HTTP_SUCCESS_REGEX",
+        value_name = "REGEX",
+        default_value = ""
+    )]
+    pub http_success_regex: String,
+
+    #[arg(
+        long = "ddns-http-token-file",
+        global = true,
+        env = "XP_DDNS_HTTP_TOKEN_FILE",
+        value_name = "PATH",
+        default_value = ""
+    )]
+    pub http_token_file: String,
+}
+
+impl Default for DdnsProviderConfig {
+    fn default() -> Self {
+        Self {
+            provider: DdnsProviderKind::Cloudflare,
+            rfc2136_server: String::new(),
+            rfc2136_zone: String::new(),
+            rfc2136_tsig_key_name: String::new(),
+            rfc2136_tsig_algorithm: TsigAlgorithm::HmacSha256,
+            rfc2136_tsig_secret_file: DEFAULT_DDNS_TSIG_SECRET_FILE.to_string(),
+            rfc2136_ttl: DEFAULT_DDNS_RFC2136_TTL,
+            http_url_template: String::new(),
+            http_method: DdnsHttpMethod::Get,
+            http_body_template: String::new(),
+            http_delete_url_template: String::new(),
+            http_success_regex: String::new(),
+            http_token_file: String::new(),
+        }
+    }
+}
@@ -20,7 +20,9 @@ use crate::{
 };
 
 mod reverse;
+mod signing;
 mod transport;
+use signing::{signed_headers, signed_send};
 #[cfg(test)]
 pub(crate) use transport::build_mesh_http_client_with_policy;
 pub(crate) use transport::build_unauthenticated_mesh_http_client;
@@ -256,6 +258,7 @@ pub struct MeshAwareHttpClient {
     reverse_routes: Arc<RwLock<BTreeMap<String, ReverseRelayRoute>>>,
     reverse_enabled: Arc<AtomicBool>,
     local_reverse_relay: Option<reverse::LocalReverseRelay>,
+    alternate_roots: internal_auth::AlternateRoots,
 }
 
 impl MeshAwareHttpClient {
@@ -272,6 +275,7 @@ impl MeshAwareHttpClient {
             reverse_routes: Arc::new(RwLock::new(BTreeMap::new())),
             reverse_enabled: Arc::new(AtomicBool::new(true)),
             local_reverse_relay: None,
+            alternate_roots: internal_auth::AlternateRoots::default(),
         }
     }
 
@@ -293,6 +297,11 @@ impl MeshAwareHttpClient {
         self.circuits.clone()
     }
 
+    /// Roots whose acknowledgements verify besides the local CA; shared by every clone.
+    pub fn alternate_roots(&self) -> internal_auth::AlternateRoots {
+        self.alternate_roots.clone()
+    }
+
     pub fn with_reverse_routes(mut self, routes: BTreeMap<String, ReverseRelayRoute>) -> Self {
         self.reverse_routes = Arc::new(RwLock::new(routes));
         self
@@ -378,6 +387,7 @@ impl MeshAwareHttpClient {
         internal_auth::verify_ack_v2(
             cluster_ca_key_pem,
             cluster_ca_cert_pem,
+            &self.alternate_roots.snapshot(),
             &verified,
             &peer.node_id,
             response.status().as_u16(),
@@ -524,6 +534,7 @@ impl MeshAwareHttpClient {
                         if let Err(error) = internal_auth::verify_ack_v2(
                             cluster_ca_key_pem,
                             cluster_ca_cert_pem,
+                            &self.alternate_roots.snapshot(),
                             &verified,
                             &peer.node_id,
                             response.status().as_u16(),
@@ -757,6 +768,7 @@ impl MeshAwareHttpClient {
         if let Err(error) = internal_auth::verify_ack_v2(
             cluster_ca_key_pem,
             cluster_ca_cert_pem,
+            &self.alternate_roots.snapshot(),
             &verified,
             &context.target_id,
             response.status().as_u16(),
@@ -928,6 +940,7 @@ impl MeshAwareHttpClient {
         internal_auth::verify_ack_v2(
             cluster_ca_key_pem,
             cluster_ca_cert_pem,
+            &self.alternate_roots.snapshot(),
             &outer_verified,
             &route.rendezvous.node_id,
             response.status().as_u16(),
@@ -943,6 +956,7 @@ impl MeshAwareHttpClient {
         internal_auth::verify_ack_v2(
             cluster_ca_key_pem,
             cluster_ca_cert_pem,
+            &self.alternate_roots.snapshot(),
             &inner_verified,
             &peer.node_id,
             response.status().as_u16(),
@@ -1128,76 +1142,5 @@ fn join_url(base: &str, path_and_query: &str) -> Result<String, MeshRequestError
     ))
 }
 
-async fn signed_send(
-    client: &reqwest::Client,
-    url: &str,
-    request: &MeshRequest,
-    context: &RequestContext,
-    cluster_ca_key_pem: &str,
-    cluster_ca_cert_pem: &str,
-) -> Result<(reqwest::Response, internal_auth::VerifiedRequest), reqwest::Error> {
-    let (headers, verified) =
-        signed_headers(request, context, cluster_ca_key_pem, cluster_ca_cert_pem);
-    let mut builder = client
-        .request(request.method.clone(), url)
-        .body(request.body.clone());
-    for (name, value) in &headers {
-        builder = builder.header(name, value);
-    }
-    let response = builder.send().await?;
-    Ok((response, verified))
-}
-
-fn signed_headers(
-    request: &MeshRequest,
-    context: &RequestContext,
-    cluster_ca_key_pem: &str,
-    cluster_ca_cert_pem: &str,
-) -> (axum::http::HeaderMap, internal_auth::VerifiedRequest) {
-    let uri = request
-        .path_and_query
-        .parse::<axum::http::Uri>()
-        .expect("validated request path");
-    let mut headers = axum::http::HeaderMap::new();
-    if let Some(content_type) = request.content_type.as_deref() {
-        headers.insert(
-            "content-type",
-            content_type.parse().expect("valid content type"),
-        );
-    }
-    headers.insert(
-        "content-length",
-        request
-            .body
-            .len()
-            .to_string()
-            .parse()
-            .expect("valid content length"),
-    );
-    // Signing failures are malformed local inputs, not network errors.
-    internal_auth::sign_request_v2(
-        cluster_ca_key_pem,
-        cluster_ca_cert_pem,
-        &request.method,
-        &uri,
-        request.content_type.as_deref(),
-        &request.body,
-        context,
-        &mut headers,
-    )
-    .expect("validated internal request context");
-    let verified = internal_auth::verify_request_v2(
-        cluster_ca_key_pem,
-        cluster_ca_cert_pem,
-        &request.method,
-        &uri,
-        &headers,
-        &request.body,
-        &context.cluster_id,
-        &context.target_id,
-    )
-    .expect("locally signed internal request verifies");
-    (headers, verified)
-}
 #[cfg(test)]
 mod peer_target_tests;
@@ -0,0 +1,75 @@
+use super::MeshRequest;
+use crate::internal_auth::{self, RequestContext};
+
+pub(super) async fn signed_send(
+    client: &reqwest::Client,
+    url: &str,
+    request: &MeshRequest,
+    context: &RequestContext,
+    cluster_ca_key_pem: &str,
+    cluster_ca_cert_pem: &str,
+) -> Result<(reqwest::Response, internal_auth::VerifiedRequest), reqwest::Error> {
+    let (headers, verified) =
+        signed_headers(request, context, cluster_ca_key_pem, cluster_ca_cert_pem);
+    let mut builder = client
+        .request(request.method.clone(), url)
+        .body(request.body.clone());
+    for (name, value) in &headers {
+        builder = builder.header(name, value);
+    }
+    let response = builder.send().await?;
+    Ok((response, verified))
+}
+
+pub(super) fn signed_headers(
+    request: &MeshRequest,
+    context: &RequestContext,
+    cluster_ca_key_pem: &str,
+    cluster_ca_cert_pem: &str,
+) -> (axum::http::HeaderMap, internal_auth::VerifiedRequest) {
+    let uri = request
+        .path_and_query
+        .parse::<axum::http::Uri>()
+        .expect("validated request path");
+    let mut headers = axum::http::HeaderMap::new();
+    if let Some(content_type) = request.content_type.as_deref() {
+        headers.insert(
+            "content-type",
+            content_type.parse().expect("valid content type"),
+        );
+    }
+    headers.insert(
+        "content-length",
+        request
+            .body
+            .len()
+            .to_string()
+            .parse()
+            .expect("valid content length"),
+    );
+    // Signing failures are malformed local inputs, not network errors.
+    internal_auth::sign_request_v2(
+        cluster_ca_key_pem,
+        cluster_ca_cert_pem,
+        &request.method,
+        &uri,
+        request.content_type.as_deref(),
+        &request.body,
+        context,
+        &mut headers,
+    )
+    .expect("validated internal request context");
+    let verified = internal_auth::verify_request_v2(
+        cluster_ca_key_pem,
+        cluster_ca_cert_pem,
+        &[],
+        &request.method,
+        &uri,
+        &headers,
+        &request.body,
+        &context.cluster_id,
+        &context.target_id,
+    )
+    .expect("locally signed internal request verifies");
+    (headers, verified)
+}
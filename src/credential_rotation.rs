@@ -0,0 +1,455 @@
+//! Staged rotation of the cluster CA.
+//!
+//! The CA key signs node certificates, keys internal request signatures and is the root every
+//! user credential is derived from (`credentials.rs`), so replacing it at once would break every
+//! node and client together. A rotation instead keeps both roots accepted until every node runs
+//! on the new one:
+//!
+//! 1. `staged`: the new CA travels through Raft. Xray accepts credentials derived from either
+//!    root, internal auth accepts either CA, and each node signs itself a certificate under the
+//!    new CA.
+//! 2. `promoted`: each node moves the new CA and certificate into its cluster directory. After a
+//!    restart it signs requests and hands out credentials with the new root.
+//! 3. Retire: once every node runs on the new root and the grace period has passed, the old root
+//!    is dropped from Raft, Xray and internal auth.
+
+use std::{collections::BTreeMap, fs, io, path::Path, sync::Arc, time::Duration};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::{
+    cluster_identity::{
+        CertError, generate_cluster_ca, generate_node_keypair_and_csr, sign_node_csr,
+    },
+    cluster_metadata::{ClusterPaths, write_atomic, write_atomic_private},
+    id::new_ulid_string,
+    internal_auth,
+    raft::{app::RaftFacade, types::ClientResponse},
+    state::{DesiredStateCommand, JsonSnapshotStore},
+};
+
+#[cfg(test)]
+mod tests;
+
+pub const DEFAULT_GRACE_PERIOD_HOURS: u32 = 168;
+pub const MAX_GRACE_PERIOD_HOURS: u32 = 24 * 90;
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+const STAGED_ROTATION_ID_FILE: &str = "rotation_id";
+
+/// A cluster CA; its private key doubles as the user credential root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialRoot {
+    pub ca_cert_pem: String,
+    pub ca_key_pem: String,
+}
+
+impl CredentialRoot {
+    /// Hex SHA-256 of the certificate PEM; identifies the root without exposing its key.
+    pub fn fingerprint(&self) -> String {
+        hex::encode(Sha256::digest(self.ca_cert_pem.trim().as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialRotationPhase {
+    Staged,
+    Promoted,
+}
+
+/// Where one node is in the rotation, derived from [`CredentialRotationNode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialRotationNodeStage {
+    Pending,
+    /// Holds a certificate signed by the new CA.
+    CertIssued,
+    /// Its cluster directory holds the new CA; the running process still uses the old one.
+    RestartRequired,
+    /// Runs on the new root.
+    Switched,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialRotationNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_issued_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files_switched_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub switched_at: Option<String>,
+}
+
+impl CredentialRotationNode {
+    pub fn stage(&self) -> CredentialRotationNodeStage {
+        if self.switched_at.is_some() {
+            CredentialRotationNodeStage::Switched
+        } else if self.files_switched_at.is_some() {
+            CredentialRotationNodeStage::RestartRequired
+        } else if self.cert_issued_at.is_some() {
+            CredentialRotationNodeStage::CertIssued
+        } else {
+            CredentialRotationNodeStage::Pending
+        }
+    }
+
+    fn record(&mut self, stage: CredentialRotationNodeStage, at: &str) {
+        let slot = match stage {
+            CredentialRotationNodeStage::Pending => return,
+            CredentialRotationNodeStage::CertIssued => &mut self.cert_issued_at,
+            CredentialRotationNodeStage::RestartRequired => &mut self.files_switched_at,
+            CredentialRotationNodeStage::Switched => &mut self.switched_at,
+        };
+        slot.get_or_insert_with(|| at.to_string());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialRotation {
+    pub rotation_id: String,
+    pub phase: CredentialRotationPhase,
+    pub started_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub promoted_at: Option<String>,
+    /// How long both roots stay accepted after the last node switched, so clients can refresh
+    /// their subscriptions.
+    pub grace_period_hours: u32,
+    pub previous: CredentialRoot,
+    pub next: CredentialRoot,
+    /// Progress reported by each node, by `node_id`.
+    #[serde(default)]
+    pub nodes: BTreeMap<String, CredentialRotationNode>,
+}
+
+impl CredentialRotation {
+    /// Starts a rotation away from `previous` with a freshly generated CA.
+    pub fn start(
+        cluster_id: &str,
+        previous: CredentialRoot,
+        grace_period_hours: u32,
+        started_at: String,
+    ) -> Result<Self, CertError> {
+        let next = generate_cluster_ca(cluster_id)?;
+        Ok(Self {
+            rotation_id: new_ulid_string(),
+            phase: CredentialRotationPhase::Staged,
+            started_at,
+            promoted_at: None,
+            grace_period_hours,
+            previous,
+            next: CredentialRoot {
+                ca_cert_pem: next.cert_pem,
+                ca_key_pem: next.key_pem,
+            },
+            nodes: BTreeMap::new(),
+        })
+    }
+
+    pub fn node_stage(&self, node_id: &str) -> CredentialRotationNodeStage {
+        self.nodes
+            .get(node_id)
+            .map(CredentialRotationNode::stage)
+            .unwrap_or(CredentialRotationNodeStage::Pending)
+    }
+
+    /// The root a node running on `local_ca_key_pem` accepts besides its own: the new one before
+    /// it switched, the old one after. `None` for a key outside the rotation.
+    pub fn alternate_root(&self, local_ca_key_pem: &str) -> Option<&CredentialRoot> {
+        if local_ca_key_pem == self.previous.ca_key_pem {
+            Some(&self.next)
+        } else if local_ca_key_pem == self.next.ca_key_pem {
+            Some(&self.previous)
+        } else {
+            None
+        }
+    }
+
+    /// Nodes (of `node_ids`) that have not reached `stage` yet.
+    pub fn nodes_before<'a>(
+        &self,
+        node_ids: impl IntoIterator<Item = &'a String>,
+        stage: CredentialRotationNodeStage,
+    ) -> Vec<String> {
+        node_ids
+            .into_iter()
+            .filter(|node_id| self.node_stage(node_id) < stage)
+            .cloned()
+            .collect()
+    }
+
+    /// Earliest time the old root may be retired: the grace period after promotion or after the
+    /// last node switched, whichever is later. `None` until every node in `node_ids` switched.
+    pub fn retire_not_before<'a>(
+        &self,
+        node_ids: impl IntoIterator<Item = &'a String>,
+    ) -> Option<DateTime<Utc>> {
+        let mut last = parse_rfc3339(self.promoted_at.as_deref()?)?;
+        for node_id in node_ids {
+            let switched_at = self.nodes.get(node_id)?.switched_at.as_deref()?;
+            last = last.max(parse_rfc3339(switched_at)?);
+        }
+        Some(last + chrono::Duration::hours(i64::from(self.grace_period_hours)))
+    }
+
+    pub(crate) fn check_rotation_id(&self, rotation_id: &str) -> Result<(), String> {
+        if self.rotation_id == rotation_id {
+            Ok(())
+        } else {
+            Err(format!(
+                "rotation {rotation_id} is not the current rotation {}",
+                self.rotation_id
+            ))
+        }
+    }
+
+    pub(crate) fn record_progress(
+        &mut self,
+        node_id: &str,
+        stage: CredentialRotationNodeStage,
+        at: &str,
+    ) {
+        self.nodes
+            .entry(node_id.to_string())
+            .or_default()
+            .record(stage, at);
+    }
+
+    pub(crate) fn check_promote<'a>(
+        &self,
+        node_ids: impl IntoIterator<Item = &'a String>,
+    ) -> Result<(), String> {
+        if self.phase != CredentialRotationPhase::Staged {
+            return Err("rotation is already promoted".to_string());
+        }
+        let waiting = self.nodes_before(node_ids, CredentialRotationNodeStage::CertIssued);
+        if !waiting.is_empty() {
+            return Err(format!(
+                "nodes without a certificate from the new CA: {}",
+                waiting.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_retire<'a>(
+        &self,
+        node_ids: impl IntoIterator<Item = &'a String> + Clone,
+        now: &str,
+    ) -> Result<(), String> {
+        if self.phase != CredentialRotationPhase::Promoted {
+            return Err("rotation must be promoted before it is retired".to_string());
+        }
+        let waiting = self.nodes_before(node_ids.clone(), CredentialRotationNodeStage::Switched);
+        if !waiting.is_empty() {
+            return Err(format!(
+                "nodes still running on the old root: {}",
+                waiting.join(", ")
+            ));
+        }
+        let not_before = self
+            .retire_not_before(node_ids)
+            .ok_or_else(|| "rotation progress has invalid timestamps".to_string())?;
+        let now = parse_rfc3339(now).ok_or_else(|| format!("invalid retire time: {now}"))?;
+        if now < not_before {
+            return Err(format!("grace period runs until {}", rfc3339(not_before)));
+        }
+        Ok(())
+    }
+}
+
+fn parse_rfc3339(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+pub(crate) fn rfc3339(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The new CA and node certificate a node holds for a rotation until it is promoted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StagedFiles {
+    rotation_id: String,
+    node_cert_pem: String,
+    node_csr_pem: String,
+    node_key_pem: String,
+}
+
+impl StagedFiles {
+    fn issue(
+        rotation: &CredentialRotation,
+        cluster_id: &str,
+        node_id: &str,
+    ) -> Result<Self, CertError> {
+        let csr = generate_node_keypair_and_csr(node_id)?;
+        let node_cert_pem = sign_node_csr(cluster_id, &rotation.next.ca_key_pem, &csr.csr_pem)?;
+        Ok(Self {
+            rotation_id: rotation.rotation_id.clone(),
+            node_cert_pem,
+            node_csr_pem: csr.csr_pem,
+            node_key_pem: csr.key_pem,
+        })
+    }
+
+    fn load(dir: &Path) -> Option<Self> {
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+        Some(Self {
+            rotation_id: read(STAGED_ROTATION_ID_FILE)?.trim().to_string(),
+            node_cert_pem: read("node_cert.pem")?,
+            node_csr_pem: read("node_csr.pem")?,
+            node_key_pem: read("node_key.pem")?,
+        })
+    }
+
+    fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        write_atomic_private(&dir.join("node_key.pem"), self.node_key_pem.as_bytes())?;
+        write_atomic(&dir.join("node_csr.pem"), self.node_csr_pem.as_bytes())?;
+        write_atomic(&dir.join("node_cert.pem"), self.node_cert_pem.as_bytes())?;
+        // Written last: a directory with a matching id is complete.
+        write_atomic(
+            &dir.join(STAGED_ROTATION_ID_FILE),
+            self.rotation_id.as_bytes(),
+        )
+    }
+
+    /// Replaces the node's CA and certificate with the staged ones; they take effect on restart.
+    fn install(&self, rotation: &CredentialRotation, paths: &ClusterPaths) -> io::Result<()> {
+        write_atomic_private(&paths.node_key_pem, self.node_key_pem.as_bytes())?;
+        write_atomic(&paths.node_csr_pem, self.node_csr_pem.as_bytes())?;
+        write_atomic(&paths.node_cert_pem, self.node_cert_pem.as_bytes())?;
+        write_atomic_private(
+            &paths.cluster_ca_key_pem,
+            rotation.next.ca_key_pem.as_bytes(),
+        )?;
+        write_atomic(&paths.cluster_ca_pem, rotation.next.ca_cert_pem.as_bytes())
+    }
+}
+
+/// Node-local side of a rotation: keeps internal auth accepting the other root, issues and
+/// installs this node's new certificate, and reports progress through Raft.
+pub struct CredentialRotationWorker {
+    pub data_dir: std::path::PathBuf,
+    pub cluster_id: String,
+    pub node_id: String,
+    /// The CA key this process was started with.
+    pub cluster_ca_key_pem: String,
+    /// Shared with the internal auth verifiers of this process.
+    pub alternate_roots: internal_auth::AlternateRoots,
+    pub store: Arc<Mutex<JsonSnapshotStore>>,
+    pub raft: Arc<dyn RaftFacade>,
+}
+
+pub fn spawn_credential_rotation_worker(
+    worker: CredentialRotationWorker,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(WORKER_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            if let Err(error) = worker.tick().await {
+                warn!(%error, "credential rotation step failed");
+            }
+        }
+    })
+}
+
+impl CredentialRotationWorker {
+    async fn tick(&self) -> anyhow::Result<()> {
+        let rotation = self.store.lock().await.state().credential_rotation.clone();
+        self.alternate_roots.set(
+            rotation
+                .iter()
+                .filter_map(|rotation| rotation.alternate_root(&self.cluster_ca_key_pem))
+                .map(|root| internal_auth::AlternateRoot {
+                    ca_key_pem: root.ca_key_pem.clone(),
+                    ca_cert_pem: root.ca_cert_pem.clone(),
+                })
+                .collect(),
+        );
+
+        let paths = ClusterPaths::new(&self.data_dir);
+        let staged_dir = &paths.credential_rotation_dir;
+        let Some(rotation) = rotation else {
+            if staged_dir.exists() {
+                fs::remove_dir_all(staged_dir)?;
+            }
+            return Ok(());
+        };
+
+        let stage = rotation.node_stage(&self.node_id);
+        if self.cluster_ca_key_pem == rotation.next.ca_key_pem {
+            if stage < CredentialRotationNodeStage::Switched {
+                self.report(&rotation, CredentialRotationNodeStage::Switched)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let staged = match StagedFiles::load(staged_dir)
+            .filter(|staged| staged.rotation_id == rotation.rotation_id)
+        {
+            Some(staged) => staged,
+            None => {
+                if staged_dir.exists() {
+                    fs::remove_dir_all(staged_dir)?;
+                }
+                let staged = StagedFiles::issue(&rotation, &self.cluster_id, &self.node_id)?;
+                staged.save(staged_dir)?;
+                info!(
+                    rotation_id = rotation.rotation_id,
+                    "issued node certificate under the new cluster CA"
+                );
+                staged
+            }
+        };
+        if stage < CredentialRotationNodeStage::CertIssued {
+            self.report(&rotation, CredentialRotationNodeStage::CertIssued)
+                .await?;
+        }
+
+        if rotation.phase == CredentialRotationPhase::Promoted {
+            let installed = fs::read_to_string(&paths.cluster_ca_key_pem)
+                .is_ok_and(|key| key == rotation.next.ca_key_pem);
+            if !installed {
+                staged.install(&rotation, &paths)?;
+            }
+            if stage < CredentialRotationNodeStage::RestartRequired {
+                self.report(&rotation, CredentialRotationNodeStage::RestartRequired)
+                    .await?;
+            }
+            warn!(
+                rotation_id = rotation.rotation_id,
+                "new cluster CA installed; restart xp to finish the credential rotation"
+            );
+        }
+        Ok(())
+    }
+
+    async fn report(
+        &self,
+        rotation: &CredentialRotation,
+        stage: CredentialRotationNodeStage,
+    ) -> anyhow::Result<()> {
+        let command = DesiredStateCommand::RecordCredentialRotationProgress {
+            rotation_id: rotation.rotation_id.clone(),
+            node_id: self.node_id.clone(),
+            stage,
+            at: rfc3339(Utc::now()),
+        };
+        match self.raft.client_write(command).await? {
+            ClientResponse::Ok { .. } | ClientResponse::Err { status: 409, .. } => Ok(()),
+            ClientResponse::Err {
+                status,
+                code,
+                message,
+            } => Err(anyhow::anyhow!("{status} {code}: {message}")),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+use crate::{
+    cluster_identity::generate_cluster_ca,
+    domain::{DomainError, Node, NodeQuotaReset},
+    state::{PersistedState, StoreError},
+};
+
+const CLUSTER_ID: &str = "01JTESTCLUSTERID00000000000000";
+
+fn root() -> CredentialRoot {
+    let ca = generate_cluster_ca(CLUSTER_ID).expect("generate ca");
+    CredentialRoot {
+        ca_cert_pem: ca.cert_pem,
+        ca_key_pem: ca.key_pem,
+    }
+}
+
+fn cluster_state(node_ids: &[&str]) -> PersistedState {
+    let mut state = PersistedState::empty();
+    for node_id in node_ids {
+        state.nodes.insert(
+            node_id.to_string(),
+            Node {
+                node_id: node_id.to_string(),
+                node_name: node_id.to_string(),
+                access_host: format!("{node_id}.example.com"),
+                api_base_url: format!("https://{node_id}.example.com"),
+                quota_limit_bytes: 0,
+                quota_reset: NodeQuotaReset::default(),
+            },
+        );
+    }
+    state
+}
+
+fn progress(
+    rotation_id: &str,
+    node_id: &str,
+    stage: CredentialRotationNodeStage,
+    at: &str,
+) -> DesiredStateCommand {
+    DesiredStateCommand::RecordCredentialRotationProgress {
+        rotation_id: rotation_id.to_string(),
+        node_id: node_id.to_string(),
+        stage,
+        at: at.to_string(),
+    }
+}
+
+fn conflict_reason(err: StoreError) -> String {
+    match err {
+        StoreError::Domain(DomainError::CredentialRotationConflict { reason }) => reason,
+        other => panic!("expected a credential rotation conflict, got {other:?}"),
+    }
+}
+
+#[test]
+fn rotation_moves_through_promote_and_retire_gates() {
+    let mut state = cluster_state(&["n1", "n2"]);
+    let rotation =
+        CredentialRotation::start(CLUSTER_ID, root(), 24, "2026-10-01T00:00:00Z".to_string())
+            .unwrap();
+    let rotation_id = rotation.rotation_id.clone();
+    let start = DesiredStateCommand::StartCredentialRotation {
+        rotation: Box::new(rotation),
+    };
+    start.apply(&mut state).unwrap();
+    assert!(conflict_reason(start.apply(&mut state).unwrap_err()).contains("already in progress"));
+
+    let promote = |at: &str| DesiredStateCommand::PromoteCredentialRotation {
+        rotation_id: rotation_id.clone(),
+        promoted_at: at.to_string(),
+    };
+    let retire = |at: &str| DesiredStateCommand::RetireCredentialRotation {
+        rotation_id: rotation_id.clone(),
+        retired_at: at.to_string(),
+    };
+
+    progress(
+        &rotation_id,
+        "n1",
+        CredentialRotationNodeStage::CertIssued,
+        "2026-10-01T00:01:00Z",
+    )
+    .apply(&mut state)
+    .unwrap();
+    assert_eq!(
+        conflict_reason(
+            promote("2026-10-01T00:02:00Z")
+                .apply(&mut state)
+                .unwrap_err()
+        ),
+        "nodes without a certificate from the new CA: n2"
+    );
+    assert!(
+        conflict_reason(
+            retire("2026-10-01T00:02:00Z")
+                .apply(&mut state)
+                .unwrap_err()
+        )
+        .contains("must be promoted")
+    );
+
+    progress(
+        &rotation_id,
+        "n2",
+        CredentialRotationNodeStage::CertIssued,
+        "2026-10-01T00:01:30Z",
+    )
+    .apply(&mut state)
+    .unwrap();
+    promote("2026-10-01T00:02:00Z").apply(&mut state).unwrap();
+    assert!(
+        conflict_reason(
+            DesiredStateCommand::AbortCredentialRotation {
+                rotation_id: rotation_id.clone(),
+            }
+            .apply(&mut state)
+            .unwrap_err()
+        )
+        .contains("cannot be aborted")
+    );
+
+    for (node_id, at) in [
+        ("n1", "2026-10-01T01:00:00Z"),
+        ("n2", "2026-10-01T02:00:00Z"),
+    ] {
+        progress(
+            &rotation_id,
+            node_id,
+            CredentialRotationNodeStage::Switched,
+            at,
+        )
+        .apply(&mut state)
+        .unwrap();
+    }
+    // A repeated report keeps the first timestamp.
+    progress(
+        &rotation_id,
+        "n2",
+        CredentialRotationNodeStage::Switched,
+        "2026-10-05T00:00:00Z",
+    )
+    .apply(&mut state)
+    .unwrap();
+    let current = state.credential_rotation.as_ref().unwrap();
+    assert_eq!(
+        current.node_stage("n2"),
+        CredentialRotationNodeStage::Switched
+    );
+    assert_eq!(
+        current
+            .retire_not_before(state.nodes.keys())
+            .map(rfc3339)
+            .as_deref(),
+        Some("2026-10-02T02:00:00Z")
+    );
+
+    assert_eq!(
+        conflict_reason(
+            retire("2026-10-02T01:59:59Z")
+                .apply(&mut state)
+                .unwrap_err()
+        ),
+        "grace period runs until 2026-10-02T02:00:00Z"
+    );
+    retire("2026-10-02T02:00:00Z").apply(&mut state).unwrap();
+    assert_eq!(state.credential_rotation, None);
+    assert!(matches!(
+        retire("2026-10-02T02:00:00Z").apply(&mut state),
+        Err(StoreError::Domain(
+            DomainError::CredentialRotationNotFound { .. }
+        ))
+    ));
+}
+
+#[test]
+fn staged_rotation_can_be_aborted_and_rejects_foreign_ids() {
+    let mut state = cluster_state(&["n1"]);
+    let rotation =
+        CredentialRotation::start(CLUSTER_ID, root(), 0, "2026-10-01T00:00:00Z".to_string())
+            .unwrap();
+    let rotation_id = rotation.rotation_id.clone();
+    DesiredStateCommand::StartCredentialRotation {
+        rotation: Box::new(rotation),
+    }
+    .apply(&mut state)
+    .unwrap();
+
+    let stale = progress(
+        "01JSTALEROTATION0000000000000",
+        "n1",
+        CredentialRotationNodeStage::CertIssued,
+        "2026-10-01T00:01:00Z",
+    );
+    assert!(conflict_reason(stale.apply(&mut state).unwrap_err()).contains("not the current"));
+
+    DesiredStateCommand::AbortCredentialRotation { rotation_id }
+        .apply(&mut state)
+        .unwrap();
+    assert_eq!(state.credential_rotation, None);
+}
+
+#[test]
+fn alternate_root_is_the_other_side_of_the_rotation() {
+    let previous = root();
+    let rotation = CredentialRotation::start(
+        CLUSTER_ID,
+        previous.clone(),
+        24,
+        "2026-10-01T00:00:00Z".to_string(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        rotation.alternate_root(&previous.ca_key_pem),
+        Some(&rotation.next)
+    );
+    assert_eq!(
+        rotation.alternate_root(&rotation.next.ca_key_pem),
+        Some(&rotation.previous)
+    );
+    assert_eq!(rotation.alternate_root(&root().ca_key_pem), None);
+    assert_ne!(rotation.previous.fingerprint(), rotation.next.fingerprint());
+}
+
+#[test]
+fn staged_files_round_trip_and_install_the_new_ca() {
+    let tmp = tempfile::tempdir().unwrap();
+    let paths = ClusterPaths::new(tmp.path());
+    let rotation =
+        CredentialRotation::start(CLUSTER_ID, root(), 24, "2026-10-01T00:00:00Z".to_string())
+            .unwrap();
+
+    let staged =
+        StagedFiles::issue(&rotation, CLUSTER_ID, "01JTESTNODE000000000000000000").unwrap();
+    staged.save(&paths.credential_rotation_dir).unwrap();
+    assert_eq!(
+        StagedFiles::load(&paths.credential_rotation_dir),
+        Some(staged.clone())
+    );
+
+    fs::create_dir_all(&paths.dir).unwrap();
+    staged.install(&rotation, &paths).unwrap();
+    assert_eq!(
+        fs::read_to_string(&paths.cluster_ca_key_pem).unwrap(),
+        rotation.next.ca_key_pem
+    );
+    assert_eq!(
+        fs::read_to_string(&paths.node_cert_pem).unwrap(),
+        staged.node_cert_pem
+    );
+    let ca = openssl::x509::X509::from_pem(rotation.next.ca_cert_pem.as_bytes()).unwrap();
+    let cert = openssl::x509::X509::from_pem(staged.node_cert_pem.as_bytes()).unwrap();
+    assert!(cert.verify(&ca.public_key().unwrap()).unwrap());
+}
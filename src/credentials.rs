@@ -3,17 +3,23 @@ use hmac::{Hmac, Mac as _};
 use sha2::Sha256;
 use uuid::Uuid;
 
+use crate::domain::User;
+
 type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Debug)]
 pub enum CredentialError {
     EmptySeed,
+    InvalidPskLength { len: usize },
 }
 
 impl std::fmt::Display for CredentialError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::EmptySeed => write!(f, "credential seed must be non-empty"),
+            Self::InvalidPskLength { len } => {
+                write!(f, "psk length must be between 1 and 32 bytes, got {len}")
+            }
         }
     }
 }
@@ -49,13 +55,75 @@ pub fn derive_vless_uuid(
     Ok(uuid_from_rfc4122_bytes(bytes16))
 }
 
+/// Derives the per-user SS2022 PSK. `psk_len_bytes` follows the endpoint method (16 for
+/// aes-128-gcm, 32 otherwise); the 16-byte output is a prefix of the 32-byte one, so existing
+/// aes-128-gcm users keep their keys.
 pub fn derive_ss2022_user_psk_b64(
     cluster_ca_key_pem: &str,
     user_id: &str,
     credential_epoch: u32,
+    psk_len_bytes: usize,
 ) -> Result<String, CredentialError> {
+    if psk_len_bytes == 0 || psk_len_bytes > 32 {
+        return Err(CredentialError::InvalidPskLength { len: psk_len_bytes });
+    }
     let msg = format!("xp:v1:cred:ss2022-user-psk:{user_id}:{credential_epoch}");
     let digest = hmac_sha256(cluster_ca_key_pem, &msg)?;
-    let bytes16: [u8; 16] = digest[0..16].try_into().expect("slice len is 16");
-    Ok(base64::engine::general_purpose::STANDARD.encode(bytes16))
+    Ok(base64::engine::general_purpose::STANDARD.encode(&digest[..psk_len_bytes]))
+}
+
+pub fn derive_trojan_password(
+    cluster_ca_key_pem: &str,
+    user_id: &str,
+    credential_epoch: u32,
+) -> Result<String, CredentialError> {
+    let msg = format!("xp:v1:cred:trojan-password:{user_id}:{credential_epoch}");
+    let digest = hmac_sha256(cluster_ca_key_pem, &msg)?;
+    // Hex keeps the password URI-safe for `trojan://` share links without extra encoding.
+    Ok(hex::encode(&digest[0..16]))
+}
+
+pub fn derive_hysteria2_auth(
+    cluster_ca_key_pem: &str,
+    user_id: &str,
+    credential_epoch: u32,
+) -> Result<String, CredentialError> {
+    let msg = format!("xp:v1:cred:hysteria2-auth:{user_id}:{credential_epoch}");
+    let digest = hmac_sha256(cluster_ca_key_pem, &msg)?;
+    Ok(hex::encode(&digest[0..16]))
+}
+
+/// The user's VLESS UUID: the pinned one if set, otherwise derived.
+pub fn user_vless_uuid(cluster_ca_key_pem: &str, user: &User) -> Result<String, CredentialError> {
+    match user.pinned_credentials.vless_uuid.as_deref() {
+        Some(uuid) => Ok(uuid.to_string()),
+        None => derive_vless_uuid(cluster_ca_key_pem, &user.user_id, user.credential_epoch),
+    }
+}
+
+/// The user's SS2022 PSK for a method taking `psk_len_bytes`: the pinned one if it has that
+/// length, otherwise derived.
+pub fn user_ss2022_psk_b64(
+    cluster_ca_key_pem: &str,
+    user: &User,
+    psk_len_bytes: usize,
+) -> Result<String, CredentialError> {
+    let pinned = user
+        .pinned_credentials
+        .ss2022_psk_b64
+        .as_deref()
+        .filter(|psk| {
+            base64::engine::general_purpose::STANDARD
+                .decode(psk)
+                .is_ok_and(|bytes| bytes.len() == psk_len_bytes)
+        });
+    match pinned {
+        Some(psk) => Ok(psk.to_string()),
+        None => derive_ss2022_user_psk_b64(
+            cluster_ca_key_pem,
+            &user.user_id,
+            user.credential_epoch,
+            psk_len_bytes,
+        ),
+    }
 }
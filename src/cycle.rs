@@ -1,5 +1,6 @@
 use chrono::{
     DateTime, Datelike, Duration, FixedOffset, Local, LocalResult, NaiveDate, TimeZone, Utc,
+    Weekday,
 };
 
 #[derive(Debug)]
@@ -78,7 +79,7 @@ fn next_year_month(year: i32, month: u32) -> (i32, u32) {
     }
 }
 
-fn cycle_window_at<Tz: TimeZone>(
+fn monthly_cycle_window_at<Tz: TimeZone>(
     tz: &Tz,
     now: DateTime<Tz>,
     day_of_month: u8,
@@ -104,17 +105,64 @@ where
     Ok((start, end))
 }
 
+/// Back-to-back windows of `length_days` local days, one of which starts on `anchor`.
+fn fixed_length_cycle_window_at<Tz: TimeZone>(
+    tz: &Tz,
+    now: DateTime<Tz>,
+    anchor: NaiveDate,
+    length_days: u16,
+) -> Result<(DateTime<Tz>, DateTime<Tz>), CycleWindowError>
+where
+    Tz::Offset: Copy,
+{
+    let length = i64::from(length_days.max(1));
+    let elapsed = (now.date_naive() - anchor).num_days();
+    let mut start_date = anchor + Duration::days(elapsed.div_euclid(length) * length);
+    let mut start = at_start_of_day(tz, start_date)?;
+    if now < start {
+        // Only reachable when local midnight does not exist and the day starts at 01:00.
+        start_date -= Duration::days(length);
+        start = at_start_of_day(tz, start_date)?;
+    }
+    let end = at_start_of_day(tz, start_date + Duration::days(length))?;
+    Ok((start, end))
+}
+
+fn cycle_window_at<Tz: TimeZone>(
+    tz: &Tz,
+    now: DateTime<Tz>,
+    schedule: CycleSchedule,
+) -> Result<(DateTime<Tz>, DateTime<Tz>), CycleWindowError>
+where
+    Tz::Offset: Copy,
+{
+    match schedule {
+        CycleSchedule::Monthly { day_of_month } => monthly_cycle_window_at(tz, now, day_of_month),
+        CycleSchedule::Weekly { weekday } => {
+            // 1970-01-05 is a Monday.
+            let monday = NaiveDate::from_ymd_opt(1970, 1, 5).expect("valid reference monday");
+            let anchor = monday + Duration::days(i64::from(weekday.num_days_from_monday()));
+            fixed_length_cycle_window_at(tz, now, anchor, 7)
+        }
+        CycleSchedule::Daily => fixed_length_cycle_window_at(tz, now, now.date_naive(), 1),
+        CycleSchedule::FixedDays {
+            anchor,
+            length_days,
+        } => fixed_length_cycle_window_at(tz, now, anchor, length_days),
+    }
+}
+
 pub fn current_cycle_window_now(
     tz: CycleTimeZone,
-    day_of_month: u8,
+    schedule: CycleSchedule,
 ) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), CycleWindowError> {
     let now = Utc::now();
-    current_cycle_window_at(tz, day_of_month, now)
+    current_cycle_window_at(tz, schedule, now)
 }
 
 pub fn current_cycle_window_at(
     tz: CycleTimeZone,
-    day_of_month: u8,
+    schedule: CycleSchedule,
     now_utc: DateTime<Utc>,
 ) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), CycleWindowError> {
     match tz {
@@ -122,12 +170,12 @@ pub fn current_cycle_window_at(
             let tz = FixedOffset::east_opt(i32::from(tz_offset_minutes) * 60)
                 .ok_or(CycleWindowError::InvalidTzOffsetMinutes)?;
             let now = now_utc.with_timezone(&tz);
-            let (start, end) = cycle_window_at(&tz, now, day_of_month)?;
+            let (start, end) = cycle_window_at(&tz, now, schedule)?;
             Ok((start, end))
         }
         CycleTimeZone::Local => {
             let now = now_utc.with_timezone(&Local);
-            let (start, end) = cycle_window_at(&Local, now, day_of_month)?;
+            let (start, end) = cycle_window_at(&Local, now, schedule)?;
             Ok((
                 start.with_timezone(start.offset()),
                 end.with_timezone(end.offset()),
@@ -136,6 +184,24 @@ pub fn current_cycle_window_at(
     }
 }
 
+/// When quota cycles roll over. Every schedule starts its windows at local midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleSchedule {
+    /// On `day_of_month`, or the last day of shorter months.
+    Monthly {
+        day_of_month: u8,
+    },
+    Weekly {
+        weekday: Weekday,
+    },
+    Daily,
+    /// Every `length_days` days counted from `anchor`, e.g. 30-day plans.
+    FixedDays {
+        anchor: NaiveDate,
+        length_days: u16,
+    },
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CycleTimeZone {
     FixedOffsetMinutes { tz_offset_minutes: i16 },
@@ -169,4 +235,70 @@ mod tests {
             NaiveDate::from_ymd_opt(2025, 4, 30).unwrap()
         );
     }
+
+    fn window(schedule: CycleSchedule, now: &str) -> (String, String) {
+        let now = DateTime::parse_from_rfc3339(now)
+            .unwrap()
+            .with_timezone(&Utc);
+        let tz = CycleTimeZone::FixedOffsetMinutes {
+            tz_offset_minutes: 480,
+        };
+        let (start, end) = current_cycle_window_at(tz, schedule, now).unwrap();
+        (start.to_rfc3339(), end.to_rfc3339())
+    }
+
+    #[test]
+    fn weekly_window_starts_on_the_configured_weekday() {
+        // 2026-10-17 is a Saturday; 16:30Z is already Sunday 00:30 at UTC+8.
+        let weekly = CycleSchedule::Weekly {
+            weekday: Weekday::Mon,
+        };
+        assert_eq!(
+            window(weekly, "2026-10-17T16:30:00Z"),
+            (
+                "2026-10-12T00:00:00+08:00".to_string(),
+                "2026-10-19T00:00:00+08:00".to_string()
+            )
+        );
+        let sunday = CycleSchedule::Weekly {
+            weekday: Weekday::Sun,
+        };
+        assert_eq!(
+            window(sunday, "2026-10-17T16:30:00Z").0,
+            "2026-10-18T00:00:00+08:00"
+        );
+    }
+
+    #[test]
+    fn daily_window_covers_the_local_day() {
+        assert_eq!(
+            window(CycleSchedule::Daily, "2026-10-17T15:59:59Z"),
+            (
+                "2026-10-17T00:00:00+08:00".to_string(),
+                "2026-10-18T00:00:00+08:00".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn fixed_days_windows_repeat_from_the_anchor_in_both_directions() {
+        let thirty_days = CycleSchedule::FixedDays {
+            anchor: NaiveDate::from_ymd_opt(2026, 9, 1).unwrap(),
+            length_days: 30,
+        };
+        assert_eq!(
+            window(thirty_days, "2026-10-17T00:00:00Z"),
+            (
+                "2026-10-01T00:00:00+08:00".to_string(),
+                "2026-10-31T00:00:00+08:00".to_string()
+            )
+        );
+        assert_eq!(
+            window(thirty_days, "2026-08-20T00:00:00Z"),
+            (
+                "2026-08-02T00:00:00+08:00".to_string(),
+                "2026-09-01T00:00:00+08:00".to_string()
+            )
+        );
+    }
 }
@@ -1,17 +1,24 @@
-use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+use std::{fs, net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{info, warn};
 
 use crate::{
     cloudflared_supervisor::{CloudflaredHealthHandle, CloudflaredStatus},
-    config::Config,
-    ops::cloudflare::{self, CloudflareClient, DnsRecordInfo},
+    config::{Config, DdnsProviderKind},
     public_ip_probe::{PublicIpAddressFamily, PublicIpProbeOutcome, probe_public_ip},
+    state::JsonSnapshotStore,
 };
 
+use provider::{DdnsProvider, DdnsRecord, DdnsTarget};
+
+mod cloudflare;
+mod http_template;
+mod provider;
+mod rfc2136;
+
 pub use crate::public_ip_probe::DEFAULT_TRACE_URL;
 
 const DDNS_SCHEMA_VERSION: u32 = 1;
@@ -85,6 +92,13 @@ enum AddressFamily {
 }
 
 impl AddressFamily {
+    fn of(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(_) => Self::Ipv4,
+            IpAddr::V6(_) => Self::Ipv6,
+        }
+    }
+
     fn record_type(self) -> &'static str {
         match self {
             Self::Ipv4 => "A",
@@ -118,6 +132,10 @@ struct PersistedFamilyState {
 struct PersistedDdnsState {
     schema_version: u32,
     hostname: String,
+    /// Provider that wrote this state; absent in files written before providers were pluggable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    provider: Option<String>,
+    /// Provider zone: a Cloudflare zone id or an RFC 2136 zone name.
     zone_id: Option<String>,
     snapshot: PersistedSnapshot,
     ipv4: PersistedFamilyState,
@@ -142,6 +160,7 @@ struct PersistedSnapshot {
 #[derive(Debug, Clone)]
 struct DdnsState {
     hostname: String,
+    provider: DdnsProviderKind,
     zone_id: Option<String>,
     snapshot: DdnsStatusSnapshot,
     ipv4: PersistedFamilyState,
@@ -149,9 +168,10 @@ struct DdnsState {
 }
 
 impl DdnsState {
-    fn new(hostname: String, enabled: bool) -> Self {
+    fn new(hostname: String, provider: DdnsProviderKind, enabled: bool) -> Self {
         Self {
             hostname,
+            provider,
             zone_id: None,
             snapshot: if enabled {
                 DdnsStatusSnapshot::unknown()
@@ -163,19 +183,28 @@ impl DdnsState {
         }
     }
 
-    fn load(path: &PathBuf, hostname: &str, enabled: bool) -> Self {
+    fn load(path: &PathBuf, hostname: &str, provider: DdnsProviderKind, enabled: bool) -> Self {
         let Ok(raw) = fs::read(path) else {
-            return Self::new(hostname.to_string(), enabled);
+            return Self::new(hostname.to_string(), provider, enabled);
         };
         let Ok(parsed) = serde_json::from_slice::<PersistedDdnsState>(&raw) else {
-            return Self::new(hostname.to_string(), enabled);
+            return Self::new(hostname.to_string(), provider, enabled);
         };
-        if parsed.schema_version != DDNS_SCHEMA_VERSION || parsed.hostname != hostname {
-            return Self::new(hostname.to_string(), enabled);
+        let parsed_provider = parsed
+            .provider
+            .as_deref()
+            .unwrap_or(DdnsProviderKind::Cloudflare.as_str());
+        // Record ids and zones only mean something to the provider that stored them.
+        if parsed.schema_version != DDNS_SCHEMA_VERSION
+            || parsed.hostname != hostname
+            || parsed_provider != provider.as_str()
+        {
+            return Self::new(hostname.to_string(), provider, enabled);
         }
 
         let mut state = Self {
             hostname: parsed.hostname,
+            provider,
             zone_id: parsed.zone_id,
             snapshot: DdnsStatusSnapshot {
                 status: parse_status(parsed.snapshot.status.as_str(), enabled),
@@ -203,6 +232,7 @@ impl DdnsState {
         PersistedDdnsState {
             schema_version: DDNS_SCHEMA_VERSION,
             hostname: self.hostname.clone(),
+            provider: Some(self.provider.as_str().to_string()),
             zone_id: self.zone_id.clone(),
             snapshot: PersistedSnapshot {
                 status: self.snapshot.status.as_str().to_string(),
@@ -258,8 +288,13 @@ pub struct DdnsHealthHandle {
 }
 
 impl DdnsHealthHandle {
-    fn new(persistence_path: PathBuf, hostname: String, enabled: bool) -> Self {
-        let state = DdnsState::load(&persistence_path, &hostname, enabled);
+    fn new(
+        persistence_path: PathBuf,
+        hostname: String,
+        provider: DdnsProviderKind,
+        enabled: bool,
+    ) -> Self {
+        let state = DdnsState::load(&persistence_path, &hostname, provider, enabled);
         Self {
             inner: Arc::new(RwLock::new(state)),
             persistence_path: Arc::new(persistence_path),
@@ -267,7 +302,11 @@ impl DdnsHealthHandle {
     }
 
     pub fn new_with_status(status: DdnsStatus) -> Self {
-        let mut state = DdnsState::new(String::new(), status != DdnsStatus::Disabled);
+        let mut state = DdnsState::new(
+            String::new(),
+            DdnsProviderKind::default(),
+            status != DdnsStatus::Disabled,
+        );
         state.snapshot.status = status;
         Self {
             inner: Arc::new(RwLock::new(state)),
@@ -298,11 +337,13 @@ impl DdnsHealthHandle {
 pub fn spawn_ddns_supervisor(
     config: Arc<Config>,
     cloudflared_health: CloudflaredHealthHandle,
+    store: Arc<Mutex<JsonSnapshotStore>>,
 ) -> (DdnsHealthHandle, tokio::task::JoinHandle<()>) {
     let enabled = config.cloudflare_ddns_enabled;
     let handle = DdnsHealthHandle::new(
         config.data_dir.join("ddns_state.json"),
         config.access_host.clone(),
+        config.ddns.provider,
         enabled,
     );
 
@@ -333,7 +374,7 @@ pub fn spawn_ddns_supervisor(
                 previous_cloudflared_status = Some(snapshot.status);
             }
 
-            reconcile_once(&config, &handle_clone).await;
+            reconcile_once(&config, &store, &handle_clone).await;
             handle_clone.persist().await;
 
             let sleep_for = next_interval(&config, &handle_clone, cloudflared_monitored).await;
@@ -361,11 +402,15 @@ async fn next_interval(
     }
 }
 
-async fn reconcile_once(config: &Config, handle: &DdnsHealthHandle) {
+async fn reconcile_once(
+    config: &Config,
+    store: &Mutex<JsonSnapshotStore>,
+    handle: &DdnsHealthHandle,
+) {
     let now = Utc::now();
 
-    let token = match load_token(&config.cloudflare_ddns_token_file) {
-        Ok(token) => token,
+    let provider = match provider::build_provider(config) {
+        Ok(provider) => provider,
         Err(message) => {
             apply_fatal_error(handle, now, message).await;
             return;
@@ -386,56 +431,77 @@ async fn reconcile_once(config: &Config, handle: &DdnsHealthHandle) {
         .await;
         return;
     }
+    if failover_owns_hostname(store, &hostname).await {
+        let probes = probe_public_ips(config).await;
+        let mut state = handle.inner.write().await;
+        state.hostname = hostname;
+        state.provider = provider.kind();
+        apply_failover_owned_round(&mut state, now, probes);
+        return;
+    }
 
-    let client = CloudflareClient::new(cloudflare::cloudflare_api_base(), token);
-    let zone_id = match resolve_zone_id(config, handle, &client, &hostname).await {
-        Ok(zone_id) => zone_id,
+    let (cached_zone, ipv4_synced_ip, ipv6_synced_ip) = {
+        let state = handle.inner.read().await;
+        (
+            state.zone_id.clone(),
+            state.ipv4.synced_ip.clone(),
+            state.ipv6.synced_ip.clone(),
+        )
+    };
+    let zone = match provider
+        .resolve_zone(&hostname, cached_zone.as_deref())
+        .await
+    {
+        Ok(zone) => zone,
         Err(message) => {
             apply_fatal_error(handle, now, message).await;
             return;
         }
     };
+    let target = DdnsTarget {
+        hostname: hostname.clone(),
+        zone,
+    };
 
-    let ipv4_record = match client
-        .list_dns_records_by_type(&zone_id, &hostname, "A")
+    let ipv4_record = match provider
+        .current_record(&target, AddressFamily::Ipv4, ipv4_synced_ip.as_deref())
         .await
     {
-        Ok(records) => records,
-        Err(err) => {
-            apply_fatal_error(handle, now, format!("ddns list A records: {err}")).await;
+        Ok(record) => record,
+        Err(message) => {
+            apply_fatal_error(handle, now, message).await;
             return;
         }
     };
-    if ipv4_record.len() > 1 {
-        apply_fatal_error(
-            handle,
-            now,
-            format!("ddns found multiple A records for {hostname}; refusing automatic changes"),
-        )
-        .await;
-        return;
-    }
-
-    let ipv6_record = match client
-        .list_dns_records_by_type(&zone_id, &hostname, "AAAA")
+    let ipv6_record = match provider
+        .current_record(&target, AddressFamily::Ipv6, ipv6_synced_ip.as_deref())
         .await
     {
-        Ok(records) => records,
-        Err(err) => {
-            apply_fatal_error(handle, now, format!("ddns list AAAA records: {err}")).await;
+        Ok(record) => record,
+        Err(message) => {
+            apply_fatal_error(handle, now, message).await;
             return;
         }
     };
-    if ipv6_record.len() > 1 {
-        apply_fatal_error(
-            handle,
-            now,
-            format!("ddns found multiple AAAA records for {hostname}; refusing automatic changes"),
-        )
-        .await;
-        return;
-    }
 
+    let [ipv4_probe, ipv6_probe] = probe_public_ips(config).await;
+
+    let mut state = handle.inner.write().await;
+    state.hostname = hostname;
+    state.provider = provider.kind();
+    state.zone_id = target.zone.clone();
+    apply_round(
+        &mut state,
+        now,
+        provider.as_ref(),
+        &target,
+        [(ipv4_probe, ipv4_record), (ipv6_probe, ipv6_record)],
+        config.cloudflare_ddns_family_missing_grace,
+    )
+    .await;
+}
+
+async fn probe_public_ips(config: &Config) -> [PublicIpProbeOutcome; 2] {
     let ipv4_probe = probe_public_ip(
         &config.cloudflare_ddns_ipv4_url,
         AddressFamily::Ipv4.probe_family(),
@@ -446,55 +512,133 @@ async fn reconcile_once(config: &Config, handle: &DdnsHealthHandle) {
         AddressFamily::Ipv6.probe_family(),
     )
     .await;
+    [ipv4_probe, ipv6_probe]
+}
 
-    let mut unknown_messages = Vec::new();
-    let mut updates_succeeded = false;
-    let no_public_ip;
+/// Whether a DNS failover group owns `hostname`, in which case the Raft leader publishes it.
+async fn failover_owns_hostname(store: &Mutex<JsonSnapshotStore>, hostname: &str) -> bool {
+    let store = store.lock().await;
+    store
+        .state()
+        .dns_failover_groups
+        .values()
+        .any(|group| group.hostname == hostname)
+}
 
+/// Records the probed public IPs of a node whose hostname a failover group owns, without
+/// touching the record. The IPs are still reported as current: the leader reads the primary's
+/// addresses from them. Nothing is remembered about the record, which the leader may move at any
+/// time, so a later regular round writes it again.
+fn apply_failover_owned_round(
+    state: &mut DdnsState,
+    now: DateTime<Utc>,
+    probes: [PublicIpProbeOutcome; 2],
+) {
+    state.clear_expired_fast_mode(now);
+    let mut unknown_messages = Vec::new();
+    for (family, probe) in [AddressFamily::Ipv4, AddressFamily::Ipv6]
+        .into_iter()
+        .zip(probes)
     {
-        let mut state = handle.inner.write().await;
-        state.hostname = hostname.clone();
-        state.zone_id = Some(zone_id.clone());
-        state.clear_expired_fast_mode(now);
-
-        let ipv4_synced = match apply_family_reconcile(
-            &mut state,
-            now,
-            &client,
-            &zone_id,
-            &hostname,
-            AddressFamily::Ipv4,
-            ipv4_probe,
-            ipv4_record.first().cloned(),
-            config.cloudflare_ddns_family_missing_grace,
-        )
-        .await
-        {
-            Ok(FamilyReconcileOutcome::Synced) => {
-                updates_succeeded = true;
-                true
+        *state.family_mut(family) = PersistedFamilyState::default();
+        let current = match probe {
+            PublicIpProbeOutcome::Available(ip) => Some(ip.to_string()),
+            PublicIpProbeOutcome::MissingCandidate(_reason) => None,
+            PublicIpProbeOutcome::Unknown(message) => {
+                unknown_messages.push(format!("ddns {} probe: {message}", family.label()));
+                continue;
             }
-            Ok(FamilyReconcileOutcome::MissingConfirmed) => {
-                updates_succeeded = true;
-                false
+        };
+        match family {
+            AddressFamily::Ipv4 => state.snapshot.current_ipv4 = current,
+            AddressFamily::Ipv6 => state.snapshot.current_ipv6 = current,
+        }
+    }
+    let no_public_ip = !state.any_synced_ip();
+    update_snapshot_after_round(state, now, unknown_messages, false, no_public_ip);
+}
+
+/// Points `hostname` at `addresses` (the first of each family; a family without one loses its
+/// record) through the configured provider. Unlike the node's own DDNS record nothing is kept
+/// between calls, so providers that cannot read records back are always sent an update.
+/// Returns whether any record was written.
+pub(crate) async fn publish_host_records(
+    config: &Config,
+    hostname: &str,
+    addresses: &[IpAddr],
+) -> Result<bool, String> {
+    let provider = provider::build_provider(config)?;
+    publish_records(provider.as_ref(), hostname, addresses).await
+}
+
+async fn publish_records(
+    provider: &dyn DdnsProvider,
+    hostname: &str,
+    addresses: &[IpAddr],
+) -> Result<bool, String> {
+    let hostname = hostname.trim().to_ascii_lowercase();
+    if !is_valid_hostname(&hostname) {
+        return Err(format!("ddns hostname is not a valid FQDN: {hostname}"));
+    }
+    let zone = provider.resolve_zone(&hostname, None).await?;
+    let target = DdnsTarget { hostname, zone };
+
+    let mut changed = false;
+    for family in [AddressFamily::Ipv4, AddressFamily::Ipv6] {
+        let wanted = addresses
+            .iter()
+            .copied()
+            .find(|ip| AddressFamily::of(*ip) == family);
+        let existing = provider.current_record(&target, family, None).await?;
+        match (wanted, existing) {
+            (Some(ip), existing) => {
+                let up_to_date = existing.as_ref().is_some_and(|record| {
+                    record.content == ip.to_string() && !record.stale_attributes
+                });
+                if !up_to_date {
+                    provider
+                        .upsert_record(&target, family, ip, existing.as_ref())
+                        .await?;
+                    changed = true;
+                }
             }
-            Ok(FamilyReconcileOutcome::PendingMissingGrace) => false,
-            Err(message) => {
-                unknown_messages.push(message);
-                state.snapshot.current_ipv4.is_some()
+            (None, Some(record)) => {
+                provider.delete_record(&target, family, &record).await?;
+                changed = true;
             }
-        };
+            (None, None) => {}
+        }
+    }
+    Ok(changed)
+}
 
-        let ipv6_synced = match apply_family_reconcile(
-            &mut state,
+/// Reconciles both address families against the probed IPs and updates the status snapshot.
+async fn apply_round(
+    state: &mut DdnsState,
+    now: DateTime<Utc>,
+    provider: &dyn DdnsProvider,
+    target: &DdnsTarget,
+    families: [(PublicIpProbeOutcome, Option<DdnsRecord>); 2],
+    missing_grace: u64,
+) {
+    state.clear_expired_fast_mode(now);
+
+    let mut unknown_messages = Vec::new();
+    let mut updates_succeeded = false;
+    let mut any_synced = false;
+    for (family, (probe, record)) in [AddressFamily::Ipv4, AddressFamily::Ipv6]
+        .into_iter()
+        .zip(families)
+    {
+        let synced = match apply_family_reconcile(
+            state,
             now,
-            &client,
-            &zone_id,
-            &hostname,
-            AddressFamily::Ipv6,
-            ipv6_probe,
-            ipv6_record.first().cloned(),
-            config.cloudflare_ddns_family_missing_grace,
+            provider,
+            target,
+            family,
+            probe,
+            record,
+            missing_grace,
         )
         .await
         {
@@ -509,19 +653,16 @@ async fn reconcile_once(config: &Config, handle: &DdnsHealthHandle) {
             Ok(FamilyReconcileOutcome::PendingMissingGrace) => false,
             Err(message) => {
                 unknown_messages.push(message);
-                state.snapshot.current_ipv6.is_some()
+                match family {
+                    AddressFamily::Ipv4 => state.snapshot.current_ipv4.is_some(),
+                    AddressFamily::Ipv6 => state.snapshot.current_ipv6.is_some(),
+                }
             }
         };
-
-        no_public_ip = !ipv4_synced && !ipv6_synced;
-        update_snapshot_after_round(
-            &mut state,
-            now,
-            unknown_messages,
-            updates_succeeded,
-            no_public_ip,
-        );
+        any_synced |= synced;
     }
+
+    update_snapshot_after_round(state, now, unknown_messages, updates_succeeded, !any_synced);
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -535,53 +676,33 @@ enum FamilyReconcileOutcome {
 async fn apply_family_reconcile(
     state: &mut DdnsState,
     now: DateTime<Utc>,
-    client: &CloudflareClient,
-    zone_id: &str,
-    hostname: &str,
+    provider: &dyn DdnsProvider,
+    target: &DdnsTarget,
     family: AddressFamily,
     probe: PublicIpProbeOutcome,
-    existing_record: Option<DnsRecordInfo>,
+    existing_record: Option<DdnsRecord>,
     missing_grace: u64,
 ) -> Result<FamilyReconcileOutcome, String> {
     if let Some(record) = existing_record.as_ref() {
-        state.family_mut(family).record_id = Some(record.id.clone());
+        state.family_mut(family).record_id = record.id.clone();
     }
 
     match probe {
         PublicIpProbeOutcome::Available(ip) => {
             let synced_ip = ip.to_string();
-            let family_state = state.family_mut(family);
-            family_state.missing_count = 0;
-
-            if let Some(record) = existing_record {
-                let should_patch = record.content != synced_ip
-                    || record.proxied != Some(DNS_PROXIED)
-                    || record.ttl != Some(DNS_AUTO_TTL);
-                if should_patch {
-                    client
-                        .patch_ip_dns_record(
-                            zone_id,
-                            &record.id,
-                            hostname,
-                            ip,
-                            DNS_PROXIED,
-                            DNS_AUTO_TTL,
-                        )
-                        .await
-                        .map_err(|err| {
-                            format!("ddns patch {} record: {err}", family.record_type())
-                        })?;
-                }
-                family_state.record_id = Some(record.id);
-            } else {
-                let created = client
-                    .create_ip_dns_record(zone_id, hostname, ip, DNS_PROXIED, DNS_AUTO_TTL)
-                    .await
-                    .map_err(|err| format!("ddns create {} record: {err}", family.record_type()))?;
-                family_state.record_id = Some(created.id);
+            state.family_mut(family).missing_count = 0;
+
+            let needs_update = existing_record
+                .as_ref()
+                .is_none_or(|record| record.content != synced_ip || record.stale_attributes);
+            if needs_update {
+                let published = provider
+                    .upsert_record(target, family, ip, existing_record.as_ref())
+                    .await?;
+                state.family_mut(family).record_id = published.id;
             }
 
-            family_state.synced_ip = Some(synced_ip.clone());
+            state.family_mut(family).synced_ip = Some(synced_ip.clone());
             match family {
                 AddressFamily::Ipv4 => state.snapshot.current_ipv4 = Some(synced_ip),
                 AddressFamily::Ipv6 => state.snapshot.current_ipv6 = Some(synced_ip),
@@ -593,14 +714,10 @@ async fn apply_family_reconcile(
             let family_state = state.family_mut(family);
             family_state.missing_count = family_state.missing_count.saturating_add(1);
             if family_state.missing_count >= missing_grace as u32 {
-                if let Some(record) = existing_record {
-                    client
-                        .delete_dns_record(zone_id, &record.id)
-                        .await
-                        .map_err(|err| {
-                            format!("ddns delete {} record: {err}", family.record_type())
-                        })?;
+                if let Some(record) = existing_record.as_ref() {
+                    provider.delete_record(target, family, record).await?;
                 }
+                let family_state = state.family_mut(family);
                 family_state.record_id = None;
                 family_state.synced_ip = None;
                 match family {
@@ -619,48 +736,6 @@ async fn apply_family_reconcile(
     }
 }
 
-async fn resolve_zone_id(
-    config: &Config,
-    handle: &DdnsHealthHandle,
-    client: &CloudflareClient,
-    hostname: &str,
-) -> Result<String, String> {
-    if !config.cloudflare_ddns_zone_id.trim().is_empty() {
-        return Ok(config.cloudflare_ddns_zone_id.trim().to_string());
-    }
-    {
-        let state = handle.inner.read().await;
-        if let Some(zone_id) = state.zone_id.clone() {
-            return Ok(zone_id);
-        }
-    }
-
-    let candidates = zone_name_candidates(hostname);
-    if candidates.is_empty() {
-        return Err("ddns could not derive zone candidates from hostname".to_string());
-    }
-
-    for candidate in candidates {
-        let zones = client
-            .list_zones_by_name(&candidate)
-            .await
-            .map_err(|err| format!("ddns resolve zone {candidate}: {err}"))?;
-        if zones.is_empty() {
-            continue;
-        }
-        if zones.len() > 1 {
-            return Err(format!(
-                "ddns matched multiple Cloudflare zones for {candidate}; set XP_CLOUDFLARE_DDNS_ZONE_ID"
-            ));
-        }
-        return Ok(zones[0].id.clone());
-    }
-
-    Err(format!(
-        "ddns found no Cloudflare zone for hostname {hostname}; set XP_CLOUDFLARE_DDNS_ZONE_ID"
-    ))
-}
-
 async fn apply_fatal_error(handle: &DdnsHealthHandle, now: DateTime<Utc>, message: String) {
     {
         let mut state = handle.inner.write().await;
@@ -732,18 +807,19 @@ fn update_snapshot_after_round(
     }
 }
 
-fn load_token(path: &str) -> Result<String, String> {
+/// Reads a provider secret (`label` names it in errors), trimming surrounding whitespace.
+fn load_secret_file(path: &str, label: &str) -> Result<String, String> {
     let trimmed_path = path.trim();
     if trimmed_path.is_empty() {
-        return Err("ddns token file is empty".to_string());
+        return Err(format!("ddns {label} file is empty"));
     }
     let raw = fs::read_to_string(trimmed_path)
-        .map_err(|err| format!("ddns read token file {trimmed_path}: {err}"))?;
-    let token = raw.trim();
-    if token.is_empty() {
-        return Err(format!("ddns token file {trimmed_path} is empty"));
+        .map_err(|err| format!("ddns read {label} file {trimmed_path}: {err}"))?;
+    let secret = raw.trim();
+    if secret.is_empty() {
+        return Err(format!("ddns {label} file {trimmed_path} is empty"));
     }
-    Ok(token.to_string())
+    Ok(secret.to_string())
 }
 
 fn should_enter_fast_mode(previous: Option<CloudflaredStatus>, current: CloudflaredStatus) -> bool {
@@ -763,7 +839,7 @@ fn zone_name_candidates(domain: &str) -> Vec<String> {
     out
 }
 
-fn is_valid_hostname(name: &str) -> bool {
+pub(crate) fn is_valid_hostname(name: &str) -> bool {
     if name.len() > 253 {
         return false;
     }
@@ -826,40 +902,4 @@ fn rfc3339(at: DateTime<Utc>) -> String {
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn zone_candidates_walk_suffixes() {
-        assert_eq!(
-            zone_name_candidates("edge.node.example.com"),
-            vec![
-                "edge.node.example.com".to_string(),
-                "node.example.com".to_string(),
-                "example.com".to_string(),
-                "com".to_string(),
-            ]
-        );
-    }
-
-    #[test]
-    fn fast_mode_only_triggers_when_cloudflared_becomes_up() {
-        assert!(should_enter_fast_mode(None, CloudflaredStatus::Up));
-        assert!(should_enter_fast_mode(
-            Some(CloudflaredStatus::Down),
-            CloudflaredStatus::Up
-        ));
-        assert!(!should_enter_fast_mode(
-            Some(CloudflaredStatus::Up),
-            CloudflaredStatus::Up
-        ));
-    }
-
-    #[test]
-    fn hostname_validation_matches_dns_rules() {
-        assert!(is_valid_hostname("node-1.example.com"));
-        assert!(!is_valid_hostname(""));
-        assert!(!is_valid_hostname("-bad.example.com"));
-        assert!(!is_valid_hostname("UPPER.example.com"));
-    }
-}
+mod tests;
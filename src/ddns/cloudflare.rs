@@ -0,0 +1,159 @@
+use std::net::IpAddr;
+
+use super::{
+    AddressFamily, DNS_AUTO_TTL, DNS_PROXIED, load_secret_file,
+    provider::{DdnsProvider, DdnsRecord, DdnsTarget, multiple_records_error},
+    zone_name_candidates,
+};
+use crate::{
+    config::{Config, DdnsProviderKind},
+    ops::cloudflare::{self, CloudflareClient},
+};
+
+pub(super) struct CloudflareProvider {
+    client: CloudflareClient,
+    configured_zone_id: String,
+}
+
+impl CloudflareProvider {
+    pub(super) fn from_config(config: &Config) -> Result<Self, String> {
+        let token = load_secret_file(&config.cloudflare_ddns_token_file, "token")?;
+        Ok(Self {
+            client: CloudflareClient::new(cloudflare::cloudflare_api_base(), token),
+            configured_zone_id: config.cloudflare_ddns_zone_id.trim().to_string(),
+        })
+    }
+
+    fn zone_id<'a>(&self, target: &'a DdnsTarget) -> Result<&'a str, String> {
+        target
+            .zone
+            .as_deref()
+            .ok_or_else(|| "ddns Cloudflare zone is not resolved".to_string())
+    }
+}
+
+#[async_trait::async_trait]
+impl DdnsProvider for CloudflareProvider {
+    fn kind(&self) -> DdnsProviderKind {
+        DdnsProviderKind::Cloudflare
+    }
+
+    async fn resolve_zone(
+        &self,
+        hostname: &str,
+        cached: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        if !self.configured_zone_id.is_empty() {
+            return Ok(Some(self.configured_zone_id.clone()));
+        }
+        if let Some(zone_id) = cached {
+            return Ok(Some(zone_id.to_string()));
+        }
+
+        let candidates = zone_name_candidates(hostname);
+        if candidates.is_empty() {
+            return Err("ddns could not derive zone candidates from hostname".to_string());
+        }
+
+        for candidate in candidates {
+            let zones = self
+                .client
+                .list_zones_by_name(&candidate)
+                .await
+                .map_err(|err| format!("ddns resolve zone {candidate}: {err}"))?;
+            if zones.is_empty() {
+                continue;
+            }
+            if zones.len() > 1 {
+                return Err(format!(
+                    "ddns matched multiple Cloudflare zones for {candidate}; \
+                     set XP_CLOUDFLARE_DDNS_ZONE_ID"
+                ));
+            }
+            return Ok(Some(zones[0].id.clone()));
+        }
+
+        Err(format!(
+            "ddns found no Cloudflare zone for hostname {hostname}; set XP_CLOUDFLARE_DDNS_ZONE_ID"
+        ))
+    }
+
+    async fn current_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        _last_synced: Option<&str>,
+    ) -> Result<Option<DdnsRecord>, String> {
+        let records = self
+            .client
+            .list_dns_records_by_type(
+                self.zone_id(target)?,
+                &target.hostname,
+                family.record_type(),
+            )
+            .await
+            .map_err(|err| format!("ddns list {} records: {err}", family.record_type()))?;
+        if records.len() > 1 {
+            return Err(multiple_records_error(family, &target.hostname));
+        }
+        Ok(records.into_iter().next().map(|record| DdnsRecord {
+            stale_attributes: record.proxied != Some(DNS_PROXIED)
+                || record.ttl != Some(DNS_AUTO_TTL),
+            id: Some(record.id),
+            content: record.content,
+        }))
+    }
+
+    async fn upsert_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: IpAddr,
+        existing: Option<&DdnsRecord>,
+    ) -> Result<DdnsRecord, String> {
+        let zone_id = self.zone_id(target)?;
+        let id = match existing.and_then(|record| record.id.as_deref()) {
+            Some(record_id) => {
+                self.client
+                    .patch_ip_dns_record(
+                        zone_id,
+                        record_id,
+                        &target.hostname,
+                        ip,
+                        DNS_PROXIED,
+                        DNS_AUTO_TTL,
+                    )
+                    .await
+                    .map_err(|err| format!("ddns patch {} record: {err}", family.record_type()))?;
+                record_id.to_string()
+            }
+            None => {
+                self.client
+                    .create_ip_dns_record(zone_id, &target.hostname, ip, DNS_PROXIED, DNS_AUTO_TTL)
+                    .await
+                    .map_err(|err| format!("ddns create {} record: {err}", family.record_type()))?
+                    .id
+            }
+        };
+        Ok(DdnsRecord {
+            id: Some(id),
+            content: ip.to_string(),
+            stale_attributes: false,
+        })
+    }
+
+    async fn delete_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        existing: &DdnsRecord,
+    ) -> Result<(), String> {
+        let Some(record_id) = existing.id.as_deref() else {
+            return Ok(());
+        };
+        self.client
+            .delete_dns_record(self.zone_id(target)?, record_id)
+            .await
+            .map_err(|err| format!("ddns delete {} record: {err}", family.record_type()))
+    }
+}
@@ -0,0 +1,238 @@
+//! Generic HTTP DDNS provider: calls an operator-supplied URL template (dyndns2-style update
+//! URLs, DuckDNS, dynv6, PowerDNS API gateways, ...) whenever the published address changes.
+//!
+//! Most such APIs cannot list records, so the provider trusts the IP xp last published and only
+//! calls the update URL when the probed address differs from it.
+
+use std::net::IpAddr;
+
+use regex::Regex;
+
+use super::{
+    AddressFamily, load_secret_file,
+    provider::{DdnsProvider, DdnsRecord, DdnsTarget},
+};
+use crate::config::{DdnsHttpMethod, DdnsProviderConfig, DdnsProviderKind};
+
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const ERROR_BODY_LIMIT: usize = 200;
+
+pub(super) struct HttpTemplateProvider {
+    client: reqwest::Client,
+    method: reqwest::Method,
+    url_template: String,
+    body_template: Option<String>,
+    delete_url_template: Option<String>,
+    success_regex: Option<Regex>,
+    token: Option<String>,
+}
+
+impl HttpTemplateProvider {
+    pub(super) fn from_config(config: &DdnsProviderConfig) -> Result<Self, String> {
+        let url_template = config.http_url_template.trim().to_string();
+        if url_template.is_empty() {
+            return Err(
+                "ddns http url template is empty; set XP_DDNS_HTTP_URL_TEMPLATE".to_string(),
+            );
+        }
+        validate_url_template(&url_template)?;
+        let delete_url_template = non_empty(&config.http_delete_url_template);
+        if let Some(template) = delete_url_template.as_deref() {
+            validate_url_template(template)?;
+        }
+        let success_regex = non_empty(&config.http_success_regex)
+            .map(|raw| Regex::new(&raw))
+            .transpose()
+            .map_err(|err| format!("ddns http success regex is invalid: {err}"))?;
+        let token = non_empty(&config.http_token_file)
+            .map(|path| load_secret_file(&path, "http token"))
+            .transpose()?;
+        let client = reqwest::Client::builder()
+            .user_agent("xp-ddns")
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| format!("ddns http client: {err}"))?;
+        Ok(Self {
+            client,
+            method: match config.http_method {
+                DdnsHttpMethod::Get => reqwest::Method::GET,
+                DdnsHttpMethod::Post => reqwest::Method::POST,
+                DdnsHttpMethod::Put => reqwest::Method::PUT,
+            },
+            url_template,
+            body_template: non_empty(&config.http_body_template),
+            delete_url_template,
+            success_regex,
+            token,
+        })
+    }
+
+    async fn call(
+        &self,
+        url_template: &str,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: Option<IpAddr>,
+    ) -> Result<(), String> {
+        let vars = TemplateVars {
+            hostname: &target.hostname,
+            ip: ip.map(|ip| ip.to_string()).unwrap_or_default(),
+            family,
+            token: self.token.as_deref().unwrap_or_default(),
+        };
+        let url = vars.render(url_template, true);
+        let mut request = self.client.request(self.method.clone(), &url);
+        if let Some(body) = self.body_template.as_deref() {
+            request = request.body(vars.render(body, false));
+        }
+        // The rendered URL may carry the token, so errors only name the status and body.
+        let resp = request
+            .send()
+            .await
+            .map_err(|err| format!("ddns http request failed: {}", err.without_url()))?;
+        let status = resp.status();
+        let body = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(format!(
+                "ddns http update returned {status}: {}",
+                truncate(body.trim())
+            ));
+        }
+        if let Some(regex) = self.success_regex.as_ref()
+            && !regex.is_match(&body)
+        {
+            return Err(format!(
+                "ddns http update response did not match XP_DDNS_HTTP_SUCCESS_REGEX: {}",
+                truncate(body.trim())
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DdnsProvider for HttpTemplateProvider {
+    fn kind(&self) -> DdnsProviderKind {
+        DdnsProviderKind::Http
+    }
+
+    async fn resolve_zone(
+        &self,
+        _hostname: &str,
+        _cached: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        Ok(None)
+    }
+
+    async fn current_record(
+        &self,
+        _target: &DdnsTarget,
+        _family: AddressFamily,
+        last_synced: Option<&str>,
+    ) -> Result<Option<DdnsRecord>, String> {
+        Ok(last_synced.map(|ip| DdnsRecord {
+            id: None,
+            content: ip.to_string(),
+            stale_attributes: false,
+        }))
+    }
+
+    async fn upsert_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: IpAddr,
+        _existing: Option<&DdnsRecord>,
+    ) -> Result<DdnsRecord, String> {
+        self.call(&self.url_template, target, family, Some(ip))
+            .await
+            .map_err(|err| format!("{err} ({} record)", family.record_type()))?;
+        Ok(DdnsRecord {
+            id: None,
+            content: ip.to_string(),
+            stale_attributes: false,
+        })
+    }
+
+    async fn delete_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        _existing: &DdnsRecord,
+    ) -> Result<(), String> {
+        let Some(template) = self.delete_url_template.as_deref() else {
+            return Ok(());
+        };
+        self.call(template, target, family, None)
+            .await
+            .map_err(|err| format!("{err} (delete {} record)", family.record_type()))
+    }
+}
+
+struct TemplateVars<'a> {
+    hostname: &'a str,
+    ip: String,
+    family: AddressFamily,
+    token: &'a str,
+}
+
+impl TemplateVars<'_> {
+    /// Substitutes placeholders; values are percent-encoded when rendering a URL.
+    fn render(&self, template: &str, url: bool) -> String {
+        let encode = |value: &str| {
+            if url {
+                percent_encode(value)
+            } else {
+                value.to_string()
+            }
+        };
+        template
+            .replace("{hostname}", &encode(self.hostname))
+            .replace("{ip}", &encode(&self.ip))
+            .replace("{record_type}", self.family.record_type())
+            .replace("{family}", self.family.label())
+            .replace("{token}", &encode(self.token))
+    }
+}
+
+fn validate_url_template(template: &str) -> Result<(), String> {
+    let probe = template
+        .replace("{hostname}", "host.example")
+        .replace("{ip}", "192.0.2.1")
+        .replace("{record_type}", "A")
+        .replace("{family}", "ipv4")
+        .replace("{token}", "token");
+    let url = reqwest::Url::parse(&probe)
+        .map_err(|err| format!("ddns http url template is not a valid URL: {err}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("ddns http url template must use http or https".to_string());
+    }
+    Ok(())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+fn truncate(body: &str) -> &str {
+    match body.char_indices().nth(ERROR_BODY_LIMIT) {
+        Some((idx, _)) => &body[..idx],
+        None => body,
+    }
+}
+
+#[cfg(test)]
+mod tests;
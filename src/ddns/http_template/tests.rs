@@ -0,0 +1,165 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use wiremock::matchers::{body_string, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use super::*;
+
+fn config(server: &MockServer) -> DdnsProviderConfig {
+    DdnsProviderConfig {
+        provider: DdnsProviderKind::Http,
+        http_url_template: format!(
+            "{}/nic/update?hostname={{hostname}}&myip={{ip}}&type={{record_type}}",
+            server.uri()
+        ),
+        ..DdnsProviderConfig::default()
+    }
+}
+
+fn target() -> DdnsTarget {
+    DdnsTarget {
+        hostname: "edge.example.com".to_string(),
+        zone: None,
+    }
+}
+
+#[tokio::test]
+async fn http_template_provider_renders_the_update_url() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/nic/update"))
+        .and(query_param("hostname", "edge.example.com"))
+        .and(query_param("myip", "2001:db8::7"))
+        .and(query_param("type", "AAAA"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("good 2001:db8::7"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = config(&server);
+    config.http_success_regex = "^(good|nochg)".to_string();
+    let provider = HttpTemplateProvider::from_config(&config).unwrap();
+
+    let record = provider
+        .upsert_record(
+            &target(),
+            AddressFamily::Ipv6,
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 7)),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(record.content, "2001:db8::7");
+
+    // Without a read API the last published IP stands in for the current record.
+    assert_eq!(
+        provider
+            .current_record(&target(), AddressFamily::Ipv6, Some("2001:db8::7"))
+            .await
+            .unwrap(),
+        Some(record)
+    );
+    assert_eq!(
+        provider
+            .current_record(&target(), AddressFamily::Ipv4, None)
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn http_template_provider_rejects_failed_updates() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/nic/update"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("badauth"))
+        .mount(&server)
+        .await;
+    let mut config = config(&server);
+    config.http_success_regex = "^(good|nochg)".to_string();
+    let provider = HttpTemplateProvider::from_config(&config).unwrap();
+    let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    let err = provider
+        .upsert_record(&target(), AddressFamily::Ipv4, ip, None)
+        .await
+        .unwrap_err();
+    assert!(err.contains("XP_DDNS_HTTP_SUCCESS_REGEX"), "{err}");
+    assert!(err.contains("badauth"), "{err}");
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401).set_body_string("unauthorized"))
+        .mount(&server)
+        .await;
+    let provider = HttpTemplateProvider::from_config(&self::config(&server)).unwrap();
+    let err = provider
+        .upsert_record(&target(), AddressFamily::Ipv4, ip, None)
+        .await
+        .unwrap_err();
+    assert!(err.contains("401"), "{err}");
+}
+
+#[tokio::test]
+async fn http_template_provider_posts_bodies_with_the_token_and_deletes_when_configured() {
+    let tmp = tempfile::tempdir().unwrap();
+    let token_file = tmp.path().join("token");
+    std::fs::write(&token_file, "s3cr3t\n").unwrap();
+
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/update"))
+        .and(body_string(
+            r#"{"name":"edge.example.com","content":"203.0.113.7","token":"s3cr3t"}"#,
+        ))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/delete"))
+        .and(query_param("type", "A"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let config = DdnsProviderConfig {
+        provider: DdnsProviderKind::Http,
+        http_url_template: format!("{}/update", server.uri()),
+        http_method: DdnsHttpMethod::Post,
+        http_body_template: r#"{"name":"{hostname}","content":"{ip}","token":"{token}"}"#
+            .to_string(),
+        http_delete_url_template: format!("{}/delete?type={{record_type}}", server.uri()),
+        http_token_file: token_file.display().to_string(),
+        ..DdnsProviderConfig::default()
+    };
+    let provider = HttpTemplateProvider::from_config(&config).unwrap();
+    let record = provider
+        .upsert_record(
+            &target(),
+            AddressFamily::Ipv4,
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+            None,
+        )
+        .await
+        .unwrap();
+    provider
+        .delete_record(&target(), AddressFamily::Ipv4, &record)
+        .await
+        .unwrap();
+}
+
+#[test]
+fn http_template_provider_requires_an_http_url_template() {
+    let mut config = DdnsProviderConfig {
+        provider: DdnsProviderKind::Http,
+        ..DdnsProviderConfig::default()
+    };
+    assert!(HttpTemplateProvider::from_config(&config).is_err());
+    config.http_url_template = "ftp://example.com/{ip}".to_string();
+    assert!(HttpTemplateProvider::from_config(&config).is_err());
+    config.http_url_template = "https://example.com/update?ip={ip}".to_string();
+    config.http_success_regex = "(".to_string();
+    assert!(HttpTemplateProvider::from_config(&config).is_err());
+}
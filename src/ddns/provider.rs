@@ -0,0 +1,80 @@
+use std::net::IpAddr;
+
+use super::rfc2136::Rfc2136Provider;
+use super::{AddressFamily, cloudflare::CloudflareProvider, http_template::HttpTemplateProvider};
+use crate::config::{Config, DdnsProviderKind};
+
+/// The hostname DDNS maintains, plus the provider-specific zone it lives in (a Cloudflare zone
+/// id, an RFC 2136 zone name, or nothing for providers that address records by hostname only).
+#[derive(Debug, Clone)]
+pub(super) struct DdnsTarget {
+    pub hostname: String,
+    pub zone: Option<String>,
+}
+
+/// The single `A` or `AAAA` record a provider currently publishes for the hostname.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct DdnsRecord {
+    /// Provider handle used for later updates; `None` when records are addressed by name.
+    pub id: Option<String>,
+    pub content: String,
+    /// TTL or other provider attributes differ from what xp publishes.
+    pub stale_attributes: bool,
+}
+
+/// DNS backend behind the DDNS state machine in `crate::ddns`.
+///
+/// Implementations report failures as ready-to-display `ddns ...` messages; the state machine
+/// owns status, fast mode, missing-family grace and persistence.
+#[async_trait::async_trait]
+pub(super) trait DdnsProvider: Send + Sync {
+    fn kind(&self) -> DdnsProviderKind;
+
+    /// Returns the zone to store in `ddns_state.json`; `cached` is the previously stored value.
+    async fn resolve_zone(
+        &self,
+        hostname: &str,
+        cached: Option<&str>,
+    ) -> Result<Option<String>, String>;
+
+    /// Looks up the published record. `last_synced` is the IP xp last published, for providers
+    /// that cannot read records back. More than one record is an error.
+    async fn current_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        last_synced: Option<&str>,
+    ) -> Result<Option<DdnsRecord>, String>;
+
+    async fn upsert_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: IpAddr,
+        existing: Option<&DdnsRecord>,
+    ) -> Result<DdnsRecord, String>;
+
+    async fn delete_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        existing: &DdnsRecord,
+    ) -> Result<(), String>;
+}
+
+/// Builds the configured provider. Secrets are read on every call so rotated files apply on the
+/// next reconcile round.
+pub(super) fn build_provider(config: &Config) -> Result<Box<dyn DdnsProvider>, String> {
+    match config.ddns.provider {
+        DdnsProviderKind::Cloudflare => Ok(Box::new(CloudflareProvider::from_config(config)?)),
+        DdnsProviderKind::Rfc2136 => Ok(Box::new(Rfc2136Provider::from_config(&config.ddns)?)),
+        DdnsProviderKind::Http => Ok(Box::new(HttpTemplateProvider::from_config(&config.ddns)?)),
+    }
+}
+
+pub(super) fn multiple_records_error(family: AddressFamily, hostname: &str) -> String {
+    format!(
+        "ddns found multiple {} records for {hostname}; refusing automatic changes",
+        family.record_type()
+    )
+}
@@ -0,0 +1,691 @@
+//! RFC 2136 dynamic updates signed with TSIG (RFC 8945), for zones hosted on BIND, PowerDNS,
+//! Knot or any other authoritative server that accepts signed updates.
+//!
+//! Only the small subset of the DNS wire format DDNS needs is implemented: an unsigned
+//! non-recursive query for the current `A`/`AAAA` RRset and signed updates that replace or
+//! delete that RRset. Both go over UDP; responses to signed updates must carry a valid TSIG.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use base64::Engine as _;
+use hmac::{Hmac, Mac as _};
+use sha2::{Sha256, Sha512};
+use tokio::net::UdpSocket;
+
+use super::{
+    AddressFamily, is_valid_hostname, load_secret_file,
+    provider::{DdnsProvider, DdnsRecord, DdnsTarget, multiple_records_error},
+};
+use crate::config::{DdnsProviderConfig, DdnsProviderKind, TsigAlgorithm};
+
+const DNS_PORT: u16 = 53;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const TSIG_FUDGE_SECS: u16 = 300;
+const MAX_UDP_MESSAGE: usize = 4096;
+
+const TYPE_A: u16 = 1;
+const TYPE_SOA: u16 = 6;
+const TYPE_AAAA: u16 = 28;
+const TYPE_TSIG: u16 = 250;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+
+const OPCODE_QUERY: u8 = 0;
+const OPCODE_UPDATE: u8 = 5;
+const FLAG_QR: u16 = 0x8000;
+const FLAG_TC: u16 = 0x0200;
+
+const RCODE_NOERROR: u16 = 0;
+const RCODE_NXDOMAIN: u16 = 3;
+
+#[derive(Debug, Clone)]
+pub(super) struct TsigKey {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+pub(super) struct Rfc2136Provider {
+    server: String,
+    zone: String,
+    key: TsigKey,
+    ttl: u32,
+}
+
+impl Rfc2136Provider {
+    pub(super) fn from_config(config: &DdnsProviderConfig) -> Result<Self, String> {
+        let server = config.rfc2136_server.trim();
+        if server.is_empty() {
+            return Err("ddns rfc2136 server is empty; set XP_DDNS_RFC2136_SERVER".to_string());
+        }
+        let zone = normalize_name(&config.rfc2136_zone);
+        if zone.is_empty() || !is_valid_hostname(&zone) {
+            return Err(format!(
+                "ddns rfc2136 zone is not a valid DNS name: {:?}; set XP_DDNS_RFC2136_ZONE",
+                config.rfc2136_zone
+            ));
+        }
+        let key_name = normalize_name(&config.rfc2136_tsig_key_name);
+        if key_name.is_empty() {
+            return Err(
+                "ddns rfc2136 TSIG key name is empty; set XP_DDNS_RFC2136_TSIG_KEY_NAME"
+                    .to_string(),
+            );
+        }
+        let secret = load_secret_file(&config.rfc2136_tsig_secret_file, "TSIG secret")?;
+        let secret = base64::engine::general_purpose::STANDARD
+            .decode(secret.as_bytes())
+            .map_err(|err| format!("ddns TSIG secret is not valid base64: {err}"))?;
+        Ok(Self::new(
+            server.to_string(),
+            zone,
+            TsigKey {
+                name: key_name,
+                algorithm: config.rfc2136_tsig_algorithm,
+                secret,
+            },
+            config.rfc2136_ttl,
+        ))
+    }
+
+    pub(super) fn new(server: String, zone: String, key: TsigKey, ttl: u32) -> Self {
+        Self {
+            server,
+            zone,
+            key,
+            ttl,
+        }
+    }
+
+    async fn server_addr(&self) -> Result<SocketAddr, String> {
+        if let Ok(addr) = self.server.parse::<SocketAddr>() {
+            return Ok(addr);
+        }
+        if let Ok(ip) = self.server.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(ip, DNS_PORT));
+        }
+        let lookup = if self.server.contains(':') {
+            tokio::net::lookup_host(self.server.clone())
+                .await
+                .map(|addrs| addrs.collect::<Vec<_>>())
+        } else {
+            tokio::net::lookup_host((self.server.clone(), DNS_PORT))
+                .await
+                .map(|addrs| addrs.collect::<Vec<_>>())
+        };
+        lookup
+            .map_err(|err| format!("ddns resolve rfc2136 server {}: {err}", self.server))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("ddns rfc2136 server {} has no address", self.server))
+    }
+
+    async fn send_update(
+        &self,
+        hostname: &str,
+        family: AddressFamily,
+        add: Option<IpAddr>,
+    ) -> Result<(), String> {
+        let id = rand::random::<u16>();
+        let mut message = build_update(id, &self.zone, hostname, family, add, self.ttl);
+        let request_mac = sign_message(&mut message, &self.key, unix_now(), None)?;
+        let server = self.server_addr().await?;
+        let response = exchange(server, &message, id).await?;
+        let parsed = ParsedMessage::parse(&response)?;
+        let rcode = parsed.rcode();
+        match parsed.tsig() {
+            Some(tsig) => {
+                verify_message(&response, &parsed, tsig, &self.key, id, Some(&request_mac))?;
+            }
+            None if rcode != RCODE_NOERROR => {}
+            None => return Err("ddns rfc2136 update response is not TSIG-signed".to_string()),
+        }
+        if rcode != RCODE_NOERROR {
+            return Err(format!(
+                "ddns rfc2136 update rejected: {}",
+                rcode_name(rcode)
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl DdnsProvider for Rfc2136Provider {
+    fn kind(&self) -> DdnsProviderKind {
+        DdnsProviderKind::Rfc2136
+    }
+
+    async fn resolve_zone(
+        &self,
+        hostname: &str,
+        _cached: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        if hostname != self.zone && !hostname.ends_with(&format!(".{}", self.zone)) {
+            return Err(format!(
+                "ddns access host {hostname} is outside rfc2136 zone {}",
+                self.zone
+            ));
+        }
+        Ok(Some(self.zone.clone()))
+    }
+
+    async fn current_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        _last_synced: Option<&str>,
+    ) -> Result<Option<DdnsRecord>, String> {
+        let id = rand::random::<u16>();
+        let query = build_query(id, &target.hostname, rr_type(family));
+        let server = self.server_addr().await?;
+        let response = exchange(server, &query, id)
+            .await
+            .map_err(|err| format!("{err} (query {} records)", family.record_type()))?;
+        let parsed = ParsedMessage::parse(&response)?;
+        let rcode = parsed.rcode();
+        if rcode != RCODE_NOERROR && rcode != RCODE_NXDOMAIN {
+            return Err(format!(
+                "ddns rfc2136 query {} records: {}",
+                family.record_type(),
+                rcode_name(rcode)
+            ));
+        }
+        let mut records = parsed
+            .answers()
+            .filter(|rr| {
+                rr.rr_type == rr_type(family) && rr.class == CLASS_IN && rr.name == target.hostname
+            })
+            .map(|rr| {
+                let rdata = &response[rr.rdata.clone()];
+                let ip = match family {
+                    AddressFamily::Ipv4 => <[u8; 4]>::try_from(rdata).map(IpAddr::from).ok(),
+                    AddressFamily::Ipv6 => <[u8; 16]>::try_from(rdata).map(IpAddr::from).ok(),
+                };
+                ip.map(|ip| DdnsRecord {
+                    id: None,
+                    content: ip.to_string(),
+                    stale_attributes: rr.ttl != self.ttl,
+                })
+                .ok_or_else(|| format!("ddns rfc2136 malformed {} record", family.record_type()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if records.len() > 1 {
+            return Err(multiple_records_error(family, &target.hostname));
+        }
+        Ok(records.pop())
+    }
+
+    async fn upsert_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: IpAddr,
+        _existing: Option<&DdnsRecord>,
+    ) -> Result<DdnsRecord, String> {
+        self.send_update(&target.hostname, family, Some(ip))
+            .await
+            .map_err(|err| format!("{err} (update {} record)", family.record_type()))?;
+        Ok(DdnsRecord {
+            id: None,
+            content: ip.to_string(),
+            stale_attributes: false,
+        })
+    }
+
+    async fn delete_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        _existing: &DdnsRecord,
+    ) -> Result<(), String> {
+        self.send_update(&target.hostname, family, None)
+            .await
+            .map_err(|err| format!("{err} (delete {} record)", family.record_type()))
+    }
+}
+
+fn rr_type(family: AddressFamily) -> u16 {
+    match family {
+        AddressFamily::Ipv4 => TYPE_A,
+        AddressFamily::Ipv6 => TYPE_AAAA,
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn rcode_name(rcode: u16) -> String {
+    match rcode {
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        6 => "YXDOMAIN".to_string(),
+        7 => "YXRRSET".to_string(),
+        8 => "NXRRSET".to_string(),
+        9 => "NOTAUTH".to_string(),
+        10 => "NOTZONE".to_string(),
+        16 => "BADSIG".to_string(),
+        17 => "BADKEY".to_string(),
+        18 => "BADTIME".to_string(),
+        other => format!("rcode {other}"),
+    }
+}
+
+async fn exchange(server: SocketAddr, message: &[u8], id: u16) -> Result<Vec<u8>, String> {
+    let bind: SocketAddr = if server.is_ipv4() {
+        "0.0.0.0:0".parse().expect("ipv4 bind")
+    } else {
+        "[::]:0".parse().expect("ipv6 bind")
+    };
+    let socket = UdpSocket::bind(bind)
+        .await
+        .map_err(|err| format!("ddns rfc2136 bind udp socket: {err}"))?;
+    socket
+        .connect(server)
+        .await
+        .map_err(|err| format!("ddns rfc2136 connect {server}: {err}"))?;
+    socket
+        .send(message)
+        .await
+        .map_err(|err| format!("ddns rfc2136 send to {server}: {err}"))?;
+
+    let mut buf = vec![0u8; MAX_UDP_MESSAGE];
+    tokio::time::timeout(REQUEST_TIMEOUT, async {
+        loop {
+            let len = socket
+                .recv(&mut buf)
+                .await
+                .map_err(|err| format!("ddns rfc2136 receive from {server}: {err}"))?;
+            // Ignore stray datagrams that do not answer this request.
+            if len >= 12 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+                let flags = u16::from_be_bytes([buf[2], buf[3]]);
+                if flags & FLAG_QR == 0 {
+                    continue;
+                }
+                if flags & FLAG_TC != 0 {
+                    return Err(format!("ddns rfc2136 response from {server} was truncated"));
+                }
+                return Ok(buf[..len].to_vec());
+            }
+        }
+    })
+    .await
+    .map_err(|_| format!("ddns rfc2136 server {server} did not respond"))?
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_be_bytes());
+}
+
+/// Writes `name` in uncompressed, lowercase wire form (also the TSIG canonical form).
+fn put_name(out: &mut Vec<u8>, name: &str) {
+    for label in normalize_name(name).split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+}
+
+fn put_header(out: &mut Vec<u8>, id: u16, opcode: u8, counts: [u16; 4]) {
+    put_u16(out, id);
+    put_u16(out, u16::from(opcode) << 11);
+    for count in counts {
+        put_u16(out, count);
+    }
+}
+
+pub(super) fn build_query(id: u16, hostname: &str, rr_type: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64);
+    put_header(&mut out, id, OPCODE_QUERY, [1, 0, 0, 0]);
+    put_name(&mut out, hostname);
+    put_u16(&mut out, rr_type);
+    put_u16(&mut out, CLASS_IN);
+    out
+}
+
+/// Replaces (`add = Some`) or deletes (`add = None`) the hostname's RRset for one family.
+pub(super) fn build_update(
+    id: u16,
+    zone: &str,
+    hostname: &str,
+    family: AddressFamily,
+    add: Option<IpAddr>,
+    ttl: u32,
+) -> Vec<u8> {
+    let updates = if add.is_some() { 2 } else { 1 };
+    let mut out = Vec::with_capacity(128);
+    put_header(&mut out, id, OPCODE_UPDATE, [1, 0, updates, 0]);
+    // Zone section.
+    put_name(&mut out, zone);
+    put_u16(&mut out, TYPE_SOA);
+    put_u16(&mut out, CLASS_IN);
+    // Delete the whole RRset (RFC 2136 2.5.2).
+    put_name(&mut out, hostname);
+    put_u16(&mut out, rr_type(family));
+    put_u16(&mut out, CLASS_ANY);
+    put_u32(&mut out, 0);
+    put_u16(&mut out, 0);
+    // Add the new record (RFC 2136 2.5.1).
+    if let Some(ip) = add {
+        put_name(&mut out, hostname);
+        put_u16(&mut out, rr_type(family));
+        put_u16(&mut out, CLASS_IN);
+        put_u32(&mut out, ttl);
+        match ip {
+            IpAddr::V4(ip) => {
+                put_u16(&mut out, 4);
+                out.extend_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                put_u16(&mut out, 16);
+                out.extend_from_slice(&ip.octets());
+            }
+        }
+    }
+    out
+}
+
+fn hmac_digest(algorithm: TsigAlgorithm, secret: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+    let invalid = |err: hmac::digest::InvalidLength| format!("ddns TSIG key: {err}");
+    Ok(match algorithm {
+        TsigAlgorithm::HmacSha256 => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(invalid)?;
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+        TsigAlgorithm::HmacSha512 => {
+            let mut mac = Hmac::<Sha512>::new_from_slice(secret).map_err(invalid)?;
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+    })
+}
+
+/// Computes the TSIG MAC over `message` (which must not contain the TSIG record yet) per
+/// RFC 8945 4.3: the prior MAC for responses, the message, then the TSIG variables.
+pub(super) fn tsig_mac(
+    key: &TsigKey,
+    prior_mac: Option<&[u8]>,
+    message: &[u8],
+    time_signed: u64,
+    fudge: u16,
+    error: u16,
+) -> Result<Vec<u8>, String> {
+    let mut data = Vec::with_capacity(message.len() + 128);
+    if let Some(prior_mac) = prior_mac {
+        put_u16(&mut data, prior_mac.len() as u16);
+        data.extend_from_slice(prior_mac);
+    }
+    data.extend_from_slice(message);
+    put_name(&mut data, &key.name);
+    put_u16(&mut data, CLASS_ANY);
+    put_u32(&mut data, 0);
+    put_name(&mut data, key.algorithm.as_str());
+    data.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+    put_u16(&mut data, fudge);
+    put_u16(&mut data, error);
+    put_u16(&mut data, 0);
+    hmac_digest(key.algorithm, &key.secret, &data)
+}
+
+/// Appends a TSIG record to `message` and returns its MAC (needed to verify the response).
+pub(super) fn sign_message(
+    message: &mut Vec<u8>,
+    key: &TsigKey,
+    time_signed: u64,
+    prior_mac: Option<&[u8]>,
+) -> Result<Vec<u8>, String> {
+    let mac = tsig_mac(key, prior_mac, message, time_signed, TSIG_FUDGE_SECS, 0)?;
+    let original_id = u16::from_be_bytes([message[0], message[1]]);
+
+    let mut rdata = Vec::with_capacity(mac.len() + 64);
+    put_name(&mut rdata, key.algorithm.as_str());
+    rdata.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+    put_u16(&mut rdata, TSIG_FUDGE_SECS);
+    put_u16(&mut rdata, mac.len() as u16);
+    rdata.extend_from_slice(&mac);
+    put_u16(&mut rdata, original_id);
+    put_u16(&mut rdata, 0);
+    put_u16(&mut rdata, 0);
+
+    put_name(message, &key.name);
+    put_u16(message, TYPE_TSIG);
+    put_u16(message, CLASS_ANY);
+    put_u32(message, 0);
+    put_u16(message, rdata.len() as u16);
+    message.extend_from_slice(&rdata);
+
+    let arcount = u16::from_be_bytes([message[10], message[11]]) + 1;
+    message[10..12].copy_from_slice(&arcount.to_be_bytes());
+    Ok(mac)
+}
+
+/// Checks the TSIG on a signed message; `prior_mac` is the request MAC when verifying a response.
+pub(super) fn verify_message(
+    raw: &[u8],
+    parsed: &ParsedMessage,
+    tsig: &ParsedRecord,
+    key: &TsigKey,
+    expected_id: u16,
+    prior_mac: Option<&[u8]>,
+) -> Result<(), String> {
+    let fields = TsigFields::parse(raw, tsig)?;
+    if tsig.name != key.name || fields.algorithm != key.algorithm.as_str() {
+        return Err("ddns rfc2136 response is signed with an unexpected key".to_string());
+    }
+    if fields.error != 0 {
+        return Err(format!(
+            "ddns rfc2136 TSIG rejected: {}",
+            rcode_name(fields.error)
+        ));
+    }
+    if fields.original_id != expected_id {
+        return Err("ddns rfc2136 TSIG original id mismatch".to_string());
+    }
+
+    // The MAC covers the message as it was before the TSIG record was appended.
+    let mut unsigned = raw[..tsig.start].to_vec();
+    unsigned[0..2].copy_from_slice(&fields.original_id.to_be_bytes());
+    let arcount = parsed.counts[3] - 1;
+    unsigned[10..12].copy_from_slice(&arcount.to_be_bytes());
+    let expected = tsig_mac(
+        key,
+        prior_mac,
+        &unsigned,
+        fields.time_signed,
+        fields.fudge,
+        fields.error,
+    )?;
+    let mac = &raw[fields.mac.clone()];
+    if mac.len() != expected.len() || !constant_time_eq(mac, &expected) {
+        return Err("ddns rfc2136 TSIG signature mismatch (BADSIG)".to_string());
+    }
+    if unix_now().abs_diff(fields.time_signed) > u64::from(fields.fudge) {
+        return Err("ddns rfc2136 TSIG time is outside the fudge window (BADTIME)".to_string());
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// One resource record of a parsed message; `rdata` indexes into the raw message.
+#[derive(Debug, Clone)]
+pub(super) struct ParsedRecord {
+    pub start: usize,
+    pub name: String,
+    pub rr_type: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: std::ops::Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct ParsedMessage {
+    pub flags: u16,
+    /// QDCOUNT/ZOCOUNT, ANCOUNT/PRCOUNT, NSCOUNT/UPCOUNT, ARCOUNT.
+    pub counts: [u16; 4],
+    pub records: Vec<ParsedRecord>,
+}
+
+impl ParsedMessage {
+    pub(super) fn parse(raw: &[u8]) -> Result<Self, String> {
+        let malformed = || "ddns rfc2136 malformed DNS message".to_string();
+        if raw.len() < 12 {
+            return Err(malformed());
+        }
+        let read_u16 = |at: usize| -> Result<u16, String> {
+            raw.get(at..at + 2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .ok_or_else(malformed)
+        };
+        let mut counts = [0u16; 4];
+        for (idx, count) in counts.iter_mut().enumerate() {
+            *count = read_u16(4 + idx * 2)?;
+        }
+        let mut pos = 12;
+        for _ in 0..counts[0] {
+            let (_name, next) = read_name(raw, pos)?;
+            pos = next + 4;
+        }
+        let mut records = Vec::new();
+        let total = usize::from(counts[1]) + usize::from(counts[2]) + usize::from(counts[3]);
+        for _ in 0..total {
+            let start = pos;
+            let (name, next) = read_name(raw, pos)?;
+            let rr_type = read_u16(next)?;
+            let class = read_u16(next + 2)?;
+            let ttl = (u32::from(read_u16(next + 4)?) << 16) | u32::from(read_u16(next + 6)?);
+            let rdlen = usize::from(read_u16(next + 8)?);
+            let rdata_start = next + 10;
+            if raw.len() < rdata_start + rdlen {
+                return Err(malformed());
+            }
+            records.push(ParsedRecord {
+                start,
+                name,
+                rr_type,
+                class,
+                ttl,
+                rdata: rdata_start..rdata_start + rdlen,
+            });
+            pos = rdata_start + rdlen;
+        }
+        Ok(Self {
+            flags: read_u16(2)?,
+            counts,
+            records,
+        })
+    }
+
+    pub(super) fn rcode(&self) -> u16 {
+        self.flags & 0x000f
+    }
+
+    pub(super) fn answers(&self) -> impl Iterator<Item = &ParsedRecord> {
+        self.records.iter().take(usize::from(self.counts[1]))
+    }
+
+    /// The TSIG record, which must be the last additional record.
+    pub(super) fn tsig(&self) -> Option<&ParsedRecord> {
+        if self.counts[3] == 0 {
+            return None;
+        }
+        self.records.last().filter(|rr| rr.rr_type == TYPE_TSIG)
+    }
+}
+
+pub(super) struct TsigFields {
+    pub algorithm: String,
+    pub time_signed: u64,
+    pub fudge: u16,
+    pub mac: std::ops::Range<usize>,
+    pub original_id: u16,
+    pub error: u16,
+}
+
+impl TsigFields {
+    pub(super) fn parse(raw: &[u8], tsig: &ParsedRecord) -> Result<Self, String> {
+        let malformed = || "ddns rfc2136 malformed TSIG record".to_string();
+        let end = tsig.rdata.end;
+        let bytes = |at: usize, len: usize| -> Result<&[u8], String> {
+            if at + len > end {
+                return Err(malformed());
+            }
+            Ok(&raw[at..at + len])
+        };
+        let (algorithm, pos) = read_name(raw, tsig.rdata.start)?;
+        let time = bytes(pos, 6)?;
+        let time_signed = time.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+        let fudge = u16::from_be_bytes(bytes(pos + 6, 2)?.try_into().map_err(|_| malformed())?);
+        let mac_len = usize::from(u16::from_be_bytes(
+            bytes(pos + 8, 2)?.try_into().map_err(|_| malformed())?,
+        ));
+        let mac_start = pos + 10;
+        bytes(mac_start, mac_len)?;
+        let tail = mac_start + mac_len;
+        let original_id = u16::from_be_bytes(bytes(tail, 2)?.try_into().map_err(|_| malformed())?);
+        let error = u16::from_be_bytes(bytes(tail + 2, 2)?.try_into().map_err(|_| malformed())?);
+        Ok(Self {
+            algorithm,
+            time_signed,
+            fudge,
+            mac: mac_start..tail,
+            original_id,
+            error,
+        })
+    }
+}
+
+/// Reads a possibly compressed name starting at `pos`; returns it (lowercase, no trailing dot)
+/// and the offset right after the name at its original position.
+fn read_name(raw: &[u8], mut pos: usize) -> Result<(String, usize), String> {
+    let malformed = || "ddns rfc2136 malformed DNS name".to_string();
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    for _ in 0..128 {
+        let len = *raw.get(pos).ok_or_else(malformed)?;
+        match len {
+            0 => {
+                let name = labels.join(".");
+                return Ok((name, end.unwrap_or(pos + 1)));
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *raw.get(pos + 1).ok_or_else(malformed)?;
+                end.get_or_insert(pos + 2);
+                pos = (usize::from(len & 0x3f) << 8) | usize::from(low);
+            }
+            len if len & 0xc0 == 0 => {
+                let label = raw
+                    .get(pos + 1..pos + 1 + usize::from(len))
+                    .ok_or_else(malformed)?;
+                labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+                pos += 1 + usize::from(len);
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    Err(malformed())
+}
+
+#[cfg(test)]
+mod tests;
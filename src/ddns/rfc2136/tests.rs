@@ -0,0 +1,263 @@
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::{Arc, Mutex},
+};
+
+use tokio::net::UdpSocket;
+
+use super::*;
+
+type Zone = Arc<Mutex<BTreeMap<(String, u16), Vec<(u32, Vec<u8>)>>>>;
+
+fn key(secret: &[u8]) -> TsigKey {
+    TsigKey {
+        name: "xp-ddns".to_string(),
+        algorithm: TsigAlgorithm::HmacSha256,
+        secret: secret.to_vec(),
+    }
+}
+
+fn target() -> DdnsTarget {
+    DdnsTarget {
+        hostname: "edge.example.com".to_string(),
+        zone: Some("example.com".to_string()),
+    }
+}
+
+fn response_header(out: &mut Vec<u8>, id: u16, opcode: u8, rcode: u16, counts: [u16; 4]) {
+    put_u16(out, id);
+    put_u16(out, FLAG_QR | 0x0400 | (u16::from(opcode) << 11) | rcode);
+    for count in counts {
+        put_u16(out, count);
+    }
+}
+
+/// A minimal authoritative server for `example.com`: answers queries from `zone` and applies
+/// TSIG-signed updates, signing its responses like BIND does.
+async fn spawn_nameserver(server_key: TsigKey, zone: Zone) -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let raw = buf[..len].to_vec();
+            let parsed = ParsedMessage::parse(&raw).unwrap();
+            let id = u16::from_be_bytes([raw[0], raw[1]]);
+            let opcode = ((parsed.flags >> 11) & 0x0f) as u8;
+            let (qname, after_name) = read_name(&raw, 12).unwrap();
+            let question_end = after_name + 4;
+            let qtype = u16::from_be_bytes([raw[after_name], raw[after_name + 1]]);
+
+            let mut out = Vec::new();
+            if opcode == OPCODE_QUERY {
+                let answers = zone
+                    .lock()
+                    .unwrap()
+                    .get(&(qname.clone(), qtype))
+                    .cloned()
+                    .unwrap_or_default();
+                response_header(&mut out, id, opcode, 0, [1, answers.len() as u16, 0, 0]);
+                out.extend_from_slice(&raw[12..question_end]);
+                for (ttl, rdata) in answers {
+                    // Compressed owner name pointing at the question.
+                    put_u16(&mut out, 0xc00c);
+                    put_u16(&mut out, qtype);
+                    put_u16(&mut out, CLASS_IN);
+                    put_u32(&mut out, ttl);
+                    put_u16(&mut out, rdata.len() as u16);
+                    out.extend_from_slice(&rdata);
+                }
+            } else {
+                let tsig = parsed.tsig().cloned();
+                let verified = tsig.as_ref().and_then(|tsig| {
+                    verify_message(&raw, &parsed, tsig, &server_key, id, None).ok()?;
+                    Some(raw[TsigFields::parse(&raw, tsig).unwrap().mac].to_vec())
+                });
+                let Some(request_mac) = verified else {
+                    response_header(&mut out, id, opcode, 9, [0, 0, 0, 0]);
+                    socket.send_to(&out, peer).await.unwrap();
+                    continue;
+                };
+                let updates = parsed
+                    .records
+                    .iter()
+                    .skip(usize::from(parsed.counts[1]))
+                    .take(usize::from(parsed.counts[2]));
+                let mut zone = zone.lock().unwrap();
+                for rr in updates {
+                    let rrset = (rr.name.clone(), rr.rr_type);
+                    if rr.class == CLASS_ANY {
+                        zone.remove(&rrset);
+                    } else {
+                        zone.entry(rrset)
+                            .or_default()
+                            .push((rr.ttl, raw[rr.rdata.clone()].to_vec()));
+                    }
+                }
+                drop(zone);
+                response_header(&mut out, id, opcode, 0, [0, 0, 0, 0]);
+                sign_message(&mut out, &server_key, unix_now(), Some(&request_mac)).unwrap();
+            }
+            socket.send_to(&out, peer).await.unwrap();
+        }
+    });
+    addr.to_string()
+}
+
+#[test]
+fn tsig_mac_matches_rfc8945_digest_layout() {
+    // Independently computed with Python's hmac over the RFC 8945 4.3.3 digest components.
+    let message = build_update(
+        0x1234,
+        "example.com",
+        "edge.example.com",
+        AddressFamily::Ipv4,
+        Some(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))),
+        60,
+    );
+    assert_eq!(
+        hex::encode(&message),
+        concat!(
+            // Header: id, UPDATE opcode, one zone, two updates.
+            "123428000001000000020000",
+            // Zone section: example.com SOA IN.
+            "076578616d706c6503636f6d0000060001",
+            // Delete the existing A RRset of edge.example.com.
+            "0465646765076578616d706c6503636f6d00000100ff000000000000",
+            // Add edge.example.com 60 IN A 203.0.113.7.
+            "0465646765076578616d706c6503636f6d00000100010000003c0004cb007107",
+        )
+    );
+    let mac = tsig_mac(
+        &key(b"0123456789abcdef0123456789abcdef"),
+        None,
+        &message,
+        1_700_000_000,
+        300,
+        0,
+    )
+    .unwrap();
+    assert_eq!(
+        hex::encode(mac),
+        "7f3a957eb30024f67e7339a87d7abd6542d3da21779cc6af28c76ff28b4fcd57"
+    );
+}
+
+#[tokio::test]
+async fn rfc2136_provider_replaces_reads_back_and_deletes_records() {
+    let zone = Zone::default();
+    let server = spawn_nameserver(key(b"shared-secret"), zone.clone()).await;
+    let provider =
+        Rfc2136Provider::new(server, "example.com".to_string(), key(b"shared-secret"), 60);
+    let target = target();
+
+    assert_eq!(
+        provider
+            .resolve_zone("edge.example.com", None)
+            .await
+            .unwrap(),
+        Some("example.com".to_string())
+    );
+    assert!(
+        provider
+            .resolve_zone("edge.example.net", None)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        provider
+            .current_record(&target, AddressFamily::Ipv4, None)
+            .await
+            .unwrap(),
+        None
+    );
+
+    let ip = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+    provider
+        .upsert_record(&target, AddressFamily::Ipv4, ip, None)
+        .await
+        .unwrap();
+    let ipv6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 7));
+    provider
+        .upsert_record(&target, AddressFamily::Ipv6, ipv6, None)
+        .await
+        .unwrap();
+    let record = provider
+        .current_record(&target, AddressFamily::Ipv4, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(record.content, "203.0.113.7");
+    assert!(!record.stale_attributes);
+
+    // A replacement swaps the whole RRset instead of adding a second address.
+    let next = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 8));
+    provider
+        .upsert_record(&target, AddressFamily::Ipv4, next, Some(&record))
+        .await
+        .unwrap();
+    assert_eq!(
+        zone.lock()
+            .unwrap()
+            .get(&("edge.example.com".to_string(), TYPE_A))
+            .cloned(),
+        Some(vec![(60, vec![203, 0, 113, 8])])
+    );
+
+    let record = provider
+        .current_record(&target, AddressFamily::Ipv4, None)
+        .await
+        .unwrap()
+        .unwrap();
+    provider
+        .delete_record(&target, AddressFamily::Ipv4, &record)
+        .await
+        .unwrap();
+    assert_eq!(
+        provider
+            .current_record(&target, AddressFamily::Ipv4, None)
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        provider
+            .current_record(&target, AddressFamily::Ipv6, None)
+            .await
+            .unwrap()
+            .map(|record| record.content),
+        Some("2001:db8::7".to_string())
+    );
+}
+
+#[tokio::test]
+async fn rfc2136_provider_reports_rejected_keys_and_duplicate_records() {
+    let zone = Zone::default();
+    zone.lock().unwrap().insert(
+        ("edge.example.com".to_string(), TYPE_A),
+        vec![(300, vec![192, 0, 2, 1]), (300, vec![192, 0, 2, 2])],
+    );
+    let server = spawn_nameserver(key(b"shared-secret"), zone).await;
+    let provider =
+        Rfc2136Provider::new(server, "example.com".to_string(), key(b"wrong-secret"), 60);
+    let target = target();
+
+    let err = provider
+        .current_record(&target, AddressFamily::Ipv4, None)
+        .await
+        .unwrap_err();
+    assert!(err.contains("multiple A records"), "{err}");
+
+    let err = provider
+        .upsert_record(
+            &target,
+            AddressFamily::Ipv4,
+            IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7)),
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert!(err.contains("NOTAUTH"), "{err}");
+}
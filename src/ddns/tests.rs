@@ -0,0 +1,173 @@
+use super::*;
+
+#[test]
+fn zone_candidates_walk_suffixes() {
+    assert_eq!(
+        zone_name_candidates("edge.node.example.com"),
+        vec![
+            "edge.node.example.com".to_string(),
+            "node.example.com".to_string(),
+            "example.com".to_string(),
+            "com".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn fast_mode_only_triggers_when_cloudflared_becomes_up() {
+    assert!(should_enter_fast_mode(None, CloudflaredStatus::Up));
+    assert!(should_enter_fast_mode(
+        Some(CloudflaredStatus::Down),
+        CloudflaredStatus::Up
+    ));
+    assert!(!should_enter_fast_mode(
+        Some(CloudflaredStatus::Up),
+        CloudflaredStatus::Up
+    ));
+}
+
+/// Keeps one record per family in memory and logs every write.
+#[derive(Default)]
+struct MemoryProvider {
+    records: std::sync::Mutex<std::collections::BTreeMap<&'static str, String>>,
+    writes: std::sync::Mutex<Vec<String>>,
+}
+
+#[async_trait::async_trait]
+impl DdnsProvider for MemoryProvider {
+    fn kind(&self) -> DdnsProviderKind {
+        DdnsProviderKind::Rfc2136
+    }
+
+    async fn resolve_zone(
+        &self,
+        _hostname: &str,
+        _cached: Option<&str>,
+    ) -> Result<Option<String>, String> {
+        Ok(Some("example.com".to_string()))
+    }
+
+    async fn current_record(
+        &self,
+        _target: &DdnsTarget,
+        family: AddressFamily,
+        _last_synced: Option<&str>,
+    ) -> Result<Option<DdnsRecord>, String> {
+        let records = self.records.lock().unwrap();
+        Ok(records.get(family.record_type()).map(|content| DdnsRecord {
+            id: None,
+            content: content.clone(),
+            stale_attributes: false,
+        }))
+    }
+
+    async fn upsert_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        ip: IpAddr,
+        _existing: Option<&DdnsRecord>,
+    ) -> Result<DdnsRecord, String> {
+        self.records
+            .lock()
+            .unwrap()
+            .insert(family.record_type(), ip.to_string());
+        self.writes.lock().unwrap().push(format!(
+            "upsert {} {} {ip}",
+            target.hostname,
+            family.record_type()
+        ));
+        Ok(DdnsRecord {
+            id: None,
+            content: ip.to_string(),
+            stale_attributes: false,
+        })
+    }
+
+    async fn delete_record(
+        &self,
+        target: &DdnsTarget,
+        family: AddressFamily,
+        _existing: &DdnsRecord,
+    ) -> Result<(), String> {
+        self.records.lock().unwrap().remove(family.record_type());
+        self.writes.lock().unwrap().push(format!(
+            "delete {} {}",
+            target.hostname,
+            family.record_type()
+        ));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn publish_records_converges_both_families_and_skips_no_ops() {
+    let provider = MemoryProvider::default();
+    let primary: [IpAddr; 2] = [
+        "203.0.113.10".parse().unwrap(),
+        "2001:db8::10".parse().unwrap(),
+    ];
+    let standby: [IpAddr; 1] = ["198.51.100.20".parse().unwrap()];
+
+    assert!(
+        publish_records(&provider, "Edge.Example.com", &primary)
+            .await
+            .unwrap()
+    );
+    assert!(
+        !publish_records(&provider, "edge.example.com", &primary)
+            .await
+            .unwrap()
+    );
+    assert!(
+        publish_records(&provider, "edge.example.com", &standby)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        provider.writes.lock().unwrap().clone(),
+        vec![
+            "upsert edge.example.com A 203.0.113.10".to_string(),
+            "upsert edge.example.com AAAA 2001:db8::10".to_string(),
+            "upsert edge.example.com A 198.51.100.20".to_string(),
+            "delete edge.example.com AAAA".to_string(),
+        ]
+    );
+
+    let err = publish_records(&provider, "bad_host", &standby)
+        .await
+        .unwrap_err();
+    assert!(err.contains("not a valid FQDN"), "{err}");
+}
+
+#[test]
+fn failover_owned_round_reports_public_ips_and_forgets_the_record() {
+    let now = Utc::now();
+    let mut state = DdnsState::new("edge.example.com".to_string(), DdnsProviderKind::Http, true);
+    state.ipv4.synced_ip = Some("198.51.100.1".to_string());
+    state.ipv4.record_id = Some("record-1".to_string());
+
+    apply_failover_owned_round(
+        &mut state,
+        now,
+        [
+            PublicIpProbeOutcome::Available("203.0.113.10".parse().unwrap()),
+            PublicIpProbeOutcome::MissingCandidate("no ipv6".to_string()),
+        ],
+    );
+
+    assert_eq!(state.snapshot.status, DdnsStatus::Up);
+    assert_eq!(state.snapshot.current_ipv4.as_deref(), Some("203.0.113.10"));
+    assert_eq!(state.snapshot.current_ipv6, None);
+    // A later regular round must write the record again, whatever the leader left in it.
+    assert_eq!(state.ipv4.synced_ip, None);
+    assert_eq!(state.ipv4.record_id, None);
+}
+
+#[test]
+fn hostname_validation_matches_dns_rules() {
+    assert!(is_valid_hostname("node-1.example.com"));
+    assert!(!is_valid_hostname(""));
+    assert!(!is_valid_hostname("-bad.example.com"));
+    assert!(!is_valid_hostname("UPPER.example.com"));
+}
@@ -0,0 +1,603 @@
+//! DNS failover groups: a hostname that normally resolves to a primary node and that the Raft
+//! leader repoints at a healthy standby while the primary's Xray is down or its endpoint probes
+//! fail.
+//!
+//! Subscribers keep dialing the group hostname (the primary's `access_host`), so the record is
+//! the only thing that moves. Each leader round samples every member node, and the record follows
+//! only sustained changes: it leaves a node after `FAIL_AFTER_ROUNDS` unhealthy rounds and
+//! returns to the primary after `RESTORE_AFTER_ROUNDS` healthy ones. Records are written through
+//! the leader's DDNS provider (`crate::ddns`), and every move is recorded as a runtime event.
+//! The primary's own DDNS worker leaves a group hostname alone and only reports its public IPs.
+
+use std::{collections::BTreeMap, net::IpAddr, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::{
+    config::Config,
+    control_plane_mesh::{MeshAwareHttpClient, MeshRequest, peer_target_from_node},
+    domain::{Endpoint, Node},
+    endpoint_probe::format_hour_key,
+    internal_auth::InternalRoute,
+    node_runtime::{
+        ComponentRuntimeStatus, NodeRuntimeEventKind, NodeRuntimeHandle, RuntimeComponent,
+        RuntimeStatus,
+    },
+    raft::app::RaftFacade,
+    state::{EndpointProbeHistory, JsonSnapshotStore},
+};
+
+#[cfg(test)]
+mod tests;
+
+const WORKER_INTERVAL: Duration = Duration::from_secs(30);
+/// Consecutive unhealthy rounds before the record leaves a node.
+pub const FAIL_AFTER_ROUNDS: u32 = 3;
+/// Consecutive healthy rounds before the record returns to the primary.
+pub const RESTORE_AFTER_ROUNDS: u32 = 5;
+/// Probe hours older than this no longer say anything about a node.
+const PROBE_MAX_AGE_HOURS: i64 = 2;
+/// Endpoint meta fields clients pin from a subscription; a standby must present the same ones.
+const CLIENT_PINNED_META: [&str; 3] = [
+    "/reality_keys/public_key",
+    "/active_short_id",
+    "/cert_sha256",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DnsFailoverGroup {
+    pub group_id: String,
+    /// Record the leader rewrites; it should be the primary's `access_host`.
+    pub hostname: String,
+    pub primary_node_id: String,
+    /// Failover candidates in preference order.
+    #[serde(default)]
+    pub standby_node_ids: Vec<String>,
+    /// Public IPs published for the primary when it reports none through DDNS, at most one per
+    /// address family. Its `access_host` is the group hostname and cannot be resolved instead.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub primary_addresses: Vec<IpAddr>,
+}
+
+impl DnsFailoverGroup {
+    /// Checks the parts of the group that do not depend on cluster state.
+    pub fn validate(&self) -> Result<(), String> {
+        if !crate::ddns::is_valid_hostname(&self.hostname) {
+            return Err(format!(
+                "hostname must be a lowercase FQDN: {:?}",
+                self.hostname
+            ));
+        }
+        if self.primary_node_id.trim().is_empty() {
+            return Err("primary_node_id is required".to_string());
+        }
+        if self.standby_node_ids.is_empty() {
+            return Err("at least one standby node is required".to_string());
+        }
+        for (idx, node_id) in self.standby_node_ids.iter().enumerate() {
+            if *node_id == self.primary_node_id {
+                return Err(format!("primary node {node_id} cannot also be a standby"));
+            }
+            if self.standby_node_ids[..idx].contains(node_id) {
+                return Err(format!("standby node {node_id} is listed twice"));
+            }
+        }
+        for (idx, ip) in self.primary_addresses.iter().enumerate() {
+            if self.primary_addresses[..idx]
+                .iter()
+                .any(|other| other.is_ipv4() == ip.is_ipv4())
+            {
+                return Err(format!(
+                    "primary_addresses has more than one address of the family of {ip}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn member_node_ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.primary_node_id.as_str())
+            .chain(self.standby_node_ids.iter().map(String::as_str))
+    }
+}
+
+/// Whether `candidate` serves every endpoint of `primary` under the same kind, port and
+/// client-pinned keys, so a subscription built for the primary also works against it.
+pub fn has_equivalent_endpoints(endpoints: &[Endpoint], primary: &str, candidate: &str) -> bool {
+    let mut primary_endpoints = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.node_id == primary)
+        .peekable();
+    if primary_endpoints.peek().is_none() {
+        return false;
+    }
+    primary_endpoints.all(|wanted| {
+        endpoints.iter().any(|endpoint| {
+            endpoint.node_id == candidate
+                && endpoint.kind == wanted.kind
+                && endpoint.port == wanted.port
+                && CLIENT_PINNED_META
+                    .iter()
+                    .all(|pointer| endpoint.meta.pointer(pointer) == wanted.meta.pointer(pointer))
+        })
+    })
+}
+
+/// Why a node should not receive traffic right now, or `None` when it looks healthy.
+///
+/// `components` is the node's runtime report; `None` means the node could not be reached, which
+/// counts against it. An unknown Xray status is not evidence either way.
+pub fn node_problem(
+    node_id: &str,
+    components: Option<&[ComponentRuntimeStatus]>,
+    endpoints: &[Endpoint],
+    probe_history: &BTreeMap<String, EndpointProbeHistory>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let Some(components) = components else {
+        return Some("node runtime unreachable".to_string());
+    };
+    let xray_down = components.iter().any(|component| {
+        component.component == RuntimeComponent::Xray && component.status == RuntimeStatus::Down
+    });
+    if xray_down {
+        return Some("xray is down".to_string());
+    }
+    probe_problem(node_id, endpoints, probe_history, now)
+}
+
+/// Probes fail a node when every endpoint probed in the last `PROBE_MAX_AGE_HOURS` had no
+/// successful sample in its latest hour.
+fn probe_problem(
+    node_id: &str,
+    endpoints: &[Endpoint],
+    probe_history: &BTreeMap<String, EndpointProbeHistory>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let cutoff = format_hour_key(now - chrono::Duration::hours(PROBE_MAX_AGE_HOURS));
+    let mut probed = 0;
+    for endpoint in endpoints
+        .iter()
+        .filter(|endpoint| endpoint.node_id == node_id)
+    {
+        let latest = probe_history
+            .get(&endpoint.endpoint_id)
+            .and_then(|history| {
+                history.hours.iter().rev().find_map(|(hour, samples)| {
+                    let tested = samples
+                        .by_node
+                        .values()
+                        .filter(|sample| !sample.skipped)
+                        .collect::<Vec<_>>();
+                    (!tested.is_empty()).then_some((hour, tested))
+                })
+            })
+            .filter(|(hour, _samples)| **hour >= cutoff);
+        let Some((_hour, samples)) = latest else {
+            continue;
+        };
+        if samples.iter().any(|sample| sample.ok) {
+            return None;
+        }
+        probed += 1;
+    }
+    (probed > 0).then(|| "endpoint probes failing".to_string())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FailoverDecision {
+    /// The first decision for a group since this node became leader; the record is made to
+    /// match without assuming where it pointed before.
+    Initialize { node_id: String },
+    Activate {
+        from_node_id: String,
+        to_node_id: String,
+        reason: String,
+    },
+    Restore {
+        from_node_id: String,
+        to_node_id: String,
+    },
+}
+
+impl FailoverDecision {
+    pub fn target_node_id(&self) -> &str {
+        match self {
+            Self::Initialize { node_id } => node_id,
+            Self::Activate { to_node_id, .. } | Self::Restore { to_node_id, .. } => to_node_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct HealthStreak {
+    healthy_rounds: u32,
+    unhealthy_rounds: u32,
+    problem: Option<String>,
+}
+
+/// Leader-local hysteresis state. It is rebuilt from scratch after a leadership change, so a new
+/// leader only touches a record once it has seen enough rounds to be sure.
+#[derive(Debug, Clone, Default)]
+pub struct FailoverTracker {
+    streaks: BTreeMap<String, HealthStreak>,
+    /// Node each group's record points at, by `group_id`.
+    active: BTreeMap<String, String>,
+}
+
+impl FailoverTracker {
+    /// Feeds one round of samples, `node_id` → problem (`None` when healthy). Nodes missing from
+    /// the round are forgotten.
+    pub fn observe(&mut self, samples: BTreeMap<String, Option<String>>) {
+        self.streaks
+            .retain(|node_id, _streak| samples.contains_key(node_id));
+        for (node_id, problem) in samples {
+            let streak = self.streaks.entry(node_id).or_default();
+            if problem.is_some() {
+                streak.unhealthy_rounds = streak.unhealthy_rounds.saturating_add(1);
+                streak.healthy_rounds = 0;
+            } else {
+                streak.healthy_rounds = streak.healthy_rounds.saturating_add(1);
+                streak.unhealthy_rounds = 0;
+            }
+            streak.problem = problem;
+        }
+    }
+
+    /// Decides where the group's record should move. `candidates` are the standbys currently able
+    /// to take over (existing, with equivalent endpoints), in preference order.
+    pub fn decide(
+        &self,
+        group: &DnsFailoverGroup,
+        candidates: &[&str],
+    ) -> Option<FailoverDecision> {
+        let primary = group.primary_node_id.as_str();
+        let primary_failed = self.has_failed(primary);
+        let primary_recovered = self.has_recovered(primary);
+        let active = self.active.get(&group.group_id).map(String::as_str);
+        let healthy_standby = |except: Option<&str>| {
+            candidates
+                .iter()
+                .copied()
+                .find(|node_id| Some(*node_id) != except && self.is_healthy(node_id))
+        };
+
+        match active {
+            None if primary_recovered => Some(FailoverDecision::Initialize {
+                node_id: primary.to_string(),
+            }),
+            None if primary_failed => {
+                healthy_standby(None).map(|node_id| FailoverDecision::Initialize {
+                    node_id: node_id.to_string(),
+                })
+            }
+            None => None,
+            Some(active) if active == primary => {
+                if !primary_failed {
+                    return None;
+                }
+                healthy_standby(None).map(|node_id| FailoverDecision::Activate {
+                    from_node_id: primary.to_string(),
+                    to_node_id: node_id.to_string(),
+                    reason: self.problem(primary),
+                })
+            }
+            Some(active) => {
+                if primary_recovered {
+                    return Some(FailoverDecision::Restore {
+                        from_node_id: active.to_string(),
+                        to_node_id: primary.to_string(),
+                    });
+                }
+                let reason = if !candidates.contains(&active) {
+                    "no longer an eligible standby".to_string()
+                } else if self.has_failed(active) {
+                    self.problem(active)
+                } else {
+                    return None;
+                };
+                healthy_standby(Some(active)).map(|node_id| FailoverDecision::Activate {
+                    from_node_id: active.to_string(),
+                    to_node_id: node_id.to_string(),
+                    reason,
+                })
+            }
+        }
+    }
+
+    /// Remembers where the record points once it has been published.
+    pub fn commit(&mut self, group_id: &str, node_id: &str) {
+        self.active
+            .insert(group_id.to_string(), node_id.to_string());
+    }
+
+    pub fn retain_groups(&mut self, groups: &BTreeMap<String, DnsFailoverGroup>) {
+        self.active
+            .retain(|group_id, _node_id| groups.contains_key(group_id));
+    }
+
+    fn is_healthy(&self, node_id: &str) -> bool {
+        self.streaks
+            .get(node_id)
+            .is_some_and(|streak| streak.healthy_rounds > 0)
+    }
+
+    fn has_failed(&self, node_id: &str) -> bool {
+        self.streaks
+            .get(node_id)
+            .is_some_and(|streak| streak.unhealthy_rounds >= FAIL_AFTER_ROUNDS)
+    }
+
+    fn has_recovered(&self, node_id: &str) -> bool {
+        self.streaks
+            .get(node_id)
+            .is_some_and(|streak| streak.healthy_rounds >= RESTORE_AFTER_ROUNDS)
+    }
+
+    fn problem(&self, node_id: &str) -> String {
+        self.streaks
+            .get(node_id)
+            .and_then(|streak| streak.problem.clone())
+            .unwrap_or_else(|| "unhealthy".to_string())
+    }
+}
+
+/// Addresses to publish for `node`: the public IPs its DDNS runtime reports, or else the group's
+/// `primary_addresses` for the primary, or else its resolved `access_host` unless that is the
+/// failover hostname itself.
+async fn node_addresses(
+    group: &DnsFailoverGroup,
+    node: &Node,
+    components: Option<&[ComponentRuntimeStatus]>,
+) -> Result<Vec<IpAddr>, String> {
+    let synced = components
+        .into_iter()
+        .flatten()
+        .filter(|component| component.component == RuntimeComponent::Ddns)
+        .flat_map(|component| [&component.current_ipv4, &component.current_ipv6])
+        .filter_map(|ip| ip.as_deref()?.parse::<IpAddr>().ok())
+        .collect::<Vec<_>>();
+    if !synced.is_empty() {
+        return Ok(synced);
+    }
+    if node.node_id == group.primary_node_id && !group.primary_addresses.is_empty() {
+        return Ok(group.primary_addresses.clone());
+    }
+
+    let access_host = node.access_host.trim().trim_end_matches('.');
+    if access_host.is_empty() || access_host.eq_ignore_ascii_case(&group.hostname) {
+        return Err(format!(
+            "no address known for node {}; enable its DDNS or set primary_addresses",
+            node.node_id
+        ));
+    }
+    if let Ok(ip) = access_host.parse::<IpAddr>() {
+        return Ok(vec![ip]);
+    }
+    let addresses = tokio::net::lookup_host((access_host, 0))
+        .await
+        .map_err(|err| format!("resolve {access_host}: {err}"))?
+        .map(|addr| addr.ip())
+        .collect::<Vec<_>>();
+    if addresses.is_empty() {
+        return Err(format!("{access_host} has no addresses"));
+    }
+    Ok(addresses)
+}
+
+#[derive(Deserialize)]
+struct RemoteRuntime {
+    components: Vec<ComponentRuntimeStatus>,
+}
+
+pub struct DnsFailoverWorker {
+    pub config: Arc<Config>,
+    pub cluster_id: String,
+    pub node_id: String,
+    pub cluster_ca_key_pem: String,
+    pub cluster_ca_pem: String,
+    pub store: Arc<Mutex<JsonSnapshotStore>>,
+    pub raft: Arc<dyn RaftFacade>,
+    pub node_runtime: NodeRuntimeHandle,
+    pub mesh_client: MeshAwareHttpClient,
+}
+
+pub fn spawn_dns_failover_worker(worker: DnsFailoverWorker) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(WORKER_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut tracker = FailoverTracker::default();
+        loop {
+            ticker.tick().await;
+            let is_leader = matches!(
+                worker.raft.metrics().borrow().state,
+                openraft::ServerState::Leader
+            );
+            if !is_leader {
+                tracker = FailoverTracker::default();
+                continue;
+            }
+            worker.tick(&mut tracker).await;
+        }
+    })
+}
+
+impl DnsFailoverWorker {
+    async fn tick(&self, tracker: &mut FailoverTracker) {
+        let (groups, nodes, endpoints, probe_history) = {
+            let store = self.store.lock().await;
+            let state = store.state();
+            (
+                state.dns_failover_groups.clone(),
+                state.nodes.clone(),
+                store.list_endpoints(),
+                state.endpoint_probe_history.clone(),
+            )
+        };
+        tracker.retain_groups(&groups);
+        if groups.is_empty() {
+            tracker.observe(BTreeMap::new());
+            return;
+        }
+
+        let now = Utc::now();
+        let mut runtimes = BTreeMap::new();
+        let mut samples = BTreeMap::new();
+        for node_id in groups.values().flat_map(DnsFailoverGroup::member_node_ids) {
+            if runtimes.contains_key(node_id) {
+                continue;
+            }
+            let Some(node) = nodes.get(node_id) else {
+                continue;
+            };
+            let components = self.fetch_components(node, &endpoints).await;
+            samples.insert(
+                node_id.to_string(),
+                node_problem(
+                    node_id,
+                    components.as_deref(),
+                    &endpoints,
+                    &probe_history,
+                    now,
+                ),
+            );
+            runtimes.insert(node_id.to_string(), components);
+        }
+        tracker.observe(samples);
+
+        for group in groups.values() {
+            let candidates = group
+                .standby_node_ids
+                .iter()
+                .map(String::as_str)
+                .filter(|node_id| nodes.contains_key(*node_id))
+                .filter(|node_id| {
+                    has_equivalent_endpoints(&endpoints, &group.primary_node_id, node_id)
+                })
+                .collect::<Vec<_>>();
+            let Some(decision) = tracker.decide(group, &candidates) else {
+                continue;
+            };
+            let target = decision.target_node_id();
+            let Some(node) = nodes.get(target) else {
+                continue;
+            };
+            let components = runtimes.get(target).and_then(Option::as_deref);
+            let published = match node_addresses(group, node, components).await {
+                Ok(addresses) => {
+                    crate::ddns::publish_host_records(&self.config, &group.hostname, &addresses)
+                        .await
+                }
+                Err(message) => Err(message),
+            };
+            match published {
+                Ok(changed) => {
+                    tracker.commit(&group.group_id, target);
+                    self.record_decision(group, &decision, changed).await;
+                }
+                Err(error) => {
+                    warn!(
+                        group_id = %group.group_id,
+                        hostname = %group.hostname,
+                        target_node_id = %target,
+                        %error,
+                        "dns failover update failed"
+                    );
+                }
+            }
+        }
+    }
+
+    async fn fetch_components(
+        &self,
+        node: &Node,
+        endpoints: &[Endpoint],
+    ) -> Option<Vec<ComponentRuntimeStatus>> {
+        if node.node_id == self.node_id {
+            return Some(self.node_runtime.snapshot(0).await.components);
+        }
+        let response = self
+            .mesh_client
+            .send_peer_request(
+                &peer_target_from_node(node, endpoints),
+                MeshRequest {
+                    method: reqwest::Method::GET,
+                    path_and_query: "/api/admin/_internal/nodes/runtime/local?events_limit=0"
+                        .to_string(),
+                    content_type: None,
+                    body: Vec::new(),
+                    total_budget: Duration::from_secs(10),
+                    allow_ambiguous_fallback: true,
+                    request_id: crate::id::new_ulid_string(),
+                    route: InternalRoute::MeshV2,
+                    cluster_id: self.cluster_id.clone(),
+                    sender_id: self.node_id.clone(),
+                    updates_active_path: true,
+                },
+                &self.cluster_ca_key_pem,
+                &self.cluster_ca_pem,
+            )
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response
+            .json::<RemoteRuntime>()
+            .await
+            .ok()
+            .map(|runtime| runtime.components)
+    }
+
+    async fn record_decision(
+        &self,
+        group: &DnsFailoverGroup,
+        decision: &FailoverDecision,
+        changed: bool,
+    ) {
+        let (kind, message) = match decision {
+            FailoverDecision::Initialize { node_id } => {
+                if !changed {
+                    return;
+                }
+                let kind = if *node_id == group.primary_node_id {
+                    NodeRuntimeEventKind::FailoverRestored
+                } else {
+                    NodeRuntimeEventKind::FailoverActivated
+                };
+                (
+                    kind,
+                    format!("dns failover {}: now {node_id}", group.hostname),
+                )
+            }
+            FailoverDecision::Activate {
+                from_node_id,
+                to_node_id,
+                reason,
+            } => (
+                NodeRuntimeEventKind::FailoverActivated,
+                format!(
+                    "dns failover {}: {from_node_id} -> {to_node_id} ({reason})",
+                    group.hostname
+                ),
+            ),
+            FailoverDecision::Restore {
+                from_node_id,
+                to_node_id,
+            } => (
+                NodeRuntimeEventKind::FailoverRestored,
+                format!(
+                    "dns failover {}: {from_node_id} -> {to_node_id} (primary recovered)",
+                    group.hostname
+                ),
+            ),
+        };
+        info!(group_id = %group.group_id, %message, "dns failover record moved");
+        self.node_runtime
+            .record_event(RuntimeComponent::Ddns, kind, message)
+            .await;
+    }
+}
@@ -0,0 +1,382 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::{
+    domain::{EndpointKind, NodeQuotaReset},
+    endpoint_probe::format_hour_key,
+    state::{DesiredStateCommand, EndpointProbeHour, EndpointProbeNodeSample, PersistedState},
+};
+
+fn group() -> DnsFailoverGroup {
+    DnsFailoverGroup {
+        group_id: "group-1".to_string(),
+        hostname: "edge.example.com".to_string(),
+        primary_node_id: "node-a".to_string(),
+        standby_node_ids: vec!["node-b".to_string(), "node-c".to_string()],
+        primary_addresses: Vec::new(),
+    }
+}
+
+fn node(node_id: &str, access_host: &str) -> Node {
+    Node {
+        node_id: node_id.to_string(),
+        node_name: node_id.to_string(),
+        access_host: access_host.to_string(),
+        api_base_url: format!("https://{node_id}.example.com"),
+        quota_limit_bytes: 0,
+        quota_reset: NodeQuotaReset::default(),
+    }
+}
+
+fn endpoint(endpoint_id: &str, node_id: &str, port: u16, meta: serde_json::Value) -> Endpoint {
+    Endpoint {
+        endpoint_id: endpoint_id.to_string(),
+        node_id: node_id.to_string(),
+        tag: format!("tag-{endpoint_id}"),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port,
+        meta,
+    }
+}
+
+fn component(component: RuntimeComponent, status: RuntimeStatus) -> ComponentRuntimeStatus {
+    ComponentRuntimeStatus {
+        component,
+        status,
+        last_ok_at: None,
+        last_fail_at: None,
+        down_since: None,
+        consecutive_failures: 0,
+        recoveries_observed: 0,
+        restart_attempts: 0,
+        last_restart_at: None,
+        last_restart_fail_at: None,
+        last_sync_at: None,
+        current_ipv4: None,
+        current_ipv6: None,
+        fast_mode_until: None,
+        last_error: None,
+    }
+}
+
+fn probe_history(hour: DateTime<Utc>, ok: bool) -> EndpointProbeHistory {
+    let sample = EndpointProbeNodeSample {
+        ok,
+        skipped: false,
+        checked_at: format_hour_key(hour),
+        latency_ms: None,
+        target_id: None,
+        target_url: None,
+        error: None,
+        targets: Vec::new(),
+        config_hash: "hash".to_string(),
+    };
+    EndpointProbeHistory {
+        hours: BTreeMap::from([(
+            format_hour_key(hour),
+            EndpointProbeHour {
+                by_node: BTreeMap::from([("prober".to_string(), sample)]),
+            },
+        )]),
+    }
+}
+
+fn round(tracker: &mut FailoverTracker, unhealthy: &[&str]) {
+    tracker.observe(
+        ["node-a", "node-b", "node-c"]
+            .into_iter()
+            .map(|node_id| {
+                let problem = unhealthy
+                    .contains(&node_id)
+                    .then(|| "xray is down".to_string());
+                (node_id.to_string(), problem)
+            })
+            .collect(),
+    );
+}
+
+#[test]
+fn validate_rejects_unusable_groups() {
+    assert_eq!(group().validate(), Ok(()));
+
+    let mut no_standby = group();
+    no_standby.standby_node_ids.clear();
+    let mut primary_as_standby = group();
+    primary_as_standby
+        .standby_node_ids
+        .push("node-a".to_string());
+    let mut duplicate = group();
+    duplicate.standby_node_ids.push("node-b".to_string());
+    let mut bad_hostname = group();
+    bad_hostname.hostname = "Edge.example.com".to_string();
+    let mut two_ipv4 = group();
+    two_ipv4.primary_addresses = vec![
+        "203.0.113.10".parse().unwrap(),
+        "203.0.113.11".parse().unwrap(),
+    ];
+    let invalid_groups = [
+        no_standby,
+        primary_as_standby,
+        duplicate,
+        bad_hostname,
+        two_ipv4,
+    ];
+    for invalid in invalid_groups {
+        assert!(invalid.validate().is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn equivalent_endpoints_require_matching_kind_port_and_pinned_keys() {
+    let reality = |public_key: &str, private_key: &str| {
+        json!({
+            "reality_keys": { "public_key": public_key, "private_key": private_key },
+            "active_short_id": "01",
+        })
+    };
+    let meta = reality("pk", "a");
+    let other_private = reality("pk", "b");
+    let other_public = reality("pk2", "a");
+    let endpoints = vec![
+        endpoint("a-1", "node-a", 443, meta.clone()),
+        endpoint("b-1", "node-b", 443, other_private),
+        endpoint("c-1", "node-c", 443, other_public),
+        endpoint("d-1", "node-d", 8443, meta),
+    ];
+
+    assert!(has_equivalent_endpoints(&endpoints, "node-a", "node-b"));
+    assert!(!has_equivalent_endpoints(&endpoints, "node-a", "node-c"));
+    assert!(!has_equivalent_endpoints(&endpoints, "node-a", "node-d"));
+    // A primary without endpoints has nothing a standby could be equivalent to.
+    assert!(!has_equivalent_endpoints(&endpoints, "node-e", "node-b"));
+}
+
+#[test]
+fn node_problem_reports_unreachable_xray_down_and_failing_probes() {
+    let now = Utc::now();
+    let endpoints = vec![
+        endpoint("a-1", "node-a", 443, json!({})),
+        endpoint("a-2", "node-a", 8443, json!({})),
+    ];
+    let xray_up = [component(RuntimeComponent::Xray, RuntimeStatus::Up)];
+    let xray_down = [component(RuntimeComponent::Xray, RuntimeStatus::Down)];
+    let no_history = BTreeMap::new();
+
+    assert_eq!(
+        node_problem("node-a", None, &endpoints, &no_history, now).as_deref(),
+        Some("node runtime unreachable")
+    );
+    assert_eq!(
+        node_problem("node-a", Some(&xray_down), &endpoints, &no_history, now).as_deref(),
+        Some("xray is down")
+    );
+    assert_eq!(
+        node_problem("node-a", Some(&xray_up), &endpoints, &no_history, now),
+        None
+    );
+
+    let all_failing = BTreeMap::from([
+        ("a-1".to_string(), probe_history(now, false)),
+        ("a-2".to_string(), probe_history(now, false)),
+    ]);
+    assert_eq!(
+        node_problem("node-a", Some(&xray_up), &endpoints, &all_failing, now).as_deref(),
+        Some("endpoint probes failing")
+    );
+
+    let one_ok = BTreeMap::from([
+        ("a-1".to_string(), probe_history(now, false)),
+        ("a-2".to_string(), probe_history(now, true)),
+    ]);
+    assert_eq!(
+        node_problem("node-a", Some(&xray_up), &endpoints, &one_ok, now),
+        None
+    );
+
+    let stale = BTreeMap::from([
+        (
+            "a-1".to_string(),
+            probe_history(now - chrono::Duration::hours(5), false),
+        ),
+        (
+            "a-2".to_string(),
+            probe_history(now - chrono::Duration::hours(5), false),
+        ),
+    ]);
+    assert_eq!(
+        node_problem("node-a", Some(&xray_up), &endpoints, &stale, now),
+        None
+    );
+}
+
+#[test]
+fn tracker_fails_over_and_restores_with_hysteresis() {
+    let group = group();
+    let candidates = ["node-b", "node-c"];
+    let mut tracker = FailoverTracker::default();
+
+    // A fresh leader waits for a stable primary before touching the record.
+    for _ in 1..RESTORE_AFTER_ROUNDS {
+        round(&mut tracker, &[]);
+        assert_eq!(tracker.decide(&group, &candidates), None);
+    }
+    round(&mut tracker, &[]);
+    assert_eq!(
+        tracker.decide(&group, &candidates),
+        Some(FailoverDecision::Initialize {
+            node_id: "node-a".to_string()
+        })
+    );
+    tracker.commit(&group.group_id, "node-a");
+
+    for _ in 1..FAIL_AFTER_ROUNDS {
+        round(&mut tracker, &["node-a"]);
+        assert_eq!(tracker.decide(&group, &candidates), None);
+    }
+    round(&mut tracker, &["node-a", "node-b"]);
+    assert_eq!(
+        tracker.decide(&group, &candidates),
+        Some(FailoverDecision::Activate {
+            from_node_id: "node-a".to_string(),
+            to_node_id: "node-c".to_string(),
+            reason: "xray is down".to_string(),
+        })
+    );
+    tracker.commit(&group.group_id, "node-c");
+
+    // A primary that flaps back for fewer than the restore rounds keeps the standby.
+    for _ in 1..RESTORE_AFTER_ROUNDS {
+        round(&mut tracker, &[]);
+        assert_eq!(tracker.decide(&group, &candidates), None);
+    }
+    round(&mut tracker, &["node-a"]);
+    assert_eq!(tracker.decide(&group, &candidates), None);
+
+    for _ in 1..RESTORE_AFTER_ROUNDS {
+        round(&mut tracker, &[]);
+    }
+    round(&mut tracker, &[]);
+    assert_eq!(
+        tracker.decide(&group, &candidates),
+        Some(FailoverDecision::Restore {
+            from_node_id: "node-c".to_string(),
+            to_node_id: "node-a".to_string(),
+        })
+    );
+}
+
+#[test]
+fn tracker_moves_off_a_failing_or_ineligible_standby() {
+    let group = group();
+    let mut tracker = FailoverTracker::default();
+    tracker.commit(&group.group_id, "node-b");
+
+    for _ in 0..FAIL_AFTER_ROUNDS {
+        round(&mut tracker, &["node-a", "node-b"]);
+    }
+    assert_eq!(
+        tracker.decide(&group, &["node-b", "node-c"]),
+        Some(FailoverDecision::Activate {
+            from_node_id: "node-b".to_string(),
+            to_node_id: "node-c".to_string(),
+            reason: "xray is down".to_string(),
+        })
+    );
+
+    // No healthy standby left: the record stays where it is.
+    round(&mut tracker, &["node-a", "node-b", "node-c"]);
+    assert_eq!(tracker.decide(&group, &["node-b", "node-c"]), None);
+
+    round(&mut tracker, &["node-a"]);
+    assert_eq!(
+        tracker.decide(&group, &["node-c"]),
+        Some(FailoverDecision::Activate {
+            from_node_id: "node-b".to_string(),
+            to_node_id: "node-c".to_string(),
+            reason: "no longer an eligible standby".to_string(),
+        })
+    );
+}
+
+#[test]
+fn deleting_a_node_prunes_failover_groups() {
+    let mut state = PersistedState::empty();
+    for node_id in ["node-a", "node-b", "node-c"] {
+        DesiredStateCommand::UpsertNode {
+            node: node(node_id, &format!("{node_id}.example.com")),
+            join_session: None,
+        }
+        .apply(&mut state)
+        .unwrap();
+    }
+    DesiredStateCommand::UpsertDnsFailoverGroup { group: group() }
+        .apply(&mut state)
+        .unwrap();
+
+    DesiredStateCommand::DeleteNode {
+        node_id: "node-b".to_string(),
+        expected_endpoint_ids: Vec::new(),
+        delete_endpoints: false,
+        join_session: None,
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert_eq!(
+        state.dns_failover_groups["group-1"].standby_node_ids,
+        vec!["node-c".to_string()]
+    );
+
+    DesiredStateCommand::DeleteNode {
+        node_id: "node-c".to_string(),
+        expected_endpoint_ids: Vec::new(),
+        delete_endpoints: false,
+        join_session: None,
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert!(state.dns_failover_groups.is_empty());
+}
+
+#[tokio::test]
+async fn node_addresses_fall_back_to_primary_addresses_without_ddns() {
+    // DDNS is disabled on the primary and its access_host is the failover hostname.
+    let primary = node("node-a", "edge.example.com");
+    let ddns_disabled = [component(RuntimeComponent::Ddns, RuntimeStatus::Disabled)];
+    let err = node_addresses(&group(), &primary, Some(&ddns_disabled))
+        .await
+        .unwrap_err();
+    assert!(err.contains("no address known for node node-a"), "{err}");
+
+    let mut with_addresses = group();
+    with_addresses.primary_addresses = vec![
+        "203.0.113.10".parse().unwrap(),
+        "2001:db8::10".parse().unwrap(),
+    ];
+    assert_eq!(
+        node_addresses(&with_addresses, &primary, Some(&ddns_disabled))
+            .await
+            .unwrap(),
+        with_addresses.primary_addresses
+    );
+
+    // Public IPs the primary's DDNS worker reports take precedence over the static ones.
+    let mut ddns_up = component(RuntimeComponent::Ddns, RuntimeStatus::Up);
+    ddns_up.current_ipv4 = Some("198.51.100.7".to_string());
+    assert_eq!(
+        node_addresses(&with_addresses, &primary, Some(&[ddns_up]))
+            .await
+            .unwrap(),
+        vec!["198.51.100.7".parse::<IpAddr>().unwrap()]
+    );
+
+    // Standbys resolve their own access_host; primary_addresses never applies to them.
+    let standby = node("node-b", "198.51.100.20");
+    assert_eq!(
+        node_addresses(&with_addresses, &standby, None)
+            .await
+            .unwrap(),
+        vec!["198.51.100.20".parse::<IpAddr>().unwrap()]
+    );
+}
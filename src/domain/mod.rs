@@ -1,7 +1,11 @@
 use std::collections::BTreeSet;
 
+use base64::Engine as _;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc, Weekday};
 use serde::{Deserialize, Serialize};
 
+use crate::cycle::{CycleSchedule, CycleTimeZone};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DomainError {
     InvalidPort {
@@ -10,6 +14,9 @@ pub enum DomainError {
     InvalidCycleDayOfMonth {
         day_of_month: u8,
     },
+    InvalidCycleLengthDays {
+        length_days: u16,
+    },
     InvalidTzOffsetMinutes {
         tz_offset_minutes: i16,
     },
@@ -42,6 +49,10 @@ pub enum DomainError {
         server_name: String,
         reason: String,
     },
+    InvalidServerName {
+        server_name: String,
+        reason: String,
+    },
     InvalidAcceptedAuthority {
         authority: String,
         reason: String,
@@ -62,6 +73,57 @@ pub enum DomainError {
         endpoint_id: String,
         node_id: String,
     },
+    UnsupportedSs2022Method {
+        method: String,
+    },
+    InvalidUserExpiresAt {
+        expires_at: String,
+        reason: String,
+    },
+    AdminPrincipalNotFound {
+        principal_id: String,
+    },
+    TrafficTopUpNotFound {
+        topup_id: String,
+    },
+    InvalidTrafficTopUp {
+        reason: String,
+    },
+    SubscriptionTokenNotFound {
+        token_id: String,
+    },
+    InvalidSubscriptionToken {
+        reason: String,
+    },
+    InvalidMaxConcurrentIps {
+        max_concurrent_ips: u32,
+    },
+    InvalidPinnedCredentials {
+        reason: String,
+    },
+    InvalidEndpointEgress {
+        endpoint_id: String,
+        reason: String,
+    },
+    InvalidEndpointProbeTargets {
+        reason: String,
+    },
+    DnsFailoverGroupNotFound {
+        group_id: String,
+    },
+    InvalidDnsFailoverGroup {
+        group_id: String,
+        reason: String,
+    },
+    RestoreConflict {
+        reason: String,
+    },
+    CredentialRotationNotFound {
+        rotation_id: String,
+    },
+    CredentialRotationConflict {
+        reason: String,
+    },
 }
 
 impl DomainError {
@@ -69,22 +131,40 @@ impl DomainError {
         match self {
             Self::InvalidPort { .. }
             | Self::InvalidCycleDayOfMonth { .. }
+            | Self::InvalidCycleLengthDays { .. }
             | Self::InvalidTzOffsetMinutes { .. }
             | Self::InvalidNodeQuotaConfig { .. }
             | Self::MissingUser { .. }
             | Self::MissingNode { .. }
             | Self::MissingEndpoint { .. } => "invalid_request",
-            Self::RealityDomainNotFound { .. } => "not_found",
+            Self::RealityDomainNotFound { .. }
+            | Self::AdminPrincipalNotFound { .. }
+            | Self::TrafficTopUpNotFound { .. }
+            | Self::SubscriptionTokenNotFound { .. }
+            | Self::DnsFailoverGroupNotFound { .. }
+            | Self::CredentialRotationNotFound { .. } => "not_found",
             Self::NodeInUse { .. }
             | Self::NodeEndpointSetChanged { .. }
             | Self::NodeLifecycleOperationActive { .. }
             | Self::EndpointChanged { .. } => "conflict",
-            Self::RealityDomainNameConflict { .. } => "conflict",
+            Self::RealityDomainNameConflict { .. }
+            | Self::RestoreConflict { .. }
+            | Self::CredentialRotationConflict { .. } => "conflict",
             Self::InvalidRealityServerName { .. }
+            | Self::InvalidServerName { .. }
             | Self::InvalidAcceptedAuthority { .. }
             | Self::VlessRealityServerNamesEmpty { .. }
             | Self::RealityDomainsReorderInvalid { .. }
-            | Self::RealityDomainsWouldBreakEndpoint { .. } => "invalid_request",
+            | Self::RealityDomainsWouldBreakEndpoint { .. }
+            | Self::UnsupportedSs2022Method { .. }
+            | Self::InvalidUserExpiresAt { .. }
+            | Self::InvalidTrafficTopUp { .. }
+            | Self::InvalidSubscriptionToken { .. }
+            | Self::InvalidMaxConcurrentIps { .. }
+            | Self::InvalidPinnedCredentials { .. }
+            | Self::InvalidEndpointEgress { .. }
+            | Self::InvalidEndpointProbeTargets { .. }
+            | Self::InvalidDnsFailoverGroup { .. } => "invalid_request",
         }
     }
 }
@@ -96,6 +176,10 @@ impl std::fmt::Display for DomainError {
             Self::InvalidCycleDayOfMonth { day_of_month } => {
                 write!(f, "invalid cycle_day_of_month: {day_of_month}")
             }
+            Self::InvalidCycleLengthDays { length_days } => write!(
+                f,
+                "invalid cycle length_days: {length_days} (expected 1-{MAX_CYCLE_LENGTH_DAYS})"
+            ),
             Self::InvalidTzOffsetMinutes { tz_offset_minutes } => {
                 write!(f, "invalid tz_offset_minutes: {tz_offset_minutes}")
             }
@@ -130,6 +214,12 @@ impl std::fmt::Display for DomainError {
             } => {
                 write!(f, "invalid reality server_name: {server_name} ({reason})")
             }
+            Self::InvalidServerName {
+                server_name,
+                reason,
+            } => {
+                write!(f, "invalid server_name: {server_name} ({reason})")
+            }
             Self::InvalidAcceptedAuthority { authority, reason } => {
                 write!(f, "invalid accepted authority: {authority} ({reason})")
             }
@@ -153,6 +243,56 @@ impl std::fmt::Display for DomainError {
                 f,
                 "reality domains would break global endpoint: endpoint_id={endpoint_id} node_id={node_id}"
             ),
+            Self::UnsupportedSs2022Method { method } => {
+                write!(f, "unsupported ss2022 method: {method}")
+            }
+            Self::InvalidUserExpiresAt { expires_at, reason } => {
+                write!(f, "invalid user expires_at: {expires_at} ({reason})")
+            }
+            Self::AdminPrincipalNotFound { principal_id } => {
+                write!(f, "admin principal not found: {principal_id}")
+            }
+            Self::TrafficTopUpNotFound { topup_id } => {
+                write!(f, "traffic top-up not found: {topup_id}")
+            }
+            Self::InvalidTrafficTopUp { reason } => write!(f, "invalid traffic top-up: {reason}"),
+            Self::SubscriptionTokenNotFound { token_id } => {
+                write!(f, "subscription token not found: {token_id}")
+            }
+            Self::InvalidSubscriptionToken { reason } => {
+                write!(f, "invalid subscription token: {reason}")
+            }
+            Self::InvalidMaxConcurrentIps { max_concurrent_ips } => write!(
+                f,
+                "invalid max_concurrent_ips: {max_concurrent_ips} (expected at least 1)"
+            ),
+            Self::InvalidPinnedCredentials { reason } => {
+                write!(f, "invalid pinned credentials: {reason}")
+            }
+            Self::InvalidEndpointEgress {
+                endpoint_id,
+                reason,
+            } => write!(
+                f,
+                "invalid endpoint egress: endpoint_id={endpoint_id} ({reason})"
+            ),
+            Self::InvalidEndpointProbeTargets { reason } => {
+                write!(f, "invalid endpoint probe targets: {reason}")
+            }
+            Self::DnsFailoverGroupNotFound { group_id } => {
+                write!(f, "dns failover group not found: {group_id}")
+            }
+            Self::InvalidDnsFailoverGroup { group_id, reason } => write!(
+                f,
+                "invalid dns failover group: group_id={group_id} ({reason})"
+            ),
+            Self::RestoreConflict { reason } => write!(f, "backup restore conflict: {reason}"),
+            Self::CredentialRotationNotFound { rotation_id } => {
+                write!(f, "credential rotation not found: {rotation_id}")
+            }
+            Self::CredentialRotationConflict { reason } => {
+                write!(f, "credential rotation conflict: {reason}")
+            }
         }
     }
 }
@@ -173,6 +313,63 @@ pub fn validate_cycle_day_of_month(day_of_month: u8) -> Result<(), DomainError>
     Ok(())
 }
 
+pub fn validate_max_concurrent_ips(max_concurrent_ips: Option<u32>) -> Result<(), DomainError> {
+    if let Some(max_concurrent_ips) = max_concurrent_ips
+        && max_concurrent_ips == 0
+    {
+        return Err(DomainError::InvalidMaxConcurrentIps { max_concurrent_ips });
+    }
+    Ok(())
+}
+
+pub fn validate_pinned_credentials(pinned: &UserPinnedCredentials) -> Result<(), DomainError> {
+    if let Some(uuid) = pinned.vless_uuid.as_deref()
+        && uuid::Uuid::parse_str(uuid)
+            .ok()
+            .map(|parsed| parsed.hyphenated().to_string())
+            .as_deref()
+            != Some(uuid)
+    {
+        return Err(DomainError::InvalidPinnedCredentials {
+            reason: format!("vless_uuid must be a lowercase hyphenated UUID, got {uuid}"),
+        });
+    }
+    if let Some(psk) = pinned.ss2022_psk_b64.as_deref() {
+        let len = base64::engine::general_purpose::STANDARD
+            .decode(psk)
+            .map(|bytes| bytes.len())
+            .unwrap_or(0);
+        if len != 16 && len != 32 {
+            return Err(DomainError::InvalidPinnedCredentials {
+                reason: "ss2022_psk_b64 must be standard base64 of 16 or 32 bytes".to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub const MAX_CYCLE_LENGTH_DAYS: u16 = 366;
+
+pub fn validate_cycle_length_days(length_days: u16) -> Result<(), DomainError> {
+    if !(1..=MAX_CYCLE_LENGTH_DAYS).contains(&length_days) {
+        return Err(DomainError::InvalidCycleLengthDays { length_days });
+    }
+    Ok(())
+}
+
+/// Parses an RFC3339 `expires_at` and returns it normalized to UTC seconds precision.
+pub fn normalize_user_expires_at(expires_at: &str) -> Result<String, DomainError> {
+    let parsed = DateTime::parse_from_rfc3339(expires_at.trim()).map_err(|e| {
+        DomainError::InvalidUserExpiresAt {
+            expires_at: expires_at.to_string(),
+            reason: e.to_string(),
+        }
+    })?;
+    Ok(parsed
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
 pub fn validate_tz_offset_minutes(tz_offset_minutes: i16) -> Result<(), DomainError> {
     // UTC-12 .. UTC+14
     if !(-720..=840).contains(&tz_offset_minutes) {
@@ -187,6 +384,9 @@ pub enum EndpointKind {
     VlessRealityVisionTcp,
     #[serde(rename = "ss2022_2022_blake3_aes_128_gcm")]
     Ss2022_2022Blake3Aes128Gcm,
+    TrojanRealityTcp,
+    TrojanTlsTcp,
+    Hysteria2,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -197,6 +397,32 @@ pub enum QuotaResetSource {
     Node,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaWeekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<QuotaWeekday> for Weekday {
+    fn from(value: QuotaWeekday) -> Self {
+        match value {
+            QuotaWeekday::Monday => Self::Mon,
+            QuotaWeekday::Tuesday => Self::Tue,
+            QuotaWeekday::Wednesday => Self::Wed,
+            QuotaWeekday::Thursday => Self::Thu,
+            QuotaWeekday::Friday => Self::Fri,
+            QuotaWeekday::Saturday => Self::Sat,
+            QuotaWeekday::Sunday => Self::Sun,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "policy", rename_all = "snake_case")]
 pub enum UserQuotaReset {
@@ -207,6 +433,19 @@ pub enum UserQuotaReset {
         day_of_month: u8,
         tz_offset_minutes: i16,
     },
+    Weekly {
+        weekday: QuotaWeekday,
+        tz_offset_minutes: i16,
+    },
+    Daily {
+        tz_offset_minutes: i16,
+    },
+    /// Back-to-back `length_days` windows, one of which starts on `anchor` (`YYYY-MM-DD`).
+    FixedDays {
+        anchor: NaiveDate,
+        length_days: u16,
+        tz_offset_minutes: i16,
+    },
 }
 
 impl Default for UserQuotaReset {
@@ -218,6 +457,46 @@ impl Default for UserQuotaReset {
     }
 }
 
+impl UserQuotaReset {
+    /// `None` for `Unlimited`.
+    pub fn cycle_schedule(&self) -> Option<CycleSchedule> {
+        match self {
+            Self::Unlimited { .. } => None,
+            Self::Monthly { day_of_month, .. } => Some(CycleSchedule::Monthly {
+                day_of_month: *day_of_month,
+            }),
+            Self::Weekly { weekday, .. } => Some(CycleSchedule::Weekly {
+                weekday: (*weekday).into(),
+            }),
+            Self::Daily { .. } => Some(CycleSchedule::Daily),
+            Self::FixedDays {
+                anchor,
+                length_days,
+                ..
+            } => Some(CycleSchedule::FixedDays {
+                anchor: *anchor,
+                length_days: *length_days,
+            }),
+        }
+    }
+
+    pub fn tz_offset_minutes(&self) -> i16 {
+        match self {
+            Self::Unlimited { tz_offset_minutes }
+            | Self::Monthly {
+                tz_offset_minutes, ..
+            }
+            | Self::Weekly {
+                tz_offset_minutes, ..
+            }
+            | Self::Daily { tz_offset_minutes }
+            | Self::FixedDays {
+                tz_offset_minutes, ..
+            } => *tz_offset_minutes,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "policy", rename_all = "snake_case")]
 pub enum NodeQuotaReset {
@@ -230,6 +509,22 @@ pub enum NodeQuotaReset {
         #[serde(default)]
         tz_offset_minutes: Option<i16>,
     },
+    Weekly {
+        weekday: QuotaWeekday,
+        #[serde(default)]
+        tz_offset_minutes: Option<i16>,
+    },
+    Daily {
+        #[serde(default)]
+        tz_offset_minutes: Option<i16>,
+    },
+    /// Back-to-back `length_days` windows, one of which starts on `anchor` (`YYYY-MM-DD`).
+    FixedDays {
+        anchor: NaiveDate,
+        length_days: u16,
+        #[serde(default)]
+        tz_offset_minutes: Option<i16>,
+    },
 }
 
 impl Default for NodeQuotaReset {
@@ -241,6 +536,54 @@ impl Default for NodeQuotaReset {
     }
 }
 
+impl NodeQuotaReset {
+    /// `None` for `Unlimited`.
+    pub fn cycle_schedule(&self) -> Option<CycleSchedule> {
+        match self {
+            Self::Unlimited { .. } => None,
+            Self::Monthly { day_of_month, .. } => Some(CycleSchedule::Monthly {
+                day_of_month: *day_of_month,
+            }),
+            Self::Weekly { weekday, .. } => Some(CycleSchedule::Weekly {
+                weekday: (*weekday).into(),
+            }),
+            Self::Daily { .. } => Some(CycleSchedule::Daily),
+            Self::FixedDays {
+                anchor,
+                length_days,
+                ..
+            } => Some(CycleSchedule::FixedDays {
+                anchor: *anchor,
+                length_days: *length_days,
+            }),
+        }
+    }
+
+    /// `None` means the node's local time zone.
+    pub fn tz_offset_minutes(&self) -> Option<i16> {
+        match self {
+            Self::Unlimited { tz_offset_minutes }
+            | Self::Monthly {
+                tz_offset_minutes, ..
+            }
+            | Self::Weekly {
+                tz_offset_minutes, ..
+            }
+            | Self::Daily { tz_offset_minutes }
+            | Self::FixedDays {
+                tz_offset_minutes, ..
+            } => *tz_offset_minutes,
+        }
+    }
+
+    pub fn cycle_time_zone(&self) -> CycleTimeZone {
+        match self.tz_offset_minutes() {
+            Some(tz_offset_minutes) => CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes },
+            None => CycleTimeZone::Local,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Node {
     pub node_id: String,
@@ -280,9 +623,51 @@ pub struct User {
     pub priority_tier: UserPriorityTier,
     #[serde(default)]
     pub quota_reset: UserQuotaReset,
+    /// RFC3339 instant after which the user's clients are removed from Xray.
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// Administrative disable; credentials are kept so re-enabling restores access as-is.
+    #[serde(default)]
+    pub disabled: bool,
+    /// Distinct source IPs allowed online at once on each node; `None` means no limit.
+    #[serde(default)]
+    pub max_concurrent_ips: Option<u32>,
+    #[serde(default, skip_serializing_if = "UserPinnedCredentials::is_empty")]
+    pub pinned_credentials: UserPinnedCredentials,
+}
+
+/// Credentials kept verbatim instead of being derived from the cluster CA key, e.g. for clients
+/// migrated from another panel. Rotating the user's credentials drops them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserPinnedCredentials {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vless_uuid: Option<String>,
+    /// Standard base64; used on SS2022 endpoints whose method takes a PSK of the same length.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ss2022_psk_b64: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+impl UserPinnedCredentials {
+    pub fn is_empty(&self) -> bool {
+        self.vless_uuid.is_none() && self.ss2022_psk_b64.is_none()
+    }
+}
+
+impl User {
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the user's memberships should be present on the data plane at `now`.
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        !self.disabled && !self.is_expired_at(now)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum UserPriorityTier {
     P1,
@@ -300,6 +685,76 @@ pub struct UserNodeQuota {
     pub quota_reset_source: QuotaResetSource,
 }
 
+/// Extra bytes bought on top of a user's cycle allowance. They are only drawn once the base
+/// allowance is exhausted and do not reset with the cycle.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrafficTopUp {
+    pub topup_id: String,
+    pub user_id: String,
+    /// Node whose allowance the bytes extend. Consumption is tracked in that node's local usage,
+    /// so a top-up is always bound to exactly one node.
+    pub node_id: String,
+    pub bytes: u64,
+    pub created_at: String,
+    /// RFC3339; unused bytes are dropped after this instant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl TrafficTopUp {
+    pub fn applies_to(&self, user_id: &str, node_id: &str, now: DateTime<Utc>) -> bool {
+        self.user_id == user_id && self.node_id == node_id && !self.is_expired_at(now)
+    }
+
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Subscription formats a secondary token can be limited to, as named by `/api/sub?format=`.
+pub const SUBSCRIPTION_TOKEN_FORMATS: [&str; 5] = ["base64", "raw", "clash", "mihomo", "singbox"];
+
+/// An additional, individually revocable subscription token of a user, e.g. one per device. It
+/// resolves to the same user as `User::subscription_token` but may expire or be narrowed down.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SubscriptionToken {
+    pub token_id: String,
+    pub user_id: String,
+    pub name: String,
+    pub token: String,
+    pub created_at: String,
+    /// RFC3339; the token stops resolving after this instant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// One of `SUBSCRIPTION_TOKEN_FORMATS`; also the default when a fetch names no format.
+    /// `mihomo` covers the Mihomo provider URLs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Only these endpoints are rendered; `None` means every endpoint the user can access.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_ids: Option<BTreeSet<String>>,
+}
+
+impl SubscriptionToken {
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .is_some_and(|expires_at| expires_at <= now)
+    }
+
+    pub fn allows_endpoint(&self, endpoint_id: &str) -> bool {
+        self.endpoint_ids
+            .as_ref()
+            .is_none_or(|endpoint_ids| endpoint_ids.contains(endpoint_id))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RealityDomain {
     pub domain_id: String,
@@ -0,0 +1,310 @@
+//! Per-endpoint egress: where traffic entering an endpoint leaves for the internet.
+//!
+//! By default a node's business inbounds exit directly. An endpoint can instead chain through
+//! another cluster node (entering the peer's managed VLESS endpoint with a derived credential),
+//! an operator-provided SOCKS5 proxy, or an outbound defined in the node's static Xray config
+//! such as a WARP WireGuard outbound.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use hmac::{Hmac, Mac};
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Endpoint, Node},
+    managed_default_endpoints::managed_default_vless_endpoint,
+    state::xray_alternate_email,
+    xray::{
+        builder::{self, ReverseVlessEndpoint},
+        proto::xray as xproto,
+    },
+};
+
+/// Outbound tags owned by egress reconciliation; reconcile never touches other outbounds.
+pub const EGRESS_OUTBOUND_TAG_PREFIX: &str = "xp-egress-";
+/// Xray client emails of peer nodes chaining through a local managed VLESS inbound.
+pub const EGRESS_USER_EMAIL_PREFIX: &str = "egress:";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EndpointEgress {
+    #[default]
+    Direct,
+    /// Leave from another cluster node, entering through its managed VLESS endpoint.
+    Node { node_id: String },
+    Socks {
+        address: String,
+        port: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        username: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
+    /// An outbound the operator defines in the node's static Xray config.
+    XrayOutbound { tag: String },
+}
+
+impl EndpointEgress {
+    /// Checks the parts of the egress that do not depend on cluster state.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Direct => Ok(()),
+            Self::Node { node_id } => {
+                if node_id.trim().is_empty() {
+                    return Err("node_id is required".to_string());
+                }
+                Ok(())
+            }
+            Self::Socks {
+                address,
+                port,
+                username,
+                password,
+            } => {
+                if address.trim().is_empty() || address.chars().any(char::is_whitespace) {
+                    return Err("socks address must be a host name or IP address".to_string());
+                }
+                if *port == 0 {
+                    return Err("socks port must be non-zero".to_string());
+                }
+                match (username, password) {
+                    (None, None) => Ok(()),
+                    (Some(username), Some(password))
+                        if !username.is_empty() && !password.is_empty() =>
+                    {
+                        Ok(())
+                    }
+                    _ => Err("socks username and password must be set together".to_string()),
+                }
+            }
+            Self::XrayOutbound { tag } => {
+                if tag.trim().is_empty() || tag.chars().any(char::is_whitespace) {
+                    return Err("xray outbound tag must be a non-empty word".to_string());
+                }
+                // `api` is the gRPC control outbound, `xp-*` tags belong to reconcile.
+                if tag == "api" || tag.starts_with("xp-") {
+                    return Err(format!("xray outbound tag {tag:?} is reserved"));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Derived VLESS id a node presents when chaining egress through `to_node`.
+pub fn derive_egress_uuid(cluster_ca_key: &str, from_node: &str, to_node: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(cluster_ca_key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(b"xp/egress/v1/");
+    mac.update(format!("uuid:{from_node}:{to_node}").as_bytes());
+    let bytes = mac.finalize().into_bytes();
+    let mut uuid_bytes = [0_u8; 16];
+    uuid_bytes.copy_from_slice(&bytes[..16]);
+    uuid_bytes[6] = (uuid_bytes[6] & 0x0f) | 0x40;
+    uuid_bytes[8] = (uuid_bytes[8] & 0x3f) | 0x80;
+    Uuid::from_bytes(uuid_bytes).to_string()
+}
+
+pub fn egress_user_email(from_node: &str) -> String {
+    format!("{EGRESS_USER_EMAIL_PREFIX}{from_node}")
+}
+
+/// Routing inputs contributed by egress, consumed by `desired_policy_rules`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EgressRouting {
+    /// Local business inbound tag → outbound carrying its traffic; absent means direct.
+    pub outbound_by_inbound: BTreeMap<String, String>,
+    /// Local managed VLESS inbounds admitting chained peers.
+    pub transit_inbound_tags: BTreeSet<String>,
+    /// Client emails of those peers; their traffic always leaves this node directly.
+    pub transit_user_emails: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EgressInboundUserOperation {
+    pub inbound_tag: String,
+    pub operation: xproto::common::serial::TypedMessage,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EgressXrayDesired {
+    pub outbound_requests: Vec<xproto::app::proxyman::command::AddOutboundRequest>,
+    pub owned_outbound_tags: BTreeSet<String>,
+    pub inbound_user_operations: Vec<EgressInboundUserOperation>,
+    /// Managed VLESS inbound tag → `egress:*` emails that should exist on it.
+    pub owned_inbound_user_emails: BTreeMap<String, BTreeSet<String>>,
+    pub routing: EgressRouting,
+}
+
+/// Builds the egress delta for one local node. Pure with respect to Raft state; an endpoint
+/// whose egress cannot be realized (e.g. the peer lost its managed VLESS endpoint) falls back to
+/// direct and is reported in the returned problems.
+///
+/// During a credential rotation chained peers may still derive their id from the other root, so
+/// `alternate_credential_key` admits them under a second `#alt` client.
+pub fn build_egress_desired(
+    local_node_id: &str,
+    cluster_ca_key_pem: &str,
+    alternate_credential_key: Option<&str>,
+    endpoint_egress: &BTreeMap<String, EndpointEgress>,
+    nodes: &[Node],
+    endpoints: &[Endpoint],
+) -> (EgressXrayDesired, Vec<String>) {
+    let mut desired = EgressXrayDesired::default();
+    let mut problems = Vec::new();
+    let endpoint_by_id = endpoints
+        .iter()
+        .map(|endpoint| (endpoint.endpoint_id.as_str(), endpoint))
+        .collect::<BTreeMap<_, _>>();
+    let mut chained_from = BTreeSet::<&str>::new();
+
+    for (endpoint_id, egress) in endpoint_egress {
+        let Some(endpoint) = endpoint_by_id.get(endpoint_id.as_str()) else {
+            continue;
+        };
+        if endpoint.node_id != local_node_id {
+            if matches!(egress, EndpointEgress::Node { node_id } if node_id == local_node_id) {
+                chained_from.insert(endpoint.node_id.as_str());
+            }
+            continue;
+        }
+        let outbound = match egress {
+            EndpointEgress::Direct => continue,
+            EndpointEgress::XrayOutbound { tag } => Ok((tag.clone(), None)),
+            EndpointEgress::Socks {
+                address,
+                port,
+                username,
+                password,
+            } => {
+                let credentials = username.as_deref().zip(password.as_deref());
+                Ok(content_tagged(
+                    "socks",
+                    builder::build_egress_socks_outbound_request("", address, *port, credentials),
+                ))
+            }
+            EndpointEgress::Node { node_id } => {
+                node_outbound(local_node_id, cluster_ca_key_pem, node_id, nodes, endpoints)
+            }
+        };
+        match outbound {
+            Ok((outbound_tag, request)) => {
+                if let Some(request) = request
+                    && desired.owned_outbound_tags.insert(outbound_tag.clone())
+                {
+                    desired.outbound_requests.push(request);
+                }
+                desired
+                    .routing
+                    .outbound_by_inbound
+                    .insert(endpoint.tag.clone(), outbound_tag);
+            }
+            Err(problem) => problems.push(format!("endpoint {endpoint_id}: {problem}")),
+        }
+    }
+
+    if chained_from.is_empty() {
+        return (desired, problems);
+    }
+    for endpoint in endpoints.iter().filter(|endpoint| {
+        endpoint.node_id == local_node_id && managed_default_vless_endpoint(endpoint).is_some()
+    }) {
+        for (email, uuid) in chained_from.iter().flat_map(|from_node| {
+            let email = egress_user_email(from_node);
+            let alternate = alternate_credential_key.map(|key| {
+                let uuid = derive_egress_uuid(key, from_node, local_node_id);
+                (xray_alternate_email(&email), uuid)
+            });
+            let uuid = derive_egress_uuid(cluster_ca_key_pem, from_node, local_node_id);
+            std::iter::once((email, uuid)).chain(alternate)
+        }) {
+            match builder::build_egress_add_user_operation(endpoint, &email, &uuid) {
+                Ok(operation) => {
+                    desired
+                        .inbound_user_operations
+                        .push(EgressInboundUserOperation {
+                            inbound_tag: endpoint.tag.clone(),
+                            operation,
+                        });
+                    desired
+                        .owned_inbound_user_emails
+                        .entry(endpoint.tag.clone())
+                        .or_default()
+                        .insert(email.clone());
+                    desired
+                        .routing
+                        .transit_inbound_tags
+                        .insert(endpoint.tag.clone());
+                    desired.routing.transit_user_emails.insert(email);
+                }
+                Err(error) => problems.push(format!("endpoint {}: {error}", endpoint.endpoint_id)),
+            }
+        }
+    }
+    (desired, problems)
+}
+
+type TaggedOutbound = (
+    String,
+    Option<xproto::app::proxyman::command::AddOutboundRequest>,
+);
+
+fn node_outbound(
+    local_node_id: &str,
+    cluster_ca_key_pem: &str,
+    node_id: &str,
+    nodes: &[Node],
+    endpoints: &[Endpoint],
+) -> Result<TaggedOutbound, String> {
+    let node = nodes
+        .iter()
+        .find(|node| node.node_id == node_id)
+        .ok_or_else(|| format!("egress node {node_id} is missing"))?;
+    let (endpoint, meta) = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.node_id == node_id)
+        .find_map(|endpoint| managed_default_vless_endpoint(endpoint).map(|meta| (endpoint, meta)))
+        .ok_or_else(|| format!("egress node {node_id} has no managed VLESS endpoint"))?;
+    let server_name = meta
+        .reality
+        .server_names
+        .first()
+        .cloned()
+        .ok_or_else(|| format!("egress node {node_id} has no Reality server name"))?;
+    let target = ReverseVlessEndpoint {
+        access_host: node.access_host.clone(),
+        endpoint: endpoint.clone(),
+        target_port: endpoint.port,
+        target_public_key_b64url_nopad: meta.reality_keys.public_key.clone(),
+        target_short_id_hex: meta.active_short_id.clone(),
+        server_name,
+    };
+    let request = builder::build_egress_vless_outbound_request(
+        "",
+        &egress_user_email(local_node_id),
+        &derive_egress_uuid(cluster_ca_key_pem, local_node_id, node_id),
+        &target,
+    )
+    .map_err(|error| error.to_string())?;
+    Ok(content_tagged(&format!("node-{node_id}"), request))
+}
+
+/// Tags an outbound with a hash of its configuration. HandlerService cannot replace an outbound
+/// in place, so a changed upstream gets a new tag and the old one is removed as stale.
+fn content_tagged(
+    kind: &str,
+    mut request: xproto::app::proxyman::command::AddOutboundRequest,
+) -> TaggedOutbound {
+    let hash = hex::encode(Sha256::digest(request.encode_to_vec()));
+    let tag = format!("{EGRESS_OUTBOUND_TAG_PREFIX}{kind}-{}", &hash[..12]);
+    if let Some(outbound) = request.outbound.as_mut() {
+        outbound.tag = tag.clone();
+    }
+    (tag, Some(request))
+}
+
+#[cfg(test)]
+mod tests;
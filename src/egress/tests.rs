@@ -0,0 +1,208 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+use crate::domain::EndpointKind;
+
+const CA_KEY: &str = "xp-test-cluster-ca-key";
+
+fn node(node_id: &str) -> Node {
+    Node {
+        node_id: node_id.to_string(),
+        node_name: node_id.to_string(),
+        access_host: format!("{node_id}.example.test"),
+        api_base_url: format!("https://{node_id}.example.test"),
+        quota_limit_bytes: 0,
+        quota_reset: Default::default(),
+    }
+}
+
+fn endpoint(endpoint_id: &str, node_id: &str, managed_vless: bool) -> Endpoint {
+    let (kind, meta) = if managed_vless {
+        (
+            EndpointKind::VlessRealityVisionTcp,
+            serde_json::json!({
+                "reality": xp_test_fixtures::endpoint_reality(),
+                "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+                "short_ids": xp_test_fixtures::endpoint_short_ids(),
+                "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
+                "managed_default": true
+            }),
+        )
+    } else {
+        (
+            EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+            serde_json::json!({}),
+        )
+    };
+    Endpoint {
+        endpoint_id: endpoint_id.to_string(),
+        node_id: node_id.to_string(),
+        tag: format!("tag-{endpoint_id}"),
+        kind,
+        port: 443,
+        meta,
+    }
+}
+
+fn socks(address: &str) -> EndpointEgress {
+    EndpointEgress::Socks {
+        address: address.to_string(),
+        port: 1080,
+        username: None,
+        password: None,
+    }
+}
+
+#[test]
+fn egress_validation_rejects_incomplete_upstreams() {
+    assert_eq!(EndpointEgress::Direct.validate(), Ok(()));
+    assert_eq!(socks("198.51.100.7").validate(), Ok(()));
+    for invalid in [
+        EndpointEgress::Node {
+            node_id: " ".to_string(),
+        },
+        socks(""),
+        EndpointEgress::Socks {
+            address: "proxy.example.test".to_string(),
+            port: 0,
+            username: None,
+            password: None,
+        },
+        EndpointEgress::Socks {
+            address: "proxy.example.test".to_string(),
+            port: 1080,
+            username: Some("user".to_string()),
+            password: None,
+        },
+        EndpointEgress::XrayOutbound {
+            tag: "api".to_string(),
+        },
+        EndpointEgress::XrayOutbound {
+            tag: "xp-egress-node-n2-abc".to_string(),
+        },
+    ] {
+        assert!(invalid.validate().is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn chained_nodes_get_an_outbound_on_the_source_and_a_user_on_the_target() {
+    let nodes = vec![node("n1"), node("n2")];
+    let endpoints = vec![
+        endpoint("e1", "n1", false),
+        endpoint("e2", "n1", false),
+        endpoint("e3", "n1", false),
+        endpoint("v2", "n2", true),
+    ];
+    let egress = BTreeMap::from([
+        (
+            "e1".to_string(),
+            EndpointEgress::Node {
+                node_id: "n2".to_string(),
+            },
+        ),
+        ("e2".to_string(), socks("198.51.100.7")),
+        ("e3".to_string(), socks("198.51.100.7")),
+    ]);
+
+    let (source, problems) = build_egress_desired("n1", CA_KEY, None, &egress, &nodes, &endpoints);
+    assert!(problems.is_empty(), "{problems:?}");
+    // Identical upstreams share one outbound.
+    assert_eq!(source.outbound_requests.len(), 2);
+    let node_tag = &source.routing.outbound_by_inbound["tag-e1"];
+    assert!(node_tag.starts_with("xp-egress-node-n2-"));
+    let socks_tag = &source.routing.outbound_by_inbound["tag-e2"];
+    assert!(socks_tag.starts_with("xp-egress-socks-"));
+    assert_eq!(&source.routing.outbound_by_inbound["tag-e3"], socks_tag);
+    assert_eq!(
+        source.owned_outbound_tags,
+        BTreeSet::from([node_tag.clone(), socks_tag.clone()])
+    );
+    assert!(source.inbound_user_operations.is_empty());
+
+    let (target, problems) = build_egress_desired("n2", CA_KEY, None, &egress, &nodes, &endpoints);
+    assert!(problems.is_empty(), "{problems:?}");
+    assert!(target.outbound_requests.is_empty());
+    assert_eq!(target.inbound_user_operations.len(), 1);
+    assert_eq!(target.inbound_user_operations[0].inbound_tag, "tag-v2");
+    assert_eq!(
+        target.owned_inbound_user_emails["tag-v2"],
+        BTreeSet::from(["egress:n1".to_string()])
+    );
+    assert_eq!(
+        target.routing.transit_inbound_tags,
+        BTreeSet::from(["tag-v2".to_string()])
+    );
+    assert_eq!(
+        derive_egress_uuid(CA_KEY, "n1", "n2"),
+        derive_egress_uuid(CA_KEY, "n1", "n2")
+    );
+    assert_ne!(
+        derive_egress_uuid(CA_KEY, "n1", "n2"),
+        derive_egress_uuid(CA_KEY, "n2", "n1")
+    );
+}
+
+#[test]
+fn unreachable_chain_targets_fall_back_to_direct() {
+    let nodes = vec![node("n1"), node("n2")];
+    let endpoints = vec![endpoint("e1", "n1", false), endpoint("s2", "n2", false)];
+    let egress = BTreeMap::from([
+        (
+            "e1".to_string(),
+            EndpointEgress::Node {
+                node_id: "n2".to_string(),
+            },
+        ),
+        ("s2".to_string(), socks("198.51.100.7")),
+    ]);
+    let (desired, problems) = build_egress_desired("n1", CA_KEY, None, &egress, &nodes, &endpoints);
+    assert_eq!(problems.len(), 1);
+    assert!(desired.outbound_requests.is_empty());
+    assert!(desired.routing.outbound_by_inbound.is_empty());
+
+    // Operator outbounds need nothing from reconcile besides the rule.
+    let egress = BTreeMap::from([(
+        "e1".to_string(),
+        EndpointEgress::XrayOutbound {
+            tag: "warp".to_string(),
+        },
+    )]);
+    let (desired, _) = build_egress_desired("n1", CA_KEY, None, &egress, &nodes, &endpoints);
+    assert!(desired.outbound_requests.is_empty());
+    assert_eq!(
+        desired.routing.outbound_by_inbound,
+        BTreeMap::from([("tag-e1".to_string(), "warp".to_string())])
+    );
+}
+
+#[test]
+fn chained_peers_are_admitted_under_both_roots_during_rotation() {
+    let nodes = vec![node("n1"), node("n2")];
+    let endpoints = vec![endpoint("e1", "n1", false), endpoint("v2", "n2", true)];
+    let egress = BTreeMap::from([(
+        "e1".to_string(),
+        EndpointEgress::Node {
+            node_id: "n2".to_string(),
+        },
+    )]);
+
+    let (target, problems) = build_egress_desired(
+        "n2",
+        CA_KEY,
+        Some("next-ca-key"),
+        &egress,
+        &nodes,
+        &endpoints,
+    );
+    assert!(problems.is_empty(), "{problems:?}");
+    assert_eq!(target.inbound_user_operations.len(), 2);
+    let emails = BTreeSet::from(["egress:n1".to_string(), "egress:n1#alt".to_string()]);
+    assert_eq!(target.owned_inbound_user_emails["tag-v2"], emails);
+    assert_eq!(target.routing.transit_user_emails, emails);
+    assert_ne!(
+        derive_egress_uuid(CA_KEY, "n1", "n2"),
+        derive_egress_uuid("next-ca-key", "n1", "n2")
+    );
+}
@@ -22,17 +22,22 @@ use tokio::{
 use tracing::{debug, warn};
 
 use crate::{
-    credentials::{derive_ss2022_user_psk_b64, derive_vless_uuid},
     domain::{Endpoint, EndpointKind, User, UserQuotaReset},
     id::new_ulid_string,
-    protocol::{
-        SS2022_METHOD_2022_BLAKE3_AES_128_GCM, Ss2022EndpointMeta, VLESS_XHTTP_PATH,
-        VlessRealityTransport, VlessRealityVisionTcpEndpointMeta, ss2022_password,
-    },
     raft::app::RaftFacade,
     raft::types::ClientResponse,
     state::JsonSnapshotStore,
-    state::{DesiredStateCommand, EndpointProbeAppendSample},
+    state::{DesiredStateCommand, EndpointProbeAppendSample, EndpointProbeTargetSample},
+};
+
+mod kind_probes;
+use kind_probes::{
+    probe_hysteria2, probe_ss2022, probe_trojan_reality, probe_trojan_tls, probe_vless_reality,
+};
+mod targets;
+pub use targets::{
+    ProbeTarget, UnreachableProbeTarget, default_probe_targets, unreachable_probe_targets,
+    validate_probe_targets,
 };
 
 pub const PROBE_USER_ID: &str = "user_probe";
@@ -46,33 +51,6 @@ const DEFAULT_XRAY_STARTUP_TIMEOUT: Duration = Duration::from_secs(2);
 
 type HmacSha256 = Hmac<Sha256>;
 
-#[derive(Debug, Clone)]
-pub struct ProbeTarget {
-    pub id: &'static str,
-    pub url: &'static str,
-    pub expected_status: u16,
-    pub expected_body_prefix: Option<&'static str>,
-    pub required: bool,
-}
-
-// NOTE: Keep this list stable. UI reads the resulting latency as a canonical endpoint metric.
-const DEFAULT_TARGETS: &[ProbeTarget] = &[
-    ProbeTarget {
-        id: "gstatic-204",
-        url: "https://www.gstatic.com/generate_204",
-        expected_status: 204,
-        expected_body_prefix: None,
-        required: true,
-    },
-    ProbeTarget {
-        id: "cloudflare-robots",
-        url: "https://www.cloudflare.com/robots.txt",
-        expected_status: 200,
-        expected_body_prefix: Some("User-agent"),
-        required: false,
-    },
-];
-
 #[derive(Debug, Clone)]
 pub struct EndpointProbeRunRequest {
     /// Hour bucket key like `2026-02-07T12:00:00Z`.
@@ -249,18 +227,18 @@ impl std::fmt::Display for EndpointProbeError {
 
 impl std::error::Error for EndpointProbeError {}
 
-fn compute_config_hash(concurrency: usize) -> String {
-    // Include any setting that affects probe results.
-    let targets: Vec<BTreeMap<&'static str, String>> = DEFAULT_TARGETS
+fn compute_config_hash(targets: &[ProbeTarget], concurrency: usize) -> String {
+    // Include any setting that affects probe results; labels are display-only.
+    let targets: Vec<BTreeMap<&'static str, String>> = targets
         .iter()
         .map(|t| {
             let mut m = BTreeMap::new();
-            m.insert("id", t.id.to_string());
-            m.insert("url", t.url.to_string());
+            m.insert("id", t.id.clone());
+            m.insert("url", t.url.clone());
             m.insert("expected_status", t.expected_status.to_string());
             m.insert(
                 "expected_body_prefix",
-                t.expected_body_prefix.unwrap_or_default().to_string(),
+                t.expected_body_prefix.clone().unwrap_or_default(),
             );
             m.insert("required", t.required.to_string());
             m
@@ -280,8 +258,9 @@ fn compute_config_hash(concurrency: usize) -> String {
     hex::encode(hasher.finalize())
 }
 
-pub fn probe_config_hash() -> String {
-    compute_config_hash(DEFAULT_CONCURRENCY)
+/// Hash of the probe configuration; every node must compute the same value to join a run.
+pub fn probe_config_hash(targets: &[ProbeTarget]) -> String {
+    compute_config_hash(targets, DEFAULT_CONCURRENCY)
 }
 
 pub fn format_hour_key_now() -> String {
@@ -380,10 +359,14 @@ pub fn spawn_endpoint_probe_worker(
             tokio::time::sleep(sleep_dur).await;
 
             let hour = format_hour_key(next);
+            let config_hash = {
+                let store = worker.inner.store.lock().await;
+                probe_config_hash(&store.state().endpoint_probe_targets)
+            };
             let req = EndpointProbeRunRequest {
                 hour,
                 run_id: new_ulid_string(),
-                config_hash: probe_config_hash(),
+                config_hash,
                 reason: "hourly",
             };
 
@@ -541,17 +524,10 @@ async fn run_probe_once_inner(
     inner: Arc<EndpointProbeInner>,
     req: EndpointProbeRunRequest,
 ) -> Result<(), EndpointProbeError> {
-    let local_hash = probe_config_hash();
-    if local_hash != req.config_hash {
-        return Err(EndpointProbeError::ConfigHashMismatch {
-            expected: local_hash,
-            got: req.config_hash,
-        });
-    }
-
-    // Snapshot endpoints/nodes/memberships without holding the lock across Raft writes.
-    let (endpoints, nodes, probe_endpoint_ids) = {
+    // Snapshot targets/endpoints/nodes/memberships without holding the lock across Raft writes.
+    let (targets, endpoints, nodes, probe_endpoint_ids) = {
         let store = inner.store.lock().await;
+        let targets = store.state().endpoint_probe_targets.clone();
         let probe_endpoint_ids = store
             .state()
             .node_user_endpoint_memberships
@@ -560,11 +536,19 @@ async fn run_probe_once_inner(
             .map(|m| m.endpoint_id.clone())
             .collect::<BTreeSet<_>>();
         (
+            targets,
             store.list_endpoints(),
             store.list_nodes(),
             probe_endpoint_ids,
         )
     };
+    let local_hash = probe_config_hash(&targets);
+    if local_hash != req.config_hash {
+        return Err(EndpointProbeError::ConfigHashMismatch {
+            expected: local_hash,
+            got: req.config_hash,
+        });
+    }
 
     ensure_probe_user_and_access(
         &inner.raft,
@@ -607,6 +591,7 @@ async fn run_probe_once_inner(
     let mut tasks = Vec::new();
 
     let nodes_by_id = Arc::new(nodes_by_id);
+    let targets = Arc::new(targets);
     let probe_secret = Arc::clone(&inner.probe_secret);
     let runs = Arc::clone(&inner.runs);
     let events = inner.events.clone();
@@ -643,6 +628,7 @@ async fn run_probe_once_inner(
                         "skipped: self-test disabled (XP_ENDPOINT_PROBE_SKIP_SELF_TEST)"
                             .to_string(),
                     ),
+                    targets: Vec::new(),
                     config_hash,
                 };
 
@@ -669,6 +655,7 @@ async fn run_probe_once_inner(
 
         let permit = sem.clone().acquire_owned().await.expect("semaphore");
         let nodes_by_id = Arc::clone(&nodes_by_id);
+        let targets = Arc::clone(&targets);
         let probe_secret = Arc::clone(&probe_secret);
         let config_hash = req.config_hash.clone();
         let run_id = req.run_id.clone();
@@ -685,6 +672,7 @@ async fn run_probe_once_inner(
                 endpoint,
                 probe_secret.as_ref(),
                 nodes_by_id.as_ref(),
+                targets.as_ref(),
             )
             .await;
 
@@ -776,6 +764,10 @@ async fn ensure_probe_user_and_access(
         quota_reset: UserQuotaReset::Unlimited {
             tz_offset_minutes: 0,
         },
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     };
     raft_write_best_effort(raft, DesiredStateCommand::UpsertUser { user }).await?;
 
@@ -811,6 +803,7 @@ async fn probe_one_endpoint(
     endpoint: Endpoint,
     probe_secret: &str,
     nodes_by_id: &BTreeMap<String, crate::domain::Node>,
+    targets: &[ProbeTarget],
 ) -> EndpointProbeAppendSample {
     let checked_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
 
@@ -824,6 +817,7 @@ async fn probe_one_endpoint(
             target_id: None,
             target_url: None,
             error: Some("node not found for endpoint".to_string()),
+            targets: Vec::new(),
             config_hash: config_hash.to_string(),
         };
     };
@@ -838,16 +832,26 @@ async fn probe_one_endpoint(
             target_id: None,
             target_url: None,
             error: Some("loopback access_host is not allowed for endpoint probes".to_string()),
+            targets: Vec::new(),
             config_hash: config_hash.to_string(),
         };
     }
 
     let result = match endpoint.kind {
         EndpointKind::VlessRealityVisionTcp => {
-            probe_vless_reality(run_id, probe_secret, node, &endpoint).await
+            probe_vless_reality(run_id, probe_secret, node, &endpoint, targets).await
         }
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
-            probe_ss2022(run_id, probe_secret, node, &endpoint).await
+            probe_ss2022(run_id, probe_secret, node, &endpoint, targets).await
+        }
+        EndpointKind::TrojanRealityTcp => {
+            probe_trojan_reality(run_id, probe_secret, node, &endpoint, targets).await
+        }
+        EndpointKind::TrojanTlsTcp => {
+            probe_trojan_tls(run_id, probe_secret, node, &endpoint, targets).await
+        }
+        EndpointKind::Hysteria2 => {
+            probe_hysteria2(run_id, probe_secret, node, &endpoint, targets).await
         }
     };
 
@@ -861,6 +865,7 @@ async fn probe_one_endpoint(
             target_id: ok.target_id,
             target_url: ok.target_url,
             error: ok.error,
+            targets: ok.targets,
             config_hash: config_hash.to_string(),
         },
         Err(err) => EndpointProbeAppendSample {
@@ -872,6 +877,7 @@ async fn probe_one_endpoint(
             target_id: None,
             target_url: None,
             error: Some(err.to_string()),
+            targets: Vec::new(),
             config_hash: config_hash.to_string(),
         },
     }
@@ -884,138 +890,13 @@ struct ProbeOk {
     target_id: Option<String>,
     target_url: Option<String>,
     error: Option<String>,
-}
-
-async fn probe_vless_reality(
-    run_id: &str,
-    probe_secret: &str,
-    node: &crate::domain::Node,
-    endpoint: &Endpoint,
-) -> Result<ProbeOk, EndpointProbeError> {
-    let uuid = derive_vless_uuid(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
-        EndpointProbeError::Credentials {
-            message: e.to_string(),
-        }
-    })?;
-
-    let meta: VlessRealityVisionTcpEndpointMeta = serde_json::from_value(endpoint.meta.clone())
-        .map_err(|e| EndpointProbeError::Store {
-            message: e.to_string(),
-        })?;
-    let server_name = meta
-        .reality
-        .server_names
-        .first()
-        .cloned()
-        .unwrap_or_default();
-
-    let public_key = meta.reality_keys.public_key;
-    let short_id = meta.active_short_id;
-    if server_name.is_empty() || public_key.is_empty() || short_id.is_empty() {
-        return Err(EndpointProbeError::Store {
-            message: "invalid vless reality meta (missing server_name/public_key/short_id)"
-                .to_string(),
-        });
-    }
-
-    let (flow, network, xhttp_settings) = vless_probe_transport_settings(meta.transport);
-    let mut stream_settings = serde_json::json!({
-        "network": network,
-        "security": "reality",
-        "realitySettings": {
-            "show": false,
-            "fingerprint": meta.reality.fingerprint,
-            "serverName": server_name,
-            "publicKey": public_key,
-            "shortId": short_id,
-            "spiderX": "/"
-        }
-    });
-    if let Some(xhttp_settings) = xhttp_settings {
-        stream_settings["xhttpSettings"] = xhttp_settings;
-    }
-
-    let outbound = serde_json::json!({
-        "protocol": "vless",
-        "settings": {
-            "vnext": [{
-                "address": node.access_host,
-                "port": endpoint.port,
-                "users": [{
-                    "id": uuid,
-                    "flow": flow,
-                    "encryption": "none"
-                }]
-            }]
-        },
-        "streamSettings": stream_settings
-    });
-
-    probe_via_xray_socks(run_id, outbound).await
-}
-
-fn vless_probe_transport_settings(
-    transport: VlessRealityTransport,
-) -> (&'static str, &'static str, Option<serde_json::Value>) {
-    if transport.is_vision_tcp() {
-        return ("xtls-rprx-vision", "tcp", None);
-    }
-
-    (
-        "",
-        "xhttp",
-        Some(serde_json::json!({
-            "path": VLESS_XHTTP_PATH,
-            "mode": "stream-one"
-        })),
-    )
-}
-
-async fn probe_ss2022(
-    run_id: &str,
-    probe_secret: &str,
-    node: &crate::domain::Node,
-    endpoint: &Endpoint,
-) -> Result<ProbeOk, EndpointProbeError> {
-    let meta: Ss2022EndpointMeta =
-        serde_json::from_value(endpoint.meta.clone()).map_err(|e| EndpointProbeError::Store {
-            message: e.to_string(),
-        })?;
-    if meta.method != SS2022_METHOD_2022_BLAKE3_AES_128_GCM {
-        return Err(EndpointProbeError::Store {
-            message: format!(
-                "invalid ss2022 meta method: expected {SS2022_METHOD_2022_BLAKE3_AES_128_GCM}, got {}",
-                meta.method
-            ),
-        });
-    }
-    let user_psk_b64 = derive_ss2022_user_psk_b64(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
-        EndpointProbeError::Credentials {
-            message: e.to_string(),
-        }
-    })?;
-    let password = ss2022_password(&meta.server_psk_b64, &user_psk_b64);
-
-    let outbound = serde_json::json!({
-        "protocol": "shadowsocks",
-        "settings": {
-            "servers": [{
-                "address": node.access_host,
-                "port": endpoint.port,
-                "method": meta.method,
-                "password": password,
-                "uot": false,
-                "UoTVersion": 2
-            }],
-        }
-    });
-
-    probe_via_xray_socks(run_id, outbound).await
+    targets: Vec<EndpointProbeTargetSample>,
 }
 
 async fn probe_via_xray_socks(
     run_id: &str,
     outbound: serde_json::Value,
+    targets: &[ProbeTarget],
 ) -> Result<ProbeOk, EndpointProbeError> {
     let xray_bin =
         std::env::var("XP_ENDPOINT_PROBE_XRAY_BIN").unwrap_or_else(|_| "xray".to_string());
@@ -1129,43 +1010,53 @@ async fn probe_via_xray_socks(
     let mut canonical_target_id: Option<String> = None;
     let mut canonical_target_url: Option<String> = None;
     let mut required_failed: Vec<String> = Vec::new();
+    let mut target_samples = Vec::with_capacity(targets.len());
 
-    for target in DEFAULT_TARGETS {
+    for target in targets {
         let t0 = Instant::now();
-        let resp = client.get(target.url).send().await;
+        let resp = client.get(target.url.as_str()).send().await;
         let elapsed_ms = t0.elapsed().as_millis().min(u128::from(u32::MAX)) as u32;
 
-        let ok = match resp {
+        let error = match resp {
             Ok(resp) => {
-                if resp.status().as_u16() != target.expected_status {
-                    false
-                } else if let Some(prefix) = target.expected_body_prefix {
+                let status = resp.status().as_u16();
+                if status != target.expected_status {
+                    Some(format!(
+                        "unexpected status {status} (expected {})",
+                        target.expected_status
+                    ))
+                } else if let Some(prefix) = target.expected_body_prefix.as_deref() {
                     match resp.bytes().await {
-                        Ok(bytes) => {
-                            let s = String::from_utf8_lossy(&bytes);
-                            s.starts_with(prefix)
-                        }
-                        Err(_) => false,
+                        Ok(bytes) if String::from_utf8_lossy(&bytes).starts_with(prefix) => None,
+                        Ok(_) => Some("response body prefix mismatch".to_string()),
+                        Err(err) => Some(format!("read body: {err}")),
                     }
                 } else {
-                    true
+                    None
                 }
             }
-            Err(_) => false,
+            Err(err) => Some(format!("request failed: {err}")),
         };
+        let ok = error.is_none();
+        target_samples.push(EndpointProbeTargetSample {
+            target_id: target.id.clone(),
+            ok,
+            latency_ms: ok.then_some(elapsed_ms),
+            error,
+        });
 
         if ok {
             // Canonical latency is taken from the first required target (stable and comparable).
             if target.required && canonical_latency_ms.is_none() {
                 canonical_latency_ms = Some(elapsed_ms);
-                canonical_target_id = Some(target.id.to_string());
-                canonical_target_url = Some(target.url.to_string());
+                canonical_target_id = Some(target.id.clone());
+                canonical_target_url = Some(target.url.clone());
             }
             continue;
         }
 
         if target.required {
-            required_failed.push(target.id.to_string());
+            required_failed.push(target.id.clone());
         }
     }
 
@@ -1177,6 +1068,7 @@ async fn probe_via_xray_socks(
             target_id: canonical_target_id,
             target_url: canonical_target_url,
             error: None,
+            targets: target_samples,
         }
     } else {
         ProbeOk {
@@ -1188,6 +1080,7 @@ async fn probe_via_xray_socks(
                 "required targets failed: {}",
                 required_failed.join(", ")
             )),
+            targets: target_samples,
         }
     };
 
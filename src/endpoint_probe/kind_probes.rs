@@ -0,0 +1,290 @@
+use base64::Engine as _;
+
+use super::{EndpointProbeError, PROBE_USER_ID, ProbeOk, ProbeTarget, probe_via_xray_socks};
+use crate::{
+    credentials::{
+        derive_hysteria2_auth, derive_ss2022_user_psk_b64, derive_trojan_password,
+        derive_vless_uuid,
+    },
+    domain::Endpoint,
+    protocol::{
+        Hysteria2EndpointMeta, Ss2022EndpointMeta, TrojanRealityTcpEndpointMeta,
+        TrojanTlsTcpEndpointMeta, VLESS_XHTTP_PATH, VlessRealityTransport,
+        VlessRealityVisionTcpEndpointMeta, ss2022_password, ss2022_psk_len_bytes,
+    },
+};
+
+pub(super) async fn probe_vless_reality(
+    run_id: &str,
+    probe_secret: &str,
+    node: &crate::domain::Node,
+    endpoint: &Endpoint,
+    targets: &[ProbeTarget],
+) -> Result<ProbeOk, EndpointProbeError> {
+    let uuid = derive_vless_uuid(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
+        EndpointProbeError::Credentials {
+            message: e.to_string(),
+        }
+    })?;
+
+    let meta: VlessRealityVisionTcpEndpointMeta = serde_json::from_value(endpoint.meta.clone())
+        .map_err(|e| EndpointProbeError::Store {
+            message: e.to_string(),
+        })?;
+    let server_name = meta
+        .reality
+        .server_names
+        .first()
+        .cloned()
+        .unwrap_or_default();
+
+    let public_key = meta.reality_keys.public_key;
+    let short_id = meta.active_short_id;
+    if server_name.is_empty() || public_key.is_empty() || short_id.is_empty() {
+        return Err(EndpointProbeError::Store {
+            message: "invalid vless reality meta (missing server_name/public_key/short_id)"
+                .to_string(),
+        });
+    }
+
+    let (flow, network, xhttp_settings) = vless_probe_transport_settings(meta.transport);
+    let mut stream_settings = serde_json::json!({
+        "network": network,
+        "security": "reality",
+        "realitySettings": {
+            "show": false,
+            "fingerprint": meta.reality.fingerprint,
+            "serverName": server_name,
+            "publicKey": public_key,
+            "shortId": short_id,
+            "spiderX": "/"
+        }
+    });
+    if let Some(xhttp_settings) = xhttp_settings {
+        stream_settings["xhttpSettings"] = xhttp_settings;
+    }
+
+    let outbound = serde_json::json!({
+        "protocol": "vless",
+        "settings": {
+            "vnext": [{
+                "address": node.access_host,
+                "port": endpoint.port,
+                "users": [{
+                    "id": uuid,
+                    "flow": flow,
+                    "encryption": "none"
+                }]
+            }]
+        },
+        "streamSettings": stream_settings
+    });
+
+    probe_via_xray_socks(run_id, outbound, targets).await
+}
+
+pub(super) fn vless_probe_transport_settings(
+    transport: VlessRealityTransport,
+) -> (&'static str, &'static str, Option<serde_json::Value>) {
+    if transport.is_vision_tcp() {
+        return ("xtls-rprx-vision", "tcp", None);
+    }
+
+    (
+        "",
+        "xhttp",
+        Some(serde_json::json!({
+            "path": VLESS_XHTTP_PATH,
+            "mode": "stream-one"
+        })),
+    )
+}
+
+pub(super) async fn probe_ss2022(
+    run_id: &str,
+    probe_secret: &str,
+    node: &crate::domain::Node,
+    endpoint: &Endpoint,
+    targets: &[ProbeTarget],
+) -> Result<ProbeOk, EndpointProbeError> {
+    let meta: Ss2022EndpointMeta =
+        serde_json::from_value(endpoint.meta.clone()).map_err(|e| EndpointProbeError::Store {
+            message: e.to_string(),
+        })?;
+    let psk_len = ss2022_psk_len_bytes(&meta.method).ok_or_else(|| EndpointProbeError::Store {
+        message: format!("unsupported ss2022 meta method: {}", meta.method),
+    })?;
+    let user_psk_b64 = derive_ss2022_user_psk_b64(probe_secret, PROBE_USER_ID, 0, psk_len)
+        .map_err(|e| EndpointProbeError::Credentials {
+            message: e.to_string(),
+        })?;
+    let password = ss2022_password(&meta.server_psk_b64, &user_psk_b64);
+
+    let outbound = serde_json::json!({
+        "protocol": "shadowsocks",
+        "settings": {
+            "servers": [{
+                "address": node.access_host,
+                "port": endpoint.port,
+                "method": meta.method,
+                "password": password,
+                "uot": false,
+                "UoTVersion": 2
+            }],
+        }
+    });
+
+    probe_via_xray_socks(run_id, outbound, targets).await
+}
+
+pub(super) async fn probe_trojan_reality(
+    run_id: &str,
+    probe_secret: &str,
+    node: &crate::domain::Node,
+    endpoint: &Endpoint,
+    targets: &[ProbeTarget],
+) -> Result<ProbeOk, EndpointProbeError> {
+    let password = derive_trojan_password(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
+        EndpointProbeError::Credentials {
+            message: e.to_string(),
+        }
+    })?;
+    let meta: TrojanRealityTcpEndpointMeta = serde_json::from_value(endpoint.meta.clone())
+        .map_err(|e| EndpointProbeError::Store {
+            message: e.to_string(),
+        })?;
+    let server_name = meta
+        .reality
+        .server_names
+        .first()
+        .cloned()
+        .unwrap_or_default();
+
+    let public_key = meta.reality_keys.public_key;
+    let short_id = meta.active_short_id;
+    if server_name.is_empty() || public_key.is_empty() || short_id.is_empty() {
+        return Err(EndpointProbeError::Store {
+            message: "invalid trojan reality meta (missing server_name/public_key/short_id)"
+                .to_string(),
+        });
+    }
+
+    let outbound = serde_json::json!({
+        "protocol": "trojan",
+        "settings": {
+            "servers": [{
+                "address": node.access_host,
+                "port": endpoint.port,
+                "password": password
+            }]
+        },
+        "streamSettings": {
+            "network": "tcp",
+            "security": "reality",
+            "realitySettings": {
+                "show": false,
+                "fingerprint": meta.reality.fingerprint,
+                "serverName": server_name,
+                "publicKey": public_key,
+                "shortId": short_id,
+                "spiderX": "/"
+            }
+        }
+    });
+
+    probe_via_xray_socks(run_id, outbound, targets).await
+}
+
+pub(super) async fn probe_trojan_tls(
+    run_id: &str,
+    probe_secret: &str,
+    node: &crate::domain::Node,
+    endpoint: &Endpoint,
+    targets: &[ProbeTarget],
+) -> Result<ProbeOk, EndpointProbeError> {
+    let password = derive_trojan_password(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
+        EndpointProbeError::Credentials {
+            message: e.to_string(),
+        }
+    })?;
+    let meta: TrojanTlsTcpEndpointMeta =
+        serde_json::from_value(endpoint.meta.clone()).map_err(|e| EndpointProbeError::Store {
+            message: e.to_string(),
+        })?;
+    let pinned_sha256 = pinned_cert_sha256_b64(&meta.cert_sha256)?;
+
+    let outbound = serde_json::json!({
+        "protocol": "trojan",
+        "settings": {
+            "servers": [{
+                "address": node.access_host,
+                "port": endpoint.port,
+                "password": password
+            }]
+        },
+        "streamSettings": {
+            "network": "tcp",
+            "security": "tls",
+            "tlsSettings": {
+                "serverName": meta.server_name,
+                "alpn": ["http/1.1"],
+                "pinnedPeerCertificateChainSha256": [pinned_sha256]
+            }
+        }
+    });
+
+    probe_via_xray_socks(run_id, outbound, targets).await
+}
+
+pub(super) async fn probe_hysteria2(
+    run_id: &str,
+    probe_secret: &str,
+    node: &crate::domain::Node,
+    endpoint: &Endpoint,
+    targets: &[ProbeTarget],
+) -> Result<ProbeOk, EndpointProbeError> {
+    let auth = derive_hysteria2_auth(probe_secret, PROBE_USER_ID, 0).map_err(|e| {
+        EndpointProbeError::Credentials {
+            message: e.to_string(),
+        }
+    })?;
+    let meta: Hysteria2EndpointMeta =
+        serde_json::from_value(endpoint.meta.clone()).map_err(|e| EndpointProbeError::Store {
+            message: e.to_string(),
+        })?;
+    let pinned_sha256 = pinned_cert_sha256_b64(&meta.cert_sha256)?;
+
+    let outbound = serde_json::json!({
+        "protocol": "hysteria",
+        "settings": {
+            "version": 2,
+            "address": node.access_host,
+            "port": endpoint.port
+        },
+        "streamSettings": {
+            "network": "hysteria",
+            "hysteriaSettings": {
+                "version": 2,
+                "auth": auth
+            },
+            "security": "tls",
+            "tlsSettings": {
+                "serverName": meta.server_name,
+                "alpn": ["h3"],
+                "pinnedPeerCertificateChainSha256": [pinned_sha256]
+            }
+        }
+    });
+
+    probe_via_xray_socks(run_id, outbound, targets).await
+}
+
+/// Xray pins certificates by the base64 SHA-256 of the chain; for a single self-signed
+/// certificate that is the digest of the leaf itself.
+fn pinned_cert_sha256_b64(cert_sha256: &str) -> Result<String, EndpointProbeError> {
+    hex::decode(cert_sha256)
+        .map(|digest| base64::engine::general_purpose::STANDARD.encode(digest))
+        .map_err(|e| EndpointProbeError::Store {
+            message: format!("invalid cert_sha256: {e}"),
+        })
+}
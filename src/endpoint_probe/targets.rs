@@ -0,0 +1,149 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::state::EndpointProbeHistory;
+
+const MAX_PROBE_TARGETS: usize = 16;
+
+/// One URL every endpoint is asked to fetch. The list is cluster-wide and replicated through
+/// Raft (`endpoint_probe_targets`), so every node probes the same services.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProbeTarget {
+    /// Stable identifier recorded in probe history.
+    pub id: String,
+    /// Display name; defaults to `id`.
+    #[serde(default)]
+    pub label: String,
+    pub url: String,
+    pub expected_status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_body_prefix: Option<String>,
+    /// Required targets decide whether the endpoint is up; the others are reported per target.
+    #[serde(default)]
+    pub required: bool,
+}
+
+// NOTE: Keep the defaults stable. UI reads the resulting latency as a canonical endpoint metric,
+// and the config hash of this list must match older nodes during rolling upgrades.
+pub fn default_probe_targets() -> Vec<ProbeTarget> {
+    vec![
+        ProbeTarget {
+            id: "gstatic-204".to_string(),
+            label: "Google 204".to_string(),
+            url: "https://www.gstatic.com/generate_204".to_string(),
+            expected_status: 204,
+            expected_body_prefix: None,
+            required: true,
+        },
+        ProbeTarget {
+            id: "cloudflare-robots".to_string(),
+            label: "Cloudflare robots.txt".to_string(),
+            url: "https://www.cloudflare.com/robots.txt".to_string(),
+            expected_status: 200,
+            expected_body_prefix: Some("User-agent".to_string()),
+            required: false,
+        },
+    ]
+}
+
+pub fn validate_probe_targets(targets: &[ProbeTarget]) -> Result<(), String> {
+    if targets.is_empty() || targets.len() > MAX_PROBE_TARGETS {
+        return Err(format!("expected 1-{MAX_PROBE_TARGETS} targets"));
+    }
+    if !targets.iter().any(|target| target.required) {
+        return Err("at least one target must be required".to_string());
+    }
+    let mut ids = BTreeSet::new();
+    for target in targets {
+        let id = target.id.as_str();
+        if id.is_empty()
+            || id.len() > 64
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "target id {id:?} must be 1-64 characters of a-z, 0-9, '-' or '_'"
+            ));
+        }
+        if !ids.insert(id) {
+            return Err(format!("duplicate target id: {id}"));
+        }
+        let url = reqwest::Url::parse(&target.url)
+            .map_err(|e| format!("target {id}: invalid url: {e}"))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(format!("target {id}: url must be http(s) with a host"));
+        }
+        if !(100..=599).contains(&target.expected_status) {
+            return Err(format!(
+                "target {id}: expected_status {} is not an HTTP status",
+                target.expected_status
+            ));
+        }
+        if target.expected_body_prefix.as_deref() == Some("") {
+            return Err(format!("target {id}: expected_body_prefix is empty"));
+        }
+    }
+    Ok(())
+}
+
+/// A configured target that a node has failed to reach through an endpoint in every probe
+/// since `since`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnreachableProbeTarget {
+    pub node_id: String,
+    pub target_id: String,
+    /// `checked_at` of the first failing sample of the current streak.
+    pub since: String,
+    pub last_checked_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Finds the configured targets each node currently cannot reach through one endpoint.
+///
+/// Samples are walked newest first per node. Skipped samples and samples that carry no result
+/// for a target (the probe failed before fetching anything, or the target was added later)
+/// neither start nor end a streak.
+pub fn unreachable_probe_targets(
+    history: &EndpointProbeHistory,
+    targets: &[ProbeTarget],
+) -> Vec<UnreachableProbeTarget> {
+    let node_ids = history
+        .hours
+        .values()
+        .flat_map(|bucket| bucket.by_node.keys())
+        .collect::<BTreeSet<_>>();
+    let mut out = Vec::new();
+    for node_id in node_ids {
+        for target in targets {
+            let mut streak: Option<UnreachableProbeTarget> = None;
+            for bucket in history.hours.values().rev() {
+                let Some(sample) = bucket.by_node.get(node_id).filter(|s| !s.skipped) else {
+                    continue;
+                };
+                let Some(result) = sample.targets.iter().find(|r| r.target_id == target.id) else {
+                    continue;
+                };
+                if result.ok {
+                    break;
+                }
+                match streak.as_mut() {
+                    Some(streak) => streak.since = sample.checked_at.clone(),
+                    None => {
+                        streak = Some(UnreachableProbeTarget {
+                            node_id: node_id.clone(),
+                            target_id: target.id.clone(),
+                            since: sample.checked_at.clone(),
+                            last_checked_at: sample.checked_at.clone(),
+                            error: result.error.clone(),
+                        })
+                    }
+                }
+            }
+            out.extend(streak);
+        }
+    }
+    out
+}
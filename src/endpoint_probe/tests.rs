@@ -1,5 +1,10 @@
-use super::{create_private_dir, vless_probe_transport_settings, write_private_file};
+use super::kind_probes::vless_probe_transport_settings;
+use super::{
+    ProbeTarget, UnreachableProbeTarget, create_private_dir, default_probe_targets,
+    probe_config_hash, unreachable_probe_targets, validate_probe_targets, write_private_file,
+};
 use crate::protocol::{VLESS_XHTTP_PATH, VlessRealityTransport};
+use crate::state::{EndpointProbeHistory, EndpointProbeNodeSample, EndpointProbeTargetSample};
 
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
@@ -45,3 +50,150 @@ fn xhttp_probe_uses_xhttp_stream_without_vision_flow() {
     assert_eq!(settings["path"], VLESS_XHTTP_PATH);
     assert_eq!(settings["mode"], "stream-one");
 }
+
+fn target(id: &str, required: bool) -> ProbeTarget {
+    ProbeTarget {
+        id: id.to_string(),
+        label: id.to_string(),
+        url: format!("https://{id}.example.com/"),
+        expected_status: 200,
+        expected_body_prefix: None,
+        required,
+    }
+}
+
+fn node_sample(checked_at: &str, results: &[(&str, bool)]) -> EndpointProbeNodeSample {
+    EndpointProbeNodeSample {
+        ok: results.iter().all(|(_, ok)| *ok),
+        skipped: false,
+        checked_at: checked_at.to_string(),
+        latency_ms: None,
+        target_id: None,
+        target_url: None,
+        error: None,
+        targets: results
+            .iter()
+            .map(|(target_id, ok)| EndpointProbeTargetSample {
+                target_id: target_id.to_string(),
+                ok: *ok,
+                latency_ms: None,
+                error: (!ok).then(|| "request failed".to_string()),
+            })
+            .collect(),
+        config_hash: String::new(),
+    }
+}
+
+fn history(samples: &[(&str, &str, EndpointProbeNodeSample)]) -> EndpointProbeHistory {
+    let mut history = EndpointProbeHistory::default();
+    for (hour, node_id, sample) in samples {
+        history
+            .hours
+            .entry(hour.to_string())
+            .or_default()
+            .by_node
+            .insert(node_id.to_string(), sample.clone());
+    }
+    history
+}
+
+#[test]
+fn default_targets_keep_the_rolling_upgrade_config_hash() {
+    // Nodes that predate configurable targets compute this hash; changing it breaks mixed runs.
+    assert_eq!(
+        probe_config_hash(&default_probe_targets()),
+        "d143835e8acebff617dda2a83d1a5d91a0836a97eeb43af48052f84e62aeebc4"
+    );
+
+    let mut relabeled = default_probe_targets();
+    relabeled[0].label = "renamed".to_string();
+    assert_eq!(
+        probe_config_hash(&relabeled),
+        probe_config_hash(&default_probe_targets())
+    );
+}
+
+#[test]
+fn probe_targets_validation_rejects_unusable_lists() {
+    assert_eq!(validate_probe_targets(&default_probe_targets()), Ok(()));
+    assert!(validate_probe_targets(&[]).is_err());
+    assert!(validate_probe_targets(&[target("optional", false)]).is_err());
+    assert!(validate_probe_targets(&[target("a", true), target("a", false)]).is_err());
+    assert!(validate_probe_targets(&[target("Bad Id", true)]).is_err());
+
+    let mut ftp = target("ftp", true);
+    ftp.url = "ftp://example.com/".to_string();
+    assert!(validate_probe_targets(&[ftp]).is_err());
+
+    let mut status = target("status", true);
+    status.expected_status = 42;
+    assert!(validate_probe_targets(&[status]).is_err());
+}
+
+#[test]
+fn unreachable_targets_report_the_start_of_the_current_failure_streak() {
+    let targets = [target("gstatic", true), target("internal-api", false)];
+    let history = history(&[
+        (
+            "2026-02-07T12:00:00Z",
+            "node-a",
+            node_sample(
+                "2026-02-07T12:00:05Z",
+                &[("gstatic", true), ("internal-api", true)],
+            ),
+        ),
+        (
+            "2026-02-07T13:00:00Z",
+            "node-a",
+            node_sample(
+                "2026-02-07T13:00:05Z",
+                &[("gstatic", true), ("internal-api", false)],
+            ),
+        ),
+        (
+            "2026-02-07T14:00:00Z",
+            "node-a",
+            node_sample("2026-02-07T14:00:05Z", &[]),
+        ),
+        (
+            "2026-02-07T15:00:00Z",
+            "node-a",
+            node_sample(
+                "2026-02-07T15:00:05Z",
+                &[("gstatic", true), ("internal-api", false)],
+            ),
+        ),
+        (
+            "2026-02-07T14:00:00Z",
+            "node-b",
+            node_sample(
+                "2026-02-07T14:00:05Z",
+                &[("gstatic", false), ("internal-api", false)],
+            ),
+        ),
+        (
+            "2026-02-07T15:00:00Z",
+            "node-b",
+            node_sample(
+                "2026-02-07T15:00:05Z",
+                &[("gstatic", true), ("internal-api", true)],
+            ),
+        ),
+    ]);
+
+    assert_eq!(
+        unreachable_probe_targets(&history, &targets),
+        vec![UnreachableProbeTarget {
+            node_id: "node-a".to_string(),
+            target_id: "internal-api".to_string(),
+            since: "2026-02-07T13:00:05Z".to_string(),
+            last_checked_at: "2026-02-07T15:00:05Z".to_string(),
+            error: Some("request failed".to_string()),
+        }]
+    );
+    // Targets removed from the configuration are no longer reported.
+    assert_eq!(
+        unreachable_probe_targets(&history, &targets[..1]),
+        Vec::new()
+    );
+}
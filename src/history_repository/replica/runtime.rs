@@ -50,12 +50,13 @@ const RETENTION_COMPACTION_BUCKET_LOOKAHEAD: usize = 32;
 const REPLICATION_SEGMENT_PAGE_SIZE: usize = 256;
 const MAX_QUERY_RESPONSE_BYTES: usize = 256 * 1024;
 const TOMBSTONE_HORIZON_SECONDS: u64 = 2 * 365 * 24 * 60 * 60;
-const KNOWN_SCHEMAS: [(&str, u32); 6] = [
+const KNOWN_SCHEMAS: [(&str, u32); 7] = [
     ("runtime.v1", 1),
     ("path_health.v1", 1),
     ("traffic.v1", 1),
     ("connections.v1", 1),
     ("ip_usage.v1", 1),
+    ("subscription_access.v1", 1),
     ("tombstone.v1", 1),
 ];
 
@@ -588,6 +588,7 @@ pub(crate) fn source_stream_for_schema(schema_id: &str) -> Option<&'static str>
         "traffic.v1" => "traffic",
         "connections.v1" => "connections",
         "ip_usage.v1" => "ip_usage",
+        "subscription_access.v1" => "subscription_access",
         _ => return None,
     })
 }
@@ -622,6 +622,7 @@ fn stream_for_schema(schema_id: &str) -> Option<&'static str> {
         "traffic.v1" => "traffic",
         "connections.v1" => "connections",
         "ip_usage.v1" => "ip_usage",
+        "subscription_access.v1" => "subscription_access",
         "tombstone.v1" => "tombstone",
         _ => return None,
     })
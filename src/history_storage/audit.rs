@@ -0,0 +1,146 @@
+use super::*;
+use crate::audit::AuditEntry;
+
+/// Meta key for the highest log index whose audit entry this node may lack because it caught up
+/// from a Raft snapshot instead of applying the entry.
+const AUDIT_MISSING_THROUGH_META_KEY: &str = "admin_audit_missing_through_log_index";
+
+/// Filters for one page of the admin audit log; entries come newest first.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuditLogQuery {
+    pub(crate) command: Option<String>,
+    pub(crate) target_id: Option<String>,
+    pub(crate) principal: Option<String>,
+    pub(crate) origin_node_id: Option<String>,
+    pub(crate) since_unix_seconds: Option<i64>,
+    pub(crate) until_unix_seconds: Option<i64>,
+    /// Keyset cursor: only entries with a smaller log index.
+    pub(crate) before_log_index: Option<u64>,
+    pub(crate) limit: usize,
+}
+
+impl HistoryStorage {
+    /// Appends one audit row keyed by its Raft log index. Re-applying the same log entry after a
+    /// restart keeps the first row. Audit rows are SQLite-only; degraded JSON mode drops them.
+    pub(crate) fn append_audit_entry(
+        &self,
+        entry: &AuditEntry,
+        occurred_at_unix_seconds: i64,
+    ) -> Result<()> {
+        let payload =
+            serde_json::to_vec(entry).map_err(|error| HistoryStorageError(error.to_string()))?;
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(());
+        };
+        // Delimited on both sides so a target filter can match whole ids with `instr`.
+        let targets = format!(
+            ",{},",
+            entry
+                .targets
+                .values()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        connection
+            .execute(
+                "
+                INSERT OR IGNORE INTO admin_audit_log
+                    (log_index, occurred_at, command, targets, principal, origin_node_id, payload)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                ",
+                params![
+                    durable_i64(entry.log_index, "audit log index")?,
+                    occurred_at_unix_seconds,
+                    entry.command,
+                    targets,
+                    entry.principal.label(),
+                    entry.origin_node_id,
+                    payload,
+                ],
+            )
+            .map_err(sqlite_error)?;
+        maintain_sqlite(connection)
+    }
+
+    /// Records that entries up to `log_index` were never applied on this node.
+    pub(crate) fn mark_audit_entries_missing_through(&self, log_index: u64) -> Result<()> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(());
+        };
+        let log_index = durable_i64(log_index, "audit log index")?;
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        let missing_through = read_meta_i64(&transaction, AUDIT_MISSING_THROUGH_META_KEY)?
+            .unwrap_or_default()
+            .max(log_index);
+        write_meta_i64(
+            &transaction,
+            AUDIT_MISSING_THROUGH_META_KEY,
+            missing_through,
+        )?;
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    /// Whether this node holds every audit entry: it stores them in SQLite and never caught up
+    /// from a snapshot.
+    pub(crate) fn audit_log_is_complete(&self) -> Result<bool> {
+        let backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &*backend else {
+            return Ok(false);
+        };
+        Ok(read_meta_i64(connection, AUDIT_MISSING_THROUGH_META_KEY)?.is_none())
+    }
+
+    /// Returns `(log_index, payload)` rows matching `query`.
+    pub(crate) fn audit_entries(&self, query: &AuditLogQuery) -> Result<Vec<(u64, Vec<u8>)>> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(Vec::new());
+        };
+        let before = query
+            .before_log_index
+            .map(|index| durable_i64(index, "audit cursor"))
+            .transpose()?;
+        let mut statement = connection
+            .prepare(
+                "
+                SELECT log_index, payload
+                FROM admin_audit_log
+                WHERE (?1 IS NULL OR command = ?1)
+                  AND (?2 IS NULL OR instr(targets, ',' || ?2 || ',') > 0)
+                  AND (?3 IS NULL OR principal = ?3)
+                  AND (?4 IS NULL OR origin_node_id = ?4)
+                  AND (?5 IS NULL OR occurred_at >= ?5)
+                  AND (?6 IS NULL OR occurred_at <= ?6)
+                  AND (?7 IS NULL OR log_index < ?7)
+                ORDER BY log_index DESC
+                LIMIT ?8
+                ",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(
+                params![
+                    query.command,
+                    query.target_id,
+                    query.principal,
+                    query.origin_node_id,
+                    query.since_unix_seconds,
+                    query.until_unix_seconds,
+                    before,
+                    i64::try_from(query.limit).unwrap_or(i64::MAX),
+                ],
+                |row| {
+                    Ok((
+                        u64::try_from(row.get::<_, i64>(0)?).unwrap_or_default(),
+                        row.get::<_, Vec<u8>>(1)?,
+                    ))
+                },
+            )
+            .map_err(sqlite_error)?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(sqlite_error)
+    }
+}
@@ -24,7 +24,10 @@ pub(crate) const NODE_HISTORY_KEY: &str = "node_history";
 pub(crate) const MESH_TELEMETRY_KEY: &str = "mesh_telemetry";
 pub(crate) const REPOSITORY_REPLICA_KEY: &str = "repository_replica";
 
+mod audit;
+pub(crate) use audit::AuditLogQuery;
 mod repository;
+mod subscription_access;
 #[allow(unused_imports)]
 pub(crate) use repository::{
     RepositoryHistoryCompactionCursor, RepositoryHistoryCoverage, RepositoryHistoryRecordRow,
@@ -490,6 +493,26 @@ fn ensure_schema(connection: &Connection) -> Result<()> {
                 session_id TEXT PRIMARY KEY NOT NULL,
                 expires_at INTEGER NOT NULL
             );
+            CREATE TABLE IF NOT EXISTS admin_audit_log (
+                log_index INTEGER PRIMARY KEY NOT NULL,
+                occurred_at INTEGER NOT NULL,
+                command TEXT NOT NULL,
+                targets TEXT NOT NULL,
+                principal TEXT NOT NULL,
+                origin_node_id TEXT NOT NULL,
+                payload BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS subscription_access_log (
+                access_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                accessed_at INTEGER NOT NULL,
+                user_id TEXT NOT NULL,
+                published INTEGER NOT NULL DEFAULT 0,
+                payload BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS subscription_access_log_user
+                ON subscription_access_log (user_id, accessed_at);
+            CREATE INDEX IF NOT EXISTS subscription_access_log_unpublished
+                ON subscription_access_log (published, access_id);
             ",
         )
         .map_err(sqlite_error)?;
@@ -0,0 +1,175 @@
+use super::*;
+use crate::subscription_access::{
+    SUBSCRIPTION_ACCESS_RETENTION_DAYS, SUBSCRIPTION_ACCESS_SCHEMA_ID, SubscriptionAccessEntry,
+    subscription_access_user_key_prefix,
+};
+
+impl HistoryStorage {
+    /// Appends one subscription fetch and prunes rows past the retention window. Like the audit
+    /// log this is SQLite-only; degraded JSON mode drops fetches.
+    pub(crate) fn append_subscription_access(
+        &self,
+        entry: &SubscriptionAccessEntry,
+        accessed_at_unix_seconds: i64,
+    ) -> Result<()> {
+        let payload =
+            serde_json::to_vec(entry).map_err(|error| HistoryStorageError(error.to_string()))?;
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(());
+        };
+        connection
+            .execute(
+                "
+                INSERT INTO subscription_access_log (accessed_at, user_id, payload)
+                VALUES (?1, ?2, ?3)
+                ",
+                params![accessed_at_unix_seconds, entry.user_id, payload],
+            )
+            .map_err(sqlite_error)?;
+        connection
+            .execute(
+                "DELETE FROM subscription_access_log WHERE accessed_at < ?1",
+                params![
+                    accessed_at_unix_seconds - SUBSCRIPTION_ACCESS_RETENTION_DAYS * 24 * 60 * 60
+                ],
+            )
+            .map_err(sqlite_error)?;
+        maintain_sqlite(connection)
+    }
+
+    /// Returns `(access_id, payload)` rows for `user_id` (or everyone) since the given time,
+    /// newest first.
+    pub(crate) fn subscription_access_entries(
+        &self,
+        user_id: Option<&str>,
+        since_unix_seconds: i64,
+        limit: usize,
+    ) -> Result<Vec<(u64, Vec<u8>)>> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(Vec::new());
+        };
+        let mut statement = connection
+            .prepare(
+                "
+                SELECT access_id, payload
+                FROM subscription_access_log
+                WHERE (?1 IS NULL OR user_id = ?1)
+                  AND accessed_at >= ?2
+                ORDER BY access_id DESC
+                LIMIT ?3
+                ",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(
+                params![
+                    user_id,
+                    since_unix_seconds,
+                    i64::try_from(limit).unwrap_or(i64::MAX),
+                ],
+                |row| {
+                    Ok((
+                        u64::try_from(row.get::<_, i64>(0)?).unwrap_or_default(),
+                        row.get::<_, Vec<u8>>(1)?,
+                    ))
+                },
+            )
+            .map_err(sqlite_error)?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(sqlite_error)
+    }
+
+    /// Oldest fetches not yet handed to the history repository source queue.
+    pub(crate) fn unpublished_subscription_access(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<(u64, Vec<u8>)>> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(Vec::new());
+        };
+        let mut statement = connection
+            .prepare(
+                "
+                SELECT access_id, payload
+                FROM subscription_access_log
+                WHERE published = 0
+                ORDER BY access_id
+                LIMIT ?1
+                ",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(params![i64::try_from(limit).unwrap_or(i64::MAX)], |row| {
+                Ok((
+                    u64::try_from(row.get::<_, i64>(0)?).unwrap_or_default(),
+                    row.get::<_, Vec<u8>>(1)?,
+                ))
+            })
+            .map_err(sqlite_error)?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(sqlite_error)
+    }
+
+    pub(crate) fn mark_subscription_access_published(&self, access_ids: &[u64]) -> Result<()> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(());
+        };
+        let transaction = connection.transaction().map_err(sqlite_error)?;
+        for access_id in access_ids {
+            transaction
+                .execute(
+                    "UPDATE subscription_access_log SET published = 1 WHERE access_id = ?1",
+                    params![durable_i64(*access_id, "subscription access id")?],
+                )
+                .map_err(sqlite_error)?;
+        }
+        transaction.commit().map_err(sqlite_error)
+    }
+
+    /// Returns `(record_key, payload)` of replicated fetch records this node holds as a history
+    /// repository, observed since the given time.
+    pub(crate) fn repository_subscription_access_records(
+        &self,
+        user_id: Option<&str>,
+        since_unix_seconds: i64,
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut backend = self.lock_backend();
+        let Backend::Sqlite(connection) = &mut *backend else {
+            return Ok(Vec::new());
+        };
+        let prefix =
+            user_id.map(|user_id| subscription_access_user_key_prefix(user_id).into_bytes());
+        let mut statement = connection
+            .prepare(
+                "
+                SELECT record_key, payload
+                FROM repository_history_records
+                WHERE is_tombstone = 0
+                  AND schema_id = ?1
+                  AND observed_end >= ?2
+                  AND (?3 IS NULL OR substr(record_key, 1, length(?3)) = ?3)
+                ORDER BY observed_start DESC
+                LIMIT ?4
+                ",
+            )
+            .map_err(sqlite_error)?;
+        let rows = statement
+            .query_map(
+                params![
+                    SUBSCRIPTION_ACCESS_SCHEMA_ID,
+                    since_unix_seconds,
+                    prefix,
+                    i64::try_from(limit).unwrap_or(i64::MAX),
+                ],
+                |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?)),
+            )
+            .map_err(sqlite_error)?;
+        rows.collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(sqlite_error)
+    }
+}
@@ -362,3 +362,128 @@ fn set_query_only(storage: &HistoryStorage) {
     };
     connection.pragma_update(None, "query_only", "ON").unwrap();
 }
+
+#[test]
+fn audit_entries_are_append_once_and_filterable() {
+    use crate::audit::{AuditEntry, AuditPrincipal};
+
+    let temporary = tempfile::tempdir().unwrap();
+    let storage = HistoryStorage::open(temporary.path());
+    let entry = |log_index: u64, user_id: &str| AuditEntry {
+        log_index,
+        occurred_at: String::new(),
+        command: "delete_user".to_string(),
+        targets: [("user_id".to_string(), user_id.to_string())].into(),
+        origin_node_id: "node".to_string(),
+        principal: AuditPrincipal::AdminToken,
+        changes: Vec::new(),
+        changes_truncated: false,
+    };
+    storage
+        .append_audit_entry(&entry(1, "user-a"), 100)
+        .unwrap();
+    storage
+        .append_audit_entry(&entry(2, "user-b"), 200)
+        .unwrap();
+    // A replayed log entry keeps the first row.
+    storage
+        .append_audit_entry(&entry(2, "user-c"), 300)
+        .unwrap();
+
+    let indexes = |query: AuditLogQuery| {
+        storage
+            .audit_entries(&AuditLogQuery { limit: 10, ..query })
+            .unwrap()
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(indexes(AuditLogQuery::default()), vec![2, 1]);
+    for (target, expected) in [("user-b", vec![2]), ("user-c", vec![]), ("user", vec![])] {
+        let query = AuditLogQuery {
+            target_id: Some(target.to_string()),
+            ..AuditLogQuery::default()
+        };
+        assert_eq!(indexes(query), expected, "{target}");
+    }
+    let query = AuditLogQuery {
+        since_unix_seconds: Some(150),
+        ..AuditLogQuery::default()
+    };
+    assert_eq!(indexes(query), vec![2]);
+    let query = AuditLogQuery {
+        before_log_index: Some(2),
+        principal: Some("admin_token".to_string()),
+        ..AuditLogQuery::default()
+    };
+    assert_eq!(indexes(query), vec![1]);
+}
+
+#[test]
+fn subscription_access_is_pruned_after_retention_and_published_once() {
+    use crate::subscription_access::SubscriptionAccessEntry;
+
+    let temporary = tempfile::tempdir().unwrap();
+    let storage = HistoryStorage::open(temporary.path());
+    let entry = |user_id: &str| SubscriptionAccessEntry {
+        accessed_at: String::new(),
+        user_id: user_id.to_string(),
+        token_id: None,
+        node_id: "node".to_string(),
+        format: "base64".to_string(),
+        source_ip: Some("203.0.113.7".to_string()),
+        country: None,
+        user_agent: None,
+    };
+    let day = 24 * 60 * 60;
+    storage
+        .append_subscription_access(&entry("user-a"), 0)
+        .unwrap();
+    storage
+        .append_subscription_access(&entry("user-a"), 2 * day)
+        .unwrap();
+    storage
+        .append_subscription_access(&entry("user-b"), 8 * day)
+        .unwrap();
+
+    let ids = |user_id: Option<&str>, since: i64| {
+        storage
+            .subscription_access_entries(user_id, since, 10)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>()
+    };
+    // The first fetch fell out of the 7-day window when the third was appended.
+    assert_eq!(ids(None, 0), vec![3, 2]);
+    assert_eq!(ids(Some("user-a"), 0), vec![2]);
+    assert_eq!(ids(Some("user-a"), 3 * day), Vec::<u64>::new());
+
+    let unpublished = |limit| {
+        storage
+            .unpublished_subscription_access(limit)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(unpublished(1), vec![2]);
+    storage.mark_subscription_access_published(&[2]).unwrap();
+    assert_eq!(unpublished(10), vec![3]);
+}
+
+#[test]
+fn audit_log_is_incomplete_after_a_snapshot_catch_up() {
+    let temporary = tempfile::tempdir().unwrap();
+    let storage = HistoryStorage::open(temporary.path());
+    assert!(storage.audit_log_is_complete().unwrap());
+
+    storage.mark_audit_entries_missing_through(40).unwrap();
+    storage.mark_audit_entries_missing_through(12).unwrap();
+    assert!(!storage.audit_log_is_complete().unwrap());
+    let reopened = HistoryStorage::open(temporary.path());
+    assert!(!reopened.audit_log_is_complete().unwrap());
+
+    assert!(storage.degrade_to_json());
+    assert!(!storage.audit_log_is_complete().unwrap());
+}
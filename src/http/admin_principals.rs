@@ -0,0 +1,209 @@
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    admin_principal::{AdminPrincipal, AdminScope, issue_principal_token, validate_principal_name},
+    admin_token::{AdminTokenVerifier, AdminTokenVerifyError},
+    audit::AuditPrincipal,
+    id::new_ulid_string,
+    state::{DesiredStateCommand, JsonSnapshotStore},
+};
+
+pub(super) async fn authorize_principal_token(
+    token: String,
+    verifier: &AdminTokenVerifier,
+    store: &Mutex<JsonSnapshotStore>,
+) -> Result<Option<(AuditPrincipal, AdminScope)>, AdminTokenVerifyError> {
+    let Some(principal_id) = crate::admin_principal::principal_id_from_token(&token) else {
+        return Ok(None);
+    };
+    let principal = store
+        .lock()
+        .await
+        .state()
+        .admin_principals
+        .get(principal_id)
+        .filter(|principal| principal.is_active())
+        .cloned();
+    let Some((principal, hash)) =
+        principal.and_then(|principal| principal.token_hash().map(|hash| (principal, hash)))
+    else {
+        return Ok(None);
+    };
+    if !verifier.verify(token, hash).await? {
+        return Ok(None);
+    }
+    Ok(Some((
+        AuditPrincipal::Principal {
+            principal_id: principal.principal_id,
+            name: principal.name,
+        },
+        principal.scope,
+    )))
+}
+
+/// A principal without its token hash.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct AdminPrincipalView {
+    principal_id: String,
+    name: String,
+    scope: AdminScope,
+    created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revoked_at: Option<String>,
+}
+
+impl From<AdminPrincipal> for AdminPrincipalView {
+    fn from(principal: AdminPrincipal) -> Self {
+        Self {
+            principal_id: principal.principal_id,
+            name: principal.name,
+            scope: principal.scope,
+            created_at: principal.created_at,
+            revoked_at: principal.revoked_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct AdminPrincipalsResponse {
+    items: Vec<AdminPrincipalView>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct CreateAdminPrincipalRequest {
+    name: String,
+    scope: AdminScope,
+}
+
+/// The plaintext token is only ever returned here; the cluster stores its hash.
+#[derive(Debug, Serialize)]
+pub(super) struct AdminPrincipalTokenResponse {
+    principal: AdminPrincipalView,
+    token: String,
+}
+
+async fn issue_token(principal_id: String) -> Result<(String, String), ApiError> {
+    tokio::task::spawn_blocking(move || issue_principal_token(&principal_id))
+        .await
+        .map_err(|error| ApiError::internal(error.to_string()))?
+        .map(|(token, hash)| (token, hash.as_str().to_string()))
+        .map_err(ApiError::internal)
+}
+
+async fn load_principal(state: &AppState, principal_id: &str) -> Result<AdminPrincipal, ApiError> {
+    state
+        .store
+        .lock()
+        .await
+        .state()
+        .admin_principals
+        .get(principal_id)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("admin principal not found: {principal_id}")))
+}
+
+pub(super) async fn admin_list_principals(
+    Extension(state): Extension<AppState>,
+) -> Json<AdminPrincipalsResponse> {
+    let store = state.store.lock().await;
+    Json(AdminPrincipalsResponse {
+        items: store
+            .state()
+            .admin_principals
+            .values()
+            .cloned()
+            .map(AdminPrincipalView::from)
+            .collect(),
+    })
+}
+
+pub(super) async fn admin_create_principal(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<CreateAdminPrincipalRequest>,
+) -> Result<Json<AdminPrincipalTokenResponse>, ApiError> {
+    let name = req.name.trim().to_string();
+    validate_principal_name(&name).map_err(ApiError::invalid_request)?;
+    let duplicate = state
+        .store
+        .lock()
+        .await
+        .state()
+        .admin_principals
+        .values()
+        .any(|principal| principal.is_active() && principal.name == name);
+    if duplicate {
+        return Err(ApiError::conflict(format!(
+            "an active admin principal is already named {name}"
+        )));
+    }
+
+    let principal_id = new_ulid_string();
+    let (token, token_hash) = issue_token(principal_id.clone()).await?;
+    let principal = AdminPrincipal {
+        principal_id,
+        name,
+        scope: req.scope,
+        token_hash,
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        revoked_at: None,
+    };
+    raft_write(
+        &state,
+        DesiredStateCommand::UpsertAdminPrincipal {
+            principal: principal.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(AdminPrincipalTokenResponse {
+        principal: principal.into(),
+        token,
+    }))
+}
+
+pub(super) async fn admin_rotate_principal_token(
+    Extension(state): Extension<AppState>,
+    Path(principal_id): Path<String>,
+) -> Result<Json<AdminPrincipalTokenResponse>, ApiError> {
+    let mut principal = load_principal(&state, &principal_id).await?;
+    if !principal.is_active() {
+        return Err(ApiError::conflict(format!(
+            "admin principal is revoked: {principal_id}"
+        )));
+    }
+    let (token, token_hash) = issue_token(principal_id).await?;
+    principal.token_hash = token_hash;
+    raft_write(
+        &state,
+        DesiredStateCommand::UpsertAdminPrincipal {
+            principal: principal.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(AdminPrincipalTokenResponse {
+        principal: principal.into(),
+        token,
+    }))
+}
+
+pub(super) async fn admin_revoke_principal(
+    Extension(state): Extension<AppState>,
+    Path(principal_id): Path<String>,
+) -> Result<Json<AdminPrincipalView>, ApiError> {
+    raft_write(
+        &state,
+        DesiredStateCommand::RevokeAdminPrincipal {
+            principal_id: principal_id.clone(),
+            revoked_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        },
+    )
+    .await?;
+    Ok(Json(load_principal(&state, &principal_id).await?.into()))
+}
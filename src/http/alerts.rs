@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    extract::{Extension, Query},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, AppState, history_repository::subscription_access, send_mesh_internal_read};
+use crate::{
+    ip_limit::IpLimitOffender, state::JsonSnapshotStore,
+    subscription_access::SubscriptionAccessSummary,
+};
+
+#[derive(Debug, Deserialize)]
+pub(super) struct AlertsQuery {
+    scope: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct AlertItem {
+    #[serde(rename = "type")]
+    alert_type: String,
+    membership_key: String,
+    user_id: String,
+    endpoint_id: String,
+    owner_node_id: String,
+    quota_banned: bool,
+    quota_banned_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blocked_until: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    offending_ips: Vec<IpLimitOffender>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    threshold_percent: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remaining_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    projected_exhaustion_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subscription_access: Option<SubscriptionAccessSummary>,
+    message: String,
+    action_hint: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct AlertsResponse {
+    pub(super) partial: bool,
+    pub(super) unreachable_nodes: Vec<String>,
+    pub(super) items: Vec<AlertItem>,
+}
+
+const ALERT_TYPE_QUOTA_BANNED: &str = "quota_banned_membership";
+const ALERT_MESSAGE_QUOTA_BANNED: &str = "quota enforced on owner node (membership is blocked)";
+const ALERT_ACTION_HINT_QUOTA_BANNED: &str = "wait for rollover/unban or adjust quota policy";
+const ALERT_TYPE_USER_EXPIRING: &str = "user_expiring";
+const ALERT_TYPE_USER_EXPIRED: &str = "user_expired";
+const ALERT_MESSAGE_USER_EXPIRING: &str = "user expires soon (membership will be removed)";
+const ALERT_MESSAGE_USER_EXPIRED: &str = "user expired (membership is removed from xray)";
+const ALERT_ACTION_HINT_USER_EXPIRY: &str = "extend or clear the user's expires_at";
+const ALERT_TYPE_IP_LIMIT_EXCEEDED: &str = "ip_limit_exceeded";
+const ALERT_MESSAGE_IP_LIMIT_EXCEEDED: &str =
+    "too many concurrent source IPs (membership is temporarily blocked)";
+const ALERT_ACTION_HINT_IP_LIMIT_EXCEEDED: &str =
+    "check the offending IPs for credential sharing or raise max_concurrent_ips";
+const ALERT_TYPE_QUOTA_THRESHOLD_REACHED: &str = "quota_threshold_reached";
+const ALERT_MESSAGE_QUOTA_THRESHOLD_REACHED: &str =
+    "quota usage crossed a warning threshold (membership will be blocked when it runs out)";
+const ALERT_ACTION_HINT_QUOTA_THRESHOLD_REACHED: &str =
+    "add a traffic top-up or adjust quota policy before the quota runs out";
+const ALERT_TYPE_SUBSCRIPTION_ABUSE: &str = "subscription_token_abuse";
+const ALERT_ACTION_HINT_SUBSCRIPTION_ABUSE: &str =
+    "check the user's subscription access log and reset the token if it leaked";
+/// Users expiring within this many days are reported as `user_expiring`.
+const USER_EXPIRY_ALERT_WINDOW_DAYS: i64 = 7;
+
+fn build_local_alerts(
+    store: &JsonSnapshotStore,
+    local_node_id: &str,
+    now: DateTime<Utc>,
+) -> Vec<AlertItem> {
+    let mut items = Vec::new();
+    let endpoints_by_id = store
+        .list_endpoints()
+        .into_iter()
+        .map(|e| (e.endpoint_id.clone(), e))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    for membership in store.state().node_user_endpoint_memberships.iter() {
+        let endpoint = match endpoints_by_id.get(&membership.endpoint_id) {
+            Some(endpoint) => endpoint,
+            None => continue,
+        };
+        if endpoint.node_id != local_node_id {
+            continue;
+        }
+
+        let membership_key =
+            crate::state::membership_key(&membership.user_id, &membership.endpoint_id);
+        let usage = store.get_membership_usage(&membership_key);
+        let quota_banned = usage.as_ref().is_some_and(|usage| usage.quota_banned);
+        let quota_banned_at = usage.and_then(|usage| usage.quota_banned_at);
+        let alert =
+            |alert_type: &str, expires_at: Option<String>, message: &str, hint: &str| AlertItem {
+                alert_type: alert_type.to_string(),
+                membership_key: membership_key.clone(),
+                user_id: membership.user_id.clone(),
+                endpoint_id: endpoint.endpoint_id.clone(),
+                owner_node_id: endpoint.node_id.clone(),
+                quota_banned,
+                quota_banned_at: quota_banned_at.clone(),
+                expires_at,
+                blocked_until: None,
+                offending_ips: Vec::new(),
+                threshold_percent: None,
+                remaining_bytes: None,
+                projected_exhaustion_at: None,
+                subscription_access: None,
+                message: message.to_string(),
+                action_hint: hint.to_string(),
+            };
+
+        if quota_banned {
+            items.push(alert(
+                ALERT_TYPE_QUOTA_BANNED,
+                None,
+                ALERT_MESSAGE_QUOTA_BANNED,
+                ALERT_ACTION_HINT_QUOTA_BANNED,
+            ));
+        }
+
+        if !quota_banned && let Some(warning) = store.quota_warnings().get(&membership.user_id) {
+            items.push(AlertItem {
+                threshold_percent: Some(warning.threshold_percent),
+                remaining_bytes: Some(warning.remaining_bytes),
+                projected_exhaustion_at: warning.projected_exhaustion_at.clone(),
+                ..alert(
+                    ALERT_TYPE_QUOTA_THRESHOLD_REACHED,
+                    None,
+                    ALERT_MESSAGE_QUOTA_THRESHOLD_REACHED,
+                    ALERT_ACTION_HINT_QUOTA_THRESHOLD_REACHED,
+                )
+            });
+        }
+
+        if let Some(block) = store
+            .ip_limit_states()
+            .get(&membership.user_id)
+            .filter(|state| state.is_blocked_at(now))
+        {
+            items.push(AlertItem {
+                blocked_until: block.blocked_until.clone(),
+                offending_ips: block.offending_ips.clone(),
+                ..alert(
+                    ALERT_TYPE_IP_LIMIT_EXCEEDED,
+                    None,
+                    ALERT_MESSAGE_IP_LIMIT_EXCEEDED,
+                    ALERT_ACTION_HINT_IP_LIMIT_EXCEEDED,
+                )
+            });
+        }
+
+        let Some(user) = store.get_user(&membership.user_id) else {
+            continue;
+        };
+        let Some(expires_at) = user.expires_at.clone() else {
+            continue;
+        };
+        let (alert_type, message) = if user.is_expired_at(now) {
+            (ALERT_TYPE_USER_EXPIRED, ALERT_MESSAGE_USER_EXPIRED)
+        } else if user.is_expired_at(now + chrono::Duration::days(USER_EXPIRY_ALERT_WINDOW_DAYS)) {
+            (ALERT_TYPE_USER_EXPIRING, ALERT_MESSAGE_USER_EXPIRING)
+        } else {
+            continue;
+        };
+        items.push(alert(
+            alert_type,
+            Some(expires_at),
+            message,
+            ALERT_ACTION_HINT_USER_EXPIRY,
+        ));
+    }
+    items
+}
+
+pub(super) async fn admin_get_alerts_response(
+    state: &AppState,
+    scope: Option<&str>,
+) -> Result<AlertsResponse, ApiError> {
+    if let Some(scope) = scope
+        && scope != "local"
+    {
+        return Err(ApiError::invalid_request(
+            "invalid scope, expected local or omit",
+        ));
+    }
+
+    let local_node_id = state.cluster.node_id.clone();
+    let local_items = {
+        let store = state.store.lock().await;
+        build_local_alerts(&store, &local_node_id, Utc::now())
+    };
+
+    if scope == Some("local") {
+        return Ok(AlertsResponse {
+            partial: false,
+            unreachable_nodes: Vec::new(),
+            items: local_items,
+        });
+    }
+
+    let nodes = {
+        let store = state.store.lock().await;
+        store.list_nodes()
+    };
+    let client = state.mesh_client.clone();
+
+    let mut items = local_items;
+    let mut unreachable_nodes = Vec::new();
+
+    for node in nodes {
+        if node.node_id == local_node_id {
+            continue;
+        }
+        let base = node.api_base_url.trim_end_matches('/');
+        if base.is_empty() {
+            unreachable_nodes.push(node.node_id);
+            continue;
+        }
+        let response = match send_mesh_internal_read(
+            state,
+            &client,
+            &node,
+            "/api/admin/_internal/alerts".to_string(),
+            Duration::from_secs(3),
+        )
+        .await
+        {
+            Ok(response) => response,
+            _ => {
+                unreachable_nodes.push(node.node_id);
+                continue;
+            }
+        };
+
+        if !response.status().is_success() {
+            unreachable_nodes.push(node.node_id);
+            continue;
+        }
+
+        match response.json::<AlertsResponse>().await {
+            Ok(remote) => items.extend(remote.items),
+            Err(_) => unreachable_nodes.push(node.node_id),
+        }
+    }
+
+    // Token abuse is judged on the cluster-wide access log, so only the aggregating node
+    // reports it.
+    for abuse in subscription_access::subscription_abuse(state, Utc::now()).await? {
+        items.push(AlertItem {
+            alert_type: ALERT_TYPE_SUBSCRIPTION_ABUSE.to_string(),
+            membership_key: String::new(),
+            user_id: abuse.user_id,
+            endpoint_id: String::new(),
+            owner_node_id: String::new(),
+            quota_banned: false,
+            quota_banned_at: None,
+            expires_at: None,
+            blocked_until: None,
+            offending_ips: Vec::new(),
+            threshold_percent: None,
+            remaining_bytes: None,
+            projected_exhaustion_at: None,
+            subscription_access: Some(abuse.summary),
+            message: format!("subscription token {}", abuse.reasons.join(", ")),
+            action_hint: ALERT_ACTION_HINT_SUBSCRIPTION_ABUSE.to_string(),
+        });
+    }
+
+    let partial = !unreachable_nodes.is_empty();
+    Ok(AlertsResponse {
+        partial,
+        unreachable_nodes,
+        items,
+    })
+}
+
+pub(super) async fn admin_get_alerts(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<AlertsQuery>,
+) -> Result<Json<AlertsResponse>, ApiError> {
+    Ok(Json(
+        admin_get_alerts_response(&state, query.scope.as_deref()).await?,
+    ))
+}
+
+pub(super) async fn admin_internal_get_alerts(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<AlertsResponse>, ApiError> {
+    Ok(Json(
+        admin_get_alerts_response(&state, Some("local")).await?,
+    ))
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Extension, Query, RawQuery},
+    http::{HeaderValue, header},
+    response::{IntoResponse, Response},
+};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, AppState, send_mesh_internal_read};
+use crate::{audit::AuditEntry, state::history_storage::AuditLogQuery};
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 1000;
+/// Per-node budget for reading the audit log from a peer; a JSONL export can be large.
+const REMOTE_AUDIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum AuditFormat {
+    #[default]
+    Json,
+    Jsonl,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct AuditQuery {
+    command: Option<String>,
+    /// Any id the command targets, e.g. a user or endpoint id.
+    target: Option<String>,
+    /// `admin_token` or `login_token:<jti>`.
+    principal: Option<String>,
+    origin_node_id: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    before: Option<u64>,
+    limit: Option<usize>,
+    #[serde(default)]
+    format: AuditFormat,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct AuditPage {
+    items: Vec<AuditEntry>,
+    /// Pass as `before` to fetch the next (older) page; absent on the last page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_before: Option<u64>,
+    /// Set when no node with the whole log answered and this node's own entries, which miss
+    /// what it caught up on from a snapshot, were returned instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    partial: bool,
+}
+
+fn parse_bound(name: &str, value: Option<&str>) -> Result<Option<i64>, ApiError> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|at| at.timestamp())
+                .map_err(|_| ApiError::invalid_request(format!("{name} must be RFC3339")))
+        })
+        .transpose()
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+fn audit_filter(query: AuditQuery) -> Result<(AuditLogQuery, AuditFormat), ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if limit == 0 || limit > MAX_PAGE_LIMIT {
+        return Err(ApiError::invalid_request(format!(
+            "limit must be between 1 and {MAX_PAGE_LIMIT}"
+        )));
+    }
+    let filter = AuditLogQuery {
+        since_unix_seconds: parse_bound("since", query.since.as_deref())?,
+        until_unix_seconds: parse_bound("until", query.until.as_deref())?,
+        command: non_empty(query.command),
+        target_id: non_empty(query.target),
+        principal: non_empty(query.principal),
+        origin_node_id: non_empty(query.origin_node_id),
+        before_log_index: query.before,
+        limit,
+    };
+    Ok((filter, query.format))
+}
+
+/// Newest-first audit entries, or the whole filtered log as JSON Lines with `format=jsonl`.
+///
+/// Entries are recorded by each node as it applies the Raft log, so a node that caught up from a
+/// snapshot lacks the older ones. Such a node forwards the read to the first peer that holds the
+/// whole log and only answers from its own entries, marked `partial`, when none does.
+pub(super) async fn admin_get_audit_log(
+    Extension(state): Extension<AppState>,
+    RawQuery(raw_query): RawQuery,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, ApiError> {
+    let (filter, format) = audit_filter(query)?;
+    let complete = state.store.lock().await.audit_log_is_complete();
+    if !complete && let Some(response) = forward_audit_read(&state, raw_query.as_deref()).await {
+        return Ok(response);
+    }
+    read_local_audit_log(&state, filter, format, !complete).await
+}
+
+/// Peer side of the forwarded read; refuses when this node's log is not complete either.
+pub(super) async fn admin_internal_get_audit_log(
+    Extension(state): Extension<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, ApiError> {
+    let (filter, format) = audit_filter(query)?;
+    if !state.store.lock().await.audit_log_is_complete() {
+        return Err(ApiError::conflict(
+            "this node caught up from a snapshot and does not hold the whole audit log",
+        ));
+    }
+    read_local_audit_log(&state, filter, format, false).await
+}
+
+async fn forward_audit_read(state: &AppState, raw_query: Option<&str>) -> Option<Response> {
+    let nodes = state.store.lock().await.list_nodes();
+    let path = match raw_query.filter(|query| !query.is_empty()) {
+        Some(query) => format!("/api/admin/_internal/audit?{query}"),
+        None => "/api/admin/_internal/audit".to_string(),
+    };
+    for node in nodes {
+        if node.node_id == state.cluster.node_id
+            || node.api_base_url.trim_end_matches('/').is_empty()
+        {
+            continue;
+        }
+        let read = send_mesh_internal_read(
+            state,
+            &state.mesh_client,
+            &node,
+            path.clone(),
+            REMOTE_AUDIT_TIMEOUT,
+        );
+        let Ok(remote) = read.await else {
+            continue;
+        };
+        if !remote.status().is_success() {
+            continue;
+        }
+        let headers = [header::CONTENT_TYPE, header::CONTENT_DISPOSITION].map(|name| {
+            let value = remote
+                .headers()
+                .get(name.as_str())
+                .and_then(|value| HeaderValue::from_bytes(value.as_bytes()).ok());
+            (name, value)
+        });
+        let Ok(body) = remote.bytes().await else {
+            continue;
+        };
+        let mut response = Body::from(body).into_response();
+        for (name, value) in headers {
+            if let Some(value) = value {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        return Some(response);
+    }
+    None
+}
+
+async fn read_local_audit_log(
+    state: &AppState,
+    mut filter: AuditLogQuery,
+    format: AuditFormat,
+    partial: bool,
+) -> Result<Response, ApiError> {
+    let limit = filter.limit;
+    let store = state.store.lock().await;
+    let read = |filter: &AuditLogQuery| {
+        store
+            .audit_entries(filter)
+            .map_err(|error| ApiError::internal(error.to_string()))
+    };
+    match format {
+        AuditFormat::Json => {
+            let items = read(&filter)?;
+            let next_before = (items.len() == limit)
+                .then(|| items.last().map(|entry| entry.log_index))
+                .flatten();
+            Ok(Json(AuditPage {
+                items,
+                next_before,
+                partial,
+            })
+            .into_response())
+        }
+        AuditFormat::Jsonl => {
+            filter.limit = MAX_PAGE_LIMIT;
+            let mut body = Vec::new();
+            loop {
+                let items = read(&filter)?;
+                for entry in &items {
+                    serde_json::to_writer(&mut body, entry)
+                        .map_err(|error| ApiError::internal(error.to_string()))?;
+                    body.push(b'\n');
+                }
+                match items.last() {
+                    Some(last) if items.len() == filter.limit => {
+                        filter.before_log_index = Some(last.log_index);
+                    }
+                    _ => break,
+                }
+            }
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "application/x-ndjson"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"xp-audit.jsonl\"",
+                    ),
+                ],
+                Body::from(body),
+            )
+                .into_response())
+        }
+    }
+}
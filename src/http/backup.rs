@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use axum::{Json, extract::Extension};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    backup::{BackupArtifact, RestoreConflict, create_backup, plan_restore},
+    state::DesiredStateCommand,
+};
+
+/// Backups carry the whole desired state and can outgrow the default 2 MiB request limit.
+pub(super) const RESTORE_BODY_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct RestoreBackupRequest {
+    artifact: BackupArtifact,
+    /// Backed-up node id to target node id; unlisted nodes are matched by id, then by name.
+    #[serde(default)]
+    node_map: BTreeMap<String, String>,
+    #[serde(default)]
+    dry_run: bool,
+    /// Hex public key the artifact must be signed with. The key embedded in the artifact only
+    /// proves the file is intact, not who wrote it, so a restore needs this unless
+    /// `allow_untrusted_signer` is set.
+    #[serde(default)]
+    trusted_signer: Option<String>,
+    /// Accept an artifact signed by any key. Anyone can re-sign an edited state, so this is
+    /// only for backups whose provenance was checked some other way.
+    #[serde(default)]
+    allow_untrusted_signer: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct RestoreBackupResponse {
+    dry_run: bool,
+    applied: bool,
+    source_cluster_id: String,
+    signer_public_key: String,
+    node_map: BTreeMap<String, String>,
+    conflicts: Vec<RestoreConflictView>,
+    users: usize,
+    endpoints: usize,
+    memberships: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct RestoreConflictView {
+    #[serde(flatten)]
+    conflict: RestoreConflict,
+    message: String,
+}
+
+pub(super) async fn admin_get_backup(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<BackupArtifact>, ApiError> {
+    let cluster_ca_key = state
+        .cluster_ca_key_pem
+        .as_deref()
+        .ok_or_else(|| ApiError::conflict("backup signing material is unavailable on this node"))?;
+    let store = state.store.lock().await;
+    let artifact = create_backup(
+        store.state(),
+        &state.cluster.cluster_id,
+        cluster_ca_key,
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+    .map_err(|error| ApiError::internal(error.to_string()))?;
+    Ok(Json(artifact))
+}
+
+pub(super) async fn admin_restore_backup(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<RestoreBackupRequest>,
+) -> Result<Json<RestoreBackupResponse>, ApiError> {
+    let trusted_signer = req
+        .trusted_signer
+        .as_deref()
+        .filter(|signer| !signer.trim().is_empty());
+    if trusted_signer.is_none() && !req.allow_untrusted_signer {
+        return Err(ApiError::invalid_request(
+            "trusted_signer is required: pass the signer key printed when the backup was created, \
+             or set allow_untrusted_signer",
+        ));
+    }
+    let source = req
+        .artifact
+        .verify(trusted_signer)
+        .map_err(|error| ApiError::invalid_request(error.to_string()))?;
+    let plan = {
+        let store = state.store.lock().await;
+        plan_restore(source, store.state(), &req.node_map)
+    };
+    let mut response = RestoreBackupResponse {
+        dry_run: req.dry_run,
+        applied: false,
+        source_cluster_id: req.artifact.cluster_id.clone(),
+        signer_public_key: req.artifact.signer_public_key.clone(),
+        node_map: plan.node_map,
+        conflicts: plan
+            .conflicts
+            .iter()
+            .map(|conflict| RestoreConflictView {
+                conflict: conflict.clone(),
+                message: conflict.to_string(),
+            })
+            .collect(),
+        users: plan.restored.users.len(),
+        endpoints: plan.restored.endpoints.len(),
+        memberships: plan.restored.node_user_endpoint_memberships.len(),
+    };
+    if req.dry_run {
+        return Ok(Json(response));
+    }
+    if let Some(conflict) = plan.conflicts.first() {
+        return Err(ApiError::conflict(format!(
+            "restore has {} conflict(s), run a dry run for the full list: {conflict}",
+            plan.conflicts.len()
+        )));
+    }
+    raft_write(
+        &state,
+        DesiredStateCommand::RestoreBackup {
+            restored: Box::new(plan.restored),
+        },
+    )
+    .await?;
+    state.reconcile.request_full();
+    response.applied = true;
+    Ok(Json(response))
+}
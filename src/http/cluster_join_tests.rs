@@ -177,6 +177,9 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: test_admin_token_hash(),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -205,9 +208,14 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
@@ -0,0 +1,209 @@
+use axum::{Json, extract::Extension};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    credential_rotation::{
+        CredentialRoot, CredentialRotation, CredentialRotationNodeStage, CredentialRotationPhase,
+        DEFAULT_GRACE_PERIOD_HOURS, MAX_GRACE_PERIOD_HOURS, rfc3339,
+    },
+    state::{DesiredStateCommand, PersistedState},
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct StartCredentialRotationRequest {
+    /// Hours both roots stay accepted after the last node switched; defaults to a week.
+    #[serde(default)]
+    grace_period_hours: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct CredentialRotationResponse {
+    rotation: Option<CredentialRotationView>,
+}
+
+/// A rotation without its key material.
+#[derive(Debug, Serialize)]
+struct CredentialRotationView {
+    rotation_id: String,
+    phase: CredentialRotationPhase,
+    started_at: String,
+    promoted_at: Option<String>,
+    grace_period_hours: u32,
+    previous_ca_fingerprint: String,
+    next_ca_fingerprint: String,
+    /// Set once every node switched.
+    retire_not_before: Option<String>,
+    /// Why promoting is not possible yet; `None` when it is.
+    promote_blocked_by: Option<String>,
+    /// Why retiring is not possible yet; `None` when it is.
+    retire_blocked_by: Option<String>,
+    nodes: Vec<CredentialRotationNodeView>,
+}
+
+#[derive(Debug, Serialize)]
+struct CredentialRotationNodeView {
+    node_id: String,
+    node_name: String,
+    stage: CredentialRotationNodeStage,
+    cert_issued_at: Option<String>,
+    files_switched_at: Option<String>,
+    switched_at: Option<String>,
+}
+
+fn rotation_view(state: &PersistedState) -> Option<CredentialRotationView> {
+    let rotation = state.credential_rotation.as_ref()?;
+    let now = rfc3339(Utc::now());
+    let nodes = state
+        .nodes
+        .values()
+        .map(|node| {
+            let progress = rotation
+                .nodes
+                .get(&node.node_id)
+                .cloned()
+                .unwrap_or_default();
+            CredentialRotationNodeView {
+                node_id: node.node_id.clone(),
+                node_name: node.node_name.clone(),
+                stage: progress.stage(),
+                cert_issued_at: progress.cert_issued_at,
+                files_switched_at: progress.files_switched_at,
+                switched_at: progress.switched_at,
+            }
+        })
+        .collect();
+    Some(CredentialRotationView {
+        rotation_id: rotation.rotation_id.clone(),
+        phase: rotation.phase,
+        started_at: rotation.started_at.clone(),
+        promoted_at: rotation.promoted_at.clone(),
+        grace_period_hours: rotation.grace_period_hours,
+        previous_ca_fingerprint: rotation.previous.fingerprint(),
+        next_ca_fingerprint: rotation.next.fingerprint(),
+        retire_not_before: rotation.retire_not_before(state.nodes.keys()).map(rfc3339),
+        promote_blocked_by: rotation.check_promote(state.nodes.keys()).err(),
+        retire_blocked_by: rotation.check_retire(state.nodes.keys(), &now).err(),
+        nodes,
+    })
+}
+
+async fn rotation_response(state: &AppState) -> CredentialRotationResponse {
+    let store = state.store.lock().await;
+    CredentialRotationResponse {
+        rotation: rotation_view(store.state()),
+    }
+}
+
+async fn current_rotation_id(state: &AppState) -> Result<String, ApiError> {
+    state
+        .store
+        .lock()
+        .await
+        .state()
+        .credential_rotation
+        .as_ref()
+        .map(|rotation| rotation.rotation_id.clone())
+        .ok_or_else(|| ApiError::not_found("no credential rotation is in progress"))
+}
+
+pub(super) async fn admin_get_credential_rotation(
+    Extension(state): Extension<AppState>,
+) -> Json<CredentialRotationResponse> {
+    Json(rotation_response(&state).await)
+}
+
+pub(super) async fn admin_start_credential_rotation(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<StartCredentialRotationRequest>,
+) -> Result<Json<CredentialRotationResponse>, ApiError> {
+    let grace_period_hours = req.grace_period_hours.unwrap_or(DEFAULT_GRACE_PERIOD_HOURS);
+    if grace_period_hours > MAX_GRACE_PERIOD_HOURS {
+        return Err(ApiError::invalid_request(format!(
+            "grace_period_hours must be at most {MAX_GRACE_PERIOD_HOURS}"
+        )));
+    }
+    let ca_key_pem = state
+        .cluster_ca_key_pem
+        .as_ref()
+        .clone()
+        .ok_or_else(|| ApiError::conflict("cluster ca key is not available on this node"))?;
+    if let Some(operation) = state
+        .store
+        .lock()
+        .await
+        .state()
+        .active_membership_operation()
+    {
+        return Err(ApiError::conflict(format!(
+            "membership operation {} is in progress",
+            operation.operation_id
+        )));
+    }
+
+    let rotation = CredentialRotation::start(
+        &state.cluster.cluster_id,
+        CredentialRoot {
+            ca_cert_pem: state.cluster_ca_pem.as_ref().clone(),
+            ca_key_pem,
+        },
+        grace_period_hours,
+        rfc3339(Utc::now()),
+    )
+    .map_err(|error| ApiError::internal(error.to_string()))?;
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::StartCredentialRotation {
+            rotation: Box::new(rotation),
+        },
+    )
+    .await?;
+    state.reconcile.request_full();
+    Ok(Json(rotation_response(&state).await))
+}
+
+pub(super) async fn admin_promote_credential_rotation(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<CredentialRotationResponse>, ApiError> {
+    let rotation_id = current_rotation_id(&state).await?;
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::PromoteCredentialRotation {
+            rotation_id,
+            promoted_at: rfc3339(Utc::now()),
+        },
+    )
+    .await?;
+    Ok(Json(rotation_response(&state).await))
+}
+
+pub(super) async fn admin_retire_credential_rotation(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<CredentialRotationResponse>, ApiError> {
+    let rotation_id = current_rotation_id(&state).await?;
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::RetireCredentialRotation {
+            rotation_id,
+            retired_at: rfc3339(Utc::now()),
+        },
+    )
+    .await?;
+    state.reconcile.request_full();
+    Ok(Json(rotation_response(&state).await))
+}
+
+pub(super) async fn admin_abort_credential_rotation(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<CredentialRotationResponse>, ApiError> {
+    let rotation_id = current_rotation_id(&state).await?;
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::AbortCredentialRotation { rotation_id },
+    )
+    .await?;
+    state.reconcile.request_full();
+    Ok(Json(rotation_response(&state).await))
+}
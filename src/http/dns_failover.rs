@@ -0,0 +1,112 @@
+use std::net::IpAddr;
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::StatusCode,
+};
+use serde::Deserialize;
+
+use super::{ApiError, ApiJson, AppState, Items, raft_write};
+use crate::{dns_failover::DnsFailoverGroup, id::new_ulid_string, state::DesiredStateCommand};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct DnsFailoverGroupRequest {
+    hostname: String,
+    primary_node_id: String,
+    standby_node_ids: Vec<String>,
+    #[serde(default)]
+    primary_addresses: Vec<IpAddr>,
+}
+
+impl DnsFailoverGroupRequest {
+    fn into_group(self, group_id: String) -> DnsFailoverGroup {
+        DnsFailoverGroup {
+            group_id,
+            hostname: self
+                .hostname
+                .trim()
+                .trim_end_matches('.')
+                .to_ascii_lowercase(),
+            primary_node_id: self.primary_node_id.trim().to_string(),
+            standby_node_ids: self
+                .standby_node_ids
+                .iter()
+                .map(|node_id| node_id.trim().to_string())
+                .collect(),
+            primary_addresses: self.primary_addresses,
+        }
+    }
+}
+
+pub(super) async fn admin_list_dns_failover_groups(
+    Extension(state): Extension<AppState>,
+) -> Json<Items<DnsFailoverGroup>> {
+    let store = state.store.lock().await;
+    Json(Items {
+        items: store
+            .state()
+            .dns_failover_groups
+            .values()
+            .cloned()
+            .collect(),
+    })
+}
+
+pub(super) async fn admin_create_dns_failover_group(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<DnsFailoverGroupRequest>,
+) -> Result<Json<DnsFailoverGroup>, ApiError> {
+    let group = req.into_group(new_ulid_string());
+    group.validate().map_err(ApiError::invalid_request)?;
+    raft_write(
+        &state,
+        DesiredStateCommand::UpsertDnsFailoverGroup {
+            group: group.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(group))
+}
+
+pub(super) async fn admin_put_dns_failover_group(
+    Extension(state): Extension<AppState>,
+    Path(group_id): Path<String>,
+    ApiJson(req): ApiJson<DnsFailoverGroupRequest>,
+) -> Result<Json<DnsFailoverGroup>, ApiError> {
+    let exists = state
+        .store
+        .lock()
+        .await
+        .state()
+        .dns_failover_groups
+        .contains_key(&group_id);
+    if !exists {
+        return Err(ApiError::not_found(format!(
+            "dns failover group not found: {group_id}"
+        )));
+    }
+    let group = req.into_group(group_id);
+    group.validate().map_err(ApiError::invalid_request)?;
+    raft_write(
+        &state,
+        DesiredStateCommand::UpsertDnsFailoverGroup {
+            group: group.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(group))
+}
+
+pub(super) async fn admin_delete_dns_failover_group(
+    Extension(state): Extension<AppState>,
+    Path(group_id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    raft_write(
+        &state,
+        DesiredStateCommand::DeleteDnsFailoverGroup { group_id },
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
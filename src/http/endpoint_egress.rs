@@ -0,0 +1,99 @@
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use serde::Serialize;
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{egress::EndpointEgress, state::DesiredStateCommand};
+
+/// Egress as returned by the API. The SOCKS password is write-only: responses only report
+/// whether one is set.
+#[derive(Debug, Serialize)]
+pub(super) struct EndpointEgressView {
+    #[serde(flatten)]
+    egress: EndpointEgress,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    has_password: Option<bool>,
+}
+
+impl EndpointEgressView {
+    fn new(mut egress: EndpointEgress) -> Self {
+        let has_password = match &mut egress {
+            EndpointEgress::Socks { password, .. } => Some(password.take().is_some()),
+            _ => None,
+        };
+        Self {
+            egress,
+            has_password,
+        }
+    }
+}
+
+pub(super) async fn admin_get_endpoint_egress(
+    Extension(state): Extension<AppState>,
+    Path(endpoint_id): Path<String>,
+) -> Result<Json<EndpointEgressView>, ApiError> {
+    let store = state.store.lock().await;
+    if store.get_endpoint(&endpoint_id).is_none() {
+        return Err(ApiError::not_found(format!(
+            "endpoint not found: {endpoint_id}"
+        )));
+    }
+    Ok(Json(EndpointEgressView::new(
+        store
+            .state()
+            .endpoint_egress
+            .get(&endpoint_id)
+            .cloned()
+            .unwrap_or_default(),
+    )))
+}
+
+pub(super) async fn admin_put_endpoint_egress(
+    Extension(state): Extension<AppState>,
+    Path(endpoint_id): Path<String>,
+    ApiJson(mut req): ApiJson<EndpointEgress>,
+) -> Result<Json<EndpointEgressView>, ApiError> {
+    match &mut req {
+        EndpointEgress::Direct => {}
+        EndpointEgress::Node { node_id } => *node_id = node_id.trim().to_string(),
+        EndpointEgress::Socks { address, .. } => *address = address.trim().to_string(),
+        EndpointEgress::XrayOutbound { tag } => *tag = tag.trim().to_string(),
+    }
+    {
+        let store = state.store.lock().await;
+        if store.get_endpoint(&endpoint_id).is_none() {
+            return Err(ApiError::not_found(format!(
+                "endpoint not found: {endpoint_id}"
+            )));
+        }
+        // A body read back from GET carries no password; keep the stored one for the same user.
+        if let EndpointEgress::Socks {
+            username: Some(username),
+            password,
+            ..
+        } = &mut req
+            && password.is_none()
+            && let Some(EndpointEgress::Socks {
+                username: Some(stored_username),
+                password: stored_password,
+                ..
+            }) = store.state().endpoint_egress.get(&endpoint_id)
+            && stored_username == username
+        {
+            *password = stored_password.clone();
+        }
+    }
+    req.validate().map_err(ApiError::invalid_request)?;
+    raft_write(
+        &state,
+        DesiredStateCommand::SetEndpointEgress {
+            endpoint_id,
+            egress: req.clone(),
+        },
+    )
+    .await?;
+    state.reconcile.request_full();
+    Ok(Json(EndpointEgressView::new(req)))
+}
@@ -0,0 +1,197 @@
+use serde_json::json;
+
+use super::ApiError;
+use super::endpoint_requests::{PatchEndpointRequest, RealityConfig};
+use crate::{
+    domain::{Endpoint, Node},
+    protocol::{
+        CanaryUpstreamConfig, MihomoSmuxConfig, RealityServerNamesSource,
+        SS2022_METHOD_2022_BLAKE3_AES_128_GCM, Ss2022EndpointMeta, TrojanRealityTcpEndpointMeta,
+        TrojanTlsTcpEndpointMeta, ss2022_psk_len_bytes,
+    },
+};
+
+pub(super) fn ss2022_create_meta(
+    method: Option<String>,
+    canary_upstream: Option<CanaryUpstreamConfig>,
+    accepted_authorities: Option<Vec<String>>,
+    mihomo_smux: Option<MihomoSmuxConfig>,
+) -> Result<serde_json::Value, ApiError> {
+    if canary_upstream.is_some() {
+        return Err(ApiError::invalid_request(
+            "canary_upstream is only supported for vless endpoints",
+        ));
+    }
+    if accepted_authorities.is_some() {
+        return Err(ApiError::invalid_request(
+            "accepted_authorities is only supported for managed vless endpoints",
+        ));
+    }
+    let method = method.unwrap_or_else(|| SS2022_METHOD_2022_BLAKE3_AES_128_GCM.into());
+    if ss2022_psk_len_bytes(&method).is_none() {
+        return Err(ApiError::invalid_request(format!(
+            "unsupported ss2022 method: {method}"
+        )));
+    }
+    let mihomo_smux = mihomo_smux.unwrap_or_default();
+    mihomo_smux.validate().map_err(ApiError::invalid_request)?;
+    Ok(json!({ "method": method, "mihomo_smux": mihomo_smux }))
+}
+
+pub(super) fn trojan_create_meta(
+    reality: RealityConfig,
+    mihomo_smux: Option<MihomoSmuxConfig>,
+) -> Result<serde_json::Value, ApiError> {
+    if reality.server_names_source != RealityServerNamesSource::Manual {
+        return Err(ApiError::invalid_request(
+            "trojan endpoints only support manual reality server_names",
+        ));
+    }
+    let mihomo_smux = mihomo_smux.unwrap_or_default();
+    mihomo_smux.validate().map_err(ApiError::invalid_request)?;
+    Ok(json!({ "reality": reality, "mihomo_smux": mihomo_smux }))
+}
+
+pub(super) fn trojan_tls_create_meta(
+    node: &Node,
+    server_name: Option<String>,
+    mihomo_smux: Option<MihomoSmuxConfig>,
+) -> Result<serde_json::Value, ApiError> {
+    let server_name = tls_server_name(node, server_name)?;
+    let mihomo_smux = mihomo_smux.unwrap_or_default();
+    mihomo_smux.validate().map_err(ApiError::invalid_request)?;
+    Ok(json!({ "server_name": server_name, "mihomo_smux": mihomo_smux }))
+}
+
+pub(super) fn hysteria2_create_meta(
+    node: &Node,
+    server_name: Option<String>,
+) -> Result<serde_json::Value, ApiError> {
+    Ok(json!({ "server_name": tls_server_name(node, server_name)? }))
+}
+
+/// Server name for a self-signed endpoint certificate, defaulting to the node's access_host.
+fn tls_server_name(node: &Node, server_name: Option<String>) -> Result<String, ApiError> {
+    match server_name {
+        Some(server_name) => Ok(server_name),
+        None if node.access_host.trim().is_empty() => Err(ApiError::invalid_request(
+            "server_name is required when the node has no access_host",
+        )),
+        None => Ok(node.access_host.clone()),
+    }
+}
+
+/// Rejects patch fields that only apply to VLESS endpoints.
+fn reject_vless_only_fields(req: &PatchEndpointRequest) -> Result<(), ApiError> {
+    if req.transport.is_some() {
+        return Err(ApiError::invalid_request(
+            "transport is only supported for vless endpoints",
+        ));
+    }
+    if req.canary_upstream.is_some() {
+        return Err(ApiError::invalid_request(
+            "canary_upstream is only supported for vless endpoints",
+        ));
+    }
+    if req.accepted_authorities.is_some() {
+        return Err(ApiError::invalid_request(
+            "accepted_authorities is only supported for managed vless endpoints",
+        ));
+    }
+    Ok(())
+}
+
+fn patched_mihomo_smux(
+    patch: Option<Option<MihomoSmuxConfig>>,
+) -> Result<Option<MihomoSmuxConfig>, ApiError> {
+    let Some(mihomo_smux) = patch else {
+        return Ok(None);
+    };
+    let Some(mihomo_smux) = mihomo_smux else {
+        return Err(ApiError::invalid_request("mihomo_smux cannot be null"));
+    };
+    mihomo_smux.validate().map_err(ApiError::invalid_request)?;
+    Ok(Some(mihomo_smux))
+}
+
+pub(super) fn patch_ss2022_meta(
+    endpoint: &mut Endpoint,
+    req: PatchEndpointRequest,
+) -> Result<(), ApiError> {
+    reject_vless_only_fields(&req)?;
+    if req.reality.is_some() {
+        return Err(ApiError::invalid_request(
+            "ss2022 endpoints only support port updates",
+        ));
+    }
+    if let Some(mihomo_smux) = patched_mihomo_smux(req.mihomo_smux)? {
+        let mut meta: Ss2022EndpointMeta = serde_json::from_value(endpoint.meta.clone())
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        meta.mihomo_smux = mihomo_smux;
+        endpoint.meta =
+            serde_json::to_value(meta).map_err(|e| ApiError::internal(e.to_string()))?;
+    }
+    Ok(())
+}
+
+pub(super) fn patch_trojan_meta(
+    endpoint: &mut Endpoint,
+    req: PatchEndpointRequest,
+) -> Result<(), ApiError> {
+    reject_vless_only_fields(&req)?;
+    let mut meta: TrojanRealityTcpEndpointMeta = serde_json::from_value(endpoint.meta.clone())
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    if let Some(reality) = req.reality {
+        let Some(reality) = reality else {
+            return Err(ApiError::invalid_request(
+                "reality cannot be null for trojan endpoints",
+            ));
+        };
+        if reality.server_names_source != RealityServerNamesSource::Manual {
+            return Err(ApiError::invalid_request(
+                "trojan endpoints only support manual reality server_names",
+            ));
+        }
+        meta.reality = crate::protocol::RealityConfig {
+            dest: reality.dest,
+            server_names: reality.server_names,
+            server_names_source: reality.server_names_source,
+            fingerprint: reality.fingerprint,
+        };
+    }
+    if let Some(mihomo_smux) = patched_mihomo_smux(req.mihomo_smux)? {
+        meta.mihomo_smux = mihomo_smux;
+    }
+    endpoint.meta = serde_json::to_value(meta).map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok(())
+}
+
+pub(super) fn patch_trojan_tls_meta(
+    endpoint: &mut Endpoint,
+    req: PatchEndpointRequest,
+) -> Result<(), ApiError> {
+    reject_vless_only_fields(&req)?;
+    if req.reality.is_some() {
+        return Err(ApiError::invalid_request(
+            "reality is not supported for trojan tls endpoints",
+        ));
+    }
+    if let Some(mihomo_smux) = patched_mihomo_smux(req.mihomo_smux)? {
+        let mut meta: TrojanTlsTcpEndpointMeta = serde_json::from_value(endpoint.meta.clone())
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        meta.mihomo_smux = mihomo_smux;
+        endpoint.meta =
+            serde_json::to_value(meta).map_err(|e| ApiError::internal(e.to_string()))?;
+    }
+    Ok(())
+}
+
+pub(super) fn patch_hysteria2_meta(req: &PatchEndpointRequest) -> Result<(), ApiError> {
+    reject_vless_only_fields(req)?;
+    if req.reality.is_some() || req.mihomo_smux.is_some() {
+        return Err(ApiError::invalid_request(
+            "hysteria2 endpoints only support node_id and port updates",
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use axum::{Json, extract::Extension};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, Items, raft_write};
+use crate::{
+    endpoint_probe::{
+        ProbeTarget, UnreachableProbeTarget, probe_config_hash, unreachable_probe_targets,
+        validate_probe_targets,
+    },
+    state::{DesiredStateCommand, EndpointProbeHistory, PersistedState},
+};
+
+#[derive(Debug, Serialize)]
+pub(super) struct AdminEndpointProbeTargetsResponse {
+    targets: Vec<ProbeTarget>,
+    config_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct AdminPutEndpointProbeTargetsRequest {
+    targets: Vec<ProbeTarget>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(super) struct AdminUnreachableProbeTarget {
+    endpoint_id: String,
+    #[serde(flatten)]
+    target: UnreachableProbeTarget,
+    target_label: String,
+    target_url: String,
+}
+
+pub(super) fn unreachable_targets_for_endpoint(
+    state: &PersistedState,
+    endpoint_id: &str,
+    history: &EndpointProbeHistory,
+) -> Vec<AdminUnreachableProbeTarget> {
+    let targets = &state.endpoint_probe_targets;
+    unreachable_probe_targets(history, targets)
+        .into_iter()
+        .filter_map(|target| {
+            let config = targets.iter().find(|t| t.id == target.target_id)?;
+            Some(AdminUnreachableProbeTarget {
+                endpoint_id: endpoint_id.to_string(),
+                target_label: config.label.clone(),
+                target_url: config.url.clone(),
+                target,
+            })
+        })
+        .collect()
+}
+
+fn targets_response(targets: Vec<ProbeTarget>) -> AdminEndpointProbeTargetsResponse {
+    AdminEndpointProbeTargetsResponse {
+        config_hash: probe_config_hash(&targets),
+        targets,
+    }
+}
+
+pub(super) async fn admin_get_endpoint_probe_targets(
+    Extension(state): Extension<AppState>,
+) -> Json<AdminEndpointProbeTargetsResponse> {
+    let store = state.store.lock().await;
+    Json(targets_response(
+        store.state().endpoint_probe_targets.clone(),
+    ))
+}
+
+pub(super) async fn admin_put_endpoint_probe_targets(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<AdminPutEndpointProbeTargetsRequest>,
+) -> Result<Json<AdminEndpointProbeTargetsResponse>, ApiError> {
+    let targets = req
+        .targets
+        .into_iter()
+        .map(|mut target| {
+            target.id = target.id.trim().to_string();
+            target.url = target.url.trim().to_string();
+            target.label = target.label.trim().to_string();
+            if target.label.is_empty() {
+                target.label = target.id.clone();
+            }
+            target
+        })
+        .collect::<Vec<_>>();
+    validate_probe_targets(&targets).map_err(ApiError::invalid_request)?;
+    raft_write(
+        &state,
+        DesiredStateCommand::SetEndpointProbeTargets {
+            targets: targets.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(targets_response(targets)))
+}
+
+/// Cluster-wide view of endpoints whose probes currently fail a configured target.
+pub(super) async fn admin_list_unreachable_probe_targets(
+    Extension(state): Extension<AppState>,
+) -> Json<Items<AdminUnreachableProbeTarget>> {
+    let store = state.store.lock().await;
+    let items = store
+        .state()
+        .endpoint_probe_history
+        .iter()
+        .filter(|(endpoint_id, _history)| store.get_endpoint(endpoint_id).is_some())
+        .flat_map(|(endpoint_id, history)| {
+            unreachable_targets_for_endpoint(store.state(), endpoint_id, history)
+        })
+        .collect();
+    Json(Items { items })
+}
@@ -22,6 +22,15 @@ where
     Ok(Some(Option::<String>::deserialize(deserializer)?))
 }
 
+pub(super) fn deserialize_optional_u32<'de, D>(
+    deserializer: D,
+) -> Result<Option<Option<u32>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Some(Option::<u32>::deserialize(deserializer)?))
+}
+
 fn deserialize_optional_reality<'de, D>(
     deserializer: D,
 ) -> Result<Option<Option<RealityConfig>>, D::Error>
@@ -93,12 +102,37 @@ pub(super) enum CreateEndpointRequest {
         node_id: String,
         port: u16,
         #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
         canary_upstream: Option<CanaryUpstreamConfig>,
         #[serde(default)]
         accepted_authorities: Option<Vec<String>>,
         #[serde(default)]
         mihomo_smux: Option<MihomoSmuxConfig>,
     },
+    TrojanRealityTcp {
+        node_id: String,
+        port: u16,
+        reality: RealityConfig,
+        #[serde(default)]
+        mihomo_smux: Option<MihomoSmuxConfig>,
+    },
+    TrojanTlsTcp {
+        node_id: String,
+        port: u16,
+        /// TLS server name for the generated certificate; defaults to the node's access_host.
+        #[serde(default)]
+        server_name: Option<String>,
+        #[serde(default)]
+        mihomo_smux: Option<MihomoSmuxConfig>,
+    },
+    Hysteria2 {
+        node_id: String,
+        port: u16,
+        /// TLS server name for the generated certificate; defaults to the node's access_host.
+        #[serde(default)]
+        server_name: Option<String>,
+    },
 }
 
 #[derive(Deserialize)]
@@ -37,6 +37,7 @@ pub(super) const INTERNAL_HISTORY_REPOSITORY_RELAY_DELIVER: &str =
     "/api/admin/_internal/history-repository/relay-deliver";
 
 pub(super) mod gaps;
+pub(super) mod subscription_access;
 mod worker;
 pub(crate) use worker::spawn_repository_replica_worker;
 
@@ -0,0 +1,216 @@
+use super::*;
+use crate::subscription_access::{
+    SubscriptionAbuseThresholds, SubscriptionAccessEntry, SubscriptionAccessSummary,
+};
+use axum::extract::Path;
+use chrono::{DateTime, Duration};
+
+/// Upper bound on fetches read from one log or repository replica per request.
+const MAX_SUBSCRIPTION_ACCESS_ITEMS: usize = 5000;
+const INTERNAL_SUBSCRIPTION_ACCESS: &str =
+    "/api/admin/_internal/history-repository/subscription-access";
+
+#[derive(Debug, Deserialize)]
+pub(in crate::http) struct SubscriptionAccessQuery {
+    /// RFC3339; defaults to the start of the abuse detection window.
+    since: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(in crate::http) struct SubscriptionAccessResponse {
+    /// Set when no history repository answered, so only this node's own fetches are included.
+    partial: bool,
+    since: String,
+    summary: SubscriptionAccessSummary,
+    /// Why the token looks shared over the detection window; empty when it does not.
+    abuse_reasons: Vec<String>,
+    /// Newest first.
+    items: Vec<SubscriptionAccessEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(in crate::http) struct InternalSubscriptionAccessRequest {
+    user_id: Option<String>,
+    since_unix_seconds: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(in crate::http) struct InternalSubscriptionAccessResponse {
+    items: Vec<KeyedSubscriptionAccess>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyedSubscriptionAccess {
+    record_key: String,
+    entry: SubscriptionAccessEntry,
+}
+
+/// A user whose token was fetched more widely than the configured thresholds allow.
+pub(in crate::http) struct SubscriptionAbuse {
+    pub(in crate::http) user_id: String,
+    pub(in crate::http) summary: SubscriptionAccessSummary,
+    pub(in crate::http) reasons: Vec<String>,
+}
+
+pub(in crate::http) fn abuse_thresholds(state: &AppState) -> SubscriptionAbuseThresholds {
+    SubscriptionAbuseThresholds {
+        max_ips: state.config.subscription_abuse_max_ips,
+        max_countries: state.config.subscription_abuse_max_countries,
+    }
+}
+
+fn abuse_window_start(state: &AppState, now: DateTime<Utc>) -> DateTime<Utc> {
+    now - Duration::hours(i64::from(state.config.subscription_abuse_window_hours))
+}
+
+/// Fetches this node can see without asking peers: its own log plus its repository replica.
+async fn local_subscription_access(
+    state: &AppState,
+    user_id: Option<&str>,
+    since: DateTime<Utc>,
+) -> Result<Vec<(String, SubscriptionAccessEntry)>, ApiError> {
+    let store = state.store.lock().await;
+    let mut items =
+        store.repository_subscription_access(user_id, since, MAX_SUBSCRIPTION_ACCESS_ITEMS)?;
+    items.extend(store.local_subscription_access(user_id, since, MAX_SUBSCRIPTION_ACCESS_ITEMS)?);
+    Ok(items)
+}
+
+/// Cluster-wide fetches since `since`, merged from every ready history repository and this
+/// node's own log, with countries filled from the IP geo cache. Returns the entries newest first
+/// and whether the view is partial.
+async fn cluster_subscription_access(
+    state: &AppState,
+    user_id: Option<&str>,
+    since: DateTime<Utc>,
+) -> Result<(Vec<SubscriptionAccessEntry>, bool), ApiError> {
+    let mut merged = local_subscription_access(state, user_id, since)
+        .await?
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let (ready_repository_ids, peers) = worker::ready_repository_peers(state)
+        .await
+        .unwrap_or_default();
+    let mut answered = ready_repository_ids
+        .iter()
+        .any(|repository_id| repository_id == &state.cluster.node_id);
+    let body = serde_json::to_vec(&InternalSubscriptionAccessRequest {
+        user_id: user_id.map(str::to_owned),
+        since_unix_seconds: since.timestamp(),
+    })
+    .map_err(|error| ApiError::internal(error.to_string()))?;
+    for peer in peers
+        .iter()
+        .filter(|peer| peer.node_id != state.cluster.node_id)
+        .take(MAX_REPAIR_REQUEST_IDS)
+    {
+        if let Ok(response) =
+            worker::repository_direct_request::<InternalSubscriptionAccessResponse>(
+                state,
+                peer,
+                axum::http::Method::POST,
+                INTERNAL_SUBSCRIPTION_ACCESS,
+                body.clone(),
+            )
+            .await
+        {
+            answered = true;
+            merged.extend(
+                response
+                    .items
+                    .into_iter()
+                    .map(|item| (item.record_key, item.entry)),
+            );
+        }
+    }
+
+    let geo_resolver = state.geo_db_update.resolver();
+    let mut items = merged.into_values().collect::<Vec<_>>();
+    for item in &mut items {
+        item.resolve_country(&geo_resolver);
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.accessed_at_unix_seconds()));
+    Ok((items, !answered))
+}
+
+pub(in crate::http) async fn admin_get_user_subscription_access(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<SubscriptionAccessQuery>,
+) -> Result<Json<SubscriptionAccessResponse>, ApiError> {
+    if state.store.lock().await.get_user(&user_id).is_none() {
+        return Err(ApiError::not_found(format!("user not found: {user_id}")));
+    }
+    let now = Utc::now();
+    let window_start = abuse_window_start(&state, now);
+    let since = match query.since.as_deref() {
+        Some(raw) => DateTime::parse_from_rfc3339(raw)
+            .map(|at| at.with_timezone(&Utc))
+            .map_err(|_| ApiError::invalid_request("since must be RFC3339"))?,
+        None => window_start,
+    };
+    let (items, partial) =
+        cluster_subscription_access(&state, Some(&user_id), since.min(window_start)).await?;
+    let window_summary = SubscriptionAccessSummary::from_entries(
+        items
+            .iter()
+            .filter(|item| item.accessed_since(window_start)),
+    );
+    let abuse_reasons = window_summary.abuse_reasons(abuse_thresholds(&state));
+    let items = items
+        .into_iter()
+        .filter(|item| item.accessed_since(since))
+        .collect::<Vec<_>>();
+    Ok(Json(SubscriptionAccessResponse {
+        partial,
+        since: since.to_rfc3339(),
+        summary: SubscriptionAccessSummary::from_entries(&items),
+        abuse_reasons,
+        items,
+    }))
+}
+
+pub(in crate::http) async fn admin_internal_subscription_access(
+    Extension(state): Extension<AppState>,
+    internal: Option<Extension<InternalSignatureAuth>>,
+    ApiJson(request): ApiJson<InternalSubscriptionAccessRequest>,
+) -> Result<Json<InternalSubscriptionAccessResponse>, ApiError> {
+    ensure_cluster_sender(&state, internal).await?;
+    let since = DateTime::from_timestamp(request.since_unix_seconds, 0)
+        .ok_or_else(|| ApiError::invalid_request("since_unix_seconds is out of range"))?;
+    let items = local_subscription_access(&state, request.user_id.as_deref(), since)
+        .await?
+        .into_iter()
+        .map(|(record_key, entry)| KeyedSubscriptionAccess { record_key, entry })
+        .collect();
+    Ok(Json(InternalSubscriptionAccessResponse { items }))
+}
+
+/// Users over the abuse thresholds within the detection window, cluster-wide.
+pub(in crate::http) async fn subscription_abuse(
+    state: &AppState,
+    now: DateTime<Utc>,
+) -> Result<Vec<SubscriptionAbuse>, ApiError> {
+    let thresholds = abuse_thresholds(state);
+    if thresholds.max_ips == 0 && thresholds.max_countries == 0 {
+        return Ok(Vec::new());
+    }
+    let (items, _) =
+        cluster_subscription_access(state, None, abuse_window_start(state, now)).await?;
+    let mut by_user = BTreeMap::<String, Vec<SubscriptionAccessEntry>>::new();
+    for item in items {
+        by_user.entry(item.user_id.clone()).or_default().push(item);
+    }
+    Ok(by_user
+        .into_iter()
+        .filter_map(|(user_id, entries)| {
+            let summary = SubscriptionAccessSummary::from_entries(&entries);
+            let reasons = summary.abuse_reasons(thresholds);
+            (!reasons.is_empty()).then_some(SubscriptionAbuse {
+                user_id,
+                summary,
+                reasons,
+            })
+        })
+        .collect())
+}
@@ -36,6 +36,8 @@ const MAX_REPOSITORY_PEERS_PER_CYCLE: usize = 4;
 const READY_STABILITY_WINDOW: Duration = Duration::from_secs(5 * 60);
 const MAX_SOURCE_PAYLOAD_BYTES: usize = 32 * 1024;
 const MAX_SOURCE_SUMMARY_ITEMS: usize = 64;
+/// Subscription fetches published per source tick; the rest wait for the next one.
+const MAX_SOURCE_SUBSCRIPTION_ACCESS: usize = 256;
 const MAX_INITIAL_BACKFILL_PAGE_BYTES: usize = 192 * 1024;
 const MAX_INITIAL_BACKFILL_PAGE_RECORDS: usize = 64;
 const CLUSTER_RELAY_KEY_CONTEXT: &[u8] = b"xp-history-repository-relay-key-v1\0";
@@ -269,6 +271,11 @@ async fn publish_local_history_segment(
         let gaps = runtime.local_source_backpressure_gaps(&state.cluster.node_id);
         (segments, gaps)
     };
+    // Queued segments are retried by the source queue, so the fetches count as handed over.
+    if !source_batch.subscription_access_ids.is_empty() {
+        let store = state.store.lock().await;
+        store.mark_subscription_access_published(&source_batch.subscription_access_ids)?;
+    }
     if segments.is_empty() && gaps.is_empty() {
         return Ok(());
     }
@@ -1,8 +1,11 @@
 use super::*;
+use crate::subscription_access::{SUBSCRIPTION_ACCESS_SCHEMA_ID, subscription_access_record_key};
 
 pub(super) struct SourceRecordBatch {
     pub(super) records: Vec<SyncRecord>,
     pub(super) deletion_markers: Vec<crate::node_history::RepositoryHistoryDeletionMarker>,
+    /// Local log rows carried by `records`, marked published once the batch is queued.
+    pub(super) subscription_access_ids: Vec<u64>,
 }
 
 pub(super) async fn source_records(
@@ -122,6 +125,21 @@ pub(super) async fn source_records(
             )?);
         }
     }
+    let subscription_access = state
+        .store
+        .lock()
+        .await
+        .unpublished_subscription_access(MAX_SOURCE_SUBSCRIPTION_ACCESS)?;
+    for (access_id, entry) in &subscription_access {
+        live_records.push(source_record_with_key(
+            SUBSCRIPTION_ACCESS_SCHEMA_ID,
+            &state.cluster.node_id,
+            now,
+            subscription_access_record_key(&entry.user_id, &entry.node_id, *access_id).into_bytes(),
+            serde_json::to_value(entry)?,
+            false,
+        )?);
+    }
     let records = source_records_with_deletions(
         &state.cluster.node_id,
         now,
@@ -140,6 +158,10 @@ pub(super) async fn source_records(
     Ok(SourceRecordBatch {
         records,
         deletion_markers,
+        subscription_access_ids: subscription_access
+            .into_iter()
+            .map(|(access_id, _)| access_id)
+            .collect(),
     })
 }
 
@@ -136,6 +136,7 @@ async fn mesh_capability_response(
     let verified = internal_auth::verify_request_v2(
         &state.ca_key_pem,
         &state.ca_cert_pem,
+        &[],
         &method,
         &uri,
         &headers,
@@ -291,6 +292,9 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: String::new(),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -319,9 +323,14 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
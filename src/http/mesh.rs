@@ -284,6 +284,7 @@ pub(super) async fn admin_internal_reverse_relay(
     let verified_inner = internal_auth::verify_request_v2(
         ca_key_pem,
         &state.cluster_ca_pem,
+        &state.alternate_roots.snapshot(),
         &method,
         &uri,
         &inner_headers,
@@ -400,6 +401,7 @@ pub(super) async fn admin_internal_reverse_relay(
     internal_auth::verify_ack_v2(
         ca_key_pem,
         &state.cluster_ca_pem,
+        &state.alternate_roots.snapshot(),
         &verified_inner,
         &assignment.target_node_id,
         status.as_u16(),
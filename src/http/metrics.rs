@@ -0,0 +1,402 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Router,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::Utc;
+use sha2::{Digest as _, Sha256};
+use tokio::sync::Mutex;
+
+use super::{ApiError, extract_bearer_token};
+use crate::{
+    admin_token::{AdminTokenHash, AdminTokenVerifier, AdminTokenVerifyError},
+    cloudflared_supervisor::CloudflaredHealthHandle,
+    ddns::DdnsHealthHandle,
+    mesh_telemetry::{
+        MeshQuality, MeshTelemetryHandle, availability_for, latency_percentiles_for,
+        quality_for_peer,
+    },
+    raft::app::RaftFacade,
+    state::{JsonSnapshotStore, membership_key},
+    xray_supervisor::XrayHealthHandle,
+};
+
+mod exposition;
+#[cfg(test)]
+mod tests;
+
+use exposition::{MetricKind, MetricsWriter};
+
+const CONTENT_TYPE_PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Window used for mesh peer availability and latency gauges.
+const MESH_WINDOW_MINUTES: i64 = 60;
+
+/// Everything the `/metrics` listener reads; all of it is node-local.
+#[derive(Clone)]
+pub struct MetricsState {
+    pub node_id: String,
+    pub store: Arc<Mutex<JsonSnapshotStore>>,
+    pub raft: Arc<dyn RaftFacade>,
+    pub xray_health: XrayHealthHandle,
+    pub cloudflared_health: CloudflaredHealthHandle,
+    pub ddns_health: DdnsHealthHandle,
+    pub mesh_telemetry: MeshTelemetryHandle,
+    /// Bearer token hash for scrapers; deliberately not the admin token.
+    pub token_hash: AdminTokenHash,
+    pub token_verifier: MetricsTokenVerifier,
+}
+
+/// Argon2 verification with a memory of the last accepted token. Scrapers present the same token
+/// every interval, so after the first success a request only costs a SHA-256 and a constant-time
+/// digest compare.
+#[derive(Clone, Default)]
+pub struct MetricsTokenVerifier {
+    argon2: AdminTokenVerifier,
+    verified_sha256: Arc<std::sync::Mutex<Option<[u8; 32]>>>,
+}
+
+impl MetricsTokenVerifier {
+    async fn verify(
+        &self,
+        token: String,
+        expected: AdminTokenHash,
+    ) -> Result<bool, AdminTokenVerifyError> {
+        let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        let known = *self
+            .verified_sha256
+            .lock()
+            .expect("metrics token cache lock poisoned");
+        if known.is_some_and(|known| constant_time_eq(&known, &digest)) {
+            return Ok(true);
+        }
+        let ok = self.argon2.verify(token, expected).await?;
+        if ok {
+            *self
+                .verified_sha256
+                .lock()
+                .expect("metrics token cache lock poisoned") = Some(digest);
+        }
+        Ok(ok)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub fn build_metrics_router(state: MetricsState) -> Router {
+    Router::new()
+        .route("/metrics", get(get_metrics))
+        .layer(Extension(state))
+}
+
+async fn get_metrics(Extension(state): Extension<MetricsState>, headers: HeaderMap) -> Response {
+    let Some(token) = extract_bearer_token(&headers) else {
+        return ApiError::unauthorized("missing or invalid metrics token").into_response();
+    };
+    match state
+        .token_verifier
+        .verify(token, state.token_hash.clone())
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return ApiError::unauthorized("missing or invalid metrics token").into_response();
+        }
+        Err(AdminTokenVerifyError::Busy) => {
+            return ApiError::too_many_requests("metrics authentication is busy").into_response();
+        }
+        Err(AdminTokenVerifyError::Unavailable) => {
+            return ApiError::internal("metrics authentication is unavailable").into_response();
+        }
+    }
+
+    let body = render_metrics(&state).await;
+    ([(header::CONTENT_TYPE, CONTENT_TYPE_PROMETHEUS_TEXT)], body).into_response()
+}
+
+async fn render_metrics(state: &MetricsState) -> String {
+    let mut w = MetricsWriter::default();
+    w.family("xp_build_info", "xp build information.", MetricKind::Gauge);
+    w.sample(
+        "xp_build_info",
+        &[("version", crate::version::VERSION)],
+        1.0,
+    );
+
+    write_raft_metrics(&mut w, &state.raft.metrics().borrow());
+    write_xray_metrics(&mut w, state).await;
+    write_cloudflared_metrics(&mut w, state).await;
+    write_ddns_metrics(&mut w, state).await;
+    write_mesh_metrics(&mut w, state).await;
+    {
+        let store = state.store.lock().await;
+        write_membership_metrics(&mut w, &store, &state.node_id);
+        write_endpoint_probe_metrics(&mut w, &store, &state.node_id);
+    }
+    w.finish()
+}
+
+fn write_raft_metrics(
+    w: &mut MetricsWriter,
+    metrics: &openraft::RaftMetrics<crate::raft::types::NodeId, crate::raft::types::NodeMeta>,
+) {
+    let role = match metrics.state {
+        openraft::ServerState::Learner => "learner",
+        openraft::ServerState::Follower => "follower",
+        openraft::ServerState::Candidate => "candidate",
+        openraft::ServerState::Leader => "leader",
+        openraft::ServerState::Shutdown => "shutdown",
+    };
+    w.state_set(
+        "xp_raft_role",
+        "Raft role of this node.",
+        "role",
+        &["learner", "follower", "candidate", "leader", "shutdown"],
+        role,
+    );
+    w.single(
+        "xp_raft_current_term",
+        "Current Raft term.",
+        MetricKind::Gauge,
+        metrics.current_term as f64,
+    );
+    w.single(
+        "xp_raft_last_log_index",
+        "Index of the last Raft log entry on this node.",
+        MetricKind::Gauge,
+        metrics.last_log_index.unwrap_or(0) as f64,
+    );
+    w.single(
+        "xp_raft_last_applied_index",
+        "Index of the last Raft entry applied to the state machine.",
+        MetricKind::Gauge,
+        metrics.last_applied.map(|log_id| log_id.index).unwrap_or(0) as f64,
+    );
+}
+
+async fn write_xray_metrics(w: &mut MetricsWriter, state: &MetricsState) {
+    let xray = state.xray_health.snapshot().await;
+    w.state_set(
+        "xp_xray_status",
+        "Xray health as seen by the local supervisor.",
+        "status",
+        &["unknown", "up", "down"],
+        xray.status.as_str(),
+    );
+    w.single(
+        "xp_xray_consecutive_failures",
+        "Consecutive failed Xray health checks.",
+        MetricKind::Gauge,
+        f64::from(xray.consecutive_failures),
+    );
+    w.single(
+        "xp_xray_recoveries_total",
+        "Xray down-to-up recoveries observed since start.",
+        MetricKind::Counter,
+        xray.recoveries_observed as f64,
+    );
+    w.single(
+        "xp_xray_restart_attempts_total",
+        "Xray restart attempts since start.",
+        MetricKind::Counter,
+        xray.restart_attempts as f64,
+    );
+}
+
+async fn write_cloudflared_metrics(w: &mut MetricsWriter, state: &MetricsState) {
+    let cloudflared = state.cloudflared_health.snapshot().await;
+    w.state_set(
+        "xp_cloudflared_status",
+        "cloudflared health as seen by the local supervisor.",
+        "status",
+        &["disabled", "unknown", "up", "down"],
+        cloudflared.status.as_str(),
+    );
+    w.single(
+        "xp_cloudflared_restart_attempts_total",
+        "cloudflared restart attempts since start.",
+        MetricKind::Counter,
+        cloudflared.restart_attempts as f64,
+    );
+}
+
+async fn write_ddns_metrics(w: &mut MetricsWriter, state: &MetricsState) {
+    let ddns = state.ddns_health.snapshot().await;
+    w.state_set(
+        "xp_ddns_status",
+        "DDNS sync status.",
+        "status",
+        &["disabled", "unknown", "up", "degraded", "down"],
+        ddns.status.as_str(),
+    );
+    w.single(
+        "xp_ddns_consecutive_failures",
+        "Consecutive failed DDNS syncs.",
+        MetricKind::Gauge,
+        f64::from(ddns.consecutive_failures),
+    );
+    if let Some(last_sync_at) = ddns.last_sync_at {
+        w.single(
+            "xp_ddns_last_sync_timestamp_seconds",
+            "Unix time of the last successful DDNS sync.",
+            MetricKind::Gauge,
+            last_sync_at.timestamp() as f64,
+        );
+    }
+}
+
+fn mesh_quality_str(quality: MeshQuality) -> &'static str {
+    match quality {
+        MeshQuality::Good => "good",
+        MeshQuality::Slow => "slow",
+        MeshQuality::Unstable => "unstable",
+        MeshQuality::Down => "down",
+        MeshQuality::Unknown => "unknown",
+    }
+}
+
+async fn write_mesh_metrics(w: &mut MetricsWriter, state: &MetricsState) {
+    let snapshot = state.mesh_telemetry.snapshot().await;
+    let now = Utc::now();
+
+    w.family(
+        "xp_mesh_peer_quality",
+        "Mesh link quality to each peer (1 for the current quality).",
+        MetricKind::Gauge,
+    );
+    for peer in &snapshot.peers {
+        let current = mesh_quality_str(quality_for_peer(peer, now));
+        for quality in ["good", "slow", "unstable", "down", "unknown"] {
+            w.sample(
+                "xp_mesh_peer_quality",
+                &[("peer_id", &peer.peer_id), ("quality", quality)],
+                f64::from(u8::from(quality == current)),
+            );
+        }
+    }
+
+    w.family(
+        "xp_mesh_peer_availability_ratio",
+        "Share of successful end-to-end mesh requests to each peer over the last hour.",
+        MetricKind::Gauge,
+    );
+    for peer in &snapshot.peers {
+        if let Some(ratio) = availability_for(peer, MESH_WINDOW_MINUTES, now) {
+            w.sample(
+                "xp_mesh_peer_availability_ratio",
+                &[("peer_id", &peer.peer_id)],
+                ratio,
+            );
+        }
+    }
+
+    w.family(
+        "xp_mesh_peer_latency_ms",
+        "Mesh request latency percentiles to each peer over the last hour.",
+        MetricKind::Gauge,
+    );
+    for peer in &snapshot.peers {
+        let (p50, p95) = latency_percentiles_for(peer, MESH_WINDOW_MINUTES, now);
+        for (quantile, value) in [("0.5", p50), ("0.95", p95)] {
+            if let Some(value) = value {
+                w.sample(
+                    "xp_mesh_peer_latency_ms",
+                    &[("peer_id", &peer.peer_id), ("quantile", quantile)],
+                    f64::from(value),
+                );
+            }
+        }
+    }
+}
+
+fn write_membership_metrics(w: &mut MetricsWriter, store: &JsonSnapshotStore, node_id: &str) {
+    let memberships = store
+        .state()
+        .node_user_endpoint_memberships
+        .iter()
+        .filter(|m| m.node_id == node_id && m.user_id != crate::endpoint_probe::PROBE_USER_ID)
+        .map(|m| {
+            let usage = store.get_membership_usage(&membership_key(&m.user_id, &m.endpoint_id));
+            (m, usage)
+        })
+        .collect::<Vec<_>>();
+
+    w.family(
+        "xp_membership_used_bytes",
+        "Traffic used by each local membership in the current quota cycle.",
+        MetricKind::Gauge,
+    );
+    for (membership, usage) in &memberships {
+        w.sample(
+            "xp_membership_used_bytes",
+            &[
+                ("user_id", &membership.user_id),
+                ("endpoint_id", &membership.endpoint_id),
+            ],
+            usage.as_ref().map(|u| u.used_bytes).unwrap_or(0) as f64,
+        );
+    }
+
+    w.family(
+        "xp_membership_quota_banned",
+        "Whether quota enforcement currently blocks each local membership.",
+        MetricKind::Gauge,
+    );
+    for (membership, usage) in &memberships {
+        let banned = usage.as_ref().is_some_and(|u| u.quota_banned);
+        w.sample(
+            "xp_membership_quota_banned",
+            &[
+                ("user_id", &membership.user_id),
+                ("endpoint_id", &membership.endpoint_id),
+            ],
+            f64::from(u8::from(banned)),
+        );
+    }
+}
+
+fn write_endpoint_probe_metrics(w: &mut MetricsWriter, store: &JsonSnapshotStore, node_id: &str) {
+    // Only this node's own observations, so scraping every node does not double count.
+    let latest = store
+        .state()
+        .endpoint_probe_history
+        .iter()
+        .filter_map(|(endpoint_id, history)| {
+            let (_hour, samples) = history.hours.last_key_value()?;
+            let sample = samples.by_node.get(node_id)?;
+            (!sample.skipped).then_some((endpoint_id, sample))
+        })
+        .collect::<Vec<_>>();
+
+    w.family(
+        "xp_endpoint_probe_up",
+        "Result of the latest endpoint probe run from this node.",
+        MetricKind::Gauge,
+    );
+    for (endpoint_id, sample) in &latest {
+        w.sample(
+            "xp_endpoint_probe_up",
+            &[("endpoint_id", endpoint_id)],
+            f64::from(u8::from(sample.ok)),
+        );
+    }
+
+    w.family(
+        "xp_endpoint_probe_latency_ms",
+        "Latency of the latest endpoint probe run from this node.",
+        MetricKind::Gauge,
+    );
+    for (endpoint_id, sample) in &latest {
+        if let Some(latency_ms) = sample.latency_ms {
+            w.sample(
+                "xp_endpoint_probe_latency_ms",
+                &[("endpoint_id", endpoint_id)],
+                f64::from(latency_ms),
+            );
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy)]
+pub(super) enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Gauge => "gauge",
+            Self::Counter => "counter",
+        }
+    }
+}
+
+/// Prometheus text exposition (format 0.0.4) writer.
+///
+/// Callers emit each family header once and then all of its samples, which keeps the output
+/// grouped the way the format requires.
+#[derive(Debug, Default)]
+pub(super) struct MetricsWriter {
+    out: String,
+}
+
+impl MetricsWriter {
+    pub(super) fn family(&mut self, name: &str, help: &str, kind: MetricKind) {
+        let _ = writeln!(self.out, "# HELP {name} {help}");
+        let _ = writeln!(self.out, "# TYPE {name} {}", kind.as_str());
+    }
+
+    pub(super) fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (idx, (key, value)) in labels.iter().enumerate() {
+                if idx > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{key}=\"{}\"", escape_label_value(value));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {value}");
+    }
+
+    /// Writes a single-sample family.
+    pub(super) fn single(&mut self, name: &str, help: &str, kind: MetricKind, value: f64) {
+        self.family(name, help, kind);
+        self.sample(name, &[], value);
+    }
+
+    /// Writes a one-hot state family: the sample whose `label` equals `current` is 1.
+    pub(super) fn state_set(
+        &mut self,
+        name: &str,
+        help: &str,
+        label: &str,
+        states: &[&str],
+        current: &str,
+    ) {
+        self.family(name, help, MetricKind::Gauge);
+        for state in states {
+            self.sample(
+                name,
+                &[(label, state)],
+                f64::from(u8::from(*state == current)),
+            );
+        }
+    }
+
+    pub(super) fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use http_body_util::BodyExt;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+use tokio::sync::{Mutex, watch};
+use tower::util::ServiceExt;
+
+use super::{
+    MetricsState, MetricsTokenVerifier, build_metrics_router,
+    exposition::{MetricKind, MetricsWriter},
+};
+use crate::{
+    admin_token::hash_admin_token_argon2id,
+    cloudflared_supervisor::{CloudflaredHealthHandle, CloudflaredStatus},
+    ddns::{DdnsHealthHandle, DdnsStatus},
+    mesh_telemetry::MeshTelemetryHandle,
+    raft::{app::LocalRaft, types::raft_node_id_from_ulid},
+    state::{JsonSnapshotStore, NodeUserEndpointMembership, StoreInit, membership_key},
+    xray_supervisor::XrayHealthHandle,
+};
+
+const METRICS_TOKEN: &str = "metrics-token-0123456789abcdef0123456789";
+
+fn metrics_app(tmp: &TempDir) -> axum::Router {
+    let node_id = xp_test_fixtures::identifier_ulid_d().to_owned();
+    let mut store = JsonSnapshotStore::load_or_init(StoreInit {
+        data_dir: tmp.path().to_path_buf(),
+        bootstrap_node_id: Some(node_id.clone()),
+        bootstrap_node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+        bootstrap_access_host: xp_test_fixtures::label_empty().to_owned(),
+        bootstrap_api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+    })
+    .unwrap();
+    let user_id = xp_test_fixtures::primary_user_id();
+    let endpoint_id = xp_test_fixtures::primary_endpoint_id();
+    store
+        .state_mut()
+        .node_user_endpoint_memberships
+        .insert(NodeUserEndpointMembership {
+            user_id: user_id.to_owned(),
+            node_id: node_id.clone(),
+            endpoint_id: endpoint_id.to_owned(),
+        });
+    store
+        .set_quota_banned(
+            &membership_key(user_id, endpoint_id),
+            "2026-01-01T00:00:00Z".to_string(),
+        )
+        .unwrap();
+    let store = Arc::new(Mutex::new(store));
+
+    let mut metrics = openraft::RaftMetrics::new_initial(raft_node_id_from_ulid(&node_id).unwrap());
+    metrics.current_term = 7;
+    metrics.state = openraft::ServerState::Leader;
+    let (_tx, rx) = watch::channel(metrics);
+
+    build_metrics_router(MetricsState {
+        node_id,
+        store: store.clone(),
+        raft: Arc::new(LocalRaft::new(store, rx)),
+        xray_health: XrayHealthHandle::new_unknown(),
+        cloudflared_health: CloudflaredHealthHandle::new_with_status(CloudflaredStatus::Disabled),
+        ddns_health: DdnsHealthHandle::new_with_status(DdnsStatus::Disabled),
+        mesh_telemetry: MeshTelemetryHandle::load(tmp.path()).unwrap(),
+        token_hash: hash_admin_token_argon2id(METRICS_TOKEN).unwrap(),
+        token_verifier: MetricsTokenVerifier::default(),
+    })
+}
+
+fn metrics_request(token: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder().uri("/metrics");
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    builder.body(Body::empty()).unwrap()
+}
+
+#[test]
+fn writer_renders_families_and_escapes_label_values() {
+    let mut w = MetricsWriter::default();
+    w.family("xp_example", "Example family.", MetricKind::Counter);
+    w.sample("xp_example", &[("name", "a\"b\\c\nd")], 3.0);
+    w.state_set(
+        "xp_state",
+        "Example state.",
+        "state",
+        &["up", "down"],
+        "down",
+    );
+
+    assert_eq!(
+        w.finish(),
+        concat!(
+            "# HELP xp_example Example family.\n",
+            "# TYPE xp_example counter\n",
+            "xp_example{name=\"a\\\"b\\\\c\\nd\"} 3\n",
+            "# HELP xp_state Example state.\n",
+            "# TYPE xp_state gauge\n",
+            "xp_state{state=\"up\"} 0\n",
+            "xp_state{state=\"down\"} 1\n",
+        )
+    );
+}
+
+#[tokio::test]
+async fn metrics_require_metrics_token() {
+    let tmp = tempfile::tempdir().unwrap();
+    let app = metrics_app(&tmp);
+
+    let res = app.clone().oneshot(metrics_request(None)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = app
+        .oneshot(metrics_request(Some(xp_test_fixtures::primary_token())))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn metrics_token_verifier_remembers_the_accepted_token() {
+    let verifier = MetricsTokenVerifier::default();
+    let hash = hash_admin_token_argon2id(METRICS_TOKEN).unwrap();
+    let other_hash =
+        hash_admin_token_argon2id("some-other-metrics-token-0123456789abcdef").unwrap();
+
+    assert_eq!(
+        verifier
+            .verify(METRICS_TOKEN.to_string(), hash.clone())
+            .await,
+        Ok(true)
+    );
+    // A repeat scrape is answered from the digest, without running Argon2 again.
+    assert_eq!(
+        verifier.verify(METRICS_TOKEN.to_string(), other_hash).await,
+        Ok(true)
+    );
+    assert_eq!(
+        verifier.verify("wrong-token".to_string(), hash).await,
+        Ok(false)
+    );
+}
+
+#[tokio::test]
+async fn metrics_render_node_local_state() {
+    let tmp = tempfile::tempdir().unwrap();
+    let app = metrics_app(&tmp);
+
+    let res = app
+        .oneshot(metrics_request(Some(METRICS_TOKEN)))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[header::CONTENT_TYPE],
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    let body = res.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    assert!(body.contains("xp_raft_role{role=\"leader\"} 1\n"));
+    assert!(body.contains("xp_raft_current_term 7\n"));
+    assert!(body.contains("xp_xray_status{status=\"unknown\"} 1\n"));
+    assert!(body.contains("xp_ddns_status{status=\"disabled\"} 1\n"));
+    let labels = format!(
+        "{{user_id=\"{}\",endpoint_id=\"{}\"}}",
+        xp_test_fixtures::primary_user_id(),
+        xp_test_fixtures::primary_endpoint_id()
+    );
+    assert!(body.contains(&format!("xp_membership_used_bytes{labels} 0\n")));
+    assert!(body.contains(&format!("xp_membership_quota_banned{labels} 1\n")));
+}
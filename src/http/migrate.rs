@@ -0,0 +1,86 @@
+use axum::{Json, extract::Extension};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write, user_bulk::IMPORT_BODY_LIMIT_BYTES};
+use crate::{
+    migrate::{MigratedEndpoint, MigrationSource, plan_migration},
+    state::DesiredStateCommand,
+    user_bulk::{UserImportAction, UserImportRow},
+};
+
+pub(super) const MIGRATE_BODY_LIMIT_BYTES: usize = IMPORT_BODY_LIMIT_BYTES;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct MigrateRequest {
+    /// Node id or name that receives the migrated inbounds.
+    node: String,
+    source: MigrationSource,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct MigrateResponse {
+    dry_run: bool,
+    applied: bool,
+    panel: String,
+    node_id: Option<String>,
+    endpoints: Vec<MigratedEndpoint>,
+    created: usize,
+    issues: Vec<String>,
+    warnings: Vec<String>,
+    users: Vec<UserImportRow>,
+}
+
+pub(super) async fn admin_migrate(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<MigrateRequest>,
+) -> Result<Json<MigrateResponse>, ApiError> {
+    let plan = {
+        let store = state.store.lock().await;
+        plan_migration(store.state(), &req.node, &req.source)
+    };
+    let mut response = MigrateResponse {
+        dry_run: req.dry_run,
+        applied: false,
+        panel: req.source.panel.clone(),
+        node_id: plan.node_id.clone(),
+        endpoints: plan.endpoint_views.clone(),
+        created: plan.users.count(UserImportAction::Create),
+        issues: plan.issues.clone(),
+        warnings: req.source.warnings.clone(),
+        users: plan.users.rows.clone(),
+    };
+    if req.dry_run {
+        return Ok(Json(response));
+    }
+    if let Some(issue) = plan.issues.first() {
+        return Err(ApiError::invalid_request(format!(
+            "migration has {} issue(s), run a dry run for the full list: {issue}",
+            plan.issues.len()
+        )));
+    }
+    for endpoint in plan.endpoints {
+        raft_write(
+            &state,
+            DesiredStateCommand::UpsertEndpoint {
+                endpoint,
+                expected: None,
+            },
+        )
+        .await?;
+    }
+    if !plan.users.entries.is_empty() {
+        raft_write(
+            &state,
+            DesiredStateCommand::ImportUsers {
+                entries: plan.users.entries,
+            },
+        )
+        .await?;
+    }
+    state.reconcile.request_full();
+    response.applied = true;
+    Ok(Json(response))
+}
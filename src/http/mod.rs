@@ -9,7 +9,7 @@ use std::{
 use axum::{
     Json, Router,
     body::Body,
-    extract::{Extension, FromRequest, Path, Query, Request, State},
+    extract::{ConnectInfo, DefaultBodyLimit, Extension, FromRequest, Path, Query, Request, State},
     http::{HeaderMap, Method, StatusCode, header},
     middleware::{self, Next},
     response::{
@@ -27,20 +27,49 @@ use tokio::{
     time::Duration,
 };
 
+mod admin_principals;
+mod alerts;
+mod audit;
+mod backup;
+mod credential_rotation;
+mod dns_failover;
 mod embedded_ui;
+mod endpoint_egress;
+mod endpoint_kinds;
+mod endpoint_probe_targets;
 mod endpoint_requests;
 mod mesh;
+mod metrics;
+mod migrate;
+mod notifications;
+mod routing_policy;
 mod status_events;
+mod subscription_headers;
+mod subscription_tokens;
+mod traffic_topups;
+mod user_bulk;
+mod user_lifecycle;
+mod user_node_quota_status;
+use alerts::{
+    AlertsResponse, admin_get_alerts, admin_get_alerts_response, admin_internal_get_alerts,
+};
 use mesh::{
     MeshCapabilityProbeResponse, admin_get_mesh_status, admin_internal_raft_client_write,
     admin_internal_reverse_relay, admin_run_mesh_probes, send_mesh_internal_capability_read,
     send_mesh_internal_read, send_mesh_internal_request, spawn_mesh_probe_worker,
     spawn_reverse_assignment_worker,
 };
+pub use metrics::{MetricsState, MetricsTokenVerifier, build_metrics_router};
 use status_events::StatusEventsHub;
+use user_node_quota_status::{
+    AdminUserNodeQuotaStatusItem, build_local_user_node_quota_status,
+    fetch_remote_user_node_quota_status,
+};
 
 use crate::{
+    admin_principal::AdminScope,
     admin_token::{AdminTokenHash, AdminTokenVerifier, AdminTokenVerifyError},
+    audit::{AuditContext, AuditPrincipal},
     cloudflared_supervisor::CloudflaredHealthHandle,
     cluster_identity::JoinToken,
     cluster_metadata::ClusterMetadata,
@@ -48,10 +77,10 @@ use crate::{
     control_plane_mesh::{
         MeshAwareHttpClient, MeshPeerTarget, MeshRequest, build_mesh_http_client,
     },
-    cycle::{CycleTimeZone, current_cycle_window_at},
+    cycle::{CycleSchedule, CycleTimeZone, current_cycle_window_at},
     domain::{
-        Endpoint, EndpointKind, Node, NodeQuotaReset, QuotaResetSource, RealityDomain, User,
-        UserNodeQuota, UserQuotaReset,
+        Endpoint, EndpointKind, Node, NodeQuotaReset, RealityDomain, User, UserNodeQuota,
+        UserQuotaReset,
     },
     inbound_ip_usage::{
         InboundIpUsageListItem as IpListEntry, InboundIpUsageMembershipView,
@@ -102,6 +131,7 @@ use crate::{
         history_repository::{HistoryStorage, replica::RepositoryReplicaRuntime},
     },
     subscription,
+    subscription_access::{self, SubscriptionAccessEntry},
     tcp_connection_usage::{
         TcpConnectionEndpointView, TcpConnectionUsageEndpointOption,
         TcpConnectionUsageEndpointSeries, TcpConnectionUsageWarning, TcpConnectionUsageWindow,
@@ -123,7 +153,10 @@ mod node_metadata;
 mod version_check;
 mod web_assets;
 use capabilities::api_capabilities;
-use endpoint_requests::{CreateEndpointRequest, PatchEndpointRequest, deserialize_optional_string};
+use endpoint_requests::{
+    CreateEndpointRequest, PatchEndpointRequest, deserialize_optional_string,
+    deserialize_optional_u32,
+};
 use version_check::{VersionCheckCache, api_version_check};
 #[derive(Clone)]
 pub struct AppState {
@@ -140,6 +173,8 @@ pub struct AppState {
     pub cluster: Arc<ClusterMetadata>,
     pub cluster_ca_pem: Arc<String>,
     pub cluster_ca_key_pem: Arc<Option<String>>,
+    /// Roots accepted besides the local CA during a credential rotation.
+    pub alternate_roots: internal_auth::AlternateRoots,
     pub mihomo_resource_directory: Arc<mihomo_resources::ResourceDirectoryCache>,
     pub raft: Arc<dyn RaftFacade>,
     pub raft_rpc: Option<openraft::Raft<crate::raft::types::TypeConfig>>,
@@ -190,6 +225,10 @@ impl ApiError {
         Self::new("unauthorized", StatusCode::UNAUTHORIZED, message)
     }
 
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new("forbidden", StatusCode::FORBIDDEN, message)
+    }
+
     pub fn not_implemented(message: impl Into<String>) -> Self {
         Self::new("not_implemented", StatusCode::NOT_IMPLEMENTED, message)
     }
@@ -218,11 +257,15 @@ impl From<StoreError> for ApiError {
                 crate::domain::DomainError::MissingUser { .. }
                 | crate::domain::DomainError::MissingNode { .. }
                 | crate::domain::DomainError::MissingEndpoint { .. }
-                | crate::domain::DomainError::RealityDomainNotFound { .. } => {
+                | crate::domain::DomainError::RealityDomainNotFound { .. }
+                | crate::domain::DomainError::TrafficTopUpNotFound { .. }
+                | crate::domain::DomainError::SubscriptionTokenNotFound { .. }
+                | crate::domain::DomainError::CredentialRotationNotFound { .. } => {
                     ApiError::not_found(domain.to_string())
                 }
                 crate::domain::DomainError::RealityDomainNameConflict { .. }
-                | crate::domain::DomainError::NodeInUse { .. } => {
+                | crate::domain::DomainError::NodeInUse { .. }
+                | crate::domain::DomainError::CredentialRotationConflict { .. } => {
                     ApiError::conflict(domain.to_string())
                 }
                 crate::domain::DomainError::NodeEndpointSetChanged { .. }
@@ -233,12 +276,16 @@ impl From<StoreError> for ApiError {
                 _ => ApiError::invalid_request(domain.to_string()),
             },
             StoreError::SchemaVersionMismatch { .. } => ApiError::internal(value.to_string()),
-            StoreError::Migration { .. } => ApiError::internal(value.to_string()),
+            StoreError::Migration { .. } | StoreError::HistoryStorage { .. } => {
+                ApiError::internal(value.to_string())
+            }
             StoreError::InvalidJoinSession { .. }
             | StoreError::InvalidMembershipOperation { .. } => {
                 ApiError::conflict(value.to_string())
             }
-            StoreError::Io(_) | StoreError::SerdeJson(_) => ApiError::internal(value.to_string()),
+            StoreError::Io(_) | StoreError::SerdeJson(_) | StoreError::Cert(_) => {
+                ApiError::internal(value.to_string())
+            }
         }
     }
 }
@@ -370,6 +417,8 @@ struct AdminEndpointProbeHistoryNode {
     target_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<crate::state::EndpointProbeTargetSample>,
     config_hash: String,
 }
 
@@ -396,6 +445,8 @@ struct AdminEndpointProbeHistoryResponse {
     // Compatibility alias for one release while the web UI migrates.
     expected_nodes: usize,
     slots: Vec<AdminEndpointProbeHistorySlot>,
+    /// Configured targets each node currently fails to reach through this endpoint.
+    unreachable_targets: Vec<endpoint_probe_targets::AdminUnreachableProbeTarget>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -752,6 +803,10 @@ struct AdminServiceConfigResponse {
     vless_https_canary_status: crate::vless_https_canary::VlessHttpsCanaryStatus,
     quota_poll_interval_secs: u64,
     quota_auto_unban: bool,
+    quota_warning_thresholds: Vec<u8>,
+    subscription_abuse_max_ips: u32,
+    subscription_abuse_max_countries: u32,
+    subscription_abuse_window_hours: u32,
     ip_geo_enabled: bool,
     ip_geo_origin: String,
     mihomo_resource_allow_private_targets: bool,
@@ -778,6 +833,12 @@ struct PatchUserRequest {
     priority_tier: Option<crate::domain::UserPriorityTier>,
     #[serde(default)]
     quota_reset: Option<UserQuotaReset>,
+    /// `null` removes the limit.
+    #[serde(default, deserialize_with = "deserialize_optional_u32")]
+    max_concurrent_ips: Option<Option<u32>>,
+    /// Replaces the pinned credentials; `{}` goes back to derived ones.
+    #[serde(default)]
+    pinned_credentials: Option<crate::domain::UserPinnedCredentials>,
 }
 
 #[derive(Deserialize)]
@@ -969,6 +1030,7 @@ pub fn build_router_with_mesh_telemetry(
         cluster_id,
         cluster_ca_key_pem: cluster_ca_key_pem.clone(),
         cluster_ca_pem: cluster_ca_pem.clone(),
+        alternate_roots: mesh_client.alternate_roots(),
         local_node_id: cluster.node_id.clone(),
         store: store.clone(),
         bootstrap_sender_path: config
@@ -1011,6 +1073,7 @@ pub fn build_router_with_mesh_telemetry(
         cluster: Arc::new(cluster),
         cluster_ca_pem: Arc::new(cluster_ca_pem),
         cluster_ca_key_pem: Arc::new(cluster_ca_key_pem),
+        alternate_roots: mesh_client.alternate_roots(),
         mihomo_resource_directory: Arc::new(mihomo_resources::ResourceDirectoryCache::new()),
         raft,
         raft_rpc: raft_rpc.clone(),
@@ -1067,9 +1130,10 @@ pub fn build_router_with_mesh_telemetry(
         )
         .route(
             "/_internal/users/{user_id}/node-quotas/status",
-            get(admin_internal_get_user_node_quota_status),
+            get(user_node_quota_status::admin_internal_get_user_node_quota_status),
         )
         .route("/_internal/alerts", get(admin_internal_get_alerts))
+        .route("/_internal/audit", get(audit::admin_internal_get_audit_log))
         .route(
             "/_internal/history-repository/sync",
             post(history_repository::admin_internal_receive_history_repository_segment),
@@ -1102,6 +1166,10 @@ pub fn build_router_with_mesh_telemetry(
             "/_internal/history-repository/relay",
             post(history_repository::admin_internal_forward_history_repository_relay),
         )
+        .route(
+            "/_internal/history-repository/subscription-access",
+            post(history_repository::subscription_access::admin_internal_subscription_access),
+        )
         .route(
             "/_internal/history-repository/relay-deliver",
             post(history_repository::admin_internal_deliver_history_repository_relay),
@@ -1172,6 +1240,11 @@ pub fn build_router_with_mesh_telemetry(
             "/endpoints/{endpoint_id}/canary-probe",
             post(admin_probe_endpoint_canary),
         )
+        .route(
+            "/endpoints/{endpoint_id}/egress",
+            get(endpoint_egress::admin_get_endpoint_egress)
+                .put(endpoint_egress::admin_put_endpoint_egress),
+        )
         .route(
             "/reality-domains",
             get(admin_list_reality_domains).post(admin_create_reality_domain),
@@ -1185,6 +1258,25 @@ pub fn build_router_with_mesh_telemetry(
             patch(admin_patch_reality_domain).delete(admin_delete_reality_domain),
         )
         .route("/endpoints/probe/run", post(admin_run_endpoint_probe_run))
+        .route(
+            "/endpoints/probe/targets",
+            get(endpoint_probe_targets::admin_get_endpoint_probe_targets)
+                .put(endpoint_probe_targets::admin_put_endpoint_probe_targets),
+        )
+        .route(
+            "/endpoints/probe/unreachable-targets",
+            get(endpoint_probe_targets::admin_list_unreachable_probe_targets),
+        )
+        .route(
+            "/dns-failover-groups",
+            get(dns_failover::admin_list_dns_failover_groups)
+                .post(dns_failover::admin_create_dns_failover_group),
+        )
+        .route(
+            "/dns-failover-groups/{group_id}",
+            put(dns_failover::admin_put_dns_failover_group)
+                .delete(dns_failover::admin_delete_dns_failover_group),
+        )
         .route(
             "/endpoints/probe/runs/{run_id}",
             get(admin_get_endpoint_probe_run_status),
@@ -1202,6 +1294,21 @@ pub fn build_router_with_mesh_telemetry(
             "/users/quota-summaries",
             get(admin_list_user_quota_summaries),
         )
+        .route("/users/export", get(user_bulk::admin_export_users))
+        .route(
+            "/users/import",
+            post(user_bulk::admin_import_users)
+                .layer(DefaultBodyLimit::max(user_bulk::IMPORT_BODY_LIMIT_BYTES)),
+        )
+        .route(
+            "/_internal/users/export",
+            get(user_bulk::admin_export_users),
+        )
+        .route(
+            "/_internal/users/import",
+            post(user_bulk::admin_import_users)
+                .layer(DefaultBodyLimit::max(user_bulk::IMPORT_BODY_LIMIT_BYTES)),
+        )
         .route(
             "/quota-policy/nodes/{node_id}/weight-rows",
             get(admin_list_quota_policy_node_weight_rows),
@@ -1227,10 +1334,14 @@ pub fn build_router_with_mesh_telemetry(
         .route("/users/{user_id}/reset-token", post(admin_reset_user_token))
         .route(
             "/users/{user_id}/node-quotas/status",
-            get(admin_get_user_node_quota_status),
+            get(user_node_quota_status::admin_get_user_node_quota_status),
         )
         .route("/users/{user_id}/ip-usage", get(admin_get_user_ip_usage))
         .route("/users/{user_id}/traffic", get(admin_get_user_traffic))
+        .route(
+            "/users/{user_id}/subscription-access",
+            get(history_repository::subscription_access::admin_get_user_subscription_access),
+        )
         .route(
             "/users/{user_id}/node-quotas",
             get(admin_list_user_node_quotas),
@@ -1259,6 +1370,36 @@ pub fn build_router_with_mesh_telemetry(
             "/users/{user_id}/reset-credentials",
             post(admin_reset_user_credentials),
         )
+        .route(
+            "/users/{user_id}/expiry",
+            put(user_lifecycle::admin_put_user_expiry),
+        )
+        .route(
+            "/users/{user_id}/disable",
+            post(user_lifecycle::admin_disable_user),
+        )
+        .route(
+            "/users/{user_id}/enable",
+            post(user_lifecycle::admin_enable_user),
+        )
+        .route(
+            "/users/{user_id}/topups",
+            get(traffic_topups::admin_list_user_topups)
+                .post(traffic_topups::admin_create_user_topup),
+        )
+        .route(
+            "/users/{user_id}/topups/{topup_id}",
+            delete(traffic_topups::admin_delete_user_topup),
+        )
+        .route(
+            "/users/{user_id}/subscription-tokens",
+            get(subscription_tokens::admin_list_user_subscription_tokens)
+                .post(subscription_tokens::admin_create_user_subscription_token),
+        )
+        .route(
+            "/users/{user_id}/subscription-tokens/{token_id}",
+            delete(subscription_tokens::admin_revoke_user_subscription_token),
+        )
         .route(
             "/_internal/endpoint-probe/run",
             post(admin_internal_endpoint_probe_run),
@@ -1329,6 +1470,79 @@ pub fn build_router_with_mesh_telemetry(
                 .delete(admin_internal_clear_local_user_traffic),
         )
         .route("/alerts", get(admin_get_alerts))
+        .route("/audit", get(audit::admin_get_audit_log))
+        .route(
+            "/credential-rotation",
+            get(credential_rotation::admin_get_credential_rotation)
+                .post(credential_rotation::admin_start_credential_rotation)
+                .delete(credential_rotation::admin_abort_credential_rotation),
+        )
+        .route(
+            "/credential-rotation/promote",
+            post(credential_rotation::admin_promote_credential_rotation),
+        )
+        .route(
+            "/credential-rotation/retire",
+            post(credential_rotation::admin_retire_credential_rotation),
+        )
+        .route("/backup", get(backup::admin_get_backup))
+        .route(
+            "/backup/restore",
+            post(backup::admin_restore_backup)
+                .layer(DefaultBodyLimit::max(backup::RESTORE_BODY_LIMIT_BYTES)),
+        )
+        .route(
+            "/migrate",
+            post(migrate::admin_migrate)
+                .layer(DefaultBodyLimit::max(migrate::MIGRATE_BODY_LIMIT_BYTES)),
+        )
+        .route(
+            "/_internal/migrate",
+            post(migrate::admin_migrate)
+                .layer(DefaultBodyLimit::max(migrate::MIGRATE_BODY_LIMIT_BYTES)),
+        )
+        .route("/_internal/backup", get(backup::admin_get_backup))
+        .route(
+            "/_internal/backup/restore",
+            post(backup::admin_restore_backup)
+                .layer(DefaultBodyLimit::max(backup::RESTORE_BODY_LIMIT_BYTES)),
+        )
+        .route(
+            "/principals",
+            get(admin_principals::admin_list_principals)
+                .post(admin_principals::admin_create_principal),
+        )
+        .route(
+            "/principals/{principal_id}/rotate-token",
+            post(admin_principals::admin_rotate_principal_token),
+        )
+        .route(
+            "/principals/{principal_id}/revoke",
+            post(admin_principals::admin_revoke_principal),
+        )
+        .route(
+            "/_internal/principals",
+            get(admin_principals::admin_list_principals)
+                .post(admin_principals::admin_create_principal),
+        )
+        .route(
+            "/_internal/principals/{principal_id}/rotate-token",
+            post(admin_principals::admin_rotate_principal_token),
+        )
+        .route(
+            "/_internal/principals/{principal_id}/revoke",
+            post(admin_principals::admin_revoke_principal),
+        )
+        .route(
+            "/notifications/webhooks",
+            get(notifications::admin_get_notification_webhooks)
+                .put(notifications::admin_put_notification_webhooks),
+        )
+        .route(
+            "/xray-routing-policy",
+            get(routing_policy::admin_get_xray_routing_policy)
+                .put(routing_policy::admin_put_xray_routing_policy),
+        )
         .route(
             "/history-repositories",
             get(history_repository::admin_list_history_repositories)
@@ -1375,6 +1589,7 @@ pub fn build_router_with_mesh_telemetry(
                         local_node_id: app_state.cluster.node_id.clone(),
                         cluster_ca_key_pem: cluster_ca_key_pem.to_string(),
                         cluster_ca_cert_pem: (*app_state.cluster_ca_pem).clone(),
+                        alternate_roots: app_state.alternate_roots.clone(),
                         store: app_state.store.clone(),
                         bootstrap_sender: crate::raft::http_rpc::read_bootstrap_sender_marker(
                             app_state
@@ -1434,6 +1649,7 @@ async fn admin_auth(
         let verified = match internal_auth::verify_request_v2(
             ca_key_pem,
             &auth.cluster_ca_pem,
+            &auth.alternate_roots.snapshot(),
             req.method(),
             &canonical_uri,
             req.headers(),
@@ -1497,13 +1713,29 @@ async fn admin_auth(
     let Some(token) = extract_bearer_token(req.headers()) else {
         return ApiError::unauthorized("missing or invalid authorization token").into_response();
     };
-    let Some(expected) = auth.admin_token_hash.as_ref() else {
-        return ApiError::unauthorized("missing or invalid authorization token").into_response();
-    };
 
-    match authorize_admin_token(token, expected, &auth.cluster_id, &auth.verifier).await {
-        Ok(true) => return next.run(req).await,
-        Ok(false) => {}
+    let authorized = authorize_admin_token(
+        token,
+        auth.admin_token_hash.as_ref(),
+        &auth.cluster_id,
+        &auth.verifier,
+        &auth.store,
+    )
+    .await;
+    match authorized {
+        Ok(Some((principal, scope))) => {
+            let required = crate::admin_principal::required_scope(req.method(), req.uri().path());
+            if !scope.permits(required) {
+                return ApiError::forbidden(format!(
+                    "{} scope is required; this token has {}",
+                    required.as_str(),
+                    scope.as_str()
+                ))
+                .into_response();
+            }
+            return crate::audit::with_principal(principal, next.run(req)).await;
+        }
+        Ok(None) => {}
         Err(AdminTokenVerifyError::Busy) => {
             return ApiError::too_many_requests("admin authentication is busy").into_response();
         }
@@ -1540,18 +1772,27 @@ struct AdminAuthState {
     cluster_id: String,
     cluster_ca_key_pem: Option<String>,
     cluster_ca_pem: String,
+    alternate_roots: internal_auth::AlternateRoots,
     local_node_id: String,
     store: Arc<Mutex<JsonSnapshotStore>>,
     bootstrap_sender_path: PathBuf,
     verifier: AdminTokenVerifier,
 }
 
+/// Authenticates a bearer token: a principal token, a login token, or the cluster admin token.
 pub(super) async fn authorize_admin_token(
     token: String,
-    expected: &AdminTokenHash,
+    expected: Option<&AdminTokenHash>,
     cluster_id: &str,
     verifier: &AdminTokenVerifier,
-) -> Result<bool, AdminTokenVerifyError> {
+    store: &Mutex<JsonSnapshotStore>,
+) -> Result<Option<(AuditPrincipal, AdminScope)>, AdminTokenVerifyError> {
+    if crate::admin_principal::principal_id_from_token(&token).is_some() {
+        return admin_principals::authorize_principal_token(token, verifier, store).await;
+    }
+    let Some(expected) = expected else {
+        return Ok(None);
+    };
     if token.bytes().filter(|byte| *byte == b'.').count() == 2 {
         return Ok(crate::login_token::decode_and_validate_login_token_jwt(
             &token,
@@ -1559,9 +1800,20 @@ pub(super) async fn authorize_admin_token(
             expected.as_str(),
             cluster_id,
         )
-        .is_ok());
+        .ok()
+        .map(|claims| {
+            (
+                AuditPrincipal::LoginToken {
+                    token_id: claims.jti,
+                },
+                claims.scope,
+            )
+        }));
     }
-    verifier.verify(token, expected.clone()).await
+    Ok(verifier
+        .verify(token, expected.clone())
+        .await?
+        .then_some((AuditPrincipal::AdminToken, AdminScope::Admin)))
 }
 
 async fn health(Extension(state): Extension<AppState>) -> Json<serde_json::Value> {
@@ -1630,6 +1882,15 @@ async fn raft_write(
     state: &AppState,
     cmd: crate::state::DesiredStateCommand,
 ) -> Result<crate::state::DesiredStateApplyResult, ApiError> {
+    // Writes issued on behalf of an authenticated admin carry their principal into the log.
+    let cmd = match crate::audit::current_principal() {
+        Some(principal) => cmd.audited(AuditContext {
+            principal,
+            origin_node_id: state.cluster.node_id.clone(),
+            requested_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        }),
+        None => cmd,
+    };
     let resp = state
         .raft
         .client_write(cmd)
@@ -1936,6 +2197,7 @@ async fn admin_create_join_token(
     if !is_leader(&metrics) {
         return Err(ApiError::invalid_request("not leader"));
     }
+    reject_join_during_credential_rotation(&state).await?;
 
     let ca_key_pem = state
         .cluster_ca_key_pem
@@ -1956,6 +2218,18 @@ async fn admin_create_join_token(
     }))
 }
 
+/// A node joining mid-rotation would only receive the CA it was invited with; finish or abort the
+/// rotation first.
+async fn reject_join_during_credential_rotation(state: &AppState) -> Result<(), ApiError> {
+    match &state.store.lock().await.state().credential_rotation {
+        Some(rotation) => Err(ApiError::conflict(format!(
+            "credential rotation {} is in progress; finish or abort it before joining nodes",
+            rotation.rotation_id
+        ))),
+        None => Ok(()),
+    }
+}
+
 async fn cluster_join(
     Extension(state): Extension<AppState>,
     ApiJson(req): ApiJson<ClusterJoinRequest>,
@@ -1965,6 +2239,8 @@ async fn cluster_join(
         return Err(ApiError::invalid_request("not leader"));
     }
 
+    reject_join_during_credential_rotation(&state).await?;
+
     let token = JoinToken::decode_base64url_json(&req.join_token)
         .map_err(|e| ApiError::invalid_request(e.to_string()))?;
 
@@ -4407,6 +4683,10 @@ async fn build_admin_service_config_response(
         vless_https_canary_status,
         quota_poll_interval_secs: state.config.quota_poll_interval_secs,
         quota_auto_unban: state.config.quota_auto_unban,
+        quota_warning_thresholds: state.config.quota_warning_thresholds.clone(),
+        subscription_abuse_max_ips: state.config.subscription_abuse_max_ips,
+        subscription_abuse_max_countries: state.config.subscription_abuse_max_countries,
+        subscription_abuse_window_hours: state.config.subscription_abuse_window_hours,
         ip_geo_enabled: state.config.ip_geo_enabled,
         ip_geo_origin,
         mihomo_resource_allow_private_targets,
@@ -4744,30 +5024,60 @@ async fn admin_create_endpoint(
         CreateEndpointRequest::Ss2022_2022Blake3Aes128Gcm {
             node_id,
             port,
+            method,
             canary_upstream,
             accepted_authorities,
             mihomo_smux,
         } => {
-            if canary_upstream.is_some() {
-                return Err(ApiError::invalid_request(
-                    "canary_upstream is only supported for vless endpoints",
-                ));
-            }
-            if accepted_authorities.is_some() {
-                return Err(ApiError::invalid_request(
-                    "accepted_authorities is only supported for managed vless endpoints",
-                ));
-            }
-            let mihomo_smux = mihomo_smux.unwrap_or_default();
-            mihomo_smux.validate().map_err(ApiError::invalid_request)?;
+            let meta = endpoint_kinds::ss2022_create_meta(
+                method,
+                canary_upstream,
+                accepted_authorities,
+                mihomo_smux,
+            )?;
             let store = state.store.lock().await;
             store.build_endpoint(
                 node_id,
-                crate::domain::EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
                 port,
-                json!({ "mihomo_smux": mihomo_smux }),
+                meta,
             )?
         }
+        CreateEndpointRequest::TrojanRealityTcp {
+            node_id,
+            port,
+            reality,
+            mihomo_smux,
+        } => {
+            let meta = endpoint_kinds::trojan_create_meta(reality, mihomo_smux)?;
+            let store = state.store.lock().await;
+            store.build_endpoint(node_id, EndpointKind::TrojanRealityTcp, port, meta)?
+        }
+        CreateEndpointRequest::TrojanTlsTcp {
+            node_id,
+            port,
+            server_name,
+            mihomo_smux,
+        } => {
+            let store = state.store.lock().await;
+            let node = store
+                .get_node(&node_id)
+                .ok_or_else(|| ApiError::invalid_request(format!("node not found: {node_id}")))?;
+            let meta = endpoint_kinds::trojan_tls_create_meta(&node, server_name, mihomo_smux)?;
+            store.build_endpoint(node_id, EndpointKind::TrojanTlsTcp, port, meta)?
+        }
+        CreateEndpointRequest::Hysteria2 {
+            node_id,
+            port,
+            server_name,
+        } => {
+            let store = state.store.lock().await;
+            let node = store
+                .get_node(&node_id)
+                .ok_or_else(|| ApiError::invalid_request(format!("node not found: {node_id}")))?;
+            let meta = endpoint_kinds::hysteria2_create_meta(&node, server_name)?;
+            store.build_endpoint(node_id, EndpointKind::Hysteria2, port, meta)?
+        }
     };
     let _ = raft_write(
         &state,
@@ -5121,6 +5431,7 @@ fn build_endpoint_probe_history_response(
                     target_id: sample.target_id.clone(),
                     target_url: sample.target_url.clone(),
                     error: sample.error.clone(),
+                    targets: sample.targets.clone(),
                     config_hash: sample.config_hash.clone(),
                 });
             }
@@ -5160,11 +5471,22 @@ fn build_endpoint_probe_history_response(
         .map(|slot| slot.participating_nodes)
         .unwrap_or(0);
 
+    let unreachable_targets = history
+        .map(|history| {
+            endpoint_probe_targets::unreachable_targets_for_endpoint(
+                store.state(),
+                endpoint_id,
+                history,
+            )
+        })
+        .unwrap_or_default();
+
     AdminEndpointProbeHistoryResponse {
         endpoint_id: endpoint_id.to_string(),
         participating_nodes,
         expected_nodes: participating_nodes,
         slots,
+        unreachable_targets,
     }
 }
 
@@ -5195,11 +5517,12 @@ async fn admin_run_endpoint_probe_run(
     let run_id = crate::id::new_ulid_string();
     let now = Utc::now();
     let hour = crate::endpoint_probe::format_hour_key(now);
-    let config_hash = crate::endpoint_probe::probe_config_hash();
-
-    let nodes = {
+    let (config_hash, nodes) = {
         let store = state.store.lock().await;
-        store.list_nodes()
+        (
+            crate::endpoint_probe::probe_config_hash(&store.state().endpoint_probe_targets),
+            store.list_nodes(),
+        )
     };
 
     let local_node_id = state.cluster.node_id.clone();
@@ -5881,7 +6204,10 @@ async fn admin_internal_endpoint_probe_run(
         return Err(ApiError::invalid_request("hour is empty"));
     }
 
-    let local_hash = crate::endpoint_probe::probe_config_hash();
+    let local_hash = {
+        let store = state.store.lock().await;
+        crate::endpoint_probe::probe_config_hash(&store.state().endpoint_probe_targets)
+    };
     if local_hash != req.config_hash {
         return Err(ApiError::conflict(format!(
             "probe config hash mismatch: expected {local_hash}, got {}",
@@ -5965,7 +6291,7 @@ async fn admin_internal_endpoint_probe_run_events(
 async fn admin_patch_endpoint(
     Extension(state): Extension<AppState>,
     Path(endpoint_id): Path<String>,
-    ApiJson(req): ApiJson<PatchEndpointRequest>,
+    ApiJson(mut req): ApiJson<PatchEndpointRequest>,
 ) -> Result<Json<Endpoint>, ApiError> {
     let (mut endpoint, nodes, endpoints) = {
         let store = state.store.lock().await;
@@ -5976,7 +6302,7 @@ async fn admin_patch_endpoint(
     };
     let expected = endpoint.clone();
 
-    let desired_node_id = match req.node_id {
+    let desired_node_id = match req.node_id.take() {
         None => endpoint.node_id.clone(),
         Some(None) => {
             return Err(ApiError::invalid_request("node_id cannot be null"));
@@ -6106,39 +6432,13 @@ async fn admin_patch_endpoint(
             }
         }
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
-            if req.transport.is_some() {
-                return Err(ApiError::invalid_request(
-                    "transport is only supported for vless endpoints",
-                ));
-            }
-            if req.reality.is_some() {
-                return Err(ApiError::invalid_request(
-                    "ss2022 endpoints only support port updates",
-                ));
-            }
-            if req.canary_upstream.is_some() {
-                return Err(ApiError::invalid_request(
-                    "canary_upstream is only supported for vless endpoints",
-                ));
-            }
-            if req.accepted_authorities.is_some() {
-                return Err(ApiError::invalid_request(
-                    "accepted_authorities is only supported for managed vless endpoints",
-                ));
-            }
-            if let Some(mihomo_smux) = req.mihomo_smux {
-                let Some(mihomo_smux) = mihomo_smux else {
-                    return Err(ApiError::invalid_request("mihomo_smux cannot be null"));
-                };
-                mihomo_smux.validate().map_err(ApiError::invalid_request)?;
-                let mut meta: crate::protocol::Ss2022EndpointMeta =
-                    serde_json::from_value(endpoint.meta.clone())
-                        .map_err(|e| ApiError::internal(e.to_string()))?;
-                meta.mihomo_smux = mihomo_smux;
-                endpoint.meta =
-                    serde_json::to_value(meta).map_err(|e| ApiError::internal(e.to_string()))?;
-            }
+            endpoint_kinds::patch_ss2022_meta(&mut endpoint, req)?;
         }
+        EndpointKind::TrojanRealityTcp => endpoint_kinds::patch_trojan_meta(&mut endpoint, req)?,
+        EndpointKind::TrojanTlsTcp => {
+            endpoint_kinds::patch_trojan_tls_meta(&mut endpoint, req)?;
+        }
+        EndpointKind::Hysteria2 => endpoint_kinds::patch_hysteria2_meta(&req)?,
     }
 
     let _ = raft_write(
@@ -6629,6 +6929,12 @@ async fn admin_patch_user(
     if let Some(quota_reset) = req.quota_reset {
         user.quota_reset = quota_reset;
     }
+    if let Some(max_concurrent_ips) = req.max_concurrent_ips {
+        user.max_concurrent_ips = max_concurrent_ips;
+    }
+    if let Some(pinned_credentials) = req.pinned_credentials {
+        user.pinned_credentials = pinned_credentials;
+    }
 
     let _ = raft_write(
         &state,
@@ -7009,49 +7315,28 @@ struct ScopeQuery {
     scope: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum QuotaResetPolicy {
-    Monthly,
-    Unlimited,
-}
-
 fn resolve_node_quota_reset_for_status(
     store: &JsonSnapshotStore,
     node_id: &str,
-) -> Result<(QuotaResetPolicy, CycleTimeZone, u8), ApiError> {
+) -> Result<(Option<CycleSchedule>, CycleTimeZone), ApiError> {
     let node = store
         .get_node(node_id)
         .ok_or_else(|| ApiError::not_found(format!("node not found: {node_id}")))?;
 
-    let (policy, day_of_month, tz) = match node.quota_reset {
-        NodeQuotaReset::Unlimited { tz_offset_minutes } => (
-            QuotaResetPolicy::Unlimited,
-            1,
-            match tz_offset_minutes {
-                Some(tz_offset_minutes) => CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes },
-                None => CycleTimeZone::Local,
-            },
-        ),
-        NodeQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => (
-            QuotaResetPolicy::Monthly,
-            day_of_month,
-            match tz_offset_minutes {
-                Some(tz_offset_minutes) => CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes },
-                None => CycleTimeZone::Local,
-            },
-        ),
-    };
-
-    if !(1..=31).contains(&day_of_month) {
-        return Err(ApiError::internal(format!(
-            "invalid day_of_month: {day_of_month}"
-        )));
+    let schedule = node.quota_reset.cycle_schedule();
+    match schedule {
+        Some(CycleSchedule::Monthly { day_of_month }) if !(1..=31).contains(&day_of_month) => {
+            return Err(ApiError::internal(format!(
+                "invalid day_of_month: {day_of_month}"
+            )));
+        }
+        Some(CycleSchedule::FixedDays { length_days: 0, .. }) => {
+            return Err(ApiError::internal("invalid length_days: 0"));
+        }
+        _ => {}
     }
 
-    Ok((policy, tz, day_of_month))
+    Ok((schedule, node.quota_reset.cycle_time_zone()))
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -7167,14 +7452,14 @@ fn build_local_user_quota_summaries(
 
     // In shared-quota mode, the per-user "limit" is derived from the node budget and the user's
     // tier/weight. Compute it once so the loop can stay simple and stable.
-    let mut shared_cycle: Option<(QuotaResetPolicy, CycleTimeZone, u8)> = None;
+    let mut shared_cycle: Option<(Option<CycleSchedule>, CycleTimeZone)> = None;
     let mut shared_base_by_user: std::collections::BTreeMap<String, u64> =
         std::collections::BTreeMap::new();
     if shared_quota_enabled {
-        let (policy, tz, day_of_month) = resolve_node_quota_reset_for_status(store, local_node_id)?;
-        shared_cycle = Some((policy, tz, day_of_month));
+        let (schedule, tz) = resolve_node_quota_reset_for_status(store, local_node_id)?;
+        shared_cycle = Some((schedule, tz));
 
-        if policy != QuotaResetPolicy::Unlimited {
+        if schedule.is_some() {
             // Only allocate base quota among P1/P2 users (matching enforcement behavior).
             let mut items: Vec<(String, u16)> = Vec::new();
             for user_id in memberships_by_user.keys() {
@@ -7211,66 +7496,49 @@ fn build_local_user_quota_summaries(
 
         // Under the shared node quota policy, per-user quota summaries are derived from the
         // node's quota budget + quota_reset (not from per-grant/static quotas).
-        let (quota_limit_kind, quota_limit_bytes, policy, tz, day_of_month) =
-            if shared_quota_enabled {
-                if membership_keys.is_empty() {
-                    continue;
-                }
-                let (policy, tz, day_of_month) = shared_cycle
-                    .as_ref()
-                    .cloned()
-                    .expect("shared_cycle is set when shared_quota_enabled");
+        let (quota_limit_kind, quota_limit_bytes, schedule, tz) = if shared_quota_enabled {
+            if membership_keys.is_empty() {
+                continue;
+            }
+            let (schedule, tz) =
+                shared_cycle.expect("shared_cycle is set when shared_quota_enabled");
 
-                if policy == QuotaResetPolicy::Unlimited {
+            if schedule.is_none() {
+                (AdminUserQuotaLimitKind::Unlimited, 0, schedule, tz)
+            } else {
+                let tier = store
+                    .get_user(&user_id)
+                    .map(|u| u.priority_tier)
+                    .unwrap_or_default();
+                if tier == crate::domain::UserPriorityTier::P3 {
+                    // P3 has no fixed base share; it can only consume overflow.
                     (
-                        AdminUserQuotaLimitKind::Unlimited,
+                        AdminUserQuotaLimitKind::SharedOpportunistic,
                         0,
-                        policy,
+                        schedule,
                         tz,
-                        day_of_month,
                     )
                 } else {
-                    let tier = store
-                        .get_user(&user_id)
-                        .map(|u| u.priority_tier)
-                        .unwrap_or_default();
-                    if tier == crate::domain::UserPriorityTier::P3 {
-                        // P3 has no fixed base share; it can only consume overflow.
-                        (
-                            AdminUserQuotaLimitKind::SharedOpportunistic,
-                            0,
-                            policy,
-                            tz,
-                            day_of_month,
-                        )
-                    } else {
-                        let base = shared_base_by_user.get(&user_id).copied().unwrap_or(0);
-                        (
-                            AdminUserQuotaLimitKind::SharedBase,
-                            base,
-                            policy,
-                            tz,
-                            day_of_month,
-                        )
-                    }
+                    let base = shared_base_by_user.get(&user_id).copied().unwrap_or(0);
+                    (AdminUserQuotaLimitKind::SharedBase, base, schedule, tz)
                 }
-            } else {
-                // No fixed quota in membership-only mode. Still include users that have access
-                // so the admin UI can show current usage under an unlimited budget.
-                if membership_keys.is_empty() {
-                    continue;
-                };
-                (
-                    AdminUserQuotaLimitKind::Unlimited,
-                    0,
-                    QuotaResetPolicy::Unlimited,
-                    CycleTimeZone::Local,
-                    1,
-                )
+            }
+        } else {
+            // No fixed quota in membership-only mode. Still include users that have access
+            // so the admin UI can show current usage under an unlimited budget.
+            if membership_keys.is_empty() {
+                continue;
             };
+            (
+                AdminUserQuotaLimitKind::Unlimited,
+                0,
+                None,
+                CycleTimeZone::Local,
+            )
+        };
 
-        let (cycle_start_at, cycle_end_at) = if policy == QuotaResetPolicy::Monthly {
-            let (cycle_start, cycle_end) = current_cycle_window_at(tz, day_of_month, now)
+        let (cycle_start_at, cycle_end_at) = if let Some(schedule) = schedule {
+            let (cycle_start, cycle_end) = current_cycle_window_at(tz, schedule, now)
                 .map_err(|e| ApiError::internal(e.to_string()))?;
             (Some(cycle_start.to_rfc3339()), Some(cycle_end.to_rfc3339()))
         } else {
@@ -7292,6 +7560,13 @@ fn build_local_user_quota_summaries(
 
         let remaining_bytes = match quota_limit_kind {
             AdminUserQuotaLimitKind::Unlimited | AdminUserQuotaLimitKind::SharedOpportunistic => 0,
+            AdminUserQuotaLimitKind::SharedBase => {
+                let topups =
+                    crate::quota::active_topups(store.state(), &user_id, local_node_id, now);
+                quota_limit_bytes.saturating_sub(used_bytes).saturating_add(
+                    crate::quota::topup_remaining_bytes(store.topup_consumed_bytes(), &topups),
+                )
+            }
             _ => quota_limit_bytes.saturating_sub(used_bytes),
         };
         items.push(AdminUserQuotaSummaryItem {
@@ -7428,285 +7703,50 @@ async fn admin_list_user_quota_summaries(
     }))
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct AdminUserNodeQuotaStatusItem {
-    user_id: String,
-    node_id: String,
-    quota_limit_bytes: u64,
-    used_bytes: u64,
-    remaining_bytes: u64,
-    cycle_end_at: Option<String>,
-    quota_reset_source: QuotaResetSource,
+async fn admin_internal_list_user_quota_summaries(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<AdminUserQuotaSummariesResponse>, ApiError> {
+    let local_node_id = state.cluster.node_id.clone();
+    let items = {
+        let store = state.store.lock().await;
+        build_local_user_quota_summaries(&store, &local_node_id)?
+    };
+    Ok(Json(AdminUserQuotaSummariesResponse {
+        partial: false,
+        unreachable_nodes: Vec::new(),
+        items,
+    }))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct AdminUserNodeQuotaStatusResponse {
-    partial: bool,
-    unreachable_nodes: Vec<String>,
-    items: Vec<AdminUserNodeQuotaStatusItem>,
+async fn admin_put_user_node_quota(
+    Extension(state): Extension<AppState>,
+    Path((user_id, node_id)): Path<(String, String)>,
+    ApiJson(req): ApiJson<serde_json::Value>,
+) -> Result<Json<UserNodeQuota>, ApiError> {
+    // Deprecated: static per-user node quotas can bypass the shared quota policy.
+    // Keep the read-only status endpoints; deny writes.
+    let _ = (&state, &user_id, &node_id, &req);
+    Err(ApiError::new(
+        "gone",
+        StatusCode::GONE,
+        "user node quotas are no longer editable; configure node quota_limit_bytes + user node weights instead",
+    ))
 }
 
-fn build_local_user_node_quota_status(
-    store: &JsonSnapshotStore,
-    local_node_id: &str,
-    user_id: &str,
-) -> Result<Vec<AdminUserNodeQuotaStatusItem>, ApiError> {
-    let now = Utc::now();
-    let node_quota_limit_bytes = store
-        .get_node(local_node_id)
-        .map(|n| n.quota_limit_bytes)
-        .unwrap_or(0);
-    let (policy, tz, day_of_month) = resolve_node_quota_reset_for_status(store, local_node_id)?;
-    let (cycle_start_at, cycle_end_at) = if policy == QuotaResetPolicy::Monthly {
-        let (cycle_start, cycle_end) = current_cycle_window_at(tz, day_of_month, now)
-            .map_err(|e| ApiError::internal(e.to_string()))?;
-        (Some(cycle_start.to_rfc3339()), Some(cycle_end.to_rfc3339()))
-    } else {
-        (None, None)
-    };
-
-    let endpoints_by_id = store
-        .list_endpoints()
-        .into_iter()
-        .map(|e| (e.endpoint_id.clone(), e))
-        .collect::<std::collections::BTreeMap<_, _>>();
-
-    let membership_keys: Vec<String> = store
-        .state()
-        .node_user_endpoint_memberships
-        .iter()
-        .filter(|m| m.user_id == user_id)
-        .filter(|m| {
-            endpoints_by_id
-                .get(&m.endpoint_id)
-                .is_some_and(|ep| ep.node_id == local_node_id)
-        })
-        .map(|m| crate::state::membership_key(&m.user_id, &m.endpoint_id))
-        .collect();
-
-    if membership_keys.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let tier = store
-        .get_user(user_id)
-        .map(|u| u.priority_tier)
-        .unwrap_or_default();
-
-    let quota_limit_bytes =
-        if node_quota_limit_bytes == 0 || tier == crate::domain::UserPriorityTier::P3 {
-            0
-        } else {
-            // Compute the user's base share of the node budget (P1/P2 only).
-            let mut items: Vec<(String, u16)> = Vec::new();
-            let mut node_user_ids = BTreeSet::<String>::new();
-            for m in store.state().node_user_endpoint_memberships.iter() {
-                if endpoints_by_id
-                    .get(&m.endpoint_id)
-                    .is_some_and(|ep| ep.node_id == local_node_id)
-                {
-                    node_user_ids.insert(m.user_id.clone());
-                }
-            }
-
-            for uid in node_user_ids {
-                if uid == crate::endpoint_probe::PROBE_USER_ID {
-                    continue;
-                }
-                let tier = store
-                    .get_user(&uid)
-                    .map(|u| u.priority_tier)
-                    .unwrap_or_default();
-                if tier == crate::domain::UserPriorityTier::P3 {
-                    continue;
-                }
-                let weight = store.resolve_user_node_weight(&uid, local_node_id);
-                items.push((uid, weight));
-            }
-            items.sort_by(|a, b| a.0.cmp(&b.0));
-            items.dedup_by(|a, b| a.0 == b.0);
-
-            let distributable = crate::quota_policy::distributable_bytes(node_quota_limit_bytes);
-            let base_by_user = crate::quota_policy::allocate_total_by_weight(distributable, &items);
-            base_by_user
-                .into_iter()
-                .find_map(|(uid, base)| (uid == user_id).then_some(base))
-                .unwrap_or(0)
-        };
-
-    let used_bytes = membership_keys.iter().fold(0u64, |acc, key| {
-        let Some(usage) = store.get_membership_usage(key) else {
-            return acc;
-        };
-        if let (Some(expected_start), Some(expected_end)) = (&cycle_start_at, &cycle_end_at)
-            && (usage.cycle_start_at != *expected_start || usage.cycle_end_at != *expected_end)
-        {
-            return acc;
-        }
-        acc.saturating_add(usage.used_bytes)
-    });
-
-    let remaining_bytes = match quota_limit_bytes {
-        0 => 0,
-        _ => quota_limit_bytes.saturating_sub(used_bytes),
-    };
-    Ok(vec![AdminUserNodeQuotaStatusItem {
-        user_id: user_id.to_string(),
-        node_id: local_node_id.to_string(),
-        quota_limit_bytes,
-        used_bytes,
-        remaining_bytes,
-        cycle_end_at,
-        quota_reset_source: QuotaResetSource::Node,
-    }])
-}
-
-async fn admin_get_user_node_quota_status(
-    Extension(state): Extension<AppState>,
-    Path(user_id): Path<String>,
-    Query(query): Query<ScopeQuery>,
-) -> Result<Json<AdminUserNodeQuotaStatusResponse>, ApiError> {
-    if let Some(scope) = query.scope.as_deref()
-        && scope != "local"
-    {
-        return Err(ApiError::invalid_request(
-            "invalid scope, expected local or omit",
-        ));
-    }
-
-    let local_node_id = state.cluster.node_id.clone();
-    let local_items = {
-        let store = state.store.lock().await;
-        if store.get_user(&user_id).is_none() {
-            return Err(ApiError::not_found(format!("user not found: {user_id}")));
-        }
-        build_local_user_node_quota_status(&store, &local_node_id, &user_id)?
-    };
-
-    if query.scope.as_deref() == Some("local") {
-        return Ok(Json(AdminUserNodeQuotaStatusResponse {
-            partial: false,
-            unreachable_nodes: Vec::new(),
-            items: local_items,
-        }));
-    }
-
-    let nodes = {
-        let store = state.store.lock().await;
-        store.list_nodes()
-    };
-    let client = state.mesh_client.clone();
-
-    let mut items = local_items;
-    let mut unreachable_nodes = Vec::new();
-
-    for node in nodes {
-        if node.node_id == local_node_id {
-            continue;
-        }
-        let base = node.api_base_url.trim_end_matches('/');
-        if base.is_empty() {
-            unreachable_nodes.push(node.node_id);
-            continue;
-        }
-        let response = match send_mesh_internal_read(
-            &state,
-            &client,
-            &node,
-            format!("/api/admin/_internal/users/{user_id}/node-quotas/status"),
-            Duration::from_secs(3),
-        )
-        .await
-        {
-            Ok(response) => response,
-            _ => {
-                unreachable_nodes.push(node.node_id);
-                continue;
-            }
-        };
-
-        if !response.status().is_success() {
-            unreachable_nodes.push(node.node_id);
-            continue;
-        }
-
-        match response.json::<AdminUserNodeQuotaStatusResponse>().await {
-            Ok(remote) => items.extend(remote.items),
-            Err(_) => unreachable_nodes.push(node.node_id),
-        }
-    }
-
-    let partial = !unreachable_nodes.is_empty();
-    Ok(Json(AdminUserNodeQuotaStatusResponse {
-        partial,
-        unreachable_nodes,
-        items,
-    }))
-}
-
-async fn admin_internal_list_user_quota_summaries(
-    Extension(state): Extension<AppState>,
-) -> Result<Json<AdminUserQuotaSummariesResponse>, ApiError> {
-    let local_node_id = state.cluster.node_id.clone();
-    let items = {
-        let store = state.store.lock().await;
-        build_local_user_quota_summaries(&store, &local_node_id)?
-    };
-    Ok(Json(AdminUserQuotaSummariesResponse {
-        partial: false,
-        unreachable_nodes: Vec::new(),
-        items,
-    }))
-}
-
-async fn admin_internal_get_user_node_quota_status(
-    Extension(state): Extension<AppState>,
-    Path(user_id): Path<String>,
-) -> Result<Json<AdminUserNodeQuotaStatusResponse>, ApiError> {
-    let local_node_id = state.cluster.node_id.clone();
-    let items = {
-        let store = state.store.lock().await;
-        if store.get_user(&user_id).is_none() {
-            return Err(ApiError::not_found(format!("user not found: {user_id}")));
-        }
-        build_local_user_node_quota_status(&store, &local_node_id, &user_id)?
-    };
-    Ok(Json(AdminUserNodeQuotaStatusResponse {
-        partial: false,
-        unreachable_nodes: Vec::new(),
-        items,
-    }))
-}
-
-async fn admin_put_user_node_quota(
-    Extension(state): Extension<AppState>,
-    Path((user_id, node_id)): Path<(String, String)>,
-    ApiJson(req): ApiJson<serde_json::Value>,
-) -> Result<Json<UserNodeQuota>, ApiError> {
-    // Deprecated: static per-user node quotas can bypass the shared quota policy.
-    // Keep the read-only status endpoints; deny writes.
-    let _ = (&state, &user_id, &node_id, &req);
-    Err(ApiError::new(
-        "gone",
-        StatusCode::GONE,
-        "user node quotas are no longer editable; configure node quota_limit_bytes + user node weights instead",
-    ))
-}
-
-async fn admin_get_user_access(
-    Extension(state): Extension<AppState>,
-    Path(user_id): Path<String>,
-) -> Result<Json<GetUserAccessResponse>, ApiError> {
-    let store = state.store.lock().await;
-    let items = store.list_user_access(&user_id).map_err(ApiError::from)?;
-    let auto_assign_endpoint_kinds = store
-        .list_user_auto_assign_endpoint_kinds(&user_id)
-        .map_err(ApiError::from)?;
-    Ok(Json(GetUserAccessResponse {
-        items,
-        auto_assign_endpoint_kinds,
-    }))
-}
+async fn admin_get_user_access(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<GetUserAccessResponse>, ApiError> {
+    let store = state.store.lock().await;
+    let items = store.list_user_access(&user_id).map_err(ApiError::from)?;
+    let auto_assign_endpoint_kinds = store
+        .list_user_auto_assign_endpoint_kinds(&user_id)
+        .map_err(ApiError::from)?;
+    Ok(Json(GetUserAccessResponse {
+        items,
+        auto_assign_endpoint_kinds,
+    }))
+}
 
 async fn admin_put_user_access(
     Extension(state): Extension<AppState>,
@@ -7925,174 +7965,6 @@ async fn admin_reset_user_credentials(
     }))
 }
 
-#[derive(Debug, Deserialize)]
-struct AlertsQuery {
-    scope: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct AlertItem {
-    #[serde(rename = "type")]
-    alert_type: String,
-    membership_key: String,
-    user_id: String,
-    endpoint_id: String,
-    owner_node_id: String,
-    quota_banned: bool,
-    quota_banned_at: Option<String>,
-    message: String,
-    action_hint: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct AlertsResponse {
-    partial: bool,
-    unreachable_nodes: Vec<String>,
-    items: Vec<AlertItem>,
-}
-
-const ALERT_TYPE_QUOTA_BANNED: &str = "quota_banned_membership";
-const ALERT_MESSAGE_QUOTA_BANNED: &str = "quota enforced on owner node (membership is blocked)";
-const ALERT_ACTION_HINT_QUOTA_BANNED: &str = "wait for rollover/unban or adjust quota policy";
-
-fn build_local_alerts(store: &JsonSnapshotStore, local_node_id: &str) -> Vec<AlertItem> {
-    let mut items = Vec::new();
-    let endpoints_by_id = store
-        .list_endpoints()
-        .into_iter()
-        .map(|e| (e.endpoint_id.clone(), e))
-        .collect::<std::collections::BTreeMap<_, _>>();
-
-    for membership in store.state().node_user_endpoint_memberships.iter() {
-        let endpoint = match endpoints_by_id.get(&membership.endpoint_id) {
-            Some(endpoint) => endpoint,
-            None => continue,
-        };
-        if endpoint.node_id != local_node_id {
-            continue;
-        }
-
-        let membership_key =
-            crate::state::membership_key(&membership.user_id, &membership.endpoint_id);
-        let usage = match store.get_membership_usage(&membership_key) {
-            Some(usage) => usage,
-            None => continue,
-        };
-        if !usage.quota_banned {
-            continue;
-        }
-
-        items.push(AlertItem {
-            alert_type: ALERT_TYPE_QUOTA_BANNED.to_string(),
-            membership_key,
-            user_id: membership.user_id.clone(),
-            endpoint_id: endpoint.endpoint_id.clone(),
-            owner_node_id: endpoint.node_id.clone(),
-            quota_banned: usage.quota_banned,
-            quota_banned_at: usage.quota_banned_at.clone(),
-            message: ALERT_MESSAGE_QUOTA_BANNED.to_string(),
-            action_hint: ALERT_ACTION_HINT_QUOTA_BANNED.to_string(),
-        });
-    }
-    items
-}
-
-async fn admin_get_alerts_response(
-    state: &AppState,
-    scope: Option<&str>,
-) -> Result<AlertsResponse, ApiError> {
-    if let Some(scope) = scope
-        && scope != "local"
-    {
-        return Err(ApiError::invalid_request(
-            "invalid scope, expected local or omit",
-        ));
-    }
-
-    let local_node_id = state.cluster.node_id.clone();
-    let local_items = {
-        let store = state.store.lock().await;
-        build_local_alerts(&store, &local_node_id)
-    };
-
-    if scope == Some("local") {
-        return Ok(AlertsResponse {
-            partial: false,
-            unreachable_nodes: Vec::new(),
-            items: local_items,
-        });
-    }
-
-    let nodes = {
-        let store = state.store.lock().await;
-        store.list_nodes()
-    };
-    let client = state.mesh_client.clone();
-
-    let mut items = local_items;
-    let mut unreachable_nodes = Vec::new();
-
-    for node in nodes {
-        if node.node_id == local_node_id {
-            continue;
-        }
-        let base = node.api_base_url.trim_end_matches('/');
-        if base.is_empty() {
-            unreachable_nodes.push(node.node_id);
-            continue;
-        }
-        let response = match send_mesh_internal_read(
-            state,
-            &client,
-            &node,
-            "/api/admin/_internal/alerts".to_string(),
-            Duration::from_secs(3),
-        )
-        .await
-        {
-            Ok(response) => response,
-            _ => {
-                unreachable_nodes.push(node.node_id);
-                continue;
-            }
-        };
-
-        if !response.status().is_success() {
-            unreachable_nodes.push(node.node_id);
-            continue;
-        }
-
-        match response.json::<AlertsResponse>().await {
-            Ok(remote) => items.extend(remote.items),
-            Err(_) => unreachable_nodes.push(node.node_id),
-        }
-    }
-
-    let partial = !unreachable_nodes.is_empty();
-    Ok(AlertsResponse {
-        partial,
-        unreachable_nodes,
-        items,
-    })
-}
-
-async fn admin_get_alerts(
-    Extension(state): Extension<AppState>,
-    Query(query): Query<AlertsQuery>,
-) -> Result<Json<AlertsResponse>, ApiError> {
-    Ok(Json(
-        admin_get_alerts_response(&state, query.scope.as_deref()).await?,
-    ))
-}
-
-async fn admin_internal_get_alerts(
-    Extension(state): Extension<AppState>,
-) -> Result<Json<AlertsResponse>, ApiError> {
-    Ok(Json(
-        admin_get_alerts_response(&state, Some("local")).await?,
-    ))
-}
-
 async fn fallback_not_found() -> ApiError {
     ApiError::not_found("not found")
 }
@@ -8118,6 +7990,9 @@ struct SubscriptionQuery {
 #[derive(Clone)]
 struct SubscriptionContext {
     user: User,
+    /// The token from the request path, which may be one of the user's secondary tokens.
+    subscription_token: String,
+    secondary_token: Option<crate::domain::SubscriptionToken>,
     memberships: Vec<crate::state::NodeUserEndpointMembership>,
     endpoints: Vec<Endpoint>,
     nodes: Vec<Node>,
@@ -8140,6 +8015,15 @@ fn text_plain_utf8(body: String) -> Response {
     (headers, body).into_response()
 }
 
+fn application_json_utf8(body: String) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "application/json; charset=utf-8".parse().unwrap(),
+    );
+    (headers, body).into_response()
+}
+
 fn text_yaml_utf8(body: String) -> Response {
     let mut headers = HeaderMap::new();
     headers.insert(
@@ -8154,18 +8038,23 @@ async fn load_subscription_context(
     subscription_token: &str,
 ) -> Result<SubscriptionContext, ApiError> {
     let store = state.store.lock().await;
-    let user = store
-        .get_user_by_subscription_token(subscription_token)
+    let (user, secondary_token) = store
+        .resolve_subscription_token(subscription_token, Utc::now())
         .ok_or_else(|| ApiError::not_found("not found"))?;
-    let memberships = store
+    let mut memberships = store
         .list_user_access(&user.user_id)
         .map_err(ApiError::from)?;
+    if let Some(token) = &secondary_token {
+        memberships.retain(|membership| token.allows_endpoint(&membership.endpoint_id));
+    }
     let endpoints = store.list_endpoints();
     let nodes = store.list_nodes();
     let node_egress_probes = store.list_node_egress_probes();
     let mihomo_profile = store.get_user_mihomo_profile(&user.user_id);
     Ok(SubscriptionContext {
         user,
+        subscription_token: subscription_token.to_string(),
+        secondary_token,
         memberships,
         endpoints,
         nodes,
@@ -8174,6 +8063,25 @@ async fn load_subscription_context(
     })
 }
 
+/// Picks the format to serve: the requested one, else the token's own, else base64. Tokens
+/// limited to a format refuse every other one.
+fn resolve_subscription_format<'a>(
+    ctx: &'a SubscriptionContext,
+    requested: Option<&'a str>,
+) -> Result<&'a str, ApiError> {
+    let limited_to = ctx
+        .secondary_token
+        .as_ref()
+        .and_then(|token| token.format.as_deref());
+    match (requested, limited_to) {
+        (Some(requested), Some(limited_to)) if requested != limited_to => Err(ApiError::forbidden(
+            format!("subscription token is limited to format {limited_to}"),
+        )),
+        (Some(format), _) | (None, Some(format)) => Ok(format),
+        (None, None) => Ok("base64"),
+    }
+}
+
 fn map_subscription_render_error(err: subscription::SubscriptionError) -> ApiError {
     match err {
         other @ subscription::SubscriptionError::MihomoReservedProxyProviderNameConflict {
@@ -8281,7 +8189,7 @@ fn render_mihomo_subscription(
                 let origin = resolve_request_origin(headers, fallback_api_base_url);
                 let system_provider_url = format!(
                     "{origin}/api/sub/{}/mihomo/provider/system",
-                    ctx.user.subscription_token
+                    ctx.subscription_token
                 );
                 let resource_mirror_base_url = format!("{origin}/api/mihomo/resources");
                 subscription::build_mihomo_provider_yaml_with_node_probes_mode(
@@ -8320,22 +8228,24 @@ fn render_mihomo_subscription(
 
 async fn get_subscription(
     Extension(state): Extension<AppState>,
+    peer: Option<Extension<ConnectInfo<std::net::SocketAddr>>>,
     headers: HeaderMap,
     Path(subscription_token): Path<String>,
     axum::extract::Query(query): axum::extract::Query<SubscriptionQuery>,
 ) -> Result<Response, ApiError> {
-    let format = match query.format.as_deref() {
-        None => "base64",
-        Some("raw") => "raw",
-        Some("clash") => "clash",
-        Some("mihomo") => "mihomo",
+    let requested_format = match query.format.as_deref() {
+        None => None,
+        Some(format @ ("raw" | "clash" | "mihomo" | "singbox")) => Some(format),
         Some(_) => {
             return Err(ApiError::invalid_request(
-                "invalid format, expected raw|clash|mihomo or omit for base64",
+                "invalid format, expected raw|clash|mihomo|singbox or omit for base64",
             ));
         }
     };
 
+    let ctx = load_subscription_context(&state, &subscription_token).await?;
+    let format = resolve_subscription_format(&ctx, requested_format)?;
+
     let external_resource_mode = match query.external_resources.as_deref() {
         None => subscription::MihomoExternalResourceMode::Direct,
         Some("mirror") if format == "mihomo" => subscription::MihomoExternalResourceMode::Mirror,
@@ -8357,9 +8267,7 @@ async fn get_subscription(
         .as_ref()
         .ok_or_else(|| ApiError::internal("cluster ca key is not available on this node"))?;
 
-    let ctx = load_subscription_context(&state, &subscription_token).await?;
-
-    match format {
+    let response = match format {
         "raw" => subscription::build_raw_text(
             ca_key_pem,
             &ctx.user,
@@ -8387,6 +8295,15 @@ async fn get_subscription(
         )
         .map(text_yaml_utf8)
         .map_err(|_e| ApiError::internal("failed to build subscription")),
+        "singbox" => subscription::build_singbox_json(
+            ca_key_pem,
+            &ctx.user,
+            &ctx.memberships,
+            &ctx.endpoints,
+            &ctx.nodes,
+        )
+        .map(application_json_utf8)
+        .map_err(|_e| ApiError::internal("failed to build subscription")),
         "mihomo" => render_mihomo_subscription(
             ca_key_pem,
             &ctx,
@@ -8396,11 +8313,71 @@ async fn get_subscription(
             external_resource_mode,
         ),
         _ => Err(ApiError::internal("unreachable subscription format")),
+    }?;
+    let extension = match format {
+        "clash" | "mihomo" => "yaml",
+        "singbox" => "json",
+        _ => "txt",
+    };
+    record_subscription_access(&state, &ctx, &headers, peer, format).await;
+    Ok(subscription_response(&state, &ctx, response, extension).await)
+}
+
+/// Logs a served fetch for the per-user access log and token abuse detection.
+async fn record_subscription_access(
+    state: &AppState,
+    ctx: &SubscriptionContext,
+    headers: &HeaderMap,
+    peer: Option<Extension<ConnectInfo<std::net::SocketAddr>>>,
+    format: &str,
+) {
+    let geo_resolver = state.geo_db_update.resolver();
+    let mut entry = SubscriptionAccessEntry {
+        accessed_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        user_id: ctx.user.user_id.clone(),
+        token_id: ctx
+            .secondary_token
+            .as_ref()
+            .map(|token| token.token_id.clone()),
+        node_id: state.cluster.node_id.clone(),
+        format: format.to_string(),
+        source_ip: subscription_access::client_ip(
+            headers,
+            peer.map(|Extension(ConnectInfo(addr))| addr),
+        ),
+        country: None,
+        user_agent: subscription_access::user_agent(headers),
+    };
+    entry.resolve_country(&geo_resolver);
+    if entry.country.is_none()
+        && geo_resolver.ip_geo_source() != IpGeoSource::Missing
+        && let Some(ip) = entry.source_ip.clone()
+    {
+        // Primed by the quota worker; abuse detection re-reads the cache later.
+        geo_resolver.enqueue_pending_ips(&[ip]).await;
     }
+    state.store.lock().await.record_subscription_access(&entry);
+}
+
+async fn subscription_response(
+    state: &AppState,
+    ctx: &SubscriptionContext,
+    response: Response,
+    extension: &str,
+) -> Response {
+    let userinfo = subscription_headers::load_subscription_userinfo(state, &ctx.user).await;
+    subscription_headers::with_subscription_headers(
+        response,
+        &ctx.user,
+        &userinfo,
+        state.config.subscription_update_interval_hours,
+        extension,
+    )
 }
 
 async fn get_subscription_mihomo_provider(
     Extension(state): Extension<AppState>,
+    peer: Option<Extension<ConnectInfo<std::net::SocketAddr>>>,
     headers: HeaderMap,
     Path(subscription_token): Path<String>,
     axum::extract::Query(query): axum::extract::Query<SubscriptionQuery>,
@@ -8411,7 +8388,8 @@ async fn get_subscription_mihomo_provider(
         .as_ref()
         .ok_or_else(|| ApiError::internal("cluster ca key is not available on this node"))?;
     let ctx = load_subscription_context(&state, &subscription_token).await?;
-    render_mihomo_subscription(
+    resolve_subscription_format(&ctx, Some("mihomo"))?;
+    let response = render_mihomo_subscription(
         ca_key_pem,
         &ctx,
         MihomoRenderMode::Provider,
@@ -8426,11 +8404,14 @@ async fn get_subscription_mihomo_provider(
                 ));
             }
         },
-    )
+    )?;
+    record_subscription_access(&state, &ctx, &headers, peer, "mihomo_provider").await;
+    Ok(subscription_response(&state, &ctx, response, "yaml").await)
 }
 
 async fn get_subscription_mihomo_provider_system(
     Extension(state): Extension<AppState>,
+    peer: Option<Extension<ConnectInfo<std::net::SocketAddr>>>,
     headers: HeaderMap,
     Path(subscription_token): Path<String>,
 ) -> Result<Response, ApiError> {
@@ -8440,14 +8421,17 @@ async fn get_subscription_mihomo_provider_system(
         .as_ref()
         .ok_or_else(|| ApiError::internal("cluster ca key is not available on this node"))?;
     let ctx = load_subscription_context(&state, &subscription_token).await?;
-    render_mihomo_subscription(
+    resolve_subscription_format(&ctx, Some("mihomo"))?;
+    let response = render_mihomo_subscription(
         ca_key_pem,
         &ctx,
         MihomoRenderMode::ProviderSystem,
         &headers,
         &state.config.api_base_url,
         subscription::MihomoExternalResourceMode::Direct,
-    )
+    )?;
+    record_subscription_access(&state, &ctx, &headers, peer, "mihomo_provider_system").await;
+    Ok(subscription_response(&state, &ctx, response, "yaml").await)
 }
 
 async fn get_mihomo_resource(
@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::{Json, extract::Extension};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, endpoint_requests::deserialize_optional_string};
+use crate::{
+    notify::{
+        NotificationEventKind, NotificationWebhook, WebhookFormat, validate_notification_webhooks,
+    },
+    state::DesiredStateCommand,
+};
+
+use super::raft_write;
+
+/// Webhooks as returned by the API. Signing secrets and Telegram bot tokens are write-only:
+/// responses only report whether one is set.
+#[derive(Debug, Serialize)]
+pub(super) struct NotificationWebhooksView {
+    webhooks: Vec<NotificationWebhookView>,
+}
+
+#[derive(Debug, Serialize)]
+struct NotificationWebhookView {
+    #[serde(flatten)]
+    webhook: NotificationWebhook,
+    has_secret: bool,
+    has_telegram_bot_token: bool,
+}
+
+impl NotificationWebhooksView {
+    fn new(webhooks: &[NotificationWebhook]) -> Self {
+        Self {
+            webhooks: webhooks
+                .iter()
+                .map(|webhook| NotificationWebhookView {
+                    has_secret: webhook.secret.is_some(),
+                    has_telegram_bot_token: webhook.telegram_bot_token.is_some(),
+                    webhook: NotificationWebhook {
+                        secret: None,
+                        telegram_bot_token: None,
+                        ..webhook.clone()
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct NotificationWebhooksRequest {
+    webhooks: Vec<NotificationWebhookRequest>,
+}
+
+/// One webhook in a PUT body. Omitting `secret` or `telegram_bot_token` keeps the value already
+/// stored for a webhook of the same name, so a document read back from GET can be written
+/// unchanged; `null` clears it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NotificationWebhookRequest {
+    name: String,
+    url: String,
+    format: WebhookFormat,
+    #[serde(default)]
+    telegram_chat_id: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_string")]
+    telegram_bot_token: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_optional_string")]
+    secret: Option<Option<String>>,
+    /// Echoed from GET; ignored.
+    #[serde(default)]
+    #[allow(dead_code)]
+    has_secret: bool,
+    /// Echoed from GET; ignored.
+    #[serde(default)]
+    #[allow(dead_code)]
+    has_telegram_bot_token: bool,
+    #[serde(default)]
+    events: BTreeSet<NotificationEventKind>,
+    #[serde(default)]
+    disabled: bool,
+}
+
+impl NotificationWebhookRequest {
+    fn into_webhook(self, current: &BTreeMap<&str, &NotificationWebhook>) -> NotificationWebhook {
+        let name = self.name.trim().to_string();
+        let current = current.get(name.as_str());
+        let secret = match self.secret {
+            Some(secret) => secret,
+            None => current.and_then(|webhook| webhook.secret.clone()),
+        };
+        let telegram_bot_token = match self.telegram_bot_token {
+            Some(token) => token,
+            None => current.and_then(|webhook| webhook.telegram_bot_token.clone()),
+        };
+        NotificationWebhook {
+            name,
+            url: self.url,
+            format: self.format,
+            telegram_chat_id: self.telegram_chat_id,
+            telegram_bot_token,
+            secret,
+            events: self.events,
+            disabled: self.disabled,
+        }
+    }
+}
+
+pub(super) async fn admin_get_notification_webhooks(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<NotificationWebhooksView>, ApiError> {
+    let store = state.store.lock().await;
+    Ok(Json(NotificationWebhooksView::new(
+        &store.state().notification_webhooks,
+    )))
+}
+
+pub(super) async fn admin_put_notification_webhooks(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<NotificationWebhooksRequest>,
+) -> Result<Json<NotificationWebhooksView>, ApiError> {
+    let webhooks = {
+        let store = state.store.lock().await;
+        let current = store
+            .state()
+            .notification_webhooks
+            .iter()
+            .map(|webhook| (webhook.name.as_str(), webhook))
+            .collect::<BTreeMap<_, _>>();
+        req.webhooks
+            .into_iter()
+            .map(|webhook| webhook.into_webhook(&current))
+            .collect::<Vec<_>>()
+    };
+    validate_notification_webhooks(&webhooks).map_err(ApiError::invalid_request)?;
+    raft_write(
+        &state,
+        DesiredStateCommand::SetNotificationWebhooks {
+            webhooks: webhooks.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(NotificationWebhooksView::new(&webhooks)))
+}
@@ -0,0 +1,52 @@
+use axum::{Json, extract::Extension};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    routing_policy::{XrayRoutingPolicy, validate_routing_policy},
+    state::DesiredStateCommand,
+};
+
+pub(super) async fn admin_get_xray_routing_policy(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<XrayRoutingPolicy>, ApiError> {
+    let store = state.store.lock().await;
+    Ok(Json(store.state().xray_routing_policy.clone()))
+}
+
+pub(super) async fn admin_put_xray_routing_policy(
+    Extension(state): Extension<AppState>,
+    ApiJson(mut req): ApiJson<XrayRoutingPolicy>,
+) -> Result<Json<XrayRoutingPolicy>, ApiError> {
+    req.blocked_domains = trimmed_entries(req.blocked_domains);
+    req.blocked_ips = trimmed_entries(req.blocked_ips);
+    validate_routing_policy(&req).map_err(ApiError::invalid_request)?;
+    {
+        let store = state.store.lock().await;
+        if let Some(endpoint_id) = req
+            .exempt_endpoint_ids
+            .iter()
+            .find(|endpoint_id| store.get_endpoint(endpoint_id).is_none())
+        {
+            return Err(ApiError::invalid_request(format!(
+                "exempt endpoint not found: {endpoint_id}"
+            )));
+        }
+    }
+    raft_write(
+        &state,
+        DesiredStateCommand::SetXrayRoutingPolicy {
+            policy: req.clone(),
+        },
+    )
+    .await?;
+    state.reconcile.request_full();
+    Ok(Json(req))
+}
+
+fn trimmed_entries(entries: Vec<String>) -> Vec<String> {
+    entries
+        .into_iter()
+        .map(|entry| entry.trim().to_string())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
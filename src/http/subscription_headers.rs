@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use axum::{
+    http::{HeaderValue, header},
+    response::Response,
+};
+use chrono::DateTime;
+
+use super::{
+    AdminUserNodeQuotaStatusItem, AppState, build_local_user_node_quota_status,
+    fetch_remote_user_node_quota_status,
+};
+use crate::domain::User;
+
+/// Overall budget for reading the other nodes' usage while a subscription is served; slower
+/// nodes are left out of the header rather than delaying the response.
+const USERINFO_REMOTE_DEADLINE: Duration = Duration::from_millis(1500);
+
+/// Usage and limits advertised to clients through `subscription-userinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct SubscriptionUserinfo {
+    upload: u64,
+    download: u64,
+    /// `0` when any node the user can reach has no limit.
+    total: u64,
+    expire: Option<i64>,
+}
+
+impl SubscriptionUserinfo {
+    /// Quota usage is metered per cycle as one combined byte count (uplink and downlink are only
+    /// kept as raw counter baselines), so all of it is reported as `download` and `upload` is
+    /// always `0`.
+    fn from_quota_status(user: &User, items: &[AdminUserNodeQuotaStatusItem]) -> Self {
+        let download = items
+            .iter()
+            .fold(0u64, |acc, item| acc.saturating_add(item.used_bytes));
+        let total = if items.iter().any(|item| item.quota_limit_bytes == 0) {
+            0
+        } else {
+            // Traffic drawn from top-ups counts towards the total so `total - download` stays
+            // the bytes the user can still spend.
+            items.iter().fold(0u64, |acc, item| {
+                acc.saturating_add(
+                    item.quota_limit_bytes
+                        .max(item.used_bytes)
+                        .saturating_add(item.topup_remaining_bytes),
+                )
+            })
+        };
+        let expire = user
+            .expires_at
+            .as_deref()
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .map(|at| at.timestamp());
+        Self {
+            upload: 0,
+            download,
+            total,
+            expire,
+        }
+    }
+
+    fn header_value(&self) -> String {
+        let mut value = format!(
+            "upload={}; download={}; total={}",
+            self.upload, self.download, self.total
+        );
+        if let Some(expire) = self.expire {
+            value.push_str(&format!("; expire={expire}"));
+        }
+        value
+    }
+}
+
+/// Sums the user's quota status across the cluster. Unreachable or slow nodes are left out
+/// rather than failing or stalling the subscription.
+pub(super) async fn load_subscription_userinfo(
+    state: &AppState,
+    user: &User,
+) -> SubscriptionUserinfo {
+    let mut items = {
+        let store = state.store.lock().await;
+        build_local_user_node_quota_status(&store, &state.cluster.node_id, &user.user_id)
+            .unwrap_or_default()
+    };
+    let (remote_items, _unreachable_nodes) =
+        fetch_remote_user_node_quota_status(state, &user.user_id, USERINFO_REMOTE_DEADLINE).await;
+    items.extend(remote_items);
+    SubscriptionUserinfo::from_quota_status(user, &items)
+}
+
+/// `filename*` keeps non-ASCII display names intact; `filename` is an ASCII fallback.
+fn content_disposition(display_name: &str, extension: &str) -> String {
+    let fallback = display_name
+        .chars()
+        .map(|ch| {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+                ch
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let fallback = if fallback.trim_matches('_').is_empty() {
+        "xp".to_string()
+    } else {
+        fallback
+    };
+    let encoded = display_name
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect::<String>();
+    format!(
+        "attachment; filename=\"{fallback}.{extension}\"; filename*=UTF-8''{encoded}.{extension}"
+    )
+}
+
+pub(super) fn with_subscription_headers(
+    mut response: Response,
+    user: &User,
+    userinfo: &SubscriptionUserinfo,
+    update_interval_hours: u32,
+    extension: &str,
+) -> Response {
+    let headers = response.headers_mut();
+    let values = [
+        ("subscription-userinfo", userinfo.header_value()),
+        ("profile-update-interval", update_interval_hours.to_string()),
+        (
+            header::CONTENT_DISPOSITION.as_str(),
+            content_disposition(&user.display_name, extension),
+        ),
+    ];
+    for (name, value) in values {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::QuotaResetSource;
+
+    fn item(quota_limit_bytes: u64, used_bytes: u64) -> AdminUserNodeQuotaStatusItem {
+        AdminUserNodeQuotaStatusItem {
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            quota_limit_bytes,
+            used_bytes,
+            remaining_bytes: quota_limit_bytes.saturating_sub(used_bytes),
+            topup_remaining_bytes: 0,
+            cycle_end_at: None,
+            quota_reset_source: QuotaResetSource::Node,
+        }
+    }
+
+    fn user(expires_at: Option<&str>) -> User {
+        User {
+            user_id: "user".to_string(),
+            display_name: "alice".to_string(),
+            subscription_token: "sub".to_string(),
+            credential_epoch: 0,
+            priority_tier: Default::default(),
+            quota_reset: Default::default(),
+            expires_at: expires_at.map(str::to_string),
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
+        }
+    }
+
+    #[test]
+    fn userinfo_sums_nodes_and_reports_expiry() {
+        let info = SubscriptionUserinfo::from_quota_status(
+            &user(Some("2026-01-01T00:00:00Z")),
+            &[item(100, 10), item(50, 5)],
+        );
+        assert_eq!(
+            info.header_value(),
+            "upload=0; download=15; total=150; expire=1767225600"
+        );
+    }
+
+    #[test]
+    fn userinfo_total_is_unlimited_when_any_node_is_unlimited() {
+        let info =
+            SubscriptionUserinfo::from_quota_status(&user(None), &[item(100, 10), item(0, 7)]);
+        assert_eq!(info.header_value(), "upload=0; download=17; total=0");
+    }
+
+    #[test]
+    fn userinfo_total_includes_topups() {
+        let mut topped_up = item(100, 130);
+        topped_up.topup_remaining_bytes = 70;
+        topped_up.remaining_bytes = 70;
+        let info = SubscriptionUserinfo::from_quota_status(&user(None), &[topped_up]);
+        assert_eq!(info.header_value(), "upload=0; download=130; total=200");
+    }
+
+    #[test]
+    fn content_disposition_encodes_non_ascii_names() {
+        assert_eq!(
+            content_disposition("小明 phone", "yaml"),
+            concat!(
+                "attachment; filename=\"___phone.yaml\"; ",
+                "filename*=UTF-8''%E5%B0%8F%E6%98%8E%20phone.yaml"
+            )
+        );
+    }
+}
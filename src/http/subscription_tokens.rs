@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::StatusCode,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{domain::SubscriptionToken, id::new_ulid_string, state::DesiredStateCommand};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct CreateSubscriptionTokenRequest {
+    /// Label shown to admins, e.g. the device the token is handed to; unique per user.
+    name: String,
+    /// RFC3339 timestamp after which the token stops resolving.
+    #[serde(default)]
+    expires_at: Option<String>,
+    /// Restricts fetches to one format, which also becomes the default for the token.
+    #[serde(default)]
+    format: Option<String>,
+    /// Restricts the rendered endpoints; omit for every endpoint the user can access.
+    #[serde(default)]
+    endpoint_ids: Option<BTreeSet<String>>,
+}
+
+#[derive(Serialize)]
+pub(super) struct SubscriptionTokensResponse {
+    items: Vec<SubscriptionToken>,
+}
+
+pub(super) async fn admin_list_user_subscription_tokens(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<SubscriptionTokensResponse>, ApiError> {
+    let store = state.store.lock().await;
+    if store.get_user(&user_id).is_none() {
+        return Err(ApiError::not_found(format!("user not found: {user_id}")));
+    }
+    let items = store
+        .state()
+        .subscription_tokens
+        .values()
+        .filter(|token| token.user_id == user_id)
+        .cloned()
+        .collect();
+    Ok(Json(SubscriptionTokensResponse { items }))
+}
+
+pub(super) async fn admin_create_user_subscription_token(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+    ApiJson(req): ApiJson<CreateSubscriptionTokenRequest>,
+) -> Result<Json<SubscriptionToken>, ApiError> {
+    let token = SubscriptionToken {
+        token_id: new_ulid_string(),
+        user_id,
+        name: req.name.trim().to_string(),
+        token: format!("sub_{}", new_ulid_string()),
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        expires_at: req.expires_at.filter(|raw| !raw.trim().is_empty()),
+        format: req.format.filter(|format| !format.trim().is_empty()),
+        endpoint_ids: req.endpoint_ids,
+    };
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::CreateSubscriptionToken {
+            token: token.clone(),
+        },
+    )
+    .await?;
+    Ok(Json(token))
+}
+
+pub(super) async fn admin_revoke_user_subscription_token(
+    Extension(state): Extension<AppState>,
+    Path((user_id, token_id)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    let belongs_to_user = state
+        .store
+        .lock()
+        .await
+        .state()
+        .subscription_tokens
+        .get(&token_id)
+        .is_some_and(|token| token.user_id == user_id);
+    if !belongs_to_user {
+        return Err(ApiError::not_found(format!(
+            "subscription token not found: {token_id}"
+        )));
+    }
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::RevokeSubscriptionToken { token_id },
+    )
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -22,14 +22,31 @@ use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 #[path = "tests/admin_auth_tests.rs"]
 mod admin_auth_tests;
+mod admin_principals;
+mod audit;
+mod backup;
+mod credential_rotation;
+mod dns_failover;
+mod endpoint_egress;
+mod endpoint_probe_targets;
 #[path = "tests/history_repository.rs"]
 mod history_repository;
+mod login_token;
 mod managed_vless_create;
 mod mihomo_smux;
+mod notifications;
+mod routing_policy;
 #[path = "tests/status_events.rs"]
 mod status_events;
+mod subscription_access;
+mod subscription_headers;
+mod subscription_tokens;
+mod traffic_topups;
+mod user_bulk;
+mod user_lifecycle;
 mod vless_xhttp;
 use crate::{
+    admin_principal::AdminScope,
     cloudflared_supervisor::{CloudflaredHealthHandle, CloudflaredStatus},
     cluster_metadata::ClusterMetadata,
     config::Config,
@@ -147,6 +164,9 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: hash,
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -175,9 +195,14 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
@@ -796,6 +821,7 @@ fn endpoint_probe_sample(
         } else {
             Some("probe failed".to_string())
         },
+        targets: Vec::new(),
         config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
     }
 }
@@ -1062,6 +1088,7 @@ async fn setup_subscription_fixtures(tmp: &TempDir, app: &axum::Router) -> Subsc
         &cluster_ca_key_pem,
         &user_id,
         credential_epoch,
+        crate::protocol::SS2022_PSK_LEN_BYTES_AES_128,
     )
     .expect("derive ss2022 user_psk");
     let password = ss2022_password(&meta.server_psk_b64, &user_psk_b64);
@@ -1093,59 +1120,6 @@ async fn unauthorized_admin_returns_401_with_error_shape() {
     assert!(json["error"]["details"].is_object());
 }
 
-#[tokio::test]
-async fn login_token_jwt_can_access_admin_endpoints() {
-    let tmp = tempfile::tempdir().unwrap();
-    let app = app(&tmp);
-    let meta = ClusterMetadata::load(tmp.path()).unwrap();
-
-    let token_id = crate::id::new_ulid_string();
-    let now = chrono::Utc::now();
-    let secret = test_admin_token_hash();
-    let jwt = crate::login_token::issue_login_token_jwt(&meta.cluster_id, &token_id, now, &secret);
-
-    let res = app
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri("/api/admin/alerts")
-                .header(header::AUTHORIZATION, format!("Bearer {jwt}"))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
-}
-
-#[tokio::test]
-async fn expired_login_token_jwt_is_rejected() {
-    let tmp = tempfile::tempdir().unwrap();
-    let app = app(&tmp);
-    let meta = ClusterMetadata::load(tmp.path()).unwrap();
-
-    let token_id = crate::id::new_ulid_string();
-    let now = chrono::Utc::now();
-    let issued_at =
-        now - chrono::Duration::seconds(crate::login_token::LOGIN_TOKEN_TTL_SECONDS + 1);
-    let secret = test_admin_token_hash();
-    let jwt =
-        crate::login_token::issue_login_token_jwt(&meta.cluster_id, &token_id, issued_at, &secret);
-
-    let res = app
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri("/api/admin/alerts")
-                .header(header::AUTHORIZATION, format!("Bearer {jwt}"))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
-}
-
 #[tokio::test]
 async fn internal_client_write_requires_admin_auth() {
     let tmp = tempfile::tempdir().unwrap();
@@ -3080,6 +3054,7 @@ async fn admin_config_returns_safe_view_and_masks_token() {
     assert!(json.get("vless_https_canary_status").is_some());
     assert_eq!(json["quota_poll_interval_secs"], 10);
     assert_eq!(json["quota_auto_unban"], true);
+    assert_eq!(json["quota_warning_thresholds"], json!([80, 95]));
     assert_eq!(json["mihomo_resource_allow_private_targets"], false);
     assert!(json.get("mihomo_delivery_mode").is_none());
 
@@ -8994,7 +8969,7 @@ async fn user_node_quota_status_includes_membership_usage() {
             crate::cycle::CycleTimeZone::FixedOffsetMinutes {
                 tz_offset_minutes: 0,
             },
-            1,
+            crate::cycle::CycleSchedule::Monthly { day_of_month: 1 },
             chrono::Utc::now(),
         )
         .unwrap();
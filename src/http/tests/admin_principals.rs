@@ -0,0 +1,218 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+fn bearer(method: &str, uri: &str, token: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {token}"));
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    }
+}
+
+async fn create_principal(app: &axum::Router, name: &str, scope: &str) -> (String, String) {
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/principals",
+            json!({ "name": name, "scope": scope }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = body_json(res).await;
+    assert_eq!(body["principal"]["scope"], scope);
+    assert!(body["principal"].get("token_hash").is_none());
+    (
+        body["principal"]["principal_id"]
+            .as_str()
+            .unwrap()
+            .to_string(),
+        body["token"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn status(app: &axum::Router, req: Request<Body>) -> StatusCode {
+    app.clone().oneshot(req).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn principal_scopes_are_enforced_per_route() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, _store) = app_with(&tmp, ReconcileHandle::noop());
+    let (_, reader) = create_principal(&app, "reader", "read_only").await;
+    let (_, manager) = create_principal(&app, "manager", "user_manager").await;
+    let new_user = || Some(json!({ "display_name": "alice" }));
+    let webhooks = || Some(json!({ "webhooks": [] }));
+
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &reader, None)).await,
+        StatusCode::OK
+    );
+    // User reads carry subscription tokens.
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/users", &reader, None)).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/users", &manager, None)).await,
+        StatusCode::OK
+    );
+    let res = app
+        .clone()
+        .oneshot(bearer("POST", "/api/admin/users", &reader, new_user()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(body_json(res).await["error"]["code"], "forbidden");
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/principals", &reader, None)).await,
+        StatusCode::FORBIDDEN
+    );
+
+    assert_eq!(
+        status(
+            &app,
+            bearer("POST", "/api/admin/users", &manager, new_user())
+        )
+        .await,
+        StatusCode::OK
+    );
+    let put_webhooks = bearer(
+        "PUT",
+        "/api/admin/notifications/webhooks",
+        &manager,
+        webhooks(),
+    );
+    assert_eq!(status(&app, put_webhooks).await, StatusCode::FORBIDDEN);
+    assert_eq!(
+        status(
+            &app,
+            req_authed_json(
+                "PUT",
+                "/api/admin/notifications/webhooks",
+                json!({ "webhooks": [] })
+            )
+        )
+        .await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn principal_tokens_rotate_and_revoke_individually() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    let (principal_id, token) = create_principal(&app, "ops", "admin").await;
+    let (_, other) = create_principal(&app, "other", "read_only").await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/principals",
+            json!({ "name": "ops", "scope": "read_only" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let res = app
+        .clone()
+        .oneshot(bearer(
+            "POST",
+            &format!("/api/admin/principals/{principal_id}/rotate-token"),
+            &token,
+            None,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let rotated = body_json(res).await["token"].as_str().unwrap().to_string();
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &token, None)).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &rotated, None)).await,
+        StatusCode::OK
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "POST",
+            &format!("/api/admin/principals/{principal_id}/revoke"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(body_json(res).await["revoked_at"].is_string());
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &rotated, None)).await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &other, None)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(
+            &app,
+            req_authed("POST", "/api/admin/principals/missing/revoke")
+        )
+        .await,
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(store.lock().await.state().admin_principals.len(), 2);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/audit?principal=principal:{principal_id}"),
+        ))
+        .await
+        .unwrap();
+    let items = body_json(res).await["items"].as_array().unwrap().clone();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["command"], "upsert_admin_principal");
+    assert_eq!(items[0]["principal"]["name"], "ops");
+}
+
+#[tokio::test]
+async fn scoped_login_tokens_are_enforced() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, _store) = app_with(&tmp, ReconcileHandle::noop());
+    let meta = ClusterMetadata::load(tmp.path()).unwrap();
+    let jwt = crate::login_token::issue_login_token_jwt(
+        &meta.cluster_id,
+        "01JTESTTOKENID",
+        chrono::Utc::now(),
+        &test_admin_token_hash(),
+        AdminScope::ReadOnly,
+    );
+
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/endpoints", &jwt, None)).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        status(&app, bearer("GET", "/api/admin/users", &jwt, None)).await,
+        StatusCode::FORBIDDEN
+    );
+    let create = bearer(
+        "POST",
+        "/api/admin/users",
+        &jwt,
+        Some(json!({ "display_name": "alice" })),
+    );
+    assert_eq!(status(&app, create).await, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,163 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+async fn put_webhooks(app: &axum::Router, name: &str) {
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            json!({
+                "webhooks": [{
+                    "name": name,
+                    "url": "https://hooks.example.invalid/xp",
+                    "format": "json",
+                    "secret": "s3cret",
+                }]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn admin_writes_are_audited_with_principal_and_redacted_diff() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    put_webhooks(&app, "ops").await;
+    put_webhooks(&app, "pager").await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/audit?limit=1"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_json(res).await;
+    let items = page["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    let entry = &items[0];
+    assert_eq!(entry["command"], "set_notification_webhooks");
+    assert_eq!(entry["principal"], json!({ "kind": "admin_token" }));
+    let node_id = store
+        .lock()
+        .await
+        .state()
+        .nodes
+        .keys()
+        .next()
+        .unwrap()
+        .clone();
+    assert_eq!(entry["origin_node_id"], node_id);
+    assert_eq!(
+        entry["changes"],
+        json!([{ "path": "notification_webhooks", "before": [{
+            "name": "ops",
+            "url": "https://hooks.example.invalid/xp",
+            "format": "json",
+            "secret": "[redacted]",
+            "events": [],
+            "disabled": false,
+        }], "after": [{
+            "name": "pager",
+            "url": "https://hooks.example.invalid/xp",
+            "format": "json",
+            "secret": "[redacted]",
+            "events": [],
+            "disabled": false,
+        }] }])
+    );
+
+    let next_before = page["next_before"].as_u64().unwrap();
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/audit?before={next_before}&command=set_notification_webhooks"),
+        ))
+        .await
+        .unwrap();
+    let page = body_json(res).await;
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+    assert!(page.get("next_before").is_none());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            "/api/admin/audit?principal=login_token:x",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(body_json(res).await, json!({ "items": [] }));
+}
+
+#[tokio::test]
+async fn audit_log_exports_jsonl_and_rejects_bad_filters() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, _store) = app_with(&tmp, ReconcileHandle::noop());
+    put_webhooks(&app, "ops").await;
+    put_webhooks(&app, "pager").await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/audit?format=jsonl"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers()[axum::http::header::CONTENT_TYPE],
+        "application/x-ndjson"
+    );
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lines = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    let newest: Value = serde_json::from_str(lines[0]).unwrap();
+    let oldest: Value = serde_json::from_str(lines[1]).unwrap();
+    assert!(newest["log_index"].as_u64() > oldest["log_index"].as_u64());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/audit?since=yesterday"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/audit?limit=0"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn audit_reads_are_partial_on_a_node_that_caught_up_from_a_snapshot() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    put_webhooks(&app, "ops").await;
+    store.lock().await.mark_audit_entries_missing_through(1);
+
+    // No peer holds the whole log, so the local entries are served and flagged.
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/audit"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let page = body_json(res).await;
+    assert_eq!(page["partial"], true);
+    assert_eq!(page["items"].as_array().unwrap().len(), 1);
+
+    let res = app
+        .oneshot(req_authed("GET", "/api/admin/_internal/audit"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,140 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+async fn export_backup(app: &axum::Router) -> Value {
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/backup"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    body_json(res).await
+}
+
+#[tokio::test]
+async fn backup_restores_into_a_fresh_cluster_after_a_dry_run() {
+    let source_tmp = tempfile::tempdir().unwrap();
+    let (source, source_store) = app_with(&source_tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&source_store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&source_tmp, &source).await;
+    let artifact = export_backup(&source).await;
+    assert_eq!(artifact["format"], crate::backup::BACKUP_FORMAT);
+
+    let res = source
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({
+                "artifact": artifact,
+                "dry_run": true,
+                "trusted_signer": artifact["signer_public_key"],
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let report = body_json(res).await;
+    let kinds = report["conflicts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|conflict| conflict["kind"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec!["user_exists", "endpoint_exists"]);
+
+    let target_tmp = tempfile::tempdir().unwrap();
+    let (target, target_store) = app_with(&target_tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&target_store, "example.com").await;
+    let res = target
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({
+                "artifact": artifact,
+                "trusted_signer": artifact["signer_public_key"],
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let report = body_json(res).await;
+    assert_eq!(report["applied"], true);
+    assert_eq!(report["users"], 1);
+    assert_eq!(
+        report["node_map"][&fixtures.node_id],
+        fixtures.node_id.as_str()
+    );
+
+    let token = &fixtures.subscription_token;
+    let res = target
+        .oneshot(req("GET", &format!("/api/sub/{token}?format=raw")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn restore_rejects_tampered_backups_and_conflicting_writes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    setup_subscription_fixtures(&tmp, &app).await;
+    let artifact = export_backup(&app).await;
+
+    let signer = artifact["signer_public_key"].clone();
+    let mut tampered = artifact.clone();
+    tampered["cluster_id"] = json!("someone-else");
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({ "artifact": tampered, "trusted_signer": signer }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({ "artifact": artifact, "trusted_signer": signer }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn restore_requires_a_trusted_signer_unless_explicitly_waived() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let artifact = export_backup(&app).await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({ "artifact": artifact, "dry_run": true }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(body_json(res).await["error"]["code"], "invalid_request");
+
+    let res = app
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/backup/restore",
+            json!({ "artifact": artifact, "dry_run": true, "allow_untrusted_signer": true }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn credential_rotation_starts_reports_progress_and_aborts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    let node_id = store.lock().await.list_nodes()[0].node_id.clone();
+    let uri = "/api/admin/credential-rotation";
+
+    let res = app.clone().oneshot(req_authed("GET", uri)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await, json!({ "rotation": null }));
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("POST", "/api/admin/credential-rotation/promote"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            uri,
+            json!({ "grace_period_hours": 10_000 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            uri,
+            json!({ "grace_period_hours": 48 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let rotation = body_json(res).await["rotation"].clone();
+    assert_eq!(rotation["phase"], "staged");
+    assert_eq!(rotation["grace_period_hours"], 48);
+    assert_eq!(rotation["nodes"][0]["node_id"], node_id.as_str());
+    assert_eq!(rotation["nodes"][0]["stage"], "pending");
+    assert_eq!(
+        rotation["promote_blocked_by"],
+        format!("nodes without a certificate from the new CA: {node_id}")
+    );
+    assert_ne!(
+        rotation["previous_ca_fingerprint"],
+        rotation["next_ca_fingerprint"]
+    );
+    // Key material stays in Raft; the admin view only carries fingerprints.
+    assert!(!rotation.to_string().contains("PRIVATE KEY"));
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json("POST", uri, json!({})))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/cluster/join-tokens",
+            json!({ "ttl_seconds": 60 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let rotation_id = rotation["rotation_id"].as_str().unwrap().to_string();
+    DesiredStateCommand::RecordCredentialRotationProgress {
+        rotation_id,
+        node_id: node_id.clone(),
+        stage: crate::credential_rotation::CredentialRotationNodeStage::CertIssued,
+        at: "2026-10-01T00:00:00Z".to_string(),
+    }
+    .apply(store.lock().await.state_mut())
+    .unwrap();
+    let res = app.clone().oneshot(req_authed("GET", uri)).await.unwrap();
+    let rotation = body_json(res).await["rotation"].clone();
+    assert_eq!(rotation["nodes"][0]["stage"], "cert_issued");
+    assert_eq!(rotation["promote_blocked_by"], Value::Null);
+    assert_eq!(
+        rotation["retire_blocked_by"],
+        "rotation must be promoted before it is retired"
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("DELETE", uri))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await, json!({ "rotation": null }));
+    assert!(store.lock().await.state().credential_rotation.is_none());
+}
@@ -0,0 +1,132 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+const PRIMARY: &str = "01JFAILOVERPRIMARY00000000";
+const STANDBY: &str = "01JFAILOVERSTANDBY00000000";
+
+#[tokio::test]
+async fn dns_failover_groups_crud_and_validation() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    {
+        let mut store = store.lock().await;
+        add_cluster_node(&mut store, PRIMARY, || "failover-primary");
+        add_cluster_node(&mut store, STANDBY, || "failover-standby");
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/dns-failover-groups",
+            json!({
+                "hostname": " Edge.Example.com. ",
+                "primary_node_id": PRIMARY,
+                "standby_node_ids": [STANDBY]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let created = body_json(res).await;
+    assert_eq!(created["hostname"], json!("edge.example.com"));
+    let group_id = created["group_id"].as_str().unwrap().to_string();
+    let path = format!("/api/admin/dns-failover-groups/{group_id}");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/dns-failover-groups"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await, json!({ "items": [created] }));
+
+    let group = |hostname: &str, primary: &str, standbys: Value| {
+        json!({
+            "hostname": hostname,
+            "primary_node_id": primary,
+            "standby_node_ids": standbys
+        })
+    };
+    let mut two_ipv4 = group("other.example.com", PRIMARY, json!([STANDBY]));
+    two_ipv4["primary_addresses"] = json!(["203.0.113.10", "203.0.113.11"]);
+    for invalid in [
+        group("edge.example.com", PRIMARY, json!([])),
+        group("edge.example.com", PRIMARY, json!([PRIMARY])),
+        group("not a host", PRIMARY, json!([STANDBY])),
+        group(
+            "other.example.com",
+            PRIMARY,
+            json!(["01JUNKNOWNNODE000000000000"]),
+        ),
+        // The hostname already belongs to the first group.
+        group("edge.example.com", STANDBY, json!([PRIMARY])),
+        two_ipv4,
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req_authed_json(
+                "POST",
+                "/api/admin/dns-failover-groups",
+                invalid.clone(),
+            ))
+            .await
+            .unwrap();
+        assert!(res.status().is_client_error(), "{invalid}");
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &path,
+            json!({
+                "hostname": "edge.example.com",
+                "primary_node_id": STANDBY,
+                "standby_node_ids": [PRIMARY],
+                "primary_addresses": ["203.0.113.10", "2001:db8::10"]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        body_json(res).await["primary_addresses"],
+        json!(["203.0.113.10", "2001:db8::10"])
+    );
+    assert_eq!(
+        store.lock().await.state().dns_failover_groups[&group_id].primary_node_id,
+        STANDBY
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/dns-failover-groups/01JUNKNOWNGROUP00000000000",
+            json!({
+                "hostname": "edge.example.com",
+                "primary_node_id": STANDBY,
+                "standby_node_ids": [PRIMARY]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("DELETE", &path))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert!(store.lock().await.state().dns_failover_groups.is_empty());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("DELETE", &path))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
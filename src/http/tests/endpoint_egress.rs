@@ -0,0 +1,146 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn endpoint_egress_round_trips_and_rejects_unusable_upstreams() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+    let path = format!("/api/admin/endpoints/{}/egress", fixtures.endpoint_id);
+
+    let res = app.clone().oneshot(req_authed("GET", &path)).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await, json!({ "kind": "direct" }));
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &path,
+            json!({
+                "kind": "socks",
+                "address": " proxy.example.com ",
+                "port": 1080,
+                "username": "relay",
+                "password": "secret"
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["address"], json!("proxy.example.com"));
+    {
+        let store = store.lock().await;
+        assert!(matches!(
+            store.state().endpoint_egress.get(&fixtures.endpoint_id),
+            Some(crate::egress::EndpointEgress::Socks { port: 1080, .. })
+        ));
+    }
+
+    for invalid in [
+        json!({ "kind": "socks", "address": "proxy.example.com", "port": 0 }),
+        json!({ "kind": "socks", "address": "proxy.example.com", "port": 1080, "username": "u" }),
+        json!({ "kind": "xray_outbound", "tag": "api" }),
+        json!({ "kind": "node", "node_id": fixtures.node_id }),
+        json!({ "kind": "node", "node_id": "01JUNKNOWNNODE000000000000" }),
+        json!({ "kind": "warp" }),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req_authed_json("PUT", &path, invalid.clone()))
+            .await
+            .unwrap();
+        assert!(res.status().is_client_error(), "{invalid}");
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/endpoints/01JUNKNOWNENDPOINT00000000/egress",
+            json!({ "kind": "direct" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &path,
+            json!({ "kind": "xray_outbound", "tag": "warp" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Deleting the endpoint drops its egress.
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "DELETE",
+            &format!("/api/admin/endpoints/{}", fixtures.endpoint_id),
+        ))
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert!(store.lock().await.state().endpoint_egress.is_empty());
+}
+
+#[tokio::test]
+async fn endpoint_egress_socks_password_is_write_only() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+    let path = format!("/api/admin/endpoints/{}/egress", fixtures.endpoint_id);
+    let socks = json!({
+        "kind": "socks",
+        "address": "proxy.example.com",
+        "port": 1080,
+        "username": "relay",
+        "password": "secret"
+    });
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json("PUT", &path, socks))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let redacted = json!({
+        "kind": "socks",
+        "address": "proxy.example.com",
+        "port": 1080,
+        "username": "relay",
+        "has_password": true
+    });
+    assert_eq!(body_json(res).await, redacted);
+    let res = app.clone().oneshot(req_authed("GET", &path)).await.unwrap();
+    assert_eq!(body_json(res).await, redacted);
+
+    // Writing back what GET returned keeps the stored password.
+    let res = app
+        .clone()
+        .oneshot(req_authed_json("PUT", &path, redacted.clone()))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        store
+            .lock()
+            .await
+            .state()
+            .endpoint_egress
+            .get(&fixtures.endpoint_id),
+        Some(&crate::egress::EndpointEgress::Socks {
+            address: "proxy.example.com".to_string(),
+            port: 1080,
+            username: Some("relay".to_string()),
+            password: Some("secret".to_string()),
+        })
+    );
+}
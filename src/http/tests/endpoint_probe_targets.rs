@@ -0,0 +1,176 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn endpoint_probe_targets_replace_the_list_and_change_the_config_hash() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/endpoints/probe/targets"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = body_json(res).await;
+    let default_hash = body["config_hash"].clone();
+    assert_eq!(
+        default_hash,
+        json!(crate::endpoint_probe::probe_config_hash(
+            &crate::endpoint_probe::default_probe_targets()
+        ))
+    );
+    assert_eq!(body["targets"][0]["id"], json!("gstatic-204"));
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/endpoints/probe/targets",
+            json!({
+                "targets": [
+                    {
+                        "id": "gstatic-204",
+                        "url": "https://www.gstatic.com/generate_204",
+                        "expected_status": 204,
+                        "required": true
+                    },
+                    {
+                        "id": "internal-api",
+                        "label": " Internal API ",
+                        "url": "https://api.example.com/healthz",
+                        "expected_status": 200,
+                        "expected_body_prefix": "ok"
+                    }
+                ]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = body_json(res).await;
+    assert_eq!(body["targets"][0]["label"], json!("gstatic-204"));
+    assert_eq!(body["targets"][1]["label"], json!("Internal API"));
+    assert_eq!(body["targets"][1]["required"], json!(false));
+    assert_ne!(body["config_hash"], default_hash);
+    {
+        let store = store.lock().await;
+        let targets = &store.state().endpoint_probe_targets;
+        assert_eq!(targets.len(), 2);
+        assert_eq!(
+            crate::endpoint_probe::probe_config_hash(targets),
+            body["config_hash"].as_str().unwrap()
+        );
+    }
+
+    for invalid in [
+        json!({ "targets": [] }),
+        json!({ "targets": [{
+            "id": "a",
+            "url": "https://a.example.com/",
+            "expected_status": 200
+        }] }),
+        json!({ "targets": [{
+            "id": "a",
+            "url": "file:///etc/passwd",
+            "expected_status": 200,
+            "required": true
+        }] }),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req_authed_json(
+                "PUT",
+                "/api/admin/endpoints/probe/targets",
+                invalid.clone(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{invalid}");
+    }
+}
+
+#[tokio::test]
+async fn endpoint_probe_history_reports_targets_a_node_cannot_reach() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let (endpoint_id, node_id) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                443,
+                json!({}),
+            )
+            .unwrap();
+        let mut sample = endpoint_probe_sample(true, false, Some(120));
+        sample.targets = vec![
+            crate::state::EndpointProbeTargetSample {
+                target_id: "gstatic-204".to_string(),
+                ok: true,
+                latency_ms: Some(120),
+                error: None,
+            },
+            crate::state::EndpointProbeTargetSample {
+                target_id: "cloudflare-robots".to_string(),
+                ok: false,
+                latency_ms: None,
+                error: Some("unexpected status 403 (expected 200)".to_string()),
+            },
+        ];
+        store
+            .state_mut()
+            .endpoint_probe_history
+            .entry(endpoint.endpoint_id.clone())
+            .or_default()
+            .hours
+            .entry(probe_hour_now())
+            .or_default()
+            .by_node
+            .insert(node_id.clone(), sample);
+        store.save().unwrap();
+        (endpoint.endpoint_id, node_id)
+    };
+
+    let expected = json!([{
+        "endpoint_id": endpoint_id,
+        "node_id": node_id,
+        "target_id": "cloudflare-robots",
+        "target_label": "Cloudflare robots.txt",
+        "target_url": "https://www.cloudflare.com/robots.txt",
+        "since": xp_test_fixtures::timestamp_at20240101_t092800_z(),
+        "last_checked_at": xp_test_fixtures::timestamp_at20240101_t092800_z(),
+        "error": "unexpected status 403 (expected 200)"
+    }]);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/endpoints/{endpoint_id}/probe-history?hours=1"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = body_json(res).await;
+    assert_eq!(body["unreachable_targets"], expected);
+    assert_eq!(
+        body["slots"][0]["by_node"][0]["targets"][1]["target_id"],
+        json!("cloudflare-robots")
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            "/api/admin/endpoints/probe/unreachable-targets",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["items"], expected);
+}
@@ -241,6 +241,7 @@ async fn ss2022_endpoint_creation_persists_server_psk_and_password_uses_server_a
         &cluster_ca_key_pem,
         user_id,
         credential_epoch,
+        crate::protocol::SS2022_PSK_LEN_BYTES_AES_128,
     )
     .expect("derive ss2022 user_psk");
     let password = ss2022_password(server_psk_b64, &user_psk_b64);
@@ -0,0 +1,149 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn notification_webhooks_round_trip_and_validate() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/notifications/webhooks"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await, json!({ "webhooks": [] }));
+
+    let webhooks = json!({
+        "webhooks": [
+            {
+                "name": "ops",
+                "url": "https://hooks.example.invalid/xp",
+                "format": "json",
+                "secret": "s3cret",
+                "events": ["quota_banned", "node_unreachable"],
+                "disabled": false,
+            },
+            {
+                "name": "tg",
+                "url": "https://api.telegram.org",
+                "format": "telegram",
+                "telegram_chat_id": "-100123",
+                "telegram_bot_token": "123:abc",
+                "events": [],
+                "disabled": false,
+            },
+        ]
+    });
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            webhooks.clone(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let mut redacted = webhooks.clone();
+    redacted["webhooks"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("secret");
+    redacted["webhooks"][1]
+        .as_object_mut()
+        .unwrap()
+        .remove("telegram_bot_token");
+    redacted["webhooks"][0]["has_secret"] = json!(true);
+    redacted["webhooks"][0]["has_telegram_bot_token"] = json!(false);
+    redacted["webhooks"][1]["has_secret"] = json!(false);
+    redacted["webhooks"][1]["has_telegram_bot_token"] = json!(true);
+    assert_eq!(body_json(res).await, redacted);
+    assert_eq!(
+        store.lock().await.state().notification_webhooks[0].secret,
+        Some("s3cret".to_string())
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/notifications/webhooks"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let listed = body_json(res).await;
+    assert_eq!(listed, redacted);
+
+    // Writing back what GET returned keeps the stored secret.
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            listed.clone(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let stored = store.lock().await.state().notification_webhooks.clone();
+    assert_eq!(stored[0].secret, Some("s3cret".to_string()));
+    assert_eq!(stored[1].telegram_bot_token, Some("123:abc".to_string()));
+
+    let mut cleared = listed;
+    cleared["webhooks"][0]["secret"] = Value::Null;
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            cleared,
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["webhooks"][0]["has_secret"], false);
+    assert_eq!(
+        store.lock().await.state().notification_webhooks[0].secret,
+        None
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            json!({
+                "webhooks": [{
+                    "name": "tg",
+                    "url": "https://api.telegram.org/botTOKEN/sendMessage",
+                    "format": "telegram",
+                }]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(store.lock().await.state().notification_webhooks.len(), 2);
+
+    // The token belongs in `telegram_bot_token`, never in the url.
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/notifications/webhooks",
+            json!({
+                "webhooks": [{
+                    "name": "tg",
+                    "url": "https://api.telegram.org/bot456:def/sendMessage",
+                    "format": "telegram",
+                    "telegram_chat_id": "-100123",
+                    "telegram_bot_token": "456:def",
+                }]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(!body_json(res).await.to_string().contains("456:def"));
+    assert_eq!(store.lock().await.state().notification_webhooks.len(), 2);
+}
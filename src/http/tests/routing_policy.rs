@@ -0,0 +1,94 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn xray_routing_policy_round_trips_and_drops_deleted_exempt_endpoints() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/xray-routing-policy"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        body_json(res).await,
+        json!({
+            "sniffing": false,
+            "block_private": false,
+            "block_bittorrent": false,
+            "blocked_domains": [],
+            "blocked_ips": [],
+            "exempt_endpoint_ids": [],
+            "exempt_priority_tiers": []
+        })
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            "/api/admin/xray-routing-policy",
+            json!({
+                "sniffing": true,
+                "block_private": true,
+                "block_bittorrent": true,
+                "blocked_domains": [" keyword:tracker ", ""],
+                "blocked_ips": ["198.51.100.0/24"],
+                "exempt_endpoint_ids": [fixtures.endpoint_id],
+                "exempt_priority_tiers": ["p1"]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let saved = body_json(res).await;
+    assert_eq!(saved["blocked_domains"], json!(["keyword:tracker"]));
+    {
+        let store = store.lock().await;
+        let policy = &store.state().xray_routing_policy;
+        assert!(policy.block_bittorrent);
+        assert!(policy.exempt_endpoint_ids.contains(&fixtures.endpoint_id));
+    }
+
+    for invalid in [
+        json!({ "block_bittorrent": true }),
+        json!({ "sniffing": true, "blocked_domains": ["regexp:("] }),
+        json!({ "blocked_ips": ["198.51.100.0/33"] }),
+        json!({ "exempt_endpoint_ids": ["01JUNKNOWNENDPOINT00000000"] }),
+        json!({ "exempt_priority_tiers": ["p9"] }),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req_authed_json(
+                "PUT",
+                "/api/admin/xray-routing-policy",
+                invalid.clone(),
+            ))
+            .await
+            .unwrap();
+        assert!(res.status().is_client_error(), "{invalid}");
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "DELETE",
+            &format!("/api/admin/endpoints/{}", fixtures.endpoint_id),
+        ))
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/xray-routing-policy"))
+        .await
+        .unwrap();
+    let policy = body_json(res).await;
+    assert_eq!(policy["exempt_endpoint_ids"], json!([]));
+    assert_eq!(policy["blocked_ips"], json!(["198.51.100.0/24"]));
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+async fn fetch_subscription(app: &axum::Router, token: &str, query: &str, ip: &str) {
+    let res = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/api/sub/{token}{query}"))
+                .header("x-forwarded-for", ip)
+                .header("user-agent", "clash-verge/v2.0")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+async fn abuse_alerts(app: &axum::Router) -> Vec<Value> {
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/alerts"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    body_json(res).await["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|item| item["type"] == "subscription_token_abuse")
+        .cloned()
+        .collect()
+}
+
+#[tokio::test]
+async fn subscription_fetches_are_logged_per_user_and_flag_widely_shared_tokens() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+    let token = &fixtures.subscription_token;
+
+    fetch_subscription(&app, token, "", "203.0.113.1").await;
+    fetch_subscription(&app, token, "?format=clash", "203.0.113.2").await;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/users/{}/subscription-access", fixtures.user_id),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let log = body_json(res).await;
+    // No history repository is configured, so only this node's own log is visible.
+    assert_eq!(log["partial"], true);
+    assert_eq!(log["summary"]["fetches"], 2);
+    assert_eq!(log["abuse_reasons"], json!([]));
+    let items = log["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    let formats = items
+        .iter()
+        .map(|item| item["format"].as_str().unwrap())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(formats, BTreeSet::from(["base64", "clash"]));
+    for item in items {
+        assert_eq!(item["user_id"], fixtures.user_id.as_str());
+        assert_eq!(item["user_agent"], "clash-verge/v2.0");
+        assert!(item["node_id"].as_str().is_some_and(|id| !id.is_empty()));
+    }
+    assert!(abuse_alerts(&app).await.is_empty());
+
+    // The test config allows 10 distinct IPs per window.
+    for n in 3..=11 {
+        fetch_subscription(&app, token, "", &format!("203.0.113.{n}")).await;
+    }
+    let alerts = abuse_alerts(&app).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0]["user_id"], fixtures.user_id.as_str());
+    assert_eq!(
+        alerts[0]["message"],
+        "subscription token fetched from 11 distinct IPs (limit 10)"
+    );
+    assert_eq!(alerts[0]["subscription_access"]["fetches"], 11);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/users/{}/subscription-access", fixtures.user_id),
+        ))
+        .await
+        .unwrap();
+    let log = body_json(res).await;
+    assert_eq!(
+        log["abuse_reasons"],
+        json!(["fetched from 11 distinct IPs (limit 10)"])
+    );
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            "/api/admin/users/01JUNKNOWNUSER000000000000/subscription-access",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!(
+                "/api/admin/users/{}/subscription-access?since=yesterday",
+                fixtures.user_id
+            ),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+}
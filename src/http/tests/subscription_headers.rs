@@ -0,0 +1,145 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn subscription_formats_carry_userinfo_and_profile_headers() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+
+    let quota_limit_bytes = {
+        let mut store = store.lock().await;
+        let node = store.state_mut().nodes.values_mut().next().unwrap();
+        node.quota_limit_bytes = 10_000;
+        node.quota_reset = NodeQuotaReset::Unlimited {
+            tz_offset_minutes: None,
+        };
+        store
+            .state_mut()
+            .users
+            .get_mut(&fixtures.user_id)
+            .unwrap()
+            .expires_at = Some("2030-01-01T00:00:00Z".to_string());
+        store.save().unwrap();
+        store
+            .apply_membership_usage_sample(
+                &fixtures.membership_key,
+                "2026-01-01T00:00:00Z".to_string(),
+                "2026-02-01T00:00:00Z".to_string(),
+                300,
+                700,
+                "2026-01-02T00:00:00Z".to_string(),
+            )
+            .unwrap();
+        crate::quota_policy::distributable_bytes(10_000)
+    };
+
+    for (query, filename) in [
+        ("", "alice.txt"),
+        ("?format=raw", "alice.txt"),
+        ("?format=clash", "alice.yaml"),
+        ("?format=mihomo", "alice.yaml"),
+        ("?format=singbox", "alice.json"),
+    ] {
+        let token = &fixtures.subscription_token;
+        let res = app
+            .clone()
+            .oneshot(req("GET", &format!("/api/sub/{token}{query}")))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "format {query}");
+        let headers = res.headers();
+        assert_eq!(
+            headers.get("subscription-userinfo").unwrap(),
+            &format!("upload=0; download=1000; total={quota_limit_bytes}; expire=1893456000"),
+        );
+        assert_eq!(headers.get("profile-update-interval").unwrap(), "24");
+        assert_eq!(
+            headers.get(header::CONTENT_DISPOSITION).unwrap(),
+            &format!("attachment; filename=\"{filename}\"; filename*=UTF-8''{filename}"),
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscription_format_singbox_returns_json_outbounds() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+    let token = fixtures.subscription_token;
+
+    let res = app
+        .oneshot(req("GET", &format!("/api/sub/{token}?format=singbox")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json; charset=utf-8"
+    );
+    let config = body_json(res).await;
+    let ss = config["outbounds"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|outbound| outbound["type"] == "shadowsocks")
+        .expect("shadowsocks outbound");
+    assert_eq!(ss["server"], "example.com");
+    assert_eq!(ss["password"], fixtures.ss2022_password);
+}
+
+#[tokio::test]
+async fn subscription_userinfo_does_not_wait_on_stalled_nodes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+
+    // Accepts connections and never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stalled_base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            held.push(socket);
+        }
+    });
+    {
+        let mut store = store.lock().await;
+        for node_id in [
+            xp_test_fixtures::identifier_ulid_a(),
+            xp_test_fixtures::identifier_ulid_b(),
+            xp_test_fixtures::identifier_ulid_c(),
+        ] {
+            store
+                .upsert_node(Node {
+                    node_id: node_id.to_owned(),
+                    node_name: node_id.to_owned(),
+                    access_host: xp_test_fixtures::label_empty().to_owned(),
+                    api_base_url: stalled_base_url.clone(),
+                    quota_limit_bytes: 0,
+                    quota_reset: NodeQuotaReset::default(),
+                })
+                .unwrap();
+        }
+    }
+
+    let started = std::time::Instant::now();
+    let token = &fixtures.subscription_token;
+    let res = app
+        .oneshot(req("GET", &format!("/api/sub/{token}")))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get("subscription-userinfo").is_some());
+    // Read one after another, three stalled nodes would take at least 4.5s.
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(3),
+        "{:?}",
+        started.elapsed()
+    );
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeSet;
+
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+async fn fetch(app: &axum::Router, uri: &str) -> axum::response::Response {
+    app.clone().oneshot(req("GET", uri)).await.unwrap()
+}
+
+async fn raw_lines(app: &axum::Router, token: &str) -> usize {
+    let res = fetch(app, &format!("/api/sub/{token}?format=raw")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    body_text(res)
+        .await
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count()
+}
+
+#[tokio::test]
+async fn secondary_subscription_tokens_are_scoped_expiring_and_revocable() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+    set_bootstrap_node_access_host(&store, "example.com").await;
+    let fixtures = setup_subscription_fixtures(&tmp, &app).await;
+    let user_id = &fixtures.user_id;
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/endpoints",
+            json!({
+              "node_id": fixtures.node_id,
+              "kind": "ss2022_2022_blake3_aes_128_gcm",
+              "port": 8389
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let second_endpoint_id = body_json(res).await["endpoint_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &format!("/api/admin/users/{user_id}/access"),
+            json!({
+              "items": [
+                { "endpoint_id": fixtures.endpoint_id },
+                { "endpoint_id": second_endpoint_id }
+              ]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(raw_lines(&app, &fixtures.subscription_token).await, 2);
+
+    let tokens_uri = format!("/api/admin/users/{user_id}/subscription-tokens");
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            &tokens_uri,
+            json!({
+              "name": "phone",
+              "format": "raw",
+              "endpoint_ids": [fixtures.endpoint_id]
+            }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let phone = body_json(res).await;
+    let phone_token = phone["token"].as_str().unwrap().to_string();
+    let phone_id = phone["token_id"].as_str().unwrap().to_string();
+    assert_ne!(phone_token, fixtures.subscription_token);
+
+    // The token's format is its default, other formats are refused.
+    let res = fetch(&app, &format!("/api/sub/{phone_token}")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = body_text(res).await;
+    assert_eq!(body.lines().filter(|line| !line.is_empty()).count(), 1);
+    assert!(body.starts_with("ss://"), "{body}");
+    assert_eq!(raw_lines(&app, &phone_token).await, 1);
+    for uri in [
+        format!("/api/sub/{phone_token}?format=clash"),
+        format!("/api/sub/{phone_token}/mihomo/provider"),
+    ] {
+        assert_eq!(fetch(&app, &uri).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/users/{user_id}/subscription-access"),
+        ))
+        .await
+        .unwrap();
+    let access = body_json(res).await;
+    let mut token_ids = access["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["token_id"].as_str())
+        .collect::<Vec<_>>();
+    token_ids.sort();
+    assert_eq!(
+        token_ids,
+        vec![None, Some(phone_id.as_str()), Some(phone_id.as_str())]
+    );
+
+    for invalid in [
+        json!({ "name": "phone" }),
+        json!({ "name": "laptop", "format": "yaml" }),
+        json!({ "name": "laptop", "expires_at": "tomorrow" }),
+        json!({ "name": "laptop", "endpoint_ids": [] }),
+    ] {
+        let res = app
+            .clone()
+            .oneshot(req_authed_json("POST", &tokens_uri, invalid.clone()))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{invalid}");
+    }
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            &tokens_uri,
+            json!({ "name": "old laptop", "expires_at": "2020-01-01T00:00:00Z" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let expired_token = body_json(res).await["token"].as_str().unwrap().to_string();
+    let res = fetch(&app, &format!("/api/sub/{expired_token}")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", &tokens_uri))
+        .await
+        .unwrap();
+    let names = body_json(res).await["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["name"].as_str().unwrap().to_string())
+        .collect::<BTreeSet<_>>();
+    assert_eq!(
+        names,
+        BTreeSet::from(["phone".to_string(), "old laptop".to_string()])
+    );
+
+    let revoke_uri = format!("{tokens_uri}/{phone_id}");
+    let res = app
+        .clone()
+        .oneshot(req_authed("DELETE", &revoke_uri))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = fetch(&app, &format!("/api/sub/{phone_token}")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert_eq!(raw_lines(&app, &fixtures.subscription_token).await, 2);
+    let res = app
+        .clone()
+        .oneshot(req_authed("DELETE", &revoke_uri))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,120 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn user_topups_round_trip_and_extend_node_quota_status() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let (user_id, node_id) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+        let node = store.state_mut().nodes.get_mut(&node_id).unwrap();
+        node.quota_limit_bytes = 256 * 1024 * 1024 + 1_000;
+        node.quota_reset = NodeQuotaReset::Monthly {
+            day_of_month: 1,
+            tz_offset_minutes: Some(0),
+        };
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        (user.user_id, node_id)
+    };
+    let topups_uri = format!("/api/admin/users/{user_id}/topups");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            &topups_uri,
+            json!({ "bytes": 0, "node_id": node_id }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    // Top-ups are drawn per node, so one without a node would be credited on every node.
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            &topups_uri,
+            json!({ "bytes": 5_000 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            &topups_uri,
+            json!({ "bytes": 5_000, "node_id": node_id, "note": "order 42" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let created = body_json(res).await;
+    let topup_id = created["topup_id"].as_str().unwrap().to_string();
+    assert_eq!(created["bytes"], 5_000);
+    assert_eq!(created["note"], "order 42");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", &topups_uri))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let items = body_json(res).await["items"].as_array().unwrap().clone();
+    assert_eq!(items, vec![created]);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "GET",
+            &format!("/api/admin/users/{user_id}/node-quotas/status?scope=local"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let item = body_json(res).await["items"][0].clone();
+    let base = item["quota_limit_bytes"].as_u64().unwrap();
+    assert!(base > 0);
+    assert_eq!(item["topup_remaining_bytes"], 5_000);
+    assert_eq!(item["remaining_bytes"].as_u64().unwrap(), base + 5_000);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "DELETE",
+            &format!("/api/admin/users/{user_id}/topups/{topup_id}"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed(
+            "DELETE",
+            &format!("/api/admin/users/{user_id}/topups/{topup_id}"),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,118 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn users_import_dry_run_reports_and_apply_commits_atomically() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let (node_id, node_name, endpoint) = {
+        let mut store = store.lock().await;
+        let node = store.list_nodes()[0].clone();
+        let endpoint = store
+            .create_endpoint(
+                node.node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                json!({}),
+            )
+            .unwrap();
+        (node.node_id, node.node_name, endpoint)
+    };
+
+    let mut users = (0..101)
+        .map(|index| {
+            json!({
+                "display_name": format!("user-{index}"),
+                "priority_tier": "p1",
+                "quota_reset": { "policy": "daily", "tz_offset_minutes": 0 },
+                "endpoints": [endpoint.tag],
+                "node_weights": { node_name.clone(): 300 },
+            })
+        })
+        .collect::<Vec<_>>();
+    users.push(json!({ "display_name": "broken", "endpoints": ["missing"] }));
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/users/import",
+            json!({ "users": users, "dry_run": true }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let report = body_json(res).await;
+    assert_eq!(report["applied"], false);
+    assert_eq!(report["created"], 101);
+    assert_eq!(
+        report["issues"],
+        json!([{ "row": 102, "message": "endpoint not found: missing" }])
+    );
+    assert!(store.lock().await.list_users().is_empty());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/users/import",
+            json!({ "users": users }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert!(store.lock().await.list_users().is_empty());
+
+    users.pop();
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/users/import",
+            json!({ "users": users }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let report = body_json(res).await;
+    assert_eq!(report["applied"], true);
+    assert_eq!(report["created"], 101);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/users/export"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let exported = body_json(res).await["users"].as_array().unwrap().clone();
+    assert_eq!(exported.len(), 101);
+    let first = &exported[0];
+    assert_eq!(first["priority_tier"], "p1");
+    assert_eq!(first["quota_reset"]["policy"], "daily");
+    assert_eq!(first["endpoints"], json!([endpoint.endpoint_id]));
+    assert_eq!(first["node_weights"], json!({ node_id.clone(): 300 }));
+
+    let renamed = json!({
+        "user_id": first["user_id"],
+        "display_name": "renamed",
+        "endpoints": [],
+    });
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "POST",
+            "/api/admin/users/import",
+            json!({ "users": [renamed] }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["updated"], 1);
+    let store = store.lock().await;
+    let user = store.get_user(first["user_id"].as_str().unwrap()).unwrap();
+    assert_eq!(user.display_name, "renamed");
+    assert_eq!(user.priority_tier, crate::domain::UserPriorityTier::P1);
+    assert!(store.list_user_access(&user.user_id).unwrap().is_empty());
+}
@@ -0,0 +1,217 @@
+use super::*;
+
+use chrono::{SecondsFormat, Utc};
+use pretty_assertions::assert_eq;
+
+async fn local_alert_types(app: &axum::Router) -> Vec<(String, Value)> {
+    let res = app
+        .clone()
+        .oneshot(req_authed("GET", "/api/admin/alerts?scope=local"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let json = body_json(res).await;
+    json["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| (item["type"].as_str().unwrap().to_string(), item.clone()))
+        .collect()
+}
+
+#[tokio::test]
+async fn user_expiry_and_disable_round_trip_and_surface_alerts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let (user_id, endpoint_id) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        (user.user_id, endpoint.endpoint_id)
+    };
+    let expiry_uri = format!("/api/admin/users/{user_id}/expiry");
+
+    let soon = (Utc::now() + chrono::Duration::days(2)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &expiry_uri,
+            json!({ "expires_at": soon }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["expires_at"], soon);
+    let alerts = local_alert_types(&app).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].0, "user_expiring");
+    assert_eq!(alerts[0].1["endpoint_id"], endpoint_id);
+    assert_eq!(alerts[0].1["expires_at"], soon);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &expiry_uri,
+            json!({ "expires_at": "2000-01-01T08:00:00+08:00" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["expires_at"], "2000-01-01T00:00:00Z");
+    let alerts = local_alert_types(&app).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].0, "user_expired");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &expiry_uri,
+            json!({ "expires_at": null }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["expires_at"], Value::Null);
+    assert!(local_alert_types(&app).await.is_empty());
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PUT",
+            &expiry_uri,
+            json!({ "expires_at": "soon" }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    for (action, disabled) in [("disable", true), ("enable", false)] {
+        let res = app
+            .clone()
+            .oneshot(req_authed(
+                "POST",
+                &format!("/api/admin/users/{user_id}/{action}"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let user = body_json(res).await;
+        assert_eq!(user["disabled"], disabled);
+        assert_eq!(user["credential_epoch"], 0);
+    }
+
+    let res = app
+        .oneshot(req_authed("POST", "/api/admin/users/missing/disable"))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn max_concurrent_ips_patch_round_trips_and_blocks_surface_alerts() {
+    let tmp = tempfile::tempdir().unwrap();
+    let (app, store) = app_with(&tmp, ReconcileHandle::noop());
+
+    let user_id = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        user.user_id
+    };
+    let user_uri = format!("/api/admin/users/{user_id}");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PATCH",
+            &user_uri,
+            json!({ "max_concurrent_ips": 0 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PATCH",
+            &user_uri,
+            json!({ "max_concurrent_ips": 2 }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["max_concurrent_ips"], 2);
+    assert!(local_alert_types(&app).await.is_empty());
+
+    let blocked_until =
+        (Utc::now() + chrono::Duration::minutes(5)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    store
+        .lock()
+        .await
+        .update_usage(|usage| {
+            usage.ip_limits.insert(
+                user_id.clone(),
+                crate::ip_limit::UserIpLimitState {
+                    blocked_until: Some(blocked_until.clone()),
+                    offending_ips: vec![crate::ip_limit::IpLimitOffender {
+                        ip: "203.0.113.7".to_string(),
+                        geo: Default::default(),
+                    }],
+                    ..Default::default()
+                },
+            );
+        })
+        .unwrap();
+    let alerts = local_alert_types(&app).await;
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].0, "ip_limit_exceeded");
+    assert_eq!(alerts[0].1["blocked_until"], blocked_until);
+    assert_eq!(alerts[0].1["offending_ips"][0]["ip"], "203.0.113.7");
+
+    let res = app
+        .clone()
+        .oneshot(req_authed_json(
+            "PATCH",
+            &user_uri,
+            json!({ "max_concurrent_ips": null }),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(body_json(res).await["max_concurrent_ips"], Value::Null);
+}
@@ -0,0 +1,96 @@
+use axum::{
+    Json,
+    extract::{Extension, Path},
+    http::StatusCode,
+};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{domain::TrafficTopUp, id::new_ulid_string, state::DesiredStateCommand};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct CreateTrafficTopUpRequest {
+    bytes: u64,
+    /// Node the bytes are granted on. Each node draws top-ups from its own usage, so there is no
+    /// cluster-wide top-up.
+    node_id: String,
+    /// RFC3339 timestamp after which unused bytes are forfeited.
+    #[serde(default)]
+    expires_at: Option<String>,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct TrafficTopUpsResponse {
+    items: Vec<TrafficTopUp>,
+}
+
+pub(super) async fn admin_list_user_topups(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<TrafficTopUpsResponse>, ApiError> {
+    let store = state.store.lock().await;
+    if store.get_user(&user_id).is_none() {
+        return Err(ApiError::not_found(format!("user not found: {user_id}")));
+    }
+    let items = store
+        .state()
+        .traffic_topups
+        .values()
+        .filter(|topup| topup.user_id == user_id)
+        .cloned()
+        .collect();
+    Ok(Json(TrafficTopUpsResponse { items }))
+}
+
+pub(super) async fn admin_create_user_topup(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+    ApiJson(req): ApiJson<CreateTrafficTopUpRequest>,
+) -> Result<Json<TrafficTopUp>, ApiError> {
+    let topup = TrafficTopUp {
+        topup_id: new_ulid_string(),
+        user_id,
+        node_id: req.node_id.trim().to_string(),
+        bytes: req.bytes,
+        created_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        expires_at: req.expires_at.filter(|raw| !raw.trim().is_empty()),
+        note: req.note.filter(|note| !note.trim().is_empty()),
+    };
+    let _ = raft_write(
+        &state,
+        DesiredStateCommand::CreateTrafficTopUp {
+            topup: topup.clone(),
+        },
+    )
+    .await?;
+
+    // Lifted quota bans should reach the data plane without waiting for the periodic pass.
+    state.reconcile.request_full();
+
+    Ok(Json(topup))
+}
+
+pub(super) async fn admin_delete_user_topup(
+    Extension(state): Extension<AppState>,
+    Path((user_id, topup_id)): Path<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    let belongs_to_user = state
+        .store
+        .lock()
+        .await
+        .state()
+        .traffic_topups
+        .get(&topup_id)
+        .is_some_and(|topup| topup.user_id == user_id);
+    if !belongs_to_user {
+        return Err(ApiError::not_found(format!(
+            "traffic top-up not found: {topup_id}"
+        )));
+    }
+    let _ = raft_write(&state, DesiredStateCommand::DeleteTrafficTopUp { topup_id }).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
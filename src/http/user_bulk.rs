@@ -0,0 +1,81 @@
+use axum::{Json, extract::Extension};
+use serde::{Deserialize, Serialize};
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    state::DesiredStateCommand,
+    user_bulk::{
+        UserBulkDocument, UserBulkRecord, UserImportAction, UserImportIssue, UserImportRow,
+        export_users, plan_user_import,
+    },
+};
+
+/// Large user lists can outgrow the default 2 MiB request limit.
+pub(super) const IMPORT_BODY_LIMIT_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct ImportUsersRequest {
+    users: Vec<UserBulkRecord>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub(super) struct ImportUsersResponse {
+    dry_run: bool,
+    applied: bool,
+    created: usize,
+    updated: usize,
+    issues: Vec<UserImportIssue>,
+    users: Vec<UserImportRow>,
+}
+
+pub(super) async fn admin_export_users(
+    Extension(state): Extension<AppState>,
+) -> Result<Json<UserBulkDocument>, ApiError> {
+    let store = state.store.lock().await;
+    Ok(Json(UserBulkDocument {
+        users: export_users(store.state()),
+    }))
+}
+
+pub(super) async fn admin_import_users(
+    Extension(state): Extension<AppState>,
+    ApiJson(req): ApiJson<ImportUsersRequest>,
+) -> Result<Json<ImportUsersResponse>, ApiError> {
+    let plan = {
+        let store = state.store.lock().await;
+        plan_user_import(store.state(), req.users)
+    };
+    let mut response = ImportUsersResponse {
+        dry_run: req.dry_run,
+        applied: false,
+        created: plan.count(UserImportAction::Create),
+        updated: plan.count(UserImportAction::Update),
+        issues: plan.issues.clone(),
+        users: plan.rows.clone(),
+    };
+    if req.dry_run {
+        return Ok(Json(response));
+    }
+    if let Some(issue) = plan.issues.first() {
+        return Err(ApiError::invalid_request(format!(
+            "import has {} issue(s), run a dry run for the full list: {issue}",
+            plan.issues.len()
+        )));
+    }
+    if !plan.entries.is_empty() {
+        // One command for the whole import, so a failed write leaves no user half-imported.
+        raft_write(
+            &state,
+            DesiredStateCommand::ImportUsers {
+                entries: plan.entries,
+            },
+        )
+        .await?;
+        state.reconcile.request_full();
+    }
+    response.applied = true;
+    Ok(Json(response))
+}
@@ -0,0 +1,90 @@
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use serde::Deserialize;
+
+use super::{ApiError, ApiJson, AppState, raft_write};
+use crate::{
+    domain::{User, normalize_user_expires_at},
+    state::DesiredStateCommand,
+};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct PutUserExpiryRequest {
+    /// RFC3339 timestamp, or `null` to clear the expiry.
+    expires_at: Option<String>,
+}
+
+pub(super) async fn admin_put_user_expiry(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+    ApiJson(req): ApiJson<PutUserExpiryRequest>,
+) -> Result<Json<User>, ApiError> {
+    let expires_at = req
+        .expires_at
+        .as_deref()
+        .map(normalize_user_expires_at)
+        .transpose()
+        .map_err(|e| ApiError::invalid_request(e.to_string()))?;
+    apply_user_lifecycle(
+        &state,
+        &user_id,
+        DesiredStateCommand::SetUserExpiry {
+            user_id: user_id.clone(),
+            expires_at,
+        },
+    )
+    .await
+}
+
+pub(super) async fn admin_disable_user(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<User>, ApiError> {
+    set_user_disabled(&state, user_id, true).await
+}
+
+pub(super) async fn admin_enable_user(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<User>, ApiError> {
+    set_user_disabled(&state, user_id, false).await
+}
+
+async fn set_user_disabled(
+    state: &AppState,
+    user_id: String,
+    disabled: bool,
+) -> Result<Json<User>, ApiError> {
+    apply_user_lifecycle(
+        state,
+        &user_id,
+        DesiredStateCommand::SetUserDisabled {
+            user_id: user_id.clone(),
+            disabled,
+        },
+    )
+    .await
+}
+
+async fn apply_user_lifecycle(
+    state: &AppState,
+    user_id: &str,
+    cmd: DesiredStateCommand,
+) -> Result<Json<User>, ApiError> {
+    if state.store.lock().await.get_user(user_id).is_none() {
+        return Err(ApiError::not_found(format!("user not found: {user_id}")));
+    }
+    let _ = raft_write(state, cmd).await?;
+
+    // Access changes should take effect on the data plane without waiting for the periodic pass.
+    state.reconcile.request_full();
+
+    let store = state.store.lock().await;
+    store
+        .get_user(user_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("user not found: {user_id}")))
+}
@@ -0,0 +1,285 @@
+use std::{collections::BTreeSet, time::Duration};
+
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+};
+use chrono::Utc;
+use futures_util::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ApiError, AppState, ScopeQuery, resolve_node_quota_reset_for_status, send_mesh_internal_read,
+};
+use crate::{cycle::current_cycle_window_at, domain::QuotaResetSource, state::JsonSnapshotStore};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct AdminUserNodeQuotaStatusItem {
+    pub(super) user_id: String,
+    pub(super) node_id: String,
+    pub(super) quota_limit_bytes: u64,
+    pub(super) used_bytes: u64,
+    /// Base share left this cycle plus `topup_remaining_bytes`.
+    pub(super) remaining_bytes: u64,
+    #[serde(default)]
+    pub(super) topup_remaining_bytes: u64,
+    pub(super) cycle_end_at: Option<String>,
+    pub(super) quota_reset_source: QuotaResetSource,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct AdminUserNodeQuotaStatusResponse {
+    partial: bool,
+    unreachable_nodes: Vec<String>,
+    items: Vec<AdminUserNodeQuotaStatusItem>,
+}
+
+pub(super) fn build_local_user_node_quota_status(
+    store: &JsonSnapshotStore,
+    local_node_id: &str,
+    user_id: &str,
+) -> Result<Vec<AdminUserNodeQuotaStatusItem>, ApiError> {
+    let now = Utc::now();
+    let node_quota_limit_bytes = store
+        .get_node(local_node_id)
+        .map(|n| n.quota_limit_bytes)
+        .unwrap_or(0);
+    let (schedule, tz) = resolve_node_quota_reset_for_status(store, local_node_id)?;
+    let (cycle_start_at, cycle_end_at) = if let Some(schedule) = schedule {
+        let (cycle_start, cycle_end) = current_cycle_window_at(tz, schedule, now)
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        (Some(cycle_start.to_rfc3339()), Some(cycle_end.to_rfc3339()))
+    } else {
+        (None, None)
+    };
+
+    let endpoints_by_id = store
+        .list_endpoints()
+        .into_iter()
+        .map(|e| (e.endpoint_id.clone(), e))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    let membership_keys: Vec<String> = store
+        .state()
+        .node_user_endpoint_memberships
+        .iter()
+        .filter(|m| m.user_id == user_id)
+        .filter(|m| {
+            endpoints_by_id
+                .get(&m.endpoint_id)
+                .is_some_and(|ep| ep.node_id == local_node_id)
+        })
+        .map(|m| crate::state::membership_key(&m.user_id, &m.endpoint_id))
+        .collect();
+
+    if membership_keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tier = store
+        .get_user(user_id)
+        .map(|u| u.priority_tier)
+        .unwrap_or_default();
+
+    let quota_limit_bytes =
+        if node_quota_limit_bytes == 0 || tier == crate::domain::UserPriorityTier::P3 {
+            0
+        } else {
+            // Compute the user's base share of the node budget (P1/P2 only).
+            let mut items: Vec<(String, u16)> = Vec::new();
+            let mut node_user_ids = BTreeSet::<String>::new();
+            for m in store.state().node_user_endpoint_memberships.iter() {
+                if endpoints_by_id
+                    .get(&m.endpoint_id)
+                    .is_some_and(|ep| ep.node_id == local_node_id)
+                {
+                    node_user_ids.insert(m.user_id.clone());
+                }
+            }
+
+            for uid in node_user_ids {
+                if uid == crate::endpoint_probe::PROBE_USER_ID {
+                    continue;
+                }
+                let tier = store
+                    .get_user(&uid)
+                    .map(|u| u.priority_tier)
+                    .unwrap_or_default();
+                if tier == crate::domain::UserPriorityTier::P3 {
+                    continue;
+                }
+                let weight = store.resolve_user_node_weight(&uid, local_node_id);
+                items.push((uid, weight));
+            }
+            items.sort_by(|a, b| a.0.cmp(&b.0));
+            items.dedup_by(|a, b| a.0 == b.0);
+
+            let distributable = crate::quota_policy::distributable_bytes(node_quota_limit_bytes);
+            let base_by_user = crate::quota_policy::allocate_total_by_weight(distributable, &items);
+            base_by_user
+                .into_iter()
+                .find_map(|(uid, base)| (uid == user_id).then_some(base))
+                .unwrap_or(0)
+        };
+
+    let used_bytes = membership_keys.iter().fold(0u64, |acc, key| {
+        let Some(usage) = store.get_membership_usage(key) else {
+            return acc;
+        };
+        if let (Some(expected_start), Some(expected_end)) = (&cycle_start_at, &cycle_end_at)
+            && (usage.cycle_start_at != *expected_start || usage.cycle_end_at != *expected_end)
+        {
+            return acc;
+        }
+        acc.saturating_add(usage.used_bytes)
+    });
+
+    let topup_remaining_bytes = match quota_limit_bytes {
+        0 => 0,
+        _ => crate::quota::topup_remaining_bytes(
+            store.topup_consumed_bytes(),
+            &crate::quota::active_topups(store.state(), user_id, local_node_id, now),
+        ),
+    };
+    let remaining_bytes = match quota_limit_bytes {
+        0 => 0,
+        _ => quota_limit_bytes
+            .saturating_sub(used_bytes)
+            .saturating_add(topup_remaining_bytes),
+    };
+    Ok(vec![AdminUserNodeQuotaStatusItem {
+        user_id: user_id.to_string(),
+        node_id: local_node_id.to_string(),
+        quota_limit_bytes,
+        used_bytes,
+        remaining_bytes,
+        topup_remaining_bytes,
+        cycle_end_at,
+        quota_reset_source: QuotaResetSource::Node,
+    }])
+}
+
+/// Per-node budget for the cluster-wide quota status of the admin views.
+const REMOTE_QUOTA_STATUS_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Collects `user_id`'s quota status from every other node, returning the ids of nodes that
+/// could not be read alongside the items that could. Nodes are read concurrently and any node
+/// that has not answered within `deadline` counts as unreachable.
+pub(super) async fn fetch_remote_user_node_quota_status(
+    state: &AppState,
+    user_id: &str,
+    deadline: Duration,
+) -> (Vec<AdminUserNodeQuotaStatusItem>, Vec<String>) {
+    let nodes = {
+        let store = state.store.lock().await;
+        store.list_nodes()
+    };
+    let client = state.mesh_client.clone();
+
+    let reads = nodes
+        .into_iter()
+        .filter(|node| node.node_id != state.cluster.node_id)
+        .map(|node| {
+            let client = &client;
+            async move {
+                let read = async {
+                    if node.api_base_url.trim_end_matches('/').is_empty() {
+                        return None;
+                    }
+                    let response = send_mesh_internal_read(
+                        state,
+                        client,
+                        &node,
+                        format!("/api/admin/_internal/users/{user_id}/node-quotas/status"),
+                        deadline,
+                    )
+                    .await
+                    .ok()?;
+                    if !response.status().is_success() {
+                        return None;
+                    }
+                    response
+                        .json::<AdminUserNodeQuotaStatusResponse>()
+                        .await
+                        .ok()
+                };
+                match tokio::time::timeout(deadline, read).await {
+                    Ok(Some(remote)) => Ok(remote.items),
+                    _ => Err(node.node_id),
+                }
+            }
+        });
+
+    let mut items = Vec::new();
+    let mut unreachable_nodes = Vec::new();
+    for read in join_all(reads).await {
+        match read {
+            Ok(remote_items) => items.extend(remote_items),
+            Err(node_id) => unreachable_nodes.push(node_id),
+        }
+    }
+
+    (items, unreachable_nodes)
+}
+
+pub(super) async fn admin_get_user_node_quota_status(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<ScopeQuery>,
+) -> Result<Json<AdminUserNodeQuotaStatusResponse>, ApiError> {
+    if let Some(scope) = query.scope.as_deref()
+        && scope != "local"
+    {
+        return Err(ApiError::invalid_request(
+            "invalid scope, expected local or omit",
+        ));
+    }
+
+    let local_node_id = state.cluster.node_id.clone();
+    let local_items = {
+        let store = state.store.lock().await;
+        if store.get_user(&user_id).is_none() {
+            return Err(ApiError::not_found(format!("user not found: {user_id}")));
+        }
+        build_local_user_node_quota_status(&store, &local_node_id, &user_id)?
+    };
+
+    if query.scope.as_deref() == Some("local") {
+        return Ok(Json(AdminUserNodeQuotaStatusResponse {
+            partial: false,
+            unreachable_nodes: Vec::new(),
+            items: local_items,
+        }));
+    }
+
+    let (remote_items, unreachable_nodes) =
+        fetch_remote_user_node_quota_status(&state, &user_id, REMOTE_QUOTA_STATUS_TIMEOUT).await;
+    let mut items = local_items;
+    items.extend(remote_items);
+
+    let partial = !unreachable_nodes.is_empty();
+    Ok(Json(AdminUserNodeQuotaStatusResponse {
+        partial,
+        unreachable_nodes,
+        items,
+    }))
+}
+
+pub(super) async fn admin_internal_get_user_node_quota_status(
+    Extension(state): Extension<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Json<AdminUserNodeQuotaStatusResponse>, ApiError> {
+    let local_node_id = state.cluster.node_id.clone();
+    let items = {
+        let store = state.store.lock().await;
+        if store.get_user(&user_id).is_none() {
+            return Err(ApiError::not_found(format!("user not found: {user_id}")));
+        }
+        build_local_user_node_quota_status(&store, &local_node_id, &user_id)?
+    };
+    Ok(Json(AdminUserNodeQuotaStatusResponse {
+        partial: false,
+        unreachable_nodes: Vec::new(),
+        items,
+    }))
+}
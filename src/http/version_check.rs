@@ -143,22 +143,17 @@ async fn ensure_refresh_authorized(state: &AppState, headers: &HeaderMap) -> Res
             "missing or invalid authorization token",
         ));
     };
-    let Some(expected) = state.config.admin_token_hash() else {
-        return Err(ApiError::unauthorized(
-            "missing or invalid authorization token",
-        ));
-    };
-
     match super::authorize_admin_token(
         token,
-        &expected,
+        state.config.admin_token_hash().as_ref(),
         &state.cluster.cluster_id,
         &state.admin_token_verifier,
+        &state.store,
     )
     .await
     {
-        Ok(true) => return Ok(()),
-        Ok(false) => {}
+        Ok(Some(_)) => return Ok(()),
+        Ok(None) => {}
         Err(crate::admin_token::AdminTokenVerifyError::Busy) => {
             return Err(ApiError::too_many_requests("admin authentication is busy"));
         }
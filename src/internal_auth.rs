@@ -1,4 +1,7 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use axum::http::{HeaderMap, Method, Uri};
 use base64::Engine;
@@ -21,6 +24,35 @@ pub const INTERNAL_ACK_HEADER: &str = "x-xp-internal-ack";
 
 pub const AUTH_WINDOW_SECS: i64 = 120;
 
+/// A cluster CA accepted besides the local one while a credential rotation is in progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlternateRoot {
+    pub ca_key_pem: String,
+    pub ca_cert_pem: String,
+}
+
+/// Shared handle to the alternate roots: the credential rotation worker replaces them and every
+/// verifier holding a clone reads them.
+#[derive(Debug, Clone, Default)]
+pub struct AlternateRoots(Arc<RwLock<Vec<AlternateRoot>>>);
+
+impl AlternateRoots {
+    /// Replaces the roots whose signatures verify alongside the local CA.
+    pub fn set(&self, roots: Vec<AlternateRoot>) {
+        *self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = roots;
+    }
+
+    pub fn snapshot(&self) -> Vec<AlternateRoot> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub enum InternalRoute {
@@ -155,6 +187,7 @@ pub fn sign_request_v2(
 pub fn verify_request_v2(
     cluster_ca_key_pem: &str,
     cluster_ca_cert_pem: &str,
+    alternate_roots: &[AlternateRoot],
     method: &Method,
     uri: &Uri,
     headers: &HeaderMap,
@@ -202,15 +235,16 @@ pub fn verify_request_v2(
     let actual = URL_SAFE_NO_PAD
         .decode(encoded.as_bytes())
         .map_err(|_| AuthError::Invalid("invalid internal signature encoding"))?;
-    let key = derive_subkey(
+    if !verify_with_accepted_roots(
         cluster_ca_key_pem,
         cluster_ca_cert_pem,
+        alternate_roots,
         b"xp/internal-auth-v2/request",
-    )?;
-    let mut mac = HmacSha256::new_from_slice(&key).map_err(|e| AuthError::Crypto(e.to_string()))?;
-    mac.update(canonical.as_bytes());
-    mac.verify_slice(&actual)
-        .map_err(|_| AuthError::Invalid("internal signature does not verify"))?;
+        canonical.as_bytes(),
+        &actual,
+    )? {
+        return Err(AuthError::Invalid("internal signature does not verify"));
+    }
     let idempotency_sha256 = hex::encode(Sha256::digest(
         idempotency_canonical_request(
             &context,
@@ -257,6 +291,7 @@ pub fn sign_ack_v2(
 pub fn verify_ack_v2(
     cluster_ca_key_pem: &str,
     cluster_ca_cert_pem: &str,
+    alternate_roots: &[AlternateRoot],
     verified: &VerifiedRequest,
     responder_id: &str,
     status: u16,
@@ -269,15 +304,45 @@ pub fn verify_ack_v2(
         .decode(encoded.as_bytes())
         .map_err(|_| AuthError::Invalid("invalid acknowledgement encoding"))?;
     let canonical = canonical_ack(verified, responder_id, status);
-    let key = derive_subkey(
+    if verify_with_accepted_roots(
         cluster_ca_key_pem,
         cluster_ca_cert_pem,
+        alternate_roots,
         b"xp/internal-auth-v2/ack",
-    )?;
-    let mut mac = HmacSha256::new_from_slice(&key).map_err(|e| AuthError::Crypto(e.to_string()))?;
-    mac.update(canonical.as_bytes());
-    mac.verify_slice(&actual)
-        .map_err(|_| AuthError::Invalid("acknowledgement does not verify"))
+        canonical.as_bytes(),
+        &actual,
+    )? {
+        Ok(())
+    } else {
+        Err(AuthError::Invalid("acknowledgement does not verify"))
+    }
+}
+
+/// Checks `actual` against the given CA first, then against any alternate roots.
+fn verify_with_accepted_roots(
+    cluster_ca_key_pem: &str,
+    cluster_ca_cert_pem: &str,
+    alternate_roots: &[AlternateRoot],
+    info: &[u8],
+    message: &[u8],
+    actual: &[u8],
+) -> Result<bool, AuthError> {
+    let verifies = |key_pem: &str, cert_pem: &str| -> Result<bool, AuthError> {
+        let key = derive_subkey(key_pem, cert_pem, info)?;
+        let mut mac =
+            HmacSha256::new_from_slice(&key).map_err(|e| AuthError::Crypto(e.to_string()))?;
+        mac.update(message);
+        Ok(mac.verify_slice(actual).is_ok())
+    };
+    if verifies(cluster_ca_key_pem, cluster_ca_cert_pem)? {
+        return Ok(true);
+    }
+    for root in alternate_roots {
+        if verifies(&root.ca_key_pem, &root.ca_cert_pem)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 fn request_context_from_headers(headers: &HeaderMap) -> Result<RequestContext, AuthError> {
@@ -494,6 +559,7 @@ mod tests {
             verify_request_v2(
                 &key,
                 &cert,
+                &[],
                 &Method::POST,
                 &uri,
                 &headers,
@@ -507,6 +573,7 @@ mod tests {
             verify_request_v2(
                 &key,
                 &cert,
+                &[],
                 &Method::POST,
                 &uri,
                 &headers,
@@ -518,6 +585,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn alternate_roots_verify_requests_signed_under_the_other_ca() {
+        let (old_key, old_cert) = identity();
+        let (new_key, new_cert) = identity();
+        let uri: Uri = "/api/admin/_internal/raft/client-write".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        sign_request_v2(
+            &old_key,
+            &old_cert,
+            &Method::POST,
+            &uri,
+            None,
+            b"",
+            &context(),
+            &mut headers,
+        )
+        .unwrap();
+        let roots = AlternateRoots::default();
+        let verify = || {
+            verify_request_v2(
+                &new_key,
+                &new_cert,
+                &roots.snapshot(),
+                &Method::POST,
+                &uri,
+                &headers,
+                b"",
+                "01JTESTCLUSTERID00000000000000",
+                "01JTESTTARGET0000000000000000",
+            )
+        };
+
+        assert!(verify().is_err());
+        roots.set(vec![AlternateRoot {
+            ca_key_pem: old_key.clone(),
+            ca_cert_pem: old_cert.clone(),
+        }]);
+        let verified = verify().expect("old CA accepted during rotation");
+        let ack = sign_ack_v2(&old_key, &old_cert, &verified, "responder", 200).unwrap();
+        let verify_ack = || {
+            let roots = roots.snapshot();
+            verify_ack_v2(
+                &new_key,
+                &new_cert,
+                &roots,
+                &verified,
+                "responder",
+                200,
+                &ack,
+            )
+        };
+        assert!(verify_ack().is_ok());
+        roots.set(Vec::new());
+        assert!(verify().is_err());
+        assert!(verify_ack().is_err());
+    }
+
     #[test]
     fn idempotency_digest_is_stable_across_auth_timestamp_refreshes() {
         let (key, cert) = identity();
@@ -546,6 +670,7 @@ mod tests {
             verify_request_v2(
                 &key,
                 &cert,
+                &[],
                 &Method::POST,
                 &uri,
                 &headers,
@@ -598,6 +723,7 @@ mod tests {
         let verified = verify_request_v2(
             &key,
             &cert,
+            &[],
             &Method::POST,
             &uri,
             &headers,
@@ -612,6 +738,7 @@ mod tests {
             verify_ack_v2(
                 &key,
                 &cert,
+                &[],
                 &verified,
                 "01JTESTTARGET0000000000000000",
                 409,
@@ -623,6 +750,7 @@ mod tests {
             verify_ack_v2(
                 &key,
                 &cert,
+                &[],
                 &verified,
                 "01JTESTTARGET0000000000000000",
                 200,
@@ -636,6 +764,7 @@ mod tests {
             verify_ack_v2(
                 &key,
                 &cert,
+                &[],
                 &replayed,
                 "01JTESTTARGET0000000000000000",
                 409,
@@ -674,6 +803,7 @@ mod tests {
             verify_request_v2(
                 &key,
                 &cert,
+                &[],
                 &Method::GET,
                 &uri,
                 &headers,
@@ -707,6 +837,7 @@ mod tests {
             verify_request_v2(
                 &key,
                 &cert,
+                &[],
                 &Method::GET,
                 &uri,
                 &headers,
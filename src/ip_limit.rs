@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::inbound_ip_usage::{GeoLookup, PersistedInboundIpGeo, floor_minute};
+
+/// Consecutive sampled minutes over the limit before a user is blocked.
+pub const IP_LIMIT_SUSTAINED_MINUTES: u32 = 3;
+/// How long a block lasts before the user's memberships are restored.
+pub const IP_LIMIT_BLOCK_MINUTES: i64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IpLimitOffender {
+    pub ip: String,
+    #[serde(default)]
+    pub geo: PersistedInboundIpGeo,
+}
+
+/// Local-only enforcement state of one user's `max_concurrent_ips` on this node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct UserIpLimitState {
+    /// Consecutive sampled minutes, ending at `last_sample_minute`, with too many online IPs.
+    #[serde(default)]
+    pub over_limit_minutes: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sample_minute: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_until: Option<String>,
+    /// Source IPs online when the block was applied.
+    #[serde(default)]
+    pub offending_ips: Vec<IpLimitOffender>,
+}
+
+impl UserIpLimitState {
+    pub fn is_blocked_at(&self, now: DateTime<Utc>) -> bool {
+        self.blocked_until
+            .as_deref()
+            .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok())
+            .is_some_and(|until| until > now)
+    }
+}
+
+/// Feeds one minute of online IPs (per user, across the user's memberships on this node) into
+/// the per-user streaks and returns the users that became blocked.
+///
+/// Users without a limit lose their state; a gap between samples restarts the streak.
+pub fn observe_minute(
+    states: &mut BTreeMap<String, UserIpLimitState>,
+    limits: &BTreeMap<String, u32>,
+    online_ips_by_user: &BTreeMap<String, BTreeSet<String>>,
+    minute: DateTime<Utc>,
+    geo_resolver: &dyn GeoLookup,
+) -> Vec<String> {
+    let minute = floor_minute(minute);
+    let minute_str = minute.to_rfc3339();
+    let previous_minute = (minute - Duration::minutes(1)).to_rfc3339();
+    states.retain(|user_id, _| limits.contains_key(user_id));
+
+    let mut newly_blocked = Vec::new();
+    for (user_id, max_concurrent_ips) in limits {
+        let state = states.entry(user_id.clone()).or_default();
+        if state.last_sample_minute.as_deref() == Some(minute_str.as_str())
+            || state.is_blocked_at(minute)
+        {
+            continue;
+        }
+        let online = online_ips_by_user.get(user_id);
+        let online_count = online.map_or(0, BTreeSet::len);
+        if online_count > *max_concurrent_ips as usize {
+            let continues_streak =
+                state.last_sample_minute.as_deref() == Some(previous_minute.as_str());
+            state.over_limit_minutes = if continues_streak {
+                state.over_limit_minutes.saturating_add(1)
+            } else {
+                1
+            };
+        } else {
+            state.over_limit_minutes = 0;
+        }
+        state.last_sample_minute = Some(minute_str.clone());
+
+        if state.over_limit_minutes >= IP_LIMIT_SUSTAINED_MINUTES {
+            state.over_limit_minutes = 0;
+            state.blocked_at = Some(minute_str.clone());
+            state.blocked_until =
+                Some((minute + Duration::minutes(IP_LIMIT_BLOCK_MINUTES)).to_rfc3339());
+            state.offending_ips = online
+                .into_iter()
+                .flatten()
+                .map(|ip| IpLimitOffender {
+                    ip: ip.clone(),
+                    geo: geo_resolver.lookup(ip),
+                })
+                .collect();
+            newly_blocked.push(user_id.clone());
+        }
+    }
+    newly_blocked
+}
+
+/// Clears blocks that ran out by `now` (and state of users whose limit was removed). Returns
+/// whether any user was unblocked.
+pub fn lift_expired_blocks(
+    states: &mut BTreeMap<String, UserIpLimitState>,
+    limits: &BTreeMap<String, u32>,
+    now: DateTime<Utc>,
+) -> bool {
+    let mut lifted = false;
+    states.retain(|user_id, state| {
+        let blocked = state.blocked_until.is_some();
+        if !limits.contains_key(user_id) {
+            lifted |= blocked;
+            return false;
+        }
+        if blocked && !state.is_blocked_at(now) {
+            state.blocked_at = None;
+            state.blocked_until = None;
+            state.offending_ips.clear();
+            lifted = true;
+        }
+        true
+    });
+    lifted
+}
+
+#[cfg(test)]
+mod tests;
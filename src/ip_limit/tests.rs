@@ -0,0 +1,114 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+struct CountryGeo;
+
+impl GeoLookup for CountryGeo {
+    fn lookup(&self, ip: &str) -> PersistedInboundIpGeo {
+        PersistedInboundIpGeo {
+            country: if ip.starts_with("1.") { "JP" } else { "US" }.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+fn minute(raw: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(raw)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn online(ips: &[&str]) -> BTreeMap<String, BTreeSet<String>> {
+    BTreeMap::from([(
+        "u1".to_string(),
+        ips.iter().map(|ip| ip.to_string()).collect(),
+    )])
+}
+
+#[test]
+fn sustained_violation_blocks_with_offending_ips_until_expiry() {
+    let limits = BTreeMap::from([("u1".to_string(), 1)]);
+    let mut states = BTreeMap::new();
+    let too_many = online(&["1.1.1.1", "8.8.8.8"]);
+
+    for raw in ["2026-10-17T10:00:00Z", "2026-10-17T10:01:30Z"] {
+        let blocked = observe_minute(&mut states, &limits, &too_many, minute(raw), &CountryGeo);
+        assert!(blocked.is_empty());
+    }
+    let blocked = observe_minute(
+        &mut states,
+        &limits,
+        &too_many,
+        minute("2026-10-17T10:02:00Z"),
+        &CountryGeo,
+    );
+    assert_eq!(blocked, vec!["u1".to_string()]);
+
+    let state = &states["u1"];
+    assert_eq!(
+        state.blocked_until.as_deref(),
+        Some("2026-10-17T10:12:00+00:00")
+    );
+    assert_eq!(
+        state
+            .offending_ips
+            .iter()
+            .map(|offender| (offender.ip.as_str(), offender.geo.country.as_str()))
+            .collect::<Vec<_>>(),
+        vec![("1.1.1.1", "JP"), ("8.8.8.8", "US")]
+    );
+    assert!(state.is_blocked_at(minute("2026-10-17T10:11:59Z")));
+
+    assert!(!lift_expired_blocks(
+        &mut states,
+        &limits,
+        minute("2026-10-17T10:11:59Z")
+    ));
+    assert!(lift_expired_blocks(
+        &mut states,
+        &limits,
+        minute("2026-10-17T10:12:00Z")
+    ));
+    assert_eq!(states["u1"].blocked_until, None);
+    assert!(states["u1"].offending_ips.is_empty());
+}
+
+#[test]
+fn gaps_and_compliant_minutes_restart_the_streak() {
+    let limits = BTreeMap::from([("u1".to_string(), 2)]);
+    let mut states = BTreeMap::new();
+    let too_many = online(&["1.1.1.1", "1.1.1.2", "1.1.1.3"]);
+
+    for (raw, ips) in [
+        ("2026-10-17T10:00:00Z", &too_many),
+        ("2026-10-17T10:01:00Z", &too_many),
+        ("2026-10-17T10:02:00Z", &online(&["1.1.1.1", "1.1.1.2"])),
+        ("2026-10-17T10:03:00Z", &too_many),
+        ("2026-10-17T10:04:00Z", &too_many),
+        // 10:05 was not sampled.
+        ("2026-10-17T10:06:00Z", &too_many),
+    ] {
+        let blocked = observe_minute(&mut states, &limits, ips, minute(raw), &CountryGeo);
+        assert!(blocked.is_empty(), "unexpected block at {raw}");
+    }
+    assert_eq!(states["u1"].over_limit_minutes, 1);
+}
+
+#[test]
+fn removing_the_limit_drops_state_and_lifts_blocks() {
+    let mut states = BTreeMap::from([(
+        "u1".to_string(),
+        UserIpLimitState {
+            blocked_at: Some("2026-10-17T10:00:00+00:00".to_string()),
+            blocked_until: Some("2026-10-17T10:10:00+00:00".to_string()),
+            ..Default::default()
+        },
+    )]);
+    assert!(lift_expired_blocks(
+        &mut states,
+        &BTreeMap::new(),
+        minute("2026-10-17T10:01:00Z")
+    ));
+    assert!(states.is_empty());
+}
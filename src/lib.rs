@@ -1,13 +1,19 @@
+pub mod admin_principal;
 pub mod admin_token;
+pub mod audit;
+pub mod backup;
 pub mod cloudflared_supervisor;
 pub mod cluster_identity;
 pub mod cluster_metadata;
 pub mod config;
 pub mod control_plane_mesh;
+pub mod credential_rotation;
 pub mod credentials;
 pub mod cycle;
 pub mod ddns;
+pub mod dns_failover;
 pub mod domain;
+pub mod egress;
 pub mod endpoint_probe;
 pub mod history_sync;
 pub mod http;
@@ -17,16 +23,19 @@ pub mod internal_auth;
 pub mod internal_auth_epoch;
 pub mod internal_idempotency;
 pub mod ip_geo_db;
+pub mod ip_limit;
 pub mod join_coordinator;
 pub mod join_session;
 pub mod login_token;
 pub mod managed_default_endpoints;
 pub mod mesh_telemetry;
+pub mod migrate;
 pub mod mihomo_redact;
 pub mod mihomo_resources;
 pub mod node_egress_probe;
 pub mod node_history;
 pub mod node_runtime;
+pub mod notify;
 mod openrc_process;
 pub mod ops;
 pub mod protocol;
@@ -44,11 +53,14 @@ pub mod reconcile;
 pub mod reverse_mesh;
 pub mod reverse_mesh_runtime;
 pub mod reverse_relay;
+pub mod routing_policy;
 pub mod state;
 mod state_join_command;
 pub mod subscription;
+pub mod subscription_access;
 pub mod tcp_connection_usage;
 pub mod upgrade_job;
+pub mod user_bulk;
 pub mod version;
 pub mod vless_https_canary;
 pub mod xray;
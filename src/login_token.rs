@@ -4,6 +4,8 @@ use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 
+use crate::admin_principal::AdminScope;
+
 pub const LOGIN_TOKEN_TTL_SECONDS: i64 = 3600;
 
 #[derive(Debug)]
@@ -47,6 +49,9 @@ pub struct LoginTokenClaims {
     pub iat: i64,
     #[serde(default)]
     pub iss: Option<String>,
+    /// Tokens issued before scopes existed carry full admin access.
+    #[serde(default)]
+    pub scope: AdminScope,
 }
 
 pub fn issue_login_token_jwt(
@@ -54,6 +59,7 @@ pub fn issue_login_token_jwt(
     token_id: &str,
     now: DateTime<Utc>,
     admin_token: &str,
+    scope: AdminScope,
 ) -> String {
     let header = JwtHeader {
         typ: "JWT",
@@ -67,6 +73,7 @@ pub fn issue_login_token_jwt(
         exp,
         iat,
         iss: None,
+        scope,
     };
 
     let header_json = serde_json::to_vec(&header).expect("jwt header json serialization failed");
@@ -146,17 +153,30 @@ mod tests {
     #[test]
     fn jwt_roundtrip_validates_and_binds_cluster() {
         let now = Utc::now();
-        let token = issue_login_token_jwt("cluster-1", "01JTESTTOKENID", now, "adminkey");
+        let token = issue_login_token_jwt(
+            "cluster-1",
+            "01JTESTTOKENID",
+            now,
+            "adminkey",
+            AdminScope::ReadOnly,
+        );
         let claims = decode_and_validate_login_token_jwt(&token, now, "adminkey", "cluster-1")
             .expect("token should validate");
         assert_eq!(claims.cluster_id, "cluster-1");
         assert_eq!(claims.jti, "01JTESTTOKENID");
+        assert_eq!(claims.scope, AdminScope::ReadOnly);
     }
 
     #[test]
     fn jwt_rejects_wrong_cluster() {
         let now = Utc::now();
-        let token = issue_login_token_jwt("cluster-1", "01JTESTTOKENID", now, "adminkey");
+        let token = issue_login_token_jwt(
+            "cluster-1",
+            "01JTESTTOKENID",
+            now,
+            "adminkey",
+            AdminScope::Admin,
+        );
         let err =
             decode_and_validate_login_token_jwt(&token, now, "adminkey", "cluster-2").unwrap_err();
         assert!(matches!(err, LoginTokenError::ClusterMismatch));
@@ -115,7 +115,7 @@ async fn main() -> Result<()> {
         xp::config::Command::Run => run_server(config).await,
         xp::config::Command::Init => init_cluster(&config),
         xp::config::Command::Join(args) => join_cluster(config, args.token).await,
-        xp::config::Command::LoginLink => login_link(&config),
+        xp::config::Command::LoginLink(args) => login_link(&config, args.scope),
     }
 }
 
@@ -144,7 +144,7 @@ fn init_cluster(config: &xp::config::Config) -> Result<()> {
     Ok(())
 }
 
-fn login_link(config: &xp::config::Config) -> Result<()> {
+fn login_link(config: &xp::config::Config, scope: xp::admin_principal::AdminScope) -> Result<()> {
     if config.admin_token_hash().is_none() {
         anyhow::bail!("admin token hash is not configured (XP_ADMIN_TOKEN_HASH is empty/invalid)");
     }
@@ -160,6 +160,7 @@ fn login_link(config: &xp::config::Config) -> Result<()> {
         &token_id,
         now,
         &config.admin_token_hash,
+        scope,
     );
 
     let base = config.api_base_url.trim_end_matches('/');
@@ -347,6 +348,14 @@ async fn join_cluster(config: xp::config::Config, join_token: String) -> Result<
 }
 
 async fn run_server(config: xp::config::Config) -> Result<()> {
+    let metrics_token_hash = match config.metrics_bind {
+        Some(_) => Some(config.metrics_token_hash().ok_or_else(|| {
+            anyhow::anyhow!(
+                "metrics token hash is not configured (XP_METRICS_TOKEN_HASH is empty/invalid)"
+            )
+        })?),
+        None => None,
+    };
     let cluster = xp::cluster_metadata::ClusterMetadata::load(&config.data_dir)?;
     let cluster_ca_pem = cluster.read_cluster_ca_pem(&config.data_dir)?;
     let cluster_ca_key_pem_required = cluster
@@ -377,14 +386,17 @@ async fn run_server(config: xp::config::Config) -> Result<()> {
         xp::xray_supervisor::spawn_xray_supervisor(config_arc.clone(), reconcile.clone());
     let (cloudflared_health, _cloudflared_supervisor_task) =
         xp::cloudflared_supervisor::spawn_cloudflared_supervisor(config_arc.clone());
-    let (ddns_health, _ddns_supervisor_task) =
-        xp::ddns::spawn_ddns_supervisor(config_arc.clone(), cloudflared_health.clone());
+    let (ddns_health, _ddns_supervisor_task) = xp::ddns::spawn_ddns_supervisor(
+        config_arc.clone(),
+        cloudflared_health.clone(),
+        store.clone(),
+    );
     let (node_runtime, _node_runtime_task) = xp::node_runtime::spawn_node_runtime_monitor(
         config_arc.clone(),
         cluster.node_id.clone(),
         xray_health.clone(),
         cloudflared_health.clone(),
-        ddns_health,
+        ddns_health.clone(),
     );
     let node_history = xp::node_history::NodeHistoryHandle::from_config(&config);
     let _node_history_local_task = xp::node_history::spawn_node_history_local_worker(
@@ -502,6 +514,7 @@ async fn run_server(config: xp::config::Config) -> Result<()> {
             cluster.cluster_id.clone(),
             cluster_ca_key_pem_required.clone(),
             cluster_ca_pem.clone(),
+            mesh_client.alternate_roots(),
         )
         .await;
         if disable_managed_vless_reconcile_for_canary_result(
@@ -605,6 +618,48 @@ async fn run_server(config: xp::config::Config) -> Result<()> {
     );
     let _join_coordinator_task =
         xp::join_coordinator::spawn_join_coordinator(raft_facade.clone(), store.clone());
+    let _notifier_task = xp::notify::spawn_notifier(
+        cluster.node_id.clone(),
+        store.clone(),
+        raft_facade.clone(),
+        node_runtime.clone(),
+        mesh_telemetry.clone(),
+    );
+    let _dns_failover_task =
+        xp::dns_failover::spawn_dns_failover_worker(xp::dns_failover::DnsFailoverWorker {
+            config: config_arc.clone(),
+            cluster_id: cluster.cluster_id.clone(),
+            node_id: cluster.node_id.clone(),
+            cluster_ca_key_pem: cluster_ca_key_pem_required.clone(),
+            cluster_ca_pem: cluster_ca_pem.clone(),
+            store: store.clone(),
+            raft: raft_facade.clone(),
+            node_runtime: node_runtime.clone(),
+            mesh_client: mesh_client.clone(),
+        });
+    let _credential_rotation_task = xp::credential_rotation::spawn_credential_rotation_worker(
+        xp::credential_rotation::CredentialRotationWorker {
+            data_dir: config.data_dir.clone(),
+            cluster_id: cluster.cluster_id.clone(),
+            node_id: cluster.node_id.clone(),
+            cluster_ca_key_pem: cluster_ca_key_pem_required.clone(),
+            alternate_roots: mesh_client.alternate_roots(),
+            store: store.clone(),
+            raft: raft_facade.clone(),
+        },
+    );
+
+    let metrics_state = metrics_token_hash.map(|token_hash| xp::http::MetricsState {
+        node_id: cluster.node_id.clone(),
+        store: store.clone(),
+        raft: raft_facade.clone(),
+        xray_health: xray_health.clone(),
+        cloudflared_health: cloudflared_health.clone(),
+        ddns_health,
+        mesh_telemetry: mesh_telemetry.clone(),
+        token_hash,
+        token_verifier: xp::http::MetricsTokenVerifier::default(),
+    });
 
     let app = xp::http::build_router_with_mesh_telemetry(
         config.clone(),
@@ -634,6 +689,19 @@ async fn run_server(config: xp::config::Config) -> Result<()> {
         "starting xp"
     );
     let listener = tokio::net::TcpListener::bind(config.bind).await?;
+    if let (Some(metrics_bind), Some(metrics_state)) = (config.metrics_bind, metrics_state) {
+        let metrics_listener = tokio::net::TcpListener::bind(metrics_bind).await?;
+        info!(bind = %metrics_bind, "starting metrics listener");
+        let metrics_app = xp::http::build_metrics_router(metrics_state);
+        tokio::spawn(async move {
+            if let Err(err) = axum::serve(metrics_listener, metrics_app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+            {
+                tracing::warn!(error = %err, "metrics listener stopped");
+            }
+        });
+    }
     if let Some(startup_managed_default_intent) = pending_managed_default_reconcile {
         let data_dir = config.data_dir.clone();
         let raft_facade = startup_raft_facade;
@@ -650,9 +718,12 @@ async fn run_server(config: xp::config::Config) -> Result<()> {
             .await;
         });
     }
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
     Ok(())
 }
 
@@ -12,8 +12,9 @@ use crate::domain::{Endpoint, EndpointKind};
 use crate::id::new_ulid_string;
 use crate::protocol::{
     RealityConfig, RealityKeys, RealityServerNamesSource, SS2022_METHOD_2022_BLAKE3_AES_128_GCM,
-    Ss2022EndpointMeta, VlessRealityVisionTcpEndpointMeta, generate_reality_keypair,
-    generate_short_id_16hex, generate_ss2022_psk_b64, validate_reality_server_name,
+    SS2022_PSK_LEN_BYTES_AES_128, Ss2022EndpointMeta, VlessRealityVisionTcpEndpointMeta,
+    generate_reality_keypair, generate_short_id_16hex, generate_ss2022_psk_b64,
+    validate_reality_server_name,
 };
 use crate::state::DesiredStateCommand;
 
@@ -518,7 +519,7 @@ impl ManagedEndpointSpec for DefaultSsEndpointSpec {
         let mut rng = OsRng;
         let meta = Ss2022EndpointMeta {
             method: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
-            server_psk_b64: generate_ss2022_psk_b64(&mut rng),
+            server_psk_b64: generate_ss2022_psk_b64(&mut rng, SS2022_PSK_LEN_BYTES_AES_128),
             mihomo_smux: Default::default(),
             managed_default: true,
         };
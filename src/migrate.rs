@@ -0,0 +1,398 @@
+//! Migration from other Xray panels: readers that turn a 3x-ui database or a Marzban export into
+//! a panel-neutral `MigrationSource`, and the plan that maps its inbounds to endpoints on one node
+//! and its clients to users that keep their VLESS UUID and SS2022 key.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    domain::{Endpoint, EndpointKind},
+    id::new_ulid_string,
+    protocol::{
+        MihomoSmuxConfig, RealityConfig, RealityKeys, RealityServerNamesSource, Ss2022EndpointMeta,
+        VlessRealityTransport, VlessRealityVisionTcpEndpointMeta,
+        reality_keypair_from_private_key_b64url_nopad, ss2022_psk_len_bytes, validate_short_id,
+    },
+    state::{DesiredStateCommand, PersistedState, endpoint_tag},
+    user_bulk::{UserBulkRecord, UserImportPlan, plan_user_import, resolve_node},
+};
+
+pub mod marzban;
+pub mod three_x_ui;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrateError {
+    ReadSource { reason: String },
+    InvalidSource { reason: String },
+}
+
+impl std::fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadSource { reason } => write!(f, "read migration source: {reason}"),
+            Self::InvalidSource { reason } => write!(f, "invalid migration source: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MigrationSource {
+    pub panel: String,
+    pub inbounds: Vec<MigratedInbound>,
+    pub users: Vec<MigratedUser>,
+    /// Inbounds, clients and credentials that could not be carried over.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MigratedInbound {
+    pub source_tag: String,
+    pub kind: EndpointKind,
+    pub port: u16,
+    /// Complete endpoint meta, keeping the source's Reality keys, short ids and server PSK.
+    pub meta: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MigratedUser {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vless_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ss2022_psk_b64: Option<String>,
+    pub inbound_tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// Maps an Xray inbound (as both panels store it) to endpoint meta, or explains why it cannot be
+/// migrated.
+fn migrate_inbound(
+    tag: &str,
+    port: u16,
+    protocol: &str,
+    settings: &Value,
+    stream_settings: &Value,
+) -> Result<MigratedInbound, String> {
+    match protocol {
+        "vless" => {
+            let network = stream_settings["network"].as_str().unwrap_or("tcp");
+            let security = stream_settings["security"].as_str().unwrap_or("none");
+            if network != "tcp" || security != "reality" {
+                return Err(format!(
+                    "inbound {tag}: only VLESS over TCP with Reality can be migrated, \
+                     got {network}/{security}"
+                ));
+            }
+            let reality = &stream_settings["realitySettings"];
+            let dest = reality["target"]
+                .as_str()
+                .or_else(|| reality["dest"].as_str())
+                .ok_or_else(|| format!("inbound {tag}: Reality dest is missing"))?;
+            let keypair = reality["privateKey"]
+                .as_str()
+                .ok_or_else(|| "missing".to_string())
+                .and_then(|key| {
+                    reality_keypair_from_private_key_b64url_nopad(key)
+                        .map_err(|error| error.to_string())
+                })
+                .map_err(|error| format!("inbound {tag}: invalid Reality private key: {error}"))?;
+            let short_ids = string_list(&reality["shortIds"])
+                .into_iter()
+                .filter(|short_id| validate_short_id(short_id).is_ok())
+                .collect::<Vec<_>>();
+            let Some(active_short_id) = short_ids.first().cloned() else {
+                return Err(format!("inbound {tag}: no usable Reality short id"));
+            };
+            let meta = VlessRealityVisionTcpEndpointMeta {
+                reality: RealityConfig {
+                    dest: dest.to_string(),
+                    server_names: string_list(&reality["serverNames"]),
+                    server_names_source: RealityServerNamesSource::Manual,
+                    fingerprint: reality["settings"]["fingerprint"]
+                        .as_str()
+                        .filter(|fingerprint| !fingerprint.is_empty())
+                        .unwrap_or("chrome")
+                        .to_string(),
+                },
+                reality_keys: RealityKeys {
+                    private_key: keypair.private_key,
+                    public_key: keypair.public_key,
+                },
+                short_ids,
+                active_short_id,
+                canary_upstream: None,
+                accepted_authorities: Vec::new(),
+                mihomo_smux: MihomoSmuxConfig::default(),
+                transport: VlessRealityTransport::VisionTcp,
+                managed_default: false,
+            };
+            Ok(MigratedInbound {
+                source_tag: tag.to_string(),
+                kind: EndpointKind::VlessRealityVisionTcp,
+                port,
+                meta: serde_json::to_value(meta).expect("serialize vless meta"),
+            })
+        }
+        "shadowsocks" => {
+            let method = settings["method"].as_str().unwrap_or_default();
+            let Some(psk_len) = ss2022_psk_len_bytes(method) else {
+                return Err(format!(
+                    "inbound {tag}: only Shadowsocks 2022 methods can be migrated, got {method:?}"
+                ));
+            };
+            let server_psk_b64 = settings["password"]
+                .as_str()
+                .filter(|psk| decoded_len(psk) == Some(psk_len))
+                .ok_or_else(|| {
+                    format!("inbound {tag}: server password is not a {psk_len}-byte base64 key")
+                })?;
+            let meta = Ss2022EndpointMeta {
+                method: method.to_string(),
+                server_psk_b64: server_psk_b64.to_string(),
+                mihomo_smux: MihomoSmuxConfig::default(),
+                managed_default: false,
+            };
+            Ok(MigratedInbound {
+                source_tag: tag.to_string(),
+                kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                port,
+                meta: serde_json::to_value(meta).expect("serialize ss2022 meta"),
+            })
+        }
+        other => Err(format!(
+            "inbound {tag}: {other} inbounds cannot be migrated"
+        )),
+    }
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn decoded_len(psk_b64: &str) -> Option<usize> {
+    use base64::Engine as _;
+    base64::engine::general_purpose::STANDARD
+        .decode(psk_b64)
+        .ok()
+        .map(|bytes| bytes.len())
+}
+
+fn rfc3339_from_unix_millis(millis: i64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp_millis(millis)
+        .map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Collects clients by name across inbounds; a client keeps the first VLESS UUID and SS2022 key
+/// it was seen with.
+#[derive(Debug, Default)]
+struct UserCollector {
+    users: Vec<MigratedUser>,
+    enabled: Vec<bool>,
+    by_name: BTreeMap<String, usize>,
+}
+
+struct MigratedClient<'a> {
+    name: &'a str,
+    inbound: &'a MigratedInbound,
+    vless_uuid: Option<&'a str>,
+    ss2022_psk_b64: Option<&'a str>,
+    expires_at: Option<String>,
+    enabled: bool,
+}
+
+impl UserCollector {
+    fn add(&mut self, client: MigratedClient<'_>, warnings: &mut Vec<String>) {
+        let index = *self
+            .by_name
+            .entry(client.name.to_string())
+            .or_insert_with(|| {
+                self.users.push(MigratedUser {
+                    name: client.name.to_string(),
+                    ..Default::default()
+                });
+                self.enabled.push(false);
+                self.users.len() - 1
+            });
+        let user = &mut self.users[index];
+        let tag = &client.inbound.source_tag;
+        if !user.inbound_tags.contains(tag) {
+            user.inbound_tags.push(tag.clone());
+        }
+        self.enabled[index] |= client.enabled;
+        if client.expires_at > user.expires_at {
+            user.expires_at = client.expires_at;
+        }
+
+        match client.inbound.kind {
+            EndpointKind::VlessRealityVisionTcp => {
+                match (client.vless_uuid, user.vless_uuid.as_deref()) {
+                    (Some(uuid), None) => user.vless_uuid = Some(uuid.to_ascii_lowercase()),
+                    (Some(uuid), Some(pinned)) if !uuid.eq_ignore_ascii_case(pinned) => warnings
+                        .push(format!(
+                            "client {}: VLESS UUID on inbound {tag} differs from the one kept, \
+                             those clients must refresh their subscription",
+                            client.name
+                        )),
+                    _ => {}
+                }
+            }
+            EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
+                let psk_len = client
+                    .inbound
+                    .meta
+                    .get("method")
+                    .and_then(Value::as_str)
+                    .and_then(ss2022_psk_len_bytes);
+                match (client.ss2022_psk_b64, user.ss2022_psk_b64.as_deref()) {
+                    (Some(psk), _) if decoded_len(psk) != psk_len => warnings.push(format!(
+                        "client {}: password on inbound {tag} is not a usable SS2022 key",
+                        client.name
+                    )),
+                    (Some(psk), None) => user.ss2022_psk_b64 = Some(psk.to_string()),
+                    (Some(psk), Some(pinned)) if psk != pinned => warnings.push(format!(
+                        "client {}: SS2022 key on inbound {tag} differs from the one kept, \
+                         those clients must refresh their subscription",
+                        client.name
+                    )),
+                    _ => {}
+                }
+            }
+            EndpointKind::TrojanRealityTcp
+            | EndpointKind::TrojanTlsTcp
+            | EndpointKind::Hysteria2 => {}
+        }
+    }
+
+    fn finish(mut self) -> Vec<MigratedUser> {
+        for (user, enabled) in self.users.iter_mut().zip(self.enabled) {
+            user.disabled = !enabled;
+        }
+        self.users
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct MigratedEndpoint {
+    pub source_tag: String,
+    pub endpoint_id: String,
+    pub tag: String,
+    pub kind: EndpointKind,
+    pub port: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MigrationPlan {
+    pub node_id: Option<String>,
+    pub endpoints: Vec<Endpoint>,
+    pub endpoint_views: Vec<MigratedEndpoint>,
+    pub users: UserImportPlan,
+    pub issues: Vec<String>,
+}
+
+/// Builds the endpoints for `source` on `node` (id or name), then plans the users against the
+/// state with those endpoints in place.
+pub fn plan_migration(
+    state: &PersistedState,
+    node: &str,
+    source: &MigrationSource,
+) -> MigrationPlan {
+    let mut plan = MigrationPlan::default();
+    let node_id = match resolve_node(state, node) {
+        Ok(node_id) => node_id,
+        Err(message) => {
+            plan.issues.push(message);
+            return plan;
+        }
+    };
+
+    let mut scratch = state.clone();
+    let mut endpoint_ids = BTreeMap::new();
+    for inbound in &source.inbounds {
+        if let Some(existing) = scratch
+            .endpoints
+            .values()
+            .find(|endpoint| endpoint.node_id == node_id && endpoint.port == inbound.port)
+        {
+            plan.issues.push(format!(
+                "inbound {}: port {} is already used by endpoint {} on node {node_id}",
+                inbound.source_tag, inbound.port, existing.endpoint_id
+            ));
+            continue;
+        }
+        let endpoint_id = new_ulid_string();
+        let endpoint = Endpoint {
+            endpoint_id: endpoint_id.clone(),
+            node_id: node_id.clone(),
+            tag: endpoint_tag(&inbound.kind, &endpoint_id),
+            kind: inbound.kind.clone(),
+            port: inbound.port,
+            meta: inbound.meta.clone(),
+        };
+        let upsert = DesiredStateCommand::UpsertEndpoint {
+            endpoint: endpoint.clone(),
+            expected: None,
+        };
+        if let Err(error) = upsert.apply(&mut scratch) {
+            plan.issues
+                .push(format!("inbound {}: {error}", inbound.source_tag));
+            continue;
+        }
+        endpoint_ids.insert(inbound.source_tag.clone(), endpoint_id.clone());
+        plan.endpoint_views.push(MigratedEndpoint {
+            source_tag: inbound.source_tag.clone(),
+            endpoint_id,
+            tag: endpoint.tag.clone(),
+            kind: endpoint.kind.clone(),
+            port: endpoint.port,
+        });
+        plan.endpoints.push(endpoint);
+    }
+
+    let records = source
+        .users
+        .iter()
+        .map(|user| UserBulkRecord {
+            display_name: user.name.clone(),
+            endpoints: Some(
+                user.inbound_tags
+                    .iter()
+                    .filter_map(|tag| endpoint_ids.get(tag).cloned())
+                    .collect(),
+            ),
+            vless_uuid: user.vless_uuid.clone(),
+            ss2022_psk_b64: user.ss2022_psk_b64.clone(),
+            expires_at: user.expires_at.clone(),
+            disabled: Some(user.disabled),
+            ..Default::default()
+        })
+        .collect();
+    plan.users = plan_user_import(&scratch, records);
+    plan.issues.extend(plan.users.issues.iter().map(|issue| {
+        let name = &source.users[issue.row - 1].name;
+        format!("user {name}: {}", issue.message)
+    }));
+    plan.node_id = Some(node_id);
+    plan
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,116 @@
+//! Reads a Marzban export: the `GET /api/users` response (or a bare list of its users) together
+//! with the panel's `xray_config.json`, which holds the inbounds users refer to by tag.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use super::{
+    MigrateError, MigratedClient, MigrationSource, UserCollector, migrate_inbound,
+    rfc3339_from_unix_millis,
+};
+
+pub fn read_export(
+    users_json: &str,
+    xray_config_json: &str,
+) -> Result<MigrationSource, MigrateError> {
+    let invalid = |what: &str, error: serde_json::Error| MigrateError::InvalidSource {
+        reason: format!("{what}: {error}"),
+    };
+    let users: Value = serde_json::from_str(users_json).map_err(|error| invalid("users", error))?;
+    let xray_config: Value =
+        serde_json::from_str(xray_config_json).map_err(|error| invalid("xray config", error))?;
+    let users = users
+        .get("users")
+        .unwrap_or(&users)
+        .as_array()
+        .ok_or_else(|| MigrateError::InvalidSource {
+            reason: "users: expected a list or an object with `users`".to_string(),
+        })?;
+
+    let mut source = MigrationSource {
+        panel: "marzban".to_string(),
+        ..Default::default()
+    };
+    let mut by_tag = BTreeMap::new();
+    for inbound in xray_config["inbounds"].as_array().into_iter().flatten() {
+        let tag = inbound["tag"].as_str().unwrap_or_default();
+        let protocol = inbound["protocol"].as_str().unwrap_or_default();
+        let Some(port) = inbound["port"]
+            .as_u64()
+            .and_then(|port| u16::try_from(port).ok())
+        else {
+            source
+                .warnings
+                .push(format!("inbound {tag}: missing or invalid port"));
+            continue;
+        };
+        match migrate_inbound(
+            tag,
+            port,
+            protocol,
+            &inbound["settings"],
+            &inbound["streamSettings"],
+        ) {
+            Ok(migrated) => {
+                by_tag.insert(
+                    tag.to_string(),
+                    (protocol.to_string(), source.inbounds.len()),
+                );
+                source.inbounds.push(migrated);
+            }
+            Err(warning) => source.warnings.push(warning),
+        }
+    }
+
+    let mut collector = UserCollector::default();
+    for user in users {
+        let Some(username) = user["username"].as_str().filter(|name| !name.is_empty()) else {
+            source
+                .warnings
+                .push("skipped a user without username".to_string());
+            continue;
+        };
+        let expires_at = user["expire"]
+            .as_i64()
+            .filter(|seconds| *seconds > 0)
+            .and_then(|seconds| rfc3339_from_unix_millis(seconds.saturating_mul(1000)));
+        let enabled = user["status"].as_str() != Some("disabled");
+        let mut matched = false;
+        for (protocol, tags) in user["inbounds"].as_object().into_iter().flatten() {
+            let proxy = &user["proxies"][protocol.as_str()];
+            for tag in tags
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                let Some((_, index)) = by_tag
+                    .get(tag)
+                    .filter(|(inbound_protocol, _)| inbound_protocol == protocol)
+                else {
+                    continue;
+                };
+                matched = true;
+                collector.add(
+                    MigratedClient {
+                        name: username,
+                        inbound: &source.inbounds[*index],
+                        vless_uuid: proxy["id"].as_str(),
+                        ss2022_psk_b64: proxy["password"].as_str(),
+                        expires_at: expires_at.clone(),
+                        enabled,
+                    },
+                    &mut source.warnings,
+                );
+            }
+        }
+        if !matched {
+            source.warnings.push(format!(
+                "user {username}: none of its inbounds can be migrated"
+            ));
+        }
+    }
+    source.users = collector.finish();
+    Ok(source)
+}
@@ -0,0 +1,307 @@
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use super::*;
+use crate::{
+    domain::{Node, NodeQuotaReset},
+    protocol::reality_keypair_from_private_key_b64url_nopad,
+    user_bulk::UserImportAction,
+};
+
+const REALITY_PRIVATE_KEY: &str = "wHc4R8Bm0lb1l5cuCrw9xL3rM3nXwYzWfRq2o1mHc2k";
+const SERVER_PSK: &str = "AAECAwQFBgcICQoLDA0ODw==";
+const ALICE_PSK: &str = "EBESExQVFhcYGRobHB0eHw==";
+const ALICE_UUID: &str = "5f6e4d3c-2b1a-4098-8765-43210fedcba9";
+
+fn vless_stream_settings() -> Value {
+    json!({
+        "network": "tcp",
+        "security": "reality",
+        "realitySettings": {
+            "dest": "www.example.com:443",
+            "serverNames": ["www.example.com"],
+            "privateKey": REALITY_PRIVATE_KEY,
+            "shortIds": ["", "0123abcd", "not-hex"],
+            "settings": { "fingerprint": "firefox" }
+        }
+    })
+}
+
+fn three_x_ui_rows() -> Vec<three_x_ui::InboundRow> {
+    vec![
+        three_x_ui::InboundRow {
+            remark: "reality".to_string(),
+            tag: "inbound-443".to_string(),
+            enable: true,
+            port: 443,
+            protocol: "vless".to_string(),
+            settings: json!({
+                "clients": [
+                    { "email": "alice", "id": ALICE_UUID.to_ascii_uppercase(), "enable": true,
+                      "expiryTime": 1_798_675_200_000_i64 },
+                    { "email": "bob", "id": "0e5bd1a4-8a41-4c55-9a4b-2f0d6c1f8e11",
+                      "enable": false, "expiryTime": -86_400_000_i64 },
+                ]
+            })
+            .to_string(),
+            stream_settings: vless_stream_settings().to_string(),
+        },
+        three_x_ui::InboundRow {
+            remark: "ss".to_string(),
+            tag: "inbound-8388".to_string(),
+            enable: true,
+            port: 8388,
+            protocol: "shadowsocks".to_string(),
+            settings: json!({
+                "method": "2022-blake3-aes-128-gcm",
+                "password": SERVER_PSK,
+                "clients": [
+                    { "email": "alice", "password": ALICE_PSK, "enable": true },
+                    { "email": "carol", "password": "short", "enable": true },
+                ]
+            })
+            .to_string(),
+            stream_settings: String::new(),
+        },
+        three_x_ui::InboundRow {
+            remark: "trojan".to_string(),
+            tag: "inbound-8443".to_string(),
+            enable: true,
+            port: 8443,
+            protocol: "trojan".to_string(),
+            settings: json!({ "clients": [{ "email": "dave", "password": "x" }] }).to_string(),
+            stream_settings: String::new(),
+        },
+    ]
+}
+
+fn cluster_state() -> PersistedState {
+    let mut state = PersistedState::empty();
+    state.nodes.insert(
+        "n1".to_string(),
+        Node {
+            node_id: "n1".to_string(),
+            node_name: "tokyo".to_string(),
+            access_host: "n1.example.com".to_string(),
+            api_base_url: "https://n1.example.com".to_string(),
+            quota_limit_bytes: 0,
+            quota_reset: NodeQuotaReset::default(),
+        },
+    );
+    state
+}
+
+#[test]
+fn three_x_ui_database_maps_inbounds_and_merges_clients_by_email() {
+    let tmp = tempfile::tempdir().unwrap();
+    let path = tmp.path().join("x-ui.db");
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    connection
+        .execute_batch(
+            "CREATE TABLE inbounds (id INTEGER PRIMARY KEY, remark TEXT, tag TEXT, \
+             enable INTEGER, port INTEGER, protocol TEXT, settings TEXT, stream_settings TEXT);",
+        )
+        .unwrap();
+    for row in three_x_ui_rows() {
+        connection
+            .execute(
+                "INSERT INTO inbounds (remark, tag, enable, port, protocol, settings, \
+                 stream_settings) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![
+                    row.remark,
+                    row.tag,
+                    row.enable,
+                    row.port,
+                    row.protocol,
+                    row.settings,
+                    row.stream_settings
+                ],
+            )
+            .unwrap();
+    }
+    drop(connection);
+
+    let source = three_x_ui::read_database(&path).expect("read database");
+    assert_eq!(
+        source,
+        three_x_ui::from_inbound_rows(three_x_ui_rows()).unwrap()
+    );
+    assert_eq!(source.panel, "3x-ui");
+
+    assert_eq!(source.inbounds.len(), 2);
+    let vless = &source.inbounds[0];
+    assert_eq!(vless.kind, EndpointKind::VlessRealityVisionTcp);
+    let meta: VlessRealityVisionTcpEndpointMeta =
+        serde_json::from_value(vless.meta.clone()).unwrap();
+    let keypair = reality_keypair_from_private_key_b64url_nopad(REALITY_PRIVATE_KEY).unwrap();
+    assert_eq!(meta.reality_keys.public_key, keypair.public_key);
+    assert_eq!(meta.reality.dest, "www.example.com:443");
+    assert_eq!(meta.reality.fingerprint, "firefox");
+    assert_eq!(meta.short_ids, vec!["0123abcd".to_string()]);
+    assert_eq!(source.inbounds[1].meta["server_psk_b64"], SERVER_PSK);
+
+    let names = source
+        .users
+        .iter()
+        .map(|user| user.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["alice", "bob", "carol"]);
+    let alice = &source.users[0];
+    assert_eq!(alice.vless_uuid.as_deref(), Some(ALICE_UUID));
+    assert_eq!(alice.ss2022_psk_b64.as_deref(), Some(ALICE_PSK));
+    assert_eq!(alice.inbound_tags, vec!["inbound-443", "inbound-8388"]);
+    assert_eq!(alice.expires_at.as_deref(), Some("2026-12-31T00:00:00Z"));
+    assert!(!alice.disabled);
+    let bob = &source.users[1];
+    assert!(bob.disabled);
+    assert_eq!(bob.expires_at, None);
+    assert_eq!(source.users[2].ss2022_psk_b64, None);
+
+    assert_eq!(source.warnings.len(), 2, "{:?}", source.warnings);
+    assert!(source.warnings[0].starts_with("client carol: password on inbound inbound-8388"));
+    assert_eq!(
+        source.warnings[1],
+        "inbound inbound-8443: trojan inbounds cannot be migrated"
+    );
+}
+
+#[test]
+fn marzban_export_matches_users_to_inbounds_by_protocol_and_tag() {
+    let users = json!({
+        "users": [
+            {
+                "username": "alice",
+                "status": "active",
+                "expire": 1_798_675_200_i64,
+                "proxies": {
+                    "vless": { "id": ALICE_UUID },
+                    "shadowsocks": { "password": ALICE_PSK, "method": "chacha20-ietf-poly1305" }
+                },
+                "inbounds": { "vless": ["VLESS TCP REALITY"], "shadowsocks": ["Shadowsocks TCP"] }
+            },
+            {
+                "username": "bob",
+                "status": "disabled",
+                "expire": null,
+                "proxies": { "vmess": { "id": "0e5bd1a4-8a41-4c55-9a4b-2f0d6c1f8e11" } },
+                "inbounds": { "vmess": ["VMess TCP"] }
+            }
+        ]
+    });
+    let xray_config = json!({
+        "inbounds": [
+            {
+                "tag": "VLESS TCP REALITY",
+                "protocol": "vless",
+                "port": 443,
+                "settings": { "clients": [] },
+                "streamSettings": vless_stream_settings()
+            },
+            {
+                "tag": "Shadowsocks TCP",
+                "protocol": "shadowsocks",
+                "port": 1080,
+                "settings": { "clients": [], "network": "tcp,udp" }
+            },
+            { "tag": "VMess TCP", "protocol": "vmess", "port": 8080, "settings": {} }
+        ]
+    });
+
+    let source =
+        marzban::read_export(&users.to_string(), &xray_config.to_string()).expect("read export");
+    assert_eq!(source.panel, "marzban");
+    assert_eq!(source.inbounds.len(), 1);
+    assert_eq!(source.users.len(), 1);
+    let alice = &source.users[0];
+    assert_eq!(alice.vless_uuid.as_deref(), Some(ALICE_UUID));
+    assert_eq!(alice.ss2022_psk_b64, None);
+    assert_eq!(alice.inbound_tags, vec!["VLESS TCP REALITY"]);
+    assert_eq!(alice.expires_at.as_deref(), Some("2026-12-31T00:00:00Z"));
+    assert_eq!(
+        source.warnings,
+        vec![
+            "inbound Shadowsocks TCP: only Shadowsocks 2022 methods can be migrated, got \"\""
+                .to_string(),
+            "inbound VMess TCP: vmess inbounds cannot be migrated".to_string(),
+            "user bob: none of its inbounds can be migrated".to_string(),
+        ]
+    );
+
+    let error = marzban::read_export("{}", "{}").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid migration source: users: expected a list or an object with `users`"
+    );
+}
+
+#[test]
+fn plan_creates_endpoints_and_users_with_pinned_credentials() {
+    let mut state = cluster_state();
+    let source = three_x_ui::from_inbound_rows(three_x_ui_rows()).unwrap();
+
+    let plan = plan_migration(&state, "tokyo", &source);
+    assert_eq!(plan.issues, Vec::<String>::new());
+    assert_eq!(plan.node_id.as_deref(), Some("n1"));
+    assert_eq!(plan.endpoints.len(), 2);
+    assert_eq!(plan.users.count(UserImportAction::Create), 3);
+
+    for endpoint in plan.endpoints.clone() {
+        DesiredStateCommand::UpsertEndpoint {
+            endpoint,
+            expected: None,
+        }
+        .apply(&mut state)
+        .unwrap();
+    }
+    DesiredStateCommand::ImportUsers {
+        entries: plan.users.entries.clone(),
+    }
+    .apply(&mut state)
+    .unwrap();
+
+    let alice = state
+        .users
+        .values()
+        .find(|user| user.display_name == "alice")
+        .unwrap();
+    assert_eq!(
+        alice.pinned_credentials.vless_uuid.as_deref(),
+        Some(ALICE_UUID)
+    );
+    assert_eq!(
+        alice.pinned_credentials.ss2022_psk_b64.as_deref(),
+        Some(ALICE_PSK)
+    );
+    let alice_endpoints = state
+        .node_user_endpoint_memberships
+        .iter()
+        .filter(|membership| membership.user_id == alice.user_id)
+        .count();
+    assert_eq!(alice_endpoints, 2);
+    let bob = state
+        .users
+        .values()
+        .find(|user| user.display_name == "bob")
+        .unwrap();
+    assert!(bob.disabled);
+
+    // A second run hits the ports taken by the first and the already pinned UUIDs.
+    let rerun = plan_migration(&state, "n1", &source);
+    assert!(
+        rerun.issues[0].starts_with("inbound inbound-443: port 443 is already used by endpoint"),
+        "{:?}",
+        rerun.issues
+    );
+    assert!(
+        rerun
+            .issues
+            .iter()
+            .any(|issue| issue.starts_with("user alice: ")),
+        "{:?}",
+        rerun.issues
+    );
+
+    let missing = plan_migration(&state, "osaka", &source);
+    assert_eq!(missing.node_id, None);
+    assert_eq!(missing.issues.len(), 1);
+}
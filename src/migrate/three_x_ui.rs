@@ -0,0 +1,120 @@
+//! Reads the `inbounds` table of a 3x-ui (`x-ui.db`) SQLite database. Clients live in each
+//! inbound's `settings` JSON and are merged into users by email.
+
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+use serde_json::Value;
+
+use super::{
+    MigrateError, MigratedClient, MigrationSource, UserCollector, migrate_inbound,
+    rfc3339_from_unix_millis,
+};
+
+pub fn read_database(path: &Path) -> Result<MigrationSource, MigrateError> {
+    let read_error = |error: rusqlite::Error| MigrateError::ReadSource {
+        reason: format!("{}: {error}", path.display()),
+    };
+    let connection =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(read_error)?;
+    let mut statement = connection
+        .prepare(
+            "SELECT remark, tag, enable, port, protocol, settings, stream_settings \
+             FROM inbounds ORDER BY id",
+        )
+        .map_err(read_error)?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok(InboundRow {
+                remark: row.get::<_, Option<String>>(0)?.unwrap_or_default(),
+                tag: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                enable: row.get::<_, Option<bool>>(2)?.unwrap_or(true),
+                port: row.get(3)?,
+                protocol: row.get(4)?,
+                settings: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                stream_settings: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+            })
+        })
+        .map_err(read_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(read_error)?;
+    from_inbound_rows(rows)
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct InboundRow {
+    pub(super) remark: String,
+    pub(super) tag: String,
+    pub(super) enable: bool,
+    pub(super) port: i64,
+    pub(super) protocol: String,
+    pub(super) settings: String,
+    pub(super) stream_settings: String,
+}
+
+pub(super) fn from_inbound_rows(rows: Vec<InboundRow>) -> Result<MigrationSource, MigrateError> {
+    let mut source = MigrationSource {
+        panel: "3x-ui".to_string(),
+        ..Default::default()
+    };
+    let mut collector = UserCollector::default();
+    for row in rows {
+        let tag = if row.tag.is_empty() {
+            row.remark.clone()
+        } else {
+            row.tag.clone()
+        };
+        let parse = |raw: &str, what: &str| -> Result<Value, MigrateError> {
+            if raw.trim().is_empty() {
+                return Ok(Value::Null);
+            }
+            serde_json::from_str(raw).map_err(|error| MigrateError::InvalidSource {
+                reason: format!("inbound {tag}: {what}: {error}"),
+            })
+        };
+        let settings = parse(&row.settings, "settings")?;
+        let stream_settings = parse(&row.stream_settings, "stream_settings")?;
+        let Ok(port) = u16::try_from(row.port) else {
+            source
+                .warnings
+                .push(format!("inbound {tag}: invalid port {}", row.port));
+            continue;
+        };
+        let inbound = match migrate_inbound(&tag, port, &row.protocol, &settings, &stream_settings)
+        {
+            Ok(inbound) => inbound,
+            Err(warning) => {
+                source.warnings.push(warning);
+                continue;
+            }
+        };
+
+        for client in settings["clients"].as_array().into_iter().flatten() {
+            let Some(email) = client["email"].as_str().filter(|email| !email.is_empty()) else {
+                source
+                    .warnings
+                    .push(format!("inbound {tag}: skipped a client without email"));
+                continue;
+            };
+            collector.add(
+                MigratedClient {
+                    name: email,
+                    inbound: &inbound,
+                    vless_uuid: client["id"].as_str(),
+                    ss2022_psk_b64: client["password"].as_str(),
+                    // Negative values mean "expires N ms after first use", which has no
+                    // equivalent; such clients are migrated without an expiry.
+                    expires_at: client["expiryTime"]
+                        .as_i64()
+                        .filter(|millis| *millis > 0)
+                        .and_then(rfc3339_from_unix_millis),
+                    enabled: row.enable && client["enable"].as_bool().unwrap_or(true),
+                },
+                &mut source.warnings,
+            );
+        }
+        source.inbounds.push(inbound);
+    }
+    source.users = collector.finish();
+    Ok(source)
+}
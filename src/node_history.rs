@@ -16,8 +16,7 @@ use tracing::warn;
 use crate::{
     config::Config,
     control_plane_mesh::{MeshAwareHttpClient, peer_target_from_node},
-    cycle::{CycleTimeZone, current_cycle_window_at},
-    domain::{Node, NodeQuotaReset, User, UserQuotaReset},
+    domain::Node,
     node_runtime::{
         LocalNodeRuntimeSnapshot, NodeRuntimeEventKind, NodeRuntimeHandle, RuntimeComponent,
         RuntimeStatus,
@@ -25,14 +24,19 @@ use crate::{
     state::{
         JsonSnapshotStore,
         history_repository::{HistoryStorage, NODE_HISTORY_KEY},
-        membership_xray_email,
+        membership_xray_alternate_email, membership_xray_email,
     },
     xray,
 };
 
+#[path = "node_history_cycle.rs"]
+mod cycle;
 #[path = "node_history_remote.rs"]
 mod remote;
 
+pub use cycle::{TrafficCycleContext, TrafficCycleMode};
+use cycle::{traffic_cycle_for_node, traffic_cycle_for_user};
+
 const HISTORY_SCHEMA_VERSION: u32 = 2;
 const HISTORY_WINDOW_DAYS: u64 = 90;
 const TRAFFIC_ROLLUP_WINDOW_SECS: i64 = 49 * 60 * 60;
@@ -231,19 +235,6 @@ pub struct UserTrafficReport {
     pub unreachable_nodes: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct TrafficCycleContext {
-    pub start_at: String,
-    pub end_at: String,
-    pub mode: TrafficCycleMode,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum TrafficCycleMode {
-    Monthly,
-    Unlimited,
-}
-
 #[derive(Debug, Clone)]
 pub struct NodeTrafficSample {
     pub totals: Vec<NodeTrafficTotals>,
@@ -1253,15 +1244,12 @@ fn update_cycle_accumulator(
     sampled_at: &str,
 ) {
     let Some(context) = context else { return };
-    let mode = match context.mode {
-        TrafficCycleMode::Monthly => "monthly",
-        TrafficCycleMode::Unlimited => "unlimited",
-    };
+    let mode = context.mode.as_str();
     let reset = accumulator.as_ref().is_none_or(|current| {
         current.start_at != context.start_at || current.end_at != context.end_at
     });
     let configuration_changed = accumulator.as_ref().is_some_and(|current| {
-        current.mode != mode || (mode == "monthly" && current.end_at != context.start_at)
+        current.mode != mode || (mode != "unlimited" && current.end_at != context.start_at)
     });
     let had_accumulator = accumulator.is_some();
     if reset {
@@ -1646,7 +1634,7 @@ fn build_summary(
     latest_sample: DateTime<Utc>,
 ) -> TrafficSummary {
     if let Some(cycle) = &rollup.cycle
-        && cycle.mode == "monthly"
+        && cycle.mode != "unlimited"
     {
         return TrafficSummary {
             mode: "cycle".to_string(),
@@ -2053,17 +2041,22 @@ async fn collect_local_traffic_totals(
 ) -> Option<TrafficCollection> {
     let memberships = {
         let store = store.lock().await;
+        // During a credential rotation each membership also has a client for the other root.
+        let rotation_active = store.state().credential_rotation.is_some();
         store
             .state()
             .node_user_endpoint_memberships
             .iter()
             .filter(|membership| membership.node_id == local_node_id)
-            .map(|membership| {
-                (
-                    membership_xray_email(&membership.user_id, &membership.endpoint_id),
-                    membership.user_id.clone(),
-                    membership.user_id == crate::endpoint_probe::PROBE_USER_ID,
-                )
+            .flat_map(|membership| {
+                let is_probe = membership.user_id == crate::endpoint_probe::PROBE_USER_ID;
+                let email = membership_xray_email(&membership.user_id, &membership.endpoint_id);
+                let alternate_email = rotation_active.then(|| {
+                    membership_xray_alternate_email(&membership.user_id, &membership.endpoint_id)
+                });
+                std::iter::once(email)
+                    .chain(alternate_email)
+                    .map(move |email| (email, membership.user_id.clone(), is_probe))
             })
             .collect::<Vec<_>>()
     };
@@ -2115,68 +2108,6 @@ async fn collect_local_traffic_totals(
     })
 }
 
-fn traffic_cycle_for_node(node: &Node, now: DateTime<Utc>) -> Option<TrafficCycleContext> {
-    match node.quota_reset {
-        NodeQuotaReset::Unlimited { .. } => {
-            let start = (now.date_naive() - chrono::Days::new(29))
-                .and_hms_opt(0, 0, 0)?
-                .and_utc();
-            let end = (now.date_naive() + chrono::Days::new(1))
-                .and_hms_opt(0, 0, 0)?
-                .and_utc();
-            Some(TrafficCycleContext {
-                start_at: rfc3339(start),
-                end_at: rfc3339(end),
-                mode: TrafficCycleMode::Unlimited,
-            })
-        }
-        NodeQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => {
-            let tz = tz_offset_minutes
-                .map(|tz_offset_minutes| CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes })
-                .unwrap_or(CycleTimeZone::Local);
-            let (start, end) = current_cycle_window_at(tz, day_of_month, now).ok()?;
-            Some(TrafficCycleContext {
-                start_at: start.with_timezone(&Utc).to_rfc3339(),
-                end_at: end.with_timezone(&Utc).to_rfc3339(),
-                mode: TrafficCycleMode::Monthly,
-            })
-        }
-    }
-}
-
-fn traffic_cycle_for_user(user: &User, now: DateTime<Utc>) -> Option<TrafficCycleContext> {
-    match user.quota_reset {
-        UserQuotaReset::Unlimited { .. } => {
-            let start = (now.date_naive() - chrono::Days::new(29))
-                .and_hms_opt(0, 0, 0)?
-                .and_utc();
-            let end = (now.date_naive() + chrono::Days::new(1))
-                .and_hms_opt(0, 0, 0)?
-                .and_utc();
-            Some(TrafficCycleContext {
-                start_at: rfc3339(start),
-                end_at: rfc3339(end),
-                mode: TrafficCycleMode::Unlimited,
-            })
-        }
-        UserQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => {
-            let tz = CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes };
-            let (start, end) = current_cycle_window_at(tz, day_of_month, now).ok()?;
-            Some(TrafficCycleContext {
-                start_at: start.with_timezone(&Utc).to_rfc3339(),
-                end_at: end.with_timezone(&Utc).to_rfc3339(),
-                mode: TrafficCycleMode::Monthly,
-            })
-        }
-    }
-}
-
 async fn sync_remote_node_histories(
     client: &MeshAwareHttpClient,
     auth: &remote::RemoteSyncAuth<'_>,
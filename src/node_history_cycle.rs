@@ -0,0 +1,95 @@
+//! Traffic cycle windows that history rollups are accumulated over.
+
+use chrono::{DateTime, Utc};
+
+use super::rfc3339;
+use crate::{
+    cycle::{CycleSchedule, CycleTimeZone, current_cycle_window_at},
+    domain::{Node, User},
+};
+
+#[derive(Debug, Clone)]
+pub struct TrafficCycleContext {
+    pub start_at: String,
+    pub end_at: String,
+    pub mode: TrafficCycleMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficCycleMode {
+    Monthly,
+    Weekly,
+    Daily,
+    FixedDays,
+    Unlimited,
+}
+
+impl TrafficCycleMode {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Monthly => "monthly",
+            Self::Weekly => "weekly",
+            Self::Daily => "daily",
+            Self::FixedDays => "fixed_days",
+            Self::Unlimited => "unlimited",
+        }
+    }
+}
+
+impl From<CycleSchedule> for TrafficCycleMode {
+    fn from(schedule: CycleSchedule) -> Self {
+        match schedule {
+            CycleSchedule::Monthly { .. } => Self::Monthly,
+            CycleSchedule::Weekly { .. } => Self::Weekly,
+            CycleSchedule::Daily => Self::Daily,
+            CycleSchedule::FixedDays { .. } => Self::FixedDays,
+        }
+    }
+}
+
+pub(super) fn traffic_cycle_for_node(
+    node: &Node,
+    now: DateTime<Utc>,
+) -> Option<TrafficCycleContext> {
+    traffic_cycle_context(
+        node.quota_reset.cycle_schedule(),
+        node.quota_reset.cycle_time_zone(),
+        now,
+    )
+}
+
+pub(super) fn traffic_cycle_for_user(
+    user: &User,
+    now: DateTime<Utc>,
+) -> Option<TrafficCycleContext> {
+    let tz = CycleTimeZone::FixedOffsetMinutes {
+        tz_offset_minutes: user.quota_reset.tz_offset_minutes(),
+    };
+    traffic_cycle_context(user.quota_reset.cycle_schedule(), tz, now)
+}
+
+fn traffic_cycle_context(
+    schedule: Option<CycleSchedule>,
+    tz: CycleTimeZone,
+    now: DateTime<Utc>,
+) -> Option<TrafficCycleContext> {
+    let Some(schedule) = schedule else {
+        let start = (now.date_naive() - chrono::Days::new(29))
+            .and_hms_opt(0, 0, 0)?
+            .and_utc();
+        let end = (now.date_naive() + chrono::Days::new(1))
+            .and_hms_opt(0, 0, 0)?
+            .and_utc();
+        return Some(TrafficCycleContext {
+            start_at: rfc3339(start),
+            end_at: rfc3339(end),
+            mode: TrafficCycleMode::Unlimited,
+        });
+    };
+    let (start, end) = current_cycle_window_at(tz, schedule, now).ok()?;
+    Some(TrafficCycleContext {
+        start_at: start.with_timezone(&Utc).to_rfc3339(),
+        end_at: end.with_timezone(&Utc).to_rfc3339(),
+        mode: schedule.into(),
+    })
+}
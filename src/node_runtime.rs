@@ -98,6 +98,10 @@ pub enum NodeRuntimeEventKind {
     RestartRequested,
     RestartSucceeded,
     RestartFailed,
+    /// The leader pointed a DNS failover record away from its primary node.
+    FailoverActivated,
+    /// The leader pointed a DNS failover record back at its primary node.
+    FailoverRestored,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -506,6 +510,32 @@ impl NodeRuntimeHandle {
         }
     }
 
+    /// Records an event that is not a component status transition, such as a DNS failover.
+    pub async fn record_event(
+        &self,
+        component: RuntimeComponent,
+        kind: NodeRuntimeEventKind,
+        message: String,
+    ) {
+        let now = Utc::now();
+        let event = NodeRuntimeEvent {
+            event_id: new_ulid_string(),
+            occurred_at: rfc3339(now),
+            component,
+            kind,
+            message,
+            from_status: None,
+            to_status: None,
+        };
+        {
+            let mut state = self.inner.write().await;
+            state.events.push_front(event.clone());
+            state.prune(now);
+        }
+        let _ = self.events_tx.send(event);
+        self.persist().await;
+    }
+
     async fn persist(&self) {
         let persisted = {
             let state = self.inner.read().await;
@@ -867,6 +897,32 @@ mod tests {
         assert_eq!(snapshot.recent_slots.len(), SLOT_WINDOW);
     }
 
+    #[tokio::test]
+    async fn recorded_events_are_broadcast_and_persisted() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("service_runtime.json");
+        let handle = NodeRuntimeHandle::test_new(path.clone(), "node-1".to_string(), false, true);
+        let mut events = handle.subscribe();
+
+        handle
+            .record_event(
+                RuntimeComponent::Ddns,
+                NodeRuntimeEventKind::FailoverActivated,
+                "dns failover edge.example.com: node-a -> node-b".to_string(),
+            )
+            .await;
+
+        let broadcast = events.recv().await.unwrap();
+        assert_eq!(broadcast.kind, NodeRuntimeEventKind::FailoverActivated);
+        assert_eq!(broadcast.from_status, None);
+
+        let reloaded = NodeRuntimeHandle::test_new(path, "node-1".to_string(), false, true);
+        let snapshot = reloaded.snapshot(20).await;
+        assert_eq!(snapshot.events.len(), 1);
+        assert_eq!(snapshot.events[0].event_id, broadcast.event_id);
+        assert_eq!(snapshot.events[0].component, RuntimeComponent::Ddns);
+    }
+
     #[tokio::test]
     async fn persisted_state_is_restored() {
         let tmp = tempdir().unwrap();
@@ -0,0 +1,307 @@
+//! Outbound webhook notifications for alerts and cluster events.
+//!
+//! Every event has exactly one reporting node: node-local facts (quota bans, xray/cloudflared/DDNS
+//! status) are reported by the node that owns them, and cluster facts (peer reachability,
+//! membership operations) only by the current Raft leader. A short dedupe window on top of that
+//! absorbs flapping and leader hand-offs.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Mutex, broadcast::error::RecvError},
+    time::MissedTickBehavior,
+};
+
+use crate::{
+    mesh_telemetry::MeshTelemetryHandle, node_runtime::NodeRuntimeHandle, raft::app::RaftFacade,
+    state::JsonSnapshotStore,
+};
+
+mod deliver;
+mod detect;
+#[cfg(test)]
+mod tests;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+/// Identical events (same dedupe key) inside this window are delivered once.
+const DEDUPE_WINDOW: chrono::Duration = chrono::Duration::minutes(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The event itself as a JSON object.
+    Json,
+    /// A Telegram Bot API `sendMessage` body.
+    Telegram,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEventKind {
+    QuotaBanned,
+    QuotaUnbanned,
+    QuotaThresholdReached,
+    XrayStatusChanged,
+    CloudflaredStatusChanged,
+    DdnsStatusChanged,
+    NodeUnreachable,
+    NodeRecovered,
+    MembershipOperationFailed,
+}
+
+impl NotificationEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::QuotaBanned => "quota_banned",
+            Self::QuotaUnbanned => "quota_unbanned",
+            Self::QuotaThresholdReached => "quota_threshold_reached",
+            Self::XrayStatusChanged => "xray_status_changed",
+            Self::CloudflaredStatusChanged => "cloudflared_status_changed",
+            Self::DdnsStatusChanged => "ddns_status_changed",
+            Self::NodeUnreachable => "node_unreachable",
+            Self::NodeRecovered => "node_recovered",
+            Self::MembershipOperationFailed => "membership_operation_failed",
+        }
+    }
+}
+
+/// A webhook target, stored cluster-wide in desired state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationWebhook {
+    pub name: String,
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Telegram `chat_id`; required for the `telegram` format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_chat_id: Option<String>,
+    /// Bot API token for the `telegram` format. Kept out of `url`, which then only holds the API
+    /// base (`https://api.telegram.org`), so it can be redacted like other secrets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_bot_token: Option<String>,
+    /// Key for the `X-Xp-Signature` HMAC-SHA256 header on `json` deliveries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// Event kinds to deliver; empty means all.
+    #[serde(default)]
+    pub events: BTreeSet<NotificationEventKind>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl NotificationWebhook {
+    pub fn wants(&self, kind: NotificationEventKind) -> bool {
+        !self.disabled && (self.events.is_empty() || self.events.contains(&kind))
+    }
+
+    /// Where a delivery is posted.
+    pub(crate) fn delivery_url(&self) -> String {
+        match (self.format, self.telegram_bot_token.as_deref()) {
+            (WebhookFormat::Telegram, Some(token)) => {
+                format!("{}/bot{token}/sendMessage", self.url.trim_end_matches('/'))
+            }
+            _ => self.url.clone(),
+        }
+    }
+}
+
+pub fn validate_notification_webhooks(webhooks: &[NotificationWebhook]) -> Result<(), String> {
+    let mut names = BTreeSet::new();
+    for webhook in webhooks {
+        let name = webhook.name.trim();
+        if name.is_empty() {
+            return Err("webhook name is required".to_string());
+        }
+        if !names.insert(name) {
+            return Err(format!("duplicate webhook name: {name}"));
+        }
+        let url = reqwest::Url::parse(&webhook.url)
+            .map_err(|e| format!("webhook {name}: invalid url: {e}"))?;
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return Err(format!("webhook {name}: url must be an http(s) url"));
+        }
+        match webhook.format {
+            WebhookFormat::Telegram
+                if webhook
+                    .telegram_chat_id
+                    .as_deref()
+                    .is_none_or(|id| id.trim().is_empty()) =>
+            {
+                return Err(format!(
+                    "webhook {name}: telegram_chat_id is required for the telegram format"
+                ));
+            }
+            WebhookFormat::Telegram
+                if webhook
+                    .telegram_bot_token
+                    .as_deref()
+                    .is_none_or(|token| token.trim().is_empty()) =>
+            {
+                return Err(format!(
+                    "webhook {name}: telegram_bot_token is required for the telegram format"
+                ));
+            }
+            WebhookFormat::Telegram
+                if !webhook
+                    .telegram_bot_token
+                    .as_deref()
+                    .is_some_and(is_valid_telegram_bot_token) =>
+            {
+                return Err(format!(
+                    "webhook {name}: telegram_bot_token must look like <bot id>:<secret>"
+                ));
+            }
+            WebhookFormat::Telegram if !is_bot_api_base(&url) => {
+                return Err(format!(
+                    "webhook {name}: telegram url must be the Bot API base (e.g. \
+                     https://api.telegram.org); pass the token as telegram_bot_token"
+                ));
+            }
+            WebhookFormat::Telegram if webhook.secret.is_some() => {
+                return Err(format!(
+                    "webhook {name}: secret is only supported for the json format"
+                ));
+            }
+            WebhookFormat::Json if webhook.telegram_bot_token.is_some() => {
+                return Err(format!(
+                    "webhook {name}: telegram_bot_token is only supported for the telegram format"
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Bot API tokens are `<numeric bot id>:<url-safe secret>`.
+fn is_valid_telegram_bot_token(token: &str) -> bool {
+    token.split_once(':').is_some_and(|(bot_id, secret)| {
+        !bot_id.is_empty()
+            && bot_id.bytes().all(|byte| byte.is_ascii_digit())
+            && !secret.is_empty()
+            && secret
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-'))
+    })
+}
+
+/// A Bot API server root without a `bot<token>` method path or query, so the url never holds
+/// the token.
+fn is_bot_api_base(url: &reqwest::Url) -> bool {
+    url.query().is_none()
+        && url.fragment().is_none()
+        && url
+            .path_segments()
+            .is_none_or(|mut segments| !segments.any(|segment| segment.starts_with("bot")))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NotificationEvent {
+    pub event_id: String,
+    pub kind: NotificationEventKind,
+    pub occurred_at: String,
+    /// Node that observed and reported the event.
+    pub node_id: String,
+    pub node_name: String,
+    pub message: String,
+    pub details: BTreeMap<String, String>,
+    #[serde(skip)]
+    pub dedupe_key: String,
+}
+
+#[derive(Debug)]
+struct DedupeWindow {
+    seen: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl DedupeWindow {
+    fn new() -> Self {
+        Self {
+            seen: BTreeMap::new(),
+        }
+    }
+
+    /// Returns `true` the first time `key` is seen inside the window.
+    fn admit(&mut self, key: &str, now: DateTime<Utc>) -> bool {
+        self.seen.retain(|_, at| now - *at < DEDUPE_WINDOW);
+        if self.seen.contains_key(key) {
+            return false;
+        }
+        self.seen.insert(key.to_string(), now);
+        true
+    }
+}
+
+pub fn spawn_notifier(
+    node_id: String,
+    store: Arc<Mutex<JsonSnapshotStore>>,
+    raft: Arc<dyn RaftFacade>,
+    node_runtime: NodeRuntimeHandle,
+    mesh_telemetry: MeshTelemetryHandle,
+) -> tokio::task::JoinHandle<()> {
+    let client = deliver::build_client();
+    let mut runtime_events = node_runtime.subscribe();
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(POLL_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut trackers = detect::Trackers::default();
+        let mut dedupe = DedupeWindow::new();
+
+        loop {
+            let mut events = tokio::select! {
+                _ = ticker.tick() => {
+                    let is_leader = matches!(
+                        raft.metrics().borrow().state,
+                        openraft::ServerState::Leader
+                    );
+                    let peers = if is_leader {
+                        Some(mesh_telemetry.snapshot().await.peers)
+                    } else {
+                        None
+                    };
+                    let store = store.lock().await;
+                    trackers.poll(&store, &node_id, peers.as_deref(), Utc::now())
+                }
+                event = runtime_events.recv() => match event {
+                    Ok(event) => detect::runtime_status_event(&node_id, &event)
+                        .into_iter()
+                        .collect(),
+                    Err(RecvError::Lagged(_)) => Vec::new(),
+                    Err(RecvError::Closed) => break,
+                },
+            };
+            if events.is_empty() {
+                continue;
+            }
+
+            let now = Utc::now();
+            events.retain(|event| dedupe.admit(&event.dedupe_key, now));
+            let webhooks = {
+                let store = store.lock().await;
+                let node_name = store
+                    .get_node(&node_id)
+                    .map(|node| node.node_name)
+                    .unwrap_or_default();
+                for event in &mut events {
+                    event.node_name = node_name.clone();
+                }
+                store.state().notification_webhooks.clone()
+            };
+            for event in events {
+                for webhook in webhooks.iter().filter(|w| w.wants(event.kind)) {
+                    tokio::spawn(deliver::deliver_with_retry(
+                        client.clone(),
+                        webhook.clone(),
+                        event.clone(),
+                    ));
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde_json::json;
+use sha2::Sha256;
+use tracing::warn;
+
+use super::{NotificationEvent, NotificationWebhook, WebhookFormat};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_CAP: Duration = Duration::from_secs(60);
+pub(super) const SIGNATURE_HEADER: &str = "x-xp-signature";
+
+pub(super) fn build_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(format!("xp/{}", crate::version::VERSION))
+        .timeout(DELIVERY_TIMEOUT)
+        .build()
+        .expect("build reqwest client")
+}
+
+pub(super) fn render_payload(
+    webhook: &NotificationWebhook,
+    event: &NotificationEvent,
+) -> serde_json::Value {
+    match webhook.format {
+        WebhookFormat::Json => json!(event),
+        WebhookFormat::Telegram => {
+            let mut text = format!(
+                "[xp] {} on {}\n{}",
+                event.kind.as_str(),
+                if event.node_name.is_empty() {
+                    &event.node_id
+                } else {
+                    &event.node_name
+                },
+                event.message
+            );
+            for (key, value) in &event.details {
+                if !value.is_empty() {
+                    text.push_str(&format!("\n{key}: {value}"));
+                }
+            }
+            json!({
+                "chat_id": webhook.telegram_chat_id,
+                "text": text,
+                "disable_web_page_preview": true,
+            })
+        }
+    }
+}
+
+pub(super) fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts any key");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+pub(super) fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE
+        .saturating_mul(1u32 << attempt.min(16))
+        .min(RETRY_CAP)
+}
+
+/// Client errors other than 408/429 mean the target rejected the payload; retrying won't help.
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+pub(super) async fn deliver_with_retry(
+    client: reqwest::Client,
+    webhook: NotificationWebhook,
+    event: NotificationEvent,
+) -> bool {
+    deliver_with_delays(&client, &webhook, &event, retry_delay).await
+}
+
+pub(super) async fn deliver_with_delays(
+    client: &reqwest::Client,
+    webhook: &NotificationWebhook,
+    event: &NotificationEvent,
+    delay_for_attempt: impl Fn(u32) -> Duration,
+) -> bool {
+    let body = render_payload(webhook, event).to_string();
+    let url = webhook.delivery_url();
+    for attempt in 0..MAX_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(delay_for_attempt(attempt - 1)).await;
+        }
+        let mut request = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(secret) = webhook.secret.as_deref() {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+        }
+        match request.send().await {
+            Ok(res) if res.status().is_success() => return true,
+            Ok(res) if !is_retryable(res.status()) => {
+                warn!(
+                    webhook = webhook.name,
+                    event_kind = event.kind.as_str(),
+                    status = %res.status(),
+                    "notification webhook rejected event"
+                );
+                return false;
+            }
+            Ok(res) => warn!(
+                webhook = webhook.name,
+                event_kind = event.kind.as_str(),
+                attempt,
+                status = %res.status(),
+                "notification webhook delivery failed"
+            ),
+            Err(err) => warn!(
+                webhook = webhook.name,
+                event_kind = event.kind.as_str(),
+                attempt,
+                error = %err,
+                "notification webhook delivery failed"
+            ),
+        }
+    }
+    warn!(
+        webhook = webhook.name,
+        event_kind = event.kind.as_str(),
+        "notification webhook delivery gave up"
+    );
+    false
+}
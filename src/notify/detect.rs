@@ -0,0 +1,358 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{NotificationEvent, NotificationEventKind};
+use crate::{
+    id::new_ulid_string,
+    mesh_telemetry::{MeshPeerTelemetry, MeshQuality, quality_for_peer},
+    node_runtime::{NodeRuntimeEvent, NodeRuntimeEventKind, RuntimeComponent, RuntimeStatus},
+    state::{JsonSnapshotStore, MembershipOperationPhase, membership_key},
+};
+
+fn event(
+    kind: NotificationEventKind,
+    node_id: &str,
+    now: DateTime<Utc>,
+    message: String,
+    details: &[(&str, &str)],
+    dedupe_key: String,
+) -> NotificationEvent {
+    NotificationEvent {
+        event_id: new_ulid_string(),
+        kind,
+        occurred_at: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        node_id: node_id.to_string(),
+        node_name: String::new(),
+        message,
+        details: details
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        dedupe_key,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BannedMembership {
+    user_id: String,
+    endpoint_id: String,
+    banned_at: String,
+}
+
+/// Last observed state per event source. A `None` baseline means "not observed yet": the first
+/// poll only records state, so restarts and leader changes do not replay old facts.
+#[derive(Debug, Default)]
+pub(super) struct Trackers {
+    banned: Option<BTreeMap<String, BannedMembership>>,
+    /// `(cycle_start_at, threshold_percent)` per user.
+    quota_warnings: Option<BTreeMap<String, (String, u8)>>,
+    down_peers: Option<BTreeMap<String, bool>>,
+    failed_operations: Option<BTreeSet<String>>,
+}
+
+impl Trackers {
+    /// `peers` is the local mesh view and is only passed on the leader; cluster-scoped
+    /// baselines are dropped while this node is not the leader.
+    pub(super) fn poll(
+        &mut self,
+        store: &JsonSnapshotStore,
+        node_id: &str,
+        peers: Option<&[MeshPeerTelemetry]>,
+        now: DateTime<Utc>,
+    ) -> Vec<NotificationEvent> {
+        let mut events = self.poll_quota(store, node_id, now);
+        events.extend(self.poll_quota_warnings(store, node_id, now));
+        match peers {
+            Some(peers) => {
+                events.extend(self.poll_peers(store, node_id, peers, now));
+                events.extend(self.poll_membership_operations(store, node_id, now));
+            }
+            None => {
+                self.down_peers = None;
+                self.failed_operations = None;
+            }
+        }
+        events
+    }
+
+    fn poll_quota(
+        &mut self,
+        store: &JsonSnapshotStore,
+        node_id: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<NotificationEvent> {
+        let mut local_keys = BTreeSet::new();
+        let mut banned = BTreeMap::new();
+        for membership in &store.state().node_user_endpoint_memberships {
+            if membership.node_id != node_id {
+                continue;
+            }
+            let key = membership_key(&membership.user_id, &membership.endpoint_id);
+            local_keys.insert(key.clone());
+            let Some(usage) = store.get_membership_usage(&key) else {
+                continue;
+            };
+            if usage.quota_banned {
+                banned.insert(
+                    key,
+                    BannedMembership {
+                        user_id: membership.user_id.clone(),
+                        endpoint_id: membership.endpoint_id.clone(),
+                        banned_at: usage.quota_banned_at.unwrap_or_default(),
+                    },
+                );
+            }
+        }
+
+        let Some(previous) = self.banned.replace(banned.clone()) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        for (key, ban) in &banned {
+            if previous.get(key) != Some(ban) {
+                events.push(event(
+                    NotificationEventKind::QuotaBanned,
+                    node_id,
+                    now,
+                    format!(
+                        "quota exceeded; {} is blocked on {}",
+                        ban.user_id, ban.endpoint_id
+                    ),
+                    &[
+                        ("user_id", &ban.user_id),
+                        ("endpoint_id", &ban.endpoint_id),
+                        ("quota_banned_at", &ban.banned_at),
+                    ],
+                    format!("quota_banned:{key}:{}", ban.banned_at),
+                ));
+            }
+        }
+        // A deleted membership also drops its usage; that is not an unban.
+        for (key, ban) in &previous {
+            if !banned.contains_key(key) && local_keys.contains(key) {
+                events.push(event(
+                    NotificationEventKind::QuotaUnbanned,
+                    node_id,
+                    now,
+                    format!(
+                        "quota ban lifted; {} is allowed on {}",
+                        ban.user_id, ban.endpoint_id
+                    ),
+                    &[("user_id", &ban.user_id), ("endpoint_id", &ban.endpoint_id)],
+                    format!("quota_unbanned:{key}:{}", ban.banned_at),
+                ));
+            }
+        }
+        events
+    }
+
+    fn poll_quota_warnings(
+        &mut self,
+        store: &JsonSnapshotStore,
+        node_id: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<NotificationEvent> {
+        let warnings = store
+            .quota_warnings()
+            .iter()
+            .map(|(user_id, warning)| {
+                (
+                    user_id.clone(),
+                    (warning.cycle_start_at.clone(), warning.threshold_percent),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        let Some(previous) = self.quota_warnings.replace(warnings) else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        for (user_id, warning) in store.quota_warnings() {
+            let reached = (warning.cycle_start_at.clone(), warning.threshold_percent);
+            if previous.get(user_id) == Some(&reached) {
+                continue;
+            }
+            let threshold = warning.threshold_percent.to_string();
+            let remaining = warning.remaining_bytes.to_string();
+            events.push(event(
+                NotificationEventKind::QuotaThresholdReached,
+                node_id,
+                now,
+                format!("{user_id} has used {threshold}% of the node quota"),
+                &[
+                    ("user_id", user_id),
+                    ("threshold_percent", &threshold),
+                    ("remaining_bytes", &remaining),
+                    (
+                        "projected_exhaustion_at",
+                        warning
+                            .projected_exhaustion_at
+                            .as_deref()
+                            .unwrap_or_default(),
+                    ),
+                ],
+                format!(
+                    "quota_threshold_reached:{user_id}:{}:{threshold}",
+                    warning.cycle_start_at
+                ),
+            ));
+        }
+        events
+    }
+
+    fn poll_peers(
+        &mut self,
+        store: &JsonSnapshotStore,
+        node_id: &str,
+        peers: &[MeshPeerTelemetry],
+        now: DateTime<Utc>,
+    ) -> Vec<NotificationEvent> {
+        let baseline = self.down_peers.is_none();
+        let down_peers = self.down_peers.get_or_insert_with(BTreeMap::new);
+        down_peers.retain(|peer_id, _| store.state().nodes.contains_key(peer_id));
+
+        let mut events = Vec::new();
+        for peer in peers {
+            if peer.peer_id == node_id || !store.state().nodes.contains_key(&peer.peer_id) {
+                continue;
+            }
+            // `Unknown` means "no recent samples"; keep the last verdict until there are some.
+            let down = match quality_for_peer(peer, now) {
+                MeshQuality::Down => true,
+                MeshQuality::Unknown => continue,
+                MeshQuality::Good | MeshQuality::Slow | MeshQuality::Unstable => false,
+            };
+            let was_down = down_peers.insert(peer.peer_id.clone(), down);
+            if baseline || was_down.is_none_or(|was_down| was_down == down) {
+                continue;
+            }
+            let peer_name = store
+                .get_node(&peer.peer_id)
+                .map(|node| node.node_name)
+                .unwrap_or_default();
+            let (kind, message) = if down {
+                (
+                    NotificationEventKind::NodeUnreachable,
+                    format!("node {peer_name} ({}) is unreachable", peer.peer_id),
+                )
+            } else {
+                (
+                    NotificationEventKind::NodeRecovered,
+                    format!("node {peer_name} ({}) is reachable again", peer.peer_id),
+                )
+            };
+            events.push(event(
+                kind,
+                node_id,
+                now,
+                message,
+                &[
+                    ("peer_node_id", &peer.peer_id),
+                    ("peer_node_name", &peer_name),
+                ],
+                format!("{}:{}", kind.as_str(), peer.peer_id),
+            ));
+        }
+        events
+    }
+
+    fn poll_membership_operations(
+        &mut self,
+        store: &JsonSnapshotStore,
+        node_id: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<NotificationEvent> {
+        let failed = store
+            .state()
+            .membership_operations
+            .values()
+            .filter(|operation| {
+                matches!(
+                    operation.phase,
+                    MembershipOperationPhase::Blocked | MembershipOperationPhase::Expired
+                )
+            })
+            .collect::<Vec<_>>();
+        let ids = failed
+            .iter()
+            .map(|operation| operation.operation_id.clone())
+            .collect::<BTreeSet<_>>();
+        let Some(previous) = self.failed_operations.replace(ids) else {
+            return Vec::new();
+        };
+
+        failed
+            .into_iter()
+            .filter(|operation| !previous.contains(&operation.operation_id))
+            .map(|operation| {
+                let kind = snake_case(&operation.kind);
+                let phase = snake_case(&operation.phase);
+                let target = operation.node_id.clone().unwrap_or_default();
+                let evidence = operation.evidence.clone().unwrap_or_default();
+                event(
+                    NotificationEventKind::MembershipOperationFailed,
+                    node_id,
+                    now,
+                    format!("membership operation {kind} for node {target} ended as {phase}"),
+                    &[
+                        ("operation_id", &operation.operation_id),
+                        ("operation_kind", &kind),
+                        ("phase", &phase),
+                        ("target_node_id", &target),
+                        ("evidence", &evidence),
+                    ],
+                    format!("membership_operation_failed:{}", operation.operation_id),
+                )
+            })
+            .collect()
+    }
+}
+
+fn snake_case<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn is_impaired(status: RuntimeStatus) -> bool {
+    matches!(status, RuntimeStatus::Degraded | RuntimeStatus::Down)
+}
+
+/// Maps a local runtime status change to an event when it enters or leaves an impaired state;
+/// start-up transitions such as `unknown -> up` are not worth a page.
+pub(super) fn runtime_status_event(
+    node_id: &str,
+    runtime_event: &NodeRuntimeEvent,
+) -> Option<NotificationEvent> {
+    if runtime_event.kind != NodeRuntimeEventKind::StatusChanged {
+        return None;
+    }
+    let kind = match runtime_event.component {
+        RuntimeComponent::Xray => NotificationEventKind::XrayStatusChanged,
+        RuntimeComponent::Cloudflared => NotificationEventKind::CloudflaredStatusChanged,
+        RuntimeComponent::Ddns => NotificationEventKind::DdnsStatusChanged,
+        RuntimeComponent::Xp => return None,
+    };
+    let from = runtime_event.from_status?;
+    let to = runtime_event.to_status?;
+    if !is_impaired(from) && !is_impaired(to) {
+        return None;
+    }
+    let component = runtime_event.component.as_str();
+    let mut notification = event(
+        kind,
+        node_id,
+        Utc::now(),
+        runtime_event.message.clone(),
+        &[
+            ("component", component),
+            ("from_status", from.as_str()),
+            ("to_status", to.as_str()),
+        ],
+        format!("{component}:{node_id}:{}->{}", from.as_str(), to.as_str()),
+    );
+    notification.occurred_at = runtime_event.occurred_at.clone();
+    Some(notification)
+}
@@ -0,0 +1,339 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use pretty_assertions::assert_eq;
+use tempfile::TempDir;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use super::{
+    DedupeWindow, NotificationEvent, NotificationEventKind, NotificationWebhook, WebhookFormat,
+    deliver::{SIGNATURE_HEADER, deliver_with_delays, render_payload, sign},
+    detect::{Trackers, runtime_status_event},
+    validate_notification_webhooks,
+};
+use crate::{
+    node_runtime::{NodeRuntimeEvent, NodeRuntimeEventKind, RuntimeComponent, RuntimeStatus},
+    state::{
+        JsonSnapshotStore, NodeUserEndpointMembership, StoreInit, UserQuotaWarning, membership_key,
+    },
+};
+
+fn webhook(format: WebhookFormat, url: String) -> NotificationWebhook {
+    NotificationWebhook {
+        name: "ops".to_string(),
+        url,
+        format,
+        telegram_chat_id: None,
+        telegram_bot_token: None,
+        secret: None,
+        events: Default::default(),
+        disabled: false,
+    }
+}
+
+fn sample_event() -> NotificationEvent {
+    NotificationEvent {
+        event_id: "01J00000000000000000000000".to_string(),
+        kind: NotificationEventKind::QuotaBanned,
+        occurred_at: "2026-01-01T00:00:00Z".to_string(),
+        node_id: xp_test_fixtures::identifier_ulid_d().to_string(),
+        node_name: xp_test_fixtures::label_node1_variant2().to_string(),
+        message: "quota exceeded".to_string(),
+        details: [(
+            "user_id".to_string(),
+            xp_test_fixtures::primary_user_id().to_string(),
+        )]
+        .into(),
+        dedupe_key: "quota_banned:k".to_string(),
+    }
+}
+
+fn local_store(tmp: &TempDir) -> JsonSnapshotStore {
+    let node_id = xp_test_fixtures::identifier_ulid_d();
+    let mut store = JsonSnapshotStore::load_or_init(StoreInit {
+        data_dir: tmp.path().to_path_buf(),
+        bootstrap_node_id: Some(node_id.to_string()),
+        bootstrap_node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+        bootstrap_access_host: xp_test_fixtures::label_empty().to_owned(),
+        bootstrap_api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+    })
+    .unwrap();
+    store
+        .state_mut()
+        .node_user_endpoint_memberships
+        .insert(NodeUserEndpointMembership {
+            user_id: xp_test_fixtures::primary_user_id().to_owned(),
+            node_id: node_id.to_owned(),
+            endpoint_id: xp_test_fixtures::primary_endpoint_id().to_owned(),
+        });
+    store
+}
+
+fn primary_membership_key() -> String {
+    membership_key(
+        xp_test_fixtures::primary_user_id(),
+        xp_test_fixtures::primary_endpoint_id(),
+    )
+}
+
+#[test]
+fn validate_rejects_incomplete_targets() {
+    let mut telegram = webhook(
+        WebhookFormat::Telegram,
+        "https://api.telegram.org".to_string(),
+    );
+    telegram.telegram_bot_token = Some("123:abc".to_string());
+    assert!(validate_notification_webhooks(std::slice::from_ref(&telegram)).is_err());
+    telegram.telegram_chat_id = Some("-100123".to_string());
+    assert!(validate_notification_webhooks(std::slice::from_ref(&telegram)).is_ok());
+    telegram.telegram_bot_token = Some("123/abc".to_string());
+    let err = validate_notification_webhooks(std::slice::from_ref(&telegram)).unwrap_err();
+    assert!(err.contains("telegram_bot_token"), "{err}");
+    telegram.telegram_bot_token = None;
+    let err = validate_notification_webhooks(std::slice::from_ref(&telegram)).unwrap_err();
+    assert!(err.contains("telegram_bot_token"), "{err}");
+    telegram.telegram_bot_token = Some("123:abc".to_string());
+    telegram.url = "https://api.telegram.org/bot123:abc/sendMessage".to_string();
+    let err = validate_notification_webhooks(std::slice::from_ref(&telegram)).unwrap_err();
+    assert!(err.contains("Bot API base"), "{err}");
+
+    let json = webhook(
+        WebhookFormat::Json,
+        "ftp://example.invalid/hook".to_string(),
+    );
+    assert!(validate_notification_webhooks(&[json]).is_err());
+
+    let json = webhook(
+        WebhookFormat::Json,
+        "https://example.invalid/hook".to_string(),
+    );
+    let err = validate_notification_webhooks(&[json.clone(), json]).unwrap_err();
+    assert!(err.contains("duplicate"), "{err}");
+}
+
+#[test]
+fn dedupe_window_admits_each_key_once_per_window() {
+    let mut dedupe = DedupeWindow::new();
+    let now = Utc::now();
+    assert!(dedupe.admit("a", now));
+    assert!(!dedupe.admit("a", now + chrono::Duration::minutes(1)));
+    assert!(dedupe.admit("b", now + chrono::Duration::minutes(1)));
+    assert!(dedupe.admit("a", now + chrono::Duration::minutes(6)));
+}
+
+#[test]
+fn runtime_status_events_only_fire_for_impaired_transitions() {
+    let node_id = xp_test_fixtures::identifier_ulid_d();
+    let status_changed = |component, from, to| NodeRuntimeEvent {
+        event_id: "e".to_string(),
+        occurred_at: "2026-01-01T00:00:00Z".to_string(),
+        component,
+        kind: NodeRuntimeEventKind::StatusChanged,
+        message: "status changed".to_string(),
+        from_status: Some(from),
+        to_status: Some(to),
+    };
+
+    let startup = status_changed(
+        RuntimeComponent::Xray,
+        RuntimeStatus::Unknown,
+        RuntimeStatus::Up,
+    );
+    assert_eq!(runtime_status_event(node_id, &startup), None);
+
+    let down = status_changed(
+        RuntimeComponent::Xray,
+        RuntimeStatus::Up,
+        RuntimeStatus::Down,
+    );
+    let event = runtime_status_event(node_id, &down).unwrap();
+    assert_eq!(event.kind, NotificationEventKind::XrayStatusChanged);
+    assert_eq!(event.occurred_at, "2026-01-01T00:00:00Z");
+    assert_eq!(event.details["to_status"], "down");
+
+    let degraded = status_changed(
+        RuntimeComponent::Ddns,
+        RuntimeStatus::Up,
+        RuntimeStatus::Degraded,
+    );
+    assert_eq!(
+        runtime_status_event(node_id, &degraded).unwrap().kind,
+        NotificationEventKind::DdnsStatusChanged
+    );
+}
+
+#[test]
+fn quota_tracker_reports_bans_and_unbans_after_baseline() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut store = local_store(&tmp);
+    let node_id = xp_test_fixtures::identifier_ulid_d();
+    let key = primary_membership_key();
+    let now = Utc::now();
+    let mut trackers = Trackers::default();
+
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+
+    store
+        .set_quota_banned(&key, "2026-01-01T00:00:00Z".to_string())
+        .unwrap();
+    let events = trackers.poll(&store, node_id, None, now);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, NotificationEventKind::QuotaBanned);
+    assert_eq!(
+        events[0].dedupe_key,
+        format!("quota_banned:{key}:2026-01-01T00:00:00Z")
+    );
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+
+    store
+        .update_usage(|usage| {
+            let entry = usage.memberships.get_mut(&key).unwrap();
+            entry.quota_banned = false;
+            entry.quota_banned_at = None;
+        })
+        .unwrap();
+    let events = trackers.poll(&store, node_id, None, now);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, NotificationEventKind::QuotaUnbanned);
+}
+
+#[test]
+fn quota_tracker_ignores_deleted_memberships() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut store = local_store(&tmp);
+    let node_id = xp_test_fixtures::identifier_ulid_d();
+    let key = primary_membership_key();
+    let now = Utc::now();
+    store
+        .set_quota_banned(&key, "2026-01-01T00:00:00Z".to_string())
+        .unwrap();
+    let mut trackers = Trackers::default();
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+
+    store.state_mut().node_user_endpoint_memberships.clear();
+    store.clear_membership_usage(&key).unwrap();
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+}
+
+#[test]
+fn quota_warning_tracker_reports_each_new_threshold_once() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut store = local_store(&tmp);
+    let node_id = xp_test_fixtures::identifier_ulid_d();
+    let user_id = xp_test_fixtures::primary_user_id();
+    let now = Utc::now();
+    let mut trackers = Trackers::default();
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+
+    let reach = |store: &mut JsonSnapshotStore, threshold_percent: u8| {
+        store
+            .update_usage(|usage| {
+                usage.quota_warnings.insert(
+                    user_id.to_string(),
+                    UserQuotaWarning {
+                        cycle_start_at: "2026-02-02T00:00:00+00:00".to_string(),
+                        reached_percents: [threshold_percent].into(),
+                        threshold_percent,
+                        reached_at: "2026-02-02T12:00:00+00:00".to_string(),
+                        quota_bytes: 700,
+                        used_bytes: 600,
+                        remaining_bytes: 100,
+                        projected_exhaustion_at: None,
+                    },
+                );
+            })
+            .unwrap();
+    };
+
+    reach(&mut store, 80);
+    let events = trackers.poll(&store, node_id, None, now);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, NotificationEventKind::QuotaThresholdReached);
+    assert_eq!(events[0].details["threshold_percent"], "80");
+    assert_eq!(events[0].details["remaining_bytes"], "100");
+    assert_eq!(
+        events[0].dedupe_key,
+        format!("quota_threshold_reached:{user_id}:2026-02-02T00:00:00+00:00:80")
+    );
+    assert_eq!(trackers.poll(&store, node_id, None, now), Vec::new());
+
+    reach(&mut store, 95);
+    let events = trackers.poll(&store, node_id, None, now);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].details["threshold_percent"], "95");
+}
+
+#[test]
+fn telegram_payload_uses_chat_id_and_readable_text() {
+    let mut target = webhook(
+        WebhookFormat::Telegram,
+        "https://api.telegram.org".to_string(),
+    );
+    target.telegram_bot_token = Some("123:abc".to_string());
+    target.telegram_chat_id = Some("-100123".to_string());
+    let payload = render_payload(&target, &sample_event());
+
+    assert_eq!(payload["chat_id"], "-100123");
+    assert_eq!(
+        payload["text"],
+        format!(
+            "[xp] quota_banned on {}\nquota exceeded\nuser_id: {}",
+            xp_test_fixtures::label_node1_variant2(),
+            xp_test_fixtures::primary_user_id()
+        )
+    );
+}
+
+#[tokio::test]
+async fn json_delivery_retries_server_errors_and_signs_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(header_exists(SIGNATURE_HEADER))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut target = webhook(WebhookFormat::Json, format!("{}/hook", server.uri()));
+    target.secret = Some("s3cret".to_string());
+    let event = sample_event();
+    let delivered =
+        deliver_with_delays(&reqwest::Client::new(), &target, &event, |_| Duration::ZERO).await;
+    assert!(delivered);
+
+    let requests = server.received_requests().await.unwrap();
+    let last = requests.last().unwrap();
+    assert_eq!(
+        last.headers[SIGNATURE_HEADER].to_str().unwrap(),
+        sign("s3cret", &last.body)
+    );
+    let body: serde_json::Value = serde_json::from_slice(&last.body).unwrap();
+    assert_eq!(body["kind"], "quota_banned");
+    assert!(body.get("dedupe_key").is_none());
+}
+
+#[tokio::test]
+async fn delivery_does_not_retry_client_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let target = webhook(WebhookFormat::Json, format!("{}/hook", server.uri()));
+    let delivered = deliver_with_delays(&reqwest::Client::new(), &target, &sample_event(), |_| {
+        Duration::ZERO
+    })
+    .await;
+    assert!(!delivered);
+}
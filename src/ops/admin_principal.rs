@@ -0,0 +1,95 @@
+use axum::http::Method;
+
+use super::{
+    cli::{AdminPrincipalCreateArgs, AdminPrincipalListArgs, AdminPrincipalTargetArgs, ExitError},
+    paths::Paths,
+    xp::{internal_json_request, local_internal_ops_client},
+};
+
+async fn principal_request(
+    paths: &Paths,
+    api_base_url: &str,
+    method: Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<(), ExitError> {
+    let (client, auth) = local_internal_ops_client(paths, api_base_url)?;
+    let body = body
+        .map(|body| serde_json::to_vec(&body))
+        .transpose()
+        .map_err(|error| ExitError::new(5, format!("encode principal request: {error}")))?;
+    let response: serde_json::Value =
+        internal_json_request(&client, api_base_url, &auth, method, path, body).await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&response)
+            .map_err(|error| ExitError::new(5, format!("encode principal response: {error}")))?
+    );
+    Ok(())
+}
+
+pub(crate) async fn cmd_principal_list(
+    paths: Paths,
+    args: AdminPrincipalListArgs,
+) -> Result<(), ExitError> {
+    principal_request(
+        &paths,
+        &args.api_base_url,
+        Method::GET,
+        "/api/admin/_internal/principals",
+        None,
+    )
+    .await
+}
+
+pub(crate) async fn cmd_principal_create(
+    paths: Paths,
+    args: AdminPrincipalCreateArgs,
+) -> Result<(), ExitError> {
+    principal_request(
+        &paths,
+        &args.api_base_url,
+        Method::POST,
+        "/api/admin/_internal/principals",
+        Some(serde_json::json!({ "name": args.name, "scope": args.scope })),
+    )
+    .await?;
+    eprintln!("Store the token now; it cannot be shown again.");
+    Ok(())
+}
+
+pub(crate) async fn cmd_principal_rotate(
+    paths: Paths,
+    args: AdminPrincipalTargetArgs,
+) -> Result<(), ExitError> {
+    principal_request(
+        &paths,
+        &args.api_base_url,
+        Method::POST,
+        &format!(
+            "/api/admin/_internal/principals/{}/rotate-token",
+            args.principal_id
+        ),
+        None,
+    )
+    .await?;
+    eprintln!("Store the token now; it cannot be shown again.");
+    Ok(())
+}
+
+pub(crate) async fn cmd_principal_revoke(
+    paths: Paths,
+    args: AdminPrincipalTargetArgs,
+) -> Result<(), ExitError> {
+    principal_request(
+        &paths,
+        &args.api_base_url,
+        Method::POST,
+        &format!(
+            "/api/admin/_internal/principals/{}/revoke",
+            args.principal_id
+        ),
+        None,
+    )
+    .await
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use axum::http::Method;
+
+use super::{
+    cli::{BackupCreateArgs, BackupRestoreArgs, ExitError},
+    paths::Paths,
+    xp::{internal_json_request, local_internal_ops_client},
+};
+use crate::backup::BackupArtifact;
+
+pub(crate) async fn cmd_backup_create(
+    paths: Paths,
+    args: BackupCreateArgs,
+) -> Result<(), ExitError> {
+    let (client, auth) = local_internal_ops_client(&paths, &args.api_base_url)?;
+    let artifact: BackupArtifact = internal_json_request(
+        &client,
+        &args.api_base_url,
+        &auth,
+        Method::GET,
+        "/api/admin/_internal/backup",
+        None,
+    )
+    .await?;
+    let mut bytes = serde_json::to_vec_pretty(&artifact)
+        .map_err(|error| ExitError::new(5, format!("encode backup: {error}")))?;
+    bytes.push(b'\n');
+    crate::cluster_metadata::write_atomic_private(&args.output, &bytes).map_err(|error| {
+        ExitError::new(
+            5,
+            format!("write backup {}: {error}", args.output.display()),
+        )
+    })?;
+    eprintln!(
+        "Wrote backup of cluster {} to {} (signer {}).",
+        artifact.cluster_id,
+        args.output.display(),
+        artifact.signer_public_key
+    );
+    eprintln!("The backup holds subscription tokens and endpoint keys; store it like a secret.");
+    Ok(())
+}
+
+pub(crate) async fn cmd_backup_restore(
+    paths: Paths,
+    args: BackupRestoreArgs,
+) -> Result<(), ExitError> {
+    let raw = std::fs::read(&args.input).map_err(|error| {
+        ExitError::new(2, format!("read backup {}: {error}", args.input.display()))
+    })?;
+    let artifact: BackupArtifact = serde_json::from_slice(&raw)
+        .map_err(|error| ExitError::new(2, format!("invalid_input: parse backup: {error}")))?;
+    let node_map = parse_node_map(&args.node_map)?;
+    if args.allow_untrusted_signer {
+        eprintln!(
+            "warning: accepting backup signer {} without checking it against a trusted key",
+            artifact.signer_public_key
+        );
+    }
+    let body = serde_json::to_vec(&serde_json::json!({
+        "artifact": artifact,
+        "node_map": node_map,
+        "dry_run": args.dry_run,
+        "trusted_signer": args.trusted_signer,
+        "allow_untrusted_signer": args.allow_untrusted_signer,
+    }))
+    .map_err(|error| ExitError::new(5, format!("encode restore request: {error}")))?;
+
+    let (client, auth) = local_internal_ops_client(&paths, &args.api_base_url)?;
+    let report: serde_json::Value = internal_json_request(
+        &client,
+        &args.api_base_url,
+        &auth,
+        Method::POST,
+        "/api/admin/_internal/backup/restore",
+        Some(body),
+    )
+    .await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report)
+            .map_err(|error| ExitError::new(5, format!("encode restore report: {error}")))?
+    );
+    let has_conflicts = report["conflicts"]
+        .as_array()
+        .is_some_and(|conflicts| !conflicts.is_empty());
+    if args.dry_run && has_conflicts {
+        return Err(ExitError::new(3, "restore_conflicts: see the report above"));
+    }
+    Ok(())
+}
+
+fn parse_node_map(entries: &[String]) -> Result<BTreeMap<String, String>, ExitError> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .map(|(from, to)| (from.trim().to_string(), to.trim().to_string()))
+                .filter(|(from, to)| !from.is_empty() && !to.is_empty())
+                .ok_or_else(|| {
+                    ExitError::new(
+                        2,
+                        format!(
+                            "invalid_input: --node-map expects OLD_NODE_ID=NEW_NODE_ID, got {entry}"
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
@@ -1,16 +1,20 @@
+use crate::ops::admin_principal;
 use crate::ops::admin_token;
+use crate::ops::backup;
 use crate::ops::cloudflare;
 use crate::ops::container;
 use crate::ops::deploy;
 use crate::ops::init;
 use crate::ops::install;
 use crate::ops::membership_lifecycle;
+use crate::ops::migrate;
 use crate::ops::mihomo;
 use crate::ops::paths::Paths;
 use crate::ops::preflight;
 use crate::ops::status;
 use crate::ops::tui;
 use crate::ops::upgrade;
+use crate::ops::users;
 use crate::ops::xp;
 use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
@@ -57,6 +61,15 @@ pub enum Command {
     #[command(subcommand)]
     Mihomo(MihomoCommand),
 
+    #[command(subcommand)]
+    Backup(BackupCommand),
+
+    #[command(subcommand)]
+    Users(UsersCommand),
+
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+
     Status(StatusArgs),
     Tui(TuiArgs),
 }
@@ -154,6 +167,45 @@ pub struct InternalAuthV2CutoverMarkerArgs {
 pub enum AdminTokenCommand {
     Show(AdminTokenShowArgs),
     Set(AdminTokenSetArgs),
+    /// List named admin principals (token hashes are never shown).
+    PrincipalList(AdminPrincipalListArgs),
+    /// Create a named admin principal and print its token once.
+    PrincipalCreate(AdminPrincipalCreateArgs),
+    /// Issue a new token for a principal; the previous token stops working.
+    PrincipalRotate(AdminPrincipalTargetArgs),
+    /// Revoke a principal's token.
+    PrincipalRevoke(AdminPrincipalTargetArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AdminPrincipalListArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AdminPrincipalCreateArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// Operator name, unique among active principals.
+    #[arg(long)]
+    pub name: String,
+
+    #[arg(long, value_enum)]
+    pub scope: crate::admin_principal::AdminScope,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct AdminPrincipalTargetArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    #[arg(long)]
+    pub principal_id: String,
 }
 
 #[derive(Args, Debug, Clone)]
@@ -354,6 +406,9 @@ pub struct DeployArgs {
     #[command(flatten)]
     pub ddns_toggle: DdnsToggle,
 
+    #[command(flatten)]
+    pub ddns_provider: DdnsProviderArgs,
+
     /// Enable inbound IP Geo enrichment through the default country.is origin.
     ///
     /// When omitted, deploy preserves an existing XP_IP_GEO_ENABLED value and
@@ -468,6 +523,43 @@ impl DdnsToggle {
     }
 }
 
+/// DDNS backend selection for `--ddns`; omitted values keep what `/etc/xp/xp.env` already has.
+#[derive(Args, Debug, Clone, Default)]
+pub struct DdnsProviderArgs {
+    /// DDNS backend (defaults to the existing XP_DDNS_PROVIDER, then cloudflare).
+    #[arg(long = "ddns-provider", value_enum, value_name = "PROVIDER")]
+    pub provider: Option<crate::config::DdnsProviderKind>,
+
+    #[arg(long = "ddns-rfc2136-server", value_name = "HOST[:PORT]")]
+    pub rfc2136_server: Option<String>,
+
+    #[arg(long = "ddns-rfc2136-zone", value_name = "ZONE")]
+    pub rfc2136_zone: Option<String>,
+
+    #[arg(long = "ddns-tsig-key-name", value_name = "NAME")]
+    pub tsig_key_name: Option<String>,
+
+    #[arg(long = "ddns-tsig-algorithm", value_enum, value_name = "ALGORITHM")]
+    pub tsig_algorithm: Option<crate::config::TsigAlgorithm>,
+
+    #[arg(long = "ddns-http-url-template", value_name = "URL")]
+    pub http_url_template: Option<String>,
+
+    #[arg(long = "ddns-http-method", value_enum, value_name = "METHOD")]
+    pub http_method: Option<crate::config::DdnsHttpMethod>,
+
+    #[arg(long = "ddns-http-body-template", value_name = "TEMPLATE")]
+    pub http_body_template: Option<String>,
+
+    #[arg(long = "ddns-http-success-regex", value_name = "REGEX")]
+    pub http_success_regex: Option<String>,
+
+    /// File with the TSIG secret (rfc2136) or API token (http); copied to the xp-readable
+    /// runtime file.
+    #[arg(long = "ddns-secret-file", value_name = "PATH")]
+    pub secret_file: Option<PathBuf>,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct EnableServicesToggle {
     #[arg(long, conflicts_with = "no_enable_services")]
@@ -489,6 +581,153 @@ pub enum CloudflareCommand {
     Provision(CloudflareProvisionArgs),
 }
 
+#[derive(Subcommand, Debug)]
+pub enum BackupCommand {
+    /// Export the cluster desired state as a signed backup file.
+    Create(BackupCreateArgs),
+    /// Seed this cluster from a backup file; use --dry-run to list conflicts first.
+    Restore(BackupRestoreArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupCreateArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// Backup file to write (created with mode 0600).
+    #[arg(long, value_name = "PATH")]
+    pub output: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BackupRestoreArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// Backup file written by `xp-ops backup create`.
+    #[arg(long, value_name = "PATH")]
+    pub input: PathBuf,
+
+    /// Map a backed-up node to a node of this cluster; repeatable.
+    #[arg(long, value_name = "OLD_NODE_ID=NEW_NODE_ID")]
+    pub node_map: Vec<String>,
+
+    /// Only report the node mapping and conflicts.
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Hex public key the backup must be signed with, as printed by `xp-ops backup create`.
+    #[arg(
+        long,
+        value_name = "HEX",
+        required_unless_present = "allow_untrusted_signer"
+    )]
+    pub trusted_signer: Option<String>,
+
+    /// Accept a backup signed by any key. Anyone can re-sign an edited backup; only use this
+    /// when the file's origin was verified some other way.
+    #[arg(long, conflicts_with = "trusted_signer")]
+    pub allow_untrusted_signer: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UsersCommand {
+    /// Write every user with access and quota weights to a CSV or JSON file.
+    Export(UsersExportArgs),
+    /// Create or update users from a CSV or JSON file; use --dry-run to validate first.
+    Import(UsersImportArgs),
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsersFileFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct UsersExportArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// File to write (created with mode 0600).
+    #[arg(long, value_name = "PATH")]
+    pub output: PathBuf,
+
+    /// Defaults to csv for a `.csv` file and json otherwise.
+    #[arg(long, value_enum)]
+    pub format: Option<UsersFileFormat>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct UsersImportArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// CSV or JSON file in the `xp-ops users export` layout.
+    #[arg(long, value_name = "PATH")]
+    pub input: PathBuf,
+
+    /// Defaults to csv for a `.csv` file and json otherwise.
+    #[arg(long, value_enum)]
+    pub format: Option<UsersFileFormat>,
+
+    /// Only validate and report what would change.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MigrateCommand {
+    /// Import inbounds and clients from a 3x-ui SQLite database.
+    #[command(name = "3x-ui")]
+    ThreeXUi(MigrateThreeXUiArgs),
+    /// Import inbounds and users from a Marzban users export and xray config.
+    Marzban(MigrateMarzbanArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct MigrateTargetArgs {
+    /// Local xp API base URL.
+    #[arg(long, value_name = "ORIGIN", default_value = "http://127.0.0.1:62416")]
+    pub api_base_url: String,
+
+    /// Node id or name that receives the migrated inbounds.
+    #[arg(long, value_name = "NODE")]
+    pub node: String,
+
+    /// Only report the endpoints, users and issues.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct MigrateThreeXUiArgs {
+    /// Path to `x-ui.db`; opened read-only.
+    #[arg(long, value_name = "PATH")]
+    pub db: PathBuf,
+
+    #[command(flatten)]
+    pub target: MigrateTargetArgs,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct MigrateMarzbanArgs {
+    /// JSON from Marzban's `GET /api/users`.
+    #[arg(long, value_name = "PATH")]
+    pub users: PathBuf,
+
+    /// Marzban's `xray_config.json`.
+    #[arg(long, value_name = "PATH")]
+    pub xray_config: PathBuf,
+
+    #[command(flatten)]
+    pub target: MigrateTargetArgs,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum MihomoCommand {
     Redact(MihomoRedactArgs),
@@ -659,6 +898,18 @@ pub async fn run() -> i32 {
         Some(Command::AdminToken(cmd)) => match cmd {
             AdminTokenCommand::Show(args) => admin_token::cmd_admin_token_show(paths, args).await,
             AdminTokenCommand::Set(args) => admin_token::cmd_admin_token_set(paths, args).await,
+            AdminTokenCommand::PrincipalList(args) => {
+                admin_principal::cmd_principal_list(paths, args).await
+            }
+            AdminTokenCommand::PrincipalCreate(args) => {
+                admin_principal::cmd_principal_create(paths, args).await
+            }
+            AdminTokenCommand::PrincipalRotate(args) => {
+                admin_principal::cmd_principal_rotate(paths, args).await
+            }
+            AdminTokenCommand::PrincipalRevoke(args) => {
+                admin_principal::cmd_principal_revoke(paths, args).await
+            }
         },
         Some(Command::Cloudflare(cmd)) => match cmd {
             CloudflareCommand::Token(token) => match token.command {
@@ -673,6 +924,18 @@ pub async fn run() -> i32 {
         Some(Command::Mihomo(cmd)) => match cmd {
             MihomoCommand::Redact(args) => mihomo::cmd_mihomo_redact(paths, args).await,
         },
+        Some(Command::Backup(cmd)) => match cmd {
+            BackupCommand::Create(args) => backup::cmd_backup_create(paths, args).await,
+            BackupCommand::Restore(args) => backup::cmd_backup_restore(paths, args).await,
+        },
+        Some(Command::Migrate(cmd)) => match cmd {
+            MigrateCommand::ThreeXUi(args) => migrate::cmd_migrate_three_x_ui(paths, args).await,
+            MigrateCommand::Marzban(args) => migrate::cmd_migrate_marzban(paths, args).await,
+        },
+        Some(Command::Users(cmd)) => match cmd {
+            UsersCommand::Export(args) => users::cmd_users_export(paths, args).await,
+            UsersCommand::Import(args) => users::cmd_users_import(paths, args).await,
+        },
         Some(Command::Status(args)) => status::cmd_status(paths, args).await,
         Some(Command::Tui(_args)) => tui::cmd_tui(paths).await,
         None => tui::cmd_tui(paths).await,
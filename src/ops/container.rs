@@ -32,7 +32,7 @@ mod admin_token_sync;
 mod container_managed_default;
 mod runtime_env;
 use admin_token_sync::reconcile_configured_admin_token_hash;
-use runtime_env::build_runtime_env;
+use runtime_env::{build_runtime_env, non_cloudflare_ddns_enabled};
 
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
@@ -456,6 +456,11 @@ async fn build_ddns_spec(
     if !bool_env(env_map, "XP_CLOUDFLARE_DDNS_ENABLED", false)? {
         return Ok(None);
     }
+    if non_cloudflare_ddns_enabled(env_map) {
+        // RFC 2136 / HTTP providers read their own XP_DDNS_* settings and secret files.
+        validate_hostname(&access_host.to_ascii_lowercase())?;
+        return Ok(None);
+    }
 
     validate_hostname(&access_host.to_ascii_lowercase())?;
     let token_file = optional_env(env_map, "XP_CLOUDFLARE_DDNS_TOKEN_FILE")
@@ -1686,6 +1691,10 @@ fn render_dry_run(spec: &ContainerSpec, binaries: &BinaryPaths) {
         eprintln!("  - ddns: enabled");
         eprintln!("    - zone_id: {}", ddns.zone_id);
         eprintln!("    - token_file: {}", ddns.token_file.display());
+    } else if non_cloudflare_ddns_enabled(&spec.runtime_env) {
+        eprintln!("  - ddns: enabled");
+        let provider = optional_env(&spec.runtime_env, "XP_DDNS_PROVIDER").unwrap_or_default();
+        eprintln!("    - provider: {provider}");
     } else {
         eprintln!("  - ddns: disabled");
     }
@@ -26,6 +26,14 @@ pub(super) fn build_runtime_env(
             out.insert(key.to_string(), value);
         }
     }
+    for (key, value) in env_map {
+        if key.starts_with("XP_DDNS_") && !value.trim().is_empty() {
+            out.insert(key.clone(), value.clone());
+        }
+    }
+    if ddns.is_none() && non_cloudflare_ddns_enabled(env_map) {
+        out.insert("XP_CLOUDFLARE_DDNS_ENABLED".to_string(), "true".to_string());
+    }
     if let Some(ddns) = ddns {
         out.insert("XP_CLOUDFLARE_DDNS_ENABLED".to_string(), "true".to_string());
         out.insert(
@@ -50,3 +58,9 @@ pub(super) fn build_runtime_env(
     }
     out
 }
+
+pub(super) fn non_cloudflare_ddns_enabled(env_map: &BTreeMap<String, String>) -> bool {
+    bool_env(env_map, "XP_CLOUDFLARE_DDNS_ENABLED", false).unwrap_or(false)
+        && optional_env(env_map, "XP_DDNS_PROVIDER")
+            .is_some_and(|provider| !provider.eq_ignore_ascii_case("cloudflare"))
+}
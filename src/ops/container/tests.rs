@@ -492,6 +492,33 @@ async fn container_runtime_env_includes_vless_canary_settings() {
     );
 }
 
+#[test]
+fn build_runtime_env_forwards_non_cloudflare_ddns_provider_settings() {
+    let env = env_map(&[
+        ("XP_CLOUDFLARE_DDNS_ENABLED", "true"),
+        ("XP_DDNS_PROVIDER", "rfc2136"),
+        ("XP_DDNS_RFC2136_SERVER", "ns1.example.net"),
+        ("XP_DDNS_RFC2136_ZONE", "example.net"),
+    ]);
+
+    let runtime_env = build_runtime_env(&env, None);
+    assert_eq!(
+        runtime_env
+            .get("XP_CLOUDFLARE_DDNS_ENABLED")
+            .map(String::as_str),
+        Some("true")
+    );
+    assert_eq!(
+        runtime_env.get("XP_DDNS_PROVIDER").map(String::as_str),
+        Some("rfc2136")
+    );
+    assert_eq!(
+        runtime_env.get("XP_DDNS_RFC2136_ZONE").map(String::as_str),
+        Some("example.net")
+    );
+    assert!(!runtime_env.contains_key("XP_CLOUDFLARE_DDNS_TOKEN_FILE"));
+}
+
 #[test]
 fn build_runtime_env_forwards_vless_canary_and_default_endpoint_settings() {
     let env = env_map(&[
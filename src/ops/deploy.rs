@@ -1,4 +1,5 @@
 use crate::admin_token::{hash_admin_token_argon2id, parse_admin_token_hash, verify_admin_token};
+use crate::config::DdnsProviderKind;
 use crate::ops::cli::{
     DeployArgs, ExitError, InitArgs, InitSystemArg, InstallArgs, InstallOnly, XpBootstrapArgs,
     XpInstallArgs,
@@ -70,6 +71,7 @@ struct DeployPlan {
     enable_services: bool,
     cloudflare_enabled: bool,
     ddns_enabled: bool,
+    ddns_provider: DdnsProviderKind,
     ip_geo_enabled: bool,
     ddns_zone_id: Option<String>,
     vless_canary_acme_contact_email: Option<String>,
@@ -109,7 +111,13 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
     let auto_yes = args.yes;
     let force_overwrite = args.overwrite_existing;
     let cloudflare_enabled = args.cloudflare_toggle.enabled();
-    let ddns_enabled = args.ddns_toggle.enabled();
+    let ddns_provider = resolve_ddns_provider(
+        &args.ddns_provider,
+        &crate::ops::xp_env::parse_xp_env(fs::read_to_string(paths.etc_xp_env()).ok()),
+    );
+    // Only the Cloudflare DDNS backend needs the Cloudflare API token.
+    let ddns_uses_cloudflare =
+        args.ddns_toggle.enabled() && ddns_provider == DdnsProviderKind::Cloudflare;
     let interactive = !args.non_interactive && io::stdin().is_terminal();
 
     if args.join_token_stdin && args.cloudflare_token_stdin {
@@ -143,7 +151,7 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
     let managed_vless_enabled = args.default_vless_port.is_some();
 
     if args.cloudflare_token_stdin {
-        if !cloudflare_enabled && !ddns_enabled && !managed_vless_enabled {
+        if !cloudflare_enabled && !ddns_uses_cloudflare && !managed_vless_enabled {
             return Err(ExitError::new(
                 2,
                 "invalid_args: --cloudflare-token-stdin requires --cloudflare, --ddns, or managed default vless",
@@ -171,7 +179,7 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
 
     if args.cloudflare_token.is_some()
         && !cloudflare_enabled
-        && !ddns_enabled
+        && !ddns_uses_cloudflare
         && !managed_vless_enabled
     {
         return Err(ExitError::new(
@@ -287,7 +295,17 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
         eprintln!("  - init directories and service files (no enable)");
         eprintln!("  - install xp binary");
         if plan.ddns_enabled {
-            eprintln!("  - write xp-readable cloudflare ddns token file");
+            match plan.ddns_provider {
+                DdnsProviderKind::Cloudflare => {
+                    eprintln!("  - write xp-readable cloudflare ddns token file")
+                }
+                DdnsProviderKind::Rfc2136 | DdnsProviderKind::Http
+                    if args.ddns_provider.secret_file.is_some() =>
+                {
+                    eprintln!("  - write xp-readable ddns secret file")
+                }
+                DdnsProviderKind::Rfc2136 | DdnsProviderKind::Http => {}
+            }
         }
         if plan.cloudflare_enabled {
             eprintln!("  - cloudflare provision (without starting cloudflared)");
@@ -352,7 +370,10 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
 
     let managed_vless_canary_enabled =
         plan.default_vless_port.is_some() || parsed_env.default_vless_port.is_some();
-    if plan.ddns_enabled || managed_vless_canary_enabled {
+    if plan.ddns_enabled {
+        write_ddns_secret_file(&paths, mode, plan.ddns_provider, &args.ddns_provider)?;
+    }
+    if ddns_uses_cloudflare || managed_vless_canary_enabled {
         let token = cloudflare::load_cloudflare_token_for_deploy(
             &paths,
             args.cloudflare_token.as_deref(),
@@ -369,12 +390,14 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
                 e
             }
         })?;
-        ensure_runtime_token_file(
-            &paths,
-            mode,
-            &token,
-            Path::new(crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE),
-        )?;
+        if ddns_uses_cloudflare {
+            ensure_runtime_token_file(
+                &paths,
+                mode,
+                &token,
+                Path::new(crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE),
+            )?;
+        }
         if managed_vless_canary_enabled {
             ensure_runtime_token_file(
                 &paths,
@@ -385,6 +408,16 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
         }
     }
 
+    let ddns = DdnsWriteValues {
+        enabled: plan.ddns_enabled,
+        zone_id: plan.ddns_zone_id.as_deref().unwrap_or_default(),
+        provider_settings: if plan.ddns_enabled {
+            ddns_provider_settings(plan.ddns_provider, &args.ddns_provider)
+        } else {
+            Vec::new()
+        },
+    };
+
     // After `xp-ops init`, we know the `xp` group exists (so `chown root:xp` is reliable).
     let bootstrap_admin_token = if join_or_existing_metadata {
         None
@@ -395,8 +428,7 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
             plan.node_name.as_str(),
             plan.access_host.as_str(),
             plan.api_base_url.as_str(),
-            plan.ddns_enabled,
-            plan.ddns_zone_id.as_deref().unwrap_or_default(),
+            &ddns,
             plan.ip_geo_enabled,
             &managed_defaults,
             force_overwrite,
@@ -499,8 +531,7 @@ pub async fn cmd_deploy(paths: Paths, mut args: DeployArgs) -> Result<(), ExitEr
                 plan.node_name.as_str(),
                 plan.access_host.as_str(),
                 plan.api_base_url.as_str(),
-                plan.ddns_enabled,
-                plan.ddns_zone_id.as_deref().unwrap_or_default(),
+                &ddns,
                 plan.ip_geo_enabled,
                 &managed_defaults,
                 force_overwrite,
@@ -626,8 +657,10 @@ async fn build_plan(paths: &Paths, args: &DeployArgs) -> Result<DeployPlan, Exit
     );
     let managed_vless_enabled =
         args.default_vless_port.is_some() || parsed_env.default_vless_port.is_some();
+    let ddns_provider = resolve_ddns_provider(&args.ddns_provider, &parsed_env);
+    let ddns_uses_cloudflare = ddns_enabled && ddns_provider == DdnsProviderKind::Cloudflare;
 
-    let token = if cloudflare_enabled || ddns_enabled || managed_vless_enabled {
+    let token = if cloudflare_enabled || ddns_uses_cloudflare || managed_vless_enabled {
         match cloudflare::load_cloudflare_token_for_deploy(
             paths,
             args.cloudflare_token.as_deref(),
@@ -858,7 +891,15 @@ async fn build_plan(paths: &Paths, args: &DeployArgs) -> Result<DeployPlan, Exit
         } else if !is_valid_hostname(args.access_host.trim()) {
             errors.push("ddns access-host is not a valid DNS name".to_string());
         }
-        if ddns_zone_id.is_none()
+        validate_ddns_provider_args(
+            paths,
+            ddns_provider,
+            &args.ddns_provider,
+            &parsed_env,
+            &mut errors,
+        );
+        if ddns_uses_cloudflare
+            && ddns_zone_id.is_none()
             && let Some(token) = token.as_deref()
         {
             match resolve_zone_from_domain(
@@ -892,6 +933,7 @@ async fn build_plan(paths: &Paths, args: &DeployArgs) -> Result<DeployPlan, Exit
         enable_services: args.enable_services_toggle.enabled(),
         cloudflare_enabled,
         ddns_enabled,
+        ddns_provider,
         ip_geo_enabled: args.ip_geo_enabled,
         ddns_zone_id,
         vless_canary_acme_contact_email: args.vless_canary_acme_contact_email.clone(),
@@ -1483,7 +1525,12 @@ fn render_plan(plan: &DeployPlan) {
         }
         .to_string(),
     );
-    if plan.cloudflare_enabled || plan.ddns_enabled {
+    if plan.ddns_enabled {
+        line("ddns_provider", plan.ddns_provider.as_str().to_string());
+    }
+    if plan.cloudflare_enabled
+        || (plan.ddns_enabled && plan.ddns_provider == DdnsProviderKind::Cloudflare)
+    {
         let value = match plan.cloudflare_token_source {
             Some(src) => format!("provided via {}", src.display()),
             None => "absent".to_string(),
@@ -1513,7 +1560,7 @@ fn render_plan(plan: &DeployPlan) {
         "api_base_url",
         auto(plan.api_base_url.as_str(), plan.api_base_url_source),
     );
-    if plan.ddns_enabled {
+    if plan.ddns_enabled && plan.ddns_provider == DdnsProviderKind::Cloudflare {
         line(
             "ddns_zone_id",
             plan.ddns_zone_id
@@ -1922,8 +1969,7 @@ fn ensure_xp_env_admin_token_hash_bootstrap(
     node_name: &str,
     access_host: &str,
     api_base_url: &str,
-    ddns_enabled: bool,
-    ddns_zone_id: &str,
+    ddns: &DdnsWriteValues<'_>,
     ip_geo_enabled: bool,
     managed_defaults: &ManagedDefaultsWriteValues<'_>,
     force_overwrite: bool,
@@ -1934,6 +1980,38 @@ fn ensure_xp_env_admin_token_hash_bootstrap(
     let resolved_managed_defaults =
         resolve_managed_defaults_write_values(&parsed, managed_defaults);
     let vless_canary = resolve_vless_canary_write_values(&parsed, managed_defaults);
+    let write_env = |admin_token_hash: &str| {
+        crate::ops::xp_env::write_xp_env(
+            paths,
+            mode,
+            parsed.retained_lines,
+            parsed.flags,
+            crate::ops::xp_env::XpEnvWriteValues {
+                admin_token_hash,
+                node_name,
+                access_host,
+                api_base_url,
+                vless_canary_bind: vless_canary.bind.as_str(),
+                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
+                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
+                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
+                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
+                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
+                default_vless_server_names: resolved_managed_defaults
+                    .default_vless_server_names
+                    .as_deref(),
+                default_vless_fingerprint: resolved_managed_defaults
+                    .default_vless_fingerprint
+                    .as_deref(),
+                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
+                ip_geo_enabled: ip_geo_enabled.then_some(true),
+                cloudflare_ddns_enabled: ddns.enabled,
+                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
+                cloudflare_ddns_zone_id: ddns.zone_id,
+                ddns_provider_settings: &ddns.provider_settings,
+            },
+        )
+    };
 
     if let Some(v) = parsed.node_name.as_deref()
         && v != node_name
@@ -1970,105 +2048,21 @@ fn ensure_xp_env_admin_token_hash_bootstrap(
                 "invalid_input: XP_ADMIN_TOKEN_HASH is present but invalid in /etc/xp/xp.env",
             ));
         }
-        crate::ops::xp_env::write_xp_env(
-            paths,
-            mode,
-            parsed.retained_lines,
-            parsed.flags,
-            crate::ops::xp_env::XpEnvWriteValues {
-                admin_token_hash: raw_hash,
-                node_name,
-                access_host,
-                api_base_url,
-                vless_canary_bind: vless_canary.bind.as_str(),
-                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-                default_vless_server_names: resolved_managed_defaults
-                    .default_vless_server_names
-                    .as_deref(),
-                default_vless_fingerprint: resolved_managed_defaults
-                    .default_vless_fingerprint
-                    .as_deref(),
-                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-                ip_geo_enabled: ip_geo_enabled.then_some(true),
-                cloudflare_ddns_enabled: ddns_enabled,
-                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-                cloudflare_ddns_zone_id: ddns_zone_id,
-            },
-        )?;
+        write_env(raw_hash)?;
         return Ok(None);
     }
 
     if let Some(token) = parsed.admin_token_plain.as_deref() {
         let hash = hash_admin_token_argon2id(token)
             .map_err(|e| ExitError::new(2, format!("invalid_input: admin token hash: {e}")))?;
-        crate::ops::xp_env::write_xp_env(
-            paths,
-            mode,
-            parsed.retained_lines,
-            parsed.flags,
-            crate::ops::xp_env::XpEnvWriteValues {
-                admin_token_hash: hash.as_str(),
-                node_name,
-                access_host,
-                api_base_url,
-                vless_canary_bind: vless_canary.bind.as_str(),
-                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-                default_vless_server_names: resolved_managed_defaults
-                    .default_vless_server_names
-                    .as_deref(),
-                default_vless_fingerprint: resolved_managed_defaults
-                    .default_vless_fingerprint
-                    .as_deref(),
-                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-                ip_geo_enabled: ip_geo_enabled.then_some(true),
-                cloudflare_ddns_enabled: ddns_enabled,
-                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-                cloudflare_ddns_zone_id: ddns_zone_id,
-            },
-        )?;
+        write_env(hash.as_str())?;
         return Ok(None);
     }
 
     let token = generate_admin_token();
     let hash = hash_admin_token_argon2id(&token)
         .map_err(|e| ExitError::new(2, format!("invalid_input: admin token hash: {e}")))?;
-    crate::ops::xp_env::write_xp_env(
-        paths,
-        mode,
-        parsed.retained_lines,
-        parsed.flags,
-        crate::ops::xp_env::XpEnvWriteValues {
-            admin_token_hash: hash.as_str(),
-            node_name,
-            access_host,
-            api_base_url,
-            vless_canary_bind: vless_canary.bind.as_str(),
-            vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-            vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-            vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-            vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-            default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-            default_vless_server_names: resolved_managed_defaults
-                .default_vless_server_names
-                .as_deref(),
-            default_vless_fingerprint: resolved_managed_defaults
-                .default_vless_fingerprint
-                .as_deref(),
-            default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-            ip_geo_enabled: ip_geo_enabled.then_some(true),
-            cloudflare_ddns_enabled: ddns_enabled,
-            cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-            cloudflare_ddns_zone_id: ddns_zone_id,
-        },
-    )?;
+    write_env(hash.as_str())?;
     Ok(Some(token))
 }
 
@@ -2080,8 +2074,7 @@ fn ensure_xp_env_admin_token_hash_join(
     node_name: &str,
     access_host: &str,
     api_base_url: &str,
-    ddns_enabled: bool,
-    ddns_zone_id: &str,
+    ddns: &DdnsWriteValues<'_>,
     ip_geo_enabled: bool,
     managed_defaults: &ManagedDefaultsWriteValues<'_>,
     force_overwrite: bool,
@@ -2096,6 +2089,38 @@ fn ensure_xp_env_admin_token_hash_join(
     let resolved_managed_defaults =
         resolve_managed_defaults_write_values(&parsed, managed_defaults);
     let vless_canary = resolve_vless_canary_write_values(&parsed, managed_defaults);
+    let write_env = |admin_token_hash: &str| {
+        crate::ops::xp_env::write_xp_env(
+            paths,
+            mode,
+            parsed.retained_lines,
+            parsed.flags,
+            crate::ops::xp_env::XpEnvWriteValues {
+                admin_token_hash,
+                node_name,
+                access_host,
+                api_base_url,
+                vless_canary_bind: vless_canary.bind.as_str(),
+                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
+                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
+                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
+                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
+                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
+                default_vless_server_names: resolved_managed_defaults
+                    .default_vless_server_names
+                    .as_deref(),
+                default_vless_fingerprint: resolved_managed_defaults
+                    .default_vless_fingerprint
+                    .as_deref(),
+                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
+                ip_geo_enabled: ip_geo_enabled.then_some(true),
+                cloudflare_ddns_enabled: ddns.enabled,
+                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
+                cloudflare_ddns_zone_id: ddns.zone_id,
+                ddns_provider_settings: &ddns.provider_settings,
+            },
+        )
+    };
 
     if let Some(v) = parsed.node_name.as_deref()
         && v != node_name
@@ -2138,35 +2163,7 @@ fn ensure_xp_env_admin_token_hash_join(
                 "admin_token_mismatch: existing XP_ADMIN_TOKEN_HASH differs (use --overwrite-existing to replace)",
             ));
         }
-        crate::ops::xp_env::write_xp_env(
-            paths,
-            mode,
-            parsed.retained_lines,
-            parsed.flags,
-            crate::ops::xp_env::XpEnvWriteValues {
-                admin_token_hash: expected_hash,
-                node_name,
-                access_host,
-                api_base_url,
-                vless_canary_bind: vless_canary.bind.as_str(),
-                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-                default_vless_server_names: resolved_managed_defaults
-                    .default_vless_server_names
-                    .as_deref(),
-                default_vless_fingerprint: resolved_managed_defaults
-                    .default_vless_fingerprint
-                    .as_deref(),
-                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-                ip_geo_enabled: ip_geo_enabled.then_some(true),
-                cloudflare_ddns_enabled: ddns_enabled,
-                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-                cloudflare_ddns_zone_id: ddns_zone_id,
-            },
-        )?;
+        write_env(expected_hash)?;
         return Ok(());
     }
 
@@ -2180,67 +2177,11 @@ fn ensure_xp_env_admin_token_hash_join(
                 "admin_token_mismatch: existing XP_ADMIN_TOKEN does not match cluster token (use --overwrite-existing to replace)",
             ));
         }
-        crate::ops::xp_env::write_xp_env(
-            paths,
-            mode,
-            parsed.retained_lines,
-            parsed.flags,
-            crate::ops::xp_env::XpEnvWriteValues {
-                admin_token_hash: expected_hash,
-                node_name,
-                access_host,
-                api_base_url,
-                vless_canary_bind: vless_canary.bind.as_str(),
-                vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-                vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-                vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-                vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-                default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-                default_vless_server_names: resolved_managed_defaults
-                    .default_vless_server_names
-                    .as_deref(),
-                default_vless_fingerprint: resolved_managed_defaults
-                    .default_vless_fingerprint
-                    .as_deref(),
-                default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-                ip_geo_enabled: ip_geo_enabled.then_some(true),
-                cloudflare_ddns_enabled: ddns_enabled,
-                cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-                cloudflare_ddns_zone_id: ddns_zone_id,
-            },
-        )?;
+        write_env(expected_hash)?;
         return Ok(());
     }
 
-    crate::ops::xp_env::write_xp_env(
-        paths,
-        mode,
-        parsed.retained_lines,
-        parsed.flags,
-        crate::ops::xp_env::XpEnvWriteValues {
-            admin_token_hash: expected_hash,
-            node_name,
-            access_host,
-            api_base_url,
-            vless_canary_bind: vless_canary.bind.as_str(),
-            vless_canary_acme_directory_url: vless_canary.acme_directory_url.as_str(),
-            vless_canary_acme_contact_email: vless_canary.acme_contact_email.as_str(),
-            vless_canary_cloudflare_token_file: vless_canary.cloudflare_token_file.as_str(),
-            vless_canary_cloudflare_zone_id: vless_canary.cloudflare_zone_id.as_str(),
-            default_vless_port: resolved_managed_defaults.default_vless_port.as_deref(),
-            default_vless_server_names: resolved_managed_defaults
-                .default_vless_server_names
-                .as_deref(),
-            default_vless_fingerprint: resolved_managed_defaults
-                .default_vless_fingerprint
-                .as_deref(),
-            default_ss_port: resolved_managed_defaults.default_ss_port.as_deref(),
-            ip_geo_enabled: ip_geo_enabled.then_some(true),
-            cloudflare_ddns_enabled: ddns_enabled,
-            cloudflare_ddns_token_file: crate::config::DEFAULT_CLOUDFLARE_DDNS_TOKEN_FILE,
-            cloudflare_ddns_zone_id: ddns_zone_id,
-        },
-    )?;
+    write_env(expected_hash)?;
     Ok(())
 }
 
@@ -2412,5 +2353,11 @@ fn validate_https_origin_no_port(origin: &str) -> Result<(), ExitError> {
     Ok(())
 }
 
+mod ddns;
+use ddns::{
+    DdnsWriteValues, ddns_provider_settings, resolve_ddns_provider, validate_ddns_provider_args,
+    write_ddns_secret_file,
+};
+
 #[cfg(test)]
 mod tests;
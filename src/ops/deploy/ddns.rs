@@ -0,0 +1,182 @@
+use std::fs;
+use std::path::Path;
+
+use super::ensure_runtime_token_file;
+use crate::config::DdnsProviderKind;
+use crate::ops::cli::{DdnsProviderArgs, ExitError};
+use crate::ops::paths::Paths;
+use crate::ops::util::Mode;
+
+pub(super) struct DdnsWriteValues<'a> {
+    pub(super) enabled: bool,
+    pub(super) zone_id: &'a str,
+    pub(super) provider_settings: Vec<(&'static str, String)>,
+}
+
+pub(super) fn resolve_ddns_provider(
+    args: &DdnsProviderArgs,
+    parsed: &crate::ops::xp_env::ParsedXpEnv,
+) -> DdnsProviderKind {
+    use clap::ValueEnum;
+
+    args.provider
+        .or_else(|| {
+            parsed
+                .retained_value("XP_DDNS_PROVIDER")
+                .and_then(|raw| DdnsProviderKind::from_str(&raw, true).ok())
+        })
+        .unwrap_or_default()
+}
+
+pub(super) fn ddns_secret_runtime_file(provider: DdnsProviderKind) -> Option<&'static str> {
+    match provider {
+        DdnsProviderKind::Cloudflare => None,
+        DdnsProviderKind::Rfc2136 => Some(crate::config::DEFAULT_DDNS_TSIG_SECRET_FILE),
+        DdnsProviderKind::Http => Some(crate::config::DEFAULT_DDNS_HTTP_TOKEN_FILE),
+    }
+}
+
+pub(super) fn ddns_provider_settings(
+    provider: DdnsProviderKind,
+    args: &DdnsProviderArgs,
+) -> Vec<(&'static str, String)> {
+    let mut settings = Vec::new();
+    let mut push = |key: &'static str, value: Option<String>| {
+        if let Some(value) = value {
+            settings.push((key, value));
+        }
+    };
+    push(
+        "XP_DDNS_PROVIDER",
+        args.provider.map(|kind| kind.as_str().to_string()),
+    );
+    push("XP_DDNS_RFC2136_SERVER", args.rfc2136_server.clone());
+    push("XP_DDNS_RFC2136_ZONE", args.rfc2136_zone.clone());
+    push("XP_DDNS_RFC2136_TSIG_KEY_NAME", args.tsig_key_name.clone());
+    push(
+        "XP_DDNS_RFC2136_TSIG_ALGORITHM",
+        args.tsig_algorithm.map(|alg| alg.as_str().to_string()),
+    );
+    push("XP_DDNS_HTTP_URL_TEMPLATE", args.http_url_template.clone());
+    push(
+        "XP_DDNS_HTTP_METHOD",
+        args.http_method.map(|method| method.as_str().to_string()),
+    );
+    push(
+        "XP_DDNS_HTTP_BODY_TEMPLATE",
+        args.http_body_template.clone(),
+    );
+    push(
+        "XP_DDNS_HTTP_SUCCESS_REGEX",
+        args.http_success_regex.clone(),
+    );
+    // The HTTP token is optional, so xp only reads the runtime copy when pointed at it.
+    if provider == DdnsProviderKind::Http && args.secret_file.is_some() {
+        push(
+            "XP_DDNS_HTTP_TOKEN_FILE",
+            Some(crate::config::DEFAULT_DDNS_HTTP_TOKEN_FILE.to_string()),
+        );
+    }
+    settings
+}
+
+pub(super) fn validate_ddns_provider_args(
+    paths: &Paths,
+    provider: DdnsProviderKind,
+    args: &DdnsProviderArgs,
+    parsed: &crate::ops::xp_env::ParsedXpEnv,
+    errors: &mut Vec<String>,
+) {
+    let mut require = |flag: &str, key: &str, value: Option<&String>| {
+        let provided = value.is_some_and(|value| !value.trim().is_empty());
+        if !provided && parsed.retained_value(key).is_none() {
+            errors.push(format!(
+                "ddns provider {} requires {flag} (or an existing {key})",
+                provider.as_str()
+            ));
+        }
+    };
+    match provider {
+        DdnsProviderKind::Cloudflare => {
+            let rfc2136_or_http = args.rfc2136_server.is_some()
+                || args.rfc2136_zone.is_some()
+                || args.tsig_key_name.is_some()
+                || args.http_url_template.is_some()
+                || args.secret_file.is_some();
+            if rfc2136_or_http {
+                errors.push(
+                    "ddns rfc2136/http options require --ddns-provider rfc2136 or http".to_string(),
+                );
+            }
+            return;
+        }
+        DdnsProviderKind::Rfc2136 => {
+            require(
+                "--ddns-rfc2136-server",
+                "XP_DDNS_RFC2136_SERVER",
+                args.rfc2136_server.as_ref(),
+            );
+            require(
+                "--ddns-rfc2136-zone",
+                "XP_DDNS_RFC2136_ZONE",
+                args.rfc2136_zone.as_ref(),
+            );
+            require(
+                "--ddns-tsig-key-name",
+                "XP_DDNS_RFC2136_TSIG_KEY_NAME",
+                args.tsig_key_name.as_ref(),
+            );
+        }
+        DdnsProviderKind::Http => {
+            require(
+                "--ddns-http-url-template",
+                "XP_DDNS_HTTP_URL_TEMPLATE",
+                args.http_url_template.as_ref(),
+            );
+        }
+    }
+
+    if let Some(secret_file) = args.secret_file.as_deref() {
+        if !secret_file.is_file() {
+            errors.push(format!(
+                "--ddns-secret-file {} does not exist",
+                secret_file.display()
+            ));
+        }
+    } else if provider == DdnsProviderKind::Rfc2136
+        && let Some(runtime_file) = ddns_secret_runtime_file(provider)
+        && parsed
+            .retained_value("XP_DDNS_RFC2136_TSIG_SECRET_FILE")
+            .is_none()
+        && !paths.map_abs(Path::new(runtime_file)).exists()
+    {
+        errors.push(format!(
+            "ddns provider rfc2136 requires --ddns-secret-file (or an existing {runtime_file})"
+        ));
+    }
+}
+
+/// Copies `--ddns-secret-file` to the xp-readable runtime path of providers that take a secret.
+pub(super) fn write_ddns_secret_file(
+    paths: &Paths,
+    mode: Mode,
+    provider: DdnsProviderKind,
+    args: &DdnsProviderArgs,
+) -> Result<(), ExitError> {
+    let (Some(secret_file), Some(runtime_file)) = (
+        args.secret_file.as_deref(),
+        ddns_secret_runtime_file(provider),
+    ) else {
+        return Ok(());
+    };
+    let secret = fs::read_to_string(secret_file).map_err(|e| {
+        ExitError::new(
+            2,
+            format!(
+                "invalid_args: read --ddns-secret-file {}: {e}",
+                secret_file.display()
+            ),
+        )
+    })?;
+    ensure_runtime_token_file(paths, mode, &secret, Path::new(runtime_file))
+}
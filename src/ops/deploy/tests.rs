@@ -7,12 +7,22 @@ use tokio::sync::oneshot;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+mod ddns;
+
 const VALID_ADMIN_TOKEN_HASH: &str = "$argon2id$v=19$m=65536,t=3,p=1$TqOws+M/ypxKCmnVcbWAdg$VlLbEUvXvoESmlktijJp9QYD/jJklIIljA1vuce9P+k";
 
 fn read_env(paths: &Paths) -> String {
     fs::read_to_string(paths.etc_xp_env()).unwrap()
 }
 
+fn no_ddns() -> DdnsWriteValues<'static> {
+    DdnsWriteValues {
+        enabled: false,
+        zone_id: "",
+        provider_settings: Vec::new(),
+    }
+}
+
 fn empty_managed_defaults() -> ManagedDefaultsWriteValues<'static> {
     ManagedDefaultsWriteValues {
         vless_canary_acme_contact_email: None,
@@ -41,8 +51,7 @@ fn ensure_xp_env_admin_token_hash_keeps_xray_defaults_on_second_run() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -54,8 +63,7 @@ fn ensure_xp_env_admin_token_hash_keeps_xray_defaults_on_second_run() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -110,8 +118,7 @@ XP_XRAY_CUSTOM=keep-me\n",
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -148,8 +155,7 @@ XP_CLOUDFLARED_RESTART_MODE=none\n",
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -187,8 +193,7 @@ fn ensure_xp_env_admin_token_hash_writes_managed_default_endpoint_keys() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &managed_defaults,
         false,
@@ -228,8 +233,7 @@ XP_VLESS_CANARY_CLOUDFLARE_ZONE_ID=zone-123\n",
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -270,8 +274,7 @@ XP_DEFAULT_SS_PORT=53843\n",
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -303,8 +306,7 @@ fn bootstrap_geo_opt_in_writes_true_and_omission_preserves_existing_value() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -318,8 +320,7 @@ fn bootstrap_geo_opt_in_writes_true_and_omission_preserves_existing_value() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         true,
         &empty_managed_defaults(),
         false,
@@ -349,8 +350,7 @@ fn join_geo_opt_in_writes_true_and_omission_preserves_existing_value() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         false,
         &empty_managed_defaults(),
         false,
@@ -365,8 +365,7 @@ fn join_geo_opt_in_writes_true_and_omission_preserves_existing_value() {
         "node-1",
         "example.com",
         "https://example.com",
-        false,
-        "",
+        &no_ddns(),
         true,
         &empty_managed_defaults(),
         false,
@@ -467,6 +466,7 @@ async fn build_plan_cloudflare_token_missing_error_is_actionable() {
             ddns: false,
             no_ddns: true,
         },
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: false,
         account_id: Some("acc".to_string()),
         zone_id: Some("zone".to_string()),
@@ -526,6 +526,7 @@ async fn build_plan_allows_default_vless_port_without_server_names() {
         access_host: xp_test_fixtures::host_fixture552().to_owned(),
         cloudflare_toggle: crate::ops::cli::CloudflareToggle::default(),
         ddns_toggle: crate::ops::cli::DdnsToggle::default(),
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: false,
         account_id: None,
         zone_id: None,
@@ -581,6 +582,7 @@ async fn build_plan_rejects_zero_managed_default_ports() {
         access_host: xp_test_fixtures::host_fixture552().to_owned(),
         cloudflare_toggle: crate::ops::cli::CloudflareToggle::default(),
         ddns_toggle: crate::ops::cli::DdnsToggle::default(),
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: false,
         account_id: None,
         zone_id: None,
@@ -648,6 +650,7 @@ async fn build_plan_rejects_zero_managed_default_ports_from_existing_env() {
         access_host: xp_test_fixtures::host_fixture552().to_owned(),
         cloudflare_toggle: crate::ops::cli::CloudflareToggle::default(),
         ddns_toggle: crate::ops::cli::DdnsToggle::default(),
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: false,
         account_id: None,
         zone_id: None,
@@ -719,6 +722,7 @@ async fn build_plan_detects_token_need_from_existing_managed_vless_env() {
         access_host: xp_test_fixtures::host_fixture552().to_owned(),
         cloudflare_toggle: crate::ops::cli::CloudflareToggle::default(),
         ddns_toggle: crate::ops::cli::DdnsToggle::default(),
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: false,
         account_id: None,
         zone_id: None,
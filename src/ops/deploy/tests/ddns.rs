@@ -0,0 +1,124 @@
+use super::*;
+
+#[test]
+fn ensure_xp_env_writes_ddns_provider_settings_and_keeps_the_rest() {
+    let tmp = tempdir().unwrap();
+    let paths = Paths::new(tmp.path().to_path_buf());
+
+    fs::create_dir_all(paths.etc_xp_dir()).unwrap();
+    fs::write(
+        paths.etc_xp_env(),
+        format!(
+            "XP_ADMIN_TOKEN_HASH={VALID_ADMIN_TOKEN_HASH}\n\
+XP_DDNS_PROVIDER=http\n\
+XP_DDNS_RFC2136_TTL=120\n",
+        ),
+    )
+    .unwrap();
+
+    let ddns = DdnsWriteValues {
+        enabled: true,
+        zone_id: "",
+        provider_settings: ddns_provider_settings(
+            DdnsProviderKind::Rfc2136,
+            &crate::ops::cli::DdnsProviderArgs {
+                provider: Some(DdnsProviderKind::Rfc2136),
+                rfc2136_server: Some("ns1.example.net:5353".to_string()),
+                rfc2136_zone: Some("example.net".to_string()),
+                tsig_key_name: Some("xp-ddns".to_string()),
+                ..Default::default()
+            },
+        ),
+    };
+    ensure_xp_env_admin_token_hash_bootstrap(
+        &paths,
+        Mode::Real,
+        "node-1",
+        "node-1.example.net",
+        "https://example.com",
+        &ddns,
+        false,
+        &empty_managed_defaults(),
+        false,
+    )
+    .unwrap();
+
+    let env = read_env(&paths);
+    assert!(env.contains("XP_CLOUDFLARE_DDNS_ENABLED=true"));
+    assert!(env.contains("XP_DDNS_PROVIDER='rfc2136'"));
+    assert!(!env.contains("XP_DDNS_PROVIDER=http"));
+    assert!(env.contains("XP_DDNS_RFC2136_SERVER='ns1.example.net:5353'"));
+    assert!(env.contains("XP_DDNS_RFC2136_ZONE='example.net'"));
+    assert!(env.contains("XP_DDNS_RFC2136_TSIG_KEY_NAME='xp-ddns'"));
+    assert!(env.contains("XP_DDNS_RFC2136_TTL=120"));
+
+    let parsed = crate::ops::xp_env::parse_xp_env(Some(env));
+    assert_eq!(
+        resolve_ddns_provider(&crate::ops::cli::DdnsProviderArgs::default(), &parsed),
+        DdnsProviderKind::Rfc2136
+    );
+}
+
+#[tokio::test]
+#[allow(clippy::await_holding_lock)]
+async fn build_plan_validates_rfc2136_ddns_without_a_cloudflare_token() {
+    let _lock = crate::ops::util::ENV_LOCK.lock().unwrap();
+    unsafe { std::env::remove_var("CLOUDFLARE_API_TOKEN") };
+
+    let tmp = tempdir().unwrap();
+    let paths = Paths::new(tmp.path().to_path_buf());
+    let xp_bin = tmp.path().join("xp");
+    fs::write(&xp_bin, b"dummy").unwrap();
+
+    let cli = crate::ops::cli::Cli::try_parse_from([
+        "xp-ops",
+        "deploy",
+        "--xp-bin",
+        xp_bin.to_str().unwrap(),
+        "--node-name",
+        "node-1",
+        "--access-host",
+        "node-1.example.net",
+        "--api-base-url",
+        "https://node-1.example.net",
+        "--no-cloudflare",
+        "--ddns",
+        "--ddns-provider",
+        "rfc2136",
+        "--ddns-rfc2136-server",
+        "ns1.example.net",
+        "--no-enable-services",
+        "--dry-run",
+    ])
+    .unwrap();
+    let crate::ops::cli::Command::Deploy(args) = cli.command.unwrap() else {
+        panic!("expected deploy command");
+    };
+
+    let plan = build_plan(&paths, &args).await.unwrap();
+    assert_eq!(plan.ddns_provider, DdnsProviderKind::Rfc2136);
+    assert!(
+        !plan.errors.iter().any(|e| e.contains("cloudflare token")),
+        "rfc2136 ddns must not require a cloudflare token: {:?}",
+        plan.errors
+    );
+    for flag in [
+        "--ddns-rfc2136-zone",
+        "--ddns-tsig-key-name",
+        "--ddns-secret-file",
+    ] {
+        assert!(
+            plan.errors.iter().any(|e| e.contains(flag)),
+            "expected missing {flag} error, got: {:?}",
+            plan.errors
+        );
+    }
+    assert!(
+        !plan
+            .errors
+            .iter()
+            .any(|e| e.contains("--ddns-rfc2136-server")),
+        "{:?}",
+        plan.errors
+    );
+}
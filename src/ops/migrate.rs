@@ -0,0 +1,66 @@
+use axum::http::Method;
+
+use super::{
+    cli::{ExitError, MigrateMarzbanArgs, MigrateTargetArgs, MigrateThreeXUiArgs},
+    paths::Paths,
+    xp::{internal_json_request, local_internal_ops_client},
+};
+use crate::migrate::{MigrationSource, marzban, three_x_ui};
+
+pub(crate) async fn cmd_migrate_three_x_ui(
+    paths: Paths,
+    args: MigrateThreeXUiArgs,
+) -> Result<(), ExitError> {
+    let source = three_x_ui::read_database(&args.db)
+        .map_err(|error| ExitError::new(2, format!("invalid_input: {error}")))?;
+    submit(paths, args.target, source).await
+}
+
+pub(crate) async fn cmd_migrate_marzban(
+    paths: Paths,
+    args: MigrateMarzbanArgs,
+) -> Result<(), ExitError> {
+    let read = |path: &std::path::Path| {
+        std::fs::read_to_string(path)
+            .map_err(|error| ExitError::new(2, format!("read {}: {error}", path.display())))
+    };
+    let source = marzban::read_export(&read(&args.users)?, &read(&args.xray_config)?)
+        .map_err(|error| ExitError::new(2, format!("invalid_input: {error}")))?;
+    submit(paths, args.target, source).await
+}
+
+async fn submit(
+    paths: Paths,
+    target: MigrateTargetArgs,
+    source: MigrationSource,
+) -> Result<(), ExitError> {
+    let body = serde_json::to_vec(&serde_json::json!({
+        "node": target.node,
+        "source": source,
+        "dry_run": target.dry_run,
+    }))
+    .map_err(|error| ExitError::new(5, format!("encode migrate request: {error}")))?;
+
+    let (client, auth) = local_internal_ops_client(&paths, &target.api_base_url)?;
+    let report: serde_json::Value = internal_json_request(
+        &client,
+        &target.api_base_url,
+        &auth,
+        Method::POST,
+        "/api/admin/_internal/migrate",
+        Some(body),
+    )
+    .await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report)
+            .map_err(|error| ExitError::new(5, format!("encode migrate report: {error}")))?
+    );
+    let has_issues = report["issues"]
+        .as_array()
+        .is_some_and(|issues| !issues.is_empty());
+    if has_issues {
+        return Err(ExitError::new(2, "invalid_input: see the issues above"));
+    }
+    Ok(())
+}
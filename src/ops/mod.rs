@@ -1,8 +1,10 @@
 pub mod cli;
 
+mod admin_principal;
 mod admin_token;
 #[cfg(test)]
 mod admin_token_tests;
+mod backup;
 pub(crate) mod cloudflare;
 mod cloudflare_config;
 #[cfg(test)]
@@ -15,6 +17,7 @@ mod init;
 mod install;
 pub(crate) mod internal_auth;
 pub(crate) mod membership_lifecycle;
+mod migrate;
 mod mihomo;
 mod paths;
 mod platform;
@@ -27,6 +30,7 @@ mod status;
 mod tui;
 mod upgrade;
 pub(crate) mod upgrade_artifacts;
+mod users;
 mod util;
 mod xp;
 mod xp_env;
@@ -31,6 +31,15 @@ pub fn preflight(paths: &Paths, command: &Option<Command>) -> Result<(), ExitErr
     match cmd {
         Command::Status(_) => Ok(()),
         Command::AdminToken(AdminTokenCommand::Show(_)) => Ok(()),
+        Command::AdminToken(
+            AdminTokenCommand::PrincipalList(_)
+            | AdminTokenCommand::PrincipalCreate(_)
+            | AdminTokenCommand::PrincipalRotate(_)
+            | AdminTokenCommand::PrincipalRevoke(_),
+        ) => {
+            // Runtime commands: principals live in cluster state behind the local xp API.
+            Ok(())
+        }
         Command::AdminToken(AdminTokenCommand::Set(args)) => {
             if args.dry_run {
                 return Ok(());
@@ -44,6 +53,9 @@ pub fn preflight(paths: &Paths, command: &Option<Command>) -> Result<(), ExitErr
             )
         }
 
+        // Talks to the local xp API; `backup create` writes wherever the operator points it.
+        Command::Backup(_) | Command::Users(_) | Command::Migrate(_) => Ok(()),
+
         Command::Tui(_) => preflight_tui(paths),
 
         Command::Install(args) => preflight_install(paths, args),
@@ -1,4 +1,4 @@
-use crate::ops::cli::DeployArgs;
+use crate::ops::cli::{DeployArgs, ExitError};
 use crate::ops::deploy;
 use crate::ops::paths::Paths;
 use crate::ops::util::{chmod, ensure_dir, write_string_if_changed};
@@ -16,20 +16,19 @@ use serde::{Deserialize, Serialize};
 use std::io::{self, Stdout};
 use std::time::Duration;
 
-pub async fn cmd_tui(paths: Paths) -> Result<(), crate::ops::cli::ExitError> {
+pub async fn cmd_tui(paths: Paths) -> Result<(), ExitError> {
     let mut stdout = io::stdout();
-    enable_raw_mode().map_err(|e| crate::ops::cli::ExitError::new(2, format!("{e}")))?;
+    enable_raw_mode().map_err(|e| ExitError::new(2, format!("{e}")))?;
     execute!(
         stdout,
         EnterAlternateScreen,
         EnableBracketedPaste,
         EnableMouseCapture
     )
-    .map_err(|e| crate::ops::cli::ExitError::new(2, format!("{e}")))?;
+    .map_err(|e| ExitError::new(2, format!("{e}")))?;
 
     let backend = ratatui::backend::CrosstermBackend::new(stdout);
-    let mut terminal =
-        Terminal::new(backend).map_err(|e| crate::ops::cli::ExitError::new(2, format!("{e}")))?;
+    let mut terminal = Terminal::new(backend).map_err(|e| ExitError::new(2, format!("{e}")))?;
 
     let mut app = App::new(&paths);
     let outcome = run_loop(&mut terminal, &mut app);
@@ -51,7 +50,7 @@ pub async fn cmd_tui(paths: Paths) -> Result<(), crate::ops::cli::ExitError> {
     }
 }
 
-async fn run_deploy(paths: Paths, values: AppValues) -> Result<(), crate::ops::cli::ExitError> {
+async fn run_deploy(paths: Paths, values: AppValues) -> Result<(), ExitError> {
     let args = DeployArgs {
         xp_bin: None,
         node_name: values.node_name,
@@ -64,6 +63,8 @@ async fn run_deploy(paths: Paths, values: AppValues) -> Result<(), crate::ops::c
             ddns: values.ddns_enabled,
             no_ddns: !values.ddns_enabled,
         },
+        // The TUI keeps whichever DDNS provider xp.env already selects.
+        ddns_provider: crate::ops::cli::DdnsProviderArgs::default(),
         ip_geo_enabled: values.ip_geo_enabled,
         account_id: values.account_id,
         zone_id: values.zone_id,
@@ -695,7 +696,7 @@ impl App {
         }
     }
 
-    fn to_values(&self) -> Result<AppValues, crate::ops::cli::ExitError> {
+    fn to_values(&self) -> Result<AppValues, ExitError> {
         Ok(AppValues {
             node_name: self.node_name.clone(),
             access_host: self.access_host.clone(),
@@ -851,19 +852,19 @@ fn mask_token(token: &str) -> String {
     "*".repeat(token.chars().count())
 }
 
-fn parse_optional_port(field: &str, raw: &str) -> Result<Option<u16>, crate::ops::cli::ExitError> {
+fn parse_optional_port(field: &str, raw: &str) -> Result<Option<u16>, ExitError> {
     let trimmed = raw.trim();
     if trimmed.is_empty() {
         return Ok(None);
     }
     let port = trimmed.parse::<u16>().map_err(|err| {
-        crate::ops::cli::ExitError::new(
+        ExitError::new(
             2,
             format!("invalid_input: {field} must be a valid port: {err}"),
         )
     })?;
     if port == 0 {
-        return Err(crate::ops::cli::ExitError::new(
+        return Err(ExitError::new(
             2,
             format!("invalid_input: {field} must be between 1 and 65535"),
         ));
@@ -957,7 +958,7 @@ fn load_tui_config(paths: &Paths) -> Option<TuiConfig> {
     }
 }
 
-fn save_tui_config(paths: &Paths, values: &AppValues) -> Result<(), crate::ops::cli::ExitError> {
+fn save_tui_config(paths: &Paths, values: &AppValues) -> Result<(), ExitError> {
     let cfg = TuiConfig {
         node_name: Some(values.node_name.clone()),
         access_host: Some(values.access_host.clone()),
@@ -981,7 +982,7 @@ fn save_tui_config(paths: &Paths, values: &AppValues) -> Result<(), crate::ops::
 
     let path = paths.etc_xp_ops_deploy_settings();
     ensure_dir(&paths.etc_xp_ops_deploy_dir()).map_err(|e| {
-        crate::ops::cli::ExitError::new(
+        ExitError::new(
             4,
             format!(
                 "filesystem_error: ensure dir {}: {e}",
@@ -990,9 +991,9 @@ fn save_tui_config(paths: &Paths, values: &AppValues) -> Result<(), crate::ops::
         )
     })?;
     let content = serde_json::to_string_pretty(&cfg)
-        .map_err(|e| crate::ops::cli::ExitError::new(4, format!("filesystem_error: {e}")))?;
+        .map_err(|e| ExitError::new(4, format!("filesystem_error: {e}")))?;
     write_string_if_changed(&path, &(content + "\n")).map_err(|e| {
-        crate::ops::cli::ExitError::new(
+        ExitError::new(
             4,
             format!("filesystem_error: write {}: {e}", path.display()),
         )
@@ -1001,10 +1002,7 @@ fn save_tui_config(paths: &Paths, values: &AppValues) -> Result<(), crate::ops::
     Ok(())
 }
 
-fn save_token_if_needed(
-    paths: &Paths,
-    values: &AppValues,
-) -> Result<(), crate::ops::cli::ExitError> {
+fn save_token_if_needed(paths: &Paths, values: &AppValues) -> Result<(), ExitError> {
     let token = values.cloudflare_token.trim();
     if token.is_empty() {
         // Keep existing token unchanged when input is empty.
@@ -1013,7 +1011,7 @@ fn save_token_if_needed(
 
     let token_dir = paths.etc_xp_ops_cloudflare_dir();
     ensure_dir(&token_dir).map_err(|e| {
-        crate::ops::cli::ExitError::new(
+        ExitError::new(
             4,
             format!("filesystem_error: ensure dir {}: {e}", token_dir.display()),
         )
@@ -1021,7 +1019,7 @@ fn save_token_if_needed(
 
     let token_path = paths.etc_xp_ops_cloudflare_token();
     write_string_if_changed(&token_path, token).map_err(|e| {
-        crate::ops::cli::ExitError::new(
+        ExitError::new(
             4,
             format!("filesystem_error: write {}: {e}", token_path.display()),
         )
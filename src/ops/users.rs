@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use axum::http::Method;
+
+use super::{
+    cli::{ExitError, UsersExportArgs, UsersFileFormat, UsersImportArgs},
+    paths::Paths,
+    xp::{internal_json_request, local_internal_ops_client},
+};
+use crate::user_bulk::{UserBulkDocument, records_from_csv, records_to_csv};
+
+pub(crate) async fn cmd_users_export(paths: Paths, args: UsersExportArgs) -> Result<(), ExitError> {
+    let (client, auth) = local_internal_ops_client(&paths, &args.api_base_url)?;
+    let document: UserBulkDocument = internal_json_request(
+        &client,
+        &args.api_base_url,
+        &auth,
+        Method::GET,
+        "/api/admin/_internal/users/export",
+        None,
+    )
+    .await?;
+    let bytes = match file_format(args.format, &args.output) {
+        UsersFileFormat::Csv => records_to_csv(&document.users).into_bytes(),
+        UsersFileFormat::Json => {
+            let mut bytes = serde_json::to_vec_pretty(&document)
+                .map_err(|error| ExitError::new(5, format!("encode users: {error}")))?;
+            bytes.push(b'\n');
+            bytes
+        }
+    };
+    crate::cluster_metadata::write_atomic_private(&args.output, &bytes).map_err(|error| {
+        ExitError::new(5, format!("write users {}: {error}", args.output.display()))
+    })?;
+    eprintln!(
+        "Wrote {} user(s) to {}.",
+        document.users.len(),
+        args.output.display()
+    );
+    eprintln!("The export holds subscription tokens; store it like a secret.");
+    Ok(())
+}
+
+pub(crate) async fn cmd_users_import(paths: Paths, args: UsersImportArgs) -> Result<(), ExitError> {
+    let raw = std::fs::read_to_string(&args.input).map_err(|error| {
+        ExitError::new(2, format!("read users {}: {error}", args.input.display()))
+    })?;
+    let users = match file_format(args.format, &args.input) {
+        UsersFileFormat::Csv => records_from_csv(&raw)
+            .map_err(|error| ExitError::new(2, format!("invalid_input: {error}")))?,
+        UsersFileFormat::Json => {
+            serde_json::from_str::<UserBulkDocument>(&raw)
+                .map_err(|error| ExitError::new(2, format!("invalid_input: parse users: {error}")))?
+                .users
+        }
+    };
+    let body = serde_json::to_vec(&serde_json::json!({
+        "users": users,
+        "dry_run": args.dry_run,
+    }))
+    .map_err(|error| ExitError::new(5, format!("encode import request: {error}")))?;
+
+    let (client, auth) = local_internal_ops_client(&paths, &args.api_base_url)?;
+    let report: serde_json::Value = internal_json_request(
+        &client,
+        &args.api_base_url,
+        &auth,
+        Method::POST,
+        "/api/admin/_internal/users/import",
+        Some(body),
+    )
+    .await?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report)
+            .map_err(|error| ExitError::new(5, format!("encode import report: {error}")))?
+    );
+    let has_issues = report["issues"]
+        .as_array()
+        .is_some_and(|issues| !issues.is_empty());
+    if has_issues {
+        return Err(ExitError::new(2, "invalid_input: see the issues above"));
+    }
+    Ok(())
+}
+
+fn file_format(explicit: Option<UsersFileFormat>, path: &Path) -> UsersFileFormat {
+    explicit.unwrap_or_else(|| {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+        {
+            UsersFileFormat::Csv
+        } else {
+            UsersFileFormat::Json
+        }
+    })
+}
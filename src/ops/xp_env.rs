@@ -111,6 +111,8 @@ pub struct XpEnvWriteValues<'a> {
     pub cloudflare_ddns_enabled: bool,
     pub cloudflare_ddns_token_file: &'a str,
     pub cloudflare_ddns_zone_id: &'a str,
+    /// `XP_DDNS_*` keys to (re)write; keys not listed keep their existing lines.
+    pub ddns_provider_settings: &'a [(&'static str, String)],
 }
 
 pub fn parse_xp_env(raw: Option<String>) -> ParsedXpEnv {
@@ -505,6 +507,17 @@ pub fn parse_xp_env(raw: Option<String>) -> ParsedXpEnv {
 }
 
 impl ParsedXpEnv {
+    /// Value of a key that deploy does not parse explicitly (kept in `retained_lines`).
+    pub fn retained_value(&self, key: &str) -> Option<String> {
+        let prefix = format!("{key}=");
+        self.retained_lines
+            .iter()
+            .rev()
+            .find_map(|line| line.strip_prefix(prefix.as_str()))
+            .map(|value| shell_unquote_wrapping_quotes(value).to_string())
+            .filter(|value| !value.is_empty())
+    }
+
     pub fn has_legacy_relay_probe_vars(&self) -> bool {
         self.flags.has_legacy_relay_probe_enabled
             || self.flags.has_legacy_relay_probe_bind
@@ -723,6 +736,17 @@ pub fn write_xp_env(
         )
     })?;
     lines.push(format!("XP_CLOUDFLARE_DDNS_ZONE_ID={ddns_zone_id}"));
+    for (key, value) in values.ddns_provider_settings {
+        let quoted = shell_quote_single(value).map_err(|e| {
+            ExitError::new(
+                2,
+                format!("invalid_input: {key} cannot be written safely: {e}"),
+            )
+        })?;
+        let prefix = format!("{key}=");
+        lines.retain(|line| !line.starts_with(prefix.as_str()));
+        lines.push(format!("{key}={quoted}"));
+    }
 
     let quoted = shell_quote_single(values.admin_token_hash).map_err(|e| {
         ExitError::new(
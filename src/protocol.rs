@@ -4,7 +4,21 @@ use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
 pub const SS2022_METHOD_2022_BLAKE3_AES_128_GCM: &str = "2022-blake3-aes-128-gcm";
+pub const SS2022_METHOD_2022_BLAKE3_AES_256_GCM: &str = "2022-blake3-aes-256-gcm";
+pub const SS2022_METHOD_2022_BLAKE3_CHACHA20_POLY1305: &str = "2022-blake3-chacha20-poly1305";
 pub const SS2022_PSK_LEN_BYTES_AES_128: usize = 16;
+pub const SS2022_PSK_LEN_BYTES_AES_256: usize = 32;
+pub const SS2022_PSK_LEN_BYTES_CHACHA20: usize = 32;
+
+/// Returns the PSK length the SS2022 `method` expects, or `None` for unsupported methods.
+pub fn ss2022_psk_len_bytes(method: &str) -> Option<usize> {
+    match method {
+        SS2022_METHOD_2022_BLAKE3_AES_128_GCM => Some(SS2022_PSK_LEN_BYTES_AES_128),
+        SS2022_METHOD_2022_BLAKE3_AES_256_GCM => Some(SS2022_PSK_LEN_BYTES_AES_256),
+        SS2022_METHOD_2022_BLAKE3_CHACHA20_POLY1305 => Some(SS2022_PSK_LEN_BYTES_CHACHA20),
+        _ => None,
+    }
+}
 
 // Xray-core's `xray x25519` uses base64.RawURLEncoding (no padding) for the private key input.
 // Ref: XTLS/Xray-core `main/commands/all/x25519.go`.
@@ -128,13 +142,56 @@ pub struct Ss2022EndpointMeta {
     pub managed_default: bool,
 }
 
+/// Trojan carried over Reality on a plain TCP listener. Reality materials follow the same rules
+/// as VLESS endpoints; only manually configured server names are supported.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrojanRealityTcpEndpointMeta {
+    pub reality: RealityConfig,
+    pub reality_keys: RealityKeys,
+    pub short_ids: Vec<String>,
+    pub active_short_id: String,
+    #[serde(default)]
+    pub mihomo_smux: MihomoSmuxConfig,
+}
+
+/// Trojan over plain TLS on TCP. Like Hysteria2 the endpoint presents a self-signed certificate
+/// generated at creation, and clients pin it via `cert_sha256`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrojanTlsTcpEndpointMeta {
+    pub server_name: String,
+    pub tls_cert_pem: String,
+    pub tls_key_pem: String,
+    /// Lowercase hex SHA-256 of the DER certificate.
+    pub cert_sha256: String,
+    #[serde(default)]
+    pub mihomo_smux: MihomoSmuxConfig,
+}
+
+/// Hysteria2 over QUIC. The endpoint terminates TLS itself with a self-signed certificate
+/// generated at creation; clients pin it via `cert_sha256` instead of trusting a CA.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Hysteria2EndpointMeta {
+    pub server_name: String,
+    pub tls_cert_pem: String,
+    pub tls_key_pem: String,
+    /// Lowercase hex SHA-256 of the DER certificate.
+    pub cert_sha256: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RotateShortIdResult {
     pub active_short_id: String,
     pub short_ids: Vec<String>,
 }
 
+/// Reality server names follow the generic [`validate_server_name`] hostname rules.
 pub fn validate_reality_server_name(host: &str) -> Result<(), &'static str> {
+    validate_server_name(host)
+}
+
+/// Hostname rules for any TLS server name xp configures (Reality camouflage names and the
+/// certificates of TLS-terminating endpoints alike).
+pub fn validate_server_name(host: &str) -> Result<(), &'static str> {
     let trimmed = host.trim();
     if trimmed.is_empty() {
         return Err("server_name is required");
@@ -452,11 +509,14 @@ pub fn rotate_short_ids_in_place<R: RngCore + CryptoRng>(
     }
 }
 
-pub fn generate_ss2022_psk_b64<R: RngCore + CryptoRng>(rng: &mut R) -> String {
+pub fn generate_ss2022_psk_b64<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    psk_len_bytes: usize,
+) -> String {
     // Xray-core uses SagerNet's sing-shadowsocks, which decodes the SS2022 PSK with base64.StdEncoding
-    // and requires 16 bytes for "2022-blake3-aes-128-gcm".
+    // and requires 16 bytes for "2022-blake3-aes-128-gcm" and 32 bytes for the other methods.
     // Ref: SagerNet/sing-shadowsocks `shadowaead_2022/service.go`.
-    let mut key = [0u8; SS2022_PSK_LEN_BYTES_AES_128];
+    let mut key = vec![0u8; psk_len_bytes];
     rng.fill_bytes(&mut key);
     base64::engine::general_purpose::STANDARD.encode(key)
 }
@@ -587,9 +647,30 @@ mod tests {
     #[test]
     fn ss2022_psk_is_base64_and_decodes_to_expected_length() {
         let mut rng = rand::rngs::StdRng::seed_from_u64(2);
-        let psk = generate_ss2022_psk_b64(&mut rng);
+        let psk = generate_ss2022_psk_b64(&mut rng, SS2022_PSK_LEN_BYTES_AES_128);
         let decoded = parse_ss2022_psk_b64(&psk).unwrap();
         assert_eq!(decoded.len(), SS2022_PSK_LEN_BYTES_AES_128);
+
+        let psk = generate_ss2022_psk_b64(&mut rng, SS2022_PSK_LEN_BYTES_AES_256);
+        let decoded = parse_ss2022_psk_b64(&psk).unwrap();
+        assert_eq!(decoded.len(), SS2022_PSK_LEN_BYTES_AES_256);
+    }
+
+    #[test]
+    fn ss2022_psk_len_matches_method() {
+        assert_eq!(
+            ss2022_psk_len_bytes(SS2022_METHOD_2022_BLAKE3_AES_128_GCM),
+            Some(16)
+        );
+        assert_eq!(
+            ss2022_psk_len_bytes(SS2022_METHOD_2022_BLAKE3_AES_256_GCM),
+            Some(32)
+        );
+        assert_eq!(
+            ss2022_psk_len_bytes(SS2022_METHOD_2022_BLAKE3_CHACHA20_POLY1305),
+            Some(32)
+        );
+        assert_eq!(ss2022_psk_len_bytes("aes-128-gcm"), None);
     }
 
     #[test]
@@ -1,18 +1,18 @@
 use std::{sync::Arc, time::Duration};
 
-use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono::{DateTime, Utc};
 use tokio::sync::Mutex;
 use tracing::warn;
 
 use crate::{
     config::Config,
-    cycle::{CycleTimeZone, CycleWindowError, current_cycle_window_at},
-    domain::{NodeQuotaReset, UserPriorityTier},
+    cycle::{CycleSchedule, CycleTimeZone, CycleWindowError, current_cycle_window_at},
     inbound_ip_usage::floor_minute,
     ip_geo_db::{IpGeoSource, SharedGeoResolver},
-    quota_policy,
     reconcile::ReconcileHandle,
-    state::{JsonSnapshotStore, membership_key, membership_xray_email},
+    state::{
+        JsonSnapshotStore, membership_key, membership_xray_alternate_email, membership_xray_email,
+    },
     tcp_connection_usage::{
         TcpConnectionMinuteSample, TcpConnectionUsageWarning,
         collect_established_inbound_connections_by_port,
@@ -20,6 +20,13 @@ use crate::{
     xray,
 };
 
+mod shared_pool;
+mod topup;
+
+use shared_pool::{SharedQuotaCycle, enforce_shared_node_quota_node};
+pub(crate) use topup::{active_topups, topup_remaining_bytes};
+use topup::{active_topups_by_user, consume_topups};
+
 const P1_CARRY_DAYS: u32 = 7;
 const P2_CARRY_DAYS: u32 = 2;
 
@@ -74,16 +81,12 @@ struct MembershipQuotaSnapshot {
     endpoint_id: String,
     node_id: String,
     endpoint_tag: Option<String>,
+    /// Second Xray client of the membership while a credential rotation is in progress.
+    alternate_email: Option<String>,
     node_quota_limit_bytes: u64,
-    quota_reset_policy: QuotaResetPolicy,
+    /// `None` when the node quota never resets (`unlimited`).
+    cycle_schedule: Option<CycleSchedule>,
     cycle_tz: CycleTimeZone,
-    cycle_day_of_month: u8,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum QuotaResetPolicy {
-    Monthly,
-    Unlimited,
 }
 
 #[derive(Debug, Clone)]
@@ -99,38 +102,23 @@ fn map_cycle_error(subject: &str, err: CycleWindowError) -> anyhow::Error {
 fn resolve_node_quota_reset(
     store: &JsonSnapshotStore,
     node_id: &str,
-) -> anyhow::Result<(QuotaResetPolicy, CycleTimeZone, u8)> {
+) -> anyhow::Result<(Option<CycleSchedule>, CycleTimeZone)> {
     let node = store
         .get_node(node_id)
         .ok_or_else(|| anyhow::anyhow!("node not found: {node_id}"))?;
 
-    let (policy, day_of_month, tz) = match node.quota_reset {
-        NodeQuotaReset::Unlimited { tz_offset_minutes } => (
-            QuotaResetPolicy::Unlimited,
-            1,
-            match tz_offset_minutes {
-                Some(tz_offset_minutes) => CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes },
-                None => CycleTimeZone::Local,
-            },
-        ),
-        NodeQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => (
-            QuotaResetPolicy::Monthly,
-            day_of_month,
-            match tz_offset_minutes {
-                Some(tz_offset_minutes) => CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes },
-                None => CycleTimeZone::Local,
-            },
-        ),
-    };
-
-    if !(1..=31).contains(&day_of_month) {
-        return Err(anyhow::anyhow!("invalid day_of_month: {day_of_month}"));
+    let schedule = node.quota_reset.cycle_schedule();
+    match schedule {
+        Some(CycleSchedule::Monthly { day_of_month }) if !(1..=31).contains(&day_of_month) => {
+            return Err(anyhow::anyhow!("invalid day_of_month: {day_of_month}"));
+        }
+        Some(CycleSchedule::FixedDays { length_days: 0, .. }) => {
+            return Err(anyhow::anyhow!("invalid length_days: 0"));
+        }
+        _ => {}
     }
 
-    Ok((policy, tz, day_of_month))
+    Ok((schedule, node.quota_reset.cycle_time_zone()))
 }
 
 pub async fn run_quota_tick_at(
@@ -172,19 +160,19 @@ async fn run_quota_tick_at_with_geo(
             .map(|n| n.quota_limit_bytes)
             .unwrap_or(0);
 
-        let (quota_reset_policy, cycle_tz, cycle_day_of_month) =
-            match resolve_node_quota_reset(&store, &local_node_id) {
-                Ok(v) => v,
-                Err(err) => {
-                    warn!(
-                        node_id = local_node_id,
-                        %err,
-                        "quota tick skip: node quota reset resolution failed"
-                    );
-                    return Ok(());
-                }
-            };
+        let (cycle_schedule, cycle_tz) = match resolve_node_quota_reset(&store, &local_node_id) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!(
+                    node_id = local_node_id,
+                    %err,
+                    "quota tick skip: node quota reset resolution failed"
+                );
+                return Ok(());
+            }
+        };
 
+        let rotation_active = store.state().credential_rotation.is_some();
         let mut out = Vec::new();
         for membership in store
             .state()
@@ -204,10 +192,12 @@ async fn run_quota_tick_at_with_geo(
                 endpoint_id: membership.endpoint_id.clone(),
                 node_id: local_node_id.clone(),
                 endpoint_tag: Some(endpoint.tag.clone()),
+                alternate_email: rotation_active.then(|| {
+                    membership_xray_alternate_email(&membership.user_id, &membership.endpoint_id)
+                }),
                 node_quota_limit_bytes,
-                quota_reset_policy,
+                cycle_schedule,
                 cycle_tz,
-                cycle_day_of_month,
             });
         }
 
@@ -311,7 +301,13 @@ async fn run_quota_tick_at_with_geo(
                     };
 
                     match client.get_user_online_ip_list(&email).await {
-                        Ok(ips) => {
+                        Ok(mut ips) => {
+                            if let Some(alternate_email) = snapshot.alternate_email.as_deref()
+                                && let Ok(alternate_ips) =
+                                    client.get_user_online_ip_list(alternate_email).await
+                            {
+                                ips.extend(alternate_ips);
+                            }
                             online_samples.push(crate::inbound_ip_usage::InboundIpMinuteSample {
                                 membership_key: snapshot.membership_key.clone(),
                                 user_id: snapshot.user_id.clone(),
@@ -417,9 +413,30 @@ async fn run_quota_tick_at_with_geo(
             ) {
                 warn!(%err, "quota tick: failed to persist inbound ip usage snapshot");
             }
+            drop(store_guard);
+
+            if let Some(client) = minute_client.as_mut()
+                && !online_stats_unavailable
+                && let Err(err) = enforce_user_ip_limits(
+                    sample_minute,
+                    store,
+                    reconcile,
+                    client,
+                    &snapshots,
+                    &online_samples,
+                    geo_resolver,
+                )
+                .await
+            {
+                warn!(%err, "quota tick: concurrent ip limit enforcement failed");
+            }
         }
     }
 
+    if let Err(err) = lift_expired_ip_limit_blocks(now, store, reconcile).await {
+        warn!(%err, "quota tick: failed to lift expired concurrent ip limit blocks");
+    }
+
     if snapshots.is_empty() {
         return Ok(());
     }
@@ -458,7 +475,7 @@ async fn run_quota_tick_at_with_geo(
         return Ok(());
     };
 
-    if let Err(err) = enforce_shared_node_quota_node(
+    let shared_cycle = match enforce_shared_node_quota_node(
         now,
         store,
         reconcile,
@@ -469,7 +486,24 @@ async fn run_quota_tick_at_with_geo(
     )
     .await
     {
-        warn!(%err, "quota tick: shared node quota enforcement failed");
+        Ok(shared_cycle) => shared_cycle,
+        Err(err) => {
+            warn!(%err, "quota tick: shared node quota enforcement failed");
+            return Ok(());
+        }
+    };
+
+    if let Err(err) = evaluate_quota_warnings(
+        now,
+        store,
+        &config.quota_warning_thresholds,
+        &node_id,
+        &by_user,
+        shared_cycle.as_ref(),
+    )
+    .await
+    {
+        warn!(%err, "quota tick: quota warning evaluation failed");
     }
 
     Ok(())
@@ -481,21 +515,37 @@ async fn update_membership_usage_once(
     client: &mut xray::XrayClient,
     snapshot: MembershipQuotaSnapshot,
 ) -> anyhow::Result<MembershipUsageTick> {
-    let (cycle_start_at, cycle_end_at) = match snapshot.quota_reset_policy {
-        QuotaResetPolicy::Monthly => {
+    let (cycle_start_at, cycle_end_at) = match snapshot.cycle_schedule {
+        Some(schedule) => {
             let (cycle_start, cycle_end) =
-                current_cycle_window_at(snapshot.cycle_tz, snapshot.cycle_day_of_month, now)
+                current_cycle_window_at(snapshot.cycle_tz, schedule, now)
                     .map_err(|err| map_cycle_error(&snapshot.membership_key, err))?;
             (cycle_start.to_rfc3339(), cycle_end.to_rfc3339())
         }
-        QuotaResetPolicy::Unlimited => (
+        None => (
             "1970-01-01T00:00:00Z".to_string(),
             "9999-12-31T23:59:59Z".to_string(),
         ),
     };
 
     let email = membership_xray_email(&snapshot.user_id, &snapshot.endpoint_id);
-    let (uplink_total, downlink_total) = match client.get_user_traffic_totals(&email).await {
+    let totals = match snapshot.alternate_email.as_deref() {
+        // Both clients count towards the membership; when the alternate one goes away the total
+        // drops and the usage baseline resets instead of double counting.
+        Some(alternate_email) => match client.get_user_traffic_totals(&email).await {
+            Ok((uplink, downlink)) => client.get_user_traffic_totals(alternate_email).await.map(
+                |(alt_uplink, alt_downlink)| {
+                    (
+                        uplink.saturating_add(alt_uplink),
+                        downlink.saturating_add(alt_downlink),
+                    )
+                },
+            ),
+            Err(status) => Err(status),
+        },
+        None => client.get_user_traffic_totals(&email).await,
+    };
+    let (uplink_total, downlink_total) = match totals {
         Ok(v) => v,
         Err(status) => {
             warn!(
@@ -531,581 +581,220 @@ async fn update_membership_usage_once(
     })
 }
 
-async fn enforce_shared_node_quota_node(
+/// Records, once per cycle, each configured threshold a user's node quota usage has crossed.
+///
+/// The quota is the user's base share (or what they already used beyond it) plus remaining
+/// top-up bytes; the exhaustion projection extrapolates the average rate since cycle start.
+async fn evaluate_quota_warnings(
     now: DateTime<Utc>,
     store: &Arc<Mutex<JsonSnapshotStore>>,
-    reconcile: &ReconcileHandle,
-    client: &mut xray::XrayClient,
-    quota_auto_unban: bool,
+    thresholds: &[u8],
     node_id: &str,
     by_user: &std::collections::BTreeMap<String, Vec<MembershipUsageTick>>,
+    shared_cycle: Option<&SharedQuotaCycle>,
 ) -> anyhow::Result<()> {
-    let Some(first) = by_user.values().next().and_then(|g| g.first()) else {
-        return Ok(());
-    };
-    let quota_reset_policy = first.snapshot.quota_reset_policy;
-    let cycle_tz = first.snapshot.cycle_tz;
-    let cycle_day_of_month = first.snapshot.cycle_day_of_month;
-    let node_quota_limit_bytes = first.snapshot.node_quota_limit_bytes;
-
-    if quota_reset_policy == QuotaResetPolicy::Unlimited || node_quota_limit_bytes == 0 {
-        // Shared quota is not enforceable without a finite cycle budget.
-        // Best-effort: clear local quota bans and pacing state on this node.
-        let mut store = store.lock().await;
-        let (_remove_ops, changed) = store
-            .update_usage(|usage| {
-                let mut changed = false;
-                for group in by_user.values() {
-                    for tick in group {
-                        if let Some(u) = usage.memberships.get_mut(&tick.snapshot.membership_key)
-                            && u.quota_banned
-                        {
-                            u.quota_banned = false;
-                            u.quota_banned_at = None;
-                            changed = true;
-                        }
-                    }
-                }
-
-                usage.node_pacing.remove(node_id);
-                for (_user_id, nodes) in usage.user_node_pacing.iter_mut() {
-                    nodes.remove(node_id);
-                }
-                usage
-                    .user_node_pacing
-                    .retain(|_user_id, nodes| !nodes.is_empty());
-
-                (Vec::<(String, String)>::new(), changed)
-            })
-            .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
+    let mut store = store.lock().await;
+    let mut warnings = std::collections::BTreeMap::new();
+    if let Some(cycle) = shared_cycle.filter(|_| !thresholds.is_empty()) {
+        let cycle_start_at = cycle.cycle_start.to_rfc3339();
+        // Users whose sample failed this tick keep what they already reached.
+        warnings.extend(
+            store
+                .quota_warnings()
+                .iter()
+                .filter(|(_, warning)| warning.cycle_start_at == cycle_start_at)
+                .map(|(user_id, warning)| (user_id.clone(), warning.clone())),
+        );
+        for (user_id, group) in by_user {
+            let Some(base_bytes) = cycle.base_by_user.get(user_id).copied() else {
+                continue;
+            };
+            let used_bytes = group
+                .iter()
+                .fold(0u64, |acc, tick| acc.saturating_add(tick.used_bytes));
+            let topups = active_topups(store.state(), user_id, node_id, now);
+            let quota_bytes = base_bytes
+                .max(used_bytes)
+                .saturating_add(topup_remaining_bytes(store.topup_consumed_bytes(), &topups));
+            if quota_bytes == 0 {
+                continue;
+            }
+            let used_percent = u128::from(used_bytes) * 100 / u128::from(quota_bytes);
 
-        if changed {
-            reconcile.request_full();
+            let mut reached_percents = warnings
+                .get(user_id)
+                .map(|warning| warning.reached_percents.clone())
+                .unwrap_or_default();
+            let Some(threshold_percent) = thresholds
+                .iter()
+                .copied()
+                .filter(|percent| u128::from(*percent) <= used_percent)
+                .filter(|percent| reached_percents.insert(*percent))
+                .max()
+            else {
+                continue;
+            };
+            let remaining_bytes = quota_bytes.saturating_sub(used_bytes);
+            let warning = crate::state::UserQuotaWarning {
+                cycle_start_at: cycle_start_at.clone(),
+                reached_percents,
+                threshold_percent,
+                reached_at: now.to_rfc3339(),
+                quota_bytes,
+                used_bytes,
+                remaining_bytes,
+                projected_exhaustion_at: projected_exhaustion_at(
+                    cycle,
+                    now,
+                    used_bytes,
+                    remaining_bytes,
+                )
+                .map(|at| at.to_rfc3339()),
+            };
+            warnings.insert(user_id.clone(), warning);
         }
-        return Ok(());
     }
 
-    let (cycle_start, cycle_end) = current_cycle_window_at(cycle_tz, cycle_day_of_month, now)
-        .map_err(|err| {
-            anyhow::anyhow!(
-                "node_id={node_id} cycle window error: {}",
-                map_cycle_error("shared", err)
-            )
-        })?;
-    let cycle_start_at = cycle_start.to_rfc3339();
-    let cycle_end_at = cycle_end.to_rfc3339();
+    if *store.quota_warnings() != warnings {
+        store
+            .update_usage(|usage| usage.quota_warnings = warnings)
+            .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
+    }
+    Ok(())
+}
 
-    let cycle_days_i64 = (cycle_end.date_naive() - cycle_start.date_naive()).num_days();
-    if cycle_days_i64 <= 0 {
-        return Err(anyhow::anyhow!(
-            "node_id={node_id} invalid cycle_days: {cycle_days_i64}"
-        ));
+/// When `remaining_bytes` runs out at the average rate observed since the cycle started, if
+/// that happens before the cycle resets.
+fn projected_exhaustion_at(
+    cycle: &SharedQuotaCycle,
+    now: DateTime<Utc>,
+    used_bytes: u64,
+    remaining_bytes: u64,
+) -> Option<DateTime<Utc>> {
+    let elapsed_secs = now.signed_duration_since(cycle.cycle_start).num_seconds();
+    if used_bytes == 0 || elapsed_secs <= 0 {
+        return None;
     }
-    let cycle_days = cycle_days_i64 as u32;
+    let secs_left = u128::from(remaining_bytes) * elapsed_secs as u128 / u128::from(used_bytes);
+    let at = now + chrono::Duration::seconds(i64::try_from(secs_left).ok()?);
+    (at < cycle.cycle_end).then_some(at)
+}
 
-    // Compute the day index in the configured timezone.
-    //
-    // - Fixed offset: use the configured offset consistently for both cycle_start and now.
-    // - Local: don't pin `now` to the cycle start offset because DST transitions can change the
-    //   offset within a single cycle.
-    let (cycle_start_date_local, now_date_local) = match cycle_tz {
-        CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes } => {
-            let offset_seconds = i32::from(tz_offset_minutes) * 60;
-            let offset = FixedOffset::east_opt(offset_seconds).ok_or_else(|| {
-                anyhow::anyhow!("node_id={node_id} invalid tz_offset_minutes: {tz_offset_minutes}")
-            })?;
-            (
-                cycle_start.with_timezone(&offset).date_naive(),
-                now.with_timezone(&offset).date_naive(),
-            )
-        }
-        CycleTimeZone::Local => (
-            cycle_start.with_timezone(&Local).date_naive(),
-            now.with_timezone(&Local).date_naive(),
-        ),
-    };
-    let today_index_i64 = (now_date_local - cycle_start_date_local).num_days();
-    let mut today_index = today_index_i64.max(0) as i32;
-    if today_index >= cycle_days as i32 {
-        today_index = (cycle_days as i32).saturating_sub(1);
+/// Counts distinct online source IPs per user across the user's memberships on this node and
+/// removes users that stay above `max_concurrent_ips` from Xray until their block runs out.
+async fn enforce_user_ip_limits(
+    minute: DateTime<Utc>,
+    store: &Arc<Mutex<JsonSnapshotStore>>,
+    reconcile: &ReconcileHandle,
+    client: &mut xray::XrayClient,
+    snapshots: &[MembershipQuotaSnapshot],
+    samples: &[crate::inbound_ip_usage::InboundIpMinuteSample],
+    geo_resolver: &SharedGeoResolver,
+) -> anyhow::Result<()> {
+    let mut store_guard = store.lock().await;
+    let limits = snapshots
+        .iter()
+        .filter_map(|snapshot| {
+            let max = store_guard
+                .get_user(&snapshot.user_id)?
+                .max_concurrent_ips?;
+            Some((snapshot.user_id.clone(), max))
+        })
+        .collect::<std::collections::BTreeMap<_, _>>();
+    if limits.is_empty() && store_guard.ip_limit_states().is_empty() {
+        return Ok(());
     }
 
-    // Pre-compute totals to keep the usage update closure simple.
-    let mut total_used_by_user = std::collections::BTreeMap::<String, u64>::new();
-    for (user_id, group) in by_user {
-        let total = group
-            .iter()
-            .fold(0u64, |acc, t| acc.saturating_add(t.used_bytes));
-        total_used_by_user.insert(user_id.clone(), total);
+    let mut online_ips_by_user =
+        std::collections::BTreeMap::<String, std::collections::BTreeSet<String>>::new();
+    for sample in samples {
+        online_ips_by_user
+            .entry(sample.user_id.clone())
+            .or_default()
+            .extend(
+                sample
+                    .ips
+                    .iter()
+                    .filter_map(|ip| crate::inbound_ip_usage::normalize_ip_string(ip)),
+            );
     }
 
-    // Compute tier/weight and the base quota distribution (P1+P2 cut the full distributable).
-    let mut enabled_users: Vec<String> = Vec::new();
-    let mut tier_by_user: std::collections::BTreeMap<String, UserPriorityTier> =
-        std::collections::BTreeMap::new();
-    let mut weight_by_user: std::collections::BTreeMap<String, u16> =
-        std::collections::BTreeMap::new();
-    {
-        let store = store.lock().await;
-        for user_id in by_user.keys() {
-            if user_id == crate::endpoint_probe::PROBE_USER_ID {
-                continue;
-            }
-            enabled_users.push(user_id.clone());
-            let tier = store
-                .get_user(user_id)
-                .map(|u| u.priority_tier)
-                .unwrap_or_default();
-            let weight = store.resolve_user_node_weight(user_id, node_id);
-            tier_by_user.insert(user_id.clone(), tier);
-            weight_by_user.insert(user_id.clone(), weight);
-        }
+    let newly_blocked = store_guard
+        .update_usage(|usage| {
+            crate::ip_limit::observe_minute(
+                &mut usage.ip_limits,
+                &limits,
+                &online_ips_by_user,
+                minute,
+                geo_resolver,
+            )
+        })
+        .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
+    drop(store_guard);
+    if newly_blocked.is_empty() {
+        return Ok(());
     }
-    enabled_users.sort();
-    enabled_users.dedup();
+    reconcile.request_full();
 
-    let mut p1p2_items: Vec<(String, u16)> = Vec::new();
-    let mut p1_items: Vec<(String, u16)> = Vec::new();
-    let mut p3_items: Vec<(String, u16)> = Vec::new();
-    for user_id in enabled_users.iter() {
-        let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
-        let weight = weight_by_user.get(user_id).copied().unwrap_or(100);
-        match tier {
-            UserPriorityTier::P1 => {
-                p1p2_items.push((user_id.clone(), weight));
-                p1_items.push((user_id.clone(), weight));
-            }
-            UserPriorityTier::P2 => {
-                p1p2_items.push((user_id.clone(), weight));
-            }
-            UserPriorityTier::P3 => {
-                p3_items.push((user_id.clone(), weight));
+    for snapshot in snapshots
+        .iter()
+        .filter(|snapshot| newly_blocked.contains(&snapshot.user_id))
+    {
+        let Some(tag) = snapshot.endpoint_tag.as_deref() else {
+            continue;
+        };
+        warn!(
+            user_id = %snapshot.user_id,
+            membership_key = %snapshot.membership_key,
+            "quota tick: concurrent ip limit exceeded; blocking membership"
+        );
+        use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
+        let email = membership_xray_email(&snapshot.user_id, &snapshot.endpoint_id);
+        for email in std::iter::once(&email).chain(&snapshot.alternate_email) {
+            let req = AlterInboundRequest {
+                tag: tag.to_string(),
+                operation: Some(crate::xray::builder::build_remove_user_operation(email)),
+            };
+            match client.alter_inbound(req).await {
+                Ok(_) => {}
+                Err(status) if xray::is_not_found(&status) => {}
+                Err(status) => warn!(
+                    endpoint_tag = tag,
+                    %status,
+                    "quota tick: xray alter_inbound remove_user failed"
+                ),
             }
         }
     }
+    Ok(())
+}
 
-    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
-    let base_alloc = quota_policy::allocate_total_by_weight(distributable, &p1p2_items);
-    let base_by_user: std::collections::BTreeMap<String, u64> = base_alloc.into_iter().collect();
-
-    let now_rfc3339 = now.to_rfc3339();
+async fn lift_expired_ip_limit_blocks(
+    now: DateTime<Utc>,
+    store: &Arc<Mutex<JsonSnapshotStore>>,
+    reconcile: &ReconcileHandle,
+) -> anyhow::Result<()> {
     let mut store = store.lock().await;
-    let (remove_ops, changed) = store
+    let limits = store
+        .list_users()
+        .into_iter()
+        .filter_map(|user| Some((user.user_id, user.max_concurrent_ips?)))
+        .collect::<std::collections::BTreeMap<_, _>>();
+    let stale = store.ip_limit_states().iter().any(|(user_id, state)| {
+        !limits.contains_key(user_id)
+            || (state.blocked_until.is_some() && !state.is_blocked_at(now))
+    });
+    if !stale {
+        return Ok(());
+    }
+    let lifted = store
         .update_usage(|usage| {
-            let mut changed = false;
-            let mut remove_ops: Vec<(String, String)> = Vec::new();
-
-            let node_pacing =
-                usage
-                    .node_pacing
-                    .entry(node_id.to_string())
-                    .or_insert(crate::state::NodePacing {
-                        cycle_start_at: cycle_start_at.clone(),
-                        cycle_end_at: cycle_end_at.clone(),
-                        last_day_index: -1,
-                    });
-
-            let cycle_changed = node_pacing.cycle_start_at != cycle_start_at
-                || node_pacing.cycle_end_at != cycle_end_at;
-            if cycle_changed {
-                node_pacing.cycle_start_at = cycle_start_at.clone();
-                node_pacing.cycle_end_at = cycle_end_at.clone();
-                node_pacing.last_day_index = -1;
-
-                // Reset per-user pacing for this node (bank + last_total_used).
-                for (_user_id, nodes) in usage.user_node_pacing.iter_mut() {
-                    nodes.remove(node_id);
-                }
-                usage
-                    .user_node_pacing
-                    .retain(|_user_id, nodes| !nodes.is_empty());
-
-                // Auto-unban on cycle rollover.
-                if quota_auto_unban {
-                    for group in by_user.values() {
-                        for tick in group {
-                            if let Some(u) =
-                                usage.memberships.get_mut(&tick.snapshot.membership_key)
-                                && u.quota_banned
-                            {
-                                u.quota_banned = false;
-                                u.quota_banned_at = None;
-                                changed = true;
-                            }
-                        }
-                    }
-                }
-            }
-
-            if node_pacing.last_day_index > today_index {
-                node_pacing.last_day_index = today_index;
-            }
-
-            // Capture each user's bank as-of the last tick day before applying missed rollovers.
-            // When a tick spans multiple days, we may need to replay rollovers + spending to avoid
-            // false bans caused by cap decreasing due to uneven daily credit distribution.
-            let mut pre_rollover_bank_by_user = std::collections::BTreeMap::<String, u64>::new();
-            for user_id in enabled_users.iter() {
-                let bank = usage
-                    .user_node_pacing
-                    .get(user_id)
-                    .and_then(|nodes| nodes.get(node_id))
-                    .map(|p| p.bank_bytes)
-                    .unwrap_or(0);
-                pre_rollover_bank_by_user.insert(user_id.clone(), bank);
-            }
-
-            // Day rollovers: refill banks + overflow chain.
-            let initial_last_day_index = node_pacing.last_day_index;
-            let mut day = node_pacing.last_day_index.saturating_add(1);
-            while day <= today_index {
-                let day_u32 = day.max(0) as u32;
-                let mut p1_pool = 0u64;
-                let mut p3_pool = 0u64;
-
-                for user_id in enabled_users.iter() {
-                    let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
-                    let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
-
-                    let entry = usage
-                        .user_node_pacing
-                        .entry(user_id.clone())
-                        .or_default()
-                        .entry(node_id.to_string())
-                        .or_insert(crate::state::UserNodePacing {
-                            bank_bytes: 0,
-                            last_total_used_bytes: 0,
-                            last_base_quota_bytes: 0,
-                            last_priority_tier: Default::default(),
-                        });
-
-                    // P3 quota expires daily.
-                    if tier == UserPriorityTier::P3 {
-                        entry.bank_bytes = 0;
-                        // Preserve tier state so the reconciliation phase in the same tick doesn't
-                        // treat this as a tier transition and wipe overflow tokens allocated later.
-                        entry.last_base_quota_bytes = 0;
-                        entry.last_priority_tier = tier;
-                        continue;
-                    }
-
-                    let carry_days = match tier {
-                        UserPriorityTier::P1 => P1_CARRY_DAYS,
-                        UserPriorityTier::P2 => P2_CARRY_DAYS,
-                        UserPriorityTier::P3 => 0,
-                    };
-
-                    let (bank, overflow) = quota_policy::apply_daily_rollover(
-                        entry.bank_bytes,
-                        base_quota,
-                        cycle_days,
-                        day_u32,
-                        carry_days,
-                    );
-                    entry.bank_bytes = bank;
-
-                    match tier {
-                        UserPriorityTier::P1 => p3_pool = p3_pool.saturating_add(overflow),
-                        UserPriorityTier::P2 => p1_pool = p1_pool.saturating_add(overflow),
-                        UserPriorityTier::P3 => {}
-                    }
-                }
-
-                // P1 can take P2's pacing overflow.
-                if p1_pool > 0 {
-                    if !p1_items.is_empty() {
-                        for (user_id, bonus) in
-                            quota_policy::allocate_total_by_weight(p1_pool, &p1_items)
-                        {
-                            let base_quota = base_by_user.get(&user_id).copied().unwrap_or(0);
-                            let entry = usage
-                                .user_node_pacing
-                                .entry(user_id.clone())
-                                .or_default()
-                                .entry(node_id.to_string())
-                                .or_insert(crate::state::UserNodePacing {
-                                    bank_bytes: 0,
-                                    last_total_used_bytes: 0,
-                                    last_base_quota_bytes: 0,
-                                    last_priority_tier: Default::default(),
-                                });
-                            entry.bank_bytes = entry.bank_bytes.saturating_add(bonus);
-
-                            let cap = quota_policy::cap_bytes_for_day(
-                                base_quota,
-                                cycle_days,
-                                day_u32,
-                                P1_CARRY_DAYS,
-                            );
-                            if entry.bank_bytes > cap {
-                                let overflow = entry.bank_bytes - cap;
-                                entry.bank_bytes = cap;
-                                p3_pool = p3_pool.saturating_add(overflow);
-                            }
-                        }
-                    } else {
-                        // If no P1 users exist on this node, P2 pacing overflow becomes general
-                        // surplus that P3 can opportunistically consume.
-                        p3_pool = p3_pool.saturating_add(p1_pool);
-                    }
-                }
-
-                // P3 can take any remaining overflow (no carry).
-                if p3_pool > 0 && !p3_items.is_empty() {
-                    for (user_id, bonus) in
-                        quota_policy::allocate_total_by_weight(p3_pool, &p3_items)
-                    {
-                        let entry = usage
-                            .user_node_pacing
-                            .entry(user_id.clone())
-                            .or_default()
-                            .entry(node_id.to_string())
-                            .or_insert(crate::state::UserNodePacing {
-                                bank_bytes: 0,
-                                last_total_used_bytes: 0,
-                                last_base_quota_bytes: 0,
-                                last_priority_tier: Default::default(),
-                            });
-                        entry.bank_bytes = entry.bank_bytes.saturating_add(bonus);
-                    }
-                }
-
-                node_pacing.last_day_index = day;
-                day += 1;
-            }
-
-            // Policy reconciliation: when quota inputs change mid-cycle (node quota limit,
-            // user count, user tier, weights), make pacing changes effective immediately
-            // instead of waiting for the next day rollover.
-            //
-            // We do this by adjusting each user's bank by the cap delta for *today*.
-            // If the cap is reduced below what the user already consumed, we force an
-            // immediate local-only ban even when `delta == 0` for this tick.
-            //
-            // Important: when a day rollover ran in this tick, banks have already been computed
-            // against the current policy for today. In that case we should *not* apply the cap
-            // delta again, or we'd risk false bans. We still update `last_*` fields to keep the
-            // next tick consistent.
-            let did_day_rollover = node_pacing.last_day_index != initial_last_day_index;
-            let do_cap_reconcile = !did_day_rollover;
-            let mut force_ban_users = std::collections::BTreeSet::<String>::new();
-            let today_u32 = today_index.max(0) as u32;
-            for user_id in enabled_users.iter() {
-                let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
-                let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
-
-                let entry = usage
-                    .user_node_pacing
-                    .entry(user_id.clone())
-                    .or_default()
-                    .entry(node_id.to_string())
-                    .or_insert(crate::state::UserNodePacing {
-                        bank_bytes: 0,
-                        last_total_used_bytes: 0,
-                        last_base_quota_bytes: 0,
-                        last_priority_tier: Default::default(),
-                    });
-
-                // P3 has no base quota, but may have overflow tokens for today.
-                // Only clear the bank on a tier transition (e.g. P1->P3).
-                if tier == UserPriorityTier::P3 {
-                    if entry.last_priority_tier != UserPriorityTier::P3 {
-                        entry.bank_bytes = 0;
-                    }
-
-                    // P3 should not have access unless it has overflow tokens. If the bank is
-                    // empty, force an immediate ban (even if `delta == 0`), so reconcile removes
-                    // the user from the inbound config.
-                    if entry.bank_bytes == 0 {
-                        force_ban_users.insert(user_id.clone());
-                    }
-                    entry.last_base_quota_bytes = 0;
-                    entry.last_priority_tier = tier;
-                    continue;
-                }
-
-                let new_carry = match tier {
-                    UserPriorityTier::P1 => P1_CARRY_DAYS,
-                    UserPriorityTier::P2 => P2_CARRY_DAYS,
-                    UserPriorityTier::P3 => 0,
-                };
-                let cap_new =
-                    quota_policy::cap_bytes_for_day(base_quota, cycle_days, today_u32, new_carry);
-
-                if do_cap_reconcile {
-                    let old_base = entry.last_base_quota_bytes;
-                    let old_tier = entry.last_priority_tier;
-
-                    let old_carry = match old_tier {
-                        UserPriorityTier::P1 => P1_CARRY_DAYS,
-                        UserPriorityTier::P2 => P2_CARRY_DAYS,
-                        UserPriorityTier::P3 => 0,
-                    };
-                    let cap_old =
-                        quota_policy::cap_bytes_for_day(old_base, cycle_days, today_u32, old_carry);
-
-                    if cap_new >= cap_old {
-                        entry.bank_bytes = entry.bank_bytes.saturating_add(cap_new - cap_old);
-                    } else {
-                        let drop = cap_old - cap_new;
-                        if entry.bank_bytes < drop {
-                            // User already overused relative to the new cap; ban immediately.
-                            entry.bank_bytes = 0;
-                            force_ban_users.insert(user_id.clone());
-                        } else {
-                            entry.bank_bytes = entry.bank_bytes.saturating_sub(drop);
-                        }
-                    }
-                }
-
-                // Clamp to the new cap (both in reconcile and rollover cases).
-                if entry.bank_bytes > cap_new {
-                    entry.bank_bytes = cap_new;
-                }
-
-                entry.last_base_quota_bytes = base_quota;
-                entry.last_priority_tier = tier;
-            }
-
-            // Apply traffic deltas: consume banks + ban/unban grants locally.
-            for user_id in enabled_users.iter() {
-                let Some(group) = by_user.get(user_id) else {
-                    continue;
-                };
-
-                let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
-                let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
-                let carry_days = match tier {
-                    UserPriorityTier::P1 => P1_CARRY_DAYS,
-                    UserPriorityTier::P2 => P2_CARRY_DAYS,
-                    UserPriorityTier::P3 => 0,
-                };
-
-                let total_used = total_used_by_user.get(user_id).copied().unwrap_or(0);
-                let entry = usage
-                    .user_node_pacing
-                    .entry(user_id.clone())
-                    .or_default()
-                    .entry(node_id.to_string())
-                    .or_insert(crate::state::UserNodePacing {
-                        bank_bytes: 0,
-                        last_total_used_bytes: 0,
-                        last_base_quota_bytes: 0,
-                        last_priority_tier: Default::default(),
-                    });
-
-                let delta = total_used.saturating_sub(entry.last_total_used_bytes);
-                entry.last_total_used_bytes = total_used;
-
-                let mut banned_this_tick = false;
-                let mut exceeded_bank = delta > entry.bank_bytes;
-                let mut consumed_via_replay = false;
-                if exceeded_bank
-                    && did_day_rollover
-                    && carry_days > 0
-                    && !force_ban_users.contains(user_id)
-                {
-                    let pre_bank = pre_rollover_bank_by_user.get(user_id).copied().unwrap_or(0);
-                    let day_start = initial_last_day_index.saturating_add(1).max(0) as u32;
-                    let day_end = today_u32;
-
-                    let (bank_after, remaining) = quota_policy::replay_rollovers_and_spend(
-                        pre_bank, delta, base_quota, cycle_days, day_start, day_end, carry_days,
-                    );
-                    if remaining == 0 {
-                        entry.bank_bytes = bank_after;
-                        exceeded_bank = false;
-                        consumed_via_replay = true;
-                    }
-                }
-
-                if force_ban_users.contains(user_id) || exceeded_bank {
-                    entry.bank_bytes = 0;
-                    banned_this_tick = true;
-
-                    for tick in group {
-                        let u = usage
-                            .memberships
-                            .entry(tick.snapshot.membership_key.clone())
-                            .or_insert(crate::state::MembershipUsage {
-                                cycle_start_at: cycle_start_at.clone(),
-                                cycle_end_at: cycle_end_at.clone(),
-                                used_bytes: 0,
-                                last_uplink_total: 0,
-                                last_downlink_total: 0,
-                                last_seen_at: now_rfc3339.clone(),
-                                quota_banned: false,
-                                quota_banned_at: None,
-                            });
-                        if !u.quota_banned {
-                            u.quota_banned = true;
-                            u.quota_banned_at = Some(now_rfc3339.clone());
-                            changed = true;
-                        }
-
-                        if let Some(tag) = tick.snapshot.endpoint_tag.as_deref() {
-                            let email = membership_xray_email(
-                                &tick.snapshot.user_id,
-                                &tick.snapshot.endpoint_id,
-                            );
-                            remove_ops.push((tag.to_string(), email));
-                        }
-                    }
-                } else if delta > 0 && !consumed_via_replay {
-                    entry.bank_bytes = entry.bank_bytes.saturating_sub(delta);
-                }
-
-                // Auto-unban once the user has positive bank again.
-                if quota_auto_unban && !banned_this_tick && entry.bank_bytes > 0 {
-                    let any_banned = group.iter().any(|tick| {
-                        usage
-                            .memberships
-                            .get(&tick.snapshot.membership_key)
-                            .is_some_and(|u| u.quota_banned)
-                    });
-                    if any_banned {
-                        for tick in group {
-                            if let Some(u) =
-                                usage.memberships.get_mut(&tick.snapshot.membership_key)
-                                && u.quota_banned
-                            {
-                                u.quota_banned = false;
-                                u.quota_banned_at = None;
-                                changed = true;
-                            }
-                        }
-                    }
-                }
-            }
-
-            (remove_ops, changed)
+            crate::ip_limit::lift_expired_blocks(&mut usage.ip_limits, &limits, now)
         })
         .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
-
-    if changed {
+    if lifted {
         reconcile.request_full();
     }
-    drop(store);
-
-    for (tag, email) in remove_ops {
-        use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
-        let op = crate::xray::builder::build_remove_user_operation(&email);
-        let req = AlterInboundRequest {
-            tag: tag.clone(),
-            operation: Some(op),
-        };
-        match client.alter_inbound(req).await {
-            Ok(_) => {}
-            Err(status) if xray::is_not_found(&status) => {}
-            Err(status) => warn!(
-                node_id = node_id,
-                endpoint_tag = tag,
-                %status,
-                "quota tick: xray alter_inbound remove_user failed"
-            ),
-        }
-    }
-
     Ok(())
 }
 
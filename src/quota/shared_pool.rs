@@ -0,0 +1,647 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use super::{
+    MembershipUsageTick, P1_CARRY_DAYS, P2_CARRY_DAYS, active_topups_by_user, consume_topups,
+    map_cycle_error, topup_remaining_bytes,
+};
+use crate::{
+    cycle::{CycleTimeZone, current_cycle_window_at},
+    domain::UserPriorityTier,
+    quota_policy,
+    reconcile::ReconcileHandle,
+    state::{JsonSnapshotStore, membership_xray_email},
+    xray,
+};
+
+/// The finite cycle the shared node quota was enforced against in one tick.
+#[derive(Debug, Clone)]
+pub(super) struct SharedQuotaCycle {
+    pub(super) cycle_start: DateTime<FixedOffset>,
+    pub(super) cycle_end: DateTime<FixedOffset>,
+    /// Base share of the node budget per P1/P2 user; P3 users have none.
+    pub(super) base_by_user: std::collections::BTreeMap<String, u64>,
+}
+
+pub(super) async fn enforce_shared_node_quota_node(
+    now: DateTime<Utc>,
+    store: &Arc<Mutex<JsonSnapshotStore>>,
+    reconcile: &ReconcileHandle,
+    client: &mut xray::XrayClient,
+    quota_auto_unban: bool,
+    node_id: &str,
+    by_user: &std::collections::BTreeMap<String, Vec<MembershipUsageTick>>,
+) -> anyhow::Result<Option<SharedQuotaCycle>> {
+    let Some(first) = by_user.values().next().and_then(|g| g.first()) else {
+        return Ok(None);
+    };
+    let cycle_schedule = first.snapshot.cycle_schedule;
+    let cycle_tz = first.snapshot.cycle_tz;
+    let node_quota_limit_bytes = first.snapshot.node_quota_limit_bytes;
+
+    let Some(cycle_schedule) = cycle_schedule.filter(|_| node_quota_limit_bytes > 0) else {
+        // Shared quota is not enforceable without a finite cycle budget.
+        // Best-effort: clear local quota bans and pacing state on this node.
+        let mut store = store.lock().await;
+        let (_remove_ops, changed) = store
+            .update_usage(|usage| {
+                let mut changed = false;
+                for group in by_user.values() {
+                    for tick in group {
+                        if let Some(u) = usage.memberships.get_mut(&tick.snapshot.membership_key)
+                            && u.quota_banned
+                        {
+                            u.quota_banned = false;
+                            u.quota_banned_at = None;
+                            changed = true;
+                        }
+                    }
+                }
+
+                usage.node_pacing.remove(node_id);
+                for (_user_id, nodes) in usage.user_node_pacing.iter_mut() {
+                    nodes.remove(node_id);
+                }
+                usage
+                    .user_node_pacing
+                    .retain(|_user_id, nodes| !nodes.is_empty());
+
+                (Vec::<(String, String)>::new(), changed)
+            })
+            .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
+
+        if changed {
+            reconcile.request_full();
+        }
+        return Ok(None);
+    };
+
+    let (cycle_start, cycle_end) =
+        current_cycle_window_at(cycle_tz, cycle_schedule, now).map_err(|err| {
+            anyhow::anyhow!(
+                "node_id={node_id} cycle window error: {}",
+                map_cycle_error("shared", err)
+            )
+        })?;
+    let cycle_start_at = cycle_start.to_rfc3339();
+    let cycle_end_at = cycle_end.to_rfc3339();
+
+    let cycle_days_i64 = (cycle_end.date_naive() - cycle_start.date_naive()).num_days();
+    if cycle_days_i64 <= 0 {
+        return Err(anyhow::anyhow!(
+            "node_id={node_id} invalid cycle_days: {cycle_days_i64}"
+        ));
+    }
+    let cycle_days = cycle_days_i64 as u32;
+
+    // Compute the day index in the configured timezone.
+    //
+    // - Fixed offset: use the configured offset consistently for both cycle_start and now.
+    // - Local: don't pin `now` to the cycle start offset because DST transitions can change the
+    //   offset within a single cycle.
+    let (cycle_start_date_local, now_date_local) = match cycle_tz {
+        CycleTimeZone::FixedOffsetMinutes { tz_offset_minutes } => {
+            let offset_seconds = i32::from(tz_offset_minutes) * 60;
+            let offset = FixedOffset::east_opt(offset_seconds).ok_or_else(|| {
+                anyhow::anyhow!("node_id={node_id} invalid tz_offset_minutes: {tz_offset_minutes}")
+            })?;
+            (
+                cycle_start.with_timezone(&offset).date_naive(),
+                now.with_timezone(&offset).date_naive(),
+            )
+        }
+        CycleTimeZone::Local => (
+            cycle_start.with_timezone(&Local).date_naive(),
+            now.with_timezone(&Local).date_naive(),
+        ),
+    };
+    let today_index_i64 = (now_date_local - cycle_start_date_local).num_days();
+    let mut today_index = today_index_i64.max(0) as i32;
+    if today_index >= cycle_days as i32 {
+        today_index = (cycle_days as i32).saturating_sub(1);
+    }
+
+    // Pre-compute totals to keep the usage update closure simple.
+    let mut total_used_by_user = std::collections::BTreeMap::<String, u64>::new();
+    for (user_id, group) in by_user {
+        let total = group
+            .iter()
+            .fold(0u64, |acc, t| acc.saturating_add(t.used_bytes));
+        total_used_by_user.insert(user_id.clone(), total);
+    }
+
+    // Compute tier/weight and the base quota distribution (P1+P2 cut the full distributable).
+    let mut enabled_users: Vec<String> = Vec::new();
+    let mut tier_by_user: std::collections::BTreeMap<String, UserPriorityTier> =
+        std::collections::BTreeMap::new();
+    let mut weight_by_user: std::collections::BTreeMap<String, u16> =
+        std::collections::BTreeMap::new();
+    {
+        let store = store.lock().await;
+        for user_id in by_user.keys() {
+            if user_id == crate::endpoint_probe::PROBE_USER_ID {
+                continue;
+            }
+            enabled_users.push(user_id.clone());
+            let tier = store
+                .get_user(user_id)
+                .map(|u| u.priority_tier)
+                .unwrap_or_default();
+            let weight = store.resolve_user_node_weight(user_id, node_id);
+            tier_by_user.insert(user_id.clone(), tier);
+            weight_by_user.insert(user_id.clone(), weight);
+        }
+    }
+    enabled_users.sort();
+    enabled_users.dedup();
+
+    let mut p1p2_items: Vec<(String, u16)> = Vec::new();
+    let mut p1_items: Vec<(String, u16)> = Vec::new();
+    let mut p3_items: Vec<(String, u16)> = Vec::new();
+    for user_id in enabled_users.iter() {
+        let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
+        let weight = weight_by_user.get(user_id).copied().unwrap_or(100);
+        match tier {
+            UserPriorityTier::P1 => {
+                p1p2_items.push((user_id.clone(), weight));
+                p1_items.push((user_id.clone(), weight));
+            }
+            UserPriorityTier::P2 => {
+                p1p2_items.push((user_id.clone(), weight));
+            }
+            UserPriorityTier::P3 => {
+                p3_items.push((user_id.clone(), weight));
+            }
+        }
+    }
+
+    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
+    let base_alloc = quota_policy::allocate_total_by_weight(distributable, &p1p2_items);
+    let base_by_user: std::collections::BTreeMap<String, u64> = base_alloc.into_iter().collect();
+
+    let now_rfc3339 = now.to_rfc3339();
+    let mut store = store.lock().await;
+    let topups_by_user = active_topups_by_user(store.state(), node_id, &enabled_users, now);
+    let live_topup_ids = store
+        .state()
+        .traffic_topups
+        .keys()
+        .cloned()
+        .collect::<std::collections::BTreeSet<_>>();
+    let (remove_ops, changed) = store
+        .update_usage(|usage| {
+            let mut changed = false;
+            let mut remove_ops: Vec<(String, String)> = Vec::new();
+            usage
+                .topup_consumed_bytes
+                .retain(|topup_id, _| live_topup_ids.contains(topup_id));
+
+            let node_pacing =
+                usage
+                    .node_pacing
+                    .entry(node_id.to_string())
+                    .or_insert(crate::state::NodePacing {
+                        cycle_start_at: cycle_start_at.clone(),
+                        cycle_end_at: cycle_end_at.clone(),
+                        last_day_index: -1,
+                    });
+
+            let cycle_changed = node_pacing.cycle_start_at != cycle_start_at
+                || node_pacing.cycle_end_at != cycle_end_at;
+            if cycle_changed {
+                node_pacing.cycle_start_at = cycle_start_at.clone();
+                node_pacing.cycle_end_at = cycle_end_at.clone();
+                node_pacing.last_day_index = -1;
+
+                // Reset per-user pacing for this node (bank + last_total_used).
+                for (_user_id, nodes) in usage.user_node_pacing.iter_mut() {
+                    nodes.remove(node_id);
+                }
+                usage
+                    .user_node_pacing
+                    .retain(|_user_id, nodes| !nodes.is_empty());
+
+                // Auto-unban on cycle rollover.
+                if quota_auto_unban {
+                    for group in by_user.values() {
+                        for tick in group {
+                            if let Some(u) =
+                                usage.memberships.get_mut(&tick.snapshot.membership_key)
+                                && u.quota_banned
+                            {
+                                u.quota_banned = false;
+                                u.quota_banned_at = None;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if node_pacing.last_day_index > today_index {
+                node_pacing.last_day_index = today_index;
+            }
+
+            // Capture each user's bank as-of the last tick day before applying missed rollovers.
+            // When a tick spans multiple days, we may need to replay rollovers + spending to avoid
+            // false bans caused by cap decreasing due to uneven daily credit distribution.
+            let mut pre_rollover_bank_by_user = std::collections::BTreeMap::<String, u64>::new();
+            for user_id in enabled_users.iter() {
+                let bank = usage
+                    .user_node_pacing
+                    .get(user_id)
+                    .and_then(|nodes| nodes.get(node_id))
+                    .map(|p| p.bank_bytes)
+                    .unwrap_or(0);
+                pre_rollover_bank_by_user.insert(user_id.clone(), bank);
+            }
+
+            // Day rollovers: refill banks + overflow chain.
+            let initial_last_day_index = node_pacing.last_day_index;
+            let mut day = node_pacing.last_day_index.saturating_add(1);
+            while day <= today_index {
+                let day_u32 = day.max(0) as u32;
+                let mut p1_pool = 0u64;
+                let mut p3_pool = 0u64;
+
+                for user_id in enabled_users.iter() {
+                    let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
+                    let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
+
+                    let entry = usage
+                        .user_node_pacing
+                        .entry(user_id.clone())
+                        .or_default()
+                        .entry(node_id.to_string())
+                        .or_insert(crate::state::UserNodePacing {
+                            bank_bytes: 0,
+                            last_total_used_bytes: 0,
+                            last_base_quota_bytes: 0,
+                            last_priority_tier: Default::default(),
+                        });
+
+                    // P3 quota expires daily.
+                    if tier == UserPriorityTier::P3 {
+                        entry.bank_bytes = 0;
+                        // Preserve tier state so the reconciliation phase in the same tick doesn't
+                        // treat this as a tier transition and wipe overflow tokens allocated later.
+                        entry.last_base_quota_bytes = 0;
+                        entry.last_priority_tier = tier;
+                        continue;
+                    }
+
+                    let carry_days = match tier {
+                        UserPriorityTier::P1 => P1_CARRY_DAYS,
+                        UserPriorityTier::P2 => P2_CARRY_DAYS,
+                        UserPriorityTier::P3 => 0,
+                    };
+
+                    let (bank, overflow) = quota_policy::apply_daily_rollover(
+                        entry.bank_bytes,
+                        base_quota,
+                        cycle_days,
+                        day_u32,
+                        carry_days,
+                    );
+                    entry.bank_bytes = bank;
+
+                    match tier {
+                        UserPriorityTier::P1 => p3_pool = p3_pool.saturating_add(overflow),
+                        UserPriorityTier::P2 => p1_pool = p1_pool.saturating_add(overflow),
+                        UserPriorityTier::P3 => {}
+                    }
+                }
+
+                // P1 can take P2's pacing overflow.
+                if p1_pool > 0 {
+                    if !p1_items.is_empty() {
+                        for (user_id, bonus) in
+                            quota_policy::allocate_total_by_weight(p1_pool, &p1_items)
+                        {
+                            let base_quota = base_by_user.get(&user_id).copied().unwrap_or(0);
+                            let entry = usage
+                                .user_node_pacing
+                                .entry(user_id.clone())
+                                .or_default()
+                                .entry(node_id.to_string())
+                                .or_insert(crate::state::UserNodePacing {
+                                    bank_bytes: 0,
+                                    last_total_used_bytes: 0,
+                                    last_base_quota_bytes: 0,
+                                    last_priority_tier: Default::default(),
+                                });
+                            entry.bank_bytes = entry.bank_bytes.saturating_add(bonus);
+
+                            let cap = quota_policy::cap_bytes_for_day(
+                                base_quota,
+                                cycle_days,
+                                day_u32,
+                                P1_CARRY_DAYS,
+                            );
+                            if entry.bank_bytes > cap {
+                                let overflow = entry.bank_bytes - cap;
+                                entry.bank_bytes = cap;
+                                p3_pool = p3_pool.saturating_add(overflow);
+                            }
+                        }
+                    } else {
+                        // If no P1 users exist on this node, P2 pacing overflow becomes general
+                        // surplus that P3 can opportunistically consume.
+                        p3_pool = p3_pool.saturating_add(p1_pool);
+                    }
+                }
+
+                // P3 can take any remaining overflow (no carry).
+                if p3_pool > 0 && !p3_items.is_empty() {
+                    for (user_id, bonus) in
+                        quota_policy::allocate_total_by_weight(p3_pool, &p3_items)
+                    {
+                        let entry = usage
+                            .user_node_pacing
+                            .entry(user_id.clone())
+                            .or_default()
+                            .entry(node_id.to_string())
+                            .or_insert(crate::state::UserNodePacing {
+                                bank_bytes: 0,
+                                last_total_used_bytes: 0,
+                                last_base_quota_bytes: 0,
+                                last_priority_tier: Default::default(),
+                            });
+                        entry.bank_bytes = entry.bank_bytes.saturating_add(bonus);
+                    }
+                }
+
+                node_pacing.last_day_index = day;
+                day += 1;
+            }
+
+            // Policy reconciliation: when quota inputs change mid-cycle (node quota limit,
+            // user count, user tier, weights), make pacing changes effective immediately
+            // instead of waiting for the next day rollover.
+            //
+            // We do this by adjusting each user's bank by the cap delta for *today*.
+            // If the cap is reduced below what the user already consumed, we force an
+            // immediate local-only ban even when `delta == 0` for this tick.
+            //
+            // Important: when a day rollover ran in this tick, banks have already been computed
+            // against the current policy for today. In that case we should *not* apply the cap
+            // delta again, or we'd risk false bans. We still update `last_*` fields to keep the
+            // next tick consistent.
+            let did_day_rollover = node_pacing.last_day_index != initial_last_day_index;
+            let do_cap_reconcile = !did_day_rollover;
+            let mut force_ban_users = std::collections::BTreeSet::<String>::new();
+            let today_u32 = today_index.max(0) as u32;
+            for user_id in enabled_users.iter() {
+                let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
+                let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
+
+                let entry = usage
+                    .user_node_pacing
+                    .entry(user_id.clone())
+                    .or_default()
+                    .entry(node_id.to_string())
+                    .or_insert(crate::state::UserNodePacing {
+                        bank_bytes: 0,
+                        last_total_used_bytes: 0,
+                        last_base_quota_bytes: 0,
+                        last_priority_tier: Default::default(),
+                    });
+
+                // P3 has no base quota, but may have overflow tokens for today.
+                // Only clear the bank on a tier transition (e.g. P1->P3).
+                if tier == UserPriorityTier::P3 {
+                    if entry.last_priority_tier != UserPriorityTier::P3 {
+                        entry.bank_bytes = 0;
+                    }
+
+                    // P3 should not have access unless it has overflow tokens. If the bank is
+                    // empty, force an immediate ban (even if `delta == 0`), so reconcile removes
+                    // the user from the inbound config.
+                    if entry.bank_bytes == 0 {
+                        force_ban_users.insert(user_id.clone());
+                    }
+                    entry.last_base_quota_bytes = 0;
+                    entry.last_priority_tier = tier;
+                    continue;
+                }
+
+                let new_carry = match tier {
+                    UserPriorityTier::P1 => P1_CARRY_DAYS,
+                    UserPriorityTier::P2 => P2_CARRY_DAYS,
+                    UserPriorityTier::P3 => 0,
+                };
+                let cap_new =
+                    quota_policy::cap_bytes_for_day(base_quota, cycle_days, today_u32, new_carry);
+
+                if do_cap_reconcile {
+                    let old_base = entry.last_base_quota_bytes;
+                    let old_tier = entry.last_priority_tier;
+
+                    let old_carry = match old_tier {
+                        UserPriorityTier::P1 => P1_CARRY_DAYS,
+                        UserPriorityTier::P2 => P2_CARRY_DAYS,
+                        UserPriorityTier::P3 => 0,
+                    };
+                    let cap_old =
+                        quota_policy::cap_bytes_for_day(old_base, cycle_days, today_u32, old_carry);
+
+                    if cap_new >= cap_old {
+                        entry.bank_bytes = entry.bank_bytes.saturating_add(cap_new - cap_old);
+                    } else {
+                        let drop = cap_old - cap_new;
+                        if entry.bank_bytes < drop {
+                            // User already overused relative to the new cap; ban immediately.
+                            entry.bank_bytes = 0;
+                            force_ban_users.insert(user_id.clone());
+                        } else {
+                            entry.bank_bytes = entry.bank_bytes.saturating_sub(drop);
+                        }
+                    }
+                }
+
+                // Clamp to the new cap (both in reconcile and rollover cases).
+                if entry.bank_bytes > cap_new {
+                    entry.bank_bytes = cap_new;
+                }
+
+                entry.last_base_quota_bytes = base_quota;
+                entry.last_priority_tier = tier;
+            }
+
+            // Apply traffic deltas: consume banks + ban/unban grants locally.
+            for user_id in enabled_users.iter() {
+                let Some(group) = by_user.get(user_id) else {
+                    continue;
+                };
+
+                let tier = tier_by_user.get(user_id).copied().unwrap_or_default();
+                let base_quota = base_by_user.get(user_id).copied().unwrap_or(0);
+                let carry_days = match tier {
+                    UserPriorityTier::P1 => P1_CARRY_DAYS,
+                    UserPriorityTier::P2 => P2_CARRY_DAYS,
+                    UserPriorityTier::P3 => 0,
+                };
+
+                let total_used = total_used_by_user.get(user_id).copied().unwrap_or(0);
+                let entry = usage
+                    .user_node_pacing
+                    .entry(user_id.clone())
+                    .or_default()
+                    .entry(node_id.to_string())
+                    .or_insert(crate::state::UserNodePacing {
+                        bank_bytes: 0,
+                        last_total_used_bytes: 0,
+                        last_base_quota_bytes: 0,
+                        last_priority_tier: Default::default(),
+                    });
+
+                let delta = total_used.saturating_sub(entry.last_total_used_bytes);
+                entry.last_total_used_bytes = total_used;
+                let topups = topups_by_user
+                    .get(user_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+
+                let mut banned_this_tick = false;
+                let mut exceeded_bank = delta > entry.bank_bytes;
+                let mut consumed_via_replay = false;
+                if exceeded_bank
+                    && did_day_rollover
+                    && carry_days > 0
+                    && !force_ban_users.contains(user_id)
+                {
+                    let pre_bank = pre_rollover_bank_by_user.get(user_id).copied().unwrap_or(0);
+                    let day_start = initial_last_day_index.saturating_add(1).max(0) as u32;
+                    let day_end = today_u32;
+
+                    let (bank_after, remaining) = quota_policy::replay_rollovers_and_spend(
+                        pre_bank, delta, base_quota, cycle_days, day_start, day_end, carry_days,
+                    );
+                    if remaining == 0 {
+                        entry.bank_bytes = bank_after;
+                        exceeded_bank = false;
+                        consumed_via_replay = true;
+                    }
+                }
+
+                // Top-ups cover what the cycle allowance cannot; a forced ban (empty P3 bank or
+                // a cap cut below usage) holds only while no top-up bytes are left either.
+                let mut covered_by_topups = false;
+                if force_ban_users.contains(user_id) || exceeded_bank {
+                    let overflow = if exceeded_bank {
+                        delta.saturating_sub(entry.bank_bytes)
+                    } else {
+                        0
+                    };
+                    let uncovered =
+                        consume_topups(&mut usage.topup_consumed_bytes, topups, overflow);
+                    covered_by_topups = uncovered == 0
+                        && (overflow > 0
+                            || topup_remaining_bytes(&usage.topup_consumed_bytes, topups) > 0);
+                }
+
+                if covered_by_topups {
+                    entry.bank_bytes = 0;
+                } else if force_ban_users.contains(user_id) || exceeded_bank {
+                    entry.bank_bytes = 0;
+                    banned_this_tick = true;
+
+                    for tick in group {
+                        let u = usage
+                            .memberships
+                            .entry(tick.snapshot.membership_key.clone())
+                            .or_insert(crate::state::MembershipUsage {
+                                cycle_start_at: cycle_start_at.clone(),
+                                cycle_end_at: cycle_end_at.clone(),
+                                used_bytes: 0,
+                                last_uplink_total: 0,
+                                last_downlink_total: 0,
+                                last_seen_at: now_rfc3339.clone(),
+                                quota_banned: false,
+                                quota_banned_at: None,
+                            });
+                        if !u.quota_banned {
+                            u.quota_banned = true;
+                            u.quota_banned_at = Some(now_rfc3339.clone());
+                            changed = true;
+                        }
+
+                        if let Some(tag) = tick.snapshot.endpoint_tag.as_deref() {
+                            let email = membership_xray_email(
+                                &tick.snapshot.user_id,
+                                &tick.snapshot.endpoint_id,
+                            );
+                            remove_ops.push((tag.to_string(), email));
+                            if let Some(alternate_email) = &tick.snapshot.alternate_email {
+                                remove_ops.push((tag.to_string(), alternate_email.clone()));
+                            }
+                        }
+                    }
+                } else if delta > 0 && !consumed_via_replay {
+                    entry.bank_bytes = entry.bank_bytes.saturating_sub(delta);
+                }
+
+                // Auto-unban once the user has positive bank (or top-up bytes) again.
+                if quota_auto_unban
+                    && !banned_this_tick
+                    && (entry.bank_bytes > 0
+                        || topup_remaining_bytes(&usage.topup_consumed_bytes, topups) > 0)
+                {
+                    let any_banned = group.iter().any(|tick| {
+                        usage
+                            .memberships
+                            .get(&tick.snapshot.membership_key)
+                            .is_some_and(|u| u.quota_banned)
+                    });
+                    if any_banned {
+                        for tick in group {
+                            if let Some(u) =
+                                usage.memberships.get_mut(&tick.snapshot.membership_key)
+                                && u.quota_banned
+                            {
+                                u.quota_banned = false;
+                                u.quota_banned_at = None;
+                                changed = true;
+                            }
+                        }
+                    }
+                }
+            }
+
+            (remove_ops, changed)
+        })
+        .map_err(|e| anyhow::anyhow!("update_usage: {e}"))?;
+
+    if changed {
+        reconcile.request_full();
+    }
+    drop(store);
+
+    for (tag, email) in remove_ops {
+        use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
+        let op = crate::xray::builder::build_remove_user_operation(&email);
+        let req = AlterInboundRequest {
+            tag: tag.clone(),
+            operation: Some(op),
+        };
+        match client.alter_inbound(req).await {
+            Ok(_) => {}
+            Err(status) if xray::is_not_found(&status) => {}
+            Err(status) => warn!(
+                node_id = node_id,
+                endpoint_tag = tag,
+                %status,
+                "quota tick: xray alter_inbound remove_user failed"
+            ),
+        }
+    }
+
+    Ok(Some(SharedQuotaCycle {
+        cycle_start,
+        cycle_end,
+        base_by_user,
+    }))
+}
@@ -6,7 +6,8 @@ use pretty_assertions::assert_eq;
 use tokio::sync::{Mutex, oneshot};
 
 use crate::{
-    domain::{EndpointKind, Node, NodeQuotaReset},
+    domain::{EndpointKind, Node, NodeQuotaReset, QuotaWeekday, TrafficTopUp},
+    quota_policy,
     state::{DesiredStateCommand, JsonSnapshotStore, StoreInit},
     xray::proto::xray::{
         app::{
@@ -30,6 +31,8 @@ enum OnlineStatsBehavior {
     #[default]
     Unimplemented,
     NotFound,
+    /// Online IPs by stat name (`user>>>{email}>>>online`); other names are not found.
+    Ips(BTreeMap<String, Vec<String>>),
 }
 
 #[derive(Debug, Default)]
@@ -277,13 +280,24 @@ impl StatsService for RecordingStats {
         >,
         tonic::Status,
     > {
-        let _request = request.into_inner();
+        let request = request.into_inner();
         let state = self.state.lock().await;
         match &state.online_stats_behavior {
             OnlineStatsBehavior::Unimplemented => {
                 Err(tonic::Status::unimplemented("get_stats_online_ip_list"))
             }
             OnlineStatsBehavior::NotFound => Err(tonic::Status::not_found("missing online stat")),
+            OnlineStatsBehavior::Ips(by_name) => {
+                let ips = by_name
+                    .get(&request.name)
+                    .ok_or_else(|| tonic::Status::not_found("missing online stat"))?;
+                Ok(tonic::Response::new(
+                    crate::xray::proto::xray::app::stats::command::GetStatsOnlineIpListResponse {
+                        name: request.name,
+                        ips: ips.iter().map(|ip| (ip.clone(), 1)).collect(),
+                    },
+                ))
+            }
         }
     }
 }
@@ -337,6 +351,9 @@ fn test_store_init(
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir: tmp_dir.to_path_buf(),
         admin_token_hash: String::new(),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -365,9 +382,14 @@ fn test_store_init(
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     };
@@ -576,7 +598,7 @@ async fn shared_quota_weight_change_updates_bank_immediately_same_day() {
             CycleTimeZone::FixedOffsetMinutes {
                 tz_offset_minutes: 0,
             },
-            1,
+            CycleSchedule::Monthly { day_of_month: 1 },
             now,
         )
         .unwrap();
@@ -661,83 +683,6 @@ async fn shared_quota_weight_change_updates_bank_immediately_same_day() {
     let _ = shutdown.send(());
 }
 
-#[tokio::test]
-async fn shared_quota_fixed_offset_day_index_starts_at_zero() {
-    let state = Arc::new(Mutex::new(RecordingState::default()));
-    let (addr, shutdown) = start_server(state.clone()).await;
-
-    let tmp = tempfile::tempdir().unwrap();
-    let (config, store) = test_store_init(tmp.path(), addr, true);
-
-    let (node_id, user_id, endpoint_id) = {
-        let mut store = store.lock().await;
-        let node_id = store.list_nodes()[0].node_id.clone();
-
-        // Enable shared quota with a fixed offset (UTC+8), where the cycle start timestamp is
-        // on the previous UTC date (e.g. local 00:00 == UTC 16:00).
-        let node_quota_limit_bytes = 256 * 1024 * 1024 + 31; // distributable=31 => credit=1/day
-        let _ = store
-            .upsert_node(Node {
-                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::label_empty().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: node_quota_limit_bytes,
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
-                    tz_offset_minutes: Some(480),
-                },
-            })
-            .unwrap();
-
-        let user = store.create_user("p2".to_string(), None).unwrap();
-
-        let endpoint = store
-            .create_endpoint(
-                node_id.clone(),
-                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                8388,
-                serde_json::json!({}),
-            )
-            .unwrap();
-        DesiredStateCommand::ReplaceUserAccess {
-            user_id: user.user_id.clone(),
-            endpoint_ids: vec![endpoint.endpoint_id.clone()],
-        }
-        .apply(store.state_mut())
-        .unwrap();
-
-        store.save().unwrap();
-        (node_id, user.user_id, endpoint.endpoint_id)
-    };
-
-    let email = membership_xray_email(&user_id, &endpoint_id);
-    {
-        let mut st = state.lock().await;
-        st.stats.insert(stat_name(&email, "uplink"), 0);
-        st.stats.insert(stat_name(&email, "downlink"), 0);
-    }
-
-    let reconcile = ReconcileHandle::noop();
-    let now = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-    run_quota_tick_at(now, &config, &store, &reconcile)
-        .await
-        .unwrap();
-
-    let store_guard = store.lock().await;
-    let pacing = store_guard
-        .get_user_node_pacing(&user_id, &node_id)
-        .unwrap();
-
-    // On the first tick day of the cycle, the bank should contain exactly one daily credit.
-    // A day-index off-by-one would apply two rollovers and produce 2 credits.
-    assert_eq!(pacing.bank_bytes, 1);
-
-    let _ = shutdown.send(());
-}
-
 #[tokio::test]
 async fn shared_quota_enabled_user_set_change_updates_bank_immediately_same_day() {
     let state = Arc::new(Mutex::new(RecordingState::default()));
@@ -907,7 +852,7 @@ async fn shared_quota_enabled_user_set_change_updates_bank_immediately_same_day(
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now,
     )
     .unwrap();
@@ -1123,7 +1068,7 @@ async fn shared_quota_tier_change_p3_to_p2_unbans_and_allocates_immediately() {
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now,
     )
     .unwrap();
@@ -1977,7 +1922,7 @@ async fn shared_quota_tier_promotion_p2_to_p1_unbans_immediately_without_new_tra
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now2,
     )
     .unwrap();
@@ -2146,7 +2091,7 @@ async fn shared_quota_tier_demotion_p1_to_p2_bans_immediately_without_new_traffi
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now2,
     )
     .unwrap();
@@ -2538,7 +2483,7 @@ async fn shared_quota_p2_overflow_reaches_p3_via_p1_when_p1_at_cap() {
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now0,
     )
     .unwrap();
@@ -2697,7 +2642,7 @@ async fn shared_quota_p2_overflow_flows_to_p3_when_no_p1() {
         CycleTimeZone::FixedOffsetMinutes {
             tz_offset_minutes: 0,
         },
-        1,
+        CycleSchedule::Monthly { day_of_month: 1 },
         now0,
     )
     .unwrap();
@@ -2867,43 +2812,34 @@ async fn shared_quota_quota_decrease_can_ban_without_new_traffic() {
 }
 
 #[tokio::test]
-async fn shared_quota_cycle_rollover_resets_pacing_and_unbans() {
+async fn quota_warning_thresholds_fire_once_per_cycle_with_projection() {
     let state = Arc::new(Mutex::new(RecordingState::default()));
     let (addr, shutdown) = start_server(state.clone()).await;
 
     let tmp = tempfile::tempdir().unwrap();
-    let (config, store) = test_store_init(tmp.path(), addr, true);
+    let (mut config, store) = test_store_init(tmp.path(), addr, true);
+    config.quota_warning_thresholds = vec![10, 12];
 
-    let node_quota_limit_bytes = 256 * 1024 * 1024 + 1024; // distributable=1024
-    let (node_id, user_id, endpoint_id, membership) = {
+    let (user_id, endpoint_id) = {
         let mut store = store.lock().await;
         let node_id = store.list_nodes()[0].node_id.clone();
-
         let _ = store
             .upsert_node(Node {
                 node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
                 node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
                 access_host: xp_test_fixtures::label_empty().to_owned(),
                 api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: node_quota_limit_bytes,
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
+                quota_limit_bytes: 256 * 1024 * 1024 + 700, // distributable=700
+                quota_reset: NodeQuotaReset::Weekly {
+                    weekday: QuotaWeekday::Monday,
                     tz_offset_minutes: Some(0),
                 },
             })
             .unwrap();
-
         let user = store.create_user("p2".to_string(), None).unwrap();
-        store
-            .state_mut()
-            .users
-            .get_mut(&user.user_id)
-            .unwrap()
-            .priority_tier = crate::domain::UserPriorityTier::P2;
-
         let endpoint = store
             .create_endpoint(
-                node_id.clone(),
+                node_id,
                 EndpointKind::Ss2022_2022Blake3Aes128Gcm,
                 8388,
                 serde_json::json!({}),
@@ -2915,240 +2851,92 @@ async fn shared_quota_cycle_rollover_resets_pacing_and_unbans() {
         }
         .apply(store.state_mut())
         .unwrap();
-
         store.save().unwrap();
-        (
-            node_id,
-            user.user_id.clone(),
-            endpoint.endpoint_id.clone(),
-            membership_key(&user.user_id, &endpoint.endpoint_id),
-        )
+        (user.user_id.clone(), endpoint.endpoint_id.clone())
     };
 
-    let reconcile = ReconcileHandle::noop();
-    let now_feb = DateTime::parse_from_rfc3339("2026-02-01T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-
-    // Set usage to exceed the day-0 cap and force a ban within the Feb cycle.
-    let (cycle_start, cycle_end) = current_cycle_window_at(
-        CycleTimeZone::FixedOffsetMinutes {
-            tz_offset_minutes: 0,
-        },
-        1,
-        now_feb,
-    )
-    .unwrap();
-    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
-    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
-    assert_eq!(distributable, 1024);
-    let base = distributable; // only one P2 user
-    let cap_day0 = quota_policy::cap_bytes_for_day(base, cycle_days, 0, P2_CARRY_DAYS);
-
     let email = membership_xray_email(&user_id, &endpoint_id);
-    {
-        let mut st = state.lock().await;
-        st.stats
-            .insert(stat_name(&email, "uplink"), (cap_day0 + 1) as i64);
-        st.stats.insert(stat_name(&email, "downlink"), 0);
-    }
+    let set_uplink = |bytes: i64| {
+        let state = state.clone();
+        let email = email.clone();
+        async move {
+            let mut st = state.lock().await;
+            st.stats.insert(stat_name(&email, "uplink"), bytes);
+            st.stats.insert(stat_name(&email, "downlink"), 0);
+        }
+    };
+    let at = |raw: &str| {
+        DateTime::parse_from_rfc3339(raw)
+            .unwrap()
+            .with_timezone(&Utc)
+    };
+    let reconcile = ReconcileHandle::noop();
 
-    run_quota_tick_at(now_feb, &config, &store, &reconcile)
+    // Baseline sample: counters start at zero.
+    set_uplink(0).await;
+    run_quota_tick_at(at("2026-02-02T00:00:00Z"), &config, &store, &reconcile)
         .await
         .unwrap();
-    {
-        let store = store.lock().await;
-        assert!(
-            store
-                .get_membership_usage(&membership)
-                .unwrap()
-                .quota_banned,
-            "expected ban in Feb cycle"
-        );
-    }
+    assert!(store.lock().await.quota_warnings().is_empty());
 
-    // On cycle rollover (Mar 1), the shared-quota policy should reset pacing and unban even
-    // when the underlying xray counters do not reset.
-    let now_mar = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-    run_quota_tick_at(now_mar, &config, &store, &reconcile)
+    // 75 of 700 bytes in 12h: 10% reached, the rest runs out 100h later.
+    set_uplink(75).await;
+    run_quota_tick_at(at("2026-02-02T12:00:00Z"), &config, &store, &reconcile)
         .await
         .unwrap();
-
-    let (cycle_start, cycle_end) = current_cycle_window_at(
-        CycleTimeZone::FixedOffsetMinutes {
-            tz_offset_minutes: 0,
-        },
-        1,
-        now_mar,
-    )
-    .unwrap();
-    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
-    let cap_day0 = quota_policy::cap_bytes_for_day(base, cycle_days, 0, P2_CARRY_DAYS);
-
-    let store = store.lock().await;
-    assert!(
-        !store
-            .get_membership_usage(&membership)
-            .unwrap()
-            .quota_banned,
-        "expected unban on cycle rollover"
-    );
+    let first = store.lock().await.quota_warnings()[&user_id].clone();
+    assert_eq!(first.threshold_percent, 10);
+    assert_eq!(first.quota_bytes, 700);
+    assert_eq!(first.used_bytes, 75);
+    assert_eq!(first.remaining_bytes, 625);
     assert_eq!(
-        store
-            .get_user_node_pacing(&user_id, &node_id)
-            .unwrap()
-            .bank_bytes,
-        cap_day0
+        first.projected_exhaustion_at.as_deref(),
+        Some("2026-02-06T16:00:00+00:00")
     );
 
-    let _ = shutdown.send(());
-}
-
-#[tokio::test]
-async fn shared_quota_quota_decrease_across_day_rollover_does_not_false_ban() {
-    let state = Arc::new(Mutex::new(RecordingState::default()));
-    let (addr, shutdown) = start_server(state.clone()).await;
-
-    let tmp = tempfile::tempdir().unwrap();
-    let (config, store) = test_store_init(tmp.path(), addr, true);
-
-    let (node_id, user_id, _endpoint_id, membership) = {
-        let mut store = store.lock().await;
-        let node_id = store.list_nodes()[0].node_id.clone();
-
-        let _ = store
-            .upsert_node(Node {
-                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::label_empty().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 4 * 1024 * 1024 * 1024, // 4GiB
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
-                    tz_offset_minutes: Some(0),
-                },
-            })
-            .unwrap();
-
-        let user = store.create_user("p2".to_string(), None).unwrap();
-        store
-            .state_mut()
-            .users
-            .get_mut(&user.user_id)
-            .unwrap()
-            .priority_tier = crate::domain::UserPriorityTier::P2;
-
-        let endpoint = store
-            .create_endpoint(
-                node_id.clone(),
-                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                8388,
-                serde_json::json!({}),
-            )
-            .unwrap();
-        DesiredStateCommand::ReplaceUserAccess {
-            user_id: user.user_id.clone(),
-            endpoint_ids: vec![endpoint.endpoint_id.clone()],
-        }
-        .apply(store.state_mut())
+    // Already reported this cycle: later ticks below the next threshold leave it untouched.
+    set_uplink(80).await;
+    run_quota_tick_at(at("2026-02-02T13:00:00Z"), &config, &store, &reconcile)
+        .await
         .unwrap();
+    assert_eq!(store.lock().await.quota_warnings()[&user_id], first);
 
-        store.save().unwrap();
-        (
-            node_id,
-            user.user_id.clone(),
-            endpoint.endpoint_id.clone(),
-            membership_key(&user.user_id, &endpoint.endpoint_id),
-        )
-    };
-
-    let reconcile = ReconcileHandle::noop();
-    let now0 = DateTime::parse_from_rfc3339("2026-02-01T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-
-    // Day 0 tick: initialize shared quota pacing (no traffic).
-    run_quota_tick_at(now0, &config, &store, &reconcile)
+    set_uplink(90).await;
+    run_quota_tick_at(at("2026-02-02T14:00:00Z"), &config, &store, &reconcile)
         .await
         .unwrap();
-
-    // Lower node quota budget before the next day's tick.
     {
-        let mut store = store.lock().await;
-        let _ = store
-            .upsert_node(Node {
-                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::label_empty().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 1024 * 1024 * 1024, // 1GiB
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
-                    tz_offset_minutes: Some(0),
-                },
-            })
-            .unwrap();
-        store.save().unwrap();
+        let store = store.lock().await;
+        let warning = &store.quota_warnings()[&user_id];
+        assert_eq!(warning.threshold_percent, 12);
+        assert_eq!(
+            warning.reached_percents,
+            std::collections::BTreeSet::from([10, 12])
+        );
     }
 
-    let now1 = DateTime::parse_from_rfc3339("2026-02-02T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-    run_quota_tick_at(now1, &config, &store, &reconcile)
+    // A new cycle starts from scratch.
+    run_quota_tick_at(at("2026-02-09T01:00:00Z"), &config, &store, &reconcile)
         .await
         .unwrap();
-
-    let store_guard = store.lock().await;
-    let usage = store_guard.get_membership_usage(&membership).unwrap();
-    assert!(
-        !usage.quota_banned,
-        "expected no ban when quota decreases across day rollover without traffic"
-    );
-
-    let (cycle_start, cycle_end) = current_cycle_window_at(
-        CycleTimeZone::FixedOffsetMinutes {
-            tz_offset_minutes: 0,
-        },
-        1,
-        now1,
-    )
-    .unwrap();
-    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
-    let distributable = quota_policy::distributable_bytes(1024 * 1024 * 1024);
-    let base = distributable; // only one P2 user
-    let expected_bank = quota_policy::cap_bytes_for_day(base, cycle_days, 1, P2_CARRY_DAYS);
-    let pacing = store_guard
-        .get_user_node_pacing(&user_id, &node_id)
-        .unwrap();
-    assert_eq!(pacing.bank_bytes, expected_bank);
-    drop(store_guard);
-
-    let st = state.lock().await;
-    assert!(
-        st.calls.is_empty(),
-        "expected no xray remove_user calls without a ban"
-    );
+    assert!(store.lock().await.quota_warnings().is_empty());
 
     let _ = shutdown.send(());
 }
 
 #[tokio::test]
-async fn shared_quota_tick_gap_does_not_false_ban_when_cap_decreases() {
+async fn shared_quota_topup_lifts_ban_and_covers_overflow_until_spent() {
     let state = Arc::new(Mutex::new(RecordingState::default()));
     let (addr, shutdown) = start_server(state.clone()).await;
 
     let tmp = tempfile::tempdir().unwrap();
     let (config, store) = test_store_init(tmp.path(), addr, true);
 
+    let node_quota_limit_bytes = 256 * 1024 * 1024 + 700; // distributable=700
     let (node_id, user_id, endpoint_id, membership) = {
         let mut store = store.lock().await;
         let node_id = store.list_nodes()[0].node_id.clone();
 
-        // Pick a small-but-nonzero distributable quota budget to make daily credits small
-        // (and the cap decrease observable by a few bytes).
-        let node_quota_limit_bytes = 256 * 1024 * 1024 + 311;
         let _ = store
             .upsert_node(Node {
                 node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
@@ -3156,21 +2944,14 @@ async fn shared_quota_tick_gap_does_not_false_ban_when_cap_decreases() {
                 access_host: xp_test_fixtures::label_empty().to_owned(),
                 api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
                 quota_limit_bytes: node_quota_limit_bytes,
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
+                quota_reset: NodeQuotaReset::Weekly {
+                    weekday: QuotaWeekday::Monday,
                     tz_offset_minutes: Some(0),
                 },
             })
             .unwrap();
 
         let user = store.create_user("p2".to_string(), None).unwrap();
-        store
-            .state_mut()
-            .users
-            .get_mut(&user.user_id)
-            .unwrap()
-            .priority_tier = crate::domain::UserPriorityTier::P2;
-
         let endpoint = store
             .create_endpoint(
                 node_id.clone(),
@@ -3195,84 +2976,94 @@ async fn shared_quota_tick_gap_does_not_false_ban_when_cap_decreases() {
         )
     };
 
-    let email = membership_xray_email(&user_id, &endpoint_id);
-    {
-        let mut st = state.lock().await;
-        st.stats.insert(stat_name(&email, "uplink"), 0);
-        st.stats.insert(stat_name(&email, "downlink"), 0);
-    }
-
     let reconcile = ReconcileHandle::noop();
-
-    // Initialize pacing on day 0 of a 31-day cycle (Jan 1 -> Feb 1, 2026).
-    let now0 = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+    let now = DateTime::parse_from_rfc3339("2026-02-02T00:00:00Z")
         .unwrap()
         .with_timezone(&Utc);
-    run_quota_tick_at(now0, &config, &store, &reconcile)
-        .await
-        .unwrap();
+    let cap_day0 = quota_policy::cap_bytes_for_day(700, 7, 0, P2_CARRY_DAYS);
+    assert_eq!(cap_day0, 100);
 
-    let (cycle_start, cycle_end) = current_cycle_window_at(
-        CycleTimeZone::FixedOffsetMinutes {
-            tz_offset_minutes: 0,
-        },
-        1,
-        now0,
-    )
-    .unwrap();
-    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
-    assert_eq!(cycle_days, 31);
-
-    let node_quota_limit_bytes = 256 * 1024 * 1024 + 311;
-    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
-    assert_eq!(distributable, 311);
+    let email = membership_xray_email(&user_id, &endpoint_id);
+    let set_uplink = |bytes: u64| {
+        let state = state.clone();
+        let email = email.clone();
+        async move {
+            let mut st = state.lock().await;
+            st.stats.insert(stat_name(&email, "uplink"), bytes as i64);
+            st.stats.insert(stat_name(&email, "downlink"), 0);
+        }
+    };
 
-    // Only one enabled P2 user => base_quota == distributable.
-    let base = distributable;
-    let cap_day1 = quota_policy::cap_bytes_for_day(base, cycle_days, 1, P2_CARRY_DAYS);
-    let cap_day2 = quota_policy::cap_bytes_for_day(base, cycle_days, 2, P2_CARRY_DAYS);
+    set_uplink(cap_day0 + 1).await;
+    run_quota_tick_at(now, &config, &store, &reconcile)
+        .await
+        .unwrap();
     assert!(
-        cap_day1 > cap_day2,
-        "expected cap to decrease across days due to remainder distribution"
+        store
+            .lock()
+            .await
+            .get_membership_usage(&membership)
+            .unwrap()
+            .quota_banned
     );
 
-    // Simulate usage that fits in cap(day1) but exceeds cap(day2). If the quota tick is
-    // delayed until day2, naive charging against cap(day2) can cause a false ban.
+    // Buying a top-up lifts the ban without waiting for the next tick.
     {
-        let mut st = state.lock().await;
-        st.stats
-            .insert(stat_name(&email, "uplink"), cap_day1 as i64);
-        st.stats.insert(stat_name(&email, "downlink"), 0);
+        let mut store = store.lock().await;
+        let topup = TrafficTopUp {
+            topup_id: "topup-1".to_string(),
+            user_id: user_id.clone(),
+            node_id: node_id.clone(),
+            bytes: 50,
+            created_at: "2026-02-02T00:00:00Z".to_string(),
+            expires_at: None,
+            note: None,
+        };
+        DesiredStateCommand::CreateTrafficTopUp {
+            topup: topup.clone(),
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        assert!(store.lift_quota_bans_for_topup(&topup).unwrap());
+        assert!(
+            !store
+                .get_membership_usage(&membership)
+                .unwrap()
+                .quota_banned
+        );
     }
 
-    let now2 = DateTime::parse_from_rfc3339("2026-01-03T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-    run_quota_tick_at(now2, &config, &store, &reconcile)
+    // Overflow is drawn from the top-up instead of banning.
+    set_uplink(cap_day0 + 1 + 40).await;
+    run_quota_tick_at(now, &config, &store, &reconcile)
         .await
         .unwrap();
+    {
+        let store = store.lock().await;
+        assert!(
+            !store
+                .get_membership_usage(&membership)
+                .unwrap()
+                .quota_banned
+        );
+        assert_eq!(store.topup_consumed_bytes().get("topup-1"), Some(&40));
+    }
 
-    let store_guard = store.lock().await;
-    let usage = store_guard.get_membership_usage(&membership).unwrap();
-    assert!(
-        !usage.quota_banned,
-        "expected no ban for feasible day1 usage"
-    );
-
-    let expected_bank = quota_policy::daily_credit_bytes(base, cycle_days, 2);
-    let pacing = store_guard
-        .get_user_node_pacing(&user_id, &node_id)
+    // Once the top-up is spent the ban applies again.
+    set_uplink(cap_day0 + 1 + 40 + 11).await;
+    run_quota_tick_at(now, &config, &store, &reconcile)
+        .await
         .unwrap();
-    assert_eq!(pacing.bank_bytes, expected_bank);
-    drop(store_guard);
-
-    let st = state.lock().await;
-    assert!(
-        !st.calls
-            .iter()
-            .any(|c| matches!(c, Call::RemoveUser { .. })),
-        "expected no xray remove_user to be issued without a ban"
-    );
+    {
+        let store = store.lock().await;
+        assert!(
+            store
+                .get_membership_usage(&membership)
+                .unwrap()
+                .quota_banned
+        );
+        assert_eq!(store.topup_consumed_bytes().get("topup-1"), Some(&50));
+    }
 
     let _ = shutdown.send(());
 }
@@ -3453,40 +3244,19 @@ async fn remote_membership_is_ignored_when_local_membership_exists() {
 }
 
 #[tokio::test]
-async fn rollover_does_not_auto_unban_when_disabled_in_config() {
-    let state = Arc::new(Mutex::new(RecordingState::default()));
-    let (addr, shutdown) = start_server(state.clone()).await;
+async fn xray_connect_failure_is_non_fatal_and_does_not_create_usage() {
+    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
 
     let tmp = tempfile::tempdir().unwrap();
-    let (config, store) = test_store_init(tmp.path(), addr, false);
-
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-    let reconcile = ReconcileHandle::from_sender(tx);
+    let (config, store) = test_store_init(tmp.path(), addr, true);
 
-    let banned_at = "2025-11-15T00:00:00Z".to_string();
-    let (membership, email, node_id, user_id) = {
+    let membership = {
         let mut store = store.lock().await;
         let local_node_id = store.list_nodes()[0].node_id.clone();
-
-        // Enable enforceable shared quota with a deterministic (UTC+8) reset rule.
-        let _ = store
-            .upsert_node(Node {
-                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::label_empty().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 1024 * 1024 * 1024, // 1GiB
-                quota_reset: NodeQuotaReset::Monthly {
-                    day_of_month: 1,
-                    tz_offset_minutes: Some(480),
-                },
-            })
-            .unwrap();
-
         let user = store.create_user("alice".to_string(), None).unwrap();
         let endpoint = store
             .create_endpoint(
-                local_node_id.clone(),
+                local_node_id,
                 EndpointKind::Ss2022_2022Blake3Aes128Gcm,
                 8388,
                 serde_json::json!({}),
@@ -3499,71 +3269,40 @@ async fn rollover_does_not_auto_unban_when_disabled_in_config() {
         .apply(store.state_mut())
         .unwrap();
         store.save().unwrap();
-
-        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
-        store
-            .set_quota_banned(&membership, banned_at.clone())
-            .unwrap();
-
-        (
-            membership,
-            membership_xray_email(&user.user_id, &endpoint.endpoint_id),
-            local_node_id,
-            user.user_id,
-        )
+        membership_key(&user.user_id, &endpoint.endpoint_id)
     };
 
-    {
-        let mut st = state.lock().await;
-        st.stats.insert(stat_name(&email, "uplink"), 0);
-        st.stats.insert(stat_name(&email, "downlink"), 0);
-    }
-
-    // Establish a baseline pacing/cycle in the old window so the next tick crosses a rollover.
-    let old_now = DateTime::parse_from_rfc3339("2025-11-15T00:00:00Z")
-        .unwrap()
-        .with_timezone(&Utc);
-    run_quota_tick_at(old_now, &config, &store, &reconcile)
-        .await
-        .unwrap();
-
-    let new_now = DateTime::parse_from_rfc3339("2025-12-02T00:00:00Z")
+    let reconcile = ReconcileHandle::noop();
+    let now = DateTime::parse_from_rfc3339("2025-12-18T00:00:00Z")
         .unwrap()
         .with_timezone(&Utc);
-    run_quota_tick_at(new_now, &config, &store, &reconcile)
-        .await
-        .unwrap();
-
-    let store_guard = store.lock().await;
-    let usage = store_guard.get_membership_usage(&membership).unwrap();
-    assert!(usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, Some(banned_at));
-    assert!(
-        store_guard
-            .get_user_node_pacing(&user_id, &node_id)
-            .is_some(),
-        "expected pacing to exist after ticks"
-    );
-
     assert!(
-        rx.try_recv().is_err(),
-        "expected quota_auto_unban=false to not request reconcile"
+        run_quota_tick_at(now, &config, &store, &reconcile)
+            .await
+            .is_ok()
     );
 
-    let _ = shutdown.send(());
+    let store_guard = store.lock().await;
+    assert_eq!(store_guard.get_membership_usage(&membership), None);
 }
 
 #[tokio::test]
-async fn xray_connect_failure_is_non_fatal_and_does_not_create_usage() {
-    let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+async fn sustained_concurrent_ip_violation_blocks_user_until_expiry() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
 
     let tmp = tempfile::tempdir().unwrap();
     let (config, store) = test_store_init(tmp.path(), addr, true);
 
-    let membership = {
+    let (user_id, endpoint_tag, email) = {
         let mut store = store.lock().await;
         let local_node_id = store.list_nodes()[0].node_id.clone();
         let user = store.create_user("alice".to_string(), None).unwrap();
+        let mut limited = user.clone();
+        limited.max_concurrent_ips = Some(1);
+        DesiredStateCommand::UpsertUser { user: limited }
+            .apply(store.state_mut())
+            .unwrap();
         let endpoint = store
             .create_endpoint(
                 local_node_id,
@@ -3579,21 +3318,66 @@ async fn xray_connect_failure_is_non_fatal_and_does_not_create_usage() {
         .apply(store.state_mut())
         .unwrap();
         store.save().unwrap();
-        membership_key(&user.user_id, &endpoint.endpoint_id)
+        (
+            user.user_id.clone(),
+            endpoint.tag.clone(),
+            membership_xray_email(&user.user_id, &endpoint.endpoint_id),
+        )
     };
 
+    {
+        let mut st = state.lock().await;
+        st.stats.insert(stat_name(&email, "uplink"), 0);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+        st.online_stats_behavior = OnlineStatsBehavior::Ips(BTreeMap::from([(
+            online_stat_name(&email),
+            vec!["203.0.113.7".to_string(), "198.51.100.9".to_string()],
+        )]));
+    }
+
     let reconcile = ReconcileHandle::noop();
-    let now = DateTime::parse_from_rfc3339("2025-12-18T00:00:00Z")
+    let start = DateTime::parse_from_rfc3339("2026-10-17T10:00:00Z")
         .unwrap()
         .with_timezone(&Utc);
-    assert!(
+    for minute in 0..crate::ip_limit::IP_LIMIT_SUSTAINED_MINUTES {
+        let now = start + chrono::Duration::minutes(i64::from(minute));
         run_quota_tick_at(now, &config, &store, &reconcile)
             .await
-            .is_ok()
+            .unwrap();
+    }
+
+    let blocked_at = start
+        + chrono::Duration::minutes(i64::from(crate::ip_limit::IP_LIMIT_SUSTAINED_MINUTES) - 1);
+    {
+        let store = store.lock().await;
+        let block = &store.ip_limit_states()[&user_id];
+        assert!(block.is_blocked_at(blocked_at));
+        assert_eq!(
+            block
+                .offending_ips
+                .iter()
+                .map(|offender| offender.ip.as_str())
+                .collect::<Vec<_>>(),
+            vec!["198.51.100.9", "203.0.113.7"]
+        );
+    }
+    assert!(state.lock().await.calls.iter().any(|call| matches!(
+        call,
+        Call::RemoveUser { observed_inbound_tag, email: e }
+            if observed_inbound_tag == &endpoint_tag && e == &email
+    )));
+
+    let after_block =
+        blocked_at + chrono::Duration::minutes(crate::ip_limit::IP_LIMIT_BLOCK_MINUTES);
+    run_quota_tick_at(after_block, &config, &store, &reconcile)
+        .await
+        .unwrap();
+    assert!(
+        !store.lock().await.ip_limit_states()[&user_id].is_blocked_at(after_block),
+        "block should be lifted once it expires"
     );
 
-    let store_guard = store.lock().await;
-    assert_eq!(store_guard.get_membership_usage(&membership), None);
+    let _ = shutdown.send(());
 }
 
 #[tokio::test]
@@ -3840,7 +3624,7 @@ async fn invalid_stats_values_do_not_corrupt_usage() {
             CycleTimeZone::FixedOffsetMinutes {
                 tz_offset_minutes: 480,
             },
-            1,
+            CycleSchedule::Monthly { day_of_month: 1 },
             now,
         )
         .unwrap();
@@ -3884,3 +3668,5 @@ async fn invalid_stats_values_do_not_corrupt_usage() {
 
     let _ = shutdown.send(());
 }
+
+mod cycles;
@@ -0,0 +1,704 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn shared_quota_fixed_offset_day_index_starts_at_zero() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, true);
+
+    let (node_id, user_id, endpoint_id) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+
+        // Enable shared quota with a fixed offset (UTC+8), where the cycle start timestamp is
+        // on the previous UTC date (e.g. local 00:00 == UTC 16:00).
+        let node_quota_limit_bytes = 256 * 1024 * 1024 + 31; // distributable=31 => credit=1/day
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: node_quota_limit_bytes,
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(480),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("p2".to_string(), None).unwrap();
+
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+
+        store.save().unwrap();
+        (node_id, user.user_id, endpoint.endpoint_id)
+    };
+
+    let email = membership_xray_email(&user_id, &endpoint_id);
+    {
+        let mut st = state.lock().await;
+        st.stats.insert(stat_name(&email, "uplink"), 0);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    let reconcile = ReconcileHandle::noop();
+    let now = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let store_guard = store.lock().await;
+    let pacing = store_guard
+        .get_user_node_pacing(&user_id, &node_id)
+        .unwrap();
+
+    // On the first tick day of the cycle, the bank should contain exactly one daily credit.
+    // A day-index off-by-one would apply two rollovers and produce 2 credits.
+    assert_eq!(pacing.bank_bytes, 1);
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn shared_quota_cycle_rollover_resets_pacing_and_unbans() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, true);
+
+    let node_quota_limit_bytes = 256 * 1024 * 1024 + 1024; // distributable=1024
+    let (node_id, user_id, endpoint_id, membership) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: node_quota_limit_bytes,
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(0),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("p2".to_string(), None).unwrap();
+        store
+            .state_mut()
+            .users
+            .get_mut(&user.user_id)
+            .unwrap()
+            .priority_tier = crate::domain::UserPriorityTier::P2;
+
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+
+        store.save().unwrap();
+        (
+            node_id,
+            user.user_id.clone(),
+            endpoint.endpoint_id.clone(),
+            membership_key(&user.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let reconcile = ReconcileHandle::noop();
+    let now_feb = DateTime::parse_from_rfc3339("2026-02-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+
+    // Set usage to exceed the day-0 cap and force a ban within the Feb cycle.
+    let (cycle_start, cycle_end) = current_cycle_window_at(
+        CycleTimeZone::FixedOffsetMinutes {
+            tz_offset_minutes: 0,
+        },
+        CycleSchedule::Monthly { day_of_month: 1 },
+        now_feb,
+    )
+    .unwrap();
+    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
+    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
+    assert_eq!(distributable, 1024);
+    let base = distributable; // only one P2 user
+    let cap_day0 = quota_policy::cap_bytes_for_day(base, cycle_days, 0, P2_CARRY_DAYS);
+
+    let email = membership_xray_email(&user_id, &endpoint_id);
+    {
+        let mut st = state.lock().await;
+        st.stats
+            .insert(stat_name(&email, "uplink"), (cap_day0 + 1) as i64);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    run_quota_tick_at(now_feb, &config, &store, &reconcile)
+        .await
+        .unwrap();
+    {
+        let store = store.lock().await;
+        assert!(
+            store
+                .get_membership_usage(&membership)
+                .unwrap()
+                .quota_banned,
+            "expected ban in Feb cycle"
+        );
+    }
+
+    // On cycle rollover (Mar 1), the shared-quota policy should reset pacing and unban even
+    // when the underlying xray counters do not reset.
+    let now_mar = DateTime::parse_from_rfc3339("2026-03-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now_mar, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let (cycle_start, cycle_end) = current_cycle_window_at(
+        CycleTimeZone::FixedOffsetMinutes {
+            tz_offset_minutes: 0,
+        },
+        CycleSchedule::Monthly { day_of_month: 1 },
+        now_mar,
+    )
+    .unwrap();
+    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
+    let cap_day0 = quota_policy::cap_bytes_for_day(base, cycle_days, 0, P2_CARRY_DAYS);
+
+    let store = store.lock().await;
+    assert!(
+        !store
+            .get_membership_usage(&membership)
+            .unwrap()
+            .quota_banned,
+        "expected unban on cycle rollover"
+    );
+    assert_eq!(
+        store
+            .get_user_node_pacing(&user_id, &node_id)
+            .unwrap()
+            .bank_bytes,
+        cap_day0
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn shared_quota_weekly_cycle_rollover_resets_pacing_and_unbans() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, true);
+
+    let node_quota_limit_bytes = 256 * 1024 * 1024 + 700; // distributable=700
+    let (node_id, user_id, endpoint_id, membership) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: node_quota_limit_bytes,
+                quota_reset: NodeQuotaReset::Weekly {
+                    weekday: QuotaWeekday::Monday,
+                    tz_offset_minutes: Some(0),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("p2".to_string(), None).unwrap();
+        store
+            .state_mut()
+            .users
+            .get_mut(&user.user_id)
+            .unwrap()
+            .priority_tier = crate::domain::UserPriorityTier::P2;
+
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+
+        store.save().unwrap();
+        (
+            node_id,
+            user.user_id.clone(),
+            endpoint.endpoint_id.clone(),
+            membership_key(&user.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let reconcile = ReconcileHandle::noop();
+    // 2026-02-02 and 2026-02-09 are Mondays.
+    let now_week1 = DateTime::parse_from_rfc3339("2026-02-02T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+
+    // base=700 over a 7-day cycle -> credit=100 each day.
+    let base = quota_policy::distributable_bytes(node_quota_limit_bytes);
+    assert_eq!(base, 700);
+    let cap_day0 = quota_policy::cap_bytes_for_day(base, 7, 0, P2_CARRY_DAYS);
+    assert_eq!(cap_day0, 100);
+
+    let email = membership_xray_email(&user_id, &endpoint_id);
+    {
+        let mut st = state.lock().await;
+        st.stats
+            .insert(stat_name(&email, "uplink"), (cap_day0 + 1) as i64);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    run_quota_tick_at(now_week1, &config, &store, &reconcile)
+        .await
+        .unwrap();
+    {
+        let store = store.lock().await;
+        let usage = store.get_membership_usage(&membership).unwrap();
+        assert!(usage.quota_banned, "expected ban in the first week");
+        assert_eq!(usage.cycle_start_at, "2026-02-02T00:00:00+00:00");
+        assert_eq!(usage.cycle_end_at, "2026-02-09T00:00:00+00:00");
+    }
+
+    let now_week2 = DateTime::parse_from_rfc3339("2026-02-09T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now_week2, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let store = store.lock().await;
+    let usage = store.get_membership_usage(&membership).unwrap();
+    assert!(!usage.quota_banned, "expected unban on weekly rollover");
+    assert_eq!(usage.cycle_start_at, "2026-02-09T00:00:00+00:00");
+    assert_eq!(
+        store
+            .get_user_node_pacing(&user_id, &node_id)
+            .unwrap()
+            .bank_bytes,
+        cap_day0
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn shared_quota_quota_decrease_across_day_rollover_does_not_false_ban() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, true);
+
+    let (node_id, user_id, _endpoint_id, membership) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: 4 * 1024 * 1024 * 1024, // 4GiB
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(0),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("p2".to_string(), None).unwrap();
+        store
+            .state_mut()
+            .users
+            .get_mut(&user.user_id)
+            .unwrap()
+            .priority_tier = crate::domain::UserPriorityTier::P2;
+
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+
+        store.save().unwrap();
+        (
+            node_id,
+            user.user_id.clone(),
+            endpoint.endpoint_id.clone(),
+            membership_key(&user.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let reconcile = ReconcileHandle::noop();
+    let now0 = DateTime::parse_from_rfc3339("2026-02-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+
+    // Day 0 tick: initialize shared quota pacing (no traffic).
+    run_quota_tick_at(now0, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    // Lower node quota budget before the next day's tick.
+    {
+        let mut store = store.lock().await;
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: 1024 * 1024 * 1024, // 1GiB
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(0),
+                },
+            })
+            .unwrap();
+        store.save().unwrap();
+    }
+
+    let now1 = DateTime::parse_from_rfc3339("2026-02-02T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now1, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let store_guard = store.lock().await;
+    let usage = store_guard.get_membership_usage(&membership).unwrap();
+    assert!(
+        !usage.quota_banned,
+        "expected no ban when quota decreases across day rollover without traffic"
+    );
+
+    let (cycle_start, cycle_end) = current_cycle_window_at(
+        CycleTimeZone::FixedOffsetMinutes {
+            tz_offset_minutes: 0,
+        },
+        CycleSchedule::Monthly { day_of_month: 1 },
+        now1,
+    )
+    .unwrap();
+    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
+    let distributable = quota_policy::distributable_bytes(1024 * 1024 * 1024);
+    let base = distributable; // only one P2 user
+    let expected_bank = quota_policy::cap_bytes_for_day(base, cycle_days, 1, P2_CARRY_DAYS);
+    let pacing = store_guard
+        .get_user_node_pacing(&user_id, &node_id)
+        .unwrap();
+    assert_eq!(pacing.bank_bytes, expected_bank);
+    drop(store_guard);
+
+    let st = state.lock().await;
+    assert!(
+        st.calls.is_empty(),
+        "expected no xray remove_user calls without a ban"
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn shared_quota_tick_gap_does_not_false_ban_when_cap_decreases() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, true);
+
+    let (node_id, user_id, endpoint_id, membership) = {
+        let mut store = store.lock().await;
+        let node_id = store.list_nodes()[0].node_id.clone();
+
+        // Pick a small-but-nonzero distributable quota budget to make daily credits small
+        // (and the cap decrease observable by a few bytes).
+        let node_quota_limit_bytes = 256 * 1024 * 1024 + 311;
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: node_quota_limit_bytes,
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(0),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("p2".to_string(), None).unwrap();
+        store
+            .state_mut()
+            .users
+            .get_mut(&user.user_id)
+            .unwrap()
+            .priority_tier = crate::domain::UserPriorityTier::P2;
+
+        let endpoint = store
+            .create_endpoint(
+                node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+
+        store.save().unwrap();
+        (
+            node_id,
+            user.user_id.clone(),
+            endpoint.endpoint_id.clone(),
+            membership_key(&user.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let email = membership_xray_email(&user_id, &endpoint_id);
+    {
+        let mut st = state.lock().await;
+        st.stats.insert(stat_name(&email, "uplink"), 0);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    let reconcile = ReconcileHandle::noop();
+
+    // Initialize pacing on day 0 of a 31-day cycle (Jan 1 -> Feb 1, 2026).
+    let now0 = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now0, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let (cycle_start, cycle_end) = current_cycle_window_at(
+        CycleTimeZone::FixedOffsetMinutes {
+            tz_offset_minutes: 0,
+        },
+        CycleSchedule::Monthly { day_of_month: 1 },
+        now0,
+    )
+    .unwrap();
+    let cycle_days = (cycle_end.date_naive() - cycle_start.date_naive()).num_days() as u32;
+    assert_eq!(cycle_days, 31);
+
+    let node_quota_limit_bytes = 256 * 1024 * 1024 + 311;
+    let distributable = quota_policy::distributable_bytes(node_quota_limit_bytes);
+    assert_eq!(distributable, 311);
+
+    // Only one enabled P2 user => base_quota == distributable.
+    let base = distributable;
+    let cap_day1 = quota_policy::cap_bytes_for_day(base, cycle_days, 1, P2_CARRY_DAYS);
+    let cap_day2 = quota_policy::cap_bytes_for_day(base, cycle_days, 2, P2_CARRY_DAYS);
+    assert!(
+        cap_day1 > cap_day2,
+        "expected cap to decrease across days due to remainder distribution"
+    );
+
+    // Simulate usage that fits in cap(day1) but exceeds cap(day2). If the quota tick is
+    // delayed until day2, naive charging against cap(day2) can cause a false ban.
+    {
+        let mut st = state.lock().await;
+        st.stats
+            .insert(stat_name(&email, "uplink"), cap_day1 as i64);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    let now2 = DateTime::parse_from_rfc3339("2026-01-03T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(now2, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let store_guard = store.lock().await;
+    let usage = store_guard.get_membership_usage(&membership).unwrap();
+    assert!(
+        !usage.quota_banned,
+        "expected no ban for feasible day1 usage"
+    );
+
+    let expected_bank = quota_policy::daily_credit_bytes(base, cycle_days, 2);
+    let pacing = store_guard
+        .get_user_node_pacing(&user_id, &node_id)
+        .unwrap();
+    assert_eq!(pacing.bank_bytes, expected_bank);
+    drop(store_guard);
+
+    let st = state.lock().await;
+    assert!(
+        !st.calls
+            .iter()
+            .any(|c| matches!(c, Call::RemoveUser { .. })),
+        "expected no xray remove_user to be issued without a ban"
+    );
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn rollover_does_not_auto_unban_when_disabled_in_config() {
+    let state = Arc::new(Mutex::new(RecordingState::default()));
+    let (addr, shutdown) = start_server(state.clone()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr, false);
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let reconcile = ReconcileHandle::from_sender(tx);
+
+    let banned_at = "2025-11-15T00:00:00Z".to_string();
+    let (membership, email, node_id, user_id) = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+
+        // Enable enforceable shared quota with a deterministic (UTC+8) reset rule.
+        let _ = store
+            .upsert_node(Node {
+                node_id: xp_test_fixtures::identifier_ulid_d().to_owned(),
+                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+                access_host: xp_test_fixtures::label_empty().to_owned(),
+                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+                quota_limit_bytes: 1024 * 1024 * 1024, // 1GiB
+                quota_reset: NodeQuotaReset::Monthly {
+                    day_of_month: 1,
+                    tz_offset_minutes: Some(480),
+                },
+            })
+            .unwrap();
+
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                local_node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+
+        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
+        store
+            .set_quota_banned(&membership, banned_at.clone())
+            .unwrap();
+
+        (
+            membership,
+            membership_xray_email(&user.user_id, &endpoint.endpoint_id),
+            local_node_id,
+            user.user_id,
+        )
+    };
+
+    {
+        let mut st = state.lock().await;
+        st.stats.insert(stat_name(&email, "uplink"), 0);
+        st.stats.insert(stat_name(&email, "downlink"), 0);
+    }
+
+    // Establish a baseline pacing/cycle in the old window so the next tick crosses a rollover.
+    let old_now = DateTime::parse_from_rfc3339("2025-11-15T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(old_now, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let new_now = DateTime::parse_from_rfc3339("2025-12-02T00:00:00Z")
+        .unwrap()
+        .with_timezone(&Utc);
+    run_quota_tick_at(new_now, &config, &store, &reconcile)
+        .await
+        .unwrap();
+
+    let store_guard = store.lock().await;
+    let usage = store_guard.get_membership_usage(&membership).unwrap();
+    assert!(usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, Some(banned_at));
+    assert!(
+        store_guard
+            .get_user_node_pacing(&user_id, &node_id)
+            .is_some(),
+        "expected pacing to exist after ticks"
+    );
+
+    assert!(
+        rx.try_recv().is_err(),
+        "expected quota_auto_unban=false to not request reconcile"
+    );
+
+    let _ = shutdown.send(());
+}
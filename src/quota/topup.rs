@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+
+use crate::state::PersistedState;
+
+/// `(topup_id, bytes)` of the top-ups each user can draw on `node_id`, earliest expiry first so
+/// short-lived packs are used before they lapse.
+pub(super) fn active_topups_by_user(
+    state: &PersistedState,
+    node_id: &str,
+    user_ids: &[String],
+    now: DateTime<Utc>,
+) -> std::collections::BTreeMap<String, Vec<(String, u64)>> {
+    let mut out = std::collections::BTreeMap::new();
+    for user_id in user_ids {
+        let topups = active_topups(state, user_id, node_id, now);
+        if !topups.is_empty() {
+            out.insert(user_id.clone(), topups);
+        }
+    }
+    out
+}
+
+pub(crate) fn active_topups(
+    state: &PersistedState,
+    user_id: &str,
+    node_id: &str,
+    now: DateTime<Utc>,
+) -> Vec<(String, u64)> {
+    let mut topups = state
+        .traffic_topups
+        .values()
+        .filter(|topup| topup.applies_to(user_id, node_id, now))
+        .collect::<Vec<_>>();
+    topups.sort_by_key(|topup| {
+        (
+            topup.expires_at.is_none(),
+            topup
+                .expires_at
+                .as_deref()
+                .and_then(|raw| DateTime::parse_from_rfc3339(raw).ok()),
+            topup.created_at.clone(),
+            topup.topup_id.clone(),
+        )
+    });
+    topups
+        .into_iter()
+        .map(|topup| (topup.topup_id.clone(), topup.bytes))
+        .collect()
+}
+
+pub(crate) fn topup_remaining_bytes(
+    consumed: &std::collections::BTreeMap<String, u64>,
+    topups: &[(String, u64)],
+) -> u64 {
+    topups.iter().fold(0u64, |acc, (topup_id, bytes)| {
+        let used = consumed.get(topup_id).copied().unwrap_or(0);
+        acc.saturating_add(bytes.saturating_sub(used))
+    })
+}
+
+/// Draws `amount` from `topups` in order and returns the part they could not cover.
+pub(super) fn consume_topups(
+    consumed: &mut std::collections::BTreeMap<String, u64>,
+    topups: &[(String, u64)],
+    mut amount: u64,
+) -> u64 {
+    for (topup_id, bytes) in topups {
+        if amount == 0 {
+            break;
+        }
+        let used = consumed.entry(topup_id.clone()).or_insert(0);
+        let take = bytes.saturating_sub(*used).min(amount);
+        *used = used.saturating_add(take);
+        amount -= take;
+    }
+    amount
+}
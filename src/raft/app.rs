@@ -634,7 +634,7 @@ async fn forward_client_write(
 
 fn command_requires_conditional_endpoint_update(cmd: &DesiredStateCommand) -> bool {
     matches!(
-        cmd,
+        cmd.unaudited(),
         DesiredStateCommand::UpsertEndpoint {
             expected: Some(_),
             ..
@@ -703,7 +703,9 @@ impl RaftFacade for LocalRaft {
         cmd: DesiredStateCommand,
     ) -> BoxFuture<'_, anyhow::Result<ClientResponse>> {
         Box::pin(async move {
+            let (audit, cmd) = cmd.into_audit_parts();
             let mut store = self.store.lock().await;
+            let audit_baseline = store.audit_baseline(audit, &cmd);
             // Local-only cleanup: usage keys and inbound IP history for removed memberships
             // should be deleted to keep local files compact (hard-cut behavior).
             let membership_keys_before: Option<std::collections::BTreeSet<String>> = match &cmd {
@@ -726,6 +728,9 @@ impl RaftFacade for LocalRaft {
                         .map(|m| crate::state::membership_key(&m.user_id, &m.endpoint_id))
                         .collect(),
                 ),
+                DesiredStateCommand::ImportUsers { entries } => Some(
+                    crate::user_bulk::replaced_membership_keys(store.state(), entries),
+                ),
                 _ => None,
             };
             let out = match cmd.apply(store.state_mut()) {
@@ -733,6 +738,10 @@ impl RaftFacade for LocalRaft {
                 Err(err) => return Ok(map_store_error(err)),
             };
             store.save().map_err(anyhow::Error::new)?;
+            if let Some(baseline) = audit_baseline {
+                let log_index = store.next_local_audit_log_index();
+                store.record_audit_entry(log_index, baseline, &cmd);
+            }
 
             if let Some(before) = membership_keys_before {
                 let after: std::collections::BTreeSet<String> = match &cmd {
@@ -751,6 +760,9 @@ impl RaftFacade for LocalRaft {
                         .filter(|m| m.endpoint_id == *endpoint_id)
                         .map(|m| crate::state::membership_key(&m.user_id, &m.endpoint_id))
                         .collect(),
+                    DesiredStateCommand::ImportUsers { entries } => {
+                        crate::user_bulk::replaced_membership_keys(store.state(), entries)
+                    }
                     _ => std::collections::BTreeSet::new(),
                 };
                 for membership_key in before.difference(&after) {
@@ -779,6 +791,11 @@ impl RaftFacade for LocalRaft {
                         .prune_tcp_connection_usage_endpoints()
                         .map_err(anyhow::Error::new)?;
                 }
+                DesiredStateCommand::CreateTrafficTopUp { topup } => {
+                    store
+                        .lift_quota_bans_for_topup(topup)
+                        .map_err(anyhow::Error::new)?;
+                }
                 _ => {}
             }
             Ok(ClientResponse::Ok { result: out })
@@ -817,7 +834,12 @@ fn map_store_error(err: StoreError) -> ClientResponse {
             DomainError::MissingUser { .. }
             | DomainError::MissingNode { .. }
             | DomainError::MissingEndpoint { .. }
-            | DomainError::RealityDomainNotFound { .. } => ClientResponse::Err {
+            | DomainError::RealityDomainNotFound { .. }
+            | DomainError::AdminPrincipalNotFound { .. }
+            | DomainError::TrafficTopUpNotFound { .. }
+            | DomainError::SubscriptionTokenNotFound { .. }
+            | DomainError::DnsFailoverGroupNotFound { .. }
+            | DomainError::CredentialRotationNotFound { .. } => ClientResponse::Err {
                 status: 404,
                 code: "not_found".to_string(),
                 message: domain.to_string(),
@@ -830,7 +852,9 @@ fn map_store_error(err: StoreError) -> ClientResponse {
                 code: "conflict".to_string(),
                 message: domain.to_string(),
             },
-            DomainError::RealityDomainNameConflict { .. } => ClientResponse::Err {
+            DomainError::RealityDomainNameConflict { .. }
+            | DomainError::RestoreConflict { .. }
+            | DomainError::CredentialRotationConflict { .. } => ClientResponse::Err {
                 status: 409,
                 code: "conflict".to_string(),
                 message: domain.to_string(),
@@ -34,6 +34,7 @@ pub struct RaftRpcAuth {
     pub local_node_id: String,
     pub cluster_ca_key_pem: String,
     pub cluster_ca_cert_pem: String,
+    pub alternate_roots: internal_auth::AlternateRoots,
     pub store: Arc<Mutex<JsonSnapshotStore>>,
     pub bootstrap_sender: Option<BootstrapSenderMarker>,
 }
@@ -123,6 +124,7 @@ async fn raft_auth(State(auth): State<RaftRpcAuth>, req: Request<Body>, next: Ne
     let verified = match internal_auth::verify_request_v2(
         &auth.cluster_ca_key_pem,
         &auth.cluster_ca_cert_pem,
+        &auth.alternate_roots.snapshot(),
         req.method(),
         req.uri(),
         req.headers(),
@@ -76,6 +76,7 @@ async fn signed_health(
     let verified = crate::internal_auth::verify_request_v2(
         &state.ca_key_pem,
         &state.ca_cert_pem,
+        &[],
         &method,
         &uri,
         &headers,
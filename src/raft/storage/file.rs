@@ -461,7 +461,9 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
 
             let resp = match entry.payload {
                 EntryPayload::Normal(cmd) => {
+                    let (audit, cmd) = cmd.into_audit_parts();
                     let mut store = self.store.lock().await;
+                    let audit_baseline = store.audit_baseline(audit, &cmd);
                     let rebuild_inbound = match &cmd {
                         DesiredStateCommand::UpsertEndpoint { endpoint, .. } => store
                             .get_endpoint(&endpoint.endpoint_id)
@@ -496,6 +498,9 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
                                     })
                                     .collect(),
                             ),
+                            DesiredStateCommand::ImportUsers { entries } => Some(
+                                crate::user_bulk::replaced_membership_keys(store.state(), entries),
+                            ),
                             _ => None,
                         };
                     match cmd.apply(store.state_mut()) {
@@ -507,6 +512,9 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
                                     std::io::Error::other(e.to_string()),
                                 )
                             })?;
+                            if let Some(baseline) = audit_baseline {
+                                store.record_audit_entry(log_id.index, baseline, &cmd);
+                            }
                             if let Some(endpoint_id) = rebuild_inbound {
                                 self.reconcile.request_rebuild_inbound(endpoint_id);
                             }
@@ -532,6 +540,12 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
                                             crate::state::membership_key(&m.user_id, &m.endpoint_id)
                                         })
                                         .collect(),
+                                    DesiredStateCommand::ImportUsers { entries } => {
+                                        crate::user_bulk::replaced_membership_keys(
+                                            store.state(),
+                                            entries,
+                                        )
+                                    }
                                     _ => std::collections::BTreeSet::new(),
                                 };
 
@@ -588,6 +602,15 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
                                         )
                                     })?;
                                 }
+                                DesiredStateCommand::CreateTrafficTopUp { topup } => {
+                                    store.lift_quota_bans_for_topup(topup).map_err(|e| {
+                                        io_err(
+                                            ErrorSubject::StateMachine,
+                                            ErrorVerb::Write,
+                                            std::io::Error::other(e.to_string()),
+                                        )
+                                    })?;
+                                }
                                 _ => {}
                             }
 
@@ -730,6 +753,11 @@ impl RaftStateMachine<TypeConfig> for FileStateMachine {
                     .retain(|key, _| allowed_membership_keys.contains(key));
             });
             let _ = store.prune_inbound_ip_usage_memberships();
+            // The entries folded into the snapshot were never applied here, so neither were
+            // their audit records.
+            if let Some(log_id) = meta.last_log_id {
+                store.mark_audit_entries_missing_through(log_id.index);
+            }
         }
 
         {
@@ -132,6 +132,10 @@ async fn install_snapshot_migrates_legacy_grants_state_to_v10() {
         credential_epoch: 0,
         priority_tier: Default::default(),
         quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     };
 
     let legacy_snapshot = json!({
@@ -19,15 +19,28 @@ use tracing::{debug, warn};
 
 use crate::{
     config::Config,
-    credentials,
     domain::{Endpoint, EndpointKind, User},
-    protocol::{Ss2022EndpointMeta, VlessRealityVisionTcpEndpointMeta},
+    egress::{EndpointEgress, build_egress_desired},
+    managed_default_endpoints::managed_default_vless_endpoint,
+    protocol::{
+        Ss2022EndpointMeta, TrojanRealityTcpEndpointMeta, VlessRealityVisionTcpEndpointMeta,
+    },
     reverse_mesh_runtime::{ReverseXrayDesired, ReverseXrayReconciler, build_reverse_desired},
-    state::{JsonSnapshotStore, NodeUserEndpointMembership, membership_key, membership_xray_email},
+    routing_policy::{XrayRoutingPolicy, desired_policy_rules},
+    state::{
+        JsonSnapshotStore, NodeUserEndpointMembership, membership_key,
+        membership_xray_alternate_email, membership_xray_email,
+    },
     xray,
     xray::builder,
 };
 
+mod ca_rotation;
+use ca_rotation::{alternate_credential_root, apply_membership_enabled, remove_alternate_client};
+mod egress;
+mod membership_credentials;
+mod routing_policy;
+
 const MIGRATION_MARKER_VLESS_USER_ENCRYPTION_NONE: &str = "migrations/vless_user_encryption_none";
 const MIGRATION_MARKER_VLESS_REALITY_TYPE_TCP: &str = "migrations/vless_reality_type_tcp";
 const MIGRATION_MARKER_REMOVE_GRANTS_HARD_CUT_V10: &str = "migrations/remove_grants_hard_cut_v10";
@@ -410,12 +423,17 @@ struct Snapshot {
     reverse_mesh_bootstrap_target: Option<String>,
     memberships: Vec<NodeUserEndpointMembership>,
     users_by_id: BTreeMap<String, User>,
-    quota_banned_membership_keys: BTreeSet<String>,
+    /// Memberships kept off the data plane: quota-banned, or owned by a disabled/expired user.
+    blocked_membership_keys: BTreeSet<String>,
     endpoint_users_applied: BTreeMap<String, BTreeSet<String>>,
     /// Users whose `credential_epoch` differs from the locally applied epoch.
     ///
     /// Keyed by `user_id`, value is the target epoch.
     users_needing_credential_refresh: BTreeMap<String, u32>,
+    /// Key of the root accepted besides the local one while a credential rotation is in progress.
+    alternate_credential_key: Option<String>,
+    routing_policy: XrayRoutingPolicy,
+    endpoint_egress: BTreeMap<String, EndpointEgress>,
 }
 
 #[derive(Debug, Default)]
@@ -430,6 +448,9 @@ fn endpoint_kind_key(kind: &EndpointKind) -> &'static str {
     match kind {
         EndpointKind::VlessRealityVisionTcp => "vless_reality_vision_tcp",
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => "ss2022_2022_blake3_aes_128_gcm",
+        EndpointKind::TrojanRealityTcp => "trojan_reality_tcp",
+        EndpointKind::TrojanTlsTcp => "trojan_tls_tcp",
+        EndpointKind::Hysteria2 => "hysteria2",
     }
 }
 
@@ -447,6 +468,13 @@ fn desired_inbound_hash(endpoint: &Endpoint) -> Option<String> {
                 .and_then(|m| serde_json::to_value(m).ok())
                 .unwrap_or_else(|| endpoint.meta.clone())
         }
+        EndpointKind::TrojanRealityTcp => {
+            serde_json::from_value::<TrojanRealityTcpEndpointMeta>(endpoint.meta.clone())
+                .ok()
+                .and_then(|m| serde_json::to_value(m).ok())
+                .unwrap_or_else(|| endpoint.meta.clone())
+        }
+        EndpointKind::TrojanTlsTcp | EndpointKind::Hysteria2 => endpoint.meta.clone(),
     };
     meta.as_object_mut()
         .map(|object| object.remove("mihomo_smux"));
@@ -565,14 +593,23 @@ async fn reconcile_once_with_runtime(
             users_by_id.insert(user.user_id.clone(), user);
         }
 
-        let mut quota_banned_membership_keys = BTreeSet::<String>::new();
+        let now = chrono::Utc::now();
+        let mut blocked_membership_keys = BTreeSet::<String>::new();
         for membership in memberships.iter() {
             let key = membership_key(&membership.user_id, &membership.endpoint_id);
-            if store
-                .get_membership_usage(&key)
-                .is_some_and(|u| u.quota_banned)
+            let user_inactive = users_by_id
+                .get(&membership.user_id)
+                .is_some_and(|user| !user.is_active_at(now));
+            if user_inactive
+                || store
+                    .get_membership_usage(&key)
+                    .is_some_and(|u| u.quota_banned)
+                || store
+                    .ip_limit_states()
+                    .get(&membership.user_id)
+                    .is_some_and(|state| state.is_blocked_at(now))
             {
-                quota_banned_membership_keys.insert(key);
+                blocked_membership_keys.insert(key);
             }
         }
 
@@ -592,10 +629,29 @@ async fn reconcile_once_with_runtime(
             }
         }
 
+        let (alternate_credential_key, rotation_hash_suffix) = alternate_credential_root(
+            store.state().credential_rotation.as_ref(),
+            cluster_ca_key_pem,
+        );
+        // Sniffing lives in the inbound's receiver settings, so toggling it rebuilds the inbound.
+        let routing_policy = store.state().xray_routing_policy.clone();
+        let endpoint_egress = store.state().endpoint_egress.clone();
         let desired_hash_by_endpoint_id = endpoints
             .iter()
             .filter(|e| e.node_id == local_node_id)
-            .filter_map(|e| desired_inbound_hash(e).map(|h| (e.endpoint_id.clone(), h)))
+            .filter_map(|e| {
+                let sniffing_suffix = if routing_policy.sniffs_endpoint(&e.endpoint_id) {
+                    ":sniffing"
+                } else {
+                    ""
+                };
+                desired_inbound_hash(e).map(|h| {
+                    (
+                        e.endpoint_id.clone(),
+                        format!("{h}{rotation_hash_suffix}{sniffing_suffix}"),
+                    )
+                })
+            })
             .collect::<BTreeMap<_, _>>();
         (
             local_node_id,
@@ -609,9 +665,12 @@ async fn reconcile_once_with_runtime(
                 reverse_mesh_bootstrap_target,
                 memberships,
                 users_by_id,
-                quota_banned_membership_keys,
+                blocked_membership_keys,
                 endpoint_users_applied,
                 users_needing_credential_refresh,
+                alternate_credential_key,
+                routing_policy,
+                endpoint_egress,
             },
             local_vless_endpoint_ids,
             desired_hash_by_endpoint_id,
@@ -799,10 +858,14 @@ async fn reconcile_snapshot(
         reverse_mesh_bootstrap_target,
         memberships,
         users_by_id,
-        quota_banned_membership_keys,
+        blocked_membership_keys,
         endpoint_users_applied,
         users_needing_credential_refresh,
+        alternate_credential_key,
+        routing_policy,
+        endpoint_egress,
     } = snapshot;
+    let alternate_credential_key = alternate_credential_key.as_deref();
 
     let endpoints_by_id: BTreeMap<String, Endpoint> = endpoints
         .into_iter()
@@ -824,7 +887,7 @@ async fn reconcile_snapshot(
     }
 
     let is_effective_enabled = |membership: &NodeUserEndpointMembership| {
-        !quota_banned_membership_keys.contains(&membership_key(
+        !blocked_membership_keys.contains(&membership_key(
             &membership.user_id,
             &membership.endpoint_id,
         ))
@@ -924,6 +987,7 @@ async fn reconcile_snapshot(
             Err(status) if xray::is_not_found(&status) => {}
             Err(status) => warn!(tag, email, %status, "xray alter_inbound remove_user failed"),
         }
+        remove_alternate_client(&mut client, alternate_credential_key, tag, email).await;
     }
 
     for tag in pending.remove_inbounds.iter() {
@@ -967,7 +1031,10 @@ async fn reconcile_snapshot(
         }
 
         let mut ok_add = false;
-        match builder::build_add_inbound_request(endpoint) {
+        match builder::build_add_inbound_request_with_sniffing(
+            endpoint,
+            routing_policy.sniffs_endpoint(&endpoint.endpoint_id),
+        ) {
             Ok(req) => match client.add_inbound(req).await {
                 Ok(_) => ok_add = true,
                 Err(status) if xray::is_already_exists(&status) => {
@@ -994,7 +1061,9 @@ async fn reconcile_snapshot(
                 let ok = apply_membership_enabled(
                     &mut client,
                     endpoint,
+                    routing_policy.sniffs_endpoint(&endpoint.endpoint_id),
                     cluster_ca_key_pem,
+                    alternate_credential_key,
                     user,
                     membership,
                     needs_refresh,
@@ -1027,7 +1096,10 @@ async fn reconcile_snapshot(
         .values()
         .filter(|e| e.node_id == local_node_id)
     {
-        match builder::build_add_inbound_request(endpoint) {
+        match builder::build_add_inbound_request_with_sniffing(
+            endpoint,
+            routing_policy.sniffs_endpoint(&endpoint.endpoint_id),
+        ) {
             Ok(req) => match client.add_inbound(req).await {
                 Ok(_) => {}
                 Err(status) if xray::is_already_exists(&status) => {}
@@ -1067,6 +1139,8 @@ async fn reconcile_snapshot(
                     "xray alter_inbound remove_user failed"
                 ),
             }
+            remove_alternate_client(&mut client, alternate_credential_key, &endpoint.tag, &email)
+                .await;
         }
 
         if let Some(memberships) = memberships_by_endpoint.get(&endpoint.endpoint_id) {
@@ -1081,7 +1155,9 @@ async fn reconcile_snapshot(
                     let ok = apply_membership_enabled(
                         &mut client,
                         endpoint,
+                        routing_policy.sniffs_endpoint(&endpoint.endpoint_id),
                         cluster_ca_key_pem,
+                        alternate_credential_key,
                         user,
                         membership,
                         needs_refresh,
@@ -1106,6 +1182,13 @@ async fn reconcile_snapshot(
                             "xray alter_inbound remove_user failed"
                         ),
                     }
+                    remove_alternate_client(
+                        &mut client,
+                        alternate_credential_key,
+                        &endpoint.tag,
+                        &email,
+                    )
+                    .await;
                 }
             }
         }
@@ -1113,6 +1196,83 @@ async fn reconcile_snapshot(
         next_endpoint_users_applied.insert(endpoint.endpoint_id.clone(), desired_users);
     }
 
+    // 3) Egress outbounds and chained-peer users, which the routing rules below point at.
+    let (mut egress_desired, egress_problems) = build_egress_desired(
+        local_node_id,
+        cluster_ca_key_pem,
+        alternate_credential_key,
+        &endpoint_egress,
+        &nodes,
+        &endpoints_by_id.values().cloned().collect::<Vec<_>>(),
+    );
+    for problem in egress_problems {
+        warn!(%problem, "endpoint egress unavailable; falling back to direct");
+    }
+    let failed_egress_outbounds = egress::apply_egress(&mut client, &egress_desired).await;
+    egress_desired
+        .routing
+        .outbound_by_inbound
+        .retain(|_inbound_tag, outbound_tag| !failed_egress_outbounds.contains(outbound_tag));
+
+    // 4) Routing policy, scoped to the local inbounds that are not exempt.
+    let policy_endpoints = endpoints_by_id
+        .values()
+        .filter(|e| {
+            e.node_id == local_node_id
+                && !routing_policy.exempt_endpoint_ids.contains(&e.endpoint_id)
+        })
+        .collect::<Vec<_>>();
+    let mut exempt_user_emails = BTreeSet::<String>::new();
+    for endpoint in policy_endpoints.iter() {
+        for membership in memberships_by_endpoint
+            .get(&endpoint.endpoint_id)
+            .into_iter()
+            .flatten()
+        {
+            let exempt = users_by_id.get(&membership.user_id).is_some_and(|user| {
+                routing_policy
+                    .exempt_priority_tiers
+                    .contains(&user.priority_tier)
+            });
+            if !exempt {
+                continue;
+            }
+            exempt_user_emails.insert(membership_xray_email(
+                &membership.user_id,
+                &membership.endpoint_id,
+            ));
+            if alternate_credential_key.is_some() {
+                exempt_user_emails.insert(membership_xray_alternate_email(
+                    &membership.user_id,
+                    &membership.endpoint_id,
+                ));
+            }
+        }
+    }
+    let policy_inbound_tags = policy_endpoints
+        .iter()
+        .map(|e| e.tag.clone())
+        .collect::<BTreeSet<_>>();
+    let policy_rules = desired_policy_rules(
+        &routing_policy,
+        &policy_inbound_tags,
+        &exempt_user_emails,
+        &egress_desired.routing,
+    );
+    if let Err(status) = routing_policy::reconcile_policy_rules(&mut client, &policy_rules).await {
+        warn!(%status, "xray routing policy reconciliation failed");
+    }
+    let managed_vless_inbound_tags = endpoints_by_id
+        .values()
+        .filter(|e| e.node_id == local_node_id && managed_default_vless_endpoint(e).is_some())
+        .map(|e| e.tag.clone())
+        .collect::<BTreeSet<_>>();
+    if let Err(status) =
+        egress::remove_stale_egress(&mut client, &egress_desired, &managed_vless_inbound_tags).await
+    {
+        warn!(%status, "stale egress cleanup failed");
+    }
+
     let credential_epochs_applied = users_needing_credential_refresh
         .into_iter()
         .filter(|(user_id, _epoch)| refresh_user_ok.get(user_id).copied().unwrap_or(true))
@@ -1126,166 +1286,5 @@ async fn reconcile_snapshot(
     })
 }
 
-async fn apply_membership_enabled(
-    client: &mut xray::XrayClient,
-    endpoint: &Endpoint,
-    cluster_ca_key_pem: &str,
-    user: &User,
-    membership: &NodeUserEndpointMembership,
-    needs_refresh: bool,
-) -> bool {
-    use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
-
-    let email = membership_xray_email(&membership.user_id, &membership.endpoint_id);
-
-    if needs_refresh {
-        let op = builder::build_remove_user_operation(&email);
-        let req = AlterInboundRequest {
-            tag: endpoint.tag.clone(),
-            operation: Some(op),
-        };
-        match client.alter_inbound(req).await {
-            Ok(_) => {}
-            Err(status) if xray::is_not_found(&status) => {}
-            Err(status) => warn!(
-                tag = endpoint.tag,
-                user_id = membership.user_id,
-                endpoint_id = membership.endpoint_id,
-                %status,
-                "xray alter_inbound remove_user (refresh) failed"
-            ),
-        }
-    }
-
-    let (vless_uuid, ss2022_user_psk_b64) = match endpoint.kind {
-        EndpointKind::VlessRealityVisionTcp => match credentials::derive_vless_uuid(
-            cluster_ca_key_pem,
-            &user.user_id,
-            user.credential_epoch,
-        ) {
-            Ok(uuid) => (Some(uuid), None),
-            Err(e) => {
-                warn!(user_id = user.user_id, error = %e, "failed to derive vless uuid");
-                return false;
-            }
-        },
-        EndpointKind::Ss2022_2022Blake3Aes128Gcm => match credentials::derive_ss2022_user_psk_b64(
-            cluster_ca_key_pem,
-            &user.user_id,
-            user.credential_epoch,
-        ) {
-            Ok(psk) => (None, Some(psk)),
-            Err(e) => {
-                warn!(user_id = user.user_id, error = %e, "failed to derive ss2022 user psk");
-                return false;
-            }
-        },
-    };
-
-    let op = match builder::build_add_user_operation(
-        endpoint,
-        &email,
-        vless_uuid.as_deref(),
-        ss2022_user_psk_b64.as_deref(),
-    ) {
-        Ok(op) => op,
-        Err(e) => {
-            warn!(user_id = user.user_id, error = %e, "failed to build add_user operation");
-            return false;
-        }
-    };
-
-    let req = AlterInboundRequest {
-        tag: endpoint.tag.clone(),
-        operation: Some(op),
-    };
-    match client.alter_inbound(req).await {
-        Ok(_) => true,
-        Err(status) if xray::is_already_exists(&status) => {
-            if needs_refresh {
-                // For credential rotation we must be sure the new credentials are applied.
-                // "already exists" likely means the old user wasn't removed (or Xray didn't
-                // accept the update), so keep retrying until we observe a successful add.
-                warn!(
-                    tag = endpoint.tag,
-                    user_id = user.user_id,
-                    endpoint_id = endpoint.endpoint_id,
-                    "xray alter_inbound add_user returned already_exists during credential refresh; will retry"
-                );
-                false
-            } else {
-                true
-            }
-        }
-        Err(status) if xray::is_not_found(&status) => {
-            match builder::build_add_inbound_request(endpoint) {
-                Ok(req) => match client.add_inbound(req).await {
-                    Ok(_) => {}
-                    Err(status) if xray::is_already_exists(&status) => {}
-                    Err(status) => {
-                        warn!(tag = endpoint.tag, %status, "xray add_inbound retry failed")
-                    }
-                },
-                Err(e) => {
-                    warn!(endpoint_id = endpoint.endpoint_id, error = %e, "failed to build add_inbound request (retry)")
-                }
-            }
-
-            let op = match builder::build_add_user_operation(
-                endpoint,
-                &email,
-                vless_uuid.as_deref(),
-                ss2022_user_psk_b64.as_deref(),
-            ) {
-                Ok(op) => op,
-                Err(e) => {
-                    warn!(user_id = user.user_id, error = %e, "failed to build add_user operation (retry)");
-                    return false;
-                }
-            };
-            let req = AlterInboundRequest {
-                tag: endpoint.tag.clone(),
-                operation: Some(op),
-            };
-            match client.alter_inbound(req).await {
-                Ok(_) => true,
-                Err(status) if xray::is_already_exists(&status) => {
-                    if needs_refresh {
-                        warn!(
-                            tag = endpoint.tag,
-                            user_id = user.user_id,
-                            endpoint_id = endpoint.endpoint_id,
-                            "xray alter_inbound add_user retry returned already_exists during credential refresh; will retry"
-                        );
-                        false
-                    } else {
-                        true
-                    }
-                }
-                Err(status) => {
-                    warn!(
-                        tag = endpoint.tag,
-                        user_id = user.user_id,
-                        endpoint_id = endpoint.endpoint_id,
-                        %status,
-                        "xray alter_inbound add_user retry failed"
-                    );
-                    false
-                }
-            }
-        }
-        Err(status) => {
-            warn!(
-                tag = endpoint.tag,
-                user_id = user.user_id,
-                endpoint_id = endpoint.endpoint_id,
-                %status,
-                "xray alter_inbound add_user failed"
-            );
-            false
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,147 @@
+use tracing::warn;
+
+use super::membership_credentials::{apply_membership_credentials, derive_membership_credentials};
+use crate::{
+    credential_rotation::CredentialRotation,
+    domain::{Endpoint, User},
+    state::{NodeUserEndpointMembership, membership_xray_alternate_email, xray_alternate_email},
+    xray::{self, builder},
+};
+
+/// Key of the other root accepted while a credential rotation is in progress, plus the inbound
+/// hash suffix for it. Starting or retiring a rotation rebuilds inbounds, so no stale alternate
+/// clients remain.
+pub(super) fn alternate_credential_root(
+    rotation: Option<&CredentialRotation>,
+    cluster_ca_key_pem: &str,
+) -> (Option<String>, String) {
+    let alternate_credential_key = rotation
+        .and_then(|rotation| rotation.alternate_root(cluster_ca_key_pem))
+        .map(|root| root.ca_key_pem.clone());
+    let rotation_hash_suffix = rotation
+        .filter(|_| alternate_credential_key.is_some())
+        .map(|rotation| format!(":rotation:{}", rotation.rotation_id))
+        .unwrap_or_default();
+    (alternate_credential_key, rotation_hash_suffix)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn apply_membership_enabled(
+    client: &mut xray::XrayClient,
+    endpoint: &Endpoint,
+    sniffing: bool,
+    cluster_ca_key_pem: &str,
+    alternate_credential_key: Option<&str>,
+    user: &User,
+    membership: &NodeUserEndpointMembership,
+    needs_refresh: bool,
+) -> bool {
+    let ok = apply_membership_credentials(
+        client,
+        endpoint,
+        sniffing,
+        cluster_ca_key_pem,
+        user,
+        membership,
+        needs_refresh,
+    )
+    .await;
+    if let Some(alternate_credential_key) = alternate_credential_key {
+        apply_membership_alternate_credentials(
+            client,
+            endpoint,
+            cluster_ca_key_pem,
+            alternate_credential_key,
+            user,
+            membership,
+            needs_refresh,
+        )
+        .await;
+    }
+    ok
+}
+
+/// Adds a second client with credentials derived from the other root of a credential rotation,
+/// so clients keep working whichever root their subscription was generated with. Best effort:
+/// the primary client decides whether the membership counts as applied.
+async fn apply_membership_alternate_credentials(
+    client: &mut xray::XrayClient,
+    endpoint: &Endpoint,
+    cluster_ca_key_pem: &str,
+    alternate_credential_key: &str,
+    user: &User,
+    membership: &NodeUserEndpointMembership,
+    needs_refresh: bool,
+) {
+    use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
+
+    let email = membership_xray_alternate_email(&membership.user_id, &membership.endpoint_id);
+    if needs_refresh {
+        remove_xray_user(client, &endpoint.tag, &email).await;
+    }
+
+    let Some(credentials) = derive_membership_credentials(alternate_credential_key, user, endpoint)
+    else {
+        return;
+    };
+    // Pinned credentials do not depend on the root; Xray keys VLESS clients by UUID, so a
+    // duplicate would be dropped together with the primary client.
+    if derive_membership_credentials(cluster_ca_key_pem, user, endpoint).as_ref()
+        == Some(&credentials)
+    {
+        return;
+    }
+    let op = match credentials.add_user_operation(endpoint, &email) {
+        Ok(op) => op,
+        Err(e) => {
+            warn!(
+                user_id = user.user_id,
+                error = %e,
+                "failed to build add_user operation (alternate)"
+            );
+            return;
+        }
+    };
+    let req = AlterInboundRequest {
+        tag: endpoint.tag.clone(),
+        operation: Some(op),
+    };
+    match client.alter_inbound(req).await {
+        Ok(_) => {}
+        Err(status) if xray::is_already_exists(&status) => {}
+        Err(status) => warn!(
+            tag = endpoint.tag,
+            user_id = membership.user_id,
+            endpoint_id = membership.endpoint_id,
+            %status,
+            "xray alter_inbound add_user (alternate) failed"
+        ),
+    }
+}
+
+/// Removes one client by email; a missing client or inbound counts as removed.
+async fn remove_xray_user(client: &mut xray::XrayClient, tag: &str, email: &str) {
+    use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
+
+    let req = AlterInboundRequest {
+        tag: tag.to_string(),
+        operation: Some(builder::build_remove_user_operation(email)),
+    };
+    match client.alter_inbound(req).await {
+        Ok(_) => {}
+        Err(status) if xray::is_not_found(&status) => {}
+        Err(status) => warn!(tag, email, %status, "xray alter_inbound remove_user failed"),
+    }
+}
+
+/// Removes the alternate client next to the primary client `email`, if a rotation added one.
+pub(super) async fn remove_alternate_client(
+    client: &mut xray::XrayClient,
+    alternate_credential_key: Option<&str>,
+    tag: &str,
+    email: &str,
+) {
+    if alternate_credential_key.is_some() {
+        remove_xray_user(client, tag, &xray_alternate_email(email)).await;
+    }
+}
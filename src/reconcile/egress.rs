@@ -0,0 +1,102 @@
+use std::collections::BTreeSet;
+
+use tracing::warn;
+
+use crate::{
+    egress::{EGRESS_OUTBOUND_TAG_PREFIX, EGRESS_USER_EMAIL_PREFIX, EgressXrayDesired},
+    xray::{
+        self, XrayClient, builder,
+        proto::xray::app::proxyman::command::{AlterInboundRequest, RemoveOutboundRequest},
+    },
+};
+
+/// Adds the desired egress outbounds and chained-peer users. Returns the outbounds that could
+/// not be added so their inbounds can fall back to direct instead of routing into nothing.
+pub(super) async fn apply_egress(
+    client: &mut XrayClient,
+    desired: &EgressXrayDesired,
+) -> BTreeSet<String> {
+    let mut failed = BTreeSet::new();
+    for request in desired.outbound_requests.iter().cloned() {
+        let tag = request
+            .outbound
+            .as_ref()
+            .map(|outbound| outbound.tag.clone())
+            .unwrap_or_default();
+        match client.add_outbound(request).await {
+            Ok(_) => {}
+            Err(status) if xray::is_already_exists(&status) => {}
+            Err(status) => {
+                warn!(
+                    %status,
+                    outbound_tag = %tag,
+                    "egress outbound add failed; falling back to direct"
+                );
+                failed.insert(tag);
+            }
+        }
+    }
+    for operation in desired.inbound_user_operations.iter() {
+        match client
+            .alter_inbound(AlterInboundRequest {
+                tag: operation.inbound_tag.clone(),
+                operation: Some(operation.operation.clone()),
+            })
+            .await
+        {
+            Ok(_) => {}
+            Err(status) if xray::is_already_exists(&status) => {}
+            Err(status) => {
+                warn!(%status, inbound_tag = %operation.inbound_tag, "egress peer user add failed")
+            }
+        }
+    }
+    failed
+}
+
+/// Removes `xp-egress-*` outbounds and `egress:*` users that are no longer desired. Runs after
+/// the routing rules were updated so no rule points at a removed outbound.
+pub(super) async fn remove_stale_egress(
+    client: &mut XrayClient,
+    desired: &EgressXrayDesired,
+    managed_vless_inbound_tags: &BTreeSet<String>,
+) -> Result<(), tonic::Status> {
+    for outbound in client.list_outbounds().await?.outbounds {
+        if !outbound.tag.starts_with(EGRESS_OUTBOUND_TAG_PREFIX)
+            || desired.owned_outbound_tags.contains(&outbound.tag)
+        {
+            continue;
+        }
+        match client
+            .remove_outbound(RemoveOutboundRequest { tag: outbound.tag })
+            .await
+        {
+            Ok(_) => {}
+            Err(status) if xray::is_not_found(&status) => {}
+            Err(status) => return Err(status),
+        }
+    }
+
+    for inbound_tag in managed_vless_inbound_tags {
+        let owned = desired.owned_inbound_user_emails.get(inbound_tag);
+        for user in client.get_inbound_users(inbound_tag.clone()).await?.users {
+            if !user.email.starts_with(EGRESS_USER_EMAIL_PREFIX)
+                || owned.is_some_and(|owned| owned.contains(&user.email))
+            {
+                continue;
+            }
+            match client
+                .alter_inbound(AlterInboundRequest {
+                    tag: inbound_tag.clone(),
+                    operation: Some(builder::build_remove_user_operation(&user.email)),
+                })
+                .await
+            {
+                Ok(_) => {}
+                Err(status) if xray::is_not_found(&status) => {}
+                Err(status) => return Err(status),
+            }
+        }
+    }
+    Ok(())
+}
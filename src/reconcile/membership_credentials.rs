@@ -0,0 +1,256 @@
+use tracing::warn;
+
+use crate::{
+    credentials,
+    domain::{Endpoint, EndpointKind, User},
+    protocol::{Ss2022EndpointMeta, ss2022_psk_len_bytes},
+    state::{NodeUserEndpointMembership, membership_xray_email},
+    xray::{
+        self,
+        builder::{self, BuildError},
+        proto::xray::common::serial::TypedMessage,
+    },
+};
+
+/// Credentials derived for one user on one endpoint; only the field matching the endpoint kind
+/// is set.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(super) struct MembershipCredentials {
+    vless_uuid: Option<String>,
+    ss2022_user_psk_b64: Option<String>,
+    trojan_password: Option<String>,
+    hysteria2_auth: Option<String>,
+}
+
+impl MembershipCredentials {
+    pub(super) fn add_user_operation(
+        &self,
+        endpoint: &Endpoint,
+        email: &str,
+    ) -> Result<TypedMessage, BuildError> {
+        builder::build_add_user_operation(
+            endpoint,
+            email,
+            self.vless_uuid.as_deref(),
+            self.ss2022_user_psk_b64.as_deref(),
+            self.trojan_password.as_deref(),
+            self.hysteria2_auth.as_deref(),
+        )
+    }
+}
+
+/// Resolves the user's credentials for `endpoint`, logging and returning `None` on failure.
+pub(super) fn derive_membership_credentials(
+    cluster_ca_key_pem: &str,
+    user: &User,
+    endpoint: &Endpoint,
+) -> Option<MembershipCredentials> {
+    match endpoint.kind {
+        EndpointKind::VlessRealityVisionTcp => {
+            match credentials::user_vless_uuid(cluster_ca_key_pem, user) {
+                Ok(uuid) => Some(MembershipCredentials {
+                    vless_uuid: Some(uuid),
+                    ..Default::default()
+                }),
+                Err(e) => {
+                    warn!(user_id = user.user_id, error = %e, "failed to derive vless uuid");
+                    None
+                }
+            }
+        }
+        EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
+            let Some(psk_len) = serde_json::from_value::<Ss2022EndpointMeta>(endpoint.meta.clone())
+                .ok()
+                .and_then(|meta| ss2022_psk_len_bytes(&meta.method))
+            else {
+                warn!(
+                    endpoint_id = endpoint.endpoint_id,
+                    "invalid or unsupported ss2022 endpoint method"
+                );
+                return None;
+            };
+            match credentials::user_ss2022_psk_b64(cluster_ca_key_pem, user, psk_len) {
+                Ok(psk) => Some(MembershipCredentials {
+                    ss2022_user_psk_b64: Some(psk),
+                    ..Default::default()
+                }),
+                Err(e) => {
+                    warn!(user_id = user.user_id, error = %e, "failed to derive ss2022 user psk");
+                    None
+                }
+            }
+        }
+        EndpointKind::TrojanRealityTcp | EndpointKind::TrojanTlsTcp => {
+            match credentials::derive_trojan_password(
+                cluster_ca_key_pem,
+                &user.user_id,
+                user.credential_epoch,
+            ) {
+                Ok(password) => Some(MembershipCredentials {
+                    trojan_password: Some(password),
+                    ..Default::default()
+                }),
+                Err(e) => {
+                    warn!(user_id = user.user_id, error = %e, "failed to derive trojan password");
+                    None
+                }
+            }
+        }
+        EndpointKind::Hysteria2 => match credentials::derive_hysteria2_auth(
+            cluster_ca_key_pem,
+            &user.user_id,
+            user.credential_epoch,
+        ) {
+            Ok(auth) => Some(MembershipCredentials {
+                hysteria2_auth: Some(auth),
+                ..Default::default()
+            }),
+            Err(e) => {
+                warn!(user_id = user.user_id, error = %e, "failed to derive hysteria2 auth");
+                None
+            }
+        },
+    }
+}
+
+pub(super) async fn apply_membership_credentials(
+    client: &mut xray::XrayClient,
+    endpoint: &Endpoint,
+    sniffing: bool,
+    cluster_ca_key_pem: &str,
+    user: &User,
+    membership: &NodeUserEndpointMembership,
+    needs_refresh: bool,
+) -> bool {
+    use crate::xray::proto::xray::app::proxyman::command::AlterInboundRequest;
+
+    let email = membership_xray_email(&membership.user_id, &membership.endpoint_id);
+
+    if needs_refresh {
+        let op = builder::build_remove_user_operation(&email);
+        let req = AlterInboundRequest {
+            tag: endpoint.tag.clone(),
+            operation: Some(op),
+        };
+        match client.alter_inbound(req).await {
+            Ok(_) => {}
+            Err(status) if xray::is_not_found(&status) => {}
+            Err(status) => warn!(
+                tag = endpoint.tag,
+                user_id = membership.user_id,
+                endpoint_id = membership.endpoint_id,
+                %status,
+                "xray alter_inbound remove_user (refresh) failed"
+            ),
+        }
+    }
+
+    let Some(credentials) = derive_membership_credentials(cluster_ca_key_pem, user, endpoint)
+    else {
+        return false;
+    };
+
+    let op = match credentials.add_user_operation(endpoint, &email) {
+        Ok(op) => op,
+        Err(e) => {
+            warn!(user_id = user.user_id, error = %e, "failed to build add_user operation");
+            return false;
+        }
+    };
+
+    let req = AlterInboundRequest {
+        tag: endpoint.tag.clone(),
+        operation: Some(op),
+    };
+    match client.alter_inbound(req).await {
+        Ok(_) => true,
+        Err(status) if xray::is_already_exists(&status) => {
+            if needs_refresh {
+                // For credential rotation we must be sure the new credentials are applied.
+                // "already exists" likely means the old user wasn't removed (or Xray didn't
+                // accept the update), so keep retrying until we observe a successful add.
+                warn!(
+                    tag = endpoint.tag,
+                    user_id = user.user_id,
+                    endpoint_id = endpoint.endpoint_id,
+                    "xray alter_inbound add_user returned already_exists during credential \
+                     refresh; will retry"
+                );
+                false
+            } else {
+                true
+            }
+        }
+        Err(status) if xray::is_not_found(&status) => {
+            match builder::build_add_inbound_request_with_sniffing(endpoint, sniffing) {
+                Ok(req) => match client.add_inbound(req).await {
+                    Ok(_) => {}
+                    Err(status) if xray::is_already_exists(&status) => {}
+                    Err(status) => {
+                        warn!(tag = endpoint.tag, %status, "xray add_inbound retry failed")
+                    }
+                },
+                Err(e) => {
+                    warn!(
+                        endpoint_id = endpoint.endpoint_id,
+                        error = %e,
+                        "failed to build add_inbound request (retry)"
+                    )
+                }
+            }
+
+            let op = match credentials.add_user_operation(endpoint, &email) {
+                Ok(op) => op,
+                Err(e) => {
+                    warn!(
+                        user_id = user.user_id,
+                        error = %e,
+                        "failed to build add_user operation (retry)"
+                    );
+                    return false;
+                }
+            };
+            let req = AlterInboundRequest {
+                tag: endpoint.tag.clone(),
+                operation: Some(op),
+            };
+            match client.alter_inbound(req).await {
+                Ok(_) => true,
+                Err(status) if xray::is_already_exists(&status) => {
+                    if needs_refresh {
+                        warn!(
+                            tag = endpoint.tag,
+                            user_id = user.user_id,
+                            endpoint_id = endpoint.endpoint_id,
+                            "xray alter_inbound add_user retry returned already_exists during \
+                             credential refresh; will retry"
+                        );
+                        false
+                    } else {
+                        true
+                    }
+                }
+                Err(status) => {
+                    warn!(
+                        tag = endpoint.tag,
+                        user_id = user.user_id,
+                        endpoint_id = endpoint.endpoint_id,
+                        %status,
+                        "xray alter_inbound add_user retry failed"
+                    );
+                    false
+                }
+            }
+        }
+        Err(status) => {
+            warn!(
+                tag = endpoint.tag,
+                user_id = user.user_id,
+                endpoint_id = endpoint.endpoint_id,
+                %status,
+                "xray alter_inbound add_user failed"
+            );
+            false
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    routing_policy::{POLICY_RULE_TAG_PREFIX, PolicyRule},
+    xray::{self, XrayClient, builder, proto::xray::app::router::command::RemoveRuleRequest},
+};
+
+/// Brings the router's `xp-policy-*` rules in line with `desired`.
+///
+/// Rule tags embed a hash of the rule set, so comparing the ordered live tags against the desired
+/// ones is enough to detect drift: a changed policy, a restarted Xray that lost its dynamic rules,
+/// or a partially applied previous attempt. On any mismatch every owned rule is removed and the
+/// desired set is appended again, which keeps the evaluation order exact.
+pub(super) async fn reconcile_policy_rules(
+    client: &mut XrayClient,
+    desired: &[PolicyRule],
+) -> Result<(), tonic::Status> {
+    let live = client
+        .list_rules()
+        .await?
+        .rules
+        .into_iter()
+        .map(|rule| rule.rule_tag)
+        .filter(|tag| tag.starts_with(POLICY_RULE_TAG_PREFIX))
+        .collect::<Vec<_>>();
+    if live
+        .iter()
+        .map(String::as_str)
+        .eq(desired.iter().map(|rule| rule.rule_tag.as_str()))
+    {
+        return Ok(());
+    }
+
+    for rule_tag in live {
+        match client
+            .remove_rule(RemoveRuleRequest {
+                rule_tag: rule_tag.clone(),
+            })
+            .await
+        {
+            Ok(_) => {}
+            Err(status) if xray::is_not_found(&status) => {}
+            Err(status) => return Err(status),
+        }
+    }
+    for rule in desired {
+        match client.add_rule(builder::build_policy_rule(rule)).await {
+            Ok(_) => {}
+            Err(status) if xray::is_already_exists(&status) => {}
+            Err(status) => return Err(status),
+        }
+    }
+    Ok(())
+}
@@ -18,8 +18,16 @@ use crate::{
         ListInboundsRequest, ListInboundsResponse, ListOutboundsRequest, ListOutboundsResponse,
         RemoveInboundRequest, RemoveInboundResponse, RemoveOutboundRequest, RemoveOutboundResponse,
     },
+    xray::proto::xray::app::router::command::routing_service_server::RoutingServiceServer,
 };
 
+mod backoff;
+mod credential_rotation;
+mod egress;
+mod membership_credentials;
+mod routing_policy;
+use routing_policy::RecordingRouter;
+mod user_lifecycle;
 mod vless_xhttp;
 
 const TEST_CLUSTER_CA_KEY_PEM: &str = "xp-test-cluster-ca-key";
@@ -38,6 +46,18 @@ enum Call {
         op_type: String,
         email: String,
     },
+    AddRule {
+        rule_tag: String,
+    },
+    RemoveRule {
+        rule_tag: String,
+    },
+    AddOutbound {
+        tag: String,
+    },
+    RemoveOutbound {
+        tag: String,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -53,6 +73,7 @@ struct RecordingHandler {
     calls: Arc<Mutex<Vec<Call>>>,
     behavior: Behavior,
     add_user_not_found_seen: Arc<Mutex<BTreeSet<(String, String)>>>,
+    outbound_tags: Arc<Mutex<BTreeSet<String>>>,
 }
 
 impl RecordingHandler {
@@ -61,6 +82,7 @@ impl RecordingHandler {
             calls,
             behavior,
             add_user_not_found_seen: Arc::new(Mutex::new(BTreeSet::new())),
+            outbound_tags: Arc::new(Mutex::new(BTreeSet::new())),
         }
     }
 }
@@ -179,16 +201,36 @@ impl HandlerService for RecordingHandler {
 
     async fn add_outbound(
         &self,
-        _request: tonic::Request<AddOutboundRequest>,
+        request: tonic::Request<AddOutboundRequest>,
     ) -> Result<tonic::Response<AddOutboundResponse>, tonic::Status> {
-        Err(tonic::Status::unimplemented("add_outbound"))
+        let tag = request
+            .into_inner()
+            .outbound
+            .ok_or_else(|| tonic::Status::invalid_argument("outbound required"))?
+            .tag;
+        self.calls
+            .lock()
+            .await
+            .push(Call::AddOutbound { tag: tag.clone() });
+        if !self.outbound_tags.lock().await.insert(tag) {
+            return Err(tonic::Status::already_exists("existing tag found"));
+        }
+        Ok(tonic::Response::new(AddOutboundResponse {}))
     }
 
     async fn remove_outbound(
         &self,
-        _request: tonic::Request<RemoveOutboundRequest>,
+        request: tonic::Request<RemoveOutboundRequest>,
     ) -> Result<tonic::Response<RemoveOutboundResponse>, tonic::Status> {
-        Err(tonic::Status::unimplemented("remove_outbound"))
+        let tag = request.into_inner().tag;
+        self.calls
+            .lock()
+            .await
+            .push(Call::RemoveOutbound { tag: tag.clone() });
+        if !self.outbound_tags.lock().await.remove(&tag) {
+            return Err(tonic::Status::not_found("outbound not found"));
+        }
+        Ok(tonic::Response::new(RemoveOutboundResponse {}))
     }
 
     async fn alter_outbound(
@@ -202,7 +244,19 @@ impl HandlerService for RecordingHandler {
         &self,
         _request: tonic::Request<ListOutboundsRequest>,
     ) -> Result<tonic::Response<ListOutboundsResponse>, tonic::Status> {
-        Err(tonic::Status::unimplemented("list_outbounds"))
+        let outbounds = self
+            .outbound_tags
+            .lock()
+            .await
+            .iter()
+            .map(
+                |tag| crate::xray::proto::xray::core::OutboundHandlerConfig {
+                    tag: tag.clone(),
+                    ..Default::default()
+                },
+            )
+            .collect();
+        Ok(tonic::Response::new(ListOutboundsResponse { outbounds }))
     }
 }
 
@@ -214,12 +268,14 @@ async fn start_server(
     let addr = listener.local_addr().unwrap();
     let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
 
+    let router = RecordingRouter::new(calls.clone());
     let handler = RecordingHandler::new(calls, behavior);
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
 
     tokio::spawn(async move {
         let _ = tonic::transport::Server::builder()
             .add_service(HandlerServiceServer::new(handler))
+            .add_service(RoutingServiceServer::new(router))
             .serve_with_incoming_shutdown(incoming, async move {
                 let _ = shutdown_rx.await;
             })
@@ -253,6 +309,9 @@ fn test_store_init(
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir: tmp_dir.to_path_buf(),
         admin_token_hash: String::new(),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -281,9 +340,14 @@ fn test_store_init(
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     });
@@ -886,113 +950,4 @@ async fn remove_requests_issue_calls_and_treat_not_found_as_ok() {
     let _ = shutdown.send(());
 }
 
-#[tokio::test]
-async fn add_user_not_found_triggers_add_inbound_then_retries_add_user_once() {
-    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
-    let (addr, shutdown) = start_server(
-        calls.clone(),
-        Behavior {
-            add_user_not_found_first: true,
-            ..Behavior::default()
-        },
-    )
-    .await;
-
-    let tmp = tempfile::tempdir().unwrap();
-    let (_config, store) = test_store_init(tmp.path(), addr);
-
-    let (user, endpoint, membership) = {
-        let mut store = store.lock().await;
-        let local_node_id = store.list_nodes()[0].node_id.clone();
-        let user = store.create_user("alice".to_string(), None).unwrap();
-        let endpoint = store
-            .create_endpoint(
-                local_node_id,
-                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                8388,
-                serde_json::json!({}),
-            )
-            .unwrap();
-        let membership = NodeUserEndpointMembership {
-            user_id: user.user_id.clone(),
-            node_id: endpoint.node_id.clone(),
-            endpoint_id: endpoint.endpoint_id.clone(),
-        };
-        (user, endpoint, membership)
-    };
-
-    let mut client = crate::xray::connect(addr).await.unwrap();
-    let ok = apply_membership_enabled(
-        &mut client,
-        &endpoint,
-        TEST_CLUSTER_CA_KEY_PEM,
-        &user,
-        &membership,
-        false,
-    )
-    .await;
-    assert!(ok);
-
-    let calls = calls.lock().await.clone();
-    assert_eq!(calls.len(), 3);
-    let email = membership_xray_email(&user.user_id, &endpoint.endpoint_id);
-    assert!(
-        matches!(&calls[0], Call::AlterInbound { tag, op_type, email: e } if tag == &endpoint.tag && op_type == "xray.app.proxyman.command.AddUserOperation" && e == &email)
-    );
-    assert_eq!(
-        calls[1],
-        Call::AddInbound {
-            tag: endpoint.tag.clone()
-        }
-    );
-    assert!(
-        matches!(&calls[2], Call::AlterInbound { tag, op_type, email: e } if tag == &endpoint.tag && op_type == "xray.app.proxyman.command.AddUserOperation" && e == &email)
-    );
-
-    let _ = shutdown.send(());
-}
-
-#[test]
-fn backoff_base_doubles_and_caps() {
-    let base = Duration::from_secs(1);
-    let cap = Duration::from_secs(30);
-
-    assert_eq!(base_delay_for_attempt(base, cap, 0), Duration::from_secs(1));
-    assert_eq!(base_delay_for_attempt(base, cap, 1), Duration::from_secs(2));
-    assert_eq!(base_delay_for_attempt(base, cap, 2), Duration::from_secs(4));
-    assert_eq!(base_delay_for_attempt(base, cap, 3), Duration::from_secs(8));
-    assert_eq!(
-        base_delay_for_attempt(base, cap, 4),
-        Duration::from_secs(16)
-    );
-    assert_eq!(
-        base_delay_for_attempt(base, cap, 5),
-        Duration::from_secs(30)
-    );
-    assert_eq!(
-        base_delay_for_attempt(base, cap, 6),
-        Duration::from_secs(30)
-    );
-}
-
-#[test]
-fn backoff_jitter_is_bounded_and_deterministic_with_seeded_rng() {
-    let cfg = BackoffConfig {
-        base: Duration::from_secs(1),
-        cap: Duration::from_secs(30),
-        jitter_max_divisor: 4,
-    };
-
-    let mut backoff = BackoffState::new(cfg, StdRng::seed_from_u64(1));
-    let d0 = backoff.next_delay();
-    let base0 = Duration::from_secs(1);
-    assert!(d0 >= base0);
-    assert!(d0 <= Duration::from_millis(1250));
-
-    let d1 = backoff.next_delay();
-    let base1 = Duration::from_secs(2);
-    assert!(d1 >= base1);
-    assert!(d1 <= Duration::from_millis(2500));
-}
-
 mod reverse_gate_tests;
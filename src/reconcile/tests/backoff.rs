@@ -0,0 +1,46 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[test]
+fn backoff_base_doubles_and_caps() {
+    let base = Duration::from_secs(1);
+    let cap = Duration::from_secs(30);
+
+    assert_eq!(base_delay_for_attempt(base, cap, 0), Duration::from_secs(1));
+    assert_eq!(base_delay_for_attempt(base, cap, 1), Duration::from_secs(2));
+    assert_eq!(base_delay_for_attempt(base, cap, 2), Duration::from_secs(4));
+    assert_eq!(base_delay_for_attempt(base, cap, 3), Duration::from_secs(8));
+    assert_eq!(
+        base_delay_for_attempt(base, cap, 4),
+        Duration::from_secs(16)
+    );
+    assert_eq!(
+        base_delay_for_attempt(base, cap, 5),
+        Duration::from_secs(30)
+    );
+    assert_eq!(
+        base_delay_for_attempt(base, cap, 6),
+        Duration::from_secs(30)
+    );
+}
+
+#[test]
+fn backoff_jitter_is_bounded_and_deterministic_with_seeded_rng() {
+    let cfg = BackoffConfig {
+        base: Duration::from_secs(1),
+        cap: Duration::from_secs(30),
+        jitter_max_divisor: 4,
+    };
+
+    let mut backoff = BackoffState::new(cfg, StdRng::seed_from_u64(1));
+    let d0 = backoff.next_delay();
+    let base0 = Duration::from_secs(1);
+    assert!(d0 >= base0);
+    assert!(d0 <= Duration::from_millis(1250));
+
+    let d1 = backoff.next_delay();
+    let base1 = Duration::from_secs(2);
+    assert!(d1 >= base1);
+    assert!(d1 <= Duration::from_millis(2500));
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+use crate::credential_rotation::{CredentialRoot, CredentialRotation};
+use crate::state::membership_xray_alternate_email;
+
+const PINNED_PSK: &str = "EBESExQVFhcYGRobHB0eHw==";
+
+#[tokio::test]
+async fn rotation_adds_alternate_clients_until_it_is_retired() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(calls.clone(), Behavior::default()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr);
+
+    let (endpoint_tag, email, alternate_email, pinned_alternate_email) = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let alice = store.create_user("alice".to_string(), None).unwrap();
+        let bob = store.create_user("bob".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                local_node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        for user_id in [&alice.user_id, &bob.user_id] {
+            DesiredStateCommand::ReplaceUserAccess {
+                user_id: user_id.clone(),
+                endpoint_ids: vec![endpoint.endpoint_id.clone()],
+            }
+            .apply(store.state_mut())
+            .unwrap();
+        }
+        // Pinned credentials are the same under both roots, so bob needs no second client.
+        store
+            .state_mut()
+            .users
+            .get_mut(&bob.user_id)
+            .unwrap()
+            .pinned_credentials
+            .ss2022_psk_b64 = Some(PINNED_PSK.to_string());
+        let mut rotation = CredentialRotation::start(
+            "01JTESTCLUSTERID00000000000000",
+            CredentialRoot {
+                ca_cert_pem: String::new(),
+                ca_key_pem: TEST_CLUSTER_CA_KEY_PEM.to_string(),
+            },
+            24,
+            "2026-10-01T00:00:00Z".to_string(),
+        )
+        .unwrap();
+        rotation.rotation_id = "01JTESTROTATION00000000000000".to_string();
+        store.state_mut().credential_rotation = Some(rotation);
+        store.save().unwrap();
+        (
+            endpoint.tag,
+            membership_xray_email(&alice.user_id, &endpoint.endpoint_id),
+            membership_xray_alternate_email(&alice.user_id, &endpoint.endpoint_id),
+            membership_xray_alternate_email(&bob.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let add_op = "xray.app.proxyman.command.AddUserOperation";
+    let added = |calls: &[Call], wanted: &str| {
+        calls.iter().any(|call| {
+            matches!(call, Call::AlterInbound { tag, op_type, email }
+                if tag == &endpoint_tag && op_type == add_op && email == wanted)
+        })
+    };
+    let pending = PendingBatch {
+        full: true,
+        ..Default::default()
+    };
+    let mut last_applied_hash_by_endpoint_id = BTreeMap::<String, String>::new();
+
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let snapshot = calls.lock().await.drain(..).collect::<Vec<_>>();
+    assert!(added(&snapshot, &email));
+    assert!(added(&snapshot, &alternate_email));
+    assert!(!added(&snapshot, &pinned_alternate_email));
+
+    store.lock().await.state_mut().credential_rotation = None;
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let snapshot = calls.lock().await.drain(..).collect::<Vec<_>>();
+    // Retiring the rotation rebuilds the inbound, which drops the alternate clients.
+    assert!(snapshot.contains(&Call::RemoveInbound {
+        tag: endpoint_tag.clone()
+    }));
+    assert!(added(&snapshot, &email));
+    assert!(!added(&snapshot, &alternate_email));
+
+    let _ = shutdown.send(());
+}
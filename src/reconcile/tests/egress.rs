@@ -0,0 +1,240 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+use crate::egress::EndpointEgress;
+
+fn outbound_calls(calls: &[Call]) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut added = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    for call in calls {
+        match call {
+            Call::AddOutbound { tag } => {
+                added.insert(tag.clone());
+            }
+            Call::RemoveOutbound { tag } => {
+                removed.insert(tag.clone());
+            }
+            _ => {}
+        }
+    }
+    (added, removed)
+}
+
+fn managed_vless_endpoint(endpoint_id: &str, node_id: &str, tag: &str) -> Endpoint {
+    Endpoint {
+        endpoint_id: endpoint_id.to_string(),
+        node_id: node_id.to_string(),
+        tag: tag.to_string(),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port: 443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
+            "managed_default": true
+        }),
+    }
+}
+
+fn remote_node() -> Node {
+    Node {
+        node_id: xp_test_fixtures::identifier_ulid_c().to_owned(),
+        node_name: xp_test_fixtures::label_node2().to_owned(),
+        access_host: xp_test_fixtures::secondary_host().to_owned(),
+        api_base_url: xp_test_fixtures::service_fixture451().to_owned(),
+        quota_limit_bytes: 0,
+        quota_reset: NodeQuotaReset::default(),
+    }
+}
+
+#[tokio::test]
+async fn chained_egress_adds_an_outbound_and_rule_and_removes_them_when_cleared() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(calls.clone(), Behavior::default()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr);
+
+    let endpoint_id = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let remote = remote_node();
+        store.upsert_node(remote.clone()).unwrap();
+        let local = store
+            .create_endpoint(
+                local_node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        let remote_vless = managed_vless_endpoint("remote-vless", &remote.node_id, "remote-vless");
+        store
+            .state_mut()
+            .endpoints
+            .insert(remote_vless.endpoint_id.clone(), remote_vless);
+        DesiredStateCommand::SetEndpointEgress {
+            endpoint_id: local.endpoint_id.clone(),
+            egress: EndpointEgress::Node {
+                node_id: remote.node_id,
+            },
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        local.endpoint_id
+    };
+
+    let pending = PendingBatch {
+        full: true,
+        ..Default::default()
+    };
+    let mut last_applied_hash_by_endpoint_id = BTreeMap::<String, String>::new();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let outbound_tag = {
+        let calls = calls.lock().await;
+        let (added, removed) = outbound_calls(&calls);
+        assert_eq!(added.len(), 1);
+        assert!(removed.is_empty());
+        let outbound_tag = added.into_iter().next().unwrap();
+        assert!(outbound_tag.starts_with("xp-egress-node-"));
+        let rules = calls
+            .iter()
+            .filter(|call| matches!(call, Call::AddRule { .. }))
+            .count();
+        assert_eq!(rules, 1);
+        outbound_tag
+    };
+
+    // Clearing the egress removes the rule first, then the stale outbound.
+    {
+        let mut store = store.lock().await;
+        DesiredStateCommand::SetEndpointEgress {
+            endpoint_id,
+            egress: EndpointEgress::Direct,
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        assert!(store.state().endpoint_egress.is_empty());
+        store.save().unwrap();
+    }
+    calls.lock().await.clear();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    {
+        let calls = calls.lock().await;
+        assert_eq!(
+            outbound_calls(&calls),
+            (BTreeSet::new(), BTreeSet::from([outbound_tag]))
+        );
+        let rule_removed_at = calls
+            .iter()
+            .position(|call| matches!(call, Call::RemoveRule { .. }))
+            .expect("rule removed");
+        let outbound_removed_at = calls
+            .iter()
+            .position(|call| matches!(call, Call::RemoveOutbound { .. }))
+            .unwrap();
+        assert!(rule_removed_at < outbound_removed_at);
+        assert!(
+            !calls
+                .iter()
+                .any(|call| matches!(call, Call::AddRule { .. }))
+        );
+    }
+
+    let _ = shutdown.send(());
+}
+
+#[tokio::test]
+async fn chain_target_admits_the_source_node_on_its_managed_vless_inbound() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(calls.clone(), Behavior::default()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr);
+
+    let remote_node_id = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let remote = remote_node();
+        store.upsert_node(remote.clone()).unwrap();
+        let local_vless = managed_vless_endpoint("local-vless", &local_node_id, "local-vless");
+        store
+            .state_mut()
+            .endpoints
+            .insert(local_vless.endpoint_id.clone(), local_vless);
+        let remote_ss = store
+            .create_endpoint(
+                remote.node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::SetEndpointEgress {
+            endpoint_id: remote_ss.endpoint_id,
+            egress: EndpointEgress::Node {
+                node_id: local_node_id,
+            },
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        remote.node_id
+    };
+
+    let pending = PendingBatch {
+        full: true,
+        ..Default::default()
+    };
+    let mut last_applied_hash_by_endpoint_id = BTreeMap::<String, String>::new();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+
+    let calls = calls.lock().await;
+    let expected_email = format!("egress:{remote_node_id}");
+    assert!(calls.iter().any(|call| matches!(
+        call,
+        Call::AlterInbound { tag, op_type, email }
+            if tag == "local-vless"
+                && op_type == "xray.app.proxyman.command.AddUserOperation"
+                && *email == expected_email
+    )));
+    // The target only needs the transit rule; the outbound lives on the source node.
+    assert_eq!(outbound_calls(&calls), (BTreeSet::new(), BTreeSet::new()));
+    assert_eq!(
+        calls
+            .iter()
+            .filter(|call| matches!(call, Call::AddRule { .. }))
+            .count(),
+        1
+    );
+    drop(calls);
+
+    let _ = shutdown.send(());
+}
@@ -0,0 +1,79 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+#[tokio::test]
+async fn add_user_not_found_triggers_add_inbound_then_retries_add_user_once() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(
+        calls.clone(),
+        Behavior {
+            add_user_not_found_first: true,
+            ..Behavior::default()
+        },
+    )
+    .await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (_config, store) = test_store_init(tmp.path(), addr);
+
+    let (user, endpoint, membership) = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                local_node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        let membership = NodeUserEndpointMembership {
+            user_id: user.user_id.clone(),
+            node_id: endpoint.node_id.clone(),
+            endpoint_id: endpoint.endpoint_id.clone(),
+        };
+        (user, endpoint, membership)
+    };
+
+    let mut client = crate::xray::connect(addr).await.unwrap();
+    let ok = apply_membership_enabled(
+        &mut client,
+        &endpoint,
+        false,
+        TEST_CLUSTER_CA_KEY_PEM,
+        None,
+        &user,
+        &membership,
+        false,
+    )
+    .await;
+    assert!(ok);
+
+    let calls = calls.lock().await.clone();
+    assert_eq!(calls.len(), 3);
+    let email = membership_xray_email(&user.user_id, &endpoint.endpoint_id);
+    let is_add_user = |call: &Call| match call {
+        Call::AlterInbound {
+            tag,
+            op_type,
+            email: e,
+        } => {
+            tag == &endpoint.tag
+                && op_type == "xray.app.proxyman.command.AddUserOperation"
+                && e == &email
+        }
+        _ => false,
+    };
+    assert!(is_add_user(&calls[0]));
+    assert_eq!(
+        calls[1],
+        Call::AddInbound {
+            tag: endpoint.tag.clone()
+        }
+    );
+    assert!(is_add_user(&calls[2]));
+
+    let _ = shutdown.send(());
+}
@@ -0,0 +1,281 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+use crate::{
+    domain::UserPriorityTier,
+    routing_policy::XrayRoutingPolicy,
+    xray::proto::xray::app::router::command::{
+        AddRuleRequest, AddRuleResponse, ListRuleItem, ListRuleRequest, ListRuleResponse,
+        RemoveRuleRequest, RemoveRuleResponse, routing_service_server::RoutingService,
+    },
+};
+
+/// Router mock that keeps the live rule tags in order, like Xray's dynamic routing table.
+#[derive(Debug)]
+pub(super) struct RecordingRouter {
+    calls: Arc<Mutex<Vec<Call>>>,
+    rule_tags: Arc<Mutex<Vec<String>>>,
+}
+
+impl RecordingRouter {
+    pub(super) fn new(calls: Arc<Mutex<Vec<Call>>>) -> Self {
+        Self {
+            calls,
+            rule_tags: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl RoutingService for RecordingRouter {
+    async fn add_rule(
+        &self,
+        request: tonic::Request<AddRuleRequest>,
+    ) -> Result<tonic::Response<AddRuleResponse>, tonic::Status> {
+        let config = request
+            .into_inner()
+            .config
+            .ok_or_else(|| tonic::Status::invalid_argument("config required"))?;
+        let config: crate::xray::proto::xray::app::router::Config = decode_typed(&config);
+        let mut rule_tags = self.rule_tags.lock().await;
+        for rule in config.rule {
+            self.calls.lock().await.push(Call::AddRule {
+                rule_tag: rule.rule_tag.clone(),
+            });
+            rule_tags.push(rule.rule_tag);
+        }
+        Ok(tonic::Response::new(AddRuleResponse {}))
+    }
+
+    async fn remove_rule(
+        &self,
+        request: tonic::Request<RemoveRuleRequest>,
+    ) -> Result<tonic::Response<RemoveRuleResponse>, tonic::Status> {
+        let rule_tag = request.into_inner().rule_tag;
+        self.calls.lock().await.push(Call::RemoveRule {
+            rule_tag: rule_tag.clone(),
+        });
+        let mut rule_tags = self.rule_tags.lock().await;
+        let before = rule_tags.len();
+        rule_tags.retain(|tag| tag != &rule_tag);
+        if rule_tags.len() == before {
+            return Err(tonic::Status::not_found("rule not found"));
+        }
+        Ok(tonic::Response::new(RemoveRuleResponse {}))
+    }
+
+    async fn list_rule(
+        &self,
+        _request: tonic::Request<ListRuleRequest>,
+    ) -> Result<tonic::Response<ListRuleResponse>, tonic::Status> {
+        let rules = self
+            .rule_tags
+            .lock()
+            .await
+            .iter()
+            .map(|rule_tag| ListRuleItem {
+                tag: String::new(),
+                rule_tag: rule_tag.clone(),
+            })
+            .collect();
+        Ok(tonic::Response::new(ListRuleResponse { rules }))
+    }
+}
+
+fn rule_calls(calls: &[Call]) -> (Vec<String>, Vec<String>) {
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for call in calls {
+        match call {
+            Call::AddRule { rule_tag } => added.push(rule_tag.clone()),
+            Call::RemoveRule { rule_tag } => removed.push(rule_tag.clone()),
+            _ => {}
+        }
+    }
+    (added, removed)
+}
+
+fn removed_inbounds(calls: &[Call]) -> BTreeSet<String> {
+    calls
+        .iter()
+        .filter_map(|call| match call {
+            Call::RemoveInbound { tag } => Some(tag.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn routing_policy_rules_and_sniffing_follow_the_replicated_policy() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(calls.clone(), Behavior::default()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr);
+
+    let (filtered_tag, exempt_endpoint) = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let alice = store.create_user("alice".to_string(), None).unwrap();
+        let bob = store.create_user("bob".to_string(), None).unwrap();
+        let filtered = store
+            .create_endpoint(
+                local_node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        let exempt = store
+            .create_endpoint(
+                local_node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8389,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        for user_id in [&alice.user_id, &bob.user_id] {
+            DesiredStateCommand::ReplaceUserAccess {
+                user_id: user_id.clone(),
+                endpoint_ids: vec![filtered.endpoint_id.clone(), exempt.endpoint_id.clone()],
+            }
+            .apply(store.state_mut())
+            .unwrap();
+        }
+        store
+            .state_mut()
+            .users
+            .get_mut(&bob.user_id)
+            .unwrap()
+            .priority_tier = UserPriorityTier::P1;
+        store.save().unwrap();
+        (filtered.tag, exempt)
+    };
+
+    let set_policy = |policy: XrayRoutingPolicy| {
+        let store = store.clone();
+        async move {
+            let mut store = store.lock().await;
+            DesiredStateCommand::SetXrayRoutingPolicy { policy }
+                .apply(store.state_mut())
+                .unwrap();
+            store.save().unwrap();
+        }
+    };
+    let pending = PendingBatch {
+        full: true,
+        ..Default::default()
+    };
+    let mut last_applied_hash_by_endpoint_id = BTreeMap::<String, String>::new();
+
+    // Without a policy nothing is added to the router.
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    assert_eq!(rule_calls(&calls.lock().await), (Vec::new(), Vec::new()));
+
+    let policy = XrayRoutingPolicy {
+        sniffing: true,
+        block_private: true,
+        exempt_endpoint_ids: BTreeSet::from([exempt_endpoint.endpoint_id.clone()]),
+        exempt_priority_tiers: BTreeSet::from([UserPriorityTier::P1]),
+        ..Default::default()
+    };
+    set_policy(policy.clone()).await;
+    calls.lock().await.clear();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let applied = {
+        let calls = calls.lock().await;
+        // Sniffing is part of the inbound, so only the non-exempt inbound is rebuilt.
+        assert_eq!(
+            removed_inbounds(&calls),
+            BTreeSet::from([filtered_tag.clone()])
+        );
+        let (added, removed) = rule_calls(&calls);
+        // The exempt-tier allow rule comes first, then the private-range block rule.
+        assert_eq!(added.len(), 2);
+        assert!(added.iter().all(|tag| tag.starts_with("xp-policy-")));
+        assert!(added[0].ends_with("-0") && added[1].ends_with("-1"));
+        assert!(removed.is_empty());
+        added
+    };
+
+    // A converged node makes no further router or inbound changes.
+    calls.lock().await.clear();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    {
+        let calls = calls.lock().await;
+        assert_eq!(rule_calls(&calls), (Vec::new(), Vec::new()));
+        assert!(removed_inbounds(&calls).is_empty());
+    }
+
+    // Changing the block lists replaces the whole rule set in order.
+    set_policy(XrayRoutingPolicy {
+        block_bittorrent: true,
+        blocked_ips: vec!["198.51.100.0/24".to_string()],
+        ..policy
+    })
+    .await;
+    calls.lock().await.clear();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    {
+        let calls = calls.lock().await;
+        let (added, removed) = rule_calls(&calls);
+        assert_eq!(removed, applied);
+        assert_eq!(added.len(), 4);
+        assert!(removed_inbounds(&calls).is_empty());
+    }
+
+    // Clearing the policy removes the owned rules and turns sniffing back off.
+    set_policy(XrayRoutingPolicy::default()).await;
+    calls.lock().await.clear();
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    {
+        let calls = calls.lock().await;
+        let (added, removed) = rule_calls(&calls);
+        assert!(added.is_empty());
+        assert_eq!(removed.len(), 4);
+        assert_eq!(removed_inbounds(&calls), BTreeSet::from([filtered_tag]));
+    }
+
+    let _ = shutdown.send(());
+}
@@ -0,0 +1,124 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn disabled_or_expired_user_is_removed_and_reenable_restores_access() {
+    let calls = Arc::new(Mutex::new(Vec::<Call>::new()));
+    let (addr, shutdown) = start_server(calls.clone(), Behavior::default()).await;
+
+    let tmp = tempfile::tempdir().unwrap();
+    let (config, store) = test_store_init(tmp.path(), addr);
+
+    let (user_id, endpoint_tag, email) = {
+        let mut store = store.lock().await;
+        let local_node_id = store.list_nodes()[0].node_id.clone();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                local_node_id,
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                8388,
+                serde_json::json!({}),
+            )
+            .unwrap();
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id.clone(),
+            endpoint_ids: vec![endpoint.endpoint_id.clone()],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        DesiredStateCommand::SetUserExpiry {
+            user_id: user.user_id.clone(),
+            expires_at: Some("2000-01-01T00:00:00Z".to_string()),
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        (
+            user.user_id.clone(),
+            endpoint.tag,
+            membership_xray_email(&user.user_id, &endpoint.endpoint_id),
+        )
+    };
+
+    let is_op = |call: &Call, op: &str| {
+        matches!(call, Call::AlterInbound { tag, op_type, email: e }
+            if tag == &endpoint_tag && op_type == op && e == &email)
+    };
+    let remove_op = "xray.app.proxyman.command.RemoveUserOperation";
+    let add_op = "xray.app.proxyman.command.AddUserOperation";
+    let pending = PendingBatch {
+        full: true,
+        ..Default::default()
+    };
+    let mut last_applied_hash_by_endpoint_id = BTreeMap::<String, String>::new();
+
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let snapshot = calls.lock().await.drain(..).collect::<Vec<_>>();
+    assert!(snapshot.iter().any(|c| is_op(c, remove_op)));
+    assert!(!snapshot.iter().any(|c| is_op(c, add_op)));
+
+    {
+        let mut store = store.lock().await;
+        for cmd in [
+            DesiredStateCommand::SetUserExpiry {
+                user_id: user_id.clone(),
+                expires_at: None,
+            },
+            DesiredStateCommand::SetUserDisabled {
+                user_id: user_id.clone(),
+                disabled: true,
+            },
+        ] {
+            cmd.apply(store.state_mut()).unwrap();
+        }
+        store.save().unwrap();
+    }
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let snapshot = calls.lock().await.drain(..).collect::<Vec<_>>();
+    assert!(snapshot.iter().any(|c| is_op(c, remove_op)));
+    assert!(!snapshot.iter().any(|c| is_op(c, add_op)));
+
+    {
+        let mut store = store.lock().await;
+        DesiredStateCommand::SetUserDisabled {
+            user_id: user_id.clone(),
+            disabled: false,
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        assert_eq!(store.get_user(&user_id).unwrap().credential_epoch, 0);
+    }
+    reconcile_once(
+        &config,
+        &store,
+        &pending,
+        &mut last_applied_hash_by_endpoint_id,
+        TEST_CLUSTER_CA_KEY_PEM,
+    )
+    .await
+    .unwrap();
+    let snapshot = calls.lock().await.clone();
+    assert!(snapshot.iter().any(|c| is_op(c, add_op)));
+    assert!(!snapshot.iter().any(|c| is_op(c, remove_op)));
+
+    let _ = shutdown.send(());
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::IpAddr,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use crate::{domain::UserPriorityTier, egress::EgressRouting};
+
+/// Rule tags owned by the routing policy; reconcile never touches other rules.
+pub const POLICY_RULE_TAG_PREFIX: &str = "xp-policy-";
+
+const OUTBOUND_DIRECT: &str = "direct";
+const OUTBOUND_BLOCK: &str = "block";
+
+/// Destinations blocked by `block_private`: RFC1918, CGNAT, loopback, link-local and the IPv6
+/// equivalents, so a proxy user cannot reach the node's LAN or its local services.
+const PRIVATE_CIDRS: [&str; 10] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+];
+
+/// Cluster-wide routing policy applied by reconcile to the business inbounds of every node.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct XrayRoutingPolicy {
+    /// Route-only sniffing on business inbounds; required for domain and BitTorrent matching.
+    #[serde(default)]
+    pub sniffing: bool,
+    #[serde(default)]
+    pub block_private: bool,
+    #[serde(default)]
+    pub block_bittorrent: bool,
+    /// Xray-style domain matchers: `domain:`, `full:`, `keyword:` or `regexp:`; bare values
+    /// are treated as `domain:`.
+    #[serde(default)]
+    pub blocked_domains: Vec<String>,
+    /// IP addresses or CIDRs.
+    #[serde(default)]
+    pub blocked_ips: Vec<String>,
+    /// Endpoints left untouched by the policy: no sniffing and no block rules.
+    #[serde(default)]
+    pub exempt_endpoint_ids: BTreeSet<String>,
+    /// Users in these tiers bypass the block rules on every endpoint.
+    #[serde(default)]
+    pub exempt_priority_tiers: BTreeSet<UserPriorityTier>,
+}
+
+impl XrayRoutingPolicy {
+    /// Whether inbounds of `endpoint_id` are built with sniffing enabled.
+    pub fn sniffs_endpoint(&self, endpoint_id: &str) -> bool {
+        self.sniffing && !self.exempt_endpoint_ids.contains(endpoint_id)
+    }
+
+    fn has_block_rules(&self) -> bool {
+        self.block_private
+            || self.block_bittorrent
+            || !self.blocked_domains.is_empty()
+            || !self.blocked_ips.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum DomainMatcher {
+    Domain(String),
+    Full(String),
+    Keyword(String),
+    Regexp(String),
+}
+
+impl DomainMatcher {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let (prefix, value) = raw
+            .split_once(':')
+            .filter(|(prefix, _)| matches!(*prefix, "domain" | "full" | "keyword" | "regexp"))
+            .unwrap_or(("domain", raw));
+        if value.is_empty() {
+            return Err(format!("blocked domain {raw:?} is empty"));
+        }
+        if prefix == "regexp" {
+            // Regular expressions are case-sensitive in Xray; keep them as written.
+            regex::Regex::new(value)
+                .map_err(|e| format!("blocked domain {raw:?}: invalid regexp: {e}"))?;
+            return Ok(Self::Regexp(value.to_string()));
+        }
+        if value.chars().any(char::is_whitespace) {
+            return Err(format!("blocked domain {raw:?} contains whitespace"));
+        }
+        let value = value.to_ascii_lowercase();
+        Ok(match prefix {
+            "full" => Self::Full(value),
+            "keyword" => Self::Keyword(value),
+            _ => Self::Domain(value),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IpCidr {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl IpCidr {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let raw = raw.trim();
+        let (addr, prefix) = match raw.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (raw, None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| format!("blocked ip {raw:?} is not an IP address or CIDR"))?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            None => max_prefix,
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("blocked ip {raw:?} has an invalid prefix length"))?,
+        };
+        Ok(Self { addr, prefix })
+    }
+
+    /// Address bytes in the form Xray's `CIDR.ip` expects.
+    pub fn ip_bytes(&self) -> Vec<u8> {
+        match self.addr {
+            IpAddr::V4(addr) => addr.octets().to_vec(),
+            IpAddr::V6(addr) => addr.octets().to_vec(),
+        }
+    }
+}
+
+pub fn validate_routing_policy(policy: &XrayRoutingPolicy) -> Result<(), String> {
+    if policy.block_bittorrent && !policy.sniffing {
+        return Err("block_bittorrent requires sniffing".to_string());
+    }
+    if !policy.blocked_domains.is_empty() && !policy.sniffing {
+        return Err("blocked_domains requires sniffing".to_string());
+    }
+    for domain in &policy.blocked_domains {
+        DomainMatcher::parse(domain)?;
+    }
+    for ip in &policy.blocked_ips {
+        IpCidr::parse(ip)?;
+    }
+    Ok(())
+}
+
+/// One routing rule appended by reconcile, in evaluation order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PolicyRule {
+    pub rule_tag: String,
+    pub outbound_tag: String,
+    pub inbound_tags: Vec<String>,
+    pub user_emails: Vec<String>,
+    pub domains: Vec<DomainMatcher>,
+    pub cidrs: Vec<IpCidr>,
+    pub protocols: Vec<String>,
+}
+
+/// Builds the ordered rules for one node.
+///
+/// `inbound_tags` are the node's business inbounds that are not exempt, and `exempt_user_emails`
+/// the Xray client emails of users in an exempt tier. The order is:
+///
+/// 1. chained peers arriving through `egress.transit_*` leave directly, so chains never loop;
+/// 2. exempt users skip the block rules, still honouring their inbound's egress;
+/// 3. the block rules;
+/// 4. the per-inbound egress outbounds.
+///
+/// Rule tags embed a hash of the whole rule set, so any change to the policy, the egress or the
+/// scoped inbounds/users shows up as a tag mismatch against the live router.
+pub fn desired_policy_rules(
+    policy: &XrayRoutingPolicy,
+    inbound_tags: &BTreeSet<String>,
+    exempt_user_emails: &BTreeSet<String>,
+    egress: &EgressRouting,
+) -> Vec<PolicyRule> {
+    let block_inbound_tags = if policy.has_block_rules() {
+        inbound_tags.iter().cloned().collect::<Vec<_>>()
+    } else {
+        Vec::new()
+    };
+    let rule = |outbound_tag: &str, inbound_tags: Vec<String>| PolicyRule {
+        rule_tag: String::new(),
+        outbound_tag: outbound_tag.to_string(),
+        inbound_tags,
+        user_emails: Vec::new(),
+        domains: Vec::new(),
+        cidrs: Vec::new(),
+        protocols: Vec::new(),
+    };
+    let block = || rule(OUTBOUND_BLOCK, block_inbound_tags.clone());
+    // Inbounds grouped by the outbound their traffic leaves through.
+    let group_by_outbound = |inbound_tags: &[String]| {
+        let mut groups = BTreeMap::<&str, Vec<String>>::new();
+        for tag in inbound_tags {
+            let outbound = egress
+                .outbound_by_inbound
+                .get(tag)
+                .map(String::as_str)
+                .unwrap_or(OUTBOUND_DIRECT);
+            groups.entry(outbound).or_default().push(tag.clone());
+        }
+        groups
+    };
+
+    let mut rules = Vec::new();
+    if !egress.transit_inbound_tags.is_empty() && !egress.transit_user_emails.is_empty() {
+        rules.push(PolicyRule {
+            user_emails: egress.transit_user_emails.iter().cloned().collect(),
+            ..rule(
+                OUTBOUND_DIRECT,
+                egress.transit_inbound_tags.iter().cloned().collect(),
+            )
+        });
+    }
+    if !block_inbound_tags.is_empty() {
+        if !exempt_user_emails.is_empty() {
+            for (outbound_tag, tags) in group_by_outbound(&block_inbound_tags) {
+                rules.push(PolicyRule {
+                    user_emails: exempt_user_emails.iter().cloned().collect(),
+                    ..rule(outbound_tag, tags)
+                });
+            }
+        }
+        if policy.block_private {
+            rules.push(PolicyRule {
+                cidrs: PRIVATE_CIDRS
+                    .iter()
+                    .filter_map(|cidr| IpCidr::parse(cidr).ok())
+                    .collect(),
+                ..block()
+            });
+        }
+        if policy.block_bittorrent {
+            rules.push(PolicyRule {
+                protocols: vec!["bittorrent".to_string()],
+                ..block()
+            });
+        }
+        let domains = policy
+            .blocked_domains
+            .iter()
+            .filter_map(|domain| DomainMatcher::parse(domain).ok())
+            .collect::<Vec<_>>();
+        if !domains.is_empty() {
+            rules.push(PolicyRule { domains, ..block() });
+        }
+        let cidrs = policy
+            .blocked_ips
+            .iter()
+            .filter_map(|ip| IpCidr::parse(ip).ok())
+            .collect::<Vec<_>>();
+        if !cidrs.is_empty() {
+            rules.push(PolicyRule { cidrs, ..block() });
+        }
+    }
+    let egress_inbound_tags = egress
+        .outbound_by_inbound
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    for (outbound_tag, tags) in group_by_outbound(&egress_inbound_tags) {
+        rules.push(rule(outbound_tag, tags));
+    }
+    if rules.is_empty() {
+        return rules;
+    }
+
+    let hash = serde_json::to_vec(&rules)
+        .map(|bytes| hex::encode(Sha256::digest(bytes)))
+        .unwrap_or_default();
+    for (index, rule) in rules.iter_mut().enumerate() {
+        rule.rule_tag = format!("{POLICY_RULE_TAG_PREFIX}{}-{index}", &hash[..12]);
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,160 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+fn tags(values: &[&str]) -> BTreeSet<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+#[test]
+fn domain_and_ip_entries_follow_xray_syntax() {
+    assert_eq!(
+        DomainMatcher::parse("Example.COM").unwrap(),
+        DomainMatcher::Domain("example.com".to_string())
+    );
+    assert_eq!(
+        DomainMatcher::parse("full:www.example.com").unwrap(),
+        DomainMatcher::Full("www.example.com".to_string())
+    );
+    assert_eq!(
+        DomainMatcher::parse("keyword:torrent").unwrap(),
+        DomainMatcher::Keyword("torrent".to_string())
+    );
+    assert_eq!(
+        DomainMatcher::parse("regexp:^Ads\\.").unwrap(),
+        DomainMatcher::Regexp("^Ads\\.".to_string())
+    );
+    for invalid in ["", "full:", "regexp:(", "bad domain"] {
+        assert!(DomainMatcher::parse(invalid).is_err(), "{invalid:?}");
+    }
+
+    assert_eq!(IpCidr::parse("203.0.113.7").unwrap().prefix, 32);
+    assert_eq!(IpCidr::parse("2001:db8::/32").unwrap().prefix, 32);
+    for invalid in ["203.0.113.0/33", "::/129", "example.com", "10.0.0.0/x"] {
+        assert!(IpCidr::parse(invalid).is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn sniffing_is_required_for_protocol_and_domain_matching() {
+    let policy = XrayRoutingPolicy {
+        block_bittorrent: true,
+        ..Default::default()
+    };
+    assert!(validate_routing_policy(&policy).is_err());
+    let policy = XrayRoutingPolicy {
+        blocked_domains: vec!["example.com".to_string()],
+        ..Default::default()
+    };
+    assert!(validate_routing_policy(&policy).is_err());
+    let policy = XrayRoutingPolicy {
+        sniffing: true,
+        block_bittorrent: true,
+        blocked_domains: vec!["example.com".to_string()],
+        blocked_ips: vec!["198.51.100.0/24".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(validate_routing_policy(&policy), Ok(()));
+}
+
+#[test]
+fn desired_rules_put_exempt_users_first_and_tag_by_content() {
+    let policy = XrayRoutingPolicy {
+        sniffing: true,
+        block_private: true,
+        block_bittorrent: true,
+        blocked_domains: vec!["example.com".to_string()],
+        blocked_ips: vec!["198.51.100.0/24".to_string()],
+        ..Default::default()
+    };
+    let inbounds = tags(&["a", "b"]);
+    let no_egress = EgressRouting::default();
+    let rules = desired_policy_rules(&policy, &inbounds, &tags(&["m:u1::e1"]), &no_egress);
+    assert_eq!(
+        rules
+            .iter()
+            .map(|rule| rule.outbound_tag.as_str())
+            .collect::<Vec<_>>(),
+        vec!["direct", "block", "block", "block", "block"]
+    );
+    assert_eq!(rules[0].user_emails, vec!["m:u1::e1".to_string()]);
+    assert_eq!(rules[1].cidrs.len(), PRIVATE_CIDRS.len());
+    assert_eq!(rules[2].protocols, vec!["bittorrent".to_string()]);
+    for (index, rule) in rules.iter().enumerate() {
+        assert!(rule.rule_tag.starts_with(POLICY_RULE_TAG_PREFIX));
+        assert!(rule.rule_tag.ends_with(&format!("-{index}")));
+        assert_eq!(rule.inbound_tags, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    // Same input, same tags; scoping to different inbounds changes them.
+    let again = desired_policy_rules(&policy, &inbounds, &tags(&["m:u1::e1"]), &no_egress);
+    assert_eq!(again, rules);
+    let narrowed = desired_policy_rules(&policy, &tags(&["a"]), &tags(&["m:u1::e1"]), &no_egress);
+    assert_ne!(narrowed[0].rule_tag, rules[0].rule_tag);
+
+    // No block rules or no inbounds in scope means nothing to apply, exemptions included.
+    assert!(
+        desired_policy_rules(
+            &XrayRoutingPolicy {
+                sniffing: true,
+                ..Default::default()
+            },
+            &inbounds,
+            &tags(&["m:u1::e1"]),
+            &no_egress
+        )
+        .is_empty()
+    );
+    assert!(
+        desired_policy_rules(&policy, &BTreeSet::new(), &BTreeSet::new(), &no_egress).is_empty()
+    );
+}
+
+#[test]
+fn egress_rules_follow_blocks_and_transit_peers_leave_directly() {
+    let policy = XrayRoutingPolicy {
+        block_private: true,
+        ..Default::default()
+    };
+    let egress = EgressRouting {
+        outbound_by_inbound: BTreeMap::from([
+            ("a".to_string(), "xp-egress-node-n2-abc".to_string()),
+            ("c".to_string(), "warp".to_string()),
+        ]),
+        transit_inbound_tags: tags(&["vless"]),
+        transit_user_emails: tags(&["egress:n3"]),
+    };
+    let rules = desired_policy_rules(&policy, &tags(&["a", "b"]), &tags(&["m:u1::a"]), &egress);
+    let summary = rules
+        .iter()
+        .map(|rule| (rule.outbound_tag.as_str(), rule.inbound_tags.join(",")))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            ("direct", "vless".to_string()),
+            // Exempt users keep their inbound's egress.
+            ("direct", "b".to_string()),
+            ("xp-egress-node-n2-abc", "a".to_string()),
+            ("block", "a,b".to_string()),
+            ("warp", "c".to_string()),
+            ("xp-egress-node-n2-abc", "a".to_string()),
+        ]
+    );
+    assert_eq!(rules[0].user_emails, vec!["egress:n3".to_string()]);
+    assert!(rules[5].user_emails.is_empty());
+
+    // Egress alone still produces rules, without a policy.
+    let rules = desired_policy_rules(
+        &XrayRoutingPolicy::default(),
+        &tags(&["a", "b"]),
+        &BTreeSet::new(),
+        &EgressRouting {
+            transit_inbound_tags: BTreeSet::new(),
+            transit_user_emails: BTreeSet::new(),
+            ..egress
+        },
+    );
+    assert_eq!(rules.len(), 2);
+    assert!(rules.iter().all(|rule| rule.outbound_tag != "block"));
+}
@@ -7,22 +7,35 @@ use std::{
 use serde::{Deserialize, Serialize, Serializer, ser::SerializeStruct};
 
 use crate::{
+    admin_principal::AdminPrincipal,
+    audit::AuditContext,
+    credential_rotation::{CredentialRotation, CredentialRotationNodeStage},
+    cycle::CycleSchedule,
+    dns_failover::DnsFailoverGroup,
     domain::{
         DomainError, Endpoint, EndpointKind, Node, NodeQuotaReset, QuotaResetSource, RealityDomain,
-        User, UserNodeQuota, UserPriorityTier, UserQuotaReset, validate_cycle_day_of_month,
-        validate_port, validate_tz_offset_minutes,
+        SubscriptionToken, TrafficTopUp, User, UserNodeQuota, UserPriorityTier, UserQuotaReset,
+        normalize_user_expires_at, validate_cycle_day_of_month, validate_cycle_length_days,
+        validate_max_concurrent_ips, validate_pinned_credentials, validate_port,
+        validate_tz_offset_minutes,
     },
+    egress::EndpointEgress,
+    endpoint_probe::{ProbeTarget, default_probe_targets, validate_probe_targets},
     id::new_ulid_string,
     inbound_ip_usage::{
         GeoLookup, InboundIpMinuteSample, PersistedInboundIpGeo, PersistedInboundIpUsage,
     },
+    ip_limit::UserIpLimitState,
     join_session::JoinSession,
+    notify::NotificationWebhook,
     protocol::{
-        RealityServerNamesSource, RotateShortIdResult, VlessRealityVisionTcpEndpointMeta,
+        Hysteria2EndpointMeta, RealityServerNamesSource, RotateShortIdResult,
+        TrojanRealityTcpEndpointMeta, TrojanTlsTcpEndpointMeta, VlessRealityVisionTcpEndpointMeta,
         normalize_accepted_authorities, rotate_short_ids_in_place, validate_canary_upstream,
-        validate_reality_dest, validate_reality_server_name,
+        validate_reality_dest, validate_reality_server_name, validate_server_name,
     },
     reverse_mesh::ReverseMeshAssignment,
+    routing_policy::XrayRoutingPolicy,
     state::history_repository::{
         HistoryStorage, INBOUND_IP_USAGE_KEY, STATE_KEY, TCP_CONNECTION_USAGE_KEY, USAGE_KEY,
         control::{
@@ -36,10 +49,17 @@ use crate::{
     },
 };
 
+mod audit;
+pub use audit::AuditBaseline;
+mod ca_rotation;
+mod endpoint_egress;
 mod endpoint_meta;
 use endpoint_meta::build_endpoint_meta;
 mod command_compat;
 mod membership_operation;
+mod subscription_access;
+mod subscription_tokens;
+mod traffic_topups;
 pub use membership_operation::{
     MembershipOperation, MembershipOperationKind, MembershipOperationPhase,
 };
@@ -194,9 +214,11 @@ pub enum StoreError {
     SerdeJson(serde_json::Error),
     Domain(DomainError),
     Migration { message: String },
+    HistoryStorage { message: String },
     SchemaVersionMismatch { expected: u32, got: u32 },
     InvalidJoinSession { message: &'static str },
     InvalidMembershipOperation { message: &'static str },
+    Cert(crate::cluster_identity::CertError),
 }
 impl std::fmt::Display for StoreError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -205,6 +227,7 @@ impl std::fmt::Display for StoreError {
             Self::SerdeJson(e) => write!(f, "json error: {e}"),
             Self::Domain(e) => write!(f, "{e}"),
             Self::Migration { message } => write!(f, "migration error: {message}"),
+            Self::HistoryStorage { message } => write!(f, "history storage error: {message}"),
             Self::SchemaVersionMismatch { expected, got } => {
                 write!(f, "schema_version mismatch: expected {expected}, got {got}")
             }
@@ -212,6 +235,7 @@ impl std::fmt::Display for StoreError {
             Self::InvalidMembershipOperation { message } => {
                 write!(f, "invalid membership operation: {message}")
             }
+            Self::Cert(e) => write!(f, "{e}"),
         }
     }
 }
@@ -223,9 +247,11 @@ impl std::error::Error for StoreError {
             Self::SerdeJson(e) => Some(e),
             Self::Domain(e) => Some(e),
             Self::Migration { .. } => None,
+            Self::HistoryStorage { .. } => None,
             Self::SchemaVersionMismatch { .. } => None,
             Self::InvalidJoinSession { .. } => None,
             Self::InvalidMembershipOperation { .. } => None,
+            Self::Cert(e) => Some(e),
         }
     }
 }
@@ -242,6 +268,12 @@ impl From<serde_json::Error> for StoreError {
     }
 }
 
+impl From<crate::cluster_identity::CertError> for StoreError {
+    fn from(value: crate::cluster_identity::CertError) -> Self {
+        Self::Cert(value)
+    }
+}
+
 impl From<DomainError> for StoreError {
     fn from(value: DomainError) -> Self {
         Self::Domain(value)
@@ -356,6 +388,32 @@ pub struct PersistedState {
     pub user_mihomo_profiles: BTreeMap<String, UserMihomoProfile>,
     #[serde(default)]
     pub mihomo_delivery_mode: MihomoDeliveryMode,
+    #[serde(default)]
+    pub notification_webhooks: Vec<NotificationWebhook>,
+    /// Named admin principals by `principal_id`; revoked ones are kept for the audit trail.
+    #[serde(default)]
+    pub admin_principals: BTreeMap<String, AdminPrincipal>,
+    /// Purchased traffic top-ups by `topup_id`. Consumption is tracked locally per node.
+    #[serde(default)]
+    pub traffic_topups: BTreeMap<String, TrafficTopUp>,
+    /// Secondary subscription tokens by `token_id`; revoking one removes it.
+    #[serde(default)]
+    pub subscription_tokens: BTreeMap<String, SubscriptionToken>,
+    /// Routing policy every node applies to its business inbounds.
+    #[serde(default)]
+    pub xray_routing_policy: XrayRoutingPolicy,
+    /// Non-direct egress by `endpoint_id`; endpoints without an entry exit directly.
+    #[serde(default)]
+    pub endpoint_egress: BTreeMap<String, EndpointEgress>,
+    /// URLs every endpoint probe fetches; part of the probe config hash.
+    #[serde(default = "default_probe_targets")]
+    pub endpoint_probe_targets: Vec<ProbeTarget>,
+    /// Leader-managed failover records by `group_id`.
+    #[serde(default)]
+    pub dns_failover_groups: BTreeMap<String, DnsFailoverGroup>,
+    /// In-progress cluster CA rotation; cleared once the old root is retired.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_rotation: Option<CredentialRotation>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub repository_membership: Option<RepositoryMembership>,
     /// Raft-authoritative reverse relay epoch. Runtime link state is intentionally not persisted.
@@ -395,6 +453,15 @@ impl PersistedState {
             user_auto_assign_endpoint_kinds: BTreeMap::new(),
             user_mihomo_profiles: BTreeMap::new(),
             mihomo_delivery_mode: MihomoDeliveryMode::Legacy,
+            notification_webhooks: Vec::new(),
+            admin_principals: BTreeMap::new(),
+            traffic_topups: BTreeMap::new(),
+            subscription_tokens: BTreeMap::new(),
+            xray_routing_policy: XrayRoutingPolicy::default(),
+            endpoint_egress: BTreeMap::new(),
+            endpoint_probe_targets: default_probe_targets(),
+            dns_failover_groups: BTreeMap::new(),
+            credential_rotation: None,
             repository_membership: None,
             reverse_mesh_epoch: 0,
             reverse_mesh_assignments: BTreeMap::new(),
@@ -492,10 +559,23 @@ pub struct EndpointProbeNodeSample {
     pub target_url: Option<String>,
     #[serde(default)]
     pub error: Option<String>,
+    /// Per-target outcomes; empty when the probe failed before any target was fetched.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<EndpointProbeTargetSample>,
     /// Hash of the probe configuration to ensure cluster-wide consistency.
     pub config_hash: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EndpointProbeTargetSample {
+    pub target_id: String,
+    pub ok: bool,
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct EndpointProbeAppendSample {
     pub endpoint_id: String,
@@ -512,6 +592,8 @@ pub struct EndpointProbeAppendSample {
     pub target_url: Option<String>,
     #[serde(default)]
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<EndpointProbeTargetSample>,
     pub config_hash: String,
 }
 
@@ -705,6 +787,16 @@ pub fn membership_xray_email(user_id: &str, endpoint_id: &str) -> String {
     format!("m:{}", membership_key(user_id, endpoint_id))
 }
 
+/// Xray client email for credentials derived from the other root during a credential rotation.
+pub fn membership_xray_alternate_email(user_id: &str, endpoint_id: &str) -> String {
+    xray_alternate_email(&membership_xray_email(user_id, endpoint_id))
+}
+
+/// Alternate-root client email next to the primary client `email` (memberships and egress users).
+pub fn xray_alternate_email(email: &str) -> String {
+    format!("{email}#alt")
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 enum CyclePolicyDefaultV2 {
@@ -793,6 +885,10 @@ fn migrate_v2_like_to_v3(
                     day_of_month: user.cycle_day_of_month_default,
                     tz_offset_minutes: 480,
                 },
+                expires_at: None,
+                disabled: false,
+                max_concurrent_ips: None,
+                pinned_credentials: Default::default(),
             },
         );
     }
@@ -1390,6 +1486,9 @@ fn migrate_usage_v1_to_v2(
         node_pacing: input.node_pacing,
         user_credential_epochs_applied: BTreeMap::new(),
         endpoint_users_applied: BTreeMap::new(),
+        topup_consumed_bytes: BTreeMap::new(),
+        ip_limits: BTreeMap::new(),
+        quota_warnings: BTreeMap::new(),
     };
 
     for (membership_key, entries) in grouped {
@@ -1482,413 +1581,9 @@ fn normalize_node_weight_policies(
     }
     out
 }
-#[cfg(test)]
-mod migrate_tests {
-    use super::*;
-    use std::collections::{BTreeMap, BTreeSet};
-
-    fn migrate_test_user(user_id: &str) -> User {
-        User {
-            user_id: user_id.to_string(),
-            display_name: user_id.to_string(),
-            subscription_token: xp_test_fixtures::label_sub_user1().to_owned(),
-            credential_epoch: 0,
-            priority_tier: UserPriorityTier::P2,
-            quota_reset: UserQuotaReset::default(),
-        }
-    }
-
-    fn migrate_test_node(_node_id: &str) -> Node {
-        Node {
-            node_id: xp_test_fixtures::label_node1().to_owned(),
-            node_name: xp_test_fixtures::label_node1().to_owned(),
-            access_host: xp_test_fixtures::address_loopback().to_owned(),
-            api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-            quota_limit_bytes: 0,
-            quota_reset: NodeQuotaReset::default(),
-        }
-    }
-
-    fn migrate_test_ss_endpoint_one() -> Endpoint {
-        Endpoint {
-            endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
-            node_id: xp_test_fixtures::label_node1().to_owned(),
-            tag: xp_test_fixtures::label_ss1().to_owned(),
-            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-            port: 10_000,
-            meta: serde_json::json!({}),
-        }
-    }
-
-    fn migrate_test_ss_endpoint_two() -> Endpoint {
-        Endpoint {
-            endpoint_id: xp_test_fixtures::label_ss2().to_owned(),
-            node_id: xp_test_fixtures::label_node1().to_owned(),
-            tag: xp_test_fixtures::label_ss2().to_owned(),
-            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-            port: 10_000,
-            meta: serde_json::json!({}),
-        }
-    }
-
-    #[test]
-    fn migrate_v2_like_to_v3_overrides_seeded_quota_reset_source_from_grants() {
-        let node_id = "node_1".to_string();
-        let endpoint_id = "endpoint_1".to_string();
-        let user_id = "user_1".to_string();
-
-        let mut nodes = BTreeMap::new();
-        nodes.insert(
-            node_id.clone(),
-            Node {
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::subscription_host_empty().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 0,
-                quota_reset: NodeQuotaReset::default(),
-            },
-        );
-
-        let mut endpoints = BTreeMap::new();
-        endpoints.insert(
-            endpoint_id.clone(),
-            Endpoint {
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                tag: xp_test_fixtures::endpoint_tag_fixturetest().to_owned(),
-                kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                port: 12345,
-                meta: serde_json::json!({}),
-            },
-        );
-
-        let mut users = BTreeMap::new();
-        users.insert(
-            user_id.clone(),
-            UserV2 {
-                user_id: user_id.clone(),
-                display_name: "alice".to_string(),
-                subscription_token: xp_test_fixtures::primary_token().to_owned(),
-                cycle_policy_default: CyclePolicyDefaultV2::ByUser,
-                cycle_day_of_month_default: 1,
-            },
-        );
-
-        let mut grants = BTreeMap::new();
-        grants.insert(
-            "grant_1".to_string(),
-            GrantV2 {
-                grant_id: "grant_1".to_string(),
-                user_id: user_id.clone(),
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                group_name: None,
-                enabled: true,
-                quota_limit_bytes: 123,
-                cycle_policy: CyclePolicyV2::ByNode,
-                cycle_day_of_month: Some(1),
-                note: None,
-                credentials: serde_json::json!({}),
-            },
-        );
-
-        let mut user_node_quotas = BTreeMap::new();
-        user_node_quotas
-            .entry(user_id.clone())
-            .or_insert_with(BTreeMap::new)
-            .insert(node_id.clone(), 456);
-
-        let v2 = PersistedStateV2Like {
-            schema_version: 2,
-            nodes,
-            endpoints,
-            users,
-            grants,
-            user_node_quotas,
-        };
-
-        let v3 = migrate_v2_like_to_v3(v2).expect("migration should succeed");
-        let cfg = v3
-            .user_node_quotas
-            .get(&user_id)
-            .and_then(|m| m.get(&node_id))
-            .expect("user node quota cfg should exist");
-
-        assert_eq!(cfg.quota_limit_bytes, Some(456));
-        assert_eq!(cfg.quota_reset_source, QuotaResetSource::Node);
-    }
-
-    #[test]
-    fn migrate_v4_to_v5_seeds_reality_domains_when_empty() {
-        let mut v4 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V4);
-        v4.reality_domains = Vec::new();
-
-        let v5 = migrate_v4_to_v5(v4).expect("migration should succeed");
-        assert_eq!(v5.schema_version, SCHEMA_VERSION_V5);
-        assert_eq!(v5.reality_domains, default_seed_reality_domains());
-    }
-
-    #[test]
-    fn migrate_v4_to_v5_does_not_override_existing_reality_domains() {
-        let mut v4 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V4);
-        v4.reality_domains = vec![RealityDomain {
-            domain_id: "custom_1".to_string(),
-            server_name: "example.com".to_string(),
-            disabled_node_ids: BTreeSet::new(),
-        }];
-
-        let v5 = migrate_v4_to_v5(v4).expect("migration should succeed");
-        assert_eq!(v5.schema_version, SCHEMA_VERSION_V5);
-        assert_eq!(v5.reality_domains.len(), 1);
-        assert_eq!(v5.reality_domains[0].domain_id, "custom_1");
-        assert_eq!(v5.reality_domains[0].server_name, "example.com");
-    }
-
-    #[test]
-    fn migrate_v6_to_v7_seeds_node_user_endpoint_memberships_from_grants() {
-        let mut v6 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V6);
-        v6.users.insert(
-            "user_1".to_string(),
-            User {
-                user_id: "user_1".to_string(),
-                display_name: "alice".to_string(),
-                subscription_token: xp_test_fixtures::label_sub1().to_owned(),
-                credential_epoch: 0,
-                priority_tier: UserPriorityTier::P2,
-                quota_reset: UserQuotaReset::default(),
-            },
-        );
-        v6.nodes.insert(
-            "node_1".to_string(),
-            Node {
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::address_loopback().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 0,
-                quota_reset: NodeQuotaReset::default(),
-            },
-        );
-        v6.endpoints.insert(
-            "endpoint_1".to_string(),
-            Endpoint {
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                tag: xp_test_fixtures::primary_endpoint_tag().to_owned(),
-                kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                port: 12345,
-                meta: serde_json::json!({}),
-            },
-        );
-        v6.grants.insert(
-            "grant_1".to_string(),
-            LegacyGrantCompat {
-                grant_id: "grant_1".to_string(),
-                user_id: "user_1".to_string(),
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                enabled: true,
-            },
-        );
-
-        let v7 = migrate_v6_to_v7(v6).expect("migration should succeed");
-        assert_eq!(v7.schema_version, SCHEMA_VERSION_V7);
-        assert!(
-            v7.node_user_endpoint_memberships
-                .contains(&NodeUserEndpointMembership {
-                    user_id: "user_1".to_string(),
-                    node_id: "node_1".to_string(),
-                    endpoint_id: "endpoint_1".to_string(),
-                })
-        );
-    }
-
-    #[test]
-    fn migrate_v7_to_v8_keeps_existing_weights_and_sets_latest_schema() {
-        let mut v7 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V7);
-        v7.users.insert(
-            "user_1".to_string(),
-            User {
-                user_id: "user_1".to_string(),
-                display_name: "alice".to_string(),
-                subscription_token: xp_test_fixtures::label_sub1().to_owned(),
-                credential_epoch: 0,
-                priority_tier: UserPriorityTier::P2,
-                quota_reset: UserQuotaReset::default(),
-            },
-        );
-        v7.user_global_weights
-            .insert("user_1".to_string(), UserGlobalWeightConfig { weight: 135 });
-
-        let v8 = migrate_v7_to_v8(v7).expect("migration should succeed");
-        assert_eq!(v8.schema_version, SCHEMA_VERSION_V8);
-        assert_eq!(
-            v8.user_global_weights.get("user_1"),
-            Some(&UserGlobalWeightConfig { weight: 135 })
-        );
-    }
-
-    #[test]
-    fn migrate_v9_compat_to_v10_extracts_memberships_and_clears_user_node_quotas() {
-        let mut v9 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V9);
-
-        v9.users.insert(
-            "user_1".to_string(),
-            User {
-                user_id: "user_1".to_string(),
-                display_name: "alice".to_string(),
-                subscription_token: xp_test_fixtures::label_sub1().to_owned(),
-                credential_epoch: 0,
-                priority_tier: UserPriorityTier::P2,
-                quota_reset: UserQuotaReset::default(),
-            },
-        );
-        v9.nodes.insert(
-            "node_1".to_string(),
-            Node {
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
-                access_host: xp_test_fixtures::address_loopback().to_owned(),
-                api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
-                quota_limit_bytes: 0,
-                quota_reset: NodeQuotaReset::default(),
-            },
-        );
-        v9.endpoints.insert(
-            "endpoint_1".to_string(),
-            Endpoint {
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                tag: xp_test_fixtures::primary_endpoint_tag().to_owned(),
-                kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                port: 12345,
-                meta: serde_json::json!({}),
-            },
-        );
-
-        v9.grants.insert(
-            "grant_0".to_string(),
-            LegacyGrantCompat {
-                grant_id: "grant_0".to_string(),
-                user_id: "user_1".to_string(),
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                enabled: true,
-            },
-        );
-        v9.grants.insert(
-            "grant_1".to_string(),
-            LegacyGrantCompat {
-                grant_id: "grant_1".to_string(),
-                user_id: "user_1".to_string(),
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                enabled: false,
-            },
-        );
-        v9.grants.insert(
-            "grant_orphan_user".to_string(),
-            LegacyGrantCompat {
-                grant_id: "grant_orphan_user".to_string(),
-                user_id: "user_missing".to_string(),
-                endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
-                enabled: true,
-            },
-        );
-        v9.grants.insert(
-            "grant_orphan_endpoint".to_string(),
-            LegacyGrantCompat {
-                grant_id: "grant_orphan_endpoint".to_string(),
-                user_id: "user_1".to_string(),
-                endpoint_id: xp_test_fixtures::label_endpoint_missing().to_owned(),
-                enabled: true,
-            },
-        );
-
-        v9.user_node_quotas.insert(
-            "user_1".to_string(),
-            BTreeMap::from([(
-                "node_missing".to_string(),
-                UserNodeQuotaConfig {
-                    quota_limit_bytes: Some(123),
-                    quota_reset_source: QuotaResetSource::User,
-                },
-            )]),
-        );
-        v9.user_node_quotas.insert(
-            "user_missing".to_string(),
-            BTreeMap::from([(
-                "node_1".to_string(),
-                UserNodeQuotaConfig {
-                    quota_limit_bytes: Some(321),
-                    quota_reset_source: QuotaResetSource::User,
-                },
-            )]),
-        );
-
-        let (v10, mapping, stats) = migrate_v9_compat_to_v10(v9).expect("migration should succeed");
-        assert_eq!(v10.schema_version, SCHEMA_VERSION_V10);
-        assert!(v10.user_node_quotas.is_empty());
-        assert_eq!(v10.node_user_endpoint_memberships.len(), 1);
-        assert_eq!(
-            mapping.get("grant_0"),
-            Some(&"user_1::endpoint_1".to_string())
-        );
-        assert_eq!(
-            mapping.get("grant_1"),
-            Some(&"user_1::endpoint_1".to_string())
-        );
-        assert!(!mapping.contains_key("grant_orphan_user"));
-        assert!(!mapping.contains_key("grant_orphan_endpoint"));
-        assert_eq!(stats.grants_total, 4);
-        assert_eq!(stats.grants_orphan_dropped, 2);
-        assert_eq!(stats.memberships_created, 1);
-        assert_eq!(stats.memberships_deduped, 1);
-        assert_eq!(stats.user_node_quotas_cleared, 2);
-    }
-
-    #[test]
-    fn migrate_v11_to_v12_infers_auto_assign_endpoint_kinds() {
-        let mut v11 = PersistedState::empty();
-        v11.schema_version = SCHEMA_VERSION_V11;
-        v11.users
-            .insert("user_all".to_string(), migrate_test_user("user_all"));
-        v11.users
-            .insert("user_subset".to_string(), migrate_test_user("user_subset"));
-        v11.nodes
-            .insert("node_1".to_string(), migrate_test_node("node_1"));
-        v11.endpoints
-            .insert("ss_1".to_string(), migrate_test_ss_endpoint_one());
-        v11.endpoints
-            .insert("ss_2".to_string(), migrate_test_ss_endpoint_two());
-        v11.node_user_endpoint_memberships = BTreeSet::from([
-            NodeUserEndpointMembership {
-                user_id: "user_all".to_string(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
-            },
-            NodeUserEndpointMembership {
-                user_id: "user_all".to_string(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                endpoint_id: xp_test_fixtures::label_ss2().to_owned(),
-            },
-            NodeUserEndpointMembership {
-                user_id: "user_subset".to_string(),
-                node_id: xp_test_fixtures::label_node1().to_owned(),
-                endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
-            },
-        ]);
 
-        let v12 = migrate_v11_to_v12(v11).unwrap();
-
-        assert_eq!(v12.schema_version, SCHEMA_VERSION_V12);
-        assert_eq!(
-            v12.user_auto_assign_endpoint_kinds.get("user_all"),
-            Some(&BTreeSet::from([EndpointKind::Ss2022_2022Blake3Aes128Gcm]))
-        );
-        assert!(
-            !v12.user_auto_assign_endpoint_kinds
-                .contains_key("user_subset")
-        );
-    }
-}
+#[cfg(test)]
+mod migrate_tests;
 
 fn is_false(value: &bool) -> bool {
     !*value
@@ -2010,170 +1705,105 @@ pub enum DesiredStateCommand {
     BumpUserCredentialEpoch {
         user_id: String,
     },
-    /// Legacy/WAL compatibility no-op.
-    CompatNoop {
-        note: String,
-    },
-    AppendEndpointProbeSamples {
-        hour: String,
-        from_node_id: String,
-        samples: Vec<EndpointProbeAppendSample>,
+    /// Set or clear the user's expiry; `expires_at` is RFC3339.
+    SetUserExpiry {
+        user_id: String,
+        expires_at: Option<String>,
     },
-    ReplaceRepositoryMembership {
-        membership: RepositoryMembership,
+    /// Administratively disable or re-enable a user without touching credentials.
+    SetUserDisabled {
+        user_id: String,
+        disabled: bool,
     },
-    UpdateRepositoryMemberRuntime(RepositoryMemberRuntimePatch),
-    SetReverseMeshEpoch {
-        epoch: u64,
+    /// Replaces the cluster-wide webhook notification targets.
+    SetNotificationWebhooks {
+        webhooks: Vec<NotificationWebhook>,
     },
-    UpsertReverseMeshAssignment {
-        assignment: ReverseMeshAssignment,
-        #[serde(default, skip_serializing_if = "Option::is_none")]
-        expected_generation: Option<u64>,
+    /// Creates a principal or replaces it, e.g. with a rotated token hash.
+    UpsertAdminPrincipal {
+        principal: AdminPrincipal,
     },
-    DeleteReverseMeshAssignment {
-        target_node_id: String,
-        #[serde(default, skip_serializing_if = "Option::is_none")]
-        expected_generation: Option<u64>,
-    },
-}
-
-#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
-enum GrantEnabledSourceCompat {
-    #[default]
-    Manual,
-    Quota,
-}
-
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
-struct LegacyGrantCompat {
-    #[serde(default)]
-    grant_id: String,
-    user_id: String,
-    endpoint_id: String,
-    #[serde(default)]
-    enabled: bool,
-}
-
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
-struct UserAccessItemCompat {
-    endpoint_id: String,
-    #[serde(default)]
-    note: Option<String>,
-}
-
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
-#[serde(tag = "type", rename_all = "snake_case")]
-enum DesiredStateCommandCompat {
-    UpsertNode {
-        node: Node,
-        #[serde(default)]
-        join_session: Option<JoinSession>,
+    /// Revokes a principal's token; the first `revoked_at` wins.
+    RevokeAdminPrincipal {
+        principal_id: String,
+        revoked_at: String,
     },
-    DeleteNode {
-        node_id: String,
-        #[serde(default)]
-        delete_endpoints: bool,
-        #[serde(default)]
-        expected_endpoint_ids: Vec<String>,
-        #[serde(default)]
-        join_session: Option<JoinSession>,
+    /// Records a purchased top-up; nodes lift the user's quota bans when they apply it.
+    CreateTrafficTopUp {
+        topup: TrafficTopUp,
     },
-    BeginMembershipOperation {
-        operation: Box<MembershipOperation>,
-        #[serde(default)]
-        node: Option<Node>,
-        #[serde(default)]
-        join_session: Option<JoinSession>,
+    DeleteTrafficTopUp {
+        topup_id: String,
     },
-    TransitionMembershipOperation {
-        operation: MembershipOperation,
+    /// Adds a secondary subscription token; its `token` must not resolve to anyone yet.
+    CreateSubscriptionToken {
+        token: SubscriptionToken,
     },
-    PruneMembershipOperations {
-        terminal_before: String,
+    RevokeSubscriptionToken {
+        token_id: String,
     },
-    UpsertEndpoint {
-        endpoint: Endpoint,
-        #[serde(default)]
-        expected: Option<Endpoint>,
+    /// Replaces the cluster routing policy; deleting an endpoint drops it from the exemptions.
+    SetXrayRoutingPolicy {
+        policy: XrayRoutingPolicy,
     },
-    DeleteEndpoint {
+    /// Sets where an endpoint's traffic leaves; `Direct` clears the entry.
+    SetEndpointEgress {
         endpoint_id: String,
+        egress: EndpointEgress,
     },
-    CreateRealityDomain {
-        domain: RealityDomain,
-    },
-    PatchRealityDomain {
-        domain_id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        server_name: Option<String>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        disabled_node_ids: Option<BTreeSet<String>>,
-    },
-    DeleteRealityDomain {
-        domain_id: String,
-    },
-    ReorderRealityDomains {
-        domain_ids: Vec<String>,
-    },
-    UpsertUser {
-        user: User,
+    /// Replaces the probe target list. Runs started under the old list are rejected by the
+    /// config hash check, so a change takes effect from the next hourly run.
+    SetEndpointProbeTargets {
+        targets: Vec<ProbeTarget>,
     },
-    DeleteUser {
-        user_id: String,
-    },
-    ResetUserSubscriptionToken {
-        user_id: String,
-        subscription_token: String,
+    /// Creates or replaces a failover group; every member node must exist and hostnames are
+    /// unique across groups.
+    UpsertDnsFailoverGroup {
+        group: DnsFailoverGroup,
     },
-    SetUserNodeQuota {
-        user_id: String,
-        node_id: String,
-        quota_limit_bytes: u64,
-        #[serde(default)]
-        quota_reset_source: QuotaResetSource,
+    DeleteDnsFailoverGroup {
+        group_id: String,
     },
-    SetUserNodeWeight {
-        user_id: String,
-        node_id: String,
-        weight: u16,
+    /// Creates or updates a batch of users from a validated bulk import (see
+    /// `crate::user_bulk::plan_user_import`); the batch applies atomically.
+    ImportUsers {
+        entries: Vec<crate::user_bulk::UserImportEntry>,
     },
-    SetUserGlobalWeight {
-        user_id: String,
-        weight: u16,
+    /// Stages a new cluster CA (see `crate::credential_rotation`); only one rotation may exist.
+    StartCredentialRotation {
+        rotation: Box<CredentialRotation>,
     },
-    SetNodeWeightPolicy {
+    /// A node reports how far it got; earlier timestamps are kept on repeats.
+    RecordCredentialRotationProgress {
+        rotation_id: String,
         node_id: String,
-        inherit_global: bool,
+        stage: CredentialRotationNodeStage,
+        at: String,
     },
-    SetUserMihomoProfile {
-        user_id: String,
-        profile: UserMihomoProfile,
+    /// Tells nodes to install the new CA; every node must hold a certificate under it.
+    PromoteCredentialRotation {
+        rotation_id: String,
+        promoted_at: String,
     },
-    SetMihomoDeliveryMode {
-        mode: MihomoDeliveryMode,
+    /// Drops the old root once every node switched and the grace period is over.
+    RetireCredentialRotation {
+        rotation_id: String,
+        retired_at: String,
     },
-    SetMihomoResourceAllowPrivateTargets {
-        allow: bool,
+    /// Cancels a rotation that has not been promoted yet.
+    AbortCredentialRotation {
+        rotation_id: String,
     },
-    SetGeoDbUpdateSettings {
-        settings: GeoDbUpdateSettingsCompat,
-    },
-    ReplaceUserAccess {
-        user_id: String,
-        #[serde(default)]
-        endpoint_ids: Vec<String>,
-        #[serde(default)]
-        items: Vec<UserAccessItemCompat>,
+    /// Merges a remapped backup (see `crate::backup::plan_restore`) into the current state.
+    RestoreBackup {
+        restored: Box<PersistedState>,
     },
-    EnsureMembership {
-        user_id: String,
-        endpoint_id: String,
-    },
-    BumpUserCredentialEpoch {
-        user_id: String,
+    /// An admin write wrapped with who issued it; applies exactly like `command`.
+    Audited {
+        audit: AuditContext,
+        command: Box<DesiredStateCommand>,
     },
+    /// Legacy/WAL compatibility no-op.
     CompatNoop {
         note: String,
     },
@@ -2191,40 +1821,24 @@ enum DesiredStateCommandCompat {
     },
     UpsertReverseMeshAssignment {
         assignment: ReverseMeshAssignment,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         expected_generation: Option<u64>,
     },
     DeleteReverseMeshAssignment {
         target_node_id: String,
-        #[serde(default)]
+        #[serde(default, skip_serializing_if = "Option::is_none")]
         expected_generation: Option<u64>,
     },
-    ReplaceUserGrants {
-        user_id: String,
-        grants: Vec<LegacyGrantCompat>,
-    },
-    UpsertGrant {
-        grant: LegacyGrantCompat,
-    },
-    DeleteGrant {
-        grant_id: String,
-    },
-    SetGrantEnabled {
-        grant_id: String,
-        enabled: bool,
-        #[serde(default)]
-        source: GrantEnabledSourceCompat,
-    },
 }
 
-impl<'de> Deserialize<'de> for DesiredStateCommand {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        let compat = DesiredStateCommandCompat::deserialize(deserializer)?;
-        Ok(compat.into())
-    }
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+struct LegacyGrantCompat {
+    #[serde(default)]
+    grant_id: String,
+    user_id: String,
+    endpoint_id: String,
+    #[serde(default)]
+    enabled: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -2261,46 +1875,34 @@ pub enum DesiredStateApplyResult {
 }
 
 fn validate_user_quota_reset(reset: &UserQuotaReset) -> Result<(), DomainError> {
-    match reset {
-        UserQuotaReset::Unlimited { tz_offset_minutes } => {
-            validate_tz_offset_minutes(*tz_offset_minutes)?;
-        }
-        UserQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => {
-            validate_cycle_day_of_month(*day_of_month)?;
-            validate_tz_offset_minutes(*tz_offset_minutes)?;
-        }
+    validate_cycle_schedule(reset.cycle_schedule())?;
+    validate_tz_offset_minutes(reset.tz_offset_minutes())
+}
+
+pub(super) fn validate_node_quota_reset(reset: &NodeQuotaReset) -> Result<(), DomainError> {
+    validate_cycle_schedule(reset.cycle_schedule())?;
+    if let Some(tz_offset_minutes) = reset.tz_offset_minutes() {
+        validate_tz_offset_minutes(tz_offset_minutes)?;
     }
     Ok(())
 }
 
-pub(super) fn validate_node_quota_reset(reset: &NodeQuotaReset) -> Result<(), DomainError> {
-    match reset {
-        NodeQuotaReset::Unlimited { tz_offset_minutes } => {
-            if let Some(tz_offset_minutes) = tz_offset_minutes {
-                validate_tz_offset_minutes(*tz_offset_minutes)?;
-            }
-        }
-        NodeQuotaReset::Monthly {
-            day_of_month,
-            tz_offset_minutes,
-        } => {
-            validate_cycle_day_of_month(*day_of_month)?;
-            if let Some(tz_offset_minutes) = tz_offset_minutes {
-                validate_tz_offset_minutes(*tz_offset_minutes)?;
-            }
+fn validate_cycle_schedule(schedule: Option<CycleSchedule>) -> Result<(), DomainError> {
+    match schedule {
+        Some(CycleSchedule::Monthly { day_of_month }) => validate_cycle_day_of_month(day_of_month),
+        Some(CycleSchedule::FixedDays { length_days, .. }) => {
+            validate_cycle_length_days(length_days)
         }
+        Some(CycleSchedule::Weekly { .. } | CycleSchedule::Daily) | None => Ok(()),
     }
-    Ok(())
 }
 
 pub(super) fn validate_node_quota_config(node: &Node) -> Result<(), DomainError> {
     // Shared node quota enforcement requires a finite cycle window.
     if node.quota_limit_bytes > 0 && matches!(node.quota_reset, NodeQuotaReset::Unlimited { .. }) {
         return Err(DomainError::InvalidNodeQuotaConfig {
-            reason: "quota_limit_bytes > 0 requires quota_reset policy monthly".to_string(),
+            reason: "quota_limit_bytes > 0 requires a finite quota_reset cycle (not unlimited)"
+                .to_string(),
         });
     }
     Ok(())
@@ -2409,15 +2011,41 @@ fn apply_vless_meta_updates(
 
 impl DesiredStateCommand {
     pub fn apply(&self, state: &mut PersistedState) -> Result<DesiredStateApplyResult, StoreError> {
+        if let Self::Audited { command, .. } = self {
+            return command.apply(state);
+        }
         if let Some(result) = membership_operation::apply_command(state, self) {
             return result;
         }
+        if let Some(result) = ca_rotation::apply_command(state, self) {
+            return result;
+        }
+        if let Some(result) = traffic_topups::apply_command(state, self) {
+            return result;
+        }
+        if let Some(result) = subscription_tokens::apply_command(state, self) {
+            return result;
+        }
         match self {
             Self::BeginMembershipOperation { .. }
             | Self::TransitionMembershipOperation { .. }
             | Self::PruneMembershipOperations { .. } => {
                 unreachable!("membership operation command was not handled")
             }
+            Self::StartCredentialRotation { .. }
+            | Self::RecordCredentialRotationProgress { .. }
+            | Self::PromoteCredentialRotation { .. }
+            | Self::RetireCredentialRotation { .. }
+            | Self::AbortCredentialRotation { .. } => {
+                unreachable!("credential rotation command was not handled")
+            }
+            Self::CreateTrafficTopUp { .. } | Self::DeleteTrafficTopUp { .. } => {
+                unreachable!("traffic top-up command was not handled")
+            }
+            Self::CreateSubscriptionToken { .. } | Self::RevokeSubscriptionToken { .. } => {
+                unreachable!("subscription token command was not handled")
+            }
+            Self::Audited { .. } => unreachable!("audited command was not unwrapped"),
             Self::UpsertNode { node, join_session } => {
                 crate::state_join_command::apply_upsert_node(state, node, join_session.as_ref())
             }
@@ -2471,7 +2099,17 @@ impl DesiredStateCommand {
                 for (endpoint_id, _tag) in &endpoint_refs {
                     state.endpoints.remove(endpoint_id);
                     state.endpoint_probe_history.remove(endpoint_id);
+                    state.endpoint_egress.remove(endpoint_id);
                 }
+                // Endpoints chained through the removed node fall back to direct.
+                state.endpoint_egress.retain(|_endpoint_id, egress| {
+                    !matches!(egress, EndpointEgress::Node { node_id: target } if target == node_id)
+                });
+                // A group loses a removed standby, and goes away with its primary or last standby.
+                state.dns_failover_groups.retain(|_group_id, group| {
+                    group.standby_node_ids.retain(|standby| standby != node_id);
+                    group.primary_node_id != *node_id && !group.standby_node_ids.is_empty()
+                });
 
                 state.nodes.remove(node_id);
                 crate::state_join_command::commit_session(state, join_session.as_ref());
@@ -2495,6 +2133,9 @@ impl DesiredStateCommand {
                     .user_node_weights
                     .retain(|_user_id, nodes| !nodes.is_empty());
                 state.node_weight_policies.remove(node_id);
+                state
+                    .traffic_topups
+                    .retain(|_topup_id, topup| topup.node_id != *node_id);
 
                 // Cleanup endpoint probe samples and participation for the removed node.
                 for (_endpoint_id, history) in state.endpoint_probe_history.iter_mut() {
@@ -2638,6 +2279,57 @@ impl DesiredStateCommand {
                         .into());
                     }
                     endpoint.meta = serialize_vless_meta_preserving_smux(meta, had_mihomo_smux)?;
+                } else if endpoint.kind == EndpointKind::TrojanRealityTcp {
+                    let meta: TrojanRealityTcpEndpointMeta =
+                        serde_json::from_value(endpoint.meta.clone())?;
+                    if meta.reality.server_names_source != RealityServerNamesSource::Manual {
+                        return Err(DomainError::InvalidRealityServerName {
+                            server_name: meta.reality.server_names.join(","),
+                            reason: "trojan endpoints only support manual server_names".to_string(),
+                        }
+                        .into());
+                    }
+                    let normalized = normalize_reality_server_names(&meta.reality.server_names);
+                    if normalized.is_empty() {
+                        return Err(DomainError::InvalidRealityServerName {
+                            server_name: String::new(),
+                            reason: "server_names is empty".to_string(),
+                        }
+                        .into());
+                    }
+                    for name in normalized.iter() {
+                        validate_reality_server_name(name).map_err(|reason| {
+                            DomainError::InvalidRealityServerName {
+                                server_name: name.clone(),
+                                reason: reason.to_string(),
+                            }
+                        })?;
+                    }
+                    validate_reality_dest(&meta.reality.dest).map_err(|reason| {
+                        DomainError::InvalidRealityServerName {
+                            server_name: meta.reality.dest.clone(),
+                            reason: reason.to_string(),
+                        }
+                    })?;
+                    if let Some(reality) = endpoint.meta.get_mut("reality") {
+                        reality["server_names"] = serde_json::json!(normalized);
+                    }
+                } else if endpoint.kind == EndpointKind::TrojanTlsTcp
+                    || endpoint.kind == EndpointKind::Hysteria2
+                {
+                    let server_name = if endpoint.kind == EndpointKind::TrojanTlsTcp {
+                        serde_json::from_value::<TrojanTlsTcpEndpointMeta>(endpoint.meta.clone())?
+                            .server_name
+                    } else {
+                        serde_json::from_value::<Hysteria2EndpointMeta>(endpoint.meta.clone())?
+                            .server_name
+                    };
+                    validate_server_name(&server_name).map_err(|reason| {
+                        DomainError::InvalidServerName {
+                            server_name: server_name.clone(),
+                            reason: reason.to_string(),
+                        }
+                    })?;
                 }
 
                 state
@@ -2753,6 +2445,11 @@ impl DesiredStateCommand {
                 )?;
                 let deleted = state.endpoints.remove(endpoint_id).is_some();
                 state.endpoint_probe_history.remove(endpoint_id);
+                state
+                    .xray_routing_policy
+                    .exempt_endpoint_ids
+                    .remove(endpoint_id);
+                state.endpoint_egress.remove(endpoint_id);
                 sync_node_user_endpoint_memberships(state);
                 Ok(DesiredStateApplyResult::EndpointDeleted { deleted })
             }
@@ -2952,6 +2649,22 @@ impl DesiredStateCommand {
             }
             Self::UpsertUser { user } => {
                 validate_user_quota_reset(&user.quota_reset)?;
+                if let Some(expires_at) = user.expires_at.as_deref() {
+                    normalize_user_expires_at(expires_at)?;
+                }
+                validate_max_concurrent_ips(user.max_concurrent_ips)?;
+                validate_pinned_credentials(&user.pinned_credentials)?;
+                if let Some(uuid) = user.pinned_credentials.vless_uuid.as_deref()
+                    && let Some(other) = state.users.values().find(|other| {
+                        other.user_id != user.user_id
+                            && other.pinned_credentials.vless_uuid.as_deref() == Some(uuid)
+                    })
+                {
+                    return Err(DomainError::InvalidPinnedCredentials {
+                        reason: format!("vless_uuid is already pinned by user {}", other.user_id),
+                    }
+                    .into());
+                }
                 state.users.insert(user.user_id.clone(), user.clone());
                 Ok(DesiredStateApplyResult::Applied)
             }
@@ -2962,6 +2675,12 @@ impl DesiredStateCommand {
                 state.user_node_weights.remove(user_id);
                 state.user_global_weights.remove(user_id);
                 state.user_mihomo_profiles.remove(user_id);
+                state
+                    .traffic_topups
+                    .retain(|_topup_id, topup| topup.user_id != *user_id);
+                state
+                    .subscription_tokens
+                    .retain(|_token_id, token| token.user_id != *user_id);
                 sync_node_user_endpoint_memberships(state);
                 Ok(DesiredStateApplyResult::UserDeleted { deleted })
             }
@@ -3209,11 +2928,145 @@ impl DesiredStateCommand {
                     })
                 })?;
                 user.credential_epoch = user.credential_epoch.saturating_add(1);
+                user.pinned_credentials = Default::default();
                 Ok(DesiredStateApplyResult::UserCredentialEpochBumped {
                     user_id: user_id.clone(),
                     credential_epoch: user.credential_epoch,
                 })
             }
+            Self::SetUserExpiry {
+                user_id,
+                expires_at,
+            } => {
+                let expires_at = expires_at
+                    .as_deref()
+                    .map(normalize_user_expires_at)
+                    .transpose()?;
+                let user = state.users.get_mut(user_id).ok_or_else(|| {
+                    StoreError::Domain(DomainError::MissingUser {
+                        user_id: user_id.clone(),
+                    })
+                })?;
+                user.expires_at = expires_at;
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::SetUserDisabled { user_id, disabled } => {
+                let user = state.users.get_mut(user_id).ok_or_else(|| {
+                    StoreError::Domain(DomainError::MissingUser {
+                        user_id: user_id.clone(),
+                    })
+                })?;
+                user.disabled = *disabled;
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::SetNotificationWebhooks { webhooks } => {
+                state.notification_webhooks = webhooks.clone();
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::UpsertAdminPrincipal { principal } => {
+                state
+                    .admin_principals
+                    .insert(principal.principal_id.clone(), principal.clone());
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::RevokeAdminPrincipal {
+                principal_id,
+                revoked_at,
+            } => {
+                let principal = state
+                    .admin_principals
+                    .get_mut(principal_id)
+                    .ok_or_else(|| {
+                        StoreError::Domain(DomainError::AdminPrincipalNotFound {
+                            principal_id: principal_id.clone(),
+                        })
+                    })?;
+                principal
+                    .revoked_at
+                    .get_or_insert_with(|| revoked_at.clone());
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::SetXrayRoutingPolicy { policy } => {
+                let mut policy = policy.clone();
+                // An endpoint deleted between validation and apply must not linger as exempt.
+                policy
+                    .exempt_endpoint_ids
+                    .retain(|endpoint_id| state.endpoints.contains_key(endpoint_id));
+                state.xray_routing_policy = policy;
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::SetEndpointEgress {
+                endpoint_id,
+                egress,
+            } => {
+                endpoint_egress::set_endpoint_egress(state, endpoint_id, egress)?;
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::SetEndpointProbeTargets { targets } => {
+                validate_probe_targets(targets)
+                    .map_err(|reason| DomainError::InvalidEndpointProbeTargets { reason })?;
+                state.endpoint_probe_targets = targets.clone();
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::UpsertDnsFailoverGroup { group } => {
+                let invalid = |reason: String| {
+                    Err(DomainError::InvalidDnsFailoverGroup {
+                        group_id: group.group_id.clone(),
+                        reason,
+                    }
+                    .into())
+                };
+                if let Err(reason) = group.validate() {
+                    return invalid(reason);
+                }
+                if let Some(node_id) = std::iter::once(&group.primary_node_id)
+                    .chain(&group.standby_node_ids)
+                    .find(|node_id| !state.nodes.contains_key(*node_id))
+                {
+                    return Err(DomainError::MissingNode {
+                        node_id: node_id.clone(),
+                    }
+                    .into());
+                }
+                if let Some(other) = state.dns_failover_groups.values().find(|other| {
+                    other.group_id != group.group_id && other.hostname == group.hostname
+                }) {
+                    return invalid(format!(
+                        "hostname {} is already used by group {}",
+                        group.hostname, other.group_id
+                    ));
+                }
+                state
+                    .dns_failover_groups
+                    .insert(group.group_id.clone(), group.clone());
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::DeleteDnsFailoverGroup { group_id } => {
+                if state.dns_failover_groups.remove(group_id).is_none() {
+                    return Err(DomainError::DnsFailoverGroupNotFound {
+                        group_id: group_id.clone(),
+                    }
+                    .into());
+                }
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::ImportUsers { entries } => {
+                let mut next = state.clone();
+                for entry in entries {
+                    crate::user_bulk::apply_user_import_entry(&mut next, entry)?;
+                }
+                *state = next;
+                Ok(DesiredStateApplyResult::Applied)
+            }
+            Self::RestoreBackup { restored } => {
+                if let Some(conflict) = crate::backup::restore_conflicts(restored, state).first() {
+                    return Err(StoreError::Domain(DomainError::RestoreConflict {
+                        reason: conflict.to_string(),
+                    }));
+                }
+                crate::backup::apply_restore(state, restored.as_ref().clone());
+                Ok(DesiredStateApplyResult::Applied)
+            }
             Self::CompatNoop { note } => {
                 if let Some((node_id, probe)) = decode_node_egress_probe_compat_note(note) {
                     if !state.nodes.contains_key(&node_id) {
@@ -3257,6 +3110,7 @@ impl DesiredStateCommand {
                             target_id: sample.target_id.clone(),
                             target_url: sample.target_url.clone(),
                             error: sample.error.clone(),
+                            targets: sample.targets.clone(),
                             config_hash: sample.config_hash.clone(),
                         },
                     );
@@ -3301,6 +3155,15 @@ pub struct PersistedUsage {
     /// Keyed by `endpoint_id`, values are `user_id` sets (excluding quota-banned memberships).
     #[serde(default)]
     pub endpoint_users_applied: BTreeMap<String, BTreeSet<String>>,
+    /// Local-only: bytes this node has drawn from each traffic top-up, keyed by `topup_id`.
+    #[serde(default)]
+    pub topup_consumed_bytes: BTreeMap<String, u64>,
+    /// Local-only `max_concurrent_ips` streaks and temporary blocks, keyed by `user_id`.
+    #[serde(default)]
+    pub ip_limits: BTreeMap<String, UserIpLimitState>,
+    /// Local-only quota warning thresholds crossed in the current cycle, keyed by `user_id`.
+    #[serde(default)]
+    pub quota_warnings: BTreeMap<String, UserQuotaWarning>,
 }
 
 impl PersistedUsage {
@@ -3312,6 +3175,9 @@ impl PersistedUsage {
             node_pacing: BTreeMap::new(),
             user_credential_epochs_applied: BTreeMap::new(),
             endpoint_users_applied: BTreeMap::new(),
+            topup_consumed_bytes: BTreeMap::new(),
+            ip_limits: BTreeMap::new(),
+            quota_warnings: BTreeMap::new(),
         }
     }
 }
@@ -3332,6 +3198,25 @@ pub struct UserNodePacing {
     pub last_priority_tier: UserPriorityTier,
 }
 
+/// Warning thresholds one user crossed on this node during the cycle starting at
+/// `cycle_start_at`, with the figures observed when the highest one was reached.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserQuotaWarning {
+    pub cycle_start_at: String,
+    /// Every threshold (percent) already reported this cycle.
+    #[serde(default)]
+    pub reached_percents: BTreeSet<u8>,
+    pub threshold_percent: u8,
+    pub reached_at: String,
+    /// Base share plus remaining top-up bytes the percentage is measured against.
+    pub quota_bytes: u64,
+    pub used_bytes: u64,
+    pub remaining_bytes: u64,
+    /// `None` when the current rate would not exhaust the quota before the cycle ends.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projected_exhaustion_at: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct NodePacing {
     pub cycle_start_at: String,
@@ -3770,6 +3655,21 @@ impl JsonSnapshotStore {
         &self.tcp_connection_usage
     }
 
+    /// Bytes drawn from each traffic top-up on this node.
+    pub fn topup_consumed_bytes(&self) -> &BTreeMap<String, u64> {
+        &self.usage.topup_consumed_bytes
+    }
+
+    /// `max_concurrent_ips` enforcement state on this node, keyed by `user_id`.
+    pub fn ip_limit_states(&self) -> &BTreeMap<String, UserIpLimitState> {
+        &self.usage.ip_limits
+    }
+
+    /// Quota warning thresholds crossed on this node this cycle, keyed by `user_id`.
+    pub fn quota_warnings(&self) -> &BTreeMap<String, UserQuotaWarning> {
+        &self.usage.quota_warnings
+    }
+
     pub fn save_inbound_ip_usage(&self) -> Result<(), StoreError> {
         let bytes = serde_json::to_vec_pretty(&self.inbound_ip_usage)?;
         write_history_snapshot(&self.history_storage, INBOUND_IP_USAGE_KEY, &bytes)?;
@@ -3965,6 +3865,31 @@ impl JsonSnapshotStore {
         Ok(())
     }
 
+    /// Lifts local quota bans that `topup` now covers; returns whether anything changed.
+    pub fn lift_quota_bans_for_topup(&mut self, topup: &TrafficTopUp) -> Result<bool, StoreError> {
+        let membership_keys = self
+            .state
+            .node_user_endpoint_memberships
+            .iter()
+            .filter(|m| m.user_id == topup.user_id && m.node_id == topup.node_id)
+            .map(|m| membership_key(&m.user_id, &m.endpoint_id))
+            .collect::<Vec<_>>();
+        let mut changed = false;
+        for key in membership_keys {
+            if let Some(entry) = self.usage.memberships.get_mut(&key)
+                && entry.quota_banned
+            {
+                entry.quota_banned = false;
+                entry.quota_banned_at = None;
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_usage()?;
+        }
+        Ok(changed)
+    }
+
     pub fn clear_quota_banned(&mut self, membership_key: &str) -> Result<(), StoreError> {
         if let Some(entry) = self.usage.memberships.get_mut(membership_key) {
             entry.quota_banned = false;
@@ -4180,6 +4105,10 @@ impl JsonSnapshotStore {
             credential_epoch: 0,
             priority_tier: Default::default(),
             quota_reset,
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
         })
     }
 
@@ -4443,6 +4372,26 @@ impl JsonSnapshotStore {
             .cloned()
     }
 
+    /// Resolves a user's primary token or one of their secondary tokens. Expired secondary
+    /// tokens do not resolve.
+    pub fn resolve_subscription_token(
+        &self,
+        subscription_token: &str,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Option<(User, Option<SubscriptionToken>)> {
+        if let Some(user) = self.get_user_by_subscription_token(subscription_token) {
+            return Some((user, None));
+        }
+        let token = self
+            .state
+            .subscription_tokens
+            .values()
+            .find(|token| token.token == subscription_token)
+            .filter(|token| !token.is_expired_at(now))?;
+        let user = self.get_user(&token.user_id)?;
+        Some((user, Some(token.clone())))
+    }
+
     pub fn list_node_users_with_endpoint_ids(&self, node_id: &str) -> Vec<(String, Vec<String>)> {
         let mut by_user = BTreeMap::<String, Vec<String>>::new();
         for membership in self.state.node_user_endpoint_memberships.iter() {
@@ -4536,10 +4485,13 @@ impl JsonSnapshotStore {
     }
 }
 
-fn endpoint_tag(kind: &EndpointKind, endpoint_id: &str) -> String {
+pub(crate) fn endpoint_tag(kind: &EndpointKind, endpoint_id: &str) -> String {
     let kind_short = match kind {
         EndpointKind::VlessRealityVisionTcp => "vless-vision",
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => "ss2022",
+        EndpointKind::TrojanRealityTcp => "trojan",
+        EndpointKind::TrojanTlsTcp => "trojan-tls",
+        EndpointKind::Hysteria2 => "hy2",
     };
     format!("{kind_short}-{endpoint_id}")
 }
@@ -0,0 +1,308 @@
+use chrono::DateTime;
+use tracing::warn;
+
+use serde_json::Value;
+
+use super::{DesiredStateCommand, JsonSnapshotStore, PersistedState, StoreError};
+use crate::{
+    audit::{AuditContext, AuditEntry, build_entry, diff_snapshot},
+    state::history_storage::AuditLogQuery,
+};
+
+impl DesiredStateCommand {
+    /// Wraps an admin write with its audit context. Already-audited commands are kept as-is.
+    pub fn audited(self, audit: AuditContext) -> Self {
+        match self {
+            Self::Audited { .. } => self,
+            command => Self::Audited {
+                audit,
+                command: Box::new(command),
+            },
+        }
+    }
+
+    /// Splits off the audit context so apply-side matching sees the underlying command.
+    pub fn into_audit_parts(self) -> (Option<AuditContext>, Self) {
+        match self {
+            Self::Audited { audit, command } => (Some(audit), *command),
+            command => (None, command),
+        }
+    }
+
+    pub fn unaudited(&self) -> &Self {
+        match self {
+            Self::Audited { command, .. } => command,
+            command => command,
+        }
+    }
+}
+
+/// Top-level state sections a scoped audit diff can cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AuditSection {
+    Users,
+    UserNodeQuotas,
+    UserNodeWeights,
+    UserGlobalWeights,
+    NodeWeightPolicies,
+    UserMihomoProfiles,
+    MihomoDeliveryMode,
+    MihomoResourceAllowPrivateTargets,
+    Memberships,
+    UserAutoAssignEndpointKinds,
+    NotificationWebhooks,
+    AdminPrincipals,
+    TrafficTopUps,
+    SubscriptionTokens,
+    XrayRoutingPolicy,
+    Endpoints,
+    EndpointEgress,
+    EndpointProbeTargets,
+    DnsFailoverGroups,
+    RealityDomains,
+    CredentialRotation,
+}
+
+impl AuditSection {
+    /// The section under its serialized key, so scoped and full diffs share paths.
+    fn snapshot(self, state: &PersistedState) -> (&'static str, Value) {
+        let (key, value) = match self {
+            Self::Users => ("users", serde_json::to_value(&state.users)),
+            Self::UserNodeQuotas => (
+                "user_node_quotas",
+                serde_json::to_value(&state.user_node_quotas),
+            ),
+            Self::UserNodeWeights => (
+                "user_node_weights",
+                serde_json::to_value(&state.user_node_weights),
+            ),
+            Self::UserGlobalWeights => (
+                "user_global_weights",
+                serde_json::to_value(&state.user_global_weights),
+            ),
+            Self::NodeWeightPolicies => (
+                "node_weight_policies",
+                serde_json::to_value(&state.node_weight_policies),
+            ),
+            Self::UserMihomoProfiles => (
+                "user_mihomo_profiles",
+                serde_json::to_value(&state.user_mihomo_profiles),
+            ),
+            Self::MihomoDeliveryMode => (
+                "mihomo_delivery_mode",
+                serde_json::to_value(state.mihomo_delivery_mode),
+            ),
+            Self::MihomoResourceAllowPrivateTargets => (
+                "mihomo_resource_allow_private_targets",
+                serde_json::to_value(state.mihomo_resource_allow_private_targets),
+            ),
+            Self::Memberships => (
+                "node_user_endpoint_memberships",
+                serde_json::to_value(&state.node_user_endpoint_memberships),
+            ),
+            Self::UserAutoAssignEndpointKinds => (
+                "user_auto_assign_endpoint_kinds",
+                serde_json::to_value(&state.user_auto_assign_endpoint_kinds),
+            ),
+            Self::NotificationWebhooks => (
+                "notification_webhooks",
+                serde_json::to_value(&state.notification_webhooks),
+            ),
+            Self::AdminPrincipals => (
+                "admin_principals",
+                serde_json::to_value(&state.admin_principals),
+            ),
+            Self::TrafficTopUps => (
+                "traffic_topups",
+                serde_json::to_value(&state.traffic_topups),
+            ),
+            Self::SubscriptionTokens => (
+                "subscription_tokens",
+                serde_json::to_value(&state.subscription_tokens),
+            ),
+            Self::XrayRoutingPolicy => (
+                "xray_routing_policy",
+                serde_json::to_value(&state.xray_routing_policy),
+            ),
+            Self::Endpoints => ("endpoints", serde_json::to_value(&state.endpoints)),
+            Self::EndpointEgress => (
+                "endpoint_egress",
+                serde_json::to_value(&state.endpoint_egress),
+            ),
+            Self::EndpointProbeTargets => (
+                "endpoint_probe_targets",
+                serde_json::to_value(&state.endpoint_probe_targets),
+            ),
+            Self::DnsFailoverGroups => (
+                "dns_failover_groups",
+                serde_json::to_value(&state.dns_failover_groups),
+            ),
+            Self::RealityDomains => (
+                "reality_domains",
+                serde_json::to_value(&state.reality_domains),
+            ),
+            Self::CredentialRotation => (
+                "credential_rotation",
+                serde_json::to_value(&state.credential_rotation),
+            ),
+        };
+        (key, value.unwrap_or(Value::Null))
+    }
+}
+
+/// Sections `command` can change when applied, or `None` for commands that may touch arbitrary
+/// state (node and user removal, imports, restores, membership operations).
+pub(super) fn audited_sections(command: &DesiredStateCommand) -> Option<&'static [AuditSection]> {
+    use AuditSection as S;
+    use DesiredStateCommand as C;
+
+    Some(match command.unaudited() {
+        C::UpsertUser { .. }
+        | C::ResetUserSubscriptionToken { .. }
+        | C::BumpUserCredentialEpoch { .. }
+        | C::SetUserExpiry { .. }
+        | C::SetUserDisabled { .. } => &[S::Users],
+        C::SetUserNodeQuota { .. } => &[S::UserNodeQuotas],
+        C::SetUserNodeWeight { .. } => &[S::UserNodeWeights, S::NodeWeightPolicies],
+        C::SetUserGlobalWeight { .. } => &[S::UserGlobalWeights],
+        C::SetNodeWeightPolicy { .. } => &[S::NodeWeightPolicies],
+        C::SetUserMihomoProfile { .. } => &[S::UserMihomoProfiles],
+        C::SetMihomoDeliveryMode { .. } => &[S::MihomoDeliveryMode],
+        C::SetMihomoResourceAllowPrivateTargets { .. } => &[S::MihomoResourceAllowPrivateTargets],
+        C::SetGeoDbUpdateSettings { .. } => &[],
+        C::ReplaceUserAccess { .. } | C::EnsureMembership { .. } => {
+            &[S::Memberships, S::UserAutoAssignEndpointKinds]
+        }
+        C::SetNotificationWebhooks { .. } => &[S::NotificationWebhooks],
+        C::UpsertAdminPrincipal { .. } | C::RevokeAdminPrincipal { .. } => &[S::AdminPrincipals],
+        C::CreateTrafficTopUp { .. } | C::DeleteTrafficTopUp { .. } => &[S::TrafficTopUps],
+        C::CreateSubscriptionToken { .. } | C::RevokeSubscriptionToken { .. } => {
+            &[S::SubscriptionTokens]
+        }
+        C::SetXrayRoutingPolicy { .. } => &[S::XrayRoutingPolicy],
+        C::SetEndpointEgress { .. } => &[S::EndpointEgress],
+        C::SetEndpointProbeTargets { .. } => &[S::EndpointProbeTargets],
+        C::UpsertDnsFailoverGroup { .. } | C::DeleteDnsFailoverGroup { .. } => {
+            &[S::DnsFailoverGroups]
+        }
+        C::CreateRealityDomain { .. }
+        | C::PatchRealityDomain { .. }
+        | C::DeleteRealityDomain { .. }
+        | C::ReorderRealityDomains { .. } => &[S::RealityDomains, S::Endpoints],
+        C::StartCredentialRotation { .. }
+        | C::RecordCredentialRotationProgress { .. }
+        | C::PromoteCredentialRotation { .. }
+        | C::RetireCredentialRotation { .. }
+        | C::AbortCredentialRotation { .. } => &[S::CredentialRotation],
+        _ => return None,
+    })
+}
+
+/// The part of `state` an audit diff of `command` is taken over. Scoped commands serialize only
+/// their own sections, so a routine admin write does not turn the whole state into JSON twice
+/// while the store is locked.
+pub(super) fn audit_snapshot(state: &PersistedState, command: &DesiredStateCommand) -> Value {
+    match audited_sections(command) {
+        Some(sections) => Value::Object(
+            sections
+                .iter()
+                .map(|section| section.snapshot(state))
+                // Matches the full snapshot, which skips unset optional sections.
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        ),
+        None => diff_snapshot(state),
+    }
+}
+
+/// Desired state captured before an audited command is applied.
+pub struct AuditBaseline {
+    audit: AuditContext,
+    before: Value,
+}
+
+impl JsonSnapshotStore {
+    pub fn audit_baseline(
+        &self,
+        audit: Option<AuditContext>,
+        command: &DesiredStateCommand,
+    ) -> Option<AuditBaseline> {
+        audit.map(|audit| AuditBaseline {
+            audit,
+            before: audit_snapshot(self.state(), command),
+        })
+    }
+
+    /// Records the entry for an applied command. Failures are logged rather than returned: the
+    /// command is already applied and the state machine must not diverge over the audit trail.
+    pub fn record_audit_entry(
+        &self,
+        log_index: u64,
+        baseline: AuditBaseline,
+        command: &DesiredStateCommand,
+    ) {
+        let occurred_at_unix_seconds = DateTime::parse_from_rfc3339(&baseline.audit.requested_at)
+            .map(|at| at.timestamp())
+            .unwrap_or_default();
+        let entry = build_entry(
+            log_index,
+            baseline.audit.requested_at.clone(),
+            baseline.audit,
+            command,
+            &baseline.before,
+            &audit_snapshot(self.state(), command),
+        );
+        let result = self
+            .history_storage
+            .append_audit_entry(&entry, occurred_at_unix_seconds);
+        if let Err(error) = result {
+            warn!(log_index, command = entry.command, %error, "record admin audit entry failed");
+        }
+    }
+
+    /// Notes that this node installed a snapshot covering `log_index` and so lacks the audit
+    /// entries of the log entries it folds in.
+    pub fn mark_audit_entries_missing_through(&self, log_index: u64) {
+        if let Err(error) = self
+            .history_storage
+            .mark_audit_entries_missing_through(log_index)
+        {
+            warn!(log_index, %error, "record admin audit gap failed");
+        }
+    }
+
+    /// Whether `audit_entries` can answer for the whole cluster history.
+    pub(crate) fn audit_log_is_complete(&self) -> bool {
+        self.history_storage
+            .audit_log_is_complete()
+            .unwrap_or(false)
+    }
+
+    /// Next index for stores that apply commands without a Raft log.
+    pub fn next_local_audit_log_index(&self) -> u64 {
+        let query = AuditLogQuery {
+            limit: 1,
+            ..AuditLogQuery::default()
+        };
+        self.history_storage
+            .audit_entries(&query)
+            .ok()
+            .and_then(|rows| rows.first().map(|(index, _)| index + 1))
+            .unwrap_or(1)
+    }
+
+    pub(crate) fn audit_entries(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEntry>, StoreError> {
+        let rows = self.history_storage.audit_entries(query).map_err(|error| {
+            StoreError::HistoryStorage {
+                message: format!("read admin audit log: {error}"),
+            }
+        })?;
+        rows.into_iter()
+            .map(|(_, payload)| serde_json::from_slice(&payload).map_err(StoreError::from))
+            .collect()
+    }
+}
@@ -0,0 +1,101 @@
+use super::{DesiredStateApplyResult, DesiredStateCommand, PersistedState, StoreError};
+use crate::{
+    credential_rotation::{CredentialRotation, CredentialRotationPhase},
+    domain::DomainError,
+};
+
+/// Applies the credential rotation commands; `None` for any other command.
+pub(super) fn apply_command(
+    state: &mut PersistedState,
+    command: &DesiredStateCommand,
+) -> Option<Result<DesiredStateApplyResult, StoreError>> {
+    let result = match command {
+        DesiredStateCommand::StartCredentialRotation { rotation } => start(state, rotation),
+        DesiredStateCommand::RecordCredentialRotationProgress {
+            rotation_id,
+            node_id,
+            stage,
+            at,
+        } => current_credential_rotation(state, rotation_id)
+            .map(|rotation| rotation.record_progress(node_id, *stage, at)),
+        DesiredStateCommand::PromoteCredentialRotation {
+            rotation_id,
+            promoted_at,
+        } => promote(state, rotation_id, promoted_at),
+        DesiredStateCommand::RetireCredentialRotation {
+            rotation_id,
+            retired_at,
+        } => retire(state, rotation_id, retired_at),
+        DesiredStateCommand::AbortCredentialRotation { rotation_id } => abort(state, rotation_id),
+        _ => return None,
+    };
+    Some(
+        result
+            .map(|()| DesiredStateApplyResult::Applied)
+            .map_err(StoreError::from),
+    )
+}
+
+fn start(state: &mut PersistedState, rotation: &CredentialRotation) -> Result<(), DomainError> {
+    if let Some(current) = &state.credential_rotation {
+        return Err(DomainError::CredentialRotationConflict {
+            reason: format!("rotation {} is already in progress", current.rotation_id),
+        });
+    }
+    state.credential_rotation = Some(rotation.clone());
+    Ok(())
+}
+
+fn promote(
+    state: &mut PersistedState,
+    rotation_id: &str,
+    promoted_at: &str,
+) -> Result<(), DomainError> {
+    let node_ids = state.nodes.keys().cloned().collect::<Vec<_>>();
+    let rotation = current_credential_rotation(state, rotation_id)?;
+    rotation
+        .check_promote(&node_ids)
+        .map_err(|reason| DomainError::CredentialRotationConflict { reason })?;
+    rotation.phase = CredentialRotationPhase::Promoted;
+    rotation.promoted_at = Some(promoted_at.to_string());
+    Ok(())
+}
+
+fn retire(
+    state: &mut PersistedState,
+    rotation_id: &str,
+    retired_at: &str,
+) -> Result<(), DomainError> {
+    let node_ids = state.nodes.keys().cloned().collect::<Vec<_>>();
+    current_credential_rotation(state, rotation_id)?
+        .check_retire(&node_ids, retired_at)
+        .map_err(|reason| DomainError::CredentialRotationConflict { reason })?;
+    state.credential_rotation = None;
+    Ok(())
+}
+
+fn abort(state: &mut PersistedState, rotation_id: &str) -> Result<(), DomainError> {
+    let rotation = current_credential_rotation(state, rotation_id)?;
+    if rotation.phase != CredentialRotationPhase::Staged {
+        return Err(DomainError::CredentialRotationConflict {
+            reason: "a promoted rotation cannot be aborted; finish it instead".to_string(),
+        });
+    }
+    state.credential_rotation = None;
+    Ok(())
+}
+
+fn current_credential_rotation<'a>(
+    state: &'a mut PersistedState,
+    rotation_id: &str,
+) -> Result<&'a mut CredentialRotation, DomainError> {
+    let rotation = state.credential_rotation.as_mut().ok_or_else(|| {
+        DomainError::CredentialRotationNotFound {
+            rotation_id: rotation_id.to_string(),
+        }
+    })?;
+    rotation
+        .check_rotation_id(rotation_id)
+        .map_err(|reason| DomainError::CredentialRotationConflict { reason })?;
+    Ok(rotation)
+}
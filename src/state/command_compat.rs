@@ -1,6 +1,15 @@
 use std::collections::BTreeSet;
 
-use super::{DesiredStateCommand, DesiredStateCommandCompat};
+use serde::Deserialize;
+
+use super::{
+    AdminPrincipal, AuditContext, CredentialRotation, CredentialRotationNodeStage,
+    DesiredStateCommand, DnsFailoverGroup, Endpoint, EndpointEgress, EndpointProbeAppendSample,
+    GeoDbUpdateSettingsCompat, JoinSession, LegacyGrantCompat, MembershipOperation,
+    MihomoDeliveryMode, Node, NotificationWebhook, PersistedState, ProbeTarget, QuotaResetSource,
+    RealityDomain, RepositoryMemberRuntimePatch, RepositoryMembership, ReverseMeshAssignment,
+    SubscriptionToken, TrafficTopUp, User, UserMihomoProfile, XrayRoutingPolicy,
+};
 
 impl From<DesiredStateCommandCompat> for DesiredStateCommand {
     fn from(value: DesiredStateCommandCompat) -> Self {
@@ -134,6 +143,98 @@ impl From<DesiredStateCommandCompat> for DesiredStateCommand {
             DesiredStateCommandCompat::BumpUserCredentialEpoch { user_id } => {
                 Self::BumpUserCredentialEpoch { user_id }
             }
+            DesiredStateCommandCompat::SetUserExpiry {
+                user_id,
+                expires_at,
+            } => Self::SetUserExpiry {
+                user_id,
+                expires_at,
+            },
+            DesiredStateCommandCompat::SetUserDisabled { user_id, disabled } => {
+                Self::SetUserDisabled { user_id, disabled }
+            }
+            DesiredStateCommandCompat::SetNotificationWebhooks { webhooks } => {
+                Self::SetNotificationWebhooks { webhooks }
+            }
+            DesiredStateCommandCompat::UpsertAdminPrincipal { principal } => {
+                Self::UpsertAdminPrincipal { principal }
+            }
+            DesiredStateCommandCompat::RevokeAdminPrincipal {
+                principal_id,
+                revoked_at,
+            } => Self::RevokeAdminPrincipal {
+                principal_id,
+                revoked_at,
+            },
+            DesiredStateCommandCompat::CreateTrafficTopUp { topup } => {
+                Self::CreateTrafficTopUp { topup }
+            }
+            DesiredStateCommandCompat::DeleteTrafficTopUp { topup_id } => {
+                Self::DeleteTrafficTopUp { topup_id }
+            }
+            DesiredStateCommandCompat::CreateSubscriptionToken { token } => {
+                Self::CreateSubscriptionToken { token }
+            }
+            DesiredStateCommandCompat::RevokeSubscriptionToken { token_id } => {
+                Self::RevokeSubscriptionToken { token_id }
+            }
+            DesiredStateCommandCompat::SetXrayRoutingPolicy { policy } => {
+                Self::SetXrayRoutingPolicy { policy }
+            }
+            DesiredStateCommandCompat::SetEndpointEgress {
+                endpoint_id,
+                egress,
+            } => Self::SetEndpointEgress {
+                endpoint_id,
+                egress,
+            },
+            DesiredStateCommandCompat::SetEndpointProbeTargets { targets } => {
+                Self::SetEndpointProbeTargets { targets }
+            }
+            DesiredStateCommandCompat::UpsertDnsFailoverGroup { group } => {
+                Self::UpsertDnsFailoverGroup { group }
+            }
+            DesiredStateCommandCompat::DeleteDnsFailoverGroup { group_id } => {
+                Self::DeleteDnsFailoverGroup { group_id }
+            }
+            DesiredStateCommandCompat::ImportUsers { entries } => Self::ImportUsers { entries },
+            DesiredStateCommandCompat::StartCredentialRotation { rotation } => {
+                Self::StartCredentialRotation { rotation }
+            }
+            DesiredStateCommandCompat::RecordCredentialRotationProgress {
+                rotation_id,
+                node_id,
+                stage,
+                at,
+            } => Self::RecordCredentialRotationProgress {
+                rotation_id,
+                node_id,
+                stage,
+                at,
+            },
+            DesiredStateCommandCompat::PromoteCredentialRotation {
+                rotation_id,
+                promoted_at,
+            } => Self::PromoteCredentialRotation {
+                rotation_id,
+                promoted_at,
+            },
+            DesiredStateCommandCompat::RetireCredentialRotation {
+                rotation_id,
+                retired_at,
+            } => Self::RetireCredentialRotation {
+                rotation_id,
+                retired_at,
+            },
+            DesiredStateCommandCompat::AbortCredentialRotation { rotation_id } => {
+                Self::AbortCredentialRotation { rotation_id }
+            }
+            DesiredStateCommandCompat::RestoreBackup { restored } => {
+                Self::RestoreBackup { restored }
+            }
+            DesiredStateCommandCompat::Audited { audit, command } => {
+                Self::Audited { audit, command }
+            }
             DesiredStateCommandCompat::CompatNoop { note } => Self::CompatNoop { note },
             DesiredStateCommandCompat::AppendEndpointProbeSamples {
                 hour,
@@ -193,3 +294,259 @@ impl From<DesiredStateCommandCompat> for DesiredStateCommand {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum GrantEnabledSourceCompat {
+    #[default]
+    Manual,
+    Quota,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+struct UserAccessItemCompat {
+    endpoint_id: String,
+    #[serde(default)]
+    note: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DesiredStateCommandCompat {
+    UpsertNode {
+        node: Node,
+        #[serde(default)]
+        join_session: Option<JoinSession>,
+    },
+    DeleteNode {
+        node_id: String,
+        #[serde(default)]
+        delete_endpoints: bool,
+        #[serde(default)]
+        expected_endpoint_ids: Vec<String>,
+        #[serde(default)]
+        join_session: Option<JoinSession>,
+    },
+    BeginMembershipOperation {
+        operation: Box<MembershipOperation>,
+        #[serde(default)]
+        node: Option<Node>,
+        #[serde(default)]
+        join_session: Option<JoinSession>,
+    },
+    TransitionMembershipOperation {
+        operation: MembershipOperation,
+    },
+    PruneMembershipOperations {
+        terminal_before: String,
+    },
+    UpsertEndpoint {
+        endpoint: Endpoint,
+        #[serde(default)]
+        expected: Option<Endpoint>,
+    },
+    DeleteEndpoint {
+        endpoint_id: String,
+    },
+    CreateRealityDomain {
+        domain: RealityDomain,
+    },
+    PatchRealityDomain {
+        domain_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        server_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        disabled_node_ids: Option<BTreeSet<String>>,
+    },
+    DeleteRealityDomain {
+        domain_id: String,
+    },
+    ReorderRealityDomains {
+        domain_ids: Vec<String>,
+    },
+    UpsertUser {
+        user: User,
+    },
+    DeleteUser {
+        user_id: String,
+    },
+    ResetUserSubscriptionToken {
+        user_id: String,
+        subscription_token: String,
+    },
+    SetUserNodeQuota {
+        user_id: String,
+        node_id: String,
+        quota_limit_bytes: u64,
+        #[serde(default)]
+        quota_reset_source: QuotaResetSource,
+    },
+    SetUserNodeWeight {
+        user_id: String,
+        node_id: String,
+        weight: u16,
+    },
+    SetUserGlobalWeight {
+        user_id: String,
+        weight: u16,
+    },
+    SetNodeWeightPolicy {
+        node_id: String,
+        inherit_global: bool,
+    },
+    SetUserMihomoProfile {
+        user_id: String,
+        profile: UserMihomoProfile,
+    },
+    SetMihomoDeliveryMode {
+        mode: MihomoDeliveryMode,
+    },
+    SetMihomoResourceAllowPrivateTargets {
+        allow: bool,
+    },
+    SetGeoDbUpdateSettings {
+        settings: GeoDbUpdateSettingsCompat,
+    },
+    ReplaceUserAccess {
+        user_id: String,
+        #[serde(default)]
+        endpoint_ids: Vec<String>,
+        #[serde(default)]
+        items: Vec<UserAccessItemCompat>,
+    },
+    EnsureMembership {
+        user_id: String,
+        endpoint_id: String,
+    },
+    BumpUserCredentialEpoch {
+        user_id: String,
+    },
+    SetUserExpiry {
+        user_id: String,
+        #[serde(default)]
+        expires_at: Option<String>,
+    },
+    SetUserDisabled {
+        user_id: String,
+        disabled: bool,
+    },
+    SetNotificationWebhooks {
+        webhooks: Vec<NotificationWebhook>,
+    },
+    UpsertAdminPrincipal {
+        principal: AdminPrincipal,
+    },
+    RevokeAdminPrincipal {
+        principal_id: String,
+        revoked_at: String,
+    },
+    CreateTrafficTopUp {
+        topup: TrafficTopUp,
+    },
+    DeleteTrafficTopUp {
+        topup_id: String,
+    },
+    CreateSubscriptionToken {
+        token: SubscriptionToken,
+    },
+    RevokeSubscriptionToken {
+        token_id: String,
+    },
+    SetXrayRoutingPolicy {
+        policy: XrayRoutingPolicy,
+    },
+    SetEndpointEgress {
+        endpoint_id: String,
+        egress: EndpointEgress,
+    },
+    SetEndpointProbeTargets {
+        targets: Vec<ProbeTarget>,
+    },
+    UpsertDnsFailoverGroup {
+        group: DnsFailoverGroup,
+    },
+    DeleteDnsFailoverGroup {
+        group_id: String,
+    },
+    ImportUsers {
+        entries: Vec<crate::user_bulk::UserImportEntry>,
+    },
+    StartCredentialRotation {
+        rotation: Box<CredentialRotation>,
+    },
+    RecordCredentialRotationProgress {
+        rotation_id: String,
+        node_id: String,
+        stage: CredentialRotationNodeStage,
+        at: String,
+    },
+    PromoteCredentialRotation {
+        rotation_id: String,
+        promoted_at: String,
+    },
+    RetireCredentialRotation {
+        rotation_id: String,
+        retired_at: String,
+    },
+    AbortCredentialRotation {
+        rotation_id: String,
+    },
+    RestoreBackup {
+        restored: Box<PersistedState>,
+    },
+    Audited {
+        audit: AuditContext,
+        command: Box<DesiredStateCommand>,
+    },
+    CompatNoop {
+        note: String,
+    },
+    AppendEndpointProbeSamples {
+        hour: String,
+        from_node_id: String,
+        samples: Vec<EndpointProbeAppendSample>,
+    },
+    ReplaceRepositoryMembership {
+        membership: RepositoryMembership,
+    },
+    UpdateRepositoryMemberRuntime(RepositoryMemberRuntimePatch),
+    SetReverseMeshEpoch {
+        epoch: u64,
+    },
+    UpsertReverseMeshAssignment {
+        assignment: ReverseMeshAssignment,
+        #[serde(default)]
+        expected_generation: Option<u64>,
+    },
+    DeleteReverseMeshAssignment {
+        target_node_id: String,
+        #[serde(default)]
+        expected_generation: Option<u64>,
+    },
+    ReplaceUserGrants {
+        user_id: String,
+        grants: Vec<LegacyGrantCompat>,
+    },
+    UpsertGrant {
+        grant: LegacyGrantCompat,
+    },
+    DeleteGrant {
+        grant_id: String,
+    },
+    SetGrantEnabled {
+        grant_id: String,
+        enabled: bool,
+        #[serde(default)]
+        source: GrantEnabledSourceCompat,
+    },
+}
+
+impl<'de> Deserialize<'de> for DesiredStateCommand {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let compat = DesiredStateCommandCompat::deserialize(deserializer)?;
+        Ok(compat.into())
+    }
+}
@@ -0,0 +1,51 @@
+use super::PersistedState;
+use crate::{
+    domain::DomainError, egress::EndpointEgress,
+    managed_default_endpoints::managed_default_vless_endpoint,
+};
+
+/// Validates and stores `egress` for `endpoint_id`; `Direct` removes the entry.
+pub(super) fn set_endpoint_egress(
+    state: &mut PersistedState,
+    endpoint_id: &str,
+    egress: &EndpointEgress,
+) -> Result<(), DomainError> {
+    let invalid = |reason: String| DomainError::InvalidEndpointEgress {
+        endpoint_id: endpoint_id.to_string(),
+        reason,
+    };
+    let Some(endpoint) = state.endpoints.get(endpoint_id) else {
+        return Err(DomainError::MissingEndpoint {
+            endpoint_id: endpoint_id.to_string(),
+        });
+    };
+    egress.validate().map_err(invalid)?;
+    if let EndpointEgress::Node { node_id } = egress {
+        if !state.nodes.contains_key(node_id) {
+            return Err(DomainError::MissingNode {
+                node_id: node_id.clone(),
+            });
+        }
+        if *node_id == endpoint.node_id {
+            return Err(invalid(
+                "cannot chain egress through its own node".to_string(),
+            ));
+        }
+        let chainable = state.endpoints.values().any(|candidate| {
+            candidate.node_id == *node_id && managed_default_vless_endpoint(candidate).is_some()
+        });
+        if !chainable {
+            return Err(invalid(format!(
+                "node {node_id} has no managed VLESS endpoint"
+            )));
+        }
+    }
+    if *egress == EndpointEgress::Direct {
+        state.endpoint_egress.remove(endpoint_id);
+    } else {
+        state
+            .endpoint_egress
+            .insert(endpoint_id.to_string(), egress.clone());
+    }
+    Ok(())
+}
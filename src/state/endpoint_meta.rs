@@ -2,11 +2,14 @@ use serde::Deserialize;
 
 use super::StoreError;
 use crate::{
-    domain::EndpointKind,
+    cluster_identity::{SelfSignedServerCertPem, generate_self_signed_server_cert},
+    domain::{DomainError, EndpointKind},
     protocol::{
-        MihomoSmuxConfig, RealityKeys, SS2022_METHOD_2022_BLAKE3_AES_128_GCM, Ss2022EndpointMeta,
-        VlessRealityTransport, VlessRealityVisionTcpEndpointMeta, generate_reality_keypair,
-        generate_short_id_16hex, generate_ss2022_psk_b64,
+        Hysteria2EndpointMeta, MihomoSmuxConfig, RealityKeys,
+        SS2022_METHOD_2022_BLAKE3_AES_128_GCM, Ss2022EndpointMeta, TrojanRealityTcpEndpointMeta,
+        TrojanTlsTcpEndpointMeta, VlessRealityTransport, VlessRealityVisionTcpEndpointMeta,
+        generate_reality_keypair, generate_short_id_16hex, generate_ss2022_psk_b64,
+        ss2022_psk_len_bytes, validate_server_name,
     },
 };
 
@@ -29,10 +32,49 @@ fn default_new_vless_transport() -> VlessRealityTransport {
 
 #[derive(Debug, Deserialize)]
 struct Ss2022EndpointMetaInput {
+    #[serde(default = "default_new_ss2022_method")]
+    method: String,
     #[serde(default)]
     mihomo_smux: MihomoSmuxConfig,
 }
 
+fn default_new_ss2022_method() -> String {
+    SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string()
+}
+
+#[derive(Debug, Deserialize)]
+struct TrojanRealityEndpointMetaInput {
+    reality: crate::protocol::RealityConfig,
+    #[serde(default)]
+    mihomo_smux: MihomoSmuxConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct TrojanTlsEndpointMetaInput {
+    server_name: String,
+    #[serde(default)]
+    mihomo_smux: MihomoSmuxConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hysteria2EndpointMetaInput {
+    server_name: String,
+}
+
+/// Validates `server_name` and issues the self-signed certificate a TLS-terminating endpoint
+/// presents. Returns the normalized name with the generated certificate.
+fn self_signed_endpoint_cert(
+    server_name: &str,
+) -> Result<(String, SelfSignedServerCertPem), StoreError> {
+    let server_name = server_name.trim().trim_end_matches('.').to_string();
+    validate_server_name(&server_name).map_err(|reason| DomainError::InvalidServerName {
+        server_name: server_name.clone(),
+        reason: reason.to_string(),
+    })?;
+    let cert = generate_self_signed_server_cert(&server_name)?;
+    Ok((server_name, cert))
+}
+
 pub(super) fn build_endpoint_meta(
     kind: &EndpointKind,
     meta_input: serde_json::Value,
@@ -62,13 +104,54 @@ pub(super) fn build_endpoint_meta(
         }
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
             let input: Ss2022EndpointMetaInput = serde_json::from_value(meta_input)?;
-            let server_psk_b64 = generate_ss2022_psk_b64(&mut rng);
+            let psk_len = ss2022_psk_len_bytes(&input.method).ok_or_else(|| {
+                DomainError::UnsupportedSs2022Method {
+                    method: input.method.clone(),
+                }
+            })?;
+            let server_psk_b64 = generate_ss2022_psk_b64(&mut rng, psk_len);
             Ok(serde_json::to_value(Ss2022EndpointMeta {
-                method: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
+                method: input.method,
                 server_psk_b64,
                 mihomo_smux: input.mihomo_smux,
                 managed_default: false,
             })?)
         }
+        EndpointKind::TrojanRealityTcp => {
+            let input: TrojanRealityEndpointMetaInput = serde_json::from_value(meta_input)?;
+            let keypair = generate_reality_keypair(&mut rng);
+            let short_id = generate_short_id_16hex(&mut rng);
+            Ok(serde_json::to_value(TrojanRealityTcpEndpointMeta {
+                reality: input.reality,
+                reality_keys: RealityKeys {
+                    private_key: keypair.private_key,
+                    public_key: keypair.public_key,
+                },
+                short_ids: vec![short_id.clone()],
+                active_short_id: short_id,
+                mihomo_smux: input.mihomo_smux,
+            })?)
+        }
+        EndpointKind::TrojanTlsTcp => {
+            let input: TrojanTlsEndpointMetaInput = serde_json::from_value(meta_input)?;
+            let (server_name, cert) = self_signed_endpoint_cert(&input.server_name)?;
+            Ok(serde_json::to_value(TrojanTlsTcpEndpointMeta {
+                server_name,
+                tls_cert_pem: cert.cert_pem,
+                tls_key_pem: cert.key_pem,
+                cert_sha256: cert.cert_sha256,
+                mihomo_smux: input.mihomo_smux,
+            })?)
+        }
+        EndpointKind::Hysteria2 => {
+            let input: Hysteria2EndpointMetaInput = serde_json::from_value(meta_input)?;
+            let (server_name, cert) = self_signed_endpoint_cert(&input.server_name)?;
+            Ok(serde_json::to_value(Hysteria2EndpointMeta {
+                server_name,
+                tls_cert_pem: cert.cert_pem,
+                tls_key_pem: cert.key_pem,
+                cert_sha256: cert.cert_sha256,
+            })?)
+        }
     }
 }
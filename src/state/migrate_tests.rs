@@ -0,0 +1,420 @@
+use super::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+fn migrate_test_user(user_id: &str) -> User {
+    User {
+        user_id: user_id.to_string(),
+        display_name: user_id.to_string(),
+        subscription_token: xp_test_fixtures::label_sub_user1().to_owned(),
+        credential_epoch: 0,
+        priority_tier: UserPriorityTier::P2,
+        quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
+    }
+}
+
+fn migrate_test_node(_node_id: &str) -> Node {
+    Node {
+        node_id: xp_test_fixtures::label_node1().to_owned(),
+        node_name: xp_test_fixtures::label_node1().to_owned(),
+        access_host: xp_test_fixtures::address_loopback().to_owned(),
+        api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+        quota_limit_bytes: 0,
+        quota_reset: NodeQuotaReset::default(),
+    }
+}
+
+fn migrate_test_ss_endpoint_one() -> Endpoint {
+    Endpoint {
+        endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
+        node_id: xp_test_fixtures::label_node1().to_owned(),
+        tag: xp_test_fixtures::label_ss1().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 10_000,
+        meta: serde_json::json!({}),
+    }
+}
+
+fn migrate_test_ss_endpoint_two() -> Endpoint {
+    Endpoint {
+        endpoint_id: xp_test_fixtures::label_ss2().to_owned(),
+        node_id: xp_test_fixtures::label_node1().to_owned(),
+        tag: xp_test_fixtures::label_ss2().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 10_000,
+        meta: serde_json::json!({}),
+    }
+}
+
+#[test]
+fn migrate_v2_like_to_v3_overrides_seeded_quota_reset_source_from_grants() {
+    let node_id = "node_1".to_string();
+    let endpoint_id = "endpoint_1".to_string();
+    let user_id = "user_1".to_string();
+
+    let mut nodes = BTreeMap::new();
+    nodes.insert(
+        node_id.clone(),
+        Node {
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+            access_host: xp_test_fixtures::subscription_host_empty().to_owned(),
+            api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+            quota_limit_bytes: 0,
+            quota_reset: NodeQuotaReset::default(),
+        },
+    );
+
+    let mut endpoints = BTreeMap::new();
+    endpoints.insert(
+        endpoint_id.clone(),
+        Endpoint {
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            tag: xp_test_fixtures::endpoint_tag_fixturetest().to_owned(),
+            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+            port: 12345,
+            meta: serde_json::json!({}),
+        },
+    );
+
+    let mut users = BTreeMap::new();
+    users.insert(
+        user_id.clone(),
+        UserV2 {
+            user_id: user_id.clone(),
+            display_name: "alice".to_string(),
+            subscription_token: xp_test_fixtures::primary_token().to_owned(),
+            cycle_policy_default: CyclePolicyDefaultV2::ByUser,
+            cycle_day_of_month_default: 1,
+        },
+    );
+
+    let mut grants = BTreeMap::new();
+    grants.insert(
+        "grant_1".to_string(),
+        GrantV2 {
+            grant_id: "grant_1".to_string(),
+            user_id: user_id.clone(),
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            group_name: None,
+            enabled: true,
+            quota_limit_bytes: 123,
+            cycle_policy: CyclePolicyV2::ByNode,
+            cycle_day_of_month: Some(1),
+            note: None,
+            credentials: serde_json::json!({}),
+        },
+    );
+
+    let mut user_node_quotas = BTreeMap::new();
+    user_node_quotas
+        .entry(user_id.clone())
+        .or_insert_with(BTreeMap::new)
+        .insert(node_id.clone(), 456);
+
+    let v2 = PersistedStateV2Like {
+        schema_version: 2,
+        nodes,
+        endpoints,
+        users,
+        grants,
+        user_node_quotas,
+    };
+
+    let v3 = migrate_v2_like_to_v3(v2).expect("migration should succeed");
+    let cfg = v3
+        .user_node_quotas
+        .get(&user_id)
+        .and_then(|m| m.get(&node_id))
+        .expect("user node quota cfg should exist");
+
+    assert_eq!(cfg.quota_limit_bytes, Some(456));
+    assert_eq!(cfg.quota_reset_source, QuotaResetSource::Node);
+}
+
+#[test]
+fn migrate_v4_to_v5_seeds_reality_domains_when_empty() {
+    let mut v4 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V4);
+    v4.reality_domains = Vec::new();
+
+    let v5 = migrate_v4_to_v5(v4).expect("migration should succeed");
+    assert_eq!(v5.schema_version, SCHEMA_VERSION_V5);
+    assert_eq!(v5.reality_domains, default_seed_reality_domains());
+}
+
+#[test]
+fn migrate_v4_to_v5_does_not_override_existing_reality_domains() {
+    let mut v4 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V4);
+    v4.reality_domains = vec![RealityDomain {
+        domain_id: "custom_1".to_string(),
+        server_name: "example.com".to_string(),
+        disabled_node_ids: BTreeSet::new(),
+    }];
+
+    let v5 = migrate_v4_to_v5(v4).expect("migration should succeed");
+    assert_eq!(v5.schema_version, SCHEMA_VERSION_V5);
+    assert_eq!(v5.reality_domains.len(), 1);
+    assert_eq!(v5.reality_domains[0].domain_id, "custom_1");
+    assert_eq!(v5.reality_domains[0].server_name, "example.com");
+}
+
+#[test]
+fn migrate_v6_to_v7_seeds_node_user_endpoint_memberships_from_grants() {
+    let mut v6 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V6);
+    v6.users.insert(
+        "user_1".to_string(),
+        User {
+            user_id: "user_1".to_string(),
+            display_name: "alice".to_string(),
+            subscription_token: xp_test_fixtures::label_sub1().to_owned(),
+            credential_epoch: 0,
+            priority_tier: UserPriorityTier::P2,
+            quota_reset: UserQuotaReset::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
+        },
+    );
+    v6.nodes.insert(
+        "node_1".to_string(),
+        Node {
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+            access_host: xp_test_fixtures::address_loopback().to_owned(),
+            api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+            quota_limit_bytes: 0,
+            quota_reset: NodeQuotaReset::default(),
+        },
+    );
+    v6.endpoints.insert(
+        "endpoint_1".to_string(),
+        Endpoint {
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            tag: xp_test_fixtures::primary_endpoint_tag().to_owned(),
+            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+            port: 12345,
+            meta: serde_json::json!({}),
+        },
+    );
+    v6.grants.insert(
+        "grant_1".to_string(),
+        LegacyGrantCompat {
+            grant_id: "grant_1".to_string(),
+            user_id: "user_1".to_string(),
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            enabled: true,
+        },
+    );
+
+    let v7 = migrate_v6_to_v7(v6).expect("migration should succeed");
+    assert_eq!(v7.schema_version, SCHEMA_VERSION_V7);
+    assert!(
+        v7.node_user_endpoint_memberships
+            .contains(&NodeUserEndpointMembership {
+                user_id: "user_1".to_string(),
+                node_id: "node_1".to_string(),
+                endpoint_id: "endpoint_1".to_string(),
+            })
+    );
+}
+
+#[test]
+fn migrate_v7_to_v8_keeps_existing_weights_and_sets_latest_schema() {
+    let mut v7 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V7);
+    v7.users.insert(
+        "user_1".to_string(),
+        User {
+            user_id: "user_1".to_string(),
+            display_name: "alice".to_string(),
+            subscription_token: xp_test_fixtures::label_sub1().to_owned(),
+            credential_epoch: 0,
+            priority_tier: UserPriorityTier::P2,
+            quota_reset: UserQuotaReset::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
+        },
+    );
+    v7.user_global_weights
+        .insert("user_1".to_string(), UserGlobalWeightConfig { weight: 135 });
+
+    let v8 = migrate_v7_to_v8(v7).expect("migration should succeed");
+    assert_eq!(v8.schema_version, SCHEMA_VERSION_V8);
+    assert_eq!(
+        v8.user_global_weights.get("user_1"),
+        Some(&UserGlobalWeightConfig { weight: 135 })
+    );
+}
+
+#[test]
+fn migrate_v9_compat_to_v10_extracts_memberships_and_clears_user_node_quotas() {
+    let mut v9 = PersistedStateV9Compat::empty_with_version(SCHEMA_VERSION_V9);
+
+    v9.users.insert(
+        "user_1".to_string(),
+        User {
+            user_id: "user_1".to_string(),
+            display_name: "alice".to_string(),
+            subscription_token: xp_test_fixtures::label_sub1().to_owned(),
+            credential_epoch: 0,
+            priority_tier: UserPriorityTier::P2,
+            quota_reset: UserQuotaReset::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
+        },
+    );
+    v9.nodes.insert(
+        "node_1".to_string(),
+        Node {
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
+            access_host: xp_test_fixtures::address_loopback().to_owned(),
+            api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
+            quota_limit_bytes: 0,
+            quota_reset: NodeQuotaReset::default(),
+        },
+    );
+    v9.endpoints.insert(
+        "endpoint_1".to_string(),
+        Endpoint {
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            tag: xp_test_fixtures::primary_endpoint_tag().to_owned(),
+            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+            port: 12345,
+            meta: serde_json::json!({}),
+        },
+    );
+
+    v9.grants.insert(
+        "grant_0".to_string(),
+        LegacyGrantCompat {
+            grant_id: "grant_0".to_string(),
+            user_id: "user_1".to_string(),
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            enabled: true,
+        },
+    );
+    v9.grants.insert(
+        "grant_1".to_string(),
+        LegacyGrantCompat {
+            grant_id: "grant_1".to_string(),
+            user_id: "user_1".to_string(),
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            enabled: false,
+        },
+    );
+    v9.grants.insert(
+        "grant_orphan_user".to_string(),
+        LegacyGrantCompat {
+            grant_id: "grant_orphan_user".to_string(),
+            user_id: "user_missing".to_string(),
+            endpoint_id: xp_test_fixtures::label_endpoint1().to_owned(),
+            enabled: true,
+        },
+    );
+    v9.grants.insert(
+        "grant_orphan_endpoint".to_string(),
+        LegacyGrantCompat {
+            grant_id: "grant_orphan_endpoint".to_string(),
+            user_id: "user_1".to_string(),
+            endpoint_id: xp_test_fixtures::label_endpoint_missing().to_owned(),
+            enabled: true,
+        },
+    );
+
+    v9.user_node_quotas.insert(
+        "user_1".to_string(),
+        BTreeMap::from([(
+            "node_missing".to_string(),
+            UserNodeQuotaConfig {
+                quota_limit_bytes: Some(123),
+                quota_reset_source: QuotaResetSource::User,
+            },
+        )]),
+    );
+    v9.user_node_quotas.insert(
+        "user_missing".to_string(),
+        BTreeMap::from([(
+            "node_1".to_string(),
+            UserNodeQuotaConfig {
+                quota_limit_bytes: Some(321),
+                quota_reset_source: QuotaResetSource::User,
+            },
+        )]),
+    );
+
+    let (v10, mapping, stats) = migrate_v9_compat_to_v10(v9).expect("migration should succeed");
+    assert_eq!(v10.schema_version, SCHEMA_VERSION_V10);
+    assert!(v10.user_node_quotas.is_empty());
+    assert_eq!(v10.node_user_endpoint_memberships.len(), 1);
+    assert_eq!(
+        mapping.get("grant_0"),
+        Some(&"user_1::endpoint_1".to_string())
+    );
+    assert_eq!(
+        mapping.get("grant_1"),
+        Some(&"user_1::endpoint_1".to_string())
+    );
+    assert!(!mapping.contains_key("grant_orphan_user"));
+    assert!(!mapping.contains_key("grant_orphan_endpoint"));
+    assert_eq!(stats.grants_total, 4);
+    assert_eq!(stats.grants_orphan_dropped, 2);
+    assert_eq!(stats.memberships_created, 1);
+    assert_eq!(stats.memberships_deduped, 1);
+    assert_eq!(stats.user_node_quotas_cleared, 2);
+}
+
+#[test]
+fn migrate_v11_to_v12_infers_auto_assign_endpoint_kinds() {
+    let mut v11 = PersistedState::empty();
+    v11.schema_version = SCHEMA_VERSION_V11;
+    v11.users
+        .insert("user_all".to_string(), migrate_test_user("user_all"));
+    v11.users
+        .insert("user_subset".to_string(), migrate_test_user("user_subset"));
+    v11.nodes
+        .insert("node_1".to_string(), migrate_test_node("node_1"));
+    v11.endpoints
+        .insert("ss_1".to_string(), migrate_test_ss_endpoint_one());
+    v11.endpoints
+        .insert("ss_2".to_string(), migrate_test_ss_endpoint_two());
+    v11.node_user_endpoint_memberships = BTreeSet::from([
+        NodeUserEndpointMembership {
+            user_id: "user_all".to_string(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
+        },
+        NodeUserEndpointMembership {
+            user_id: "user_all".to_string(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            endpoint_id: xp_test_fixtures::label_ss2().to_owned(),
+        },
+        NodeUserEndpointMembership {
+            user_id: "user_subset".to_string(),
+            node_id: xp_test_fixtures::label_node1().to_owned(),
+            endpoint_id: xp_test_fixtures::label_ss1().to_owned(),
+        },
+    ]);
+
+    let v12 = migrate_v11_to_v12(v11).unwrap();
+
+    assert_eq!(v12.schema_version, SCHEMA_VERSION_V12);
+    assert_eq!(
+        v12.user_auto_assign_endpoint_kinds.get("user_all"),
+        Some(&BTreeSet::from([EndpointKind::Ss2022_2022Blake3Aes128Gcm]))
+    );
+    assert!(
+        !v12.user_auto_assign_endpoint_kinds
+            .contains_key("user_subset")
+    );
+}
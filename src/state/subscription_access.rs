@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+use super::{JsonSnapshotStore, StoreError};
+use crate::subscription_access::{SubscriptionAccessEntry, subscription_access_record_key};
+
+fn read_error(what: &str, error: impl std::fmt::Display) -> StoreError {
+    StoreError::HistoryStorage {
+        message: format!("read {what}: {error}"),
+    }
+}
+
+/// Keys local rows the same way as their repository records, so both views can be merged.
+fn keyed_rows(rows: Vec<(u64, Vec<u8>)>) -> Vec<(String, SubscriptionAccessEntry)> {
+    rows.into_iter()
+        .filter_map(|(access_id, payload)| {
+            let entry = serde_json::from_slice::<SubscriptionAccessEntry>(&payload).ok()?;
+            let key = subscription_access_record_key(&entry.user_id, &entry.node_id, access_id);
+            Some((key, entry))
+        })
+        .collect()
+}
+
+impl JsonSnapshotStore {
+    /// Failures are logged rather than returned: a subscription fetch must not fail over its log.
+    pub fn record_subscription_access(&self, entry: &SubscriptionAccessEntry) {
+        let accessed_at = DateTime::parse_from_rfc3339(&entry.accessed_at)
+            .map(|at| at.timestamp())
+            .unwrap_or_default();
+        if let Err(error) = self
+            .history_storage
+            .append_subscription_access(entry, accessed_at)
+        {
+            warn!(user_id = entry.user_id, %error, "record subscription access failed");
+        }
+    }
+
+    /// Fetches served by this node, newest first, keyed by their repository record key.
+    pub(crate) fn local_subscription_access(
+        &self,
+        user_id: Option<&str>,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<(String, SubscriptionAccessEntry)>, StoreError> {
+        let rows = self
+            .history_storage
+            .subscription_access_entries(user_id, since.timestamp(), limit)
+            .map_err(|error| read_error("subscription access log", error))?;
+        Ok(keyed_rows(rows))
+    }
+
+    /// Fetches from every node that reached this node's history repository replica.
+    pub(crate) fn repository_subscription_access(
+        &self,
+        user_id: Option<&str>,
+        since: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<(String, SubscriptionAccessEntry)>, StoreError> {
+        let rows = self
+            .history_storage
+            .repository_subscription_access_records(user_id, since.timestamp(), limit)
+            .map_err(|error| read_error("repository subscription access", error))?;
+        // Compacted buckets past the raw window no longer carry individual fetches.
+        Ok(rows
+            .into_iter()
+            .filter_map(|(key, payload)| {
+                Some((
+                    String::from_utf8(key).ok()?,
+                    serde_json::from_slice(&payload).ok()?,
+                ))
+            })
+            .collect())
+    }
+
+    pub(crate) fn unpublished_subscription_access(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<(u64, SubscriptionAccessEntry)>, StoreError> {
+        let rows = self
+            .history_storage
+            .unpublished_subscription_access(limit)
+            .map_err(|error| read_error("subscription access log", error))?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(access_id, payload)| {
+                Some((access_id, serde_json::from_slice(&payload).ok()?))
+            })
+            .collect())
+    }
+
+    pub(crate) fn mark_subscription_access_published(
+        &self,
+        access_ids: &[u64],
+    ) -> Result<(), StoreError> {
+        self.history_storage
+            .mark_subscription_access_published(access_ids)
+            .map_err(|error| StoreError::HistoryStorage {
+                message: format!("mark subscription access published: {error}"),
+            })
+    }
+}
@@ -0,0 +1,96 @@
+use super::{DesiredStateApplyResult, DesiredStateCommand, PersistedState, StoreError};
+use crate::domain::{DomainError, SUBSCRIPTION_TOKEN_FORMATS, SubscriptionToken};
+
+/// Applies the secondary subscription token commands; `None` for any other command.
+pub(super) fn apply_command(
+    state: &mut PersistedState,
+    command: &DesiredStateCommand,
+) -> Option<Result<DesiredStateApplyResult, StoreError>> {
+    let result = match command {
+        DesiredStateCommand::CreateSubscriptionToken { token } => {
+            validate_subscription_token(state, token).map(|()| {
+                state
+                    .subscription_tokens
+                    .insert(token.token_id.clone(), token.clone());
+            })
+        }
+        DesiredStateCommand::RevokeSubscriptionToken { token_id } => state
+            .subscription_tokens
+            .remove(token_id)
+            .map(|_| ())
+            .ok_or_else(|| DomainError::SubscriptionTokenNotFound {
+                token_id: token_id.clone(),
+            }),
+        _ => return None,
+    };
+    Some(
+        result
+            .map(|()| DesiredStateApplyResult::Applied)
+            .map_err(StoreError::from),
+    )
+}
+
+fn validate_subscription_token(
+    state: &PersistedState,
+    token: &SubscriptionToken,
+) -> Result<(), DomainError> {
+    let invalid = |reason: String| Err(DomainError::InvalidSubscriptionToken { reason });
+    if !state.users.contains_key(&token.user_id) {
+        return Err(DomainError::MissingUser {
+            user_id: token.user_id.clone(),
+        });
+    }
+    if token.name.trim().is_empty() {
+        return invalid("name must not be empty".to_string());
+    }
+    if token.token.trim().is_empty() {
+        return invalid("token must not be empty".to_string());
+    }
+    if state
+        .users
+        .values()
+        .any(|user| user.subscription_token == token.token)
+        || state
+            .subscription_tokens
+            .values()
+            .any(|existing| existing.token == token.token)
+    {
+        return invalid("token is already in use".to_string());
+    }
+    if state
+        .subscription_tokens
+        .values()
+        .any(|existing| existing.user_id == token.user_id && existing.name == token.name)
+    {
+        return invalid(format!("user already has a token named {}", token.name));
+    }
+    if let Some(expires_at) = token.expires_at.as_deref()
+        && chrono::DateTime::parse_from_rfc3339(expires_at).is_err()
+    {
+        return invalid(format!("expires_at must be RFC3339: {expires_at}"));
+    }
+    if let Some(format) = token.format.as_deref()
+        && !SUBSCRIPTION_TOKEN_FORMATS.contains(&format)
+    {
+        return invalid(format!(
+            "format must be one of {}: {format}",
+            SUBSCRIPTION_TOKEN_FORMATS.join("|")
+        ));
+    }
+    if let Some(endpoint_ids) = token.endpoint_ids.as_ref() {
+        if endpoint_ids.is_empty() {
+            return invalid(
+                "endpoint_ids must not be empty; omit it for all endpoints".to_string(),
+            );
+        }
+        if let Some(endpoint_id) = endpoint_ids
+            .iter()
+            .find(|endpoint_id| !state.endpoints.contains_key(*endpoint_id))
+        {
+            return Err(DomainError::MissingEndpoint {
+                endpoint_id: endpoint_id.clone(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -47,6 +47,10 @@ fn test_user(user_id: &str) -> User {
         credential_epoch: 0,
         priority_tier: UserPriorityTier::P2,
         quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     }
 }
 
@@ -194,6 +198,7 @@ fn probe_state_with_stale_deleted_node() -> PersistedState {
             target_id: None,
             target_url: None,
             error: None,
+            targets: Vec::new(),
             config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
         },
     );
@@ -207,6 +212,7 @@ fn probe_state_with_stale_deleted_node() -> PersistedState {
             target_id: None,
             target_url: None,
             error: None,
+            targets: Vec::new(),
             config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
         },
     );
@@ -403,6 +409,10 @@ fn load_or_init_recovers_when_state_is_v10_but_usage_is_v1() {
             credential_epoch: 0,
             priority_tier: UserPriorityTier::P2,
             quota_reset: UserQuotaReset::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
         },
     );
     state
@@ -1268,150 +1278,89 @@ fn save_load_roundtrip_persists_entities() {
 }
 
 #[test]
-fn validation_rejects_invalid_cycle_day_of_month() {
-    assert!(validate_cycle_day_of_month(0).is_err());
-    assert!(validate_cycle_day_of_month(32).is_err());
-    assert!(validate_cycle_day_of_month(1).is_ok());
-    assert!(validate_cycle_day_of_month(31).is_ok());
-}
-
-#[test]
-fn validation_rejects_invalid_port() {
-    assert!(validate_port(0).is_err());
-    assert!(validate_port(1).is_ok());
-    assert!(validate_port(65535).is_ok());
-}
-
-#[test]
-fn load_usage_json_missing_quota_fields_is_backward_compatible() {
-    let tmp = tempfile::tempdir().unwrap();
-    fs::create_dir_all(tmp.path()).unwrap();
-
-    let membership = {
-        let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-        let user = store.create_user("alice".to_string(), None).unwrap();
-        let endpoint = store
-            .create_endpoint(
-                store.list_nodes()[0].node_id.clone(),
-                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                443,
-                json!({}),
-            )
-            .unwrap();
-        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
-        DesiredStateCommand::ReplaceUserAccess {
-            user_id: user.user_id,
-            endpoint_ids: vec![endpoint.endpoint_id],
-        }
-        .apply(store.state_mut())
-        .unwrap();
-        store.save().unwrap();
-        membership
-    };
-    let bytes = serde_json::to_vec_pretty(&json!({
-        "schema_version": USAGE_SCHEMA_VERSION,
-        "memberships": {
-            membership.clone(): {
-                "cycle_start_at": "2025-12-01T00:00:00Z",
-                "cycle_end_at": "2026-01-01T00:00:00Z",
-                "used_bytes": 123,
-                "last_uplink_total": 100,
-                "last_downlink_total": 23,
-                "last_seen_at": "2025-12-18T00:00:00Z"
-            }
-        }
-    }))
-    .unwrap();
-    write_sqlite_snapshot(tmp.path(), crate::state::history_storage::USAGE_KEY, &bytes);
-
-    let store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-    let usage = store.get_membership_usage(&membership).unwrap();
-    assert!(!usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, None);
-}
-
-#[test]
-fn set_and_clear_quota_banned_persists_and_survives_reload() {
-    let tmp = tempfile::tempdir().unwrap();
-    let banned_at = "2025-12-18T00:00:00Z".to_string();
-    let membership = {
-        let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-        let user = store.create_user("alice".to_string(), None).unwrap();
-        let endpoint = store
-            .create_endpoint(
-                store.list_nodes()[0].node_id.clone(),
-                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-                443,
-                json!({}),
-            )
-            .unwrap();
-        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
-        DesiredStateCommand::ReplaceUserAccess {
-            user_id: user.user_id,
-            endpoint_ids: vec![endpoint.endpoint_id],
-        }
-        .apply(store.state_mut())
+fn subscription_tokens_are_unique_and_dropped_with_their_user() {
+    let mut state = PersistedState::empty();
+    let user = test_user("user_1");
+    let primary_token = user.subscription_token.clone();
+    DesiredStateCommand::UpsertUser { user }
+        .apply(&mut state)
         .unwrap();
-        store.save().unwrap();
-        membership
+    let token = crate::domain::SubscriptionToken {
+        token_id: "token_1".to_string(),
+        user_id: "user_1".to_string(),
+        name: "phone".to_string(),
+        token: "sub_phone".to_string(),
+        created_at: "2026-10-01T00:00:00Z".to_string(),
+        expires_at: Some("2026-11-01T00:00:00Z".to_string()),
+        format: Some("clash".to_string()),
+        endpoint_ids: None,
     };
 
-    let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-    store
-        .set_quota_banned(&membership, banned_at.clone())
-        .unwrap();
-    let usage = store.get_membership_usage(&membership).unwrap();
-    assert!(usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, Some(banned_at.clone()));
-
-    store.clear_quota_banned(&membership).unwrap();
-    let usage = store.get_membership_usage(&membership).unwrap();
-    assert!(!usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, None);
-
-    drop(store);
+    DesiredStateCommand::CreateSubscriptionToken {
+        token: token.clone(),
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert_eq!(state.subscription_tokens.get("token_1"), Some(&token));
+
+    for invalid in [
+        crate::domain::SubscriptionToken {
+            token_id: "token_2".to_string(),
+            token: primary_token,
+            name: "laptop".to_string(),
+            ..token.clone()
+        },
+        crate::domain::SubscriptionToken {
+            token_id: "token_2".to_string(),
+            token: "sub_laptop".to_string(),
+            ..token.clone()
+        },
+        crate::domain::SubscriptionToken {
+            token_id: "token_2".to_string(),
+            token: "sub_laptop".to_string(),
+            name: "laptop".to_string(),
+            format: Some("yaml".to_string()),
+            ..token.clone()
+        },
+        crate::domain::SubscriptionToken {
+            token_id: "token_2".to_string(),
+            token: "sub_laptop".to_string(),
+            name: "laptop".to_string(),
+            endpoint_ids: Some(BTreeSet::from(["missing".to_string()])),
+            ..token.clone()
+        },
+    ] {
+        let err = DesiredStateCommand::CreateSubscriptionToken { token: invalid }
+            .apply(&mut state)
+            .unwrap_err();
+        let StoreError::Domain(err) = err else {
+            panic!("expected domain error, got {err:?}");
+        };
+        assert_eq!(err.code(), "invalid_request");
+    }
 
-    let store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-    let usage = store.get_membership_usage(&membership).unwrap();
-    assert!(!usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, None);
+    DesiredStateCommand::DeleteUser {
+        user_id: "user_1".to_string(),
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert!(state.subscription_tokens.is_empty());
+    let err = DesiredStateCommand::RevokeSubscriptionToken {
+        token_id: "token_1".to_string(),
+    }
+    .apply(&mut state)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::Domain(DomainError::SubscriptionTokenNotFound { .. })
+    ));
 }
 
 #[test]
-fn apply_membership_usage_sample_keeps_quota_markers_on_cycle_change() {
-    let tmp = tempfile::tempdir().unwrap();
-    let membership_key = "user_1::endpoint_1";
-    let banned_at = "2025-12-18T00:00:00Z".to_string();
-
-    let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
-    store
-        .apply_membership_usage_sample(
-            membership_key,
-            "2025-12-01T00:00:00Z".to_string(),
-            "2026-01-01T00:00:00Z".to_string(),
-            10,
-            20,
-            "2025-12-18T00:00:00Z".to_string(),
-        )
-        .unwrap();
-    store
-        .set_quota_banned(membership_key, banned_at.clone())
-        .unwrap();
-
-    store
-        .apply_membership_usage_sample(
-            membership_key,
-            "2026-01-01T00:00:00Z".to_string(),
-            "2026-02-01T00:00:00Z".to_string(),
-            0,
-            0,
-            "2026-01-01T00:00:00Z".to_string(),
-        )
-        .unwrap();
-
-    let usage = store.get_membership_usage(membership_key).unwrap();
-    assert!(usage.quota_banned);
-    assert_eq!(usage.quota_banned_at, Some(banned_at));
+fn validation_rejects_invalid_port() {
+    assert!(validate_port(0).is_err());
+    assert!(validate_port(1).is_ok());
+    assert!(validate_port(65535).is_ok());
 }
 
 #[test]
@@ -1676,6 +1625,10 @@ fn desired_state_apply_user_create_reset_token_and_delete_are_deterministic() {
         credential_epoch: 0,
         priority_tier: Default::default(),
         quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     };
 
     DesiredStateCommand::UpsertUser { user: user.clone() }
@@ -1787,6 +1740,10 @@ fn desired_state_apply_ensure_membership_is_idempotent() {
             credential_epoch: 0,
             priority_tier: Default::default(),
             quota_reset: UserQuotaReset::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
         },
     );
     state.endpoints.insert(
@@ -1839,6 +1796,64 @@ fn desired_state_apply_bump_user_credential_epoch_increments_and_returns_epoch()
     assert_eq!(credential_epoch, 1);
     assert_eq!(store.get_user(&user.user_id).unwrap().credential_epoch, 1);
 }
+
+#[test]
+fn desired_state_apply_pinned_credentials_are_validated_unique_and_cleared_by_epoch_bump() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+    let alice = store.create_user("alice".to_string(), None).unwrap();
+    let bob = store.create_user("bob".to_string(), None).unwrap();
+    let uuid = "5f6e4d3c-2b1a-4098-8765-43210fedcba9";
+
+    let pin = |user: &User, vless_uuid: &str, psk: &str| DesiredStateCommand::UpsertUser {
+        user: User {
+            pinned_credentials: crate::domain::UserPinnedCredentials {
+                vless_uuid: Some(vless_uuid.to_string()),
+                ss2022_psk_b64: Some(psk.to_string()),
+            },
+            ..user.clone()
+        },
+    };
+    let err = pin(
+        &alice,
+        &uuid.to_ascii_uppercase(),
+        "AAECAwQFBgcICQoLDA0ODw==",
+    )
+    .apply(store.state_mut())
+    .unwrap_err();
+    assert!(
+        err.to_string().contains("invalid pinned credentials"),
+        "{err}"
+    );
+    let err = pin(&alice, uuid, "AAEC")
+        .apply(store.state_mut())
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("invalid pinned credentials"),
+        "{err}"
+    );
+
+    pin(&alice, uuid, "AAECAwQFBgcICQoLDA0ODw==")
+        .apply(store.state_mut())
+        .unwrap();
+    let err = pin(&bob, uuid, "AAECAwQFBgcICQoLDA0ODw==")
+        .apply(store.state_mut())
+        .unwrap_err();
+    assert!(err.to_string().contains("already pinned"), "{err}");
+
+    DesiredStateCommand::BumpUserCredentialEpoch {
+        user_id: alice.user_id.clone(),
+    }
+    .apply(store.state_mut())
+    .unwrap();
+    assert!(
+        store
+            .get_user(&alice.user_id)
+            .unwrap()
+            .pinned_credentials
+            .is_empty()
+    );
+}
 #[test]
 fn load_or_init_migrates_v10_geo_db_settings_defaults() {
     let tmp = tempfile::tempdir().unwrap();
@@ -1958,6 +1973,7 @@ fn desired_state_apply_append_endpoint_probe_samples_prunes_participants_and_his
         target_id: None,
         target_url: None,
         error: None,
+        targets: Vec::new(),
         config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
     };
     let commands = [
@@ -2168,6 +2184,7 @@ fn endpoint_probe_participants_for_hour_unions_participant_map_and_legacy_sample
                 target_id: None,
                 target_url: None,
                 error: None,
+                targets: Vec::new(),
                 config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
             },
         );
@@ -2190,6 +2207,7 @@ fn endpoint_probe_participants_for_hour_unions_participant_map_and_legacy_sample
                 target_id: None,
                 target_url: None,
                 error: Some("dial failed".to_string()),
+                targets: Vec::new(),
                 config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
             },
         );
@@ -2262,6 +2280,7 @@ fn desired_state_apply_delete_node_removes_probe_participation_for_removed_node(
                 target_id: None,
                 target_url: None,
                 error: None,
+                targets: Vec::new(),
                 config_hash: xp_test_fixtures::primary_probe_config_hash().to_owned(),
             },
         );
@@ -2430,5 +2449,8 @@ fn desired_state_apply_set_geo_db_update_settings_is_noop() {
     assert_eq!(state, before);
 }
 
+mod audit;
 mod endpoint_meta;
+mod quota;
 mod reverse_assignment;
+mod user_lifecycle;
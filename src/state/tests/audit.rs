@@ -0,0 +1,123 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+use super::super::audit::{audit_snapshot, audited_sections};
+use crate::audit::{AuditContext, AuditPrincipal, build_entry, diff_snapshot};
+
+fn state_with_user() -> (PersistedState, String) {
+    let mut state = PersistedState::empty();
+    let user = User {
+        user_id: xp_test_fixtures::primary_user_id().to_owned(),
+        display_name: "alice".to_string(),
+        subscription_token: xp_test_fixtures::primary_token().to_owned(),
+        credential_epoch: 0,
+        priority_tier: UserPriorityTier::P2,
+        quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
+    };
+    let user_id = user.user_id.clone();
+    DesiredStateCommand::UpsertUser { user }
+        .apply(&mut state)
+        .unwrap();
+    (state, user_id)
+}
+
+/// Applies `command` and returns the changes recorded from the scoped and the full snapshot.
+fn scoped_and_full_changes(
+    state: &mut PersistedState,
+    command: DesiredStateCommand,
+) -> (
+    Vec<crate::audit::AuditChange>,
+    Vec<crate::audit::AuditChange>,
+) {
+    let context = AuditContext {
+        principal: AuditPrincipal::AdminToken,
+        origin_node_id: xp_test_fixtures::identifier_ulid_d().to_string(),
+        requested_at: "2026-01-01T00:00:00Z".to_string(),
+    };
+    let scoped_before = audit_snapshot(state, &command);
+    let full_before = diff_snapshot(state);
+    command.apply(state).unwrap();
+    let scoped = build_entry(
+        1,
+        String::new(),
+        context.clone(),
+        &command,
+        &scoped_before,
+        &audit_snapshot(state, &command),
+    );
+    let full = build_entry(
+        1,
+        String::new(),
+        context,
+        &command,
+        &full_before,
+        &diff_snapshot(state),
+    );
+    (scoped.changes, full.changes)
+}
+
+#[test]
+fn scoped_audit_snapshots_record_the_same_changes_as_the_full_state() {
+    let (mut state, user_id) = state_with_user();
+
+    let commands = [
+        DesiredStateCommand::SetUserDisabled {
+            user_id: user_id.clone(),
+            disabled: true,
+        },
+        DesiredStateCommand::SetUserGlobalWeight {
+            user_id: user_id.clone(),
+            weight: 7,
+        },
+        DesiredStateCommand::SetMihomoDeliveryMode {
+            mode: MihomoDeliveryMode::Provider,
+        },
+    ];
+    for command in commands {
+        assert!(audited_sections(&command).is_some());
+        let (scoped, full) = scoped_and_full_changes(&mut state, command);
+        assert!(!scoped.is_empty());
+        assert_eq!(scoped, full);
+    }
+}
+
+#[test]
+fn commands_with_wide_effects_are_diffed_over_the_full_state() {
+    let (mut state, user_id) = state_with_user();
+    let command = DesiredStateCommand::DeleteUser {
+        user_id: user_id.clone(),
+    };
+    assert_eq!(audited_sections(&command), None);
+
+    let (scoped, full) = scoped_and_full_changes(&mut state, command);
+    assert_eq!(scoped, full);
+    assert_eq!(scoped[0].path, format!("users.{user_id}"));
+}
+
+#[test]
+fn audit_snapshots_redact_telegram_bot_tokens() {
+    let mut state = PersistedState::empty();
+    state.notification_webhooks = vec![crate::notify::NotificationWebhook {
+        name: "tg".to_string(),
+        url: "https://api.telegram.org".to_string(),
+        format: crate::notify::WebhookFormat::Telegram,
+        telegram_chat_id: Some("-100123".to_string()),
+        telegram_bot_token: Some("123:abc".to_string()),
+        secret: None,
+        events: Default::default(),
+        disabled: false,
+    }];
+    let command = DesiredStateCommand::SetNotificationWebhooks {
+        webhooks: Vec::new(),
+    };
+
+    let (changes, _) = scoped_and_full_changes(&mut state, command);
+    let recorded = serde_json::to_string(&changes).unwrap();
+    assert!(!recorded.contains("123:abc"), "{recorded}");
+    assert!(recorded.contains("[redacted]"), "{recorded}");
+}
@@ -121,3 +121,19 @@ fn conditional_upsert_rejects_stale_snapshot_and_uses_legacy_command_shape() {
     ));
     assert_eq!(state.endpoints.get(&updated.endpoint_id), Some(&updated));
 }
+
+#[test]
+fn tls_endpoint_server_names_report_a_generic_server_name_error() {
+    for kind in [EndpointKind::Hysteria2, EndpointKind::TrojanTlsTcp] {
+        let err =
+            build_endpoint_meta(&kind, serde_json::json!({ "server_name": "no-dot" })).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                StoreError::Domain(DomainError::InvalidServerName { ref server_name, .. })
+                    if server_name == "no-dot"
+            ),
+            "{kind:?}: {err:?}"
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+#[test]
+fn validation_rejects_invalid_cycle_day_of_month() {
+    assert!(validate_cycle_day_of_month(0).is_err());
+    assert!(validate_cycle_day_of_month(32).is_err());
+    assert!(validate_cycle_day_of_month(1).is_ok());
+    assert!(validate_cycle_day_of_month(31).is_ok());
+}
+
+#[test]
+fn quota_reset_accepts_weekly_daily_and_fixed_days_cycles() {
+    let mut state = PersistedState::empty();
+    let mut user = test_user("user_1");
+    user.quota_reset = serde_json::from_value(json!({
+        "policy": "fixed_days",
+        "anchor": "2026-01-15",
+        "length_days": 30,
+        "tz_offset_minutes": 480
+    }))
+    .unwrap();
+    DesiredStateCommand::UpsertUser { user: user.clone() }
+        .apply(&mut state)
+        .unwrap();
+
+    user.quota_reset = UserQuotaReset::FixedDays {
+        anchor: chrono::NaiveDate::from_ymd_opt(2026, 1, 15).unwrap(),
+        length_days: 0,
+        tz_offset_minutes: 480,
+    };
+    let err = DesiredStateCommand::UpsertUser { user }
+        .apply(&mut state)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::Domain(DomainError::InvalidCycleLengthDays { length_days: 0 })
+    ));
+
+    let mut node = test_node("node_1");
+    node.quota_limit_bytes = 1024;
+    node.quota_reset = serde_json::from_value(json!({
+        "policy": "weekly",
+        "weekday": "monday"
+    }))
+    .unwrap();
+    validate_node_quota_reset(&node.quota_reset).unwrap();
+    validate_node_quota_config(&node).unwrap();
+    node.quota_reset = NodeQuotaReset::Daily {
+        tz_offset_minutes: Some(0),
+    };
+    validate_node_quota_config(&node).unwrap();
+    node.quota_reset = NodeQuotaReset::Unlimited {
+        tz_offset_minutes: None,
+    };
+    assert!(validate_node_quota_config(&node).is_err());
+}
+
+#[test]
+fn traffic_topups_are_validated_and_dropped_with_their_user() {
+    let mut state = PersistedState::empty();
+    let node = test_node("node_1");
+    state.nodes.insert(node.node_id.clone(), node.clone());
+    DesiredStateCommand::UpsertUser {
+        user: test_user("user_1"),
+    }
+    .apply(&mut state)
+    .unwrap();
+    let topup = crate::domain::TrafficTopUp {
+        topup_id: "topup_1".to_string(),
+        user_id: "user_1".to_string(),
+        node_id: node.node_id.clone(),
+        bytes: 1024,
+        created_at: "2026-10-01T00:00:00Z".to_string(),
+        expires_at: Some("2026-11-01T00:00:00Z".to_string()),
+        note: None,
+    };
+
+    for invalid in [
+        crate::domain::TrafficTopUp {
+            bytes: 0,
+            ..topup.clone()
+        },
+        crate::domain::TrafficTopUp {
+            expires_at: Some("next month".to_string()),
+            ..topup.clone()
+        },
+        crate::domain::TrafficTopUp {
+            node_id: "missing".to_string(),
+            ..topup.clone()
+        },
+    ] {
+        let err = DesiredStateCommand::CreateTrafficTopUp { topup: invalid }
+            .apply(&mut state)
+            .unwrap_err();
+        let StoreError::Domain(err) = err else {
+            panic!("expected domain error, got {err:?}");
+        };
+        assert_eq!(err.code(), "invalid_request");
+    }
+
+    DesiredStateCommand::CreateTrafficTopUp {
+        topup: topup.clone(),
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert_eq!(state.traffic_topups.get("topup_1"), Some(&topup));
+
+    DesiredStateCommand::DeleteUser {
+        user_id: "user_1".to_string(),
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert!(state.traffic_topups.is_empty());
+    let err = DesiredStateCommand::DeleteTrafficTopUp {
+        topup_id: "topup_1".to_string(),
+    }
+    .apply(&mut state)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::Domain(DomainError::TrafficTopUpNotFound { .. })
+    ));
+}
+
+#[test]
+fn load_usage_json_missing_quota_fields_is_backward_compatible() {
+    let tmp = tempfile::tempdir().unwrap();
+    fs::create_dir_all(tmp.path()).unwrap();
+
+    let membership = {
+        let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                store.list_nodes()[0].node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                443,
+                json!({}),
+            )
+            .unwrap();
+        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id,
+            endpoint_ids: vec![endpoint.endpoint_id],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        membership
+    };
+    let bytes = serde_json::to_vec_pretty(&json!({
+        "schema_version": USAGE_SCHEMA_VERSION,
+        "memberships": {
+            membership.clone(): {
+                "cycle_start_at": "2025-12-01T00:00:00Z",
+                "cycle_end_at": "2026-01-01T00:00:00Z",
+                "used_bytes": 123,
+                "last_uplink_total": 100,
+                "last_downlink_total": 23,
+                "last_seen_at": "2025-12-18T00:00:00Z"
+            }
+        }
+    }))
+    .unwrap();
+    write_sqlite_snapshot(tmp.path(), crate::state::history_storage::USAGE_KEY, &bytes);
+
+    let store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+    let usage = store.get_membership_usage(&membership).unwrap();
+    assert!(!usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, None);
+}
+
+#[test]
+fn set_and_clear_quota_banned_persists_and_survives_reload() {
+    let tmp = tempfile::tempdir().unwrap();
+    let banned_at = "2025-12-18T00:00:00Z".to_string();
+    let membership = {
+        let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+        let user = store.create_user("alice".to_string(), None).unwrap();
+        let endpoint = store
+            .create_endpoint(
+                store.list_nodes()[0].node_id.clone(),
+                EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+                443,
+                json!({}),
+            )
+            .unwrap();
+        let membership = membership_key(&user.user_id, &endpoint.endpoint_id);
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user.user_id,
+            endpoint_ids: vec![endpoint.endpoint_id],
+        }
+        .apply(store.state_mut())
+        .unwrap();
+        store.save().unwrap();
+        membership
+    };
+
+    let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+    store
+        .set_quota_banned(&membership, banned_at.clone())
+        .unwrap();
+    let usage = store.get_membership_usage(&membership).unwrap();
+    assert!(usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, Some(banned_at.clone()));
+
+    store.clear_quota_banned(&membership).unwrap();
+    let usage = store.get_membership_usage(&membership).unwrap();
+    assert!(!usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, None);
+
+    drop(store);
+
+    let store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+    let usage = store.get_membership_usage(&membership).unwrap();
+    assert!(!usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, None);
+}
+
+#[test]
+fn apply_membership_usage_sample_keeps_quota_markers_on_cycle_change() {
+    let tmp = tempfile::tempdir().unwrap();
+    let membership_key = "user_1::endpoint_1";
+    let banned_at = "2025-12-18T00:00:00Z".to_string();
+
+    let mut store = JsonSnapshotStore::load_or_init(test_init(tmp.path())).unwrap();
+    store
+        .apply_membership_usage_sample(
+            membership_key,
+            "2025-12-01T00:00:00Z".to_string(),
+            "2026-01-01T00:00:00Z".to_string(),
+            10,
+            20,
+            "2025-12-18T00:00:00Z".to_string(),
+        )
+        .unwrap();
+    store
+        .set_quota_banned(membership_key, banned_at.clone())
+        .unwrap();
+
+    store
+        .apply_membership_usage_sample(
+            membership_key,
+            "2026-01-01T00:00:00Z".to_string(),
+            "2026-02-01T00:00:00Z".to_string(),
+            0,
+            0,
+            "2026-01-01T00:00:00Z".to_string(),
+        )
+        .unwrap();
+
+    let usage = store.get_membership_usage(membership_key).unwrap();
+    assert!(usage.quota_banned);
+    assert_eq!(usage.quota_banned_at, Some(banned_at));
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+
+fn state_with_user() -> (PersistedState, String) {
+    let mut state = PersistedState::empty();
+    let user = User {
+        user_id: xp_test_fixtures::primary_user_id().to_owned(),
+        display_name: "alice".to_string(),
+        subscription_token: xp_test_fixtures::primary_token().to_owned(),
+        credential_epoch: 3,
+        priority_tier: UserPriorityTier::P2,
+        quota_reset: UserQuotaReset::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
+    };
+    let user_id = user.user_id.clone();
+    DesiredStateCommand::UpsertUser { user }
+        .apply(&mut state)
+        .unwrap();
+    (state, user_id)
+}
+
+#[test]
+fn set_user_expiry_normalizes_to_utc_and_can_be_cleared() {
+    let (mut state, user_id) = state_with_user();
+
+    DesiredStateCommand::SetUserExpiry {
+        user_id: user_id.clone(),
+        expires_at: Some("2030-01-01T08:00:00+08:00".to_string()),
+    }
+    .apply(&mut state)
+    .unwrap();
+    let user = &state.users[&user_id];
+    assert_eq!(user.expires_at.as_deref(), Some("2030-01-01T00:00:00Z"));
+    let before = chrono::DateTime::parse_from_rfc3339("2029-12-31T23:59:59Z").unwrap();
+    let after = chrono::DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z").unwrap();
+    assert!(user.is_active_at(before.with_timezone(&chrono::Utc)));
+    assert!(!user.is_active_at(after.with_timezone(&chrono::Utc)));
+
+    DesiredStateCommand::SetUserExpiry {
+        user_id: user_id.clone(),
+        expires_at: None,
+    }
+    .apply(&mut state)
+    .unwrap();
+    assert_eq!(state.users[&user_id].expires_at, None);
+}
+
+#[test]
+fn set_user_expiry_rejects_invalid_timestamp_and_missing_user() {
+    let (mut state, user_id) = state_with_user();
+
+    let err = DesiredStateCommand::SetUserExpiry {
+        user_id,
+        expires_at: Some("next tuesday".to_string()),
+    }
+    .apply(&mut state)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::Domain(DomainError::InvalidUserExpiresAt { .. })
+    ));
+
+    let err = DesiredStateCommand::SetUserDisabled {
+        user_id: "missing".to_string(),
+        disabled: true,
+    }
+    .apply(&mut state)
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        StoreError::Domain(DomainError::MissingUser { .. })
+    ));
+}
+
+#[test]
+fn set_user_disabled_keeps_credentials() {
+    let (mut state, user_id) = state_with_user();
+    let before = state.users[&user_id].clone();
+
+    for disabled in [true, false] {
+        DesiredStateCommand::SetUserDisabled {
+            user_id: user_id.clone(),
+            disabled,
+        }
+        .apply(&mut state)
+        .unwrap();
+        assert_eq!(state.users[&user_id].disabled, disabled);
+    }
+    assert_eq!(state.users[&user_id], before);
+}
+
+#[test]
+fn set_user_expiry_commands_roundtrip_through_compat_deserializer() {
+    let cmd = DesiredStateCommand::SetUserExpiry {
+        user_id: xp_test_fixtures::primary_user_id().to_owned(),
+        expires_at: Some("2030-01-01T00:00:00Z".to_string()),
+    };
+    let json = serde_json::to_value(&cmd).unwrap();
+    assert_eq!(json["type"], "set_user_expiry");
+    let decoded: DesiredStateCommand = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, cmd);
+}
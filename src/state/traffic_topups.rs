@@ -0,0 +1,56 @@
+use super::{DesiredStateApplyResult, DesiredStateCommand, PersistedState, StoreError};
+use crate::domain::{DomainError, TrafficTopUp};
+
+/// Applies the traffic top-up commands; `None` for any other command.
+pub(super) fn apply_command(
+    state: &mut PersistedState,
+    command: &DesiredStateCommand,
+) -> Option<Result<DesiredStateApplyResult, StoreError>> {
+    let result = match command {
+        DesiredStateCommand::CreateTrafficTopUp { topup } => validate_traffic_topup(state, topup)
+            .map(|()| {
+                state
+                    .traffic_topups
+                    .insert(topup.topup_id.clone(), topup.clone());
+            }),
+        DesiredStateCommand::DeleteTrafficTopUp { topup_id } => state
+            .traffic_topups
+            .remove(topup_id)
+            .map(|_| ())
+            .ok_or_else(|| DomainError::TrafficTopUpNotFound {
+                topup_id: topup_id.clone(),
+            }),
+        _ => return None,
+    };
+    Some(
+        result
+            .map(|()| DesiredStateApplyResult::Applied)
+            .map_err(StoreError::from),
+    )
+}
+
+fn validate_traffic_topup(state: &PersistedState, topup: &TrafficTopUp) -> Result<(), DomainError> {
+    if !state.users.contains_key(&topup.user_id) {
+        return Err(DomainError::MissingUser {
+            user_id: topup.user_id.clone(),
+        });
+    }
+    if !state.nodes.contains_key(&topup.node_id) {
+        return Err(DomainError::MissingNode {
+            node_id: topup.node_id.clone(),
+        });
+    }
+    if topup.bytes == 0 {
+        return Err(DomainError::InvalidTrafficTopUp {
+            reason: "bytes must be greater than 0".to_string(),
+        });
+    }
+    if let Some(expires_at) = topup.expires_at.as_deref()
+        && chrono::DateTime::parse_from_rfc3339(expires_at).is_err()
+    {
+        return Err(DomainError::InvalidTrafficTopUp {
+            reason: format!("expires_at must be RFC3339: {expires_at}"),
+        });
+    }
+    Ok(())
+}
@@ -8,19 +8,27 @@ use crate::{
     domain::{Endpoint, EndpointKind, Node, User},
     managed_default_endpoints::managed_default_vless_endpoint,
     protocol::{
-        MihomoSmuxConfig, SS2022_METHOD_2022_BLAKE3_AES_128_GCM, Ss2022EndpointMeta,
-        VLESS_XHTTP_PATH, VlessRealityTransport, ss2022_password,
+        MihomoSmuxConfig, Ss2022EndpointMeta, TrojanRealityTcpEndpointMeta, VLESS_XHTTP_PATH,
+        VlessRealityTransport, ss2022_password, ss2022_psk_len_bytes,
     },
     state::{
         NodeEgressProbeState, NodeSubscriptionRegion, NodeUserEndpointMembership, UserMihomoProfile,
     },
 };
 
+mod external_resources;
+use external_resources::rewrite_mihomo_external_resources;
+pub use external_resources::{mihomo_external_resource_urls, normalize_mihomo_external_url};
+
 mod clash_proxy;
+mod hysteria2;
+mod trojan_tls;
 use clash_proxy::{
-    ClashProxy, ClashRealityOpts, ClashSsProxy, ClashVlessProxy, mihomo_smux_config,
-    mihomo_vless_transport_config, mihomo_xhttp_share_extra_json,
+    ClashProxy, ClashRealityOpts, ClashSsProxy, ClashTrojanProxy, ClashVlessProxy,
+    mihomo_smux_config, mihomo_vless_transport_config, mihomo_xhttp_share_extra_json,
 };
+use hysteria2::{hysteria2_clash_proxy, hysteria2_endpoint_auth, hysteria2_raw_uri};
+use trojan_tls::{parse_trojan_tls_meta, trojan_tls_clash_proxy, trojan_tls_raw_uri};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SubscriptionError {
@@ -50,9 +58,20 @@ pub enum SubscriptionError {
         endpoint_id: String,
         reason: String,
     },
+    InvalidEndpointMetaTrojan {
+        endpoint_id: String,
+        reason: String,
+    },
+    InvalidEndpointMetaHysteria2 {
+        endpoint_id: String,
+        reason: String,
+    },
     YamlSerialize {
         reason: String,
     },
+    JsonSerialize {
+        reason: String,
+    },
     VlessRealityServerNamesEmpty {
         endpoint_id: String,
     },
@@ -151,7 +170,26 @@ impl std::fmt::Display for SubscriptionError {
                     "invalid vless endpoint meta: endpoint_id={endpoint_id}: {reason}"
                 )
             }
+            Self::InvalidEndpointMetaTrojan {
+                endpoint_id,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "invalid trojan endpoint meta: endpoint_id={endpoint_id}: {reason}"
+                )
+            }
+            Self::InvalidEndpointMetaHysteria2 {
+                endpoint_id,
+                reason,
+            } => {
+                write!(
+                    f,
+                    "invalid hysteria2 endpoint meta: endpoint_id={endpoint_id}: {reason}"
+                )
+            }
             Self::YamlSerialize { reason } => write!(f, "clash yaml serialize error: {reason}"),
+            Self::JsonSerialize { reason } => write!(f, "sing-box json serialize error: {reason}"),
             Self::VlessRealityServerNamesEmpty { endpoint_id } => write!(
                 f,
                 "vless reality server_names is empty: endpoint_id={endpoint_id}"
@@ -247,9 +285,84 @@ fn endpoint_kind_key(kind: &EndpointKind) -> &'static str {
     match kind {
         EndpointKind::VlessRealityVisionTcp => "vless_reality_vision_tcp",
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => "ss2022_2022_blake3_aes_128_gcm",
+        EndpointKind::TrojanRealityTcp => "trojan_reality_tcp",
+        EndpointKind::TrojanTlsTcp => "trojan_tls_tcp",
+        EndpointKind::Hysteria2 => "hysteria2",
     }
 }
 
+/// Parses SS2022 endpoint meta and returns it with the full `server:user` password for `user`.
+fn ss2022_endpoint_password(
+    cluster_ca_key_pem: &str,
+    user: &User,
+    endpoint: &Endpoint,
+) -> Result<(Ss2022EndpointMeta, String), SubscriptionError> {
+    let meta: Ss2022EndpointMeta = serde_json::from_value(endpoint.meta.clone()).map_err(|e| {
+        SubscriptionError::Ss2022UnsupportedMethod {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            got_method: format!("invalid endpoint meta: {e}"),
+        }
+    })?;
+    let Some(psk_len) = ss2022_psk_len_bytes(&meta.method) else {
+        return Err(SubscriptionError::Ss2022UnsupportedMethod {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            got_method: meta.method,
+        });
+    };
+    let user_psk_b64 = credentials::user_ss2022_psk_b64(cluster_ca_key_pem, user, psk_len)
+        .map_err(|e| SubscriptionError::CredentialDerive {
+            reason: e.to_string(),
+        })?;
+    let password = ss2022_password(&meta.server_psk_b64, &user_psk_b64);
+    Ok((meta, password))
+}
+
+fn parse_trojan_reality_meta<'a, R: RngCore + ?Sized>(
+    endpoint: &Endpoint,
+    meta: &'a TrojanRealityTcpEndpointMeta,
+    rng: &mut R,
+) -> Result<&'a str, SubscriptionError> {
+    let sni = pick_server_name(&meta.reality.server_names, rng).ok_or_else(|| {
+        SubscriptionError::InvalidEndpointMetaTrojan {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            reason: "reality server_names is empty".to_string(),
+        }
+    })?;
+    if meta.active_short_id.is_empty() {
+        return Err(SubscriptionError::InvalidEndpointMetaTrojan {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            reason: "missing active_short_id".to_string(),
+        });
+    }
+    Ok(sni)
+}
+
+fn trojan_clash_proxy(
+    name: String,
+    server: &str,
+    port: u16,
+    password: &str,
+    sni: &str,
+    meta: &TrojanRealityTcpEndpointMeta,
+) -> ClashProxy {
+    ClashProxy::Trojan(ClashTrojanProxy {
+        name,
+        proxy_type: "trojan".to_string(),
+        server: server.to_string(),
+        port,
+        password: password.to_string(),
+        udp: true,
+        sni: sni.to_string(),
+        client_fingerprint: meta.reality.fingerprint.clone(),
+        reality_opts: ClashRealityOpts {
+            public_key: meta.reality_keys.public_key.clone(),
+            short_id: meta.active_short_id.clone(),
+        },
+        smux: mihomo_smux_config(&meta.mihomo_smux),
+        dialer_proxy: None,
+    })
+}
+
 fn pick_server_name<'a, R: RngCore + ?Sized>(
     server_names: &'a [String],
     rng: &mut R,
@@ -312,6 +425,17 @@ pub fn build_clash_yaml(
     })
 }
 
+pub fn build_singbox_json(
+    cluster_ca_key_pem: &str,
+    user: &User,
+    memberships: &[NodeUserEndpointMembership],
+    endpoints: &[Endpoint],
+    nodes: &[Node],
+) -> Result<String, SubscriptionError> {
+    let items = build_items(cluster_ca_key_pem, user, memberships, endpoints, nodes)?;
+    singbox::build_config(items)
+}
+
 pub fn build_mihomo_yaml(
     cluster_ca_key_pem: &str,
     user: &User,
@@ -659,211 +783,6 @@ fn build_mihomo_provider_roots_with_node_probes(
     Ok((root, system_root))
 }
 
-fn rewrite_mihomo_external_resources(
-    root: &mut serde_yaml::Mapping,
-    mode: MihomoExternalResourceMode,
-    cluster_ca_key_pem: &str,
-    resource_mirror_base_url: &str,
-) -> Result<(), SubscriptionError> {
-    if mode == MihomoExternalResourceMode::Direct {
-        return Ok(());
-    }
-
-    let mut geox = match root.remove(serde_yaml::Value::String("geox-url".to_string())) {
-        None => serde_yaml::Mapping::new(),
-        Some(serde_yaml::Value::Mapping(map)) => map,
-        Some(_) => {
-            return Err(SubscriptionError::MihomoExternalResourceMirrorInvalid {
-                kind: "geox-url".to_string(),
-                name: "geox-url".to_string(),
-                reason: "must be a mapping",
-            });
-        }
-    };
-
-    for (key, value) in geox.iter_mut() {
-        let Some(name) = key.as_str() else {
-            continue;
-        };
-        let Some(url) = value.as_str() else {
-            return Err(SubscriptionError::MihomoExternalResourceMirrorInvalid {
-                kind: "geox-url".to_string(),
-                name: name.to_string(),
-                reason: "URL must be a string",
-            });
-        };
-        *value = serde_yaml::Value::String(build_mihomo_mirror_url(
-            cluster_ca_key_pem,
-            resource_mirror_base_url,
-            "geox-url",
-            name,
-            url,
-        )?);
-    }
-    for (name, url) in crate::mihomo_resources::fixed_geox_assets() {
-        geox.entry(serde_yaml::Value::String((*name).to_string()))
-            .or_insert_with(|| {
-                serde_yaml::Value::String(
-                    build_mihomo_mirror_url(
-                        cluster_ca_key_pem,
-                        resource_mirror_base_url,
-                        "geox-url",
-                        name,
-                        url,
-                    )
-                    .expect("fixed Mihomo GeoX URL must be mirrorable"),
-                )
-            });
-    }
-    root.insert(
-        serde_yaml::Value::String("geox-url".to_string()),
-        serde_yaml::Value::Mapping(geox),
-    );
-
-    for provider_kind in ["rule-providers", "proxy-providers"] {
-        let Some(serde_yaml::Value::Mapping(providers)) =
-            root.get_mut(serde_yaml::Value::String(provider_kind.to_string()))
-        else {
-            continue;
-        };
-        for (provider_name, provider) in providers.iter_mut() {
-            let Some(name) = provider_name.as_str() else {
-                continue;
-            };
-            if provider_kind == "proxy-providers" && name == MIHOMO_SYSTEM_PROVIDER_NAME {
-                continue;
-            }
-            let serde_yaml::Value::Mapping(provider_map) = provider else {
-                continue;
-            };
-            let Some(url_value) = provider_map.get(serde_yaml::Value::String("url".to_string()))
-            else {
-                continue;
-            };
-            let Some(url) = url_value.as_str().map(str::to_string) else {
-                return Err(SubscriptionError::MihomoExternalResourceMirrorInvalid {
-                    kind: provider_kind.to_string(),
-                    name: name.to_string(),
-                    reason: "URL must be a string",
-                });
-            };
-            if provider_map.contains_key(serde_yaml::Value::String("header".to_string()))
-                || provider_map.contains_key(serde_yaml::Value::String("headers".to_string()))
-            {
-                return Err(SubscriptionError::MihomoExternalResourceMirrorInvalid {
-                    kind: provider_kind.to_string(),
-                    name: name.to_string(),
-                    reason: "custom headers are not allowed",
-                });
-            }
-            let mirror_url = build_mihomo_mirror_url(
-                cluster_ca_key_pem,
-                resource_mirror_base_url,
-                provider_kind,
-                name,
-                &url,
-            )?;
-            provider_map.insert(
-                serde_yaml::Value::String("url".to_string()),
-                serde_yaml::Value::String(mirror_url),
-            );
-            provider_map.insert(
-                serde_yaml::Value::String("proxy".to_string()),
-                serde_yaml::Value::String("DIRECT".to_string()),
-            );
-        }
-    }
-
-    Ok(())
-}
-
-fn build_mihomo_mirror_url(
-    cluster_ca_key_pem: &str,
-    resource_mirror_base_url: &str,
-    kind: &str,
-    name: &str,
-    original_url: &str,
-) -> Result<String, SubscriptionError> {
-    let normalized = normalize_mihomo_external_url(original_url).ok_or_else(|| {
-        SubscriptionError::MihomoExternalResourceMirrorInvalid {
-            kind: kind.to_string(),
-            name: name.to_string(),
-            reason: "only HTTPS URLs without userinfo are allowed",
-        }
-    })?;
-    let resource_id = crate::mihomo_resources::resource_id(cluster_ca_key_pem, &normalized);
-    Ok(format!(
-        "{}/{}",
-        resource_mirror_base_url.trim_end_matches('/'),
-        resource_id
-    ))
-}
-
-pub fn normalize_mihomo_external_url(raw: &str) -> Option<Url> {
-    let mut url = Url::parse(raw.trim()).ok()?;
-    if url.scheme() != "https" || !url.username().is_empty() || url.password().is_some() {
-        return None;
-    }
-    if let Some(host) = url.host_str() {
-        let lower = host.to_ascii_lowercase();
-        if lower != host {
-            url.set_host(Some(&lower)).ok()?;
-        }
-    }
-    url.set_fragment(None);
-    Some(url)
-}
-
-pub fn mihomo_external_resource_urls(
-    profile: &UserMihomoProfile,
-) -> Result<Vec<Url>, SubscriptionError> {
-    let mut urls = Vec::new();
-    let root = parse_mixin_mapping(&profile.mixin_yaml)?;
-    collect_mihomo_mapping_urls(&root, "geox-url", &mut urls);
-    collect_mihomo_provider_urls(&root, "rule-providers", &mut urls);
-    collect_mihomo_provider_urls(&root, "proxy-providers", &mut urls);
-    let extra_providers = parse_extra_proxy_providers_yaml(&profile.extra_proxy_providers_yaml)?;
-    collect_urls_from_provider_map(&extra_providers, &mut urls);
-    Ok(urls)
-}
-
-fn collect_mihomo_mapping_urls(root: &serde_yaml::Mapping, key: &str, urls: &mut Vec<Url>) {
-    let Some(serde_yaml::Value::Mapping(map)) =
-        root.get(serde_yaml::Value::String(key.to_string()))
-    else {
-        return;
-    };
-    for value in map.values() {
-        if let Some(url) = value.as_str().and_then(normalize_mihomo_external_url) {
-            urls.push(url);
-        }
-    }
-}
-
-fn collect_mihomo_provider_urls(root: &serde_yaml::Mapping, key: &str, urls: &mut Vec<Url>) {
-    let Some(serde_yaml::Value::Mapping(map)) =
-        root.get(serde_yaml::Value::String(key.to_string()))
-    else {
-        return;
-    };
-    collect_urls_from_provider_map(map, urls);
-}
-
-fn collect_urls_from_provider_map(map: &serde_yaml::Mapping, urls: &mut Vec<Url>) {
-    for value in map.values() {
-        let Some(provider) = value.as_mapping() else {
-            continue;
-        };
-        if let Some(url) = provider
-            .get(serde_yaml::Value::String("url".to_string()))
-            .and_then(serde_yaml::Value::as_str)
-            .and_then(normalize_mihomo_external_url)
-        {
-            urls.push(url);
-        }
-    }
-}
-
 pub fn build_mihomo_provider_system_yaml(
     cluster_ca_key_pem: &str,
     user: &User,
@@ -4901,12 +4820,12 @@ fn build_mihomo_generated_proxies<R: RngCore + ?Sized>(
         nodes.iter().map(|n| (n.node_id.as_str(), n)).collect();
     let node_prefix_map = build_node_prefix_map(nodes);
 
-    let vless_uuid =
-        credentials::derive_vless_uuid(cluster_ca_key_pem, &user.user_id, user.credential_epoch)
-            .map_err(|e| SubscriptionError::CredentialDerive {
-                reason: e.to_string(),
-            })?;
-    let ss2022_user_psk_b64 = credentials::derive_ss2022_user_psk_b64(
+    let vless_uuid = credentials::user_vless_uuid(cluster_ca_key_pem, user).map_err(|e| {
+        SubscriptionError::CredentialDerive {
+            reason: e.to_string(),
+        }
+    })?;
+    let trojan_password = credentials::derive_trojan_password(
         cluster_ca_key_pem,
         &user.user_id,
         user.credential_epoch,
@@ -5053,24 +4972,14 @@ fn build_mihomo_generated_proxies<R: RngCore + ?Sized>(
                 })?);
             }
             EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
-                let meta: Ss2022EndpointMeta = serde_json::from_value(endpoint.meta.clone())
-                    .map_err(|e| SubscriptionError::Ss2022UnsupportedMethod {
-                        endpoint_id: endpoint.endpoint_id.clone(),
-                        got_method: format!("invalid endpoint meta: {e}"),
-                    })?;
-                if meta.method != SS2022_METHOD_2022_BLAKE3_AES_128_GCM {
-                    return Err(SubscriptionError::Ss2022UnsupportedMethod {
-                        endpoint_id: endpoint.endpoint_id.clone(),
-                        got_method: meta.method,
-                    });
-                }
-                let password = ss2022_password(&meta.server_psk_b64, &ss2022_user_psk_b64);
+                let (meta, password) =
+                    ss2022_endpoint_password(cluster_ca_key_pem, user, endpoint)?;
                 let direct = ClashProxy::Ss(ClashSsProxy {
                     name: format!("{prefix}-ss"),
                     proxy_type: "ss".to_string(),
                     server: node.access_host.clone(),
                     port: endpoint.port,
-                    cipher: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
+                    cipher: meta.method.clone(),
                     password: password.clone(),
                     udp: true,
                     dialer_proxy: None,
@@ -5088,7 +4997,7 @@ fn build_mihomo_generated_proxies<R: RngCore + ?Sized>(
                     proxy_type: "ss".to_string(),
                     server: node.access_host.clone(),
                     port: endpoint.port,
-                    cipher: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
+                    cipher: meta.method.clone(),
                     password: password.clone(),
                     udp: true,
                     dialer_proxy: Some(relay_group_name.clone()),
@@ -5101,6 +5010,59 @@ fn build_mihomo_generated_proxies<R: RngCore + ?Sized>(
                     }
                 })?);
             }
+            EndpointKind::TrojanRealityTcp => {
+                let meta: TrojanRealityTcpEndpointMeta =
+                    serde_json::from_value(endpoint.meta.clone()).map_err(|e| {
+                        SubscriptionError::InvalidEndpointMetaTrojan {
+                            endpoint_id: endpoint.endpoint_id.clone(),
+                            reason: e.to_string(),
+                        }
+                    })?;
+                let sni = parse_trojan_reality_meta(endpoint, &meta, rng)?;
+                let proxy = trojan_clash_proxy(
+                    format!("{prefix}-trojan"),
+                    &node.access_host,
+                    endpoint.port,
+                    &trojan_password,
+                    sni,
+                    &meta,
+                );
+                out.push(serde_yaml::to_value(proxy).map_err(|e| {
+                    SubscriptionError::YamlSerialize {
+                        reason: e.to_string(),
+                    }
+                })?);
+            }
+            EndpointKind::TrojanTlsTcp => {
+                let meta = parse_trojan_tls_meta(endpoint)?;
+                let proxy = trojan_tls_clash_proxy(
+                    format!("{prefix}-trojan-tls"),
+                    &node.access_host,
+                    endpoint.port,
+                    &trojan_password,
+                    &meta,
+                );
+                out.push(serde_yaml::to_value(proxy).map_err(|e| {
+                    SubscriptionError::YamlSerialize {
+                        reason: e.to_string(),
+                    }
+                })?);
+            }
+            EndpointKind::Hysteria2 => {
+                let (meta, auth) = hysteria2_endpoint_auth(cluster_ca_key_pem, user, endpoint)?;
+                let proxy = hysteria2_clash_proxy(
+                    format!("{prefix}-hy2"),
+                    &node.access_host,
+                    endpoint.port,
+                    &auth,
+                    &meta,
+                );
+                out.push(serde_yaml::to_value(proxy).map_err(|e| {
+                    SubscriptionError::YamlSerialize {
+                        reason: e.to_string(),
+                    }
+                })?);
+            }
         }
     }
 
@@ -5140,12 +5102,12 @@ fn build_items_with_rng<R: RngCore + ?Sized>(
     let nodes_by_id: std::collections::HashMap<&str, &Node> =
         nodes.iter().map(|n| (n.node_id.as_str(), n)).collect();
 
-    let vless_uuid =
-        credentials::derive_vless_uuid(cluster_ca_key_pem, &user.user_id, user.credential_epoch)
-            .map_err(|e| SubscriptionError::CredentialDerive {
-                reason: e.to_string(),
-            })?;
-    let ss2022_user_psk_b64 = credentials::derive_ss2022_user_psk_b64(
+    let vless_uuid = credentials::user_vless_uuid(cluster_ca_key_pem, user).map_err(|e| {
+        SubscriptionError::CredentialDerive {
+            reason: e.to_string(),
+        }
+    })?;
+    let trojan_password = credentials::derive_trojan_password(
         cluster_ca_key_pem,
         &user.user_id,
         user.credential_epoch,
@@ -5278,27 +5240,12 @@ fn build_items_with_rng<R: RngCore + ?Sized>(
                 (uri, proxy)
             }
             EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
-                let meta: Ss2022EndpointMeta = serde_json::from_value(endpoint.meta.clone())
-                    .map_err(|e| SubscriptionError::Ss2022UnsupportedMethod {
-                        endpoint_id: endpoint.endpoint_id.clone(),
-                        got_method: format!("invalid endpoint meta: {e}"),
-                    })?;
-                if meta.method != SS2022_METHOD_2022_BLAKE3_AES_128_GCM {
-                    return Err(SubscriptionError::Ss2022UnsupportedMethod {
-                        endpoint_id: endpoint.endpoint_id.clone(),
-                        got_method: meta.method,
-                    });
-                }
-
-                let password = ss2022_password(&meta.server_psk_b64, &ss2022_user_psk_b64);
+                let (meta, password) =
+                    ss2022_endpoint_password(cluster_ca_key_pem, user, endpoint)?;
                 let password_encoded = percent_encode_rfc3986(&password);
                 let uri = format!(
                     "ss://{}:{}@{}:{}#{}",
-                    SS2022_METHOD_2022_BLAKE3_AES_128_GCM,
-                    password_encoded,
-                    host,
-                    port,
-                    name_encoded
+                    meta.method, password_encoded, host, port, name_encoded
                 );
 
                 let proxy = ClashProxy::Ss(ClashSsProxy {
@@ -5306,7 +5253,7 @@ fn build_items_with_rng<R: RngCore + ?Sized>(
                     proxy_type: "ss".to_string(),
                     server: host.to_string(),
                     port,
-                    cipher: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
+                    cipher: meta.method.clone(),
                     password,
                     udp: true,
                     dialer_proxy: None,
@@ -5316,6 +5263,49 @@ fn build_items_with_rng<R: RngCore + ?Sized>(
 
                 (uri, proxy)
             }
+            EndpointKind::TrojanRealityTcp => {
+                let meta: TrojanRealityTcpEndpointMeta =
+                    serde_json::from_value(endpoint.meta.clone()).map_err(|e| {
+                        SubscriptionError::InvalidEndpointMetaTrojan {
+                            endpoint_id: endpoint.endpoint_id.clone(),
+                            reason: e.to_string(),
+                        }
+                    })?;
+                let sni = parse_trojan_reality_meta(endpoint, &meta, rng)?;
+
+                let uri = format!(
+                    concat!(
+                        "trojan://{}@{}:{}?security=reality&type=tcp",
+                        "&sni={}&fp={}&pbk={}&sid={}#{}"
+                    ),
+                    percent_encode_rfc3986(&trojan_password),
+                    host,
+                    port,
+                    percent_encode_rfc3986(sni),
+                    percent_encode_rfc3986(&meta.reality.fingerprint),
+                    percent_encode_rfc3986(&meta.reality_keys.public_key),
+                    percent_encode_rfc3986(&meta.active_short_id),
+                    name_encoded
+                );
+                let proxy =
+                    trojan_clash_proxy(name.clone(), host, port, &trojan_password, sni, &meta);
+
+                (uri, proxy)
+            }
+            EndpointKind::TrojanTlsTcp => {
+                let meta = parse_trojan_tls_meta(endpoint)?;
+                (
+                    trojan_tls_raw_uri(&trojan_password, host, port, &meta, &name_encoded),
+                    trojan_tls_clash_proxy(name.clone(), host, port, &trojan_password, &meta),
+                )
+            }
+            EndpointKind::Hysteria2 => {
+                let (meta, auth) = hysteria2_endpoint_auth(cluster_ca_key_pem, user, endpoint)?;
+                (
+                    hysteria2_raw_uri(&auth, host, port, &meta, &name_encoded),
+                    hysteria2_clash_proxy(name.clone(), host, port, &auth, &meta),
+                )
+            }
         };
 
         items.push(SubscriptionItem {
@@ -5385,6 +5375,7 @@ struct ClashConfig {
 mod node_selector;
 #[cfg(test)]
 mod reality_tests;
+mod singbox;
 #[cfg(test)]
 mod test_fixtures;
 #[cfg(test)]
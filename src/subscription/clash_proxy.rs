@@ -12,6 +12,9 @@ const XHTTP_H_MAX_REUSABLE_SECS: &str = "0";
 pub(super) enum ClashProxy {
     Vless(ClashVlessProxy),
     Ss(ClashSsProxy),
+    Trojan(ClashTrojanProxy),
+    TrojanTls(super::trojan_tls::ClashTrojanTlsProxy),
+    Hysteria2(super::hysteria2::ClashHysteria2Proxy),
 }
 
 #[derive(Debug, Clone, serde::Serialize, PartialEq, Eq)]
@@ -97,6 +100,26 @@ pub(super) struct ClashSsProxy {
     pub(super) smux: Option<ClashSmuxConfig>,
 }
 
+#[derive(Debug, Clone, serde::Serialize, PartialEq, Eq)]
+pub(super) struct ClashTrojanProxy {
+    pub(super) name: String,
+    #[serde(rename = "type")]
+    pub(super) proxy_type: String,
+    pub(super) server: String,
+    pub(super) port: u16,
+    pub(super) password: String,
+    pub(super) udp: bool,
+    pub(super) sni: String,
+    #[serde(rename = "client-fingerprint")]
+    pub(super) client_fingerprint: String,
+    #[serde(rename = "reality-opts")]
+    pub(super) reality_opts: ClashRealityOpts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) smux: Option<ClashSmuxConfig>,
+    #[serde(rename = "dialer-proxy", skip_serializing_if = "Option::is_none")]
+    pub(super) dialer_proxy: Option<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, PartialEq, Eq)]
 pub(super) struct ClashSmuxConfig {
     pub(super) enabled: bool,
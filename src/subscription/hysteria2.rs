@@ -0,0 +1,96 @@
+use super::{ClashProxy, SubscriptionError, percent_encode_rfc3986};
+use crate::{
+    credentials,
+    domain::{Endpoint, User},
+    protocol::Hysteria2EndpointMeta,
+};
+
+#[derive(Debug, Clone, serde::Serialize, PartialEq, Eq)]
+pub(super) struct ClashHysteria2Proxy {
+    pub(super) name: String,
+    #[serde(rename = "type")]
+    proxy_type: &'static str,
+    pub(super) server: String,
+    pub(super) port: u16,
+    pub(super) password: String,
+    pub(super) sni: String,
+    pub(super) alpn: Vec<&'static str>,
+    #[serde(rename = "skip-cert-verify")]
+    skip_cert_verify: bool,
+    /// Mihomo verifies the leaf certificate against this SHA-256 instead of a CA chain.
+    fingerprint: String,
+    /// The self-signed certificate itself, for clients (sing-box) that trust a PEM rather than
+    /// pin a digest. Not part of the Clash proxy.
+    #[serde(skip)]
+    pub(super) certificate_pem: String,
+}
+
+/// Parses Hysteria2 endpoint meta and derives the per-user auth string.
+pub(super) fn hysteria2_endpoint_auth(
+    cluster_ca_key_pem: &str,
+    user: &User,
+    endpoint: &Endpoint,
+) -> Result<(Hysteria2EndpointMeta, String), SubscriptionError> {
+    let meta: Hysteria2EndpointMeta =
+        serde_json::from_value(endpoint.meta.clone()).map_err(|e| {
+            SubscriptionError::InvalidEndpointMetaHysteria2 {
+                endpoint_id: endpoint.endpoint_id.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+    if meta.server_name.is_empty() || meta.cert_sha256.is_empty() {
+        return Err(SubscriptionError::InvalidEndpointMetaHysteria2 {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            reason: "missing server_name or cert_sha256".to_string(),
+        });
+    }
+    let auth = credentials::derive_hysteria2_auth(
+        cluster_ca_key_pem,
+        &user.user_id,
+        user.credential_epoch,
+    )
+    .map_err(|e| SubscriptionError::CredentialDerive {
+        reason: e.to_string(),
+    })?;
+    Ok((meta, auth))
+}
+
+pub(super) fn hysteria2_raw_uri(
+    auth: &str,
+    host: &str,
+    port: u16,
+    meta: &Hysteria2EndpointMeta,
+    name_encoded: &str,
+) -> String {
+    // The certificate is self-signed, so clients skip CA verification and pin it instead.
+    format!(
+        "hysteria2://{}@{}:{}/?sni={}&insecure=1&pinSHA256={}#{}",
+        percent_encode_rfc3986(auth),
+        host,
+        port,
+        percent_encode_rfc3986(&meta.server_name),
+        percent_encode_rfc3986(&meta.cert_sha256),
+        name_encoded
+    )
+}
+
+pub(super) fn hysteria2_clash_proxy(
+    name: String,
+    server: &str,
+    port: u16,
+    auth: &str,
+    meta: &Hysteria2EndpointMeta,
+) -> ClashProxy {
+    ClashProxy::Hysteria2(ClashHysteria2Proxy {
+        name,
+        proxy_type: "hysteria2",
+        server: server.to_string(),
+        port,
+        password: auth.to_string(),
+        sni: meta.server_name.clone(),
+        alpn: vec!["h3"],
+        skip_cert_verify: false,
+        fingerprint: meta.cert_sha256.clone(),
+        certificate_pem: meta.tls_cert_pem.clone(),
+    })
+}
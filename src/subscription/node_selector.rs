@@ -1,5 +1,8 @@
 use super::*;
 
+pub(super) const NODE_SELECTOR: &str = "🚀 节点选择";
+const AUTO_NODE_SELECTOR: &str = "💎 节点选择";
+
 pub(super) fn inject_mihomo_default(
     groups: &mut Vec<serde_yaml::Value>,
     landing_groups: &[String],
@@ -17,7 +20,7 @@ pub(super) fn inject_mihomo_provider(
     provider_values: &[serde_yaml::Value],
     direct_reality_names: &[String],
 ) {
-    let mut node_selector = mihomo_select_group("🚀 节点选择", false, {
+    let mut node_selector = mihomo_select_group(NODE_SELECTOR, false, {
         let mut proxies = node_selector_proxy_names(landing_groups);
         proxies.push("💎 高质量".to_string());
         proxies
@@ -37,9 +40,9 @@ pub(super) fn inject_mihomo_provider(
     }
     groups.push(node_selector);
     groups.push(mihomo_fallback_group(
-        "💎 节点选择",
+        AUTO_NODE_SELECTOR,
         true,
-        ["🚀 节点选择".to_string(), "🤯 All".to_string()],
+        [NODE_SELECTOR.to_string(), "🤯 All".to_string()],
     ));
 }
 
@@ -64,10 +67,38 @@ fn node_selector_proxy_names(landing_groups: &[String]) -> Vec<String> {
 }
 
 fn inject_node_selector_groups(groups: &mut Vec<serde_yaml::Value>, proxies: Vec<String>) {
-    groups.push(mihomo_select_group("🚀 节点选择", false, proxies));
+    groups.push(mihomo_select_group(NODE_SELECTOR, false, proxies));
     groups.push(mihomo_fallback_group(
-        "💎 节点选择",
+        AUTO_NODE_SELECTOR,
         true,
-        ["🚀 节点选择".to_string(), "🤯 All".to_string()],
+        [NODE_SELECTOR.to_string(), "🤯 All".to_string()],
     ));
 }
+
+/// sing-box has no region groups, so the selector offers an url-test over every outbound plus each
+/// outbound by name.
+pub(super) fn singbox_node_selector_outbounds(tags: &[String]) -> Vec<serde_json::Value> {
+    let mut selectable = vec![AUTO_NODE_SELECTOR.to_string()];
+    selectable.extend(tags.iter().cloned());
+    let urltest_outbounds = if tags.is_empty() {
+        vec!["direct".to_string()]
+    } else {
+        tags.to_vec()
+    };
+    vec![
+        serde_json::json!({
+            "type": "selector",
+            "tag": NODE_SELECTOR,
+            "outbounds": selectable,
+            "default": AUTO_NODE_SELECTOR,
+        }),
+        serde_json::json!({
+            "type": "urltest",
+            "tag": AUTO_NODE_SELECTOR,
+            "outbounds": urltest_outbounds,
+            "url": MIHOMO_DEFAULT_HEALTH_CHECK_URL,
+            "interval": "5m",
+            "tolerance": 0,
+        }),
+    ]
+}
@@ -0,0 +1,188 @@
+//! sing-box (1.12+) client config rendered from the same items as the Clash builder.
+
+use serde_json::{Value, json};
+
+use super::{
+    ClashProxy, SubscriptionError, SubscriptionItem,
+    clash_proxy::{ClashRealityOpts, ClashSmuxConfig},
+    node_selector,
+};
+
+const TUN_ADDRESSES: [&str; 2] = ["172.19.0.1/30", "fdfe:dcba:9876::1/126"];
+const REMOTE_DNS_SERVER: &str = "1.1.1.1";
+
+pub(super) fn build_config(items: Vec<SubscriptionItem>) -> Result<String, SubscriptionError> {
+    let proxies = items
+        .into_iter()
+        .filter_map(|item| singbox_outbound(&item.clash_proxy))
+        .collect::<Vec<_>>();
+    let tags = proxies
+        .iter()
+        .filter_map(|outbound| outbound["tag"].as_str().map(str::to_string))
+        .collect::<Vec<_>>();
+
+    let mut outbounds = node_selector::singbox_node_selector_outbounds(&tags);
+    outbounds.extend(proxies);
+    outbounds.push(json!({ "type": "direct", "tag": "direct" }));
+
+    let config = json!({
+        "dns": {
+            "servers": [
+                {
+                    "type": "https",
+                    "tag": "remote",
+                    "server": REMOTE_DNS_SERVER,
+                    "detour": node_selector::NODE_SELECTOR,
+                },
+                { "type": "local", "tag": "local" },
+            ],
+            "final": "remote",
+        },
+        "inbounds": [{
+            "type": "tun",
+            "tag": "tun-in",
+            "address": TUN_ADDRESSES,
+            "auto_route": true,
+            "strict_route": true,
+        }],
+        "outbounds": outbounds,
+        "route": {
+            "rules": [
+                { "action": "sniff" },
+                { "protocol": "dns", "action": "hijack-dns" },
+                { "ip_is_private": true, "outbound": "direct" },
+            ],
+            "final": node_selector::NODE_SELECTOR,
+            "auto_detect_interface": true,
+            "default_domain_resolver": "local",
+        },
+    });
+    serde_json::to_string_pretty(&config).map_err(|e| SubscriptionError::JsonSerialize {
+        reason: e.to_string(),
+    })
+}
+
+/// Endpoints sing-box cannot connect to yield `None`. Self-signed TLS endpoints (Trojan TLS,
+/// Hysteria2) are trusted through their certificate PEM, since sing-box has no digest pinning.
+fn singbox_outbound(proxy: &ClashProxy) -> Option<Value> {
+    match proxy {
+        // sing-box has no XHTTP transport, so XHTTP endpoints are left out rather than emitted
+        // as outbounds it would reject.
+        ClashProxy::Vless(vless) if vless.xhttp_opts.is_some() => None,
+        ClashProxy::Vless(vless) => {
+            let mut outbound = json!({
+                "type": "vless",
+                "tag": vless.name,
+                "server": vless.server,
+                "server_port": vless.port,
+                "uuid": vless.uuid,
+                "tls": reality_tls(
+                    &vless.servername,
+                    &vless.client_fingerprint,
+                    &vless.reality_opts,
+                    vless.alpn.as_ref().map(|alpn| alpn.as_slice()),
+                ),
+            });
+            if !vless.flow.is_empty() {
+                outbound["flow"] = json!(vless.flow);
+            }
+            Some(outbound)
+        }
+        ClashProxy::Ss(ss) => {
+            let mut outbound = json!({
+                "type": "shadowsocks",
+                "tag": ss.name,
+                "server": ss.server,
+                "server_port": ss.port,
+                "method": ss.cipher,
+                "password": ss.password,
+            });
+            if let Some(smux) = &ss.smux {
+                outbound["multiplex"] = multiplex(smux);
+            }
+            Some(outbound)
+        }
+        ClashProxy::Trojan(trojan) => {
+            let mut outbound = json!({
+                "type": "trojan",
+                "tag": trojan.name,
+                "server": trojan.server,
+                "server_port": trojan.port,
+                "password": trojan.password,
+                "tls": reality_tls(
+                    &trojan.sni,
+                    &trojan.client_fingerprint,
+                    &trojan.reality_opts,
+                    None,
+                ),
+            });
+            if let Some(smux) = &trojan.smux {
+                outbound["multiplex"] = multiplex(smux);
+            }
+            Some(outbound)
+        }
+        ClashProxy::TrojanTls(trojan) => {
+            let mut outbound = json!({
+                "type": "trojan",
+                "tag": trojan.name,
+                "server": trojan.server,
+                "server_port": trojan.port,
+                "password": trojan.password,
+                "tls": {
+                    "enabled": true,
+                    "server_name": trojan.sni,
+                    "certificate": trojan.certificate_pem,
+                },
+            });
+            if let Some(smux) = &trojan.smux {
+                outbound["multiplex"] = multiplex(smux);
+            }
+            Some(outbound)
+        }
+        ClashProxy::Hysteria2(hysteria2) => Some(json!({
+            "type": "hysteria2",
+            "tag": hysteria2.name,
+            "server": hysteria2.server,
+            "server_port": hysteria2.port,
+            "password": hysteria2.password,
+            "tls": {
+                "enabled": true,
+                "server_name": hysteria2.sni,
+                "alpn": hysteria2.alpn,
+                "certificate": hysteria2.certificate_pem,
+            },
+        })),
+    }
+}
+
+fn reality_tls(
+    server_name: &str,
+    fingerprint: &str,
+    reality: &ClashRealityOpts,
+    alpn: Option<&[&str]>,
+) -> Value {
+    let mut tls = json!({
+        "enabled": true,
+        "server_name": server_name,
+        "utls": { "enabled": true, "fingerprint": fingerprint },
+        "reality": {
+            "enabled": true,
+            "public_key": reality.public_key,
+            "short_id": reality.short_id,
+        },
+    });
+    if let Some(alpn) = alpn {
+        tls["alpn"] = json!(alpn);
+    }
+    tls
+}
+
+fn multiplex(smux: &ClashSmuxConfig) -> Value {
+    json!({
+        "enabled": smux.enabled,
+        "protocol": smux.protocol,
+        "max_connections": smux.max_connections,
+        "min_streams": smux.min_streams,
+        "padding": smux.padding,
+    })
+}
@@ -5268,7 +5268,9 @@ rules: []
     assert_eq!(refs, vec!["Alpha-reality"]);
 }
 
+mod endpoint_kinds;
 mod mihomo_smux;
+mod singbox;
 mod vless_xhttp;
 
 #[test]
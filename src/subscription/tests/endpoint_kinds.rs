@@ -0,0 +1,158 @@
+use super::*;
+
+use pretty_assertions::assert_eq;
+use serde_yaml::Value;
+
+#[test]
+fn ss2022_aes_256_endpoint_emits_matching_method_and_32_byte_user_psk() {
+    let u = user("alice");
+    let n = node(
+        fixture_node_n1(),
+        fixture_label_node1_variant2,
+        fixture_host_example(),
+    );
+    let mut ep = endpoint_ss("e1", "n1", "ss", 443, endpoint_server_psk_b64());
+    let server_psk_b64 = base64::engine::general_purpose::STANDARD.encode([9u8; 32]);
+    ep.meta["method"] = serde_json::json!("2022-blake3-aes-256-gcm");
+    ep.meta["server_psk_b64"] = serde_json::json!(server_psk_b64);
+    let m = membership("n1", "e1");
+
+    let lines = build_raw_lines(
+        SEED,
+        &u,
+        std::slice::from_ref(&m),
+        std::slice::from_ref(&ep),
+        std::slice::from_ref(&n),
+    )
+    .unwrap();
+    assert!(lines[0].starts_with("ss://2022-blake3-aes-256-gcm:"));
+
+    let yaml = build_clash_yaml(SEED, &u, &[m], &[ep], &[n]).unwrap();
+    let root: Value = serde_yaml::from_str(&yaml).unwrap();
+    let proxy = &root["proxies"][0];
+    assert_eq!(
+        proxy["cipher"],
+        Value::String("2022-blake3-aes-256-gcm".to_string())
+    );
+    let password = proxy["password"].as_str().unwrap();
+    let (server, user_psk) = password.split_once(':').unwrap();
+    assert_eq!(server, server_psk_b64);
+    assert_eq!(
+        crate::protocol::parse_ss2022_psk_b64(user_psk)
+            .unwrap()
+            .len(),
+        32
+    );
+}
+
+#[test]
+fn trojan_reality_renders_raw_uri_and_clash_proxy() {
+    let u = user("alice");
+    let n = node(
+        fixture_node_n1(),
+        fixture_label_node1_variant2,
+        fixture_host_example(),
+    );
+    let ep = endpoint_trojan("e1", "n1", "trojan", 443);
+    let m = membership("n1", "e1");
+    let password =
+        credentials::derive_trojan_password(SEED, &u.user_id, u.credential_epoch).unwrap();
+
+    let lines = build_raw_lines(
+        SEED,
+        &u,
+        std::slice::from_ref(&m),
+        std::slice::from_ref(&ep),
+        std::slice::from_ref(&n),
+    )
+    .unwrap();
+    assert_eq!(lines.len(), 1);
+    let uri = &lines[0];
+    assert!(uri.starts_with(&format!("trojan://{password}@example.fixture.test:443?")));
+    assert!(uri.contains("security=reality&type=tcp"));
+    assert!(uri.contains("&pbk="));
+    assert!(uri.contains("&sid="));
+
+    let yaml = build_clash_yaml(SEED, &u, &[m], &[ep], &[n]).unwrap();
+    let root: Value = serde_yaml::from_str(&yaml).unwrap();
+    let proxy = &root["proxies"][0];
+    assert_eq!(proxy["type"], Value::String("trojan".to_string()));
+    assert_eq!(proxy["password"], Value::String(password));
+    assert!(proxy["reality-opts"]["public-key"].as_str().is_some());
+}
+
+#[test]
+fn hysteria2_renders_pinned_raw_uri_and_clash_proxy() {
+    let u = user("alice");
+    let n = node(
+        fixture_node_n1(),
+        fixture_label_node1_variant2,
+        fixture_host_example(),
+    );
+    let ep = endpoint_hysteria2("e1", "n1", "hy2", 443);
+    let m = membership("n1", "e1");
+    let auth = credentials::derive_hysteria2_auth(SEED, &u.user_id, u.credential_epoch).unwrap();
+
+    let lines = build_raw_lines(
+        SEED,
+        &u,
+        std::slice::from_ref(&m),
+        std::slice::from_ref(&ep),
+        std::slice::from_ref(&n),
+    )
+    .unwrap();
+    assert_eq!(lines.len(), 1);
+    let uri = &lines[0];
+    assert!(uri.starts_with(&format!("hysteria2://{auth}@example.fixture.test:443/?")));
+    assert!(uri.contains("sni=example.fixture.test&insecure=1"));
+    assert!(uri.contains(&format!("&pinSHA256={}", "ab".repeat(32))));
+
+    let yaml = build_clash_yaml(SEED, &u, &[m], &[ep], &[n]).unwrap();
+    let root: Value = serde_yaml::from_str(&yaml).unwrap();
+    let proxy = &root["proxies"][0];
+    assert_eq!(proxy["type"], Value::String("hysteria2".to_string()));
+    assert_eq!(proxy["password"], Value::String(auth));
+    assert_eq!(proxy["fingerprint"], Value::String("ab".repeat(32)));
+    assert_eq!(proxy["skip-cert-verify"], Value::Bool(false));
+}
+
+#[test]
+fn trojan_tls_renders_pinned_raw_uri_and_clash_proxy() {
+    let u = user("alice");
+    let n = node(
+        fixture_node_n1(),
+        fixture_label_node1_variant2,
+        fixture_host_example(),
+    );
+    let ep = endpoint_trojan_tls("e1", "n1", "trojan-tls", 443);
+    let m = membership("n1", "e1");
+    let password =
+        credentials::derive_trojan_password(SEED, &u.user_id, u.credential_epoch).unwrap();
+
+    let lines = build_raw_lines(
+        SEED,
+        &u,
+        std::slice::from_ref(&m),
+        std::slice::from_ref(&ep),
+        std::slice::from_ref(&n),
+    )
+    .unwrap();
+    assert_eq!(lines.len(), 1);
+    let uri = &lines[0];
+    assert!(uri.starts_with(&format!("trojan://{password}@example.fixture.test:443?")));
+    assert!(uri.contains("security=tls&type=tcp&sni=example.fixture.test&allowInsecure=1"));
+    assert!(uri.contains(&format!("&pcs={}", "ab".repeat(32))));
+
+    let yaml = build_clash_yaml(SEED, &u, &[m], &[ep], &[n]).unwrap();
+    let root: Value = serde_yaml::from_str(&yaml).unwrap();
+    let proxy = &root["proxies"][0];
+    assert_eq!(proxy["type"], Value::String("trojan".to_string()));
+    assert_eq!(proxy["password"], Value::String(password));
+    assert_eq!(
+        proxy["sni"],
+        Value::String("example.fixture.test".to_string())
+    );
+    assert_eq!(proxy["fingerprint"], Value::String("ab".repeat(32)));
+    assert_eq!(proxy["skip-cert-verify"], Value::Bool(false));
+    assert!(proxy.get("reality-opts").is_none());
+}
@@ -1,5 +1,6 @@
 use super::*;
 
+use crate::protocol::SS2022_METHOD_2022_BLAKE3_AES_128_GCM;
 use pretty_assertions::assert_eq;
 use serde_yaml::Value;
 use xp_test_fixtures::{
@@ -66,8 +67,13 @@ fn build_clash_yaml_has_proxies_and_derived_secrets() {
             SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string()
         ))
     );
-    let expected_user_psk =
-        crate::credentials::derive_ss2022_user_psk_b64(SEED, "u1", u.credential_epoch).unwrap();
+    let expected_user_psk = crate::credentials::derive_ss2022_user_psk_b64(
+        SEED,
+        "u1",
+        u.credential_epoch,
+        crate::protocol::SS2022_PSK_LEN_BYTES_AES_128,
+    )
+    .unwrap();
     let expected_password = format!("AAAAAAAAAAAAAAAAAAAAAA==:{expected_user_psk}");
     assert_eq!(ss.get("password"), Some(&Value::String(expected_password)));
     assert_eq!(ss.get("udp"), Some(&Value::Bool(true)));
@@ -0,0 +1,173 @@
+use super::*;
+
+use crate::protocol::SS2022_METHOD_2022_BLAKE3_AES_128_GCM;
+use pretty_assertions::assert_eq;
+use serde_json::Value;
+
+fn outbounds(root: &Value) -> &[Value] {
+    root["outbounds"]
+        .as_array()
+        .map(Vec::as_slice)
+        .expect("outbounds must be an array")
+}
+
+fn outbound_of_type<'a>(root: &'a Value, kind: &str) -> &'a Value {
+    outbounds(root)
+        .iter()
+        .find(|outbound| outbound["type"] == kind)
+        .unwrap_or_else(|| panic!("missing {kind} outbound"))
+}
+
+fn render(endpoints: &[Endpoint]) -> Value {
+    let u = user("alice");
+    let n = node(
+        fixture_node_n1(),
+        fixture_label_node1_variant2,
+        fixture_host_example(),
+    );
+    let memberships = endpoints
+        .iter()
+        .map(|endpoint| membership("n1", &endpoint.endpoint_id))
+        .collect::<Vec<_>>();
+    let json = build_singbox_json(SEED, &u, &memberships, endpoints, &[n]).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn build_singbox_json_has_outbounds_and_derived_secrets() {
+    let u = user("alice");
+    let root = render(&[
+        endpoint_ss(
+            "e1",
+            "n1",
+            "ss",
+            443,
+            xp_test_fixtures::endpoint_server_psk_b64(),
+        ),
+        endpoint_vless("e2", "n1", "vless", 8443, VlessFixtureMode::Standard),
+    ]);
+
+    let ss = outbound_of_type(&root, "shadowsocks");
+    assert_eq!(ss["server"], fixture_host_example());
+    assert_eq!(ss["server_port"], 443);
+    assert_eq!(ss["method"], SS2022_METHOD_2022_BLAKE3_AES_128_GCM);
+    let expected_user_psk = crate::credentials::derive_ss2022_user_psk_b64(
+        SEED,
+        "u1",
+        u.credential_epoch,
+        crate::protocol::SS2022_PSK_LEN_BYTES_AES_128,
+    )
+    .unwrap();
+    assert_eq!(
+        ss["password"],
+        format!("AAAAAAAAAAAAAAAAAAAAAA==:{expected_user_psk}")
+    );
+    assert_eq!(ss["multiplex"]["enabled"], true);
+    assert_eq!(ss["multiplex"]["protocol"], "smux");
+    assert_eq!(ss["multiplex"]["max_connections"], 4);
+
+    let vless = outbound_of_type(&root, "vless");
+    assert_eq!(vless["server_port"], 8443);
+    let expected_uuid =
+        crate::credentials::derive_vless_uuid(SEED, "u1", u.credential_epoch).unwrap();
+    assert_eq!(vless["uuid"], expected_uuid);
+    assert_eq!(vless["flow"], "xtls-rprx-vision");
+    assert!(vless.get("transport").is_none());
+    assert!(vless.get("multiplex").is_none());
+    let tls = &vless["tls"];
+    assert_eq!(tls["enabled"], true);
+    assert_eq!(tls["reality"]["enabled"], true);
+    assert!(tls["reality"]["public_key"].as_str().is_some());
+    assert!(tls["reality"]["short_id"].as_str().is_some());
+    assert!(tls["utls"]["fingerprint"].as_str().is_some());
+}
+
+#[test]
+fn singbox_selector_groups_every_proxy_outbound_and_routes_through_it() {
+    let root = render(&[
+        endpoint_ss(
+            "e1",
+            "n1",
+            "ss",
+            443,
+            xp_test_fixtures::endpoint_server_psk_b64(),
+        ),
+        endpoint_vless("e2", "n1", "vless", 8443, VlessFixtureMode::Standard),
+    ]);
+    let proxy_tags = outbounds(&root)
+        .iter()
+        .filter(|outbound| matches!(outbound["type"].as_str(), Some("shadowsocks" | "vless")))
+        .map(|outbound| outbound["tag"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(proxy_tags.len(), 2);
+
+    let selector = outbound_of_type(&root, "selector");
+    assert_eq!(selector["tag"], "🚀 节点选择");
+    assert_eq!(selector["default"], "💎 节点选择");
+    let mut expected = vec![Value::from("💎 节点选择")];
+    expected.extend(proxy_tags.iter().cloned());
+    assert_eq!(selector["outbounds"], Value::Array(expected));
+
+    let urltest = outbound_of_type(&root, "urltest");
+    assert_eq!(urltest["tag"], "💎 节点选择");
+    assert_eq!(urltest["outbounds"], Value::Array(proxy_tags));
+
+    assert_eq!(root["route"]["final"], "🚀 节点选择");
+    assert_eq!(root["dns"]["servers"][0]["detour"], "🚀 节点选择");
+    assert_eq!(outbound_of_type(&root, "direct")["tag"], "direct");
+}
+
+#[test]
+fn singbox_skips_vless_xhttp_endpoints() {
+    let mut xhttp = endpoint_vless("e1", "n1", "vless-xhttp", 8443, VlessFixtureMode::Standard);
+    xhttp.meta["transport"] = serde_json::json!("xhttp");
+    let root = render(&[xhttp, endpoint_trojan("e2", "n1", "trojan", 443)]);
+
+    let types = outbounds(&root)
+        .iter()
+        .map(|outbound| outbound["type"].clone())
+        .collect::<Vec<_>>();
+    assert!(!types.contains(&Value::from("vless")), "{types:?}");
+    assert!(types.contains(&Value::from("trojan")), "{types:?}");
+}
+
+#[test]
+fn singbox_renders_trojan_reality_and_hysteria2_with_certificate() {
+    let u = user("alice");
+    let hysteria2_endpoint = endpoint_hysteria2("e2", "n1", "hy2", 8443);
+    let root = render(&[
+        endpoint_trojan("e1", "n1", "trojan", 443),
+        hysteria2_endpoint.clone(),
+    ]);
+    let password =
+        credentials::derive_trojan_password(SEED, &u.user_id, u.credential_epoch).unwrap();
+
+    let trojan = outbound_of_type(&root, "trojan");
+    assert_eq!(trojan["password"], password);
+    assert_eq!(trojan["tls"]["reality"]["enabled"], true);
+
+    let hysteria2 = outbound_of_type(&root, "hysteria2");
+    assert_eq!(hysteria2["server_port"], 8443);
+    assert_eq!(
+        hysteria2["password"],
+        credentials::derive_hysteria2_auth(SEED, &u.user_id, u.credential_epoch).unwrap()
+    );
+    assert_eq!(
+        hysteria2["tls"]["server_name"],
+        hysteria2_endpoint.meta["server_name"]
+    );
+    assert_eq!(hysteria2["tls"]["alpn"], serde_json::json!(["h3"]));
+    assert_eq!(
+        hysteria2["tls"]["certificate"],
+        hysteria2_endpoint.meta["tls_cert_pem"]
+    );
+}
+
+#[test]
+fn singbox_without_memberships_keeps_a_valid_selector() {
+    let root = render(&[]);
+    assert_eq!(
+        outbound_of_type(&root, "urltest")["outbounds"],
+        serde_json::json!(["direct"])
+    );
+}
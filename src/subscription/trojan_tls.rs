@@ -0,0 +1,84 @@
+use super::{ClashProxy, SubscriptionError, clash_proxy::ClashSmuxConfig, percent_encode_rfc3986};
+use crate::{domain::Endpoint, protocol::TrojanTlsTcpEndpointMeta};
+
+#[derive(Debug, Clone, serde::Serialize, PartialEq, Eq)]
+pub(super) struct ClashTrojanTlsProxy {
+    pub(super) name: String,
+    #[serde(rename = "type")]
+    proxy_type: &'static str,
+    pub(super) server: String,
+    pub(super) port: u16,
+    pub(super) password: String,
+    udp: bool,
+    pub(super) sni: String,
+    #[serde(rename = "skip-cert-verify")]
+    skip_cert_verify: bool,
+    /// Mihomo verifies the leaf certificate against this SHA-256 instead of a CA chain.
+    fingerprint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) smux: Option<ClashSmuxConfig>,
+    /// The self-signed certificate itself, for clients (sing-box) that trust a PEM rather than
+    /// pin a digest. Not part of the Clash proxy.
+    #[serde(skip)]
+    pub(super) certificate_pem: String,
+}
+
+pub(super) fn parse_trojan_tls_meta(
+    endpoint: &Endpoint,
+) -> Result<TrojanTlsTcpEndpointMeta, SubscriptionError> {
+    let meta: TrojanTlsTcpEndpointMeta =
+        serde_json::from_value(endpoint.meta.clone()).map_err(|e| {
+            SubscriptionError::InvalidEndpointMetaTrojan {
+                endpoint_id: endpoint.endpoint_id.clone(),
+                reason: e.to_string(),
+            }
+        })?;
+    if meta.server_name.is_empty() || meta.cert_sha256.is_empty() {
+        return Err(SubscriptionError::InvalidEndpointMetaTrojan {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            reason: "missing server_name or cert_sha256".to_string(),
+        });
+    }
+    Ok(meta)
+}
+
+pub(super) fn trojan_tls_raw_uri(
+    password: &str,
+    host: &str,
+    port: u16,
+    meta: &TrojanTlsTcpEndpointMeta,
+    name_encoded: &str,
+) -> String {
+    // The certificate is self-signed, so clients skip CA verification and pin it instead.
+    format!(
+        "trojan://{}@{}:{}?security=tls&type=tcp&sni={}&allowInsecure=1&pcs={}#{}",
+        percent_encode_rfc3986(password),
+        host,
+        port,
+        percent_encode_rfc3986(&meta.server_name),
+        percent_encode_rfc3986(&meta.cert_sha256),
+        name_encoded
+    )
+}
+
+pub(super) fn trojan_tls_clash_proxy(
+    name: String,
+    server: &str,
+    port: u16,
+    password: &str,
+    meta: &TrojanTlsTcpEndpointMeta,
+) -> ClashProxy {
+    ClashProxy::TrojanTls(ClashTrojanTlsProxy {
+        name,
+        proxy_type: "trojan",
+        server: server.to_string(),
+        port,
+        password: password.to_string(),
+        udp: true,
+        sni: meta.server_name.clone(),
+        skip_cert_verify: false,
+        fingerprint: meta.cert_sha256.clone(),
+        smux: super::mihomo_smux_config(&meta.mihomo_smux),
+        certificate_pem: meta.tls_cert_pem.clone(),
+    })
+}
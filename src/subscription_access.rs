@@ -0,0 +1,177 @@
+use std::{
+    collections::BTreeSet,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::inbound_ip_usage::{GeoLookup, normalize_ip_string};
+
+/// History repository schema carrying one subscription fetch per record.
+pub const SUBSCRIPTION_ACCESS_SCHEMA_ID: &str = "subscription_access.v1";
+/// Fetches older than this are pruned from the local log; matches the repository's raw window.
+pub const SUBSCRIPTION_ACCESS_RETENTION_DAYS: i64 = 7;
+const MAX_USER_AGENT_CHARS: usize = 256;
+
+/// One successful subscription fetch, as seen by the serving node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionAccessEntry {
+    pub accessed_at: String,
+    pub user_id: String,
+    /// Secondary token the fetch used; `None` for the user's primary token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    /// Node that served the fetch.
+    pub node_id: String,
+    /// `base64`, `raw`, `clash`, `mihomo`, `singbox`, `mihomo_provider` or
+    /// `mihomo_provider_system`.
+    pub format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_ip: Option<String>,
+    /// ISO country code, when the IP geo resolver already knew the address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+}
+
+impl SubscriptionAccessEntry {
+    pub fn accessed_at_unix_seconds(&self) -> Option<i64> {
+        DateTime::parse_from_rfc3339(&self.accessed_at)
+            .ok()
+            .map(|at| at.timestamp())
+    }
+
+    pub fn accessed_since(&self, since: DateTime<Utc>) -> bool {
+        self.accessed_at_unix_seconds()
+            .is_some_and(|at| at >= since.timestamp())
+    }
+
+    /// Fills a missing country from the resolver cache.
+    pub fn resolve_country(&mut self, geo_resolver: &dyn GeoLookup) {
+        if self.country.is_some() {
+            return;
+        }
+        let Some(ip) = self.source_ip.as_deref() else {
+            return;
+        };
+        let country = geo_resolver.lookup(ip).country;
+        self.country = (!country.is_empty()).then_some(country);
+    }
+}
+
+/// Repository record key of a fetch; unique per serving node and local log row.
+pub fn subscription_access_record_key(user_id: &str, node_id: &str, access_id: u64) -> String {
+    format!(
+        "{}{node_id}:{access_id}",
+        subscription_access_user_key_prefix(user_id)
+    )
+}
+
+pub fn subscription_access_user_key_prefix(user_id: &str) -> String {
+    format!("subscription-access:user:{user_id}:")
+}
+
+/// Address of the fetching client.
+///
+/// Forwarding headers are only trusted when the socket peer is loopback, i.e. the request came
+/// through the local reverse proxy or cloudflared; otherwise a client could claim any address.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> Option<String> {
+    let peer_ip = peer.map(|peer| peer.ip());
+    if peer_ip.is_none_or(|ip| ip.is_loopback()) {
+        let forwarded = header_str(headers, "cf-connecting-ip")
+            .or_else(|| {
+                header_str(headers, "x-forwarded-for")
+                    .and_then(|value| value.split(',').next().map(str::trim))
+            })
+            .or_else(|| header_str(headers, "x-real-ip"))
+            .and_then(normalize_ip_string);
+        if forwarded.is_some() {
+            return forwarded;
+        }
+    }
+    peer_ip.map(|ip| match ip {
+        IpAddr::V6(v6) => v6
+            .to_ipv4_mapped()
+            .map_or_else(|| ip.to_string(), |v4| v4.to_string()),
+        IpAddr::V4(_) => ip.to_string(),
+    })
+}
+
+pub fn user_agent(headers: &HeaderMap) -> Option<String> {
+    header_str(headers, "user-agent")
+        .filter(|value| !value.is_empty())
+        .map(|value| value.chars().take(MAX_USER_AGENT_CHARS).collect())
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+}
+
+/// Limits on how widely one token may be fetched inside the detection window; `0` disables a
+/// limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscriptionAbuseThresholds {
+    pub max_ips: u32,
+    pub max_countries: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionAccessSummary {
+    pub fetches: usize,
+    pub distinct_ips: BTreeSet<String>,
+    pub distinct_countries: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed_at: Option<String>,
+}
+
+impl SubscriptionAccessSummary {
+    pub fn from_entries<'a>(
+        entries: impl IntoIterator<Item = &'a SubscriptionAccessEntry>,
+    ) -> Self {
+        let mut summary = Self::default();
+        for entry in entries {
+            summary.fetches += 1;
+            summary.distinct_ips.extend(entry.source_ip.iter().cloned());
+            summary
+                .distinct_countries
+                .extend(entry.country.iter().cloned());
+            if summary
+                .last_accessed_at
+                .as_deref()
+                .is_none_or(|last| entry.accessed_at.as_str() > last)
+            {
+                summary.last_accessed_at = Some(entry.accessed_at.clone());
+            }
+        }
+        summary
+    }
+
+    /// Human-readable reasons the token looks shared; empty when it is within `thresholds`.
+    pub fn abuse_reasons(&self, thresholds: SubscriptionAbuseThresholds) -> Vec<String> {
+        let mut reasons = Vec::new();
+        let ips = self.distinct_ips.len();
+        if thresholds.max_ips > 0 && ips > thresholds.max_ips as usize {
+            reasons.push(format!(
+                "fetched from {ips} distinct IPs (limit {})",
+                thresholds.max_ips
+            ));
+        }
+        let countries = self.distinct_countries.len();
+        if thresholds.max_countries > 0 && countries > thresholds.max_countries as usize {
+            reasons.push(format!(
+                "fetched from {countries} distinct countries (limit {})",
+                thresholds.max_countries
+            ));
+        }
+        reasons
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,128 @@
+use axum::http::HeaderValue;
+use pretty_assertions::assert_eq;
+
+use super::*;
+use crate::inbound_ip_usage::PersistedInboundIpGeo;
+
+struct CountryGeo;
+
+impl GeoLookup for CountryGeo {
+    fn lookup(&self, ip: &str) -> PersistedInboundIpGeo {
+        PersistedInboundIpGeo {
+            country: if ip.starts_with("1.") { "JP" } else { "US" }.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(*name, HeaderValue::from_static(value));
+    }
+    headers
+}
+
+fn entry(accessed_at: &str, source_ip: &str) -> SubscriptionAccessEntry {
+    SubscriptionAccessEntry {
+        accessed_at: accessed_at.to_string(),
+        user_id: "u1".to_string(),
+        token_id: None,
+        node_id: "n1".to_string(),
+        format: "base64".to_string(),
+        source_ip: Some(source_ip.to_string()),
+        country: None,
+        user_agent: None,
+    }
+}
+
+#[test]
+fn forwarding_headers_are_trusted_only_from_loopback_peers() {
+    let forwarded = headers(&[("x-forwarded-for", "203.0.113.7, 10.0.0.1")]);
+    let loopback = Some("127.0.0.1:40000".parse().unwrap());
+    let remote = Some("198.51.100.2:40000".parse().unwrap());
+
+    assert_eq!(
+        client_ip(&forwarded, loopback).as_deref(),
+        Some("203.0.113.7")
+    );
+    assert_eq!(
+        client_ip(&forwarded, remote).as_deref(),
+        Some("198.51.100.2")
+    );
+    assert_eq!(
+        client_ip(
+            &headers(&[
+                ("cf-connecting-ip", "2001:db8::1"),
+                ("x-forwarded-for", "203.0.113.7"),
+            ]),
+            None,
+        )
+        .as_deref(),
+        Some("2001:db8::1")
+    );
+    assert_eq!(
+        client_ip(
+            &headers(&[("x-forwarded-for", "not-an-ip")]),
+            Some("[::ffff:127.0.0.1]:1".parse().unwrap()),
+        )
+        .as_deref(),
+        Some("127.0.0.1")
+    );
+    assert_eq!(client_ip(&HeaderMap::new(), None), None);
+}
+
+#[test]
+fn summary_counts_distinct_ips_and_countries_against_thresholds() {
+    let mut entries = vec![
+        entry("2026-10-17T10:00:00Z", "1.1.1.1"),
+        entry("2026-10-17T12:00:00Z", "8.8.8.8"),
+        entry("2026-10-17T11:00:00Z", "1.0.0.1"),
+        entry("2026-10-17T09:00:00Z", "1.1.1.1"),
+    ];
+    for entry in &mut entries {
+        entry.resolve_country(&CountryGeo);
+    }
+    let summary = SubscriptionAccessSummary::from_entries(&entries);
+    assert_eq!(summary.fetches, 4);
+    assert_eq!(summary.distinct_ips.len(), 3);
+    assert_eq!(
+        summary.distinct_countries,
+        BTreeSet::from(["JP".to_string(), "US".to_string()])
+    );
+    assert_eq!(
+        summary.last_accessed_at.as_deref(),
+        Some("2026-10-17T12:00:00Z")
+    );
+
+    let within = SubscriptionAbuseThresholds {
+        max_ips: 3,
+        max_countries: 2,
+    };
+    assert!(summary.abuse_reasons(within).is_empty());
+    assert_eq!(
+        summary.abuse_reasons(SubscriptionAbuseThresholds {
+            max_ips: 2,
+            max_countries: 1,
+        }),
+        vec![
+            "fetched from 3 distinct IPs (limit 2)".to_string(),
+            "fetched from 2 distinct countries (limit 1)".to_string(),
+        ]
+    );
+    assert!(
+        summary
+            .abuse_reasons(SubscriptionAbuseThresholds {
+                max_ips: 0,
+                max_countries: 0,
+            })
+            .is_empty()
+    );
+}
+
+#[test]
+fn record_keys_share_the_user_prefix() {
+    let key = subscription_access_record_key("u1", "n1", 42);
+    assert_eq!(key, "subscription-access:user:u1:n1:42");
+    assert!(key.starts_with(&subscription_access_user_key_prefix("u1")));
+}
@@ -0,0 +1,635 @@
+//! Bulk user import/export: one flat record per user, as JSON or CSV, and the validated plan
+//! that turns a list of records into one atomic `ImportUsers` command.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    domain::{User, UserPriorityTier, UserQuotaReset, normalize_user_expires_at},
+    id::new_ulid_string,
+    state::{DesiredStateCommand, PersistedState, StoreError, membership_key},
+};
+
+pub const CSV_COLUMNS: [&str; 12] = [
+    "user_id",
+    "display_name",
+    "subscription_token",
+    "priority_tier",
+    "quota_reset",
+    "expires_at",
+    "disabled",
+    "vless_uuid",
+    "ss2022_psk_b64",
+    "endpoints",
+    "global_weight",
+    "node_weights",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct UserBulkRecord {
+    /// Updates this user when it exists; otherwise a user is created with this id, or with a
+    /// fresh id when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    pub display_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription_token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority_tier: Option<UserPriorityTier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quota_reset: Option<UserQuotaReset>,
+    /// RFC3339; an empty string clears the expiry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+    /// Pinned credentials (see `UserPinnedCredentials`); an empty string unpins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vless_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ss2022_psk_b64: Option<String>,
+    /// Endpoint ids or tags; replaces the user's access when present and leaves it untouched
+    /// when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoints: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_weight: Option<u16>,
+    /// Node id or name to the user's quota weight on that node; unlisted nodes are untouched.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_weights: BTreeMap<String, u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserBulkDocument {
+    pub users: Vec<UserBulkRecord>,
+}
+
+/// A validated record with every reference resolved to ids.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserImportEntry {
+    pub user: User,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub global_weight: Option<u16>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub node_weights: BTreeMap<String, u16>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UserImportAction {
+    Create,
+    Update,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct UserImportRow {
+    /// 1-based position of the record in the input.
+    pub row: usize,
+    pub user_id: String,
+    pub display_name: String,
+    pub action: UserImportAction,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct UserImportIssue {
+    pub row: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for UserImportIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserImportPlan {
+    pub rows: Vec<UserImportRow>,
+    pub entries: Vec<UserImportEntry>,
+    pub issues: Vec<UserImportIssue>,
+}
+
+impl UserImportPlan {
+    pub fn count(&self, action: UserImportAction) -> usize {
+        self.rows.iter().filter(|row| row.action == action).count()
+    }
+}
+
+pub fn export_users(state: &PersistedState) -> Vec<UserBulkRecord> {
+    state
+        .users
+        .values()
+        .map(|user| UserBulkRecord {
+            user_id: Some(user.user_id.clone()),
+            display_name: user.display_name.clone(),
+            subscription_token: Some(user.subscription_token.clone()),
+            priority_tier: Some(user.priority_tier),
+            quota_reset: Some(user.quota_reset.clone()),
+            expires_at: user.expires_at.clone(),
+            disabled: Some(user.disabled),
+            vless_uuid: user.pinned_credentials.vless_uuid.clone(),
+            ss2022_psk_b64: user.pinned_credentials.ss2022_psk_b64.clone(),
+            endpoints: Some(
+                state
+                    .node_user_endpoint_memberships
+                    .iter()
+                    .filter(|membership| membership.user_id == user.user_id)
+                    .map(|membership| membership.endpoint_id.clone())
+                    .collect(),
+            ),
+            global_weight: state
+                .user_global_weights
+                .get(&user.user_id)
+                .map(|config| config.weight),
+            node_weights: state
+                .user_node_weights
+                .get(&user.user_id)
+                .map(|weights| {
+                    weights
+                        .iter()
+                        .map(|(node_id, config)| (node_id.clone(), config.weight))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Validates every record against `state` and against the records before it, then trial-applies
+/// the resolved entries in order so that apply-time errors surface here as well.
+pub fn plan_user_import(state: &PersistedState, records: Vec<UserBulkRecord>) -> UserImportPlan {
+    let mut plan = UserImportPlan::default();
+    let mut scratch = state.clone();
+    let mut seen_user_ids = BTreeSet::new();
+    let mut token_owners = state
+        .users
+        .values()
+        .map(|user| (user.subscription_token.clone(), user.user_id.clone()))
+        .chain(
+            state
+                .subscription_tokens
+                .values()
+                .map(|token| (token.token.clone(), token.user_id.clone())),
+        )
+        .collect::<BTreeMap<_, _>>();
+
+    for (index, record) in records.into_iter().enumerate() {
+        let row = index + 1;
+        let mut messages = Vec::new();
+
+        if record.display_name.trim().is_empty() {
+            messages.push("display_name is required".to_string());
+        }
+        if let Some(user_id) = record.user_id.as_deref() {
+            if user_id.is_empty() || user_id.chars().any(char::is_whitespace) {
+                messages.push(format!("invalid user_id: {user_id:?}"));
+            } else if !seen_user_ids.insert(user_id.to_string()) {
+                messages.push(format!("user {user_id} appears more than once"));
+            }
+        }
+
+        let existing = record
+            .user_id
+            .as_deref()
+            .and_then(|user_id| state.users.get(user_id));
+        let action = if existing.is_some() {
+            UserImportAction::Update
+        } else {
+            UserImportAction::Create
+        };
+        let mut user = existing.cloned().unwrap_or_else(|| User {
+            user_id: record.user_id.clone().unwrap_or_else(new_ulid_string),
+            display_name: String::new(),
+            subscription_token: format!("sub_{}", new_ulid_string()),
+            credential_epoch: 0,
+            priority_tier: Default::default(),
+            quota_reset: Default::default(),
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
+        });
+        user.display_name = record.display_name;
+        if let Some(token) = record.subscription_token {
+            if token.trim().is_empty() {
+                messages.push("subscription_token must not be blank".to_string());
+            }
+            user.subscription_token = token;
+        }
+        if let Some(priority_tier) = record.priority_tier {
+            user.priority_tier = priority_tier;
+        }
+        if let Some(quota_reset) = record.quota_reset {
+            user.quota_reset = quota_reset;
+        }
+        if let Some(expires_at) = record.expires_at {
+            user.expires_at = None;
+            if !expires_at.is_empty() {
+                match normalize_user_expires_at(&expires_at) {
+                    Ok(expires_at) => user.expires_at = Some(expires_at),
+                    Err(error) => messages.push(error.to_string()),
+                }
+            }
+        }
+        if let Some(disabled) = record.disabled {
+            user.disabled = disabled;
+        }
+        if let Some(uuid) = record.vless_uuid {
+            user.pinned_credentials.vless_uuid = (!uuid.is_empty()).then_some(uuid);
+        }
+        if let Some(psk) = record.ss2022_psk_b64 {
+            user.pinned_credentials.ss2022_psk_b64 = (!psk.is_empty()).then_some(psk);
+        }
+        match token_owners.get(&user.subscription_token) {
+            Some(owner) if *owner != user.user_id => messages.push(format!(
+                "subscription token is already used by user {owner}"
+            )),
+            _ => {
+                token_owners.insert(user.subscription_token.clone(), user.user_id.clone());
+            }
+        }
+
+        let endpoint_ids = record.endpoints.map(|refs| {
+            refs.iter()
+                .filter_map(|reference| match resolve_endpoint(state, reference) {
+                    Ok(endpoint_id) => Some(endpoint_id),
+                    Err(message) => {
+                        messages.push(message);
+                        None
+                    }
+                })
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        });
+        let mut node_weights = BTreeMap::new();
+        for (reference, weight) in record.node_weights {
+            match resolve_node(state, &reference) {
+                Ok(node_id) => {
+                    if node_weights.insert(node_id.clone(), weight).is_some() {
+                        messages.push(format!("node {node_id} is weighted more than once"));
+                    }
+                }
+                Err(message) => messages.push(message),
+            }
+        }
+
+        if messages.is_empty() {
+            let entry = UserImportEntry {
+                user,
+                endpoint_ids,
+                global_weight: record.global_weight,
+                node_weights,
+            };
+            match apply_user_import_entry(&mut scratch, &entry) {
+                Ok(()) => {
+                    plan.rows.push(UserImportRow {
+                        row,
+                        user_id: entry.user.user_id.clone(),
+                        display_name: entry.user.display_name.clone(),
+                        action,
+                    });
+                    plan.entries.push(entry);
+                }
+                Err(error) => messages.push(error.to_string()),
+            }
+        }
+        plan.issues.extend(
+            messages
+                .into_iter()
+                .map(|message| UserImportIssue { row, message }),
+        );
+    }
+    plan
+}
+
+fn resolve_endpoint(state: &PersistedState, reference: &str) -> Result<String, String> {
+    if state.endpoints.contains_key(reference) {
+        return Ok(reference.to_string());
+    }
+    let mut by_tag = state
+        .endpoints
+        .values()
+        .filter(|endpoint| endpoint.tag == reference);
+    match (by_tag.next(), by_tag.next()) {
+        (Some(endpoint), None) => Ok(endpoint.endpoint_id.clone()),
+        (Some(_), Some(_)) => Err(format!("endpoint tag {reference} is ambiguous, use the id")),
+        _ => Err(format!("endpoint not found: {reference}")),
+    }
+}
+
+pub(crate) fn resolve_node(state: &PersistedState, reference: &str) -> Result<String, String> {
+    if state.nodes.contains_key(reference) {
+        return Ok(reference.to_string());
+    }
+    let mut by_name = state
+        .nodes
+        .values()
+        .filter(|node| node.node_name == reference);
+    match (by_name.next(), by_name.next()) {
+        (Some(node), None) => Ok(node.node_id.clone()),
+        (Some(_), Some(_)) => Err(format!("node name {reference} is ambiguous, use the id")),
+        _ => Err(format!("node not found: {reference}")),
+    }
+}
+
+/// Applies one entry through the same commands the single-user admin API uses.
+pub fn apply_user_import_entry(
+    state: &mut PersistedState,
+    entry: &UserImportEntry,
+) -> Result<(), StoreError> {
+    let user_id = &entry.user.user_id;
+    DesiredStateCommand::UpsertUser {
+        user: entry.user.clone(),
+    }
+    .apply(state)?;
+    if let Some(endpoint_ids) = &entry.endpoint_ids {
+        DesiredStateCommand::ReplaceUserAccess {
+            user_id: user_id.clone(),
+            endpoint_ids: endpoint_ids.clone(),
+        }
+        .apply(state)?;
+    }
+    if let Some(weight) = entry.global_weight {
+        DesiredStateCommand::SetUserGlobalWeight {
+            user_id: user_id.clone(),
+            weight,
+        }
+        .apply(state)?;
+    }
+    for (node_id, weight) in &entry.node_weights {
+        DesiredStateCommand::SetUserNodeWeight {
+            user_id: user_id.clone(),
+            node_id: node_id.clone(),
+            weight: *weight,
+        }
+        .apply(state)?;
+    }
+    Ok(())
+}
+
+/// Membership keys of the users whose access the entries replace, for local usage cleanup.
+pub fn replaced_membership_keys(
+    state: &PersistedState,
+    entries: &[UserImportEntry],
+) -> BTreeSet<String> {
+    let user_ids = entries
+        .iter()
+        .filter(|entry| entry.endpoint_ids.is_some())
+        .map(|entry| entry.user.user_id.as_str())
+        .collect::<BTreeSet<_>>();
+    state
+        .node_user_endpoint_memberships
+        .iter()
+        .filter(|m| user_ids.contains(m.user_id.as_str()))
+        .map(|m| membership_key(&m.user_id, &m.endpoint_id))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserCsvError {
+    /// 1-based record number; `0` is the header.
+    pub row: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for UserCsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.row == 0 {
+            write!(f, "csv header: {}", self.message)
+        } else {
+            write!(f, "csv row {}: {}", self.row, self.message)
+        }
+    }
+}
+
+impl std::error::Error for UserCsvError {}
+
+/// Lists are `;`-separated, node weights are `node=weight` pairs and `quota_reset` is the JSON
+/// object the API takes. Blank cells leave the field unset.
+pub fn records_to_csv(records: &[UserBulkRecord]) -> String {
+    let mut out = String::new();
+    push_csv_line(
+        &mut out,
+        CSV_COLUMNS.iter().map(|column| column.to_string()),
+    );
+    for record in records {
+        let cells = [
+            record.user_id.clone().unwrap_or_default(),
+            record.display_name.clone(),
+            record.subscription_token.clone().unwrap_or_default(),
+            record
+                .priority_tier
+                .map(|tier| serde_json::to_value(tier).expect("serialize priority tier"))
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            record
+                .quota_reset
+                .as_ref()
+                .map(|reset| serde_json::to_string(reset).expect("serialize quota reset"))
+                .unwrap_or_default(),
+            record.expires_at.clone().unwrap_or_default(),
+            record
+                .disabled
+                .map(|disabled| disabled.to_string())
+                .unwrap_or_default(),
+            record.vless_uuid.clone().unwrap_or_default(),
+            record.ss2022_psk_b64.clone().unwrap_or_default(),
+            record
+                .endpoints
+                .as_ref()
+                .map(|endpoints| endpoints.join(";"))
+                .unwrap_or_default(),
+            record
+                .global_weight
+                .map(|weight| weight.to_string())
+                .unwrap_or_default(),
+            record
+                .node_weights
+                .iter()
+                .map(|(node, weight)| format!("{node}={weight}"))
+                .collect::<Vec<_>>()
+                .join(";"),
+        ];
+        push_csv_line(&mut out, cells.into_iter());
+    }
+    out
+}
+
+pub fn records_from_csv(raw: &str) -> Result<Vec<UserBulkRecord>, UserCsvError> {
+    let mut lines = parse_csv(raw)
+        .map_err(|message| UserCsvError { row: 0, message })?
+        .into_iter();
+    let header = lines.next().ok_or_else(|| UserCsvError {
+        row: 0,
+        message: "missing header line".to_string(),
+    })?;
+    let header = header
+        .iter()
+        .map(|column| column.trim().to_string())
+        .collect::<Vec<_>>();
+    for column in &header {
+        if !CSV_COLUMNS.contains(&column.as_str()) {
+            return Err(UserCsvError {
+                row: 0,
+                message: format!("unknown column: {column}"),
+            });
+        }
+    }
+    if !header.iter().any(|column| column == "display_name") {
+        return Err(UserCsvError {
+            row: 0,
+            message: "missing column: display_name".to_string(),
+        });
+    }
+
+    lines
+        .enumerate()
+        .map(|(index, cells)| {
+            let row = index + 1;
+            if cells.len() != header.len() {
+                return Err(UserCsvError {
+                    row,
+                    message: format!("expected {} cells, got {}", header.len(), cells.len()),
+                });
+            }
+            let mut record = UserBulkRecord::default();
+            for (column, cell) in header.iter().zip(cells) {
+                let cell = cell.trim();
+                if cell.is_empty() {
+                    continue;
+                }
+                set_csv_cell(&mut record, column, cell)
+                    .map_err(|message| UserCsvError { row, message })?;
+            }
+            Ok(record)
+        })
+        .collect()
+}
+
+fn set_csv_cell(record: &mut UserBulkRecord, column: &str, cell: &str) -> Result<(), String> {
+    match column {
+        "user_id" => record.user_id = Some(cell.to_string()),
+        "display_name" => record.display_name = cell.to_string(),
+        "subscription_token" => record.subscription_token = Some(cell.to_string()),
+        "priority_tier" => {
+            record.priority_tier = Some(
+                serde_json::from_value(serde_json::Value::String(cell.to_ascii_lowercase()))
+                    .map_err(|_| format!("invalid priority_tier: {cell}"))?,
+            );
+        }
+        "quota_reset" => {
+            record.quota_reset = Some(
+                serde_json::from_str(cell)
+                    .map_err(|error| format!("invalid quota_reset: {error}"))?,
+            );
+        }
+        "expires_at" => record.expires_at = Some(cell.to_string()),
+        "disabled" => {
+            record.disabled = Some(
+                cell.to_ascii_lowercase()
+                    .parse()
+                    .map_err(|_| format!("invalid disabled: {cell}"))?,
+            );
+        }
+        "vless_uuid" => record.vless_uuid = Some(cell.to_string()),
+        "ss2022_psk_b64" => record.ss2022_psk_b64 = Some(cell.to_string()),
+        "endpoints" => {
+            record.endpoints = Some(
+                cell.split(';')
+                    .map(str::trim)
+                    .filter(|endpoint| !endpoint.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            );
+        }
+        "global_weight" => {
+            record.global_weight = Some(
+                cell.parse()
+                    .map_err(|_| format!("invalid global_weight: {cell}"))?,
+            );
+        }
+        "node_weights" => {
+            for pair in cell
+                .split(';')
+                .map(str::trim)
+                .filter(|pair| !pair.is_empty())
+            {
+                let (node, weight) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("node_weights expects node=weight, got {pair}"))?;
+                let weight = weight
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid weight for node {}: {weight}", node.trim()))?;
+                record.node_weights.insert(node.trim().to_string(), weight);
+            }
+        }
+        _ => unreachable!("columns are checked against CSV_COLUMNS"),
+    }
+    Ok(())
+}
+
+fn push_csv_line(out: &mut String, cells: impl Iterator<Item = String>) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&cell.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(&cell);
+        }
+    }
+    out.push('\n');
+}
+
+/// RFC 4180 records; blank lines are skipped.
+fn parse_csv(raw: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut lines = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut in_quotes = false;
+    let mut chars = raw.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => cell.push(ch),
+            }
+            continue;
+        }
+        match ch {
+            '"' if cell.is_empty() => in_quotes = true,
+            ',' => cells.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                cells.push(std::mem::take(&mut cell));
+                lines.push(std::mem::take(&mut cells));
+            }
+            _ => cell.push(ch),
+        }
+    }
+    if in_quotes {
+        return Err("unterminated quoted cell".to_string());
+    }
+    if !cell.is_empty() || !cells.is_empty() {
+        cells.push(cell);
+        lines.push(cells);
+    }
+    lines.retain(|cells| !(cells.len() == 1 && cells[0].trim().is_empty()));
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests;
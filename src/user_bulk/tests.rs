@@ -0,0 +1,261 @@
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use super::*;
+use crate::{
+    domain::{Endpoint, EndpointKind, Node, NodeQuotaReset},
+    state::{DesiredStateApplyResult, NodeUserEndpointMembership},
+};
+
+fn node(node_id: &str, node_name: &str) -> Node {
+    Node {
+        node_id: node_id.to_string(),
+        node_name: node_name.to_string(),
+        access_host: format!("{node_id}.example.com"),
+        api_base_url: format!("https://{node_id}.example.com"),
+        quota_limit_bytes: 0,
+        quota_reset: NodeQuotaReset::default(),
+    }
+}
+
+fn endpoint(endpoint_id: &str, node_id: &str, tag: &str) -> Endpoint {
+    Endpoint {
+        endpoint_id: endpoint_id.to_string(),
+        node_id: node_id.to_string(),
+        tag: tag.to_string(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 443,
+        meta: json!({}),
+    }
+}
+
+fn user(user_id: &str) -> User {
+    User {
+        user_id: user_id.to_string(),
+        display_name: user_id.to_string(),
+        subscription_token: format!("sub_{user_id}"),
+        credential_epoch: 3,
+        priority_tier: Default::default(),
+        quota_reset: Default::default(),
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: Some(2),
+        pinned_credentials: Default::default(),
+    }
+}
+
+fn cluster_state() -> PersistedState {
+    let mut state = PersistedState::empty();
+    state.nodes.insert("n1".to_string(), node("n1", "tokyo"));
+    state.nodes.insert("n2".to_string(), node("n2", "osaka"));
+    state
+        .endpoints
+        .insert("e1".to_string(), endpoint("e1", "n1", "tokyo-ss"));
+    state
+        .endpoints
+        .insert("e2".to_string(), endpoint("e2", "n2", "osaka-ss"));
+    state.users.insert("u1".to_string(), user("u1"));
+    state
+        .node_user_endpoint_memberships
+        .insert(NodeUserEndpointMembership {
+            user_id: "u1".to_string(),
+            node_id: "n1".to_string(),
+            endpoint_id: "e1".to_string(),
+        });
+    state
+}
+
+#[test]
+fn csv_round_trips_records_with_quoting() {
+    let records = vec![
+        UserBulkRecord {
+            user_id: Some("u1".to_string()),
+            display_name: "Alice, \"ops\"".to_string(),
+            subscription_token: Some("sub_u1".to_string()),
+            priority_tier: Some(UserPriorityTier::P1),
+            quota_reset: Some(UserQuotaReset::Daily {
+                tz_offset_minutes: 0,
+            }),
+            expires_at: Some("2026-12-31T00:00:00Z".to_string()),
+            disabled: Some(true),
+            vless_uuid: Some("5f6e4d3c-2b1a-4098-8765-43210fedcba9".to_string()),
+            ss2022_psk_b64: Some("AAECAwQFBgcICQoLDA0ODw==".to_string()),
+            endpoints: Some(vec!["e1".to_string(), "osaka-ss".to_string()]),
+            global_weight: Some(200),
+            node_weights: BTreeMap::from([("tokyo".to_string(), 50), ("n2".to_string(), 10)]),
+        },
+        UserBulkRecord {
+            display_name: "Bob".to_string(),
+            ..Default::default()
+        },
+    ];
+
+    let csv = records_to_csv(&records);
+    assert_eq!(csv.lines().next(), Some(CSV_COLUMNS.join(",").as_str()));
+    assert_eq!(records_from_csv(&csv).unwrap(), records);
+}
+
+#[test]
+fn csv_accepts_column_subsets_and_reports_bad_cells_by_row() {
+    let records = records_from_csv("display_name,priority_tier\r\nAlice,P3\r\n\r\nBob,p2\r\n")
+        .expect("parse csv");
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].priority_tier, Some(UserPriorityTier::P3));
+    assert_eq!(records[1].display_name, "Bob");
+
+    let error = records_from_csv("display_name,global_weight\nAlice,1\nBob,heavy\n").unwrap_err();
+    assert_eq!(error.row, 2);
+    assert_eq!(error.message, "invalid global_weight: heavy");
+
+    let error = records_from_csv("display_name,email\nAlice,a@example.com\n").unwrap_err();
+    assert_eq!(error.to_string(), "csv header: unknown column: email");
+}
+
+#[test]
+fn plan_resolves_references_and_keeps_unlisted_fields() {
+    let state = cluster_state();
+    let plan = plan_user_import(
+        &state,
+        vec![
+            UserBulkRecord {
+                user_id: Some("u1".to_string()),
+                display_name: "Alice".to_string(),
+                endpoints: Some(vec!["osaka-ss".to_string()]),
+                node_weights: BTreeMap::from([("tokyo".to_string(), 50)]),
+                ..Default::default()
+            },
+            UserBulkRecord {
+                display_name: "Bob".to_string(),
+                priority_tier: Some(UserPriorityTier::P3),
+                global_weight: Some(7),
+                ..Default::default()
+            },
+        ],
+    );
+
+    assert_eq!(plan.issues, Vec::new());
+    assert_eq!(plan.count(UserImportAction::Update), 1);
+    assert_eq!(plan.count(UserImportAction::Create), 1);
+    let alice = &plan.entries[0];
+    assert_eq!(alice.user.display_name, "Alice");
+    assert_eq!(alice.user.credential_epoch, 3);
+    assert_eq!(alice.user.max_concurrent_ips, Some(2));
+    assert_eq!(alice.endpoint_ids, Some(vec!["e2".to_string()]));
+    assert_eq!(alice.node_weights, BTreeMap::from([("n1".to_string(), 50)]));
+    let bob = &plan.entries[1];
+    assert!(bob.user.subscription_token.starts_with("sub_"));
+    assert_eq!(bob.endpoint_ids, None);
+
+    let mut applied = state.clone();
+    let out = DesiredStateCommand::ImportUsers {
+        entries: plan.entries.clone(),
+    }
+    .apply(&mut applied)
+    .unwrap();
+    assert_eq!(out, DesiredStateApplyResult::Applied);
+    assert_eq!(applied.users.len(), 2);
+    let alice_access = applied
+        .node_user_endpoint_memberships
+        .iter()
+        .filter(|m| m.user_id == "u1")
+        .map(|m| m.endpoint_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(alice_access, vec!["e2"]);
+    assert_eq!(applied.user_node_weights["u1"]["n1"].weight, 50);
+    assert_eq!(applied.user_global_weights[&bob.user.user_id].weight, 7);
+    assert_eq!(
+        replaced_membership_keys(&state, &plan.entries),
+        BTreeSet::from([membership_key("u1", "e1")])
+    );
+}
+
+#[test]
+fn plan_collects_every_issue_up_front() {
+    let state = cluster_state();
+    let plan = plan_user_import(
+        &state,
+        vec![
+            UserBulkRecord {
+                display_name: " ".to_string(),
+                ..Default::default()
+            },
+            UserBulkRecord {
+                display_name: "Carol".to_string(),
+                subscription_token: Some("sub_u1".to_string()),
+                endpoints: Some(vec!["missing".to_string()]),
+                ..Default::default()
+            },
+            UserBulkRecord {
+                user_id: Some("new".to_string()),
+                display_name: "Dave".to_string(),
+                node_weights: BTreeMap::from([("paris".to_string(), 1)]),
+                ..Default::default()
+            },
+            UserBulkRecord {
+                user_id: Some("new".to_string()),
+                display_name: "Dave again".to_string(),
+                ..Default::default()
+            },
+            UserBulkRecord {
+                display_name: "Erin".to_string(),
+                quota_reset: Some(UserQuotaReset::Monthly {
+                    day_of_month: 40,
+                    tz_offset_minutes: 0,
+                }),
+                ..Default::default()
+            },
+        ],
+    );
+
+    let issues = plan
+        .issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(issues.len(), 6, "{issues:?}");
+    assert_eq!(issues[0], "row 1: display_name is required");
+    assert_eq!(
+        issues[1],
+        "row 2: subscription token is already used by user u1"
+    );
+    assert_eq!(issues[2], "row 2: endpoint not found: missing");
+    assert_eq!(issues[3], "row 3: node not found: paris");
+    assert_eq!(issues[4], "row 4: user new appears more than once");
+    assert!(issues[5].starts_with("row 5: "), "{issues:?}");
+    assert!(plan.entries.is_empty());
+}
+
+#[test]
+fn export_lists_access_and_weights_and_reimports_as_updates() {
+    let mut state = cluster_state();
+    DesiredStateCommand::SetUserNodeWeight {
+        user_id: "u1".to_string(),
+        node_id: "n2".to_string(),
+        weight: 9,
+    }
+    .apply(&mut state)
+    .unwrap();
+
+    let records = export_users(&state);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].endpoints, Some(vec!["e1".to_string()]));
+    assert_eq!(
+        records[0].node_weights,
+        BTreeMap::from([("n2".to_string(), 9)])
+    );
+
+    let plan = plan_user_import(&state, records);
+    assert_eq!(plan.issues, Vec::new());
+    assert_eq!(plan.count(UserImportAction::Update), 1);
+    let mut reimported = state.clone();
+    DesiredStateCommand::ImportUsers {
+        entries: plan.entries,
+    }
+    .apply(&mut reimported)
+    .unwrap();
+    assert_eq!(reimported.users, state.users);
+    assert_eq!(
+        reimported.node_user_endpoint_memberships,
+        state.node_user_endpoint_memberships
+    );
+}
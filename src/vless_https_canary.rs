@@ -75,6 +75,7 @@ struct CanaryMeshAuth {
     cluster_id: String,
     cluster_ca_key_pem: String,
     cluster_ca_cert_pem: String,
+    alternate_roots: crate::internal_auth::AlternateRoots,
     loopback_base_url: String,
 }
 
@@ -486,6 +487,7 @@ pub async fn spawn(
     cluster_id: String,
     cluster_ca_key_pem: String,
     cluster_ca_cert_pem: String,
+    alternate_roots: crate::internal_auth::AlternateRoots,
 ) -> anyhow::Result<Option<std::thread::JoinHandle<()>>> {
     let prepared = match prepare_runtime(config.as_ref()).await {
         Ok(prepared) => prepared,
@@ -516,6 +518,7 @@ pub async fn spawn(
             cluster_id,
             cluster_ca_key_pem,
             cluster_ca_cert_pem,
+            alternate_roots,
             loopback_base_url: format!("http://127.0.0.1:{}", config.bind.port()),
         }),
     };
@@ -31,6 +31,7 @@ pub(super) async fn proxy_mesh_request(
     let verified = internal_auth::verify_request_v2(
         &auth.cluster_ca_key_pem,
         &auth.cluster_ca_cert_pem,
+        &auth.alternate_roots.snapshot(),
         &parts.method,
         &parts.uri,
         &parts.headers,
@@ -46,6 +46,9 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: "hash".to_string(),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::host_fixture465().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -69,9 +72,14 @@ fn test_config(data_dir: PathBuf) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: crate::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
@@ -671,6 +679,7 @@ async fn signed_mesh_health_reaches_loopback_over_http1_and_http2() {
     let verified = internal_auth::verify_request_v2(
         &ca.key_pem,
         &ca.cert_pem,
+        &[],
         &Method::GET,
         &request_uri,
         &signed_headers,
@@ -749,6 +758,7 @@ async fn signed_mesh_health_reaches_loopback_over_http1_and_http2() {
             cluster_id: xp_test_fixtures::cluster_fixture476().to_owned(),
             cluster_ca_key_pem: ca.key_pem.clone(),
             cluster_ca_cert_pem: ca.cert_pem.clone(),
+            alternate_roots: Default::default(),
             loopback_base_url: format!("http://{loopback_addr}"),
         }),
     };
@@ -793,6 +803,7 @@ async fn signed_mesh_health_reaches_loopback_over_http1_and_http2() {
         internal_auth::verify_ack_v2(
             &ca.key_pem,
             &ca.cert_pem,
+            &[],
             &verified,
             target_id,
             response.status().as_u16(),
@@ -3,8 +3,9 @@ use base64::Engine as _;
 use crate::{
     domain::{Endpoint, EndpointKind},
     protocol::{
-        SS2022_METHOD_2022_BLAKE3_AES_128_GCM, SS2022_PSK_LEN_BYTES_AES_128, Ss2022EndpointMeta,
-        VLESS_XHTTP_PATH, VlessRealityTransport, VlessRealityVisionTcpEndpointMeta,
+        Hysteria2EndpointMeta, RealityConfig, RealityKeys, Ss2022EndpointMeta,
+        TrojanRealityTcpEndpointMeta, TrojanTlsTcpEndpointMeta, VLESS_XHTTP_PATH,
+        VlessRealityTransport, VlessRealityVisionTcpEndpointMeta, ss2022_psk_len_bytes,
         validate_short_id,
     },
     xray::proto::xray,
@@ -18,13 +19,18 @@ const TYPE_VLESS_ACCOUNT: &str = "xray.proxy.vless.Account";
 const TYPE_SS2022_MULTIUSER_SERVER_CONFIG: &str =
     "xray.proxy.shadowsocks_2022.MultiUserServerConfig";
 const TYPE_SS2022_ACCOUNT: &str = "xray.proxy.shadowsocks_2022.Account";
+const TYPE_TROJAN_SERVER_CONFIG: &str = "xray.proxy.trojan.ServerConfig";
+const TYPE_TROJAN_ACCOUNT: &str = "xray.proxy.trojan.Account";
+const TYPE_HYSTERIA_SERVER_CONFIG: &str = "xray.proxy.hysteria.ServerConfig";
+const TYPE_HYSTERIA_ACCOUNT: &str = "xray.proxy.hysteria.account.Account";
+const TYPE_HYSTERIA_TRANSPORT_CONFIG: &str = "xray.transport.internet.hysteria.Config";
+const TYPE_TLS_SECURITY_CONFIG: &str = "xray.transport.internet.tls.Config";
 const TYPE_TCP_TRANSPORT_CONFIG: &str = "xray.transport.internet.tcp.Config";
 const TYPE_SPLITHTTP_TRANSPORT_CONFIG: &str = "xray.transport.internet.splithttp.Config";
 const TYPE_REALITY_SECURITY_CONFIG: &str = "xray.transport.internet.reality.Config";
 const TYPE_SOCKS_SERVER_CONFIG: &str = "xray.proxy.socks.ServerConfig";
 const TYPE_VLESS_OUTBOUND_CONFIG: &str = "xray.proxy.vless.outbound.Config";
 const TYPE_FREEDOM_CONFIG: &str = "xray.proxy.freedom.Config";
-const TYPE_ROUTING_CONFIG: &str = "xray.app.router.Config";
 
 // In Xray-core, TypedMessage.Type is set to `message.ProtoReflect().Descriptor().FullName()`.
 // Therefore the correct type string is the protobuf full name, e.g. "xray.app.proxyman.command.AddUserOperation".
@@ -109,6 +115,30 @@ fn parse_ss2022_meta(endpoint: &Endpoint) -> Result<Ss2022EndpointMeta, BuildErr
     })
 }
 
+fn parse_trojan_meta(endpoint: &Endpoint) -> Result<TrojanRealityTcpEndpointMeta, BuildError> {
+    serde_json::from_value(endpoint.meta.clone()).map_err(|e| BuildError::InvalidEndpointMeta {
+        endpoint_id: endpoint.endpoint_id.clone(),
+        kind: endpoint.kind.clone(),
+        reason: e.to_string(),
+    })
+}
+
+fn parse_trojan_tls_meta(endpoint: &Endpoint) -> Result<TrojanTlsTcpEndpointMeta, BuildError> {
+    serde_json::from_value(endpoint.meta.clone()).map_err(|e| BuildError::InvalidEndpointMeta {
+        endpoint_id: endpoint.endpoint_id.clone(),
+        kind: endpoint.kind.clone(),
+        reason: e.to_string(),
+    })
+}
+
+fn parse_hysteria2_meta(endpoint: &Endpoint) -> Result<Hysteria2EndpointMeta, BuildError> {
+    serde_json::from_value(endpoint.meta.clone()).map_err(|e| BuildError::InvalidEndpointMeta {
+        endpoint_id: endpoint.endpoint_id.clone(),
+        kind: endpoint.kind.clone(),
+        reason: e.to_string(),
+    })
+}
+
 fn listen_ip_any() -> xray::common::net::IpOrDomain {
     xray::common::net::IpOrDomain {
         address: Some(xray::common::net::ip_or_domain::Address::Ip(vec![
@@ -149,6 +179,45 @@ fn xhttp_transport_settings() -> xray::transport::internet::TransportConfig {
     }
 }
 
+const HYSTERIA2_VERSION: i32 = 2;
+
+fn hysteria2_transport_settings() -> xray::transport::internet::TransportConfig {
+    let hysteria = xray::transport::internet::hysteria::Config {
+        version: HYSTERIA2_VERSION,
+        auth: String::new(),
+    };
+    xray::transport::internet::TransportConfig {
+        protocol_name: "hysteria".to_string(),
+        settings: Some(to_typed_message(TYPE_HYSTERIA_TRANSPORT_CONFIG, &hysteria)),
+    }
+}
+
+fn tls_server_config(
+    endpoint: &Endpoint,
+    server_name: &str,
+    cert_pem: &str,
+    key_pem: &str,
+    alpn: &str,
+) -> Result<xray::transport::internet::tls::Config, BuildError> {
+    if cert_pem.trim().is_empty() || key_pem.trim().is_empty() {
+        return Err(BuildError::InvalidEndpointMeta {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            kind: endpoint.kind.clone(),
+            reason: "tls_cert_pem and tls_key_pem must be non-empty".to_string(),
+        });
+    }
+    Ok(xray::transport::internet::tls::Config {
+        allow_insecure: false,
+        certificate: vec![xray::transport::internet::tls::Certificate {
+            certificate: cert_pem.as_bytes().to_vec(),
+            key: key_pem.as_bytes().to_vec(),
+            usage: xray::transport::internet::tls::certificate::Usage::Encipherment as i32,
+        }],
+        server_name: server_name.to_string(),
+        next_protocol: vec![alpn.to_string()],
+    })
+}
+
 fn business_inbound_socket_settings() -> xray::transport::internet::SocketConfig {
     xray::transport::internet::SocketConfig {
         tcp_keep_alive_interval: 30,
@@ -196,10 +265,65 @@ fn decode_short_id_hex(endpoint: &Endpoint, short_id_hex: &str) -> Result<Vec<u8
     })
 }
 
+fn build_reality_server_config(
+    endpoint: &Endpoint,
+    reality: RealityConfig,
+    reality_keys: &RealityKeys,
+    short_ids: &[String],
+    active_short_id: &str,
+) -> Result<xray::transport::internet::reality::Config, BuildError> {
+    if !short_ids.iter().any(|s| s == active_short_id) {
+        return Err(BuildError::InvalidEndpointMeta {
+            endpoint_id: endpoint.endpoint_id.clone(),
+            kind: endpoint.kind.clone(),
+            reason: "active_short_id must be included in short_ids".to_string(),
+        });
+    }
+
+    let private_key = decode_reality_private_key_b64url_nopad(endpoint, &reality_keys.private_key)?;
+    let short_ids = short_ids
+        .iter()
+        .map(|s| decode_short_id_hex(endpoint, s))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(xray::transport::internet::reality::Config {
+        show: false,
+        dest: normalize_reality_dest_for_xray(&reality.dest),
+        r#type: "tcp".to_string(),
+        xver: 0,
+        server_names: reality.server_names,
+        private_key,
+        min_client_ver: vec![],
+        max_client_ver: vec![],
+        max_time_diff: 0,
+        short_ids,
+        mldsa65_seed: vec![],
+        limit_fallback_upload: None,
+        limit_fallback_download: None,
+        fingerprint: normalize_reality_fingerprint(&reality.fingerprint),
+        server_name: String::new(),
+        public_key: vec![],
+        short_id: vec![],
+        mldsa65_verify: vec![],
+        spider_x: String::new(),
+        spider_y: vec![],
+        master_key_log: String::new(),
+    })
+}
+
+fn ss2022_method_psk_len(endpoint: &Endpoint, method: &str) -> Result<usize, BuildError> {
+    ss2022_psk_len_bytes(method).ok_or_else(|| BuildError::InvalidEndpointMeta {
+        endpoint_id: endpoint.endpoint_id.clone(),
+        kind: endpoint.kind.clone(),
+        reason: format!("unsupported ss2022 method: {method}"),
+    })
+}
+
 fn validate_ss2022_psk_b64(
     endpoint: &Endpoint,
     psk_b64: &str,
     field: &'static str,
+    expected_len: usize,
 ) -> Result<(), BuildError> {
     let decoded = base64::engine::general_purpose::STANDARD
         .decode(psk_b64)
@@ -208,12 +332,12 @@ fn validate_ss2022_psk_b64(
             kind: endpoint.kind.clone(),
             reason: format!("{field} base64 decode error: {e}"),
         })?;
-    if decoded.len() != SS2022_PSK_LEN_BYTES_AES_128 {
+    if decoded.len() != expected_len {
         return Err(BuildError::InvalidEndpointMeta {
             endpoint_id: endpoint.endpoint_id.clone(),
             kind: endpoint.kind.clone(),
             reason: format!(
-                "{field} invalid length: expected {SS2022_PSK_LEN_BYTES_AES_128}, got {}",
+                "{field} invalid length: expected {expected_len}, got {}",
                 decoded.len()
             ),
         });
@@ -233,6 +357,8 @@ pub fn build_add_user_operation(
     email: &str,
     vless_uuid: Option<&str>,
     ss2022_user_psk_b64: Option<&str>,
+    trojan_password: Option<&str>,
+    hysteria2_auth: Option<&str>,
 ) -> Result<xray::common::serial::TypedMessage, BuildError> {
     match endpoint.kind {
         EndpointKind::VlessRealityVisionTcp => {
@@ -276,22 +402,14 @@ pub fn build_add_user_operation(
                 })?;
 
             let meta = parse_ss2022_meta(endpoint)?;
-            if meta.method != SS2022_METHOD_2022_BLAKE3_AES_128_GCM {
-                return Err(BuildError::InvalidEndpointMeta {
-                    endpoint_id: endpoint.endpoint_id.clone(),
-                    kind: endpoint.kind.clone(),
-                    reason: format!(
-                        "ss2022 method must be {SS2022_METHOD_2022_BLAKE3_AES_128_GCM}, got {}",
-                        meta.method
-                    ),
-                });
-            }
+            let psk_len = ss2022_method_psk_len(endpoint, &meta.method)?;
             validate_ss2022_psk_b64(
                 endpoint,
                 &meta.server_psk_b64,
                 "endpoint.meta.server_psk_b64",
+                psk_len,
             )?;
-            validate_ss2022_psk_b64(endpoint, user_psk_b64, "user_psk_b64")?;
+            validate_ss2022_psk_b64(endpoint, user_psk_b64, "user_psk_b64", psk_len)?;
 
             let account = xray::proxy::shadowsocks_2022::Account {
                 key: user_psk_b64.to_string(),
@@ -302,6 +420,53 @@ pub fn build_add_user_operation(
                 account: Some(to_typed_message(TYPE_SS2022_ACCOUNT, &account)),
             };
 
+            let op = xray::app::proxyman::command::AddUserOperation { user: Some(user) };
+            Ok(to_typed_message(TYPE_ADD_USER_OPERATION, &op))
+        }
+        EndpointKind::TrojanRealityTcp | EndpointKind::TrojanTlsTcp => {
+            let password = trojan_password.ok_or_else(|| BuildError::InvalidUserCredentials {
+                email: email.to_string(),
+                kind: endpoint.kind.clone(),
+                reason: "missing trojan_password".to_string(),
+            })?;
+            if password.is_empty() {
+                return Err(BuildError::InvalidUserCredentials {
+                    email: email.to_string(),
+                    kind: endpoint.kind.clone(),
+                    reason: "trojan_password must be non-empty".to_string(),
+                });
+            }
+
+            let account = xray::proxy::trojan::Account {
+                password: password.to_string(),
+            };
+            let user = xray::common::protocol::User {
+                level: 0,
+                email: email.to_string(),
+                account: Some(to_typed_message(TYPE_TROJAN_ACCOUNT, &account)),
+            };
+
+            let op = xray::app::proxyman::command::AddUserOperation { user: Some(user) };
+            Ok(to_typed_message(TYPE_ADD_USER_OPERATION, &op))
+        }
+        EndpointKind::Hysteria2 => {
+            let auth = hysteria2_auth
+                .filter(|auth| !auth.is_empty())
+                .ok_or_else(|| BuildError::InvalidUserCredentials {
+                    email: email.to_string(),
+                    kind: endpoint.kind.clone(),
+                    reason: "missing hysteria2_auth".to_string(),
+                })?;
+
+            let account = xray::proxy::hysteria::account::Account {
+                auth: auth.to_string(),
+            };
+            let user = xray::common::protocol::User {
+                level: 0,
+                email: email.to_string(),
+                account: Some(to_typed_message(TYPE_HYSTERIA_ACCOUNT, &account)),
+            };
+
             let op = xray::app::proxyman::command::AddUserOperation { user: Some(user) };
             Ok(to_typed_message(TYPE_ADD_USER_OPERATION, &op))
         }
@@ -313,12 +478,28 @@ pub fn build_reverse_add_user_operation(
     email: &str,
     vless_uuid: &str,
     reverse_tag: &str,
+) -> Result<xray::common::serial::TypedMessage, BuildError> {
+    build_cluster_vless_add_user_operation(
+        endpoint,
+        email,
+        vless_uuid,
+        Some(reverse_tag),
+        "reverse relay",
+    )
+}
+
+fn build_cluster_vless_add_user_operation(
+    endpoint: &Endpoint,
+    email: &str,
+    vless_uuid: &str,
+    reverse_tag: Option<&str>,
+    purpose: &str,
 ) -> Result<xray::common::serial::TypedMessage, BuildError> {
     if endpoint.kind != EndpointKind::VlessRealityVisionTcp {
         return Err(BuildError::InvalidUserCredentials {
             email: email.to_string(),
             kind: endpoint.kind.clone(),
-            reason: "reverse relay requires a VLESS endpoint".to_string(),
+            reason: format!("{purpose} requires a VLESS endpoint"),
         });
     }
     let meta = parse_vless_meta(endpoint)?;
@@ -332,8 +513,8 @@ pub fn build_reverse_add_user_operation(
         xor_mode: 0,
         seconds: 0,
         padding: String::new(),
-        reverse: Some(xray::proxy::vless::Reverse {
-            tag: reverse_tag.to_string(),
+        reverse: reverse_tag.map(|tag| xray::proxy::vless::Reverse {
+            tag: tag.to_string(),
         }),
         testpre: 0,
         testseed: Vec::new(),
@@ -351,53 +532,25 @@ pub fn build_reverse_add_user_operation(
 
 pub fn build_add_inbound_request(
     endpoint: &Endpoint,
+) -> Result<xray::app::proxyman::command::AddInboundRequest, BuildError> {
+    build_add_inbound_request_with_sniffing(endpoint, false)
+}
+
+pub fn build_add_inbound_request_with_sniffing(
+    endpoint: &Endpoint,
+    sniffing: bool,
 ) -> Result<xray::app::proxyman::command::AddInboundRequest, BuildError> {
     match endpoint.kind {
         EndpointKind::VlessRealityVisionTcp => {
             let meta = parse_vless_meta(endpoint)?;
-            if !meta.short_ids.iter().any(|s| s == &meta.active_short_id) {
-                return Err(BuildError::InvalidEndpointMeta {
-                    endpoint_id: endpoint.endpoint_id.clone(),
-                    kind: endpoint.kind.clone(),
-                    reason: "active_short_id must be included in short_ids".to_string(),
-                });
-            }
-
-            let private_key =
-                decode_reality_private_key_b64url_nopad(endpoint, &meta.reality_keys.private_key)?;
-            let short_ids = meta
-                .short_ids
-                .iter()
-                .map(|s| decode_short_id_hex(endpoint, s))
-                .collect::<Result<Vec<_>, _>>()?;
-
-            let dest = normalize_reality_dest_for_xray(&meta.reality.dest);
-            let fingerprint = normalize_reality_fingerprint(&meta.reality.fingerprint);
             let transport = meta.transport;
-
-            let reality = xray::transport::internet::reality::Config {
-                show: false,
-                dest,
-                r#type: "tcp".to_string(),
-                xver: 0,
-                server_names: meta.reality.server_names,
-                private_key,
-                min_client_ver: vec![],
-                max_client_ver: vec![],
-                max_time_diff: 0,
-                short_ids,
-                mldsa65_seed: vec![],
-                limit_fallback_upload: None,
-                limit_fallback_download: None,
-                fingerprint,
-                server_name: String::new(),
-                public_key: vec![],
-                short_id: vec![],
-                mldsa65_verify: vec![],
-                spider_x: String::new(),
-                spider_y: vec![],
-                master_key_log: String::new(),
-            };
+            let reality = build_reality_server_config(
+                endpoint,
+                meta.reality,
+                &meta.reality_keys,
+                &meta.short_ids,
+                &meta.active_short_id,
+            )?;
 
             let (protocol_name, transport_settings) = match transport {
                 VlessRealityTransport::VisionTcp => {
@@ -423,7 +576,7 @@ pub fn build_add_inbound_request(
                 listen: Some(listen_ip_any()),
                 stream_settings: Some(stream_settings),
                 receive_original_destination: false,
-                sniffing_settings: None,
+                sniffing_settings: inbound_sniffing_settings(sniffing),
             };
 
             let proxy_settings = xray::proxy::vless::inbound::Config {
@@ -451,20 +604,12 @@ pub fn build_add_inbound_request(
         }
         EndpointKind::Ss2022_2022Blake3Aes128Gcm => {
             let meta = parse_ss2022_meta(endpoint)?;
-            if meta.method != SS2022_METHOD_2022_BLAKE3_AES_128_GCM {
-                return Err(BuildError::InvalidEndpointMeta {
-                    endpoint_id: endpoint.endpoint_id.clone(),
-                    kind: endpoint.kind.clone(),
-                    reason: format!(
-                        "ss2022 method must be {SS2022_METHOD_2022_BLAKE3_AES_128_GCM}, got {}",
-                        meta.method
-                    ),
-                });
-            }
+            let psk_len = ss2022_method_psk_len(endpoint, &meta.method)?;
             validate_ss2022_psk_b64(
                 endpoint,
                 &meta.server_psk_b64,
                 "endpoint.meta.server_psk_b64",
+                psk_len,
             )?;
 
             let stream_settings = xray::transport::internet::StreamConfig {
@@ -482,11 +627,11 @@ pub fn build_add_inbound_request(
                 listen: Some(listen_ip_any()),
                 stream_settings: Some(stream_settings),
                 receive_original_destination: false,
-                sniffing_settings: None,
+                sniffing_settings: inbound_sniffing_settings(sniffing),
             };
 
             let proxy_settings = xray::proxy::shadowsocks_2022::MultiUserServerConfig {
-                method: SS2022_METHOD_2022_BLAKE3_AES_128_GCM.to_string(),
+                method: meta.method,
                 key: meta.server_psk_b64,
                 users: vec![],
                 network: vec![
@@ -507,6 +652,114 @@ pub fn build_add_inbound_request(
                 )),
             };
 
+            Ok(xray::app::proxyman::command::AddInboundRequest {
+                inbound: Some(inbound),
+            })
+        }
+        EndpointKind::TrojanRealityTcp | EndpointKind::TrojanTlsTcp => {
+            let security = if endpoint.kind == EndpointKind::TrojanTlsTcp {
+                let meta = parse_trojan_tls_meta(endpoint)?;
+                let tls = tls_server_config(
+                    endpoint,
+                    &meta.server_name,
+                    &meta.tls_cert_pem,
+                    &meta.tls_key_pem,
+                    "http/1.1",
+                )?;
+                to_typed_message(TYPE_TLS_SECURITY_CONFIG, &tls)
+            } else {
+                let meta = parse_trojan_meta(endpoint)?;
+                let reality = build_reality_server_config(
+                    endpoint,
+                    meta.reality,
+                    &meta.reality_keys,
+                    &meta.short_ids,
+                    &meta.active_short_id,
+                )?;
+                to_typed_message(TYPE_REALITY_SECURITY_CONFIG, &reality)
+            };
+
+            let stream_settings = xray::transport::internet::StreamConfig {
+                address: None,
+                port: 0,
+                protocol_name: "tcp".to_string(),
+                transport_settings: vec![tcp_transport_settings()],
+                security_type: security.r#type.clone(),
+                security_settings: vec![security],
+                socket_settings: Some(business_inbound_socket_settings()),
+            };
+
+            let receiver_settings = xray::app::proxyman::ReceiverConfig {
+                port_list: Some(port_list_single(endpoint.port)),
+                listen: Some(listen_ip_any()),
+                stream_settings: Some(stream_settings),
+                receive_original_destination: false,
+                sniffing_settings: inbound_sniffing_settings(sniffing),
+            };
+
+            let proxy_settings = xray::proxy::trojan::ServerConfig {
+                users: vec![],
+                fallbacks: vec![],
+            };
+
+            let inbound = xray::core::InboundHandlerConfig {
+                tag: endpoint.tag.clone(),
+                receiver_settings: Some(to_typed_message(
+                    TYPE_PROXYMAN_RECEIVER_CONFIG,
+                    &receiver_settings,
+                )),
+                proxy_settings: Some(to_typed_message(TYPE_TROJAN_SERVER_CONFIG, &proxy_settings)),
+            };
+
+            Ok(xray::app::proxyman::command::AddInboundRequest {
+                inbound: Some(inbound),
+            })
+        }
+        EndpointKind::Hysteria2 => {
+            let meta = parse_hysteria2_meta(endpoint)?;
+            let tls = tls_server_config(
+                endpoint,
+                &meta.server_name,
+                &meta.tls_cert_pem,
+                &meta.tls_key_pem,
+                "h3",
+            )?;
+
+            let stream_settings = xray::transport::internet::StreamConfig {
+                address: None,
+                port: 0,
+                protocol_name: "hysteria".to_string(),
+                transport_settings: vec![hysteria2_transport_settings()],
+                security_type: TYPE_TLS_SECURITY_CONFIG.to_string(),
+                security_settings: vec![to_typed_message(TYPE_TLS_SECURITY_CONFIG, &tls)],
+                socket_settings: None,
+            };
+
+            let receiver_settings = xray::app::proxyman::ReceiverConfig {
+                port_list: Some(port_list_single(endpoint.port)),
+                listen: Some(listen_ip_any()),
+                stream_settings: Some(stream_settings),
+                receive_original_destination: false,
+                sniffing_settings: inbound_sniffing_settings(sniffing),
+            };
+
+            let proxy_settings = xray::proxy::hysteria::ServerConfig {
+                version: HYSTERIA2_VERSION,
+                users: vec![],
+            };
+
+            let inbound = xray::core::InboundHandlerConfig {
+                tag: endpoint.tag.clone(),
+                receiver_settings: Some(to_typed_message(
+                    TYPE_PROXYMAN_RECEIVER_CONFIG,
+                    &receiver_settings,
+                )),
+                proxy_settings: Some(to_typed_message(
+                    TYPE_HYSTERIA_SERVER_CONFIG,
+                    &proxy_settings,
+                )),
+            };
+
             Ok(xray::app::proxyman::command::AddInboundRequest {
                 inbound: Some(inbound),
             })
@@ -595,6 +848,24 @@ pub fn build_reverse_vless_outbound_request(
     reverse_tag: &str,
     uuid: &str,
     endpoint: &ReverseVlessEndpoint,
+) -> Result<xray::app::proxyman::command::AddOutboundRequest, BuildError> {
+    build_cluster_vless_outbound_request(
+        tag,
+        &format!("reverse:{tag}"),
+        uuid,
+        Some(reverse_tag),
+        endpoint,
+        "xp reverse mesh",
+    )
+}
+
+fn build_cluster_vless_outbound_request(
+    tag: &str,
+    email: &str,
+    uuid: &str,
+    reverse_tag: Option<&str>,
+    endpoint: &ReverseVlessEndpoint,
+    comment: &str,
 ) -> Result<xray::app::proxyman::command::AddOutboundRequest, BuildError> {
     let meta = parse_vless_meta(&endpoint.endpoint)?;
     let public_key = base64::engine::general_purpose::URL_SAFE_NO_PAD
@@ -602,13 +873,13 @@ pub fn build_reverse_vless_outbound_request(
         .map_err(|e| BuildError::InvalidEndpointMeta {
             endpoint_id: endpoint.endpoint.endpoint_id.clone(),
             kind: endpoint.endpoint.kind.clone(),
-            reason: format!("reverse reality public key decode error: {e}"),
+            reason: format!("reality public key decode error: {e}"),
         })?;
     let short_id = hex::decode(&endpoint.target_short_id_hex).map_err(|e| {
         BuildError::InvalidEndpointMeta {
             endpoint_id: endpoint.endpoint.endpoint_id.clone(),
             kind: endpoint.endpoint.kind.clone(),
-            reason: format!("reverse reality short id decode error: {e}"),
+            reason: format!("reality short id decode error: {e}"),
         }
     })?;
     let reality = xray::transport::internet::reality::Config {
@@ -660,15 +931,15 @@ pub fn build_reverse_vless_outbound_request(
         xor_mode: 0,
         seconds: 0,
         padding: String::new(),
-        reverse: Some(xray::proxy::vless::Reverse {
-            tag: reverse_tag.to_string(),
+        reverse: reverse_tag.map(|tag| xray::proxy::vless::Reverse {
+            tag: tag.to_string(),
         }),
         testpre: 0,
         testseed: Vec::new(),
     };
     let user = xray::common::protocol::User {
         level: 0,
-        email: format!("reverse:{tag}"),
+        email: email.to_string(),
         account: Some(to_typed_message(TYPE_VLESS_ACCOUNT, &account)),
     };
     let vnext = xray::common::protocol::ServerEndpoint {
@@ -701,7 +972,7 @@ pub fn build_reverse_vless_outbound_request(
                 &proxy_settings,
             )),
             expire: 0,
-            comment: "xp reverse mesh".to_string(),
+            comment: comment.to_string(),
         }),
     })
 }
@@ -751,366 +1022,14 @@ pub fn build_reverse_freedom_outbound_request(
     }
 }
 
-pub fn build_reverse_route_rule(
-    rule_tag: &str,
-    portal_tag: &str,
-    origin: &str,
-    outbound_tag: &str,
-) -> xray::app::router::command::AddRuleRequest {
-    let (domain, port) = origin
-        .strip_suffix(":443")
-        .map(|domain| (domain, 443))
-        .unwrap_or((origin, 0));
-    let rule = xray::app::router::RoutingRule {
-        target_tag: Some(xray::app::router::routing_rule::TargetTag::Tag(
-            outbound_tag.to_string(),
-        )),
-        rule_tag: rule_tag.to_string(),
-        domain: vec![xray::app::router::Domain {
-            r#type: xray::app::router::domain::Type::Full as i32,
-            value: domain.to_string(),
-        }],
-        ip: Vec::new(),
-        port_list: (port != 0).then(|| xray::common::net::PortList {
-            range: vec![xray::common::net::PortRange {
-                from: port,
-                to: port,
-            }],
-        }),
-        networks: vec![xray::common::net::Network::Tcp as i32],
-        inbound_tag: vec![portal_tag.to_string()],
-        protocol: Vec::new(),
-    };
-    let config = xray::app::router::Config {
-        domain_strategy: xray::app::router::config::DomainStrategy::AsIs as i32,
-        rule: vec![rule],
-        balancing_rule: Vec::new(),
-    };
-    xray::app::router::command::AddRuleRequest {
-        config: Some(to_typed_message(TYPE_ROUTING_CONFIG, &config)),
-        should_append: true,
-    }
-}
-
-pub fn build_reverse_block_rule(
-    rule_tag: &str,
-    portal_tag: &str,
-    block_tag: &str,
-) -> xray::app::router::command::AddRuleRequest {
-    let rule = xray::app::router::RoutingRule {
-        target_tag: Some(xray::app::router::routing_rule::TargetTag::Tag(
-            block_tag.to_string(),
-        )),
-        rule_tag: rule_tag.to_string(),
-        domain: Vec::new(),
-        ip: Vec::new(),
-        port_list: None,
-        networks: vec![xray::common::net::Network::Tcp as i32],
-        inbound_tag: vec![portal_tag.to_string()],
-        protocol: Vec::new(),
-    };
-    let config = xray::app::router::Config {
-        domain_strategy: xray::app::router::config::DomainStrategy::AsIs as i32,
-        rule: vec![rule],
-        balancing_rule: Vec::new(),
-    };
-    xray::app::router::command::AddRuleRequest {
-        config: Some(to_typed_message(TYPE_ROUTING_CONFIG, &config)),
-        should_append: true,
-    }
-}
+mod egress;
+mod routing;
+pub use egress::{
+    build_egress_add_user_operation, build_egress_socks_outbound_request,
+    build_egress_vless_outbound_request,
+};
+use routing::inbound_sniffing_settings;
+pub use routing::{build_policy_rule, build_reverse_block_rule, build_reverse_route_rule};
 
 #[cfg(test)]
-mod tests {
-    use pretty_assertions::assert_eq;
-
-    use super::*;
-
-    fn decode_typed<T: prost::Message + Default>(tm: &xray::common::serial::TypedMessage) -> T {
-        T::decode(tm.value.as_slice()).unwrap()
-    }
-
-    #[test]
-    fn typed_message_roundtrip_works() {
-        let msg = xray::app::proxyman::command::RemoveUserOperation {
-            email: "a@b".to_string(),
-        };
-        let tm = to_typed_message(TYPE_REMOVE_USER_OPERATION, &msg);
-        assert_eq!(tm.r#type, TYPE_REMOVE_USER_OPERATION);
-        let decoded: xray::app::proxyman::command::RemoveUserOperation = decode_typed(&tm);
-        assert_eq!(decoded.email, "a@b");
-    }
-
-    #[test]
-    fn build_remove_user_operation_sets_type_and_email() {
-        let tm = build_remove_user_operation("m:u1::e1");
-        assert_eq!(tm.r#type, TYPE_REMOVE_USER_OPERATION);
-        let decoded: xray::app::proxyman::command::RemoveUserOperation = decode_typed(&tm);
-        assert_eq!(decoded.email, "m:u1::e1");
-    }
-
-    #[test]
-    fn reverse_route_requests_wrap_rules_in_router_config() {
-        let request =
-            build_reverse_route_rule("rule", "portal", "rvs-test.mesh.invalid:443", "freedom");
-        let typed = request.config.expect("routing config");
-        assert_eq!(typed.r#type, TYPE_ROUTING_CONFIG);
-        let config: xray::app::router::Config = decode_typed(&typed);
-        assert_eq!(config.rule.len(), 1);
-        assert_eq!(config.rule[0].rule_tag, "rule");
-        assert_eq!(config.rule[0].domain[0].value, "rvs-test.mesh.invalid");
-        assert_eq!(
-            config.rule[0].port_list.as_ref().unwrap().range[0].from,
-            443
-        );
-        assert_eq!(config.rule[0].inbound_tag, vec!["portal".to_string()]);
-    }
-
-    #[test]
-    fn build_add_user_operation_vless_encodes_uuid_and_flow() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e1().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_vless_e1().to_owned(),
-            kind: EndpointKind::VlessRealityVisionTcp,
-            port: 443,
-            meta: serde_json::json!({
-                "reality": xp_test_fixtures::endpoint_reality(),
-                "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
-                "short_ids": xp_test_fixtures::endpoint_short_ids(),
-                "active_short_id": xp_test_fixtures::endpoint_active_short_id()
-            }),
-        };
-
-        let email = "m:u1::e1";
-        let uuid = "66ad4540-b58c-4ad2-9926-ea63445a9b57";
-
-        let tm = build_add_user_operation(&endpoint, email, Some(uuid), None).unwrap();
-        assert_eq!(tm.r#type, TYPE_ADD_USER_OPERATION);
-
-        let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
-        let user = op.user.unwrap();
-        assert_eq!(user.email, email);
-
-        let account_tm = user.account.unwrap();
-        assert_eq!(account_tm.r#type, TYPE_VLESS_ACCOUNT);
-        let account: xray::proxy::vless::Account = decode_typed(&account_tm);
-        assert_eq!(account.id, uuid);
-        assert_eq!(account.flow, "xtls-rprx-vision");
-    }
-
-    #[test]
-    fn build_add_user_operation_vless_xhttp_clears_vision_flow() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e1().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_vless_e1().to_owned(),
-            kind: EndpointKind::VlessRealityVisionTcp,
-            port: 443,
-            meta: serde_json::json!({
-                "reality": xp_test_fixtures::endpoint_reality(),
-                "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
-                "short_ids": xp_test_fixtures::endpoint_short_ids(),
-                "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
-                "transport": "xhttp"
-            }),
-        };
-
-        let tm = build_add_user_operation(
-            &endpoint,
-            "m:u1::e1",
-            Some("66ad4540-b58c-4ad2-9926-ea63445a9b57"),
-            None,
-        )
-        .unwrap();
-        let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
-        let account: xray::proxy::vless::Account = decode_typed(&op.user.unwrap().account.unwrap());
-        assert_eq!(account.flow, "");
-    }
-
-    #[test]
-    fn build_add_user_operation_ss2022_extracts_user_psk_from_password() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e2().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_ss_e2().to_owned(),
-            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-            port: 8388,
-            meta: serde_json::json!({
-                "method": "2022-blake3-aes-128-gcm",
-                "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
-            }),
-        };
-
-        let email = "m:u1::e2";
-        let user_psk_b64 = xp_test_fixtures::endpoint_user_psk_b64();
-
-        let tm = build_add_user_operation(&endpoint, email, None, Some(user_psk_b64)).unwrap();
-        assert_eq!(tm.r#type, TYPE_ADD_USER_OPERATION);
-
-        let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
-        let user = op.user.unwrap();
-        assert_eq!(user.email, email);
-
-        let account_tm = user.account.unwrap();
-        assert_eq!(account_tm.r#type, TYPE_SS2022_ACCOUNT);
-        let account: xray::proxy::shadowsocks_2022::Account = decode_typed(&account_tm);
-        assert_eq!(account.key, user_psk_b64);
-    }
-
-    #[test]
-    fn build_add_inbound_request_vless_reality_sets_port_tag_and_reality_materials() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e3().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_vless_e3().to_owned(),
-            kind: EndpointKind::VlessRealityVisionTcp,
-            port: 443,
-            meta: serde_json::json!({
-                "reality": xp_test_fixtures::endpoint_reality(),
-                "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
-                "short_ids": xp_test_fixtures::endpoint_short_ids(),
-                "active_short_id": xp_test_fixtures::endpoint_active_short_id()
-            }),
-        };
-
-        let req = build_add_inbound_request(&endpoint).unwrap();
-        let inbound = req.inbound.unwrap();
-        assert_eq!(inbound.tag, xp_test_fixtures::label_vless_e3());
-
-        let receiver_tm = inbound.receiver_settings.unwrap();
-        assert_eq!(receiver_tm.r#type, TYPE_PROXYMAN_RECEIVER_CONFIG);
-        let receiver: xray::app::proxyman::ReceiverConfig = decode_typed(&receiver_tm);
-        assert_eq!(receiver.port_list.unwrap().range[0].from, 443);
-
-        let stream = receiver.stream_settings.unwrap();
-        assert_eq!(stream.protocol_name, "tcp");
-        assert_eq!(stream.security_type, TYPE_REALITY_SECURITY_CONFIG);
-        assert_eq!(stream.security_settings.len(), 1);
-        let socket_settings = stream.socket_settings.unwrap();
-        assert_eq!(socket_settings.tcp_keep_alive_idle, 300);
-        assert_eq!(socket_settings.tcp_keep_alive_interval, 30);
-        assert_eq!(socket_settings.tcp_user_timeout, 10_000);
-
-        let reality_tm = &stream.security_settings[0];
-        assert_eq!(reality_tm.r#type, TYPE_REALITY_SECURITY_CONFIG);
-        let reality: xray::transport::internet::reality::Config = decode_typed(reality_tm);
-        assert_eq!(
-            reality.dest,
-            xp_test_fixtures::endpoint_reality()["dest"]
-                .as_str()
-                .unwrap()
-        );
-        assert_eq!(reality.fingerprint, "chrome");
-        assert_eq!(
-            reality.private_key,
-            vec![0u8; crate::protocol::REALITY_X25519_PRIVATE_KEY_LEN_BYTES]
-        );
-        assert_eq!(reality.short_ids.len(), 1);
-        assert_eq!(hex::encode(&reality.short_ids[0]), "0123456789abcdef");
-
-        let proxy_tm = inbound.proxy_settings.unwrap();
-        assert_eq!(proxy_tm.r#type, TYPE_VLESS_INBOUND_CONFIG);
-        let proxy: xray::proxy::vless::inbound::Config = decode_typed(&proxy_tm);
-        assert_eq!(proxy.clients.len(), 0);
-        assert_eq!(proxy.decryption, "none");
-    }
-
-    #[test]
-    fn build_add_inbound_request_vless_xhttp_uses_splithttp_transport() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e3().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_vless_e3().to_owned(),
-            kind: EndpointKind::VlessRealityVisionTcp,
-            port: 443,
-            meta: serde_json::json!({
-                "reality": xp_test_fixtures::endpoint_reality(),
-                "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
-                "short_ids": xp_test_fixtures::endpoint_short_ids(),
-                "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
-                "transport": "xhttp"
-            }),
-        };
-
-        let req = build_add_inbound_request(&endpoint).unwrap();
-        let inbound = req.inbound.unwrap();
-        let receiver: xray::app::proxyman::ReceiverConfig =
-            decode_typed(&inbound.receiver_settings.unwrap());
-        let stream = receiver.stream_settings.unwrap();
-        assert_eq!(stream.protocol_name, "splithttp");
-        assert_eq!(stream.security_type, TYPE_REALITY_SECURITY_CONFIG);
-        assert_eq!(stream.transport_settings.len(), 1);
-
-        let transport = &stream.transport_settings[0];
-        assert_eq!(transport.protocol_name, "splithttp");
-        let settings = transport.settings.as_ref().unwrap();
-        assert_eq!(settings.r#type, TYPE_SPLITHTTP_TRANSPORT_CONFIG);
-        let xhttp: xray::transport::internet::splithttp::Config = decode_typed(settings);
-        assert_eq!(xhttp.path, VLESS_XHTTP_PATH);
-        assert_eq!(xhttp.mode, "stream-one");
-    }
-
-    #[test]
-    fn build_add_inbound_request_ss2022_sets_method_server_psk_and_udp() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e4().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_ss_e4().to_owned(),
-            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-            port: 8388,
-            meta: serde_json::json!({
-                "method": "2022-blake3-aes-128-gcm",
-                "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
-            }),
-        };
-
-        let req = build_add_inbound_request(&endpoint).unwrap();
-        let inbound = req.inbound.unwrap();
-        assert_eq!(inbound.tag, xp_test_fixtures::label_ss_e4());
-
-        let receiver_tm = inbound.receiver_settings.unwrap();
-        assert_eq!(receiver_tm.r#type, TYPE_PROXYMAN_RECEIVER_CONFIG);
-        let receiver: xray::app::proxyman::ReceiverConfig = decode_typed(&receiver_tm);
-        assert_eq!(receiver.port_list.unwrap().range[0].from, 8388);
-        let stream = receiver.stream_settings.unwrap();
-        let socket_settings = stream.socket_settings.unwrap();
-        assert_eq!(socket_settings.tcp_keep_alive_idle, 300);
-        assert_eq!(socket_settings.tcp_keep_alive_interval, 30);
-        assert_eq!(socket_settings.tcp_user_timeout, 10_000);
-
-        let proxy_tm = inbound.proxy_settings.unwrap();
-        assert_eq!(proxy_tm.r#type, TYPE_SS2022_MULTIUSER_SERVER_CONFIG);
-        let proxy: xray::proxy::shadowsocks_2022::MultiUserServerConfig = decode_typed(&proxy_tm);
-        assert_eq!(proxy.method, "2022-blake3-aes-128-gcm");
-        assert_eq!(proxy.key, "AAAAAAAAAAAAAAAAAAAAAA==");
-        assert_eq!(
-            proxy.network,
-            vec![
-                xray::common::net::Network::Tcp as i32,
-                xray::common::net::Network::Udp as i32
-            ]
-        );
-        assert_eq!(proxy.users.len(), 0);
-    }
-
-    #[test]
-    fn build_add_user_operation_ss2022_rejects_method_mismatch() {
-        let endpoint = Endpoint {
-            endpoint_id: xp_test_fixtures::label_e2().to_owned(),
-            node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
-            tag: xp_test_fixtures::label_ss_e2().to_owned(),
-            kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
-            port: 8388,
-            meta: serde_json::json!({
-                "method": "2022-blake3-aes-256-gcm",
-                "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
-            }),
-        };
-
-        let email = "m:u1::e2";
-        let user_psk_b64 = xp_test_fixtures::endpoint_user_psk_b64();
-        let err = build_add_user_operation(&endpoint, email, None, Some(user_psk_b64)).unwrap_err();
-        assert!(format!("{err}").contains("ss2022 method must be"));
-    }
-}
+mod tests;
@@ -0,0 +1,91 @@
+use super::{
+    BuildError, ReverseVlessEndpoint, TYPE_PROXYMAN_SENDER_CONFIG,
+    build_cluster_vless_add_user_operation, build_cluster_vless_outbound_request, to_typed_message,
+};
+use crate::{domain::Endpoint, xray::proto::xray};
+
+const TYPE_SOCKS_CLIENT_CONFIG: &str = "xray.proxy.socks.ClientConfig";
+const TYPE_SOCKS_ACCOUNT: &str = "xray.proxy.socks.Account";
+
+/// Admit a peer node's egress chain on a managed VLESS inbound. Unlike the reverse account it
+/// carries no Reverse tag: the peer's traffic is routed like any other client's.
+pub fn build_egress_add_user_operation(
+    endpoint: &Endpoint,
+    email: &str,
+    vless_uuid: &str,
+) -> Result<xray::common::serial::TypedMessage, BuildError> {
+    build_cluster_vless_add_user_operation(endpoint, email, vless_uuid, None, "egress chaining")
+}
+
+/// Build the source-side VLESS outbound of an egress chain: traffic routed into it leaves the
+/// cluster from the peer node that owns `endpoint`.
+pub fn build_egress_vless_outbound_request(
+    tag: &str,
+    email: &str,
+    uuid: &str,
+    endpoint: &ReverseVlessEndpoint,
+) -> Result<xray::app::proxyman::command::AddOutboundRequest, BuildError> {
+    build_cluster_vless_outbound_request(tag, email, uuid, None, endpoint, "xp egress chain")
+}
+
+/// Build an egress outbound to an operator-provided SOCKS5 proxy. Credentials are optional; when
+/// present they are sent as the upstream's username/password account.
+pub fn build_egress_socks_outbound_request(
+    tag: &str,
+    address: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> xray::app::proxyman::command::AddOutboundRequest {
+    let address = match address.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            xray::common::net::ip_or_domain::Address::Ip(ip.octets().to_vec())
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            xray::common::net::ip_or_domain::Address::Ip(ip.octets().to_vec())
+        }
+        Err(_) => xray::common::net::ip_or_domain::Address::Domain(address.to_string()),
+    };
+    let user = credentials.map(|(username, password)| xray::common::protocol::User {
+        level: 0,
+        email: String::new(),
+        account: Some(to_typed_message(
+            TYPE_SOCKS_ACCOUNT,
+            &xray::proxy::socks::Account {
+                username: username.to_string(),
+                password: password.to_string(),
+            },
+        )),
+    });
+    let proxy_settings = xray::proxy::socks::ClientConfig {
+        server: Some(xray::common::protocol::ServerEndpoint {
+            address: Some(xray::common::net::IpOrDomain {
+                address: Some(address),
+            }),
+            port: port as u32,
+            user,
+        }),
+    };
+    let sender_settings = xray::app::proxyman::SenderConfig {
+        via: None,
+        stream_settings: None,
+        proxy_settings: None,
+        multiplex_settings: None,
+        via_cidr: String::new(),
+        target_strategy: xray::transport::internet::DomainStrategy::AsIs as i32,
+    };
+    xray::app::proxyman::command::AddOutboundRequest {
+        outbound: Some(xray::core::OutboundHandlerConfig {
+            tag: tag.to_string(),
+            sender_settings: Some(to_typed_message(
+                TYPE_PROXYMAN_SENDER_CONFIG,
+                &sender_settings,
+            )),
+            proxy_settings: Some(to_typed_message(TYPE_SOCKS_CLIENT_CONFIG, &proxy_settings)),
+            expire: 0,
+            comment: "xp egress socks".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,39 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+fn decode_typed<T: prost::Message + Default>(tm: &xray::common::serial::TypedMessage) -> T {
+    T::decode(tm.value.as_slice()).unwrap()
+}
+
+#[test]
+fn egress_socks_outbound_carries_server_and_optional_account() {
+    let request = build_egress_socks_outbound_request(
+        "xp-egress-socks-1",
+        "198.51.100.7",
+        1080,
+        Some(("user", "secret")),
+    );
+    let outbound = request.outbound.expect("outbound");
+    assert_eq!(outbound.tag, "xp-egress-socks-1");
+    let proxy = outbound.proxy_settings.expect("proxy settings");
+    assert_eq!(proxy.r#type, TYPE_SOCKS_CLIENT_CONFIG);
+    let config: xray::proxy::socks::ClientConfig = decode_typed(&proxy);
+    let server = config.server.expect("server");
+    assert_eq!(server.port, 1080);
+    assert_eq!(
+        server.address.and_then(|address| address.address),
+        Some(xray::common::net::ip_or_domain::Address::Ip(vec![
+            198, 51, 100, 7
+        ]))
+    );
+    let account: xray::proxy::socks::Account =
+        decode_typed(&server.user.expect("user").account.expect("account"));
+    assert_eq!(account.username, "user");
+    assert_eq!(account.password, "secret");
+
+    let request = build_egress_socks_outbound_request("t", "proxy.example.test", 1080, None);
+    let config: xray::proxy::socks::ClientConfig =
+        decode_typed(&request.outbound.unwrap().proxy_settings.unwrap());
+    assert!(config.server.unwrap().user.is_none());
+}
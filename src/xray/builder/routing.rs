@@ -0,0 +1,156 @@
+use super::to_typed_message;
+use crate::{
+    routing_policy::{DomainMatcher, PolicyRule},
+    xray::proto::xray,
+};
+
+const TYPE_ROUTING_CONFIG: &str = "xray.app.router.Config";
+
+/// Sniffing is route-only: the sniffed domain/protocol feeds the routing policy but never
+/// rewrites the destination a client asked for.
+pub(super) fn inbound_sniffing_settings(
+    enabled: bool,
+) -> Option<xray::app::proxyman::SniffingConfig> {
+    enabled.then(|| xray::app::proxyman::SniffingConfig {
+        enabled: true,
+        destination_override: vec!["http".to_string(), "tls".to_string(), "quic".to_string()],
+        domains_excluded: Vec::new(),
+        metadata_only: false,
+        route_only: true,
+    })
+}
+
+pub fn build_reverse_route_rule(
+    rule_tag: &str,
+    portal_tag: &str,
+    origin: &str,
+    outbound_tag: &str,
+) -> xray::app::router::command::AddRuleRequest {
+    let (domain, port) = origin
+        .strip_suffix(":443")
+        .map(|domain| (domain, 443))
+        .unwrap_or((origin, 0));
+    let rule = xray::app::router::RoutingRule {
+        target_tag: Some(xray::app::router::routing_rule::TargetTag::Tag(
+            outbound_tag.to_string(),
+        )),
+        rule_tag: rule_tag.to_string(),
+        domain: vec![xray::app::router::Domain {
+            r#type: xray::app::router::domain::Type::Full as i32,
+            value: domain.to_string(),
+        }],
+        ip: Vec::new(),
+        geoip: Vec::new(),
+        port_list: (port != 0).then(|| xray::common::net::PortList {
+            range: vec![xray::common::net::PortRange {
+                from: port,
+                to: port,
+            }],
+        }),
+        networks: vec![xray::common::net::Network::Tcp as i32],
+        user_email: Vec::new(),
+        inbound_tag: vec![portal_tag.to_string()],
+        protocol: Vec::new(),
+    };
+    let config = xray::app::router::Config {
+        domain_strategy: xray::app::router::config::DomainStrategy::AsIs as i32,
+        rule: vec![rule],
+        balancing_rule: Vec::new(),
+    };
+    xray::app::router::command::AddRuleRequest {
+        config: Some(to_typed_message(TYPE_ROUTING_CONFIG, &config)),
+        should_append: true,
+    }
+}
+
+pub fn build_reverse_block_rule(
+    rule_tag: &str,
+    portal_tag: &str,
+    block_tag: &str,
+) -> xray::app::router::command::AddRuleRequest {
+    let rule = xray::app::router::RoutingRule {
+        target_tag: Some(xray::app::router::routing_rule::TargetTag::Tag(
+            block_tag.to_string(),
+        )),
+        rule_tag: rule_tag.to_string(),
+        domain: Vec::new(),
+        ip: Vec::new(),
+        geoip: Vec::new(),
+        port_list: None,
+        networks: vec![xray::common::net::Network::Tcp as i32],
+        user_email: Vec::new(),
+        inbound_tag: vec![portal_tag.to_string()],
+        protocol: Vec::new(),
+    };
+    let config = xray::app::router::Config {
+        domain_strategy: xray::app::router::config::DomainStrategy::AsIs as i32,
+        rule: vec![rule],
+        balancing_rule: Vec::new(),
+    };
+    xray::app::router::command::AddRuleRequest {
+        config: Some(to_typed_message(TYPE_ROUTING_CONFIG, &config)),
+        should_append: true,
+    }
+}
+
+pub fn build_policy_rule(rule: &PolicyRule) -> xray::app::router::command::AddRuleRequest {
+    let domain = rule
+        .domains
+        .iter()
+        .map(|matcher| {
+            let (r#type, value) = match matcher {
+                DomainMatcher::Domain(value) => (xray::app::router::domain::Type::Domain, value),
+                DomainMatcher::Full(value) => (xray::app::router::domain::Type::Full, value),
+                DomainMatcher::Keyword(value) => (xray::app::router::domain::Type::Plain, value),
+                DomainMatcher::Regexp(value) => (xray::app::router::domain::Type::Regex, value),
+            };
+            xray::app::router::Domain {
+                r#type: r#type as i32,
+                value: value.clone(),
+            }
+        })
+        .collect();
+    // CIDR lists go through an anonymous GeoIP entry, the form Xray's router compiles IP rules
+    // from.
+    let geoip = (!rule.cidrs.is_empty())
+        .then(|| xray::app::router::GeoIp {
+            country_code: String::new(),
+            cidr: rule
+                .cidrs
+                .iter()
+                .map(|cidr| xray::app::router::Cidr {
+                    ip: cidr.ip_bytes(),
+                    prefix: u32::from(cidr.prefix),
+                })
+                .collect(),
+            reverse_match: false,
+        })
+        .into_iter()
+        .collect();
+    let rule = xray::app::router::RoutingRule {
+        target_tag: Some(xray::app::router::routing_rule::TargetTag::Tag(
+            rule.outbound_tag.clone(),
+        )),
+        rule_tag: rule.rule_tag.clone(),
+        domain,
+        ip: Vec::new(),
+        geoip,
+        port_list: None,
+        networks: Vec::new(),
+        user_email: rule.user_emails.clone(),
+        inbound_tag: rule.inbound_tags.clone(),
+        protocol: rule.protocols.clone(),
+    };
+    let config = xray::app::router::Config {
+        domain_strategy: xray::app::router::config::DomainStrategy::AsIs as i32,
+        rule: vec![rule],
+        balancing_rule: Vec::new(),
+    };
+    xray::app::router::command::AddRuleRequest {
+        config: Some(to_typed_message(TYPE_ROUTING_CONFIG, &config)),
+        should_append: true,
+    }
+}
+
+#[cfg(test)]
+mod tests;
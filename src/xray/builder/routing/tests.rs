@@ -0,0 +1,68 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+fn decode_typed<T: prost::Message + Default>(tm: &xray::common::serial::TypedMessage) -> T {
+    T::decode(tm.value.as_slice()).unwrap()
+}
+
+#[test]
+fn reverse_route_requests_wrap_rules_in_router_config() {
+    let request =
+        build_reverse_route_rule("rule", "portal", "rvs-test.mesh.invalid:443", "freedom");
+    let typed = request.config.expect("routing config");
+    assert_eq!(typed.r#type, TYPE_ROUTING_CONFIG);
+    let config: xray::app::router::Config = decode_typed(&typed);
+    assert_eq!(config.rule.len(), 1);
+    assert_eq!(config.rule[0].rule_tag, "rule");
+    assert_eq!(config.rule[0].domain[0].value, "rvs-test.mesh.invalid");
+    assert_eq!(
+        config.rule[0].port_list.as_ref().unwrap().range[0].from,
+        443
+    );
+    assert_eq!(config.rule[0].inbound_tag, vec!["portal".to_string()]);
+}
+
+#[test]
+fn policy_rules_carry_users_domains_cidrs_and_protocols() {
+    let rule = PolicyRule {
+        rule_tag: "xp-policy-abc-0".to_string(),
+        outbound_tag: "block".to_string(),
+        inbound_tags: vec!["ss-e1".to_string()],
+        user_emails: vec!["m:u1::e1".to_string()],
+        domains: vec![
+            DomainMatcher::Domain("example.com".to_string()),
+            DomainMatcher::Keyword("torrent".to_string()),
+        ],
+        cidrs: vec![crate::routing_policy::IpCidr::parse("10.0.0.0/8").unwrap()],
+        protocols: vec!["bittorrent".to_string()],
+    };
+    let request = build_policy_rule(&rule);
+    assert!(request.should_append);
+    let config: xray::app::router::Config = decode_typed(&request.config.unwrap());
+    let built = &config.rule[0];
+    assert_eq!(built.rule_tag, "xp-policy-abc-0");
+    assert_eq!(
+        built.target_tag,
+        Some(xray::app::router::routing_rule::TargetTag::Tag(
+            "block".to_string()
+        ))
+    );
+    assert_eq!(built.inbound_tag, vec!["ss-e1".to_string()]);
+    assert_eq!(built.user_email, vec!["m:u1::e1".to_string()]);
+    assert_eq!(
+        built
+            .domain
+            .iter()
+            .map(|domain| domain.r#type)
+            .collect::<Vec<_>>(),
+        vec![
+            xray::app::router::domain::Type::Domain as i32,
+            xray::app::router::domain::Type::Plain as i32
+        ]
+    );
+    assert_eq!(built.geoip.len(), 1);
+    assert_eq!(built.geoip[0].cidr[0].ip, vec![10, 0, 0, 0]);
+    assert_eq!(built.geoip[0].cidr[0].prefix, 8);
+    assert_eq!(built.protocol, vec!["bittorrent".to_string()]);
+}
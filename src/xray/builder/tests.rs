@@ -0,0 +1,488 @@
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+fn decode_typed<T: prost::Message + Default>(tm: &xray::common::serial::TypedMessage) -> T {
+    T::decode(tm.value.as_slice()).unwrap()
+}
+
+#[test]
+fn typed_message_roundtrip_works() {
+    let msg = xray::app::proxyman::command::RemoveUserOperation {
+        email: "a@b".to_string(),
+    };
+    let tm = to_typed_message(TYPE_REMOVE_USER_OPERATION, &msg);
+    assert_eq!(tm.r#type, TYPE_REMOVE_USER_OPERATION);
+    let decoded: xray::app::proxyman::command::RemoveUserOperation = decode_typed(&tm);
+    assert_eq!(decoded.email, "a@b");
+}
+
+#[test]
+fn build_remove_user_operation_sets_type_and_email() {
+    let tm = build_remove_user_operation("m:u1::e1");
+    assert_eq!(tm.r#type, TYPE_REMOVE_USER_OPERATION);
+    let decoded: xray::app::proxyman::command::RemoveUserOperation = decode_typed(&tm);
+    assert_eq!(decoded.email, "m:u1::e1");
+}
+
+#[test]
+fn build_add_user_operation_vless_encodes_uuid_and_flow() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e1().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_vless_e1().to_owned(),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port: 443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id()
+        }),
+    };
+
+    let email = "m:u1::e1";
+    let uuid = "66ad4540-b58c-4ad2-9926-ea63445a9b57";
+
+    let tm = build_add_user_operation(&endpoint, email, Some(uuid), None, None, None).unwrap();
+    assert_eq!(tm.r#type, TYPE_ADD_USER_OPERATION);
+
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let user = op.user.unwrap();
+    assert_eq!(user.email, email);
+
+    let account_tm = user.account.unwrap();
+    assert_eq!(account_tm.r#type, TYPE_VLESS_ACCOUNT);
+    let account: xray::proxy::vless::Account = decode_typed(&account_tm);
+    assert_eq!(account.id, uuid);
+    assert_eq!(account.flow, "xtls-rprx-vision");
+}
+
+#[test]
+fn build_add_user_operation_vless_xhttp_clears_vision_flow() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e1().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_vless_e1().to_owned(),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port: 443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
+            "transport": "xhttp"
+        }),
+    };
+
+    let tm = build_add_user_operation(
+        &endpoint,
+        "m:u1::e1",
+        Some("66ad4540-b58c-4ad2-9926-ea63445a9b57"),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let account: xray::proxy::vless::Account = decode_typed(&op.user.unwrap().account.unwrap());
+    assert_eq!(account.flow, "");
+}
+
+#[test]
+fn build_add_user_operation_ss2022_extracts_user_psk_from_password() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e2().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_ss_e2().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 8388,
+        meta: serde_json::json!({
+            "method": "2022-blake3-aes-128-gcm",
+            "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
+        }),
+    };
+
+    let email = "m:u1::e2";
+    let user_psk_b64 = xp_test_fixtures::endpoint_user_psk_b64();
+
+    let tm =
+        build_add_user_operation(&endpoint, email, None, Some(user_psk_b64), None, None).unwrap();
+    assert_eq!(tm.r#type, TYPE_ADD_USER_OPERATION);
+
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let user = op.user.unwrap();
+    assert_eq!(user.email, email);
+
+    let account_tm = user.account.unwrap();
+    assert_eq!(account_tm.r#type, TYPE_SS2022_ACCOUNT);
+    let account: xray::proxy::shadowsocks_2022::Account = decode_typed(&account_tm);
+    assert_eq!(account.key, user_psk_b64);
+}
+
+#[test]
+fn build_add_inbound_request_vless_reality_sets_port_tag_and_reality_materials() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e3().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_vless_e3().to_owned(),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port: 443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id()
+        }),
+    };
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    assert_eq!(inbound.tag, xp_test_fixtures::label_vless_e3());
+
+    let receiver_tm = inbound.receiver_settings.unwrap();
+    assert_eq!(receiver_tm.r#type, TYPE_PROXYMAN_RECEIVER_CONFIG);
+    let receiver: xray::app::proxyman::ReceiverConfig = decode_typed(&receiver_tm);
+    assert_eq!(receiver.port_list.unwrap().range[0].from, 443);
+
+    let stream = receiver.stream_settings.unwrap();
+    assert_eq!(stream.protocol_name, "tcp");
+    assert_eq!(stream.security_type, TYPE_REALITY_SECURITY_CONFIG);
+    assert_eq!(stream.security_settings.len(), 1);
+    let socket_settings = stream.socket_settings.unwrap();
+    assert_eq!(socket_settings.tcp_keep_alive_idle, 300);
+    assert_eq!(socket_settings.tcp_keep_alive_interval, 30);
+    assert_eq!(socket_settings.tcp_user_timeout, 10_000);
+
+    let reality_tm = &stream.security_settings[0];
+    assert_eq!(reality_tm.r#type, TYPE_REALITY_SECURITY_CONFIG);
+    let reality: xray::transport::internet::reality::Config = decode_typed(reality_tm);
+    assert_eq!(
+        reality.dest,
+        xp_test_fixtures::endpoint_reality()["dest"]
+            .as_str()
+            .unwrap()
+    );
+    assert_eq!(reality.fingerprint, "chrome");
+    assert_eq!(
+        reality.private_key,
+        vec![0u8; crate::protocol::REALITY_X25519_PRIVATE_KEY_LEN_BYTES]
+    );
+    assert_eq!(reality.short_ids.len(), 1);
+    assert_eq!(hex::encode(&reality.short_ids[0]), "0123456789abcdef");
+
+    let proxy_tm = inbound.proxy_settings.unwrap();
+    assert_eq!(proxy_tm.r#type, TYPE_VLESS_INBOUND_CONFIG);
+    let proxy: xray::proxy::vless::inbound::Config = decode_typed(&proxy_tm);
+    assert_eq!(proxy.clients.len(), 0);
+    assert_eq!(proxy.decryption, "none");
+}
+
+#[test]
+fn build_add_inbound_request_vless_xhttp_uses_splithttp_transport() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e3().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_vless_e3().to_owned(),
+        kind: EndpointKind::VlessRealityVisionTcp,
+        port: 443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id(),
+            "transport": "xhttp"
+        }),
+    };
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    let receiver: xray::app::proxyman::ReceiverConfig =
+        decode_typed(&inbound.receiver_settings.unwrap());
+    let stream = receiver.stream_settings.unwrap();
+    assert_eq!(stream.protocol_name, "splithttp");
+    assert_eq!(stream.security_type, TYPE_REALITY_SECURITY_CONFIG);
+    assert_eq!(stream.transport_settings.len(), 1);
+
+    let transport = &stream.transport_settings[0];
+    assert_eq!(transport.protocol_name, "splithttp");
+    let settings = transport.settings.as_ref().unwrap();
+    assert_eq!(settings.r#type, TYPE_SPLITHTTP_TRANSPORT_CONFIG);
+    let xhttp: xray::transport::internet::splithttp::Config = decode_typed(settings);
+    assert_eq!(xhttp.path, VLESS_XHTTP_PATH);
+    assert_eq!(xhttp.mode, "stream-one");
+}
+
+#[test]
+fn build_add_inbound_request_ss2022_sets_method_server_psk_and_udp() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e4().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_ss_e4().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 8388,
+        meta: serde_json::json!({
+            "method": "2022-blake3-aes-128-gcm",
+            "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
+        }),
+    };
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    assert_eq!(inbound.tag, xp_test_fixtures::label_ss_e4());
+
+    let receiver_tm = inbound.receiver_settings.unwrap();
+    assert_eq!(receiver_tm.r#type, TYPE_PROXYMAN_RECEIVER_CONFIG);
+    let receiver: xray::app::proxyman::ReceiverConfig = decode_typed(&receiver_tm);
+    assert_eq!(receiver.port_list.unwrap().range[0].from, 8388);
+    let stream = receiver.stream_settings.unwrap();
+    let socket_settings = stream.socket_settings.unwrap();
+    assert_eq!(socket_settings.tcp_keep_alive_idle, 300);
+    assert_eq!(socket_settings.tcp_keep_alive_interval, 30);
+    assert_eq!(socket_settings.tcp_user_timeout, 10_000);
+    assert_eq!(receiver.sniffing_settings, None);
+
+    let sniffing = build_add_inbound_request_with_sniffing(&endpoint, true).unwrap();
+    let receiver: xray::app::proxyman::ReceiverConfig =
+        decode_typed(&sniffing.inbound.unwrap().receiver_settings.unwrap());
+    let sniffing = receiver.sniffing_settings.unwrap();
+    assert!(sniffing.enabled && sniffing.route_only);
+    assert_eq!(sniffing.destination_override, vec!["http", "tls", "quic"]);
+
+    let proxy_tm = inbound.proxy_settings.unwrap();
+    assert_eq!(proxy_tm.r#type, TYPE_SS2022_MULTIUSER_SERVER_CONFIG);
+    let proxy: xray::proxy::shadowsocks_2022::MultiUserServerConfig = decode_typed(&proxy_tm);
+    assert_eq!(proxy.method, "2022-blake3-aes-128-gcm");
+    assert_eq!(proxy.key, "AAAAAAAAAAAAAAAAAAAAAA==");
+    assert_eq!(
+        proxy.network,
+        vec![
+            xray::common::net::Network::Tcp as i32,
+            xray::common::net::Network::Udp as i32
+        ]
+    );
+    assert_eq!(proxy.users.len(), 0);
+}
+
+#[test]
+fn build_add_inbound_request_trojan_reality_uses_tcp_reality_and_trojan_server() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e3().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_e3().to_owned(),
+        kind: EndpointKind::TrojanRealityTcp,
+        port: 8443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id()
+        }),
+    };
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    assert_eq!(inbound.tag, xp_test_fixtures::label_e3());
+
+    let receiver: xray::app::proxyman::ReceiverConfig =
+        decode_typed(&inbound.receiver_settings.unwrap());
+    assert_eq!(receiver.port_list.unwrap().range[0].from, 8443);
+    let stream = receiver.stream_settings.unwrap();
+    assert_eq!(stream.protocol_name, "tcp");
+    assert_eq!(stream.security_type, TYPE_REALITY_SECURITY_CONFIG);
+    let reality: xray::transport::internet::reality::Config =
+        decode_typed(&stream.security_settings[0]);
+    assert_eq!(hex::encode(&reality.short_ids[0]), "0123456789abcdef");
+
+    let proxy_tm = inbound.proxy_settings.unwrap();
+    assert_eq!(proxy_tm.r#type, TYPE_TROJAN_SERVER_CONFIG);
+    let proxy: xray::proxy::trojan::ServerConfig = decode_typed(&proxy_tm);
+    assert!(proxy.users.is_empty());
+}
+
+#[test]
+fn build_add_user_operation_trojan_encodes_password() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e3().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_e3().to_owned(),
+        kind: EndpointKind::TrojanRealityTcp,
+        port: 8443,
+        meta: serde_json::json!({
+            "reality": xp_test_fixtures::endpoint_reality(),
+            "reality_keys": xp_test_fixtures::endpoint_reality_keys(),
+            "short_ids": xp_test_fixtures::endpoint_short_ids(),
+            "active_short_id": xp_test_fixtures::endpoint_active_short_id()
+        }),
+    };
+
+    let err = build_add_user_operation(&endpoint, "m:u1::e3", None, None, None, None).unwrap_err();
+    assert!(format!("{err}").contains("missing trojan_password"));
+
+    let tm =
+        build_add_user_operation(&endpoint, "m:u1::e3", None, None, Some("secret"), None).unwrap();
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let account_tm = op.user.unwrap().account.unwrap();
+    assert_eq!(account_tm.r#type, TYPE_TROJAN_ACCOUNT);
+    let account: xray::proxy::trojan::Account = decode_typed(&account_tm);
+    assert_eq!(account.password, "secret");
+}
+
+#[test]
+fn build_add_user_operation_ss2022_rejects_psk_length_mismatch() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e2().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_ss_e2().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 8388,
+        meta: serde_json::json!({
+            "method": "2022-blake3-aes-256-gcm",
+            "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
+        }),
+    };
+
+    let email = "m:u1::e2";
+    let user_psk_b64 = xp_test_fixtures::endpoint_user_psk_b64();
+    let err = build_add_user_operation(&endpoint, email, None, Some(user_psk_b64), None, None)
+        .unwrap_err();
+    assert!(format!("{err}").contains("invalid length: expected 32, got 16"));
+}
+
+#[test]
+fn build_add_user_operation_ss2022_rejects_unsupported_method() {
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e2().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_ss_e2().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 8388,
+        meta: serde_json::json!({
+            "method": "aes-128-gcm",
+            "server_psk_b64": xp_test_fixtures::endpoint_server_psk_b64()
+        }),
+    };
+
+    let email = "m:u1::e2";
+    let user_psk_b64 = xp_test_fixtures::endpoint_user_psk_b64();
+    let err = build_add_user_operation(&endpoint, email, None, Some(user_psk_b64), None, None)
+        .unwrap_err();
+    assert!(format!("{err}").contains("unsupported ss2022 method"));
+}
+
+#[test]
+fn build_add_inbound_request_ss2022_chacha20_uses_endpoint_method() {
+    let server_psk_b64 = base64::engine::general_purpose::STANDARD.encode([7u8; 32]);
+    let endpoint = Endpoint {
+        endpoint_id: xp_test_fixtures::label_e2().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_ss_e2().to_owned(),
+        kind: EndpointKind::Ss2022_2022Blake3Aes128Gcm,
+        port: 8388,
+        meta: serde_json::json!({
+            "method": "2022-blake3-chacha20-poly1305",
+            "server_psk_b64": server_psk_b64
+        }),
+    };
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let proxy: xray::proxy::shadowsocks_2022::MultiUserServerConfig =
+        decode_typed(&req.inbound.unwrap().proxy_settings.unwrap());
+    assert_eq!(proxy.method, "2022-blake3-chacha20-poly1305");
+    assert_eq!(proxy.key, server_psk_b64);
+}
+
+fn hysteria2_endpoint() -> Endpoint {
+    let cert = crate::cluster_identity::generate_self_signed_server_cert(
+        xp_test_fixtures::subscription_host_example(),
+    )
+    .unwrap();
+    Endpoint {
+        endpoint_id: xp_test_fixtures::label_e3().to_owned(),
+        node_id: xp_test_fixtures::subscription_node_n1().to_owned(),
+        tag: xp_test_fixtures::label_e3().to_owned(),
+        kind: EndpointKind::Hysteria2,
+        port: 8443,
+        meta: serde_json::json!({
+            "server_name": xp_test_fixtures::subscription_host_example(),
+            "tls_cert_pem": cert.cert_pem,
+            "tls_key_pem": cert.key_pem,
+            "cert_sha256": cert.cert_sha256
+        }),
+    }
+}
+
+#[test]
+fn build_add_inbound_request_hysteria2_uses_hysteria_transport_and_tls() {
+    let endpoint = hysteria2_endpoint();
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    let receiver: xray::app::proxyman::ReceiverConfig =
+        decode_typed(&inbound.receiver_settings.unwrap());
+    assert_eq!(receiver.port_list.unwrap().range[0].from, 8443);
+    let stream = receiver.stream_settings.unwrap();
+    assert_eq!(stream.protocol_name, "hysteria");
+    assert_eq!(
+        stream.transport_settings[0]
+            .settings
+            .as_ref()
+            .unwrap()
+            .r#type,
+        TYPE_HYSTERIA_TRANSPORT_CONFIG
+    );
+    assert_eq!(stream.security_type, TYPE_TLS_SECURITY_CONFIG);
+    let tls: xray::transport::internet::tls::Config = decode_typed(&stream.security_settings[0]);
+    assert_eq!(tls.next_protocol, vec!["h3".to_string()]);
+    assert_eq!(tls.certificate.len(), 1);
+    assert!(!tls.certificate[0].certificate.is_empty());
+    assert!(!tls.certificate[0].key.is_empty());
+
+    let proxy_tm = inbound.proxy_settings.unwrap();
+    assert_eq!(proxy_tm.r#type, TYPE_HYSTERIA_SERVER_CONFIG);
+    let proxy: xray::proxy::hysteria::ServerConfig = decode_typed(&proxy_tm);
+    assert_eq!(proxy.version, 2);
+    assert!(proxy.users.is_empty());
+}
+
+#[test]
+fn build_add_user_operation_hysteria2_encodes_auth() {
+    let endpoint = hysteria2_endpoint();
+
+    let err = build_add_user_operation(&endpoint, "m:u1::e3", None, None, None, None).unwrap_err();
+    assert!(format!("{err}").contains("missing hysteria2_auth"));
+
+    let tm =
+        build_add_user_operation(&endpoint, "m:u1::e3", None, None, None, Some("secret")).unwrap();
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let account_tm = op.user.unwrap().account.unwrap();
+    assert_eq!(account_tm.r#type, TYPE_HYSTERIA_ACCOUNT);
+    let account: xray::proxy::hysteria::account::Account = decode_typed(&account_tm);
+    assert_eq!(account.auth, "secret");
+}
+
+#[test]
+fn build_add_inbound_request_trojan_tls_uses_tcp_with_tls() {
+    let mut endpoint = hysteria2_endpoint();
+    endpoint.kind = EndpointKind::TrojanTlsTcp;
+
+    let req = build_add_inbound_request(&endpoint).unwrap();
+    let inbound = req.inbound.unwrap();
+    let receiver: xray::app::proxyman::ReceiverConfig =
+        decode_typed(&inbound.receiver_settings.unwrap());
+    let stream = receiver.stream_settings.unwrap();
+    assert_eq!(stream.protocol_name, "tcp");
+    assert_eq!(stream.security_type, TYPE_TLS_SECURITY_CONFIG);
+    let tls: xray::transport::internet::tls::Config = decode_typed(&stream.security_settings[0]);
+    assert_eq!(tls.next_protocol, vec!["http/1.1".to_string()]);
+    assert_eq!(tls.certificate.len(), 1);
+    assert_eq!(
+        inbound.proxy_settings.unwrap().r#type,
+        TYPE_TROJAN_SERVER_CONFIG
+    );
+
+    let tm = build_add_user_operation(&endpoint, "m:u1::e3", None, None, Some("pw"), None).unwrap();
+    let op: xray::app::proxyman::command::AddUserOperation = decode_typed(&tm);
+    let account: xray::proxy::trojan::Account = decode_typed(&op.user.unwrap().account.unwrap());
+    assert_eq!(account.password, "pw");
+}
@@ -65,6 +65,18 @@ pub mod xray {
         pub mod socks {
             tonic::include_proto!("xray.proxy.socks");
         }
+
+        pub mod trojan {
+            tonic::include_proto!("xray.proxy.trojan");
+        }
+
+        pub mod hysteria {
+            tonic::include_proto!("xray.proxy.hysteria");
+
+            pub mod account {
+                tonic::include_proto!("xray.proxy.hysteria.account");
+            }
+        }
     }
 
     pub mod transport {
@@ -82,6 +94,14 @@ pub mod xray {
             pub mod tcp {
                 tonic::include_proto!("xray.transport.internet.tcp");
             }
+
+            pub mod hysteria {
+                tonic::include_proto!("xray.transport.internet.hysteria");
+            }
+
+            pub mod tls {
+                tonic::include_proto!("xray.transport.internet.tls");
+            }
         }
     }
 }
@@ -322,6 +322,9 @@ async fn forwarding_raft_facade_client_write_forwards_to_leader() -> anyhow::Res
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir: leader_dir.clone(),
         admin_token_hash: test_admin_token_hash(&admin_token),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: cluster.node_name.clone(),
         access_host: cluster.access_host.clone(),
         api_base_url: admin_base_url.clone(),
@@ -350,9 +353,14 @@ async fn forwarding_raft_facade_client_write_forwards_to_leader() -> anyhow::Res
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: xp::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     };
@@ -427,6 +435,10 @@ async fn forwarding_raft_facade_client_write_forwards_to_leader() -> anyhow::Res
             day_of_month: 1,
             tz_offset_minutes: 480,
         },
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     };
     let cmd = DesiredStateCommand::UpsertUser { user: user.clone() };
 
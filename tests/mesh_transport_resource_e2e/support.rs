@@ -111,6 +111,7 @@ async fn signed_response(State(state): State<PeerServerState>, request: Request)
     let verified = match internal_auth::verify_request_v2(
         &state.ca_key_pem,
         &state.ca_cert_pem,
+        &[],
         &parts.method,
         &parts.uri,
         &parts.headers,
@@ -297,6 +297,10 @@ async fn run_raft_cluster_replication_smoke(node_count: usize) -> anyhow::Result
             day_of_month: 1,
             tz_offset_minutes: 480,
         },
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
     };
     leader
         .client_write(DesiredStateCommand::UpsertUser { user: user.clone() })
@@ -392,6 +396,10 @@ async fn raft_single_node_restart_recovers_state_and_snapshot_files() -> anyhow:
                 day_of_month: 1,
                 tz_offset_minutes: 480,
             },
+            expires_at: None,
+            disabled: false,
+            max_concurrent_ips: None,
+            pinned_credentials: Default::default(),
         };
         raft.client_write(DesiredStateCommand::UpsertUser { user: user.clone() })
             .await
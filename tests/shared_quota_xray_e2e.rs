@@ -70,6 +70,9 @@ fn test_config(data_dir: PathBuf, xray_api_addr: SocketAddr) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: test_admin_token_hash("testtoken"),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -98,9 +101,14 @@ fn test_config(data_dir: PathBuf, xray_api_addr: SocketAddr) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: xp::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
@@ -126,16 +134,18 @@ fn req_authed_json(method: &str, uri: &str, value: serde_json::Value) -> Request
         .unwrap()
 }
 
+fn derive_aes128_user_psk(cluster_ca_key_pem: &str, user_id: &str) -> String {
+    let psk_len = xp::protocol::SS2022_PSK_LEN_BYTES_AES_128;
+    xp::credentials::derive_ss2022_user_psk_b64(cluster_ca_key_pem, user_id, 0, psk_len)
+        .expect("derive ss2022 user psk")
+}
+
 async fn spawn_echo_server() -> (u16, tokio::task::JoinHandle<()>) {
     let listener = TcpListener::bind(("0.0.0.0", 0)).await.unwrap();
     let port = listener.local_addr().unwrap().port();
 
     let handle = tokio::spawn(async move {
-        loop {
-            let (mut stream, _) = match listener.accept().await {
-                Ok(v) => v,
-                Err(_) => break,
-            };
+        while let Ok((mut stream, _)) = listener.accept().await {
             tokio::spawn(async move {
                 let mut buf = vec![0u8; 16 * 1024];
                 loop {
@@ -449,9 +459,7 @@ async fn shared_quota_e2e_p3_is_banned_without_overflow_then_unbanned_with_overf
         assert_eq!(res.status(), axum::http::StatusCode::OK);
         drop(res);
 
-        let user_psk_b64 =
-            xp::credentials::derive_ss2022_user_psk_b64(&cluster_ca_key_pem, &user_id, 0)
-                .expect("derive ss2022 user psk");
+        let user_psk_b64 = derive_aes128_user_psk(&cluster_ca_key_pem, &user_id);
         let password = xp::protocol::ss2022_password(&server_psk_b64, &user_psk_b64);
         ss_password_by_user_id.insert(user_id, password);
     }
@@ -714,9 +722,7 @@ async fn shared_quota_e2e_policy_change_weight_decrease_bans_without_new_traffic
         assert_eq!(res.status(), axum::http::StatusCode::OK);
         drop(res);
 
-        let user_psk_b64 =
-            xp::credentials::derive_ss2022_user_psk_b64(&cluster_ca_key_pem, &user_id, 0)
-                .expect("derive ss2022 user psk");
+        let user_psk_b64 = derive_aes128_user_psk(&cluster_ca_key_pem, &user_id);
         let password = xp::protocol::ss2022_password(&server_psk_b64, &user_psk_b64);
         ss_password_by_user_id.insert(user_id, password);
     }
@@ -961,9 +967,7 @@ async fn shared_quota_e2e_cycle_rollover_unbans_and_resets() {
     assert_eq!(res.status(), axum::http::StatusCode::OK);
     drop(res);
 
-    let user_psk_b64 =
-        xp::credentials::derive_ss2022_user_psk_b64(&cluster_ca_key_pem, &p2_user_id, 0)
-            .expect("derive ss2022 user psk");
+    let user_psk_b64 = derive_aes128_user_psk(&cluster_ca_key_pem, &p2_user_id);
     let p2_password = xp::protocol::ss2022_password(&server_psk_b64, &user_psk_b64);
 
     let (dest_port, echo_task) = spawn_echo_server().await;
@@ -80,6 +80,9 @@ fn test_config(data_dir: PathBuf, xray_api_addr: SocketAddr) -> Config {
         cloudflared_openrc_service: "cloudflared".to_string(),
         data_dir,
         admin_token_hash: test_admin_token_hash("testtoken"),
+        metrics_bind: None,
+        metrics_token_hash: String::new(),
+        subscription_update_interval_hours: 24,
         node_name: xp_test_fixtures::label_node1_variant2().to_owned(),
         access_host: xp_test_fixtures::label_empty().to_owned(),
         api_base_url: xp_test_fixtures::url_loopback62416().to_owned(),
@@ -108,9 +111,14 @@ fn test_config(data_dir: PathBuf, xray_api_addr: SocketAddr) -> Config {
         cloudflare_ddns_fast_interval_secs: 30,
         cloudflare_ddns_fast_window_secs: 300,
         cloudflare_ddns_family_missing_grace: 3,
+        ddns: xp::config::DdnsProviderConfig::default(),
         endpoint_probe_skip_self_test: false,
         quota_poll_interval_secs: 10,
         quota_auto_unban: true,
+        quota_warning_thresholds: vec![80, 95],
+        subscription_abuse_max_ips: 10,
+        subscription_abuse_max_countries: 3,
+        subscription_abuse_window_hours: 24,
         ip_geo_enabled: false,
         ip_geo_origin: "https://api.country.is".to_string(),
     }
@@ -649,9 +657,13 @@ async fn xray_e2e_quota_enforcement_ss2022() {
         let endpoint = store.get_endpoint(&endpoint_id_ss).unwrap();
         let meta: Ss2022EndpointMeta =
             serde_json::from_value(endpoint.meta.clone()).expect("ss2022 endpoint meta");
-        let user_psk_b64 =
-            credentials::derive_ss2022_user_psk_b64(&cluster_ca_key_pem, &user_id, 0)
-                .expect("derive ss2022 user_psk");
+        let user_psk_b64 = credentials::derive_ss2022_user_psk_b64(
+            &cluster_ca_key_pem,
+            &user_id,
+            0,
+            xp::protocol::SS2022_PSK_LEN_BYTES_AES_128,
+        )
+        .expect("derive ss2022 user_psk");
         ss2022_password(&meta.server_psk_b64, &user_psk_b64)
     };
 
@@ -461,15 +461,23 @@ fn set_reality_destination_port(endpoint: &mut Endpoint, port: u16) {
         serde_json::Value::String(format!("host.docker.internal:{port}"));
 }
 
-fn render_mihomo_config(endpoint: &Endpoint, external_port: u16, socks_port: u16) -> String {
-    let user = User {
+fn e2e_user(display_name: &str) -> User {
+    User {
         user_id: xp_test_fixtures::primary_user_id().to_owned(),
-        display_name: "xhttp-e2e".to_string(),
+        display_name: display_name.to_string(),
         subscription_token: xp_test_fixtures::primary_token().to_owned(),
         credential_epoch: 0,
         priority_tier: UserPriorityTier::P2,
         quota_reset: UserQuotaReset::default(),
-    };
+        expires_at: None,
+        disabled: false,
+        max_concurrent_ips: None,
+        pinned_credentials: Default::default(),
+    }
+}
+
+fn render_mihomo_config(endpoint: &Endpoint, external_port: u16, socks_port: u16) -> String {
+    let user = e2e_user("xhttp-e2e");
     let node = Node {
         node_id: xp_test_fixtures::primary_node_id().to_owned(),
         node_name: xp_test_fixtures::primary_node_name().to_owned(),
@@ -602,6 +610,8 @@ async fn mihomo_xhttp_xmux_reuses_one_connection_and_recovers_after_disconnect()
                     ),
                     Some(&uuid),
                     None,
+                    None,
+                    None,
                 )
                 .expect("build XHTTP user"),
             ),
@@ -673,14 +683,7 @@ async fn mihomo_xhttp_xmux_reuses_one_connection_and_recovers_after_disconnect()
 async fn mihomo_provider_chain_has_no_direct_fallback() {
     let mihomo_binary = std::env::var("XP_E2E_MIHOMO_BIN")
         .expect("XP_E2E_MIHOMO_BIN for the real Mihomo provider smoke");
-    let user = User {
-        user_id: xp_test_fixtures::primary_user_id().to_owned(),
-        display_name: "mihomo-provider-e2e".to_string(),
-        subscription_token: xp_test_fixtures::primary_token().to_owned(),
-        credential_epoch: 0,
-        priority_tier: UserPriorityTier::P2,
-        quota_reset: UserQuotaReset::default(),
-    };
+    let user = e2e_user("mihomo-provider-e2e");
     let node = Node {
         node_id: xp_test_fixtures::primary_node_id().to_owned(),
         node_name: xp_test_fixtures::primary_node_name().to_owned(),
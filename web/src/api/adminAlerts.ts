@@ -10,6 +10,27 @@ export const AlertItemSchema = z.object({
 	owner_node_id: z.string(),
 	quota_banned: z.boolean(),
 	quota_banned_at: z.string().nullable(),
+	expires_at: z.string().optional(),
+	blocked_until: z.string().optional(),
+	offending_ips: z
+		.array(
+			z.object({
+				ip: z.string(),
+				geo: z
+					.object({
+						country: z.string(),
+						region: z.string(),
+						city: z.string(),
+						operator: z.string(),
+					})
+					.partial()
+					.optional(),
+			}),
+		)
+		.optional(),
+	threshold_percent: z.number().int().optional(),
+	remaining_bytes: z.number().int().nonnegative().optional(),
+	projected_exhaustion_at: z.string().optional(),
 	message: z.string(),
 	action_hint: z.string(),
 });
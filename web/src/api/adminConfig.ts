@@ -12,6 +12,7 @@ export const AdminConfigResponseSchema = z.object({
 	vless_https_canary_bind: z.string(),
 	quota_poll_interval_secs: z.number(),
 	quota_auto_unban: z.boolean(),
+	quota_warning_thresholds: z.array(z.number().int()).optional(),
 	ip_geo_enabled: z.boolean(),
 	ip_geo_origin: z.string(),
 	mihomo_resource_allow_private_targets: z.boolean().optional(),
@@ -0,0 +1,115 @@
+import { z } from "zod";
+
+import { throwIfNotOk } from "./backendError";
+
+export const AdminDnsFailoverGroupSchema = z.object({
+	group_id: z.string(),
+	hostname: z.string(),
+	primary_node_id: z.string(),
+	standby_node_ids: z.array(z.string()),
+	primary_addresses: z.array(z.string()).optional(),
+});
+
+export type AdminDnsFailoverGroup = z.infer<typeof AdminDnsFailoverGroupSchema>;
+
+export const AdminDnsFailoverGroupsResponseSchema = z.object({
+	items: z.array(AdminDnsFailoverGroupSchema),
+});
+
+export type AdminDnsFailoverGroupsResponse = z.infer<
+	typeof AdminDnsFailoverGroupsResponseSchema
+>;
+
+export type AdminDnsFailoverGroupRequest = {
+	hostname: string;
+	primary_node_id: string;
+	standby_node_ids: string[];
+	primary_addresses?: string[];
+};
+
+const GROUPS_PATH = "/api/admin/dns-failover-groups";
+
+function groupPath(groupId: string): string {
+	return `${GROUPS_PATH}/${encodeURIComponent(groupId)}`;
+}
+
+export async function fetchAdminDnsFailoverGroups(
+	adminToken: string,
+	signal?: AbortSignal,
+): Promise<AdminDnsFailoverGroupsResponse> {
+	const res = await fetch(GROUPS_PATH, {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminDnsFailoverGroupsResponseSchema.parse(json);
+}
+
+export async function createAdminDnsFailoverGroup(
+	adminToken: string,
+	payload: AdminDnsFailoverGroupRequest,
+	signal?: AbortSignal,
+): Promise<AdminDnsFailoverGroup> {
+	const res = await fetch(GROUPS_PATH, {
+		method: "POST",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(payload),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminDnsFailoverGroupSchema.parse(json);
+}
+
+export async function putAdminDnsFailoverGroup(
+	adminToken: string,
+	groupId: string,
+	payload: AdminDnsFailoverGroupRequest,
+	signal?: AbortSignal,
+): Promise<AdminDnsFailoverGroup> {
+	const res = await fetch(groupPath(groupId), {
+		method: "PUT",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(payload),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminDnsFailoverGroupSchema.parse(json);
+}
+
+export async function deleteAdminDnsFailoverGroup(
+	adminToken: string,
+	groupId: string,
+	signal?: AbortSignal,
+): Promise<void> {
+	const res = await fetch(groupPath(groupId), {
+		method: "DELETE",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+}
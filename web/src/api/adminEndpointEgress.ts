@@ -0,0 +1,67 @@
+import { z } from "zod";
+
+import { throwIfNotOk } from "./backendError";
+
+export const AdminEndpointEgressSchema = z.discriminatedUnion("kind", [
+	z.object({ kind: z.literal("direct") }),
+	z.object({ kind: z.literal("node"), node_id: z.string() }),
+	z.object({
+		kind: z.literal("socks"),
+		address: z.string(),
+		port: z.number().int(),
+		username: z.string().optional(),
+		// Write-only: responses carry `has_password` instead.
+		password: z.string().optional(),
+		has_password: z.boolean().optional(),
+	}),
+	z.object({ kind: z.literal("xray_outbound"), tag: z.string() }),
+]);
+
+export type AdminEndpointEgress = z.infer<typeof AdminEndpointEgressSchema>;
+
+function egressPath(endpointId: string): string {
+	return `/api/admin/endpoints/${encodeURIComponent(endpointId)}/egress`;
+}
+
+export async function fetchAdminEndpointEgress(
+	adminToken: string,
+	endpointId: string,
+	signal?: AbortSignal,
+): Promise<AdminEndpointEgress> {
+	const res = await fetch(egressPath(endpointId), {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminEndpointEgressSchema.parse(json);
+}
+
+export async function putAdminEndpointEgress(
+	adminToken: string,
+	endpointId: string,
+	egress: AdminEndpointEgress,
+	signal?: AbortSignal,
+): Promise<AdminEndpointEgress> {
+	const res = await fetch(egressPath(endpointId), {
+		method: "PUT",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(egress),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminEndpointEgressSchema.parse(json);
+}
@@ -48,6 +48,17 @@ export type AdminEndpointCanaryProbeResponse = z.infer<
 	typeof AdminEndpointCanaryProbeResponseSchema
 >;
 
+export const EndpointProbeTargetSampleSchema = z.object({
+	target_id: z.string(),
+	ok: z.boolean(),
+	latency_ms: z.number().int().nonnegative().nullable().optional(),
+	error: z.string().nullable().optional(),
+});
+
+export type EndpointProbeTargetSample = z.infer<
+	typeof EndpointProbeTargetSampleSchema
+>;
+
 export const AdminEndpointProbeHistoryNodeSchema = z.object({
 	node_id: z.string(),
 	ok: z.boolean(),
@@ -57,6 +68,7 @@ export const AdminEndpointProbeHistoryNodeSchema = z.object({
 	target_id: z.string().optional(),
 	target_url: z.string().optional(),
 	error: z.string().optional(),
+	targets: z.array(EndpointProbeTargetSampleSchema).optional(),
 	config_hash: z.string(),
 });
 
@@ -81,11 +93,27 @@ export type AdminEndpointProbeHistorySlot = z.infer<
 	typeof AdminEndpointProbeHistorySlotSchema
 >;
 
+export const AdminUnreachableProbeTargetSchema = z.object({
+	endpoint_id: z.string(),
+	node_id: z.string(),
+	target_id: z.string(),
+	target_label: z.string(),
+	target_url: z.string(),
+	since: z.string(),
+	last_checked_at: z.string(),
+	error: z.string().optional(),
+});
+
+export type AdminUnreachableProbeTarget = z.infer<
+	typeof AdminUnreachableProbeTargetSchema
+>;
+
 export const AdminEndpointProbeHistoryResponseSchema = z.object({
 	endpoint_id: z.string(),
 	participating_nodes: z.number().int().nonnegative().optional(),
 	expected_nodes: z.number().int().nonnegative().optional(),
 	slots: z.array(AdminEndpointProbeHistorySlotSchema),
+	unreachable_targets: z.array(AdminUnreachableProbeTargetSchema).optional(),
 });
 
 export type AdminEndpointProbeHistoryResponse = z.infer<
@@ -159,6 +187,88 @@ export async function fetchAdminEndpointProbeHistory(
 	return AdminEndpointProbeHistoryResponseSchema.parse(json);
 }
 
+export const EndpointProbeTargetSchema = z.object({
+	id: z.string(),
+	label: z.string(),
+	url: z.string(),
+	expected_status: z.number().int(),
+	expected_body_prefix: z.string().optional(),
+	required: z.boolean(),
+});
+
+export type EndpointProbeTarget = z.infer<typeof EndpointProbeTargetSchema>;
+
+export const AdminEndpointProbeTargetsResponseSchema = z.object({
+	targets: z.array(EndpointProbeTargetSchema),
+	config_hash: z.string(),
+});
+
+export type AdminEndpointProbeTargetsResponse = z.infer<
+	typeof AdminEndpointProbeTargetsResponseSchema
+>;
+
+export async function fetchAdminEndpointProbeTargets(
+	adminToken: string,
+	signal?: AbortSignal,
+): Promise<AdminEndpointProbeTargetsResponse> {
+	const res = await fetch("/api/admin/endpoints/probe/targets", {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminEndpointProbeTargetsResponseSchema.parse(json);
+}
+
+export async function putAdminEndpointProbeTargets(
+	adminToken: string,
+	targets: EndpointProbeTarget[],
+	signal?: AbortSignal,
+): Promise<AdminEndpointProbeTargetsResponse> {
+	const res = await fetch("/api/admin/endpoints/probe/targets", {
+		method: "PUT",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify({ targets }),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminEndpointProbeTargetsResponseSchema.parse(json);
+}
+
+export async function fetchAdminUnreachableProbeTargets(
+	adminToken: string,
+	signal?: AbortSignal,
+): Promise<AdminUnreachableProbeTarget[]> {
+	const res = await fetch("/api/admin/endpoints/probe/unreachable-targets", {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return z
+		.object({ items: z.array(AdminUnreachableProbeTargetSchema) })
+		.parse(json).items;
+}
+
 export const AdminEndpointProbeRunProgressStatusSchema = z.enum([
 	"running",
 	"finished",
@@ -267,6 +377,7 @@ export const EndpointProbeAppendSampleSchema = z.object({
 	target_id: z.string().nullable().optional(),
 	target_url: z.string().nullable().optional(),
 	error: z.string().nullable().optional(),
+	targets: z.array(EndpointProbeTargetSampleSchema).optional(),
 	config_hash: z.string(),
 });
 
@@ -28,6 +28,8 @@ export const RuntimeEventKindSchema = z.enum([
 	"restart_requested",
 	"restart_succeeded",
 	"restart_failed",
+	"failover_activated",
+	"failover_restored",
 ]);
 
 export const NodeRuntimeSummarySchema = z.object({
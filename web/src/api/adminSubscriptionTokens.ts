@@ -0,0 +1,109 @@
+import { z } from "zod";
+
+import { throwIfNotOk } from "./backendError";
+
+export const AdminSubscriptionTokenFormatSchema = z.enum([
+	"base64",
+	"raw",
+	"clash",
+	"mihomo",
+	"singbox",
+]);
+
+export type AdminSubscriptionTokenFormat = z.infer<
+	typeof AdminSubscriptionTokenFormatSchema
+>;
+
+export const AdminSubscriptionTokenSchema = z.object({
+	token_id: z.string(),
+	user_id: z.string(),
+	name: z.string(),
+	token: z.string(),
+	created_at: z.string(),
+	expires_at: z.string().nullable().optional(),
+	format: AdminSubscriptionTokenFormatSchema.nullable().optional(),
+	endpoint_ids: z.array(z.string()).nullable().optional(),
+});
+
+export type AdminSubscriptionToken = z.infer<
+	typeof AdminSubscriptionTokenSchema
+>;
+
+export const AdminSubscriptionTokensResponseSchema = z.object({
+	items: z.array(AdminSubscriptionTokenSchema),
+});
+
+export type AdminSubscriptionTokensResponse = z.infer<
+	typeof AdminSubscriptionTokensResponseSchema
+>;
+
+export type AdminSubscriptionTokenCreateRequest = {
+	name: string;
+	expires_at?: string;
+	format?: AdminSubscriptionTokenFormat;
+	endpoint_ids?: string[];
+};
+
+export async function fetchAdminSubscriptionTokens(
+	adminToken: string,
+	userId: string,
+	signal?: AbortSignal,
+): Promise<AdminSubscriptionTokensResponse> {
+	const res = await fetch(`/api/admin/users/${userId}/subscription-tokens`, {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminSubscriptionTokensResponseSchema.parse(json);
+}
+
+export async function createAdminSubscriptionToken(
+	adminToken: string,
+	userId: string,
+	payload: AdminSubscriptionTokenCreateRequest,
+	signal?: AbortSignal,
+): Promise<AdminSubscriptionToken> {
+	const res = await fetch(`/api/admin/users/${userId}/subscription-tokens`, {
+		method: "POST",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(payload),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminSubscriptionTokenSchema.parse(json);
+}
+
+export async function revokeAdminSubscriptionToken(
+	adminToken: string,
+	userId: string,
+	tokenId: string,
+	signal?: AbortSignal,
+): Promise<void> {
+	const res = await fetch(
+		`/api/admin/users/${userId}/subscription-tokens/${tokenId}`,
+		{
+			method: "DELETE",
+			headers: {
+				Accept: "application/json",
+				Authorization: `Bearer ${adminToken}`,
+			},
+			signal,
+		},
+	);
+
+	await throwIfNotOk(res);
+}
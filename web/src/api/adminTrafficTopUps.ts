@@ -0,0 +1,91 @@
+import { z } from "zod";
+
+import { throwIfNotOk } from "./backendError";
+
+export const AdminTrafficTopUpSchema = z.object({
+	topup_id: z.string(),
+	user_id: z.string(),
+	node_id: z.string(),
+	bytes: z.number().int().positive(),
+	created_at: z.string(),
+	expires_at: z.string().nullable().optional(),
+	note: z.string().nullable().optional(),
+});
+
+export type AdminTrafficTopUp = z.infer<typeof AdminTrafficTopUpSchema>;
+
+export const AdminTrafficTopUpsResponseSchema = z.object({
+	items: z.array(AdminTrafficTopUpSchema),
+});
+
+export type AdminTrafficTopUpsResponse = z.infer<
+	typeof AdminTrafficTopUpsResponseSchema
+>;
+
+export type AdminTrafficTopUpCreateRequest = {
+	bytes: number;
+	node_id: string;
+	expires_at?: string;
+	note?: string;
+};
+
+export async function fetchAdminTrafficTopUps(
+	adminToken: string,
+	userId: string,
+	signal?: AbortSignal,
+): Promise<AdminTrafficTopUpsResponse> {
+	const res = await fetch(`/api/admin/users/${userId}/topups`, {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminTrafficTopUpsResponseSchema.parse(json);
+}
+
+export async function createAdminTrafficTopUp(
+	adminToken: string,
+	userId: string,
+	payload: AdminTrafficTopUpCreateRequest,
+	signal?: AbortSignal,
+): Promise<AdminTrafficTopUp> {
+	const res = await fetch(`/api/admin/users/${userId}/topups`, {
+		method: "POST",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(payload),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminTrafficTopUpSchema.parse(json);
+}
+
+export async function deleteAdminTrafficTopUp(
+	adminToken: string,
+	userId: string,
+	topupId: string,
+	signal?: AbortSignal,
+): Promise<void> {
+	const res = await fetch(`/api/admin/users/${userId}/topups/${topupId}`, {
+		method: "DELETE",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+}
@@ -9,6 +9,7 @@ export const AdminUserNodeQuotaStatusItemSchema = z.object({
 	quota_limit_bytes: z.number().int().nonnegative(),
 	used_bytes: z.number().int().nonnegative(),
 	remaining_bytes: z.number().int().nonnegative(),
+	topup_remaining_bytes: z.number().int().nonnegative().optional(),
 	cycle_end_at: z.string().nullable(),
 	quota_reset_source: QuotaResetSourceSchema,
 });
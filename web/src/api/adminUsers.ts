@@ -10,6 +10,13 @@ export const AdminUserSchema = z.object({
 	credential_epoch: z.number().int().nonnegative(),
 	priority_tier: z.enum(["p1", "p2", "p3"]),
 	quota_reset: UserQuotaResetSchema,
+	max_concurrent_ips: z.number().int().positive().nullable().optional(),
+	pinned_credentials: z
+		.object({
+			vless_uuid: z.string().optional(),
+			ss2022_psk_b64: z.string().optional(),
+		})
+		.optional(),
 });
 
 export type AdminUser = z.infer<typeof AdminUserSchema>;
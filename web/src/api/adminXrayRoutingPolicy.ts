@@ -0,0 +1,58 @@
+import { z } from "zod";
+
+import { throwIfNotOk } from "./backendError";
+
+export const AdminXrayRoutingPolicySchema = z.object({
+	sniffing: z.boolean(),
+	block_private: z.boolean(),
+	block_bittorrent: z.boolean(),
+	blocked_domains: z.array(z.string()),
+	blocked_ips: z.array(z.string()),
+	exempt_endpoint_ids: z.array(z.string()),
+	exempt_priority_tiers: z.array(z.enum(["p1", "p2", "p3"])),
+});
+
+export type AdminXrayRoutingPolicy = z.infer<
+	typeof AdminXrayRoutingPolicySchema
+>;
+
+export async function fetchAdminXrayRoutingPolicy(
+	adminToken: string,
+	signal?: AbortSignal,
+): Promise<AdminXrayRoutingPolicy> {
+	const res = await fetch("/api/admin/xray-routing-policy", {
+		method: "GET",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+		},
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminXrayRoutingPolicySchema.parse(json);
+}
+
+export async function putAdminXrayRoutingPolicy(
+	adminToken: string,
+	policy: AdminXrayRoutingPolicy,
+	signal?: AbortSignal,
+): Promise<AdminXrayRoutingPolicy> {
+	const res = await fetch("/api/admin/xray-routing-policy", {
+		method: "PUT",
+		headers: {
+			Accept: "application/json",
+			Authorization: `Bearer ${adminToken}`,
+			"Content-Type": "application/json",
+		},
+		body: JSON.stringify(policy),
+		signal,
+	});
+
+	await throwIfNotOk(res);
+
+	const json: unknown = await res.json();
+	return AdminXrayRoutingPolicySchema.parse(json);
+}
@@ -3,6 +3,26 @@ import { z } from "zod";
 export const QuotaResetSourceSchema = z.enum(["user", "node"]);
 export type QuotaResetSource = z.infer<typeof QuotaResetSourceSchema>;
 
+export const QuotaResetPolicySchema = z.enum([
+	"monthly",
+	"weekly",
+	"daily",
+	"fixed_days",
+	"unlimited",
+]);
+export type QuotaResetPolicy = z.infer<typeof QuotaResetPolicySchema>;
+
+export const QuotaWeekdaySchema = z.enum([
+	"monday",
+	"tuesday",
+	"wednesday",
+	"thursday",
+	"friday",
+	"saturday",
+	"sunday",
+]);
+export type QuotaWeekday = z.infer<typeof QuotaWeekdaySchema>;
+
 export const UserQuotaResetSchema = z.union([
 	z.object({
 		policy: z.literal("unlimited"),
@@ -13,6 +33,21 @@ export const UserQuotaResetSchema = z.union([
 		day_of_month: z.number().int().min(1).max(31),
 		tz_offset_minutes: z.number().int(),
 	}),
+	z.object({
+		policy: z.literal("weekly"),
+		weekday: QuotaWeekdaySchema,
+		tz_offset_minutes: z.number().int(),
+	}),
+	z.object({
+		policy: z.literal("daily"),
+		tz_offset_minutes: z.number().int(),
+	}),
+	z.object({
+		policy: z.literal("fixed_days"),
+		anchor: z.string(),
+		length_days: z.number().int().min(1).max(366),
+		tz_offset_minutes: z.number().int(),
+	}),
 ]);
 
 export type UserQuotaReset = z.infer<typeof UserQuotaResetSchema>;
@@ -27,6 +62,21 @@ export const NodeQuotaResetSchema = z.union([
 		day_of_month: z.number().int().min(1).max(31),
 		tz_offset_minutes: z.number().int().nullable().optional(),
 	}),
+	z.object({
+		policy: z.literal("weekly"),
+		weekday: QuotaWeekdaySchema,
+		tz_offset_minutes: z.number().int().nullable().optional(),
+	}),
+	z.object({
+		policy: z.literal("daily"),
+		tz_offset_minutes: z.number().int().nullable().optional(),
+	}),
+	z.object({
+		policy: z.literal("fixed_days"),
+		anchor: z.string(),
+		length_days: z.number().int().min(1).max(366),
+		tz_offset_minutes: z.number().int().nullable().optional(),
+	}),
 ]);
 
 export type NodeQuotaReset = z.infer<typeof NodeQuotaResetSchema>;
@@ -12,6 +12,7 @@ describe("subscription format contract", () => {
 			{ value: "raw", label: "Raw" },
 			{ value: "clash", label: "Clash" },
 			{ value: "mihomo", label: "Mihomo" },
+			{ value: "singbox", label: "sing-box" },
 		]);
 		expect(
 			SUBSCRIPTION_FORMAT_OPTIONS.some((option) =>
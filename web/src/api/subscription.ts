@@ -1,6 +1,6 @@
 import { throwIfNotOk } from "./backendError";
 
-export type SubscriptionFormat = "raw" | "clash" | "mihomo";
+export type SubscriptionFormat = "raw" | "clash" | "mihomo" | "singbox";
 
 export type SubscriptionFormatOption = {
 	value: SubscriptionFormat;
@@ -11,6 +11,7 @@ export const SUBSCRIPTION_FORMAT_OPTIONS = [
 	{ value: "raw", label: "Raw" },
 	{ value: "clash", label: "Clash" },
 	{ value: "mihomo", label: "Mihomo" },
+	{ value: "singbox", label: "sing-box" },
 ] as const satisfies readonly SubscriptionFormatOption[];
 
 export const DEFAULT_SUBSCRIPTION_FORMAT: SubscriptionFormat = "raw";
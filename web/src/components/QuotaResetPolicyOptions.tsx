@@ -0,0 +1,27 @@
+import type { NodeQuotaReset, UserQuotaReset } from "../api/quotaReset";
+import { formatQuotaResetCycle } from "../utils/quotaPolicyView";
+import { SelectContent, SelectItem } from "./ui/select";
+
+type QuotaResetPolicyOptionsProps = {
+	// Weekly, daily and fixed_days cycles cannot be built here; the saved one is
+	// offered so it can be kept.
+	current?: NodeQuotaReset | UserQuotaReset;
+};
+
+export function QuotaResetPolicyOptions({
+	current,
+}: QuotaResetPolicyOptionsProps) {
+	return (
+		<SelectContent>
+			<SelectItem value="monthly">monthly</SelectItem>
+			{current &&
+			current.policy !== "monthly" &&
+			current.policy !== "unlimited" ? (
+				<SelectItem value={current.policy}>
+					{formatQuotaResetCycle(current)}
+				</SelectItem>
+			) : null}
+			<SelectItem value="unlimited">unlimited</SelectItem>
+		</SelectContent>
+	);
+}
@@ -34,6 +34,7 @@ describe("demo mock API", () => {
 			"raw",
 			"clash",
 			"mihomo",
+			"singbox",
 		]);
 		vi.useRealTimers();
 	});
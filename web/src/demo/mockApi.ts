@@ -25,6 +25,16 @@ export async function fetchDemoSubscription(
 	);
 	if (assignedEndpoints.length === 0) return "# no endpoint access assigned";
 
+	if (format === "singbox") {
+		const outbounds = assignedEndpoints.map((endpoint) => ({
+			type: endpointType(endpoint),
+			tag: endpoint.name,
+			server: endpointHost(endpoint, state.nodes),
+			server_port: endpoint.port,
+		}));
+		return JSON.stringify({ outbounds }, null, 2);
+	}
+
 	if (format === "clash" || format === "mihomo") {
 		const header =
 			format === "mihomo"
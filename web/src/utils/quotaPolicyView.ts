@@ -1,3 +1,4 @@
+import type { NodeQuotaReset, UserQuotaReset } from "../api/quotaReset";
 import { RATIO_BASIS_POINTS } from "./quotaPolicyWeights";
 
 export function formatUtcOffsetMinutes(minutes: number): string {
@@ -8,18 +9,35 @@ export function formatUtcOffsetMinutes(minutes: number): string {
 	return `UTC${sign}${hh}:${mm}`;
 }
 
-export function formatNodeQuotaResetBrief(q: {
-	policy: "monthly" | "unlimited";
-	day_of_month?: number;
-	tz_offset_minutes?: number | null;
-}): string {
+export function formatQuotaResetCycle(
+	q: NodeQuotaReset | UserQuotaReset,
+): string {
+	switch (q.policy) {
+		case "monthly":
+			return `monthly@${q.day_of_month}`;
+		case "weekly":
+			return `weekly@${q.weekday}`;
+		case "daily":
+			return "daily";
+		case "fixed_days":
+			return `every ${q.length_days}d from ${q.anchor}`;
+		case "unlimited":
+			return "unlimited";
+	}
+}
+
+export function quotaResetDayOfMonth(
+	q: NodeQuotaReset | UserQuotaReset,
+): number {
+	return q.policy === "monthly" ? q.day_of_month : 1;
+}
+
+export function formatNodeQuotaResetBrief(q: NodeQuotaReset): string {
 	const tz =
 		q.tz_offset_minutes === null || q.tz_offset_minutes === undefined
 			? "(local)"
 			: formatUtcOffsetMinutes(q.tz_offset_minutes);
-	return q.policy === "monthly"
-		? `monthly@${q.day_of_month ?? 1} ${tz}`
-		: `unlimited ${tz}`;
+	return `${formatQuotaResetCycle(q)} ${tz}`;
 }
 
 export function ratioStatusTone(
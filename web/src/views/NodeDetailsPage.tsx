@@ -3,7 +3,6 @@ import { useQuery, useQueryClient } from "@tanstack/react-query";
 import { Link, useNavigate, useParams } from "@tanstack/react-router";
 import { useCallback, useEffect, useMemo, useState } from "react";
 import { useForm } from "react-hook-form";
-import { z } from "zod";
 
 import type { AdminIpUsageWindow } from "../api/adminIpUsage";
 import {
@@ -39,6 +38,7 @@ import { PageHeader } from "../components/PageHeader";
 import { CapabilityUnavailableState, PageState } from "../components/PageState";
 import { QueryErrorState } from "../components/QueryErrorState";
 import { QueryRefreshError } from "../components/QueryRefreshError";
+import { QuotaResetPolicyOptions } from "../components/QuotaResetPolicyOptions";
 import { ReadStateBanner } from "../components/ReadStateBanner";
 import { TcpConnectionUsageView } from "../components/TcpConnectionUsageView";
 import { useToast } from "../components/Toast";
@@ -81,8 +81,11 @@ import {
 	useNodeDeleteFlow,
 } from "./nodeDetailsDeleteOperation";
 import {
+	type QuotaResetFormInput,
+	type QuotaResetFormValues,
 	isNodeQuotaDraftDirty,
 	nodeQuotaDraftFromNode,
+	quotaResetSchema,
 	toNodeQuotaReset,
 } from "./nodeQuotaDraft";
 function summaryBadgeVariant(status: string) {
@@ -121,6 +124,10 @@ function eventBadgeVariant(kind: NodeRuntimeEvent["kind"]) {
 			return "success";
 		case "restart_failed":
 			return "destructive";
+		case "failover_activated":
+			return "warning";
+		case "failover_restored":
+			return "success";
 		default:
 			return "ghost";
 	}
@@ -144,35 +151,6 @@ function historySlotClass(status: string): string {
 const SLOTS_PER_DAY = 48;
 const ACTIVITY_DAYS = 7;
 
-const quotaResetSchema = z
-	.object({
-		resetPolicy: z.enum(["monthly", "unlimited"]),
-		resetDay: z.coerce
-			.number()
-			.int("Reset day must be an integer between 1 and 31."),
-		resetTzOffsetMinutes: z
-			.string()
-			.trim()
-			.refine((value) => value === "" || /^-?\d+$/.test(value), {
-				message: "tz_offset_minutes must be an integer (or empty).",
-			}),
-	})
-	.superRefine((values, ctx) => {
-		if (
-			values.resetPolicy === "monthly" &&
-			(values.resetDay < 1 || values.resetDay > 31)
-		) {
-			ctx.addIssue({
-				code: z.ZodIssueCode.custom,
-				path: ["resetDay"],
-				message: "Reset day must be an integer between 1 and 31.",
-			});
-		}
-	});
-
-type QuotaResetFormValues = z.infer<typeof quotaResetSchema>;
-type QuotaResetFormInput = z.input<typeof quotaResetSchema>;
-
 type RuntimeActivityRow = {
 	key: string;
 	label: string;
@@ -677,8 +655,8 @@ export function NodeDetailsPage() {
 	const quotaValues = quotaForm.watch();
 
 	const desiredQuotaReset = useMemo(
-		() => toNodeQuotaReset(quotaValues),
-		[quotaValues],
+		() => toNodeQuotaReset(quotaValues, nodeQuery.data?.quota_reset),
+		[nodeQuery.data, quotaValues],
 	);
 	const isDirty = useMemo(
 		() => isNodeQuotaDraftDirty(nodeQuery.data, quotaValues),
@@ -1436,12 +1414,9 @@ export function NodeDetailsPage() {
 																<SelectValue />
 															</SelectTrigger>
 														</FormControl>
-														<SelectContent>
-															<SelectItem value="monthly">monthly</SelectItem>
-															<SelectItem value="unlimited">
-																unlimited
-															</SelectItem>
-														</SelectContent>
+														<QuotaResetPolicyOptions
+															current={nodeQuery.data?.quota_reset}
+														/>
 													</Select>
 													<FormMessage />
 												</FormItem>
@@ -47,6 +47,7 @@ import { PageHeader } from "../components/PageHeader";
 import { CapabilityUnavailableState, PageState } from "../components/PageState";
 import { QueryErrorState } from "../components/QueryErrorState";
 import { QueryRefreshError } from "../components/QueryRefreshError";
+import { QuotaResetPolicyOptions } from "../components/QuotaResetPolicyOptions";
 import { ReadStateBanner } from "../components/ReadStateBanner";
 import { SubscriptionFormatSegmentedControl } from "../components/SubscriptionFormatSegmentedControl";
 import { SubscriptionPreviewDialog } from "../components/SubscriptionPreviewDialog";
@@ -77,6 +78,7 @@ import {
 	queryIsOfflineBlocked,
 } from "../offline/queryReadState";
 import { formatQuotaBytesHuman } from "../utils/quota";
+import { quotaResetDayOfMonth } from "../utils/quotaPolicyView";
 import { normalizeMihomoProfileDraftForSave } from "../utils/userMihomoProfile";
 import { removeAdminUser, replaceAdminUser } from "./adminUsersCache";
 import { useUserRouteTransientState } from "./useUserRouteTransientState";
@@ -138,9 +140,8 @@ export function UserDetailsPage() {
 	const [trafficNodeOptionsUserId, setTrafficNodeOptionsUserId] =
 		useState(userId);
 	const [displayName, setDisplayName] = useState("");
-	const [resetPolicy, setResetPolicy] = useState<"monthly" | "unlimited">(
-		"monthly",
-	);
+	const [resetPolicy, setResetPolicy] =
+		useState<UserQuotaReset["policy"]>("monthly");
 	const [resetDay, setResetDay] = useState(1);
 	const [resetTzOffsetMinutes, setResetTzOffsetMinutes] = useState(480);
 	const [isSavingUser, setIsSavingUser] = useState(false);
@@ -354,15 +355,9 @@ export function UserDetailsPage() {
 	useEffect(() => {
 		if (!user) return;
 		setDisplayName(user.display_name);
-		if (user.quota_reset.policy === "monthly") {
-			setResetPolicy("monthly");
-			setResetDay(user.quota_reset.day_of_month);
-			setResetTzOffsetMinutes(user.quota_reset.tz_offset_minutes);
-		} else {
-			setResetPolicy("unlimited");
-			setResetDay(1);
-			setResetTzOffsetMinutes(user.quota_reset.tz_offset_minutes);
-		}
+		setResetPolicy(user.quota_reset.policy);
+		setResetDay(quotaResetDayOfMonth(user.quota_reset));
+		setResetTzOffsetMinutes(user.quota_reset.tz_offset_minutes);
 		setUserSaveError(null);
 	}, [user]);
 
@@ -798,6 +793,8 @@ export function UserDetailsPage() {
 		setIsSavingUser(true);
 		setUserSaveError(null);
 		try {
+			// Weekly, daily and fixed_days cycles are kept as-is; only the offset is
+			// editable here.
 			const quotaReset: UserQuotaReset =
 				resetPolicy === "monthly"
 					? {
@@ -805,10 +802,16 @@ export function UserDetailsPage() {
 							day_of_month: resetDay,
 							tz_offset_minutes: resetTzOffsetMinutes,
 						}
-					: {
-							policy: "unlimited",
-							tz_offset_minutes: resetTzOffsetMinutes,
-						};
+					: resetPolicy !== "unlimited" &&
+							user?.quota_reset.policy === resetPolicy
+						? {
+								...user.quota_reset,
+								tz_offset_minutes: resetTzOffsetMinutes,
+							}
+						: {
+								policy: "unlimited",
+								tz_offset_minutes: resetTzOffsetMinutes,
+							};
 			const savedUser = await patchAdminUser(adminToken, userId, {
 				display_name: normalizedDisplayName,
 				quota_reset: quotaReset,
@@ -856,13 +859,8 @@ export function UserDetailsPage() {
 	function discardUserProfileDraft() {
 		if (!user) return;
 		setDisplayName(user.display_name);
-		if (user.quota_reset.policy === "monthly") {
-			setResetPolicy("monthly");
-			setResetDay(user.quota_reset.day_of_month);
-		} else {
-			setResetPolicy("unlimited");
-			setResetDay(1);
-		}
+		setResetPolicy(user.quota_reset.policy);
+		setResetDay(quotaResetDayOfMonth(user.quota_reset));
 		setResetTzOffsetMinutes(user.quota_reset.tz_offset_minutes);
 		setUserSaveError(null);
 	}
@@ -1157,7 +1155,7 @@ export function UserDetailsPage() {
 								<Select
 									value={resetPolicy}
 									onValueChange={(value) =>
-										setResetPolicy(value as "monthly" | "unlimited")
+										setResetPolicy(value as UserQuotaReset["policy"])
 									}
 								>
 									<SelectTrigger
@@ -1166,10 +1164,7 @@ export function UserDetailsPage() {
 									>
 										<SelectValue />
 									</SelectTrigger>
-									<SelectContent>
-										<SelectItem value="monthly">monthly</SelectItem>
-										<SelectItem value="unlimited">unlimited</SelectItem>
-									</SelectContent>
+									<QuotaResetPolicyOptions current={user?.quota_reset} />
 								</Select>
 							</div>
 							<div className="xp-field-stack gap-2">
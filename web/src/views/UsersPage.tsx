@@ -23,7 +23,10 @@ import {
 	queryIsOfflineBlocked,
 } from "../offline/queryReadState";
 import { formatQuotaBytesHuman } from "../utils/quota";
-import { formatUtcOffsetMinutes } from "../utils/quotaPolicyView";
+import {
+	formatQuotaResetCycle,
+	formatUtcOffsetMinutes,
+} from "../utils/quotaPolicyView";
 
 function formatError(err: unknown): string {
 	if (isBackendApiError(err)) {
@@ -238,9 +241,7 @@ export function UsersPage() {
 									className="text-xs opacity-60 whitespace-nowrap truncate"
 									title="Quota reset policy (user default)"
 								>
-									{user.quota_reset.policy === "monthly"
-										? `Reset: monthly@${user.quota_reset.day_of_month} ${formatUtcOffsetMinutes(user.quota_reset.tz_offset_minutes)}`
-										: `Reset: unlimited ${formatUtcOffsetMinutes(user.quota_reset.tz_offset_minutes)}`}
+									{`Reset: ${formatQuotaResetCycle(user.quota_reset)} ${formatUtcOffsetMinutes(user.quota_reset.tz_offset_minutes)}`}
 								</div>
 							</div>
 						</td>
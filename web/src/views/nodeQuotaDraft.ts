@@ -1,8 +1,42 @@
+import { z } from "zod";
+
 import type { AdminNode } from "../api/adminNodes";
-import type { NodeQuotaReset } from "../api/quotaReset";
+import { type NodeQuotaReset, QuotaResetPolicySchema } from "../api/quotaReset";
+import { quotaResetDayOfMonth } from "../utils/quotaPolicyView";
+
+export const quotaResetSchema = z
+	.object({
+		resetPolicy: QuotaResetPolicySchema,
+		resetDay: z.coerce
+			.number()
+			.int("Reset day must be an integer between 1 and 31."),
+		resetTzOffsetMinutes: z
+			.string()
+			.trim()
+			.refine((value) => value === "" || /^-?\d+$/.test(value), {
+				message: "tz_offset_minutes must be an integer (or empty).",
+			}),
+	})
+	.superRefine((values, ctx) => {
+		if (
+			values.resetPolicy === "monthly" &&
+			(values.resetDay < 1 || values.resetDay > 31)
+		) {
+			ctx.addIssue({
+				code: z.ZodIssueCode.custom,
+				path: ["resetDay"],
+				message: "Reset day must be an integer between 1 and 31.",
+			});
+		}
+	});
+
+export type QuotaResetFormValues = z.infer<typeof quotaResetSchema>;
+export type QuotaResetFormInput = z.input<typeof quotaResetSchema>;
 
 export type NodeQuotaDraft = {
-	resetPolicy: "monthly" | "unlimited";
+	// Weekly, daily and fixed_days cycles are kept as-is; only monthly and
+	// unlimited are editable here.
+	resetPolicy: NodeQuotaReset["policy"];
 	resetDay: unknown;
 	resetTzOffsetMinutes: string;
 };
@@ -10,8 +44,8 @@ export type NodeQuotaDraft = {
 export function nodeQuotaDraftFromNode(node: AdminNode): NodeQuotaDraft {
 	const reset = node.quota_reset;
 	return {
-		resetPolicy: reset.policy === "monthly" ? "monthly" : "unlimited",
-		resetDay: reset.policy === "monthly" ? reset.day_of_month : 1,
+		resetPolicy: reset.policy,
+		resetDay: quotaResetDayOfMonth(reset),
 		resetTzOffsetMinutes:
 			reset.tz_offset_minutes === null || reset.tz_offset_minutes === undefined
 				? ""
@@ -19,19 +53,27 @@ export function nodeQuotaDraftFromNode(node: AdminNode): NodeQuotaDraft {
 	};
 }
 
-export function toNodeQuotaReset(draft: NodeQuotaDraft): NodeQuotaReset {
+export function toNodeQuotaReset(
+	draft: NodeQuotaDraft,
+	current?: NodeQuotaReset,
+): NodeQuotaReset {
 	const offset = draft.resetTzOffsetMinutes.trim();
 	const tzOffset = offset === "" ? undefined : Number(offset);
-	return draft.resetPolicy === "monthly"
-		? {
-				policy: "monthly",
-				day_of_month: Number(draft.resetDay),
-				...(tzOffset === undefined ? {} : { tz_offset_minutes: tzOffset }),
-			}
-		: {
-				policy: "unlimited",
-				...(tzOffset === undefined ? {} : { tz_offset_minutes: tzOffset }),
-			};
+	const tz = tzOffset === undefined ? {} : { tz_offset_minutes: tzOffset };
+	if (draft.resetPolicy === "monthly") {
+		return {
+			policy: "monthly",
+			day_of_month: Number(draft.resetDay),
+			...tz,
+		};
+	}
+	if (
+		draft.resetPolicy !== "unlimited" &&
+		current?.policy === draft.resetPolicy
+	) {
+		return { ...current, tz_offset_minutes: tzOffset ?? null };
+	}
+	return { policy: "unlimited", ...tz };
 }
 
 export function isNodeQuotaDraftDirty(
@@ -40,7 +82,7 @@ export function isNodeQuotaDraftDirty(
 ): boolean {
 	if (!node) return false;
 	const reset = node.quota_reset;
-	const day = reset.policy === "monthly" ? reset.day_of_month : 1;
+	const day = quotaResetDayOfMonth(reset);
 	const offset =
 		reset.tz_offset_minutes === null || reset.tz_offset_minutes === undefined
 			? ""